@@ -0,0 +1,116 @@
+//! 转换热点的基准测试：整篇转换、图片内联、样式文字渲染与嵌套块取文字
+//!
+//! 运行：`cargo bench`；只运行其中一组，如 `cargo bench -- render_rich_text`
+
+use std::fs;
+use std::hint::black_box;
+use std::path::PathBuf;
+
+use criterion::{BenchmarkId, Criterion, Throughput, criterion_group, criterion_main};
+use mineru_json_to_md::bench::{
+    extract_text_from_block, image_to_base64, nested_block, render_rich_text, span_heavy_block,
+    synthetic_layout, synthetic_layout_json, write_fixture_images,
+};
+use mineru_json_to_md::{
+    ConvertOptions, InputFormat, OutputFormat, convert_layout_to_markdown, convert_to_writer,
+    parse_layout,
+};
+
+const PAGES: usize = 200;
+
+/// 合成页面引用的图片所在的目录，每次运行时重新写出
+fn fixture_dir() -> PathBuf {
+    let dir = std::env::temp_dir().join("mineru-json-to-md-bench");
+    fs::create_dir_all(&dir).expect("creating fixture directory");
+    write_fixture_images(&dir).expect("writing fixture images");
+    dir
+}
+
+fn convert(c: &mut Criterion) {
+    let dir = fixture_dir();
+    let json = synthetic_layout_json(PAGES);
+    let layout = synthetic_layout(PAGES);
+
+    let mut group = c.benchmark_group("convert");
+    group.sample_size(20);
+    group.throughput(Throughput::Elements(PAGES as u64));
+    group.bench_function("parse", |b| {
+        b.iter(|| parse_layout(black_box(&json), InputFormat::Auto).unwrap())
+    });
+    for format in [
+        OutputFormat::Markdown,
+        OutputFormat::Html,
+        OutputFormat::Json,
+    ] {
+        let options = ConvertOptions {
+            format,
+            ..ConvertOptions::default()
+        };
+        group.bench_with_input(
+            BenchmarkId::new("in_memory", format!("{:?}", format)),
+            &options,
+            |b, options| b.iter(|| convert_layout_to_markdown(&layout, &dir, options).unwrap()),
+        );
+    }
+    let options = ConvertOptions::default();
+    group.bench_function("streamed", |b| {
+        b.iter(|| {
+            let mut out = Vec::new();
+            convert_to_writer(&layout, &dir, &mut out, &options).unwrap();
+            out
+        })
+    });
+    group.finish();
+}
+
+/// 以 PNG 文件头开始的伪随机字节
+fn noise(len: usize) -> Vec<u8> {
+    let mut data = b"\x89PNG\r\n\x1a\n".to_vec();
+    let mut state: u64 = 0x2545_f491_4f6c_dd1d;
+    while data.len() < len {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        data.extend_from_slice(&state.to_le_bytes());
+    }
+    data.truncate(len);
+    data
+}
+
+fn images(c: &mut Criterion) {
+    let data = noise(5 * 1024 * 1024);
+    let path = PathBuf::from("figure.png");
+    let mut group = c.benchmark_group("image_to_base64");
+    group.throughput(Throughput::Bytes(data.len() as u64));
+    group.bench_function("5MB", |b| {
+        b.iter(|| image_to_base64(&path, black_box(&data)))
+    });
+    group.finish();
+}
+
+fn rich_text(c: &mut Criterion) {
+    let options = ConvertOptions::default();
+    let mut group = c.benchmark_group("render_rich_text");
+    for spans in [100, 10_000, 100_000] {
+        let block = span_heavy_block(spans);
+        group.throughput(Throughput::Elements(spans as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(spans), &block, |b, block| {
+            b.iter(|| render_rich_text(black_box(block), &options))
+        });
+    }
+    group.finish();
+}
+
+fn nested_text(c: &mut Criterion) {
+    let mut group = c.benchmark_group("extract_text_from_block");
+    for depth in [8, 32, 128] {
+        let block = nested_block(depth, 4);
+        group.bench_with_input(BenchmarkId::from_parameter(depth), &block, |b, block| {
+            b.iter(|| extract_text_from_block(black_box(block), true))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, convert, images, rich_text, nested_text);
+criterion_main!(benches);
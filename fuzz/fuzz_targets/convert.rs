@@ -0,0 +1,88 @@
+//! 反序列化与转换的模糊测试：任意输入都不应 panic，流式输出与一次性转换的结果逐字节相同；
+//! 直接构造、page_idx 接近 `usize::MAX` 的页面应报错而不是溢出
+//!
+//! 运行：`cargo +nightly fuzz run convert`
+
+#![no_main]
+
+use std::path::Path;
+
+use libfuzzer_sys::fuzz_target;
+use mineru_json_to_md::{
+    AnchorStyle, ConvertError, ConvertOptions, Flavor, FootnoteStyle, IndexStyle, InputFormat,
+    OutputFormat, TableFormat, convert_layout_to_markdown, convert_to_writer, parse_layout_lenient,
+};
+
+const FORMATS: [OutputFormat; 6] = [
+    OutputFormat::Markdown,
+    OutputFormat::Html,
+    OutputFormat::Text,
+    OutputFormat::Latex,
+    OutputFormat::Json,
+    OutputFormat::Asciidoc,
+];
+
+/// 每种输出格式试两组选项：默认值，以及打开大部分可选处理的一组
+fn option_sets(format: OutputFormat) -> [ConvertOptions; 2] {
+    let defaults = ConvertOptions {
+        format,
+        ..ConvertOptions::default()
+    };
+    let everything = ConvertOptions {
+        format,
+        flavor: Flavor::Gfm,
+        table_format: TableFormat::Markdown,
+        footnote_style: FootnoteStyle::Markdown,
+        index_style: IndexStyle::Definitions,
+        anchors: AnchorStyle::Github,
+        collapse_empty_pages: true,
+        dedupe_headers: true,
+        number_headings: true,
+        number_figures: true,
+        list_of_figures: true,
+        dehyphenate: true,
+        normalize_whitespace: true,
+        escape_markdown: true,
+        format_references: true,
+        autolink: true,
+        provenance: true,
+        merge_cross_page: true,
+        merge_titles: true,
+        back_to_top: Some("Top".to_string()),
+        ..ConvertOptions::default()
+    };
+    [defaults, everything]
+}
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(json) = std::str::from_utf8(data) else {
+        return;
+    };
+    let Ok((mut layout, _)) = parse_layout_lenient(json, InputFormat::Auto) else {
+        return;
+    };
+    // 不存在的目录：图片全部按缺失处理，不读取本机文件
+    let base_path = Path::new("/nonexistent/mineru-fuzz");
+    for format in FORMATS {
+        for options in option_sets(format) {
+            let Ok(output) = convert_layout_to_markdown(&layout, base_path, &options) else {
+                continue;
+            };
+            let mut streamed = Vec::new();
+            convert_to_writer(&layout, base_path, &mut streamed, &options)
+                .expect("streaming failed where convert_layout_to_markdown succeeded");
+            assert_eq!(String::from_utf8_lossy(&streamed), output.markdown);
+        }
+    }
+
+    for page in &mut layout.pdf_info {
+        page.page_idx = usize::MAX - page.page_idx;
+    }
+    if !layout.pdf_info.is_empty() {
+        let result = convert_layout_to_markdown(&layout, base_path, &ConvertOptions::default());
+        assert!(matches!(
+            result,
+            Err(ConvertError::PageIndexOutOfRange { .. })
+        ));
+    }
+});
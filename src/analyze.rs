@@ -0,0 +1,1196 @@
+//! 分析：把版面块整理为文档节点（[`DocNode`]）；图片解析、图表编号与块的取舍都在这一步完成
+
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashSet};
+use std::path::Path;
+
+use crate::doc::{
+    BlockSource, Caption, CaptionKind, DocNode, ImageSource, Inline, PageNode, PageNodes, Panel,
+    TableBody,
+};
+use crate::error::{ConvertError, Warning};
+use crate::figures::{
+    FigureCounters, FigureEntry, FigureKind, body_spans, has_image_span, has_table_span,
+    number_caption,
+};
+use crate::image::{ImageResolver, Resolved, format_size};
+use crate::index::index_lines;
+use crate::list::{group_list_runs, list_items};
+use crate::merge::{drop_duplicate_blocks, merge_split_titles};
+use crate::numbering::HeadingNumbers;
+use crate::order::{block_above, column_of, compare_column_then_top, is_two_column, page_width};
+use crate::quote::{mark_indented_quotes, quote_line_groups};
+use crate::references::{is_references_title, split_entries};
+use crate::renderer::{BlockRenderer, PageContext};
+use crate::rst::SectionDepths;
+use crate::stats::ConvertStats;
+use crate::table::export_table;
+use crate::text::{
+    block_text, block_text_with_math, extract_text_from_block, generate_anchor_id, github_anchors,
+    github_slug, is_known_span, normalize_heading, normalize_latex, numbering_depth, rich_inlines,
+    split_math, strip_style_tags,
+};
+use crate::types::{Block, DiscardedBlocksCategory, PageInfo, PageSizeSource, TocEntry};
+use crate::{CaptionPosition, ConvertOptions, OutputFormat};
+
+// ==================== 分析状态 ====================
+
+/// 单个页面的分析状态；各页可并行分析，只共享只读的图片解析器
+pub(crate) struct RenderState<'a> {
+    pub images: &'a ImageResolver,
+    /// 解析本页 image_path 时使用的根目录
+    pub base_path: &'a Path,
+    pub warnings: Vec<Warning>,
+    /// 前面各页已用掉的图、表编号，开启 number_figures 时由调用方设置
+    pub counters: FigureCounters,
+    /// 本页编号的图表，用于生成图表目录
+    pub figures: Vec<FigureEntry>,
+    pub stats: ConvertStats,
+    /// 当前是否处在参考文献一节中；页面开头的状态由调用方设置，遇到标题时更新
+    pub in_references: bool,
+    /// 各级标题的编号，开启 number_headings 时页面开头的状态由调用方设置
+    pub heading_numbers: HeadingNumbers,
+    /// reStructuredText 输出的章节层级，页面开头的状态由调用方设置
+    pub section_depths: SectionDepths,
+    /// 本页开头的续文已并入上一页，由调用方设置；此时正文为空不算空白页
+    pub continued: bool,
+    /// 本页已出现的表格块，用于给导出的 CSV 文件命名
+    pub tables: usize,
+}
+
+impl<'a> RenderState<'a> {
+    pub(crate) fn new(images: &'a ImageResolver, base_path: &'a Path) -> Self {
+        RenderState {
+            images,
+            base_path,
+            warnings: Vec::new(),
+            counters: FigureCounters::default(),
+            figures: Vec::new(),
+            stats: ConvertStats::default(),
+            in_references: false,
+            heading_numbers: HeadingNumbers::default(),
+            section_depths: SectionDepths::default(),
+            continued: false,
+            tables: 0,
+        }
+    }
+
+    /// 开启 number_figures 时为说明文字编号，返回图表的锚点
+    pub(crate) fn number_figure(
+        &mut self,
+        caption: Option<&mut Caption>,
+        kind: FigureKind,
+        page_idx: usize,
+        options: &ConvertOptions,
+    ) -> Option<String> {
+        let (true, Some(caption)) = (options.number_figures, caption) else {
+            return None;
+        };
+        let entry = number_caption(&caption.text, kind, &mut self.counters, page_idx);
+        caption.text = entry.caption.clone();
+        let anchor = entry.anchor_id.clone();
+        self.figures.push(entry);
+        Some(anchor)
+    }
+
+    /// 解析图片地址；图片缺失或超出大小上限而未输出时为代替图片的占位提示文字。
+    /// 图片缺失时记录警告，或按选项直接报错
+    pub(crate) fn resolve_image(
+        &mut self,
+        image_path: &str,
+        page_idx: usize,
+        options: &ConvertOptions,
+    ) -> Result<ImageSource, ConvertError> {
+        match self.images.resolve(self.base_path, image_path)? {
+            Resolved::Src(src, size) => {
+                self.stats.images_included += 1;
+                if let Some(size) = size {
+                    self.stats.record_image_size(page_idx, image_path, size);
+                }
+                Ok(ImageSource::Src { src, size })
+            }
+            Resolved::Omitted(bytes) => Ok(ImageSource::Unavailable(format!(
+                "image omitted: {} ({})",
+                image_path,
+                format_size(bytes)
+            ))),
+            Resolved::Missing => {
+                self.stats.images_missing += 1;
+                if options.fail_on_missing_images {
+                    return Err(ConvertError::MissingImage {
+                        page_idx,
+                        path: image_path.to_string(),
+                    });
+                }
+                self.warnings.push(Warning::MissingImage {
+                    page_idx,
+                    path: image_path.to_string(),
+                });
+                Ok(ImageSource::Unavailable(format!(
+                    "figure missing: {}",
+                    image_path
+                )))
+            }
+        }
+    }
+}
+
+// ==================== 块分析 ====================
+
+/// 标题的文字、层级与锚点；没有文字的标题返回 `None`。开启 number_headings 时文字带上编号，
+/// 锚点按带编号的文字生成
+///
+/// 锚点在此尚未去重，由 [`crate::dedupe_anchors`] 在所有页面渲染完成后按顺序处理
+pub(crate) fn title_entry(
+    block: &Block,
+    options: &ConvertOptions,
+    page_idx: usize,
+    numbers: &mut HeadingNumbers,
+) -> Option<TocEntry> {
+    title_parts(block, options, page_idx, numbers).map(|(entry, _)| entry)
+}
+
+/// 标题的层级：优先使用 MinerU 给出的层级，其次按编号（3.2.1 → 3），最后才按长度猜测
+pub(crate) fn heading_level(block: &Block, text: &str) -> usize {
+    match block.level {
+        Some(level) => (level as usize).min(5),
+        None => match numbering_depth(text) {
+            Some(depth) => depth.min(5),
+            None if text.chars().count() > 20 => 2,
+            None => 1,
+        },
+    }
+}
+
+/// 同 [`title_entry`]，另外返回标题中的文字与行内公式（见 [`DocNode::Heading`] 的 `inlines`）；
+/// 目录条目的文字与锚点中的公式不带分隔符
+fn title_parts(
+    block: &Block,
+    options: &ConvertOptions,
+    page_idx: usize,
+    numbers: &mut HeadingNumbers,
+) -> Option<(TocEntry, Vec<Inline>)> {
+    let (mut text, equations) = block_text_with_math(block, options);
+    if options.normalize_headings {
+        text = normalize_heading(&text, options.title_case_headings);
+    }
+    if text.is_empty() {
+        return None;
+    }
+
+    let level = heading_level(block, &text);
+    if options.number_headings
+        && let Some(prefix) = numbers.next(&text, level)
+    {
+        text.insert_str(0, &prefix);
+    }
+
+    let (text, inlines) = split_math(&text, &equations);
+    let anchor_id = if github_anchors(options) {
+        github_slug(&text)
+    } else {
+        generate_anchor_id(&text, page_idx)
+    };
+    let entry = TocEntry {
+        title: text,
+        page_idx: page_idx + 1,
+        anchor_id,
+        level,
+        printed_page: None,
+    };
+    Some((entry, inlines))
+}
+
+/// 本页中的标题条目及标题中的文字与行内公式；reStructuredText 输出时层级按文档顺序整理为不跳级的章节层级
+pub(crate) fn heading_entry(
+    block: &Block,
+    state: &mut RenderState,
+    options: &ConvertOptions,
+    page_idx: usize,
+) -> Option<(TocEntry, Vec<Inline>)> {
+    let (mut entry, inlines) = title_parts(block, options, page_idx, &mut state.heading_numbers)?;
+    if options.format == OutputFormat::Rst {
+        entry.level = state.section_depths.next(entry.level);
+    }
+    Some((entry, inlines))
+}
+
+fn title_node(
+    block: &Block,
+    state: &mut RenderState,
+    options: &ConvertOptions,
+    page_idx: usize,
+) -> Option<DocNode> {
+    let (entry, inlines) = heading_entry(block, state, options, page_idx)?;
+    Some(DocNode::Heading {
+        level: entry.level,
+        text: entry.title,
+        anchor: entry.anchor_id,
+        inlines,
+    })
+}
+
+fn paragraph(block: &Block, options: &ConvertOptions, rotation: Option<u32>) -> Option<DocNode> {
+    let inlines = rich_inlines(block, options);
+    (!inlines.is_empty()).then_some(DocNode::Paragraph { inlines, rotation })
+}
+
+/// 按原始顺序逐行拼接 span，保留缩进，不做任何 Markdown 处理
+fn code_lines(block: &Block) -> Vec<String> {
+    block
+        .lines
+        .iter()
+        .flatten()
+        .map(|line| {
+            line.spans
+                .iter()
+                .filter_map(|span| span.content.as_deref())
+                .collect::<String>()
+        })
+        .collect()
+}
+
+/// 代码块：`code` 块（含 code_body / code_caption 子块）或 sub_type 为 code 的文本块；
+/// 没有代码正文时返回 `None`
+fn code_node(block: &Block, options: &ConvertOptions) -> Option<DocNode> {
+    let mut lines = Vec::new();
+    let mut caption = None;
+    let mut lang = block.guess_lang.clone();
+
+    match &block.blocks {
+        Some(blocks) => {
+            for sub_block in blocks {
+                match sub_block.block_type.as_str() {
+                    "code_body" => {
+                        lines.extend(code_lines(sub_block));
+                        if lang.is_none() {
+                            lang = sub_block.guess_lang.clone();
+                        }
+                    }
+                    "code_caption" => {
+                        let text = extract_text_from_block(sub_block, options.dehyphenate)
+                            .trim()
+                            .to_string();
+                        if !text.is_empty() {
+                            caption = Some(text);
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+        None => lines = code_lines(block),
+    }
+
+    // 去掉首尾空行，但保留正文中的缩进与空行
+    while lines.last().is_some_and(|line| line.trim().is_empty()) {
+        lines.pop();
+    }
+    let first = lines.iter().position(|line| !line.trim().is_empty())?;
+    Some(DocNode::Code {
+        language: lang
+            .map(|lang| lang.trim().to_string())
+            .filter(|lang| !lang.is_empty()),
+        caption,
+        code: lines[first..].join("\n"),
+    })
+}
+
+/// 指定类型子块（image_caption、table_footnote 等）的非空文字，按出现顺序；
+/// `child_types` 中每种子块类型对应一种说明文字。说明文字在图表上方还是下方按 caption_position 决定，
+/// `Auto` 时看子块是否排在 image_body / table_body 之前
+pub(crate) fn child_captions(
+    block: &Block,
+    child_types: &[(&str, CaptionKind)],
+    options: &ConvertOptions,
+) -> Vec<Caption> {
+    captions_except(block, child_types, &[], options)
+}
+
+/// 同 [`child_captions`]，跳过序号在 `skip` 中的子块（组图中属于各个分图的说明文字）
+fn captions_except(
+    block: &Block,
+    child_types: &[(&str, CaptionKind)],
+    skip: &[usize],
+    options: &ConvertOptions,
+) -> Vec<Caption> {
+    let sub_blocks = block.blocks.as_deref().unwrap_or_default();
+    let body = sub_blocks
+        .iter()
+        .position(|sub_block| sub_block.block_type.ends_with("_body"));
+    sub_blocks
+        .iter()
+        .enumerate()
+        .filter(|(i, _)| !skip.contains(i))
+        .filter_map(|(i, sub_block)| {
+            let (_, kind) = child_types
+                .iter()
+                .find(|(child_type, _)| *child_type == sub_block.block_type)?;
+            let above = match (options.caption_position, kind) {
+                (CaptionPosition::Auto, _) => body.is_some_and(|body| i < body),
+                (CaptionPosition::Above, CaptionKind::Caption) => true,
+                (CaptionPosition::Above, CaptionKind::Footnote) | (CaptionPosition::Below, _) => {
+                    false
+                }
+            };
+            Some(Caption {
+                kind: *kind,
+                text: block_text(sub_block, options),
+                rotation: sub_block.rotation(),
+                above,
+            })
+        })
+        .filter(|caption| !caption.text.is_empty())
+        .collect()
+}
+
+/// 引文，按原文的硬换行分行；没有文字时返回 `None`
+fn quote_node(block: &Block, options: &ConvertOptions) -> Option<DocNode> {
+    let lines: Vec<_> = quote_line_groups(block)
+        .into_iter()
+        .map(|lines| {
+            let group = Block {
+                lines: Some(lines),
+                ..block.clone()
+            };
+            rich_inlines(&group, options)
+        })
+        .filter(|inlines| !inlines.is_empty())
+        .collect();
+    (!lines.is_empty()).then_some(DocNode::Quote { lines })
+}
+
+/// 正文段落：代码、引文、旋转的文字、参考文献一节中的段落分别处理
+pub(crate) fn analyze_text(
+    block: &Block,
+    state: &RenderState,
+    options: &ConvertOptions,
+) -> Option<DocNode> {
+    match block.sub_type.as_deref() {
+        Some("code") => return code_node(block, options),
+        Some("quote") => return quote_node(block, options),
+        _ => {}
+    }
+    if let Some(rotation) = block.rotation() {
+        if options.drop_rotated_text {
+            return None;
+        }
+        return paragraph(block, options, Some(rotation));
+    }
+    if state.in_references {
+        return references_node(block, options);
+    }
+    paragraph(block, options, None)
+}
+
+fn references_node(block: &Block, options: &ConvertOptions) -> Option<DocNode> {
+    let entries = split_entries(block, options.dehyphenate);
+    (!entries.is_empty()).then_some(DocNode::References { entries })
+}
+
+/// 开启 number_figures 时会编号的说明文字：图片块中不属于分图的第一个图注，或表格块的第一个表注
+pub(crate) fn numbered_caption(
+    block: &Block,
+    kind: FigureKind,
+    options: &ConvertOptions,
+) -> Option<String> {
+    let (caption_type, skip) = match kind {
+        FigureKind::Figure => (
+            "image_caption",
+            panel_captions(block)
+                .into_iter()
+                .map(|(_, caption)| caption)
+                .collect(),
+        ),
+        FigureKind::Table => ("table_caption", Vec::new()),
+    };
+    captions_except(
+        block,
+        &[(caption_type, CaptionKind::Caption)],
+        &skip,
+        options,
+    )
+    .into_iter()
+    .next()
+    .map(|caption| caption.text)
+}
+
+/// 组图中属于各个分图的图注：`(image_body 序号, image_caption 序号)`。
+/// 夹在两个 image_body 之间的图注属于之前的分图；最后一个 image_body 之后的第一个图注，
+/// 在之前已有分图说明、且其后还有图注或以 `(c)` 之类的分图标号开头时属于最后一个分图，其余为整个图的图注
+fn panel_captions(block: &Block) -> Vec<(usize, usize)> {
+    let sub_blocks = block.blocks.as_deref().unwrap_or_default();
+    let bodies: Vec<usize> = sub_blocks
+        .iter()
+        .enumerate()
+        .filter(|(_, sub_block)| sub_block.block_type == "image_body")
+        .map(|(i, _)| i)
+        .collect();
+    let (Some(&first), Some(&last)) = (bodies.first(), bodies.last()) else {
+        return Vec::new();
+    };
+    if first == last {
+        return Vec::new();
+    }
+    let mut panels: Vec<(usize, usize)> = Vec::new();
+    let mut body = first;
+    for (i, sub_block) in sub_blocks.iter().enumerate().take(last).skip(first + 1) {
+        match sub_block.block_type.as_str() {
+            "image_body" => body = i,
+            "image_caption" if panels.last().is_none_or(|&(prev, _)| prev != body) => {
+                panels.push((body, i));
+            }
+            _ => {}
+        }
+    }
+    let trailing: Vec<usize> = sub_blocks
+        .iter()
+        .enumerate()
+        .skip(last + 1)
+        .filter(|(_, sub_block)| sub_block.block_type == "image_caption")
+        .map(|(i, _)| i)
+        .collect();
+    if let Some(&caption) = trailing.first()
+        && !panels.is_empty()
+        && (trailing.len() > 1
+            || starts_with_panel_label(&extract_text_from_block(&sub_blocks[caption], false)))
+    {
+        panels.push((last, caption));
+    }
+    panels
+}
+
+/// 是否以分图标号开头：`(a)`、`b)`、`(iii)`、`（1）`
+fn starts_with_panel_label(text: &str) -> bool {
+    let text = text.trim_start();
+    let rest = text
+        .strip_prefix('(')
+        .or_else(|| text.strip_prefix('（'))
+        .unwrap_or(text);
+    let label_len = rest
+        .find(|c: char| !c.is_ascii_alphanumeric())
+        .unwrap_or(rest.len());
+    (1..=4).contains(&label_len) && rest[label_len..].starts_with([')', '）'])
+}
+
+/// 图片块，连同图注与图片脚注；没有图片的块记录警告后跳过。
+/// 由多个图片 span 组成的组图中每张图片都是一个分图，分图说明跟在各自的图片之后。
+/// 纯文本输出不读取图片文件，只保留第一个图注
+pub(crate) fn analyze_image(
+    block: &Block,
+    state: &mut RenderState,
+    options: &ConvertOptions,
+    page_idx: usize,
+) -> Result<Option<DocNode>, ConvertError> {
+    let empty_figure = |state: &mut RenderState| {
+        state.warnings.push(Warning::EmptyFigure {
+            page_idx,
+            block_type: block.block_type.clone(),
+        });
+        Ok(None)
+    };
+
+    if options.format == OutputFormat::Text {
+        if !has_image_span(block) {
+            return empty_figure(state);
+        }
+        let skip: Vec<usize> = panel_captions(block)
+            .into_iter()
+            .map(|(_, caption)| caption)
+            .collect();
+        let mut captions = captions_except(
+            block,
+            &[("image_caption", CaptionKind::Caption)],
+            &skip,
+            options,
+        );
+        captions.truncate(1);
+        let anchor =
+            state.number_figure(captions.first_mut(), FigureKind::Figure, page_idx, options);
+        return Ok(Some(DocNode::Figure {
+            image: None,
+            panels: Vec::new(),
+            captions,
+            anchor,
+            rotation: block.rotation(),
+        }));
+    }
+
+    // 每个图片 span 是一个分图，同一张图片重复出现时只保留一次；分图说明按所在的 image_body 对应
+    let sub_blocks = block.blocks.as_deref().unwrap_or_default();
+    let bodies: Vec<(Option<usize>, &Block)> = if sub_blocks
+        .iter()
+        .any(|sub_block| sub_block.block_type == "image_body")
+    {
+        sub_blocks
+            .iter()
+            .enumerate()
+            .filter(|(_, sub_block)| sub_block.block_type == "image_body")
+            .map(|(i, sub_block)| (Some(i), sub_block))
+            .collect()
+    } else {
+        vec![(None, block)]
+    };
+    let panel_captions = panel_captions(block);
+    let mut seen = HashSet::new();
+    let mut panels: Vec<Panel> = Vec::new();
+    for (body, body_block) in bodies {
+        let caption = panel_captions
+            .iter()
+            .find(|&&(panel, _)| Some(panel) == body)
+            .map(|&(_, caption)| block_text(&sub_blocks[caption], options))
+            .filter(|caption| !caption.is_empty());
+        let first = panels.len();
+        for span in body_spans(body_block, "image_body") {
+            if span.span_type == "image"
+                && let Some(image_path) = &span.image_path
+                && seen.insert(image_path.as_str())
+            {
+                panels.push(Panel {
+                    image: state.resolve_image(image_path, page_idx, options)?,
+                    caption: None,
+                });
+            }
+        }
+        if panels.len() > first
+            && let Some(panel) = panels.last_mut()
+        {
+            panel.caption = caption;
+        }
+    }
+    let skip: Vec<usize> = panel_captions
+        .into_iter()
+        .map(|(_, caption)| caption)
+        .collect();
+    let mut captions = captions_except(
+        block,
+        &[
+            ("image_caption", CaptionKind::Caption),
+            ("image_footnote", CaptionKind::Footnote),
+        ],
+        &skip,
+        options,
+    );
+
+    // 只有一张图片时不是组图；组图中图片都缺失时仍逐个输出占位提示
+    let image = match panels
+        .iter()
+        .find(|panel| panel.image.src().is_some())
+        .or(panels.first())
+    {
+        Some(panel) => panel.image.clone(),
+        None => return empty_figure(state),
+    };
+    if panels.len() == 1 {
+        panels.clear();
+    }
+    let anchor = state.number_figure(
+        captions
+            .iter_mut()
+            .find(|caption| caption.kind == CaptionKind::Caption),
+        FigureKind::Figure,
+        page_idx,
+        options,
+    );
+    Ok(Some(DocNode::Figure {
+        image: Some(image),
+        panels,
+        captions,
+        anchor,
+        rotation: block.rotation(),
+    }))
+}
+
+/// 表格块，连同表注与表格脚注；优先使用 MinerU 识别出的表格 HTML，缺失时退回表格截图。
+/// 纯文本输出不读取表格内容，只保留表注
+pub(crate) fn analyze_table(
+    block: &Block,
+    state: &mut RenderState,
+    options: &ConvertOptions,
+    page_idx: usize,
+) -> Result<Option<DocNode>, ConvertError> {
+    let empty_figure = |state: &mut RenderState| {
+        state.warnings.push(Warning::EmptyFigure {
+            page_idx,
+            block_type: block.block_type.clone(),
+        });
+        Ok(None)
+    };
+
+    if options.format == OutputFormat::Text {
+        if !has_table_span(block, options) {
+            return empty_figure(state);
+        }
+        let mut captions =
+            child_captions(block, &[("table_caption", CaptionKind::Caption)], options);
+        captions.truncate(1);
+        let anchor =
+            state.number_figure(captions.first_mut(), FigureKind::Table, page_idx, options);
+        return Ok(Some(DocNode::Table {
+            body: None,
+            captions,
+            anchor,
+            rotation: block.rotation(),
+            csv: None,
+        }));
+    }
+
+    let mut table_html: Option<String> = None;
+    let mut table_src: Option<ImageSource> = None;
+    let mut missing: Option<String> = None;
+
+    for span in body_spans(block, "table_body") {
+        if span.span_type != "table" {
+            continue;
+        }
+        if !options.tables_as_image
+            && let Some(content) = &span.content
+            && !content.trim().is_empty()
+        {
+            table_html = Some(strip_style_tags(content.trim()));
+            table_src = None;
+        } else if let Some(image_path) = &span.image_path {
+            match state.resolve_image(image_path, page_idx, options)? {
+                ImageSource::Unavailable(note) => missing = Some(note),
+                src => {
+                    table_html = None;
+                    table_src = Some(src);
+                }
+            }
+        }
+    }
+    let mut captions = child_captions(
+        block,
+        &[
+            ("table_caption", CaptionKind::Caption),
+            ("table_footnote", CaptionKind::Footnote),
+        ],
+        options,
+    );
+
+    let body = match (table_html, table_src, missing) {
+        (Some(html), _, _) => TableBody::Html(html),
+        (None, Some(src), _) => TableBody::Image(src),
+        (None, None, Some(note)) => TableBody::Image(ImageSource::Unavailable(note)),
+        (None, None, None) => return empty_figure(state),
+    };
+    let anchor = state.number_figure(
+        captions
+            .iter_mut()
+            .find(|caption| caption.kind == CaptionKind::Caption),
+        FigureKind::Table,
+        page_idx,
+        options,
+    );
+
+    // 导出的 CSV 文件按表格在本页中的序号命名，只有截图的表格也占一个序号
+    state.tables += 1;
+    let csv = match (&options.table_export, &body) {
+        (Some(export), TableBody::Html(html)) => {
+            let caption = captions
+                .iter()
+                .find(|caption| caption.kind == CaptionKind::Caption)
+                .map(|caption| caption.text.as_str());
+            let csv = export_table(
+                html,
+                page_idx,
+                state.tables,
+                caption,
+                export,
+                &mut state.warnings,
+            )?;
+            state.stats.tables_exported += usize::from(csv.is_some());
+            csv
+        }
+        (Some(_), TableBody::Image(_)) => {
+            state.stats.tables_image_only += 1;
+            None
+        }
+        (None, _) => None,
+    };
+    Ok(Some(DocNode::Table {
+        body: Some(body),
+        captions,
+        anchor,
+        rotation: block.rotation(),
+        csv,
+    }))
+}
+
+/// 行间公式：默认优先使用公式截图，prefer_equation_latex 时优先使用 LaTeX，不再读取截图。
+/// 纯文本输出不读取截图，只保留 LaTeX
+pub(crate) fn analyze_equation(
+    block: &Block,
+    state: &mut RenderState,
+    options: &ConvertOptions,
+    page_idx: usize,
+) -> Result<Option<DocNode>, ConvertError> {
+    let spans = block
+        .lines
+        .iter()
+        .flatten()
+        .flat_map(|line| &line.spans)
+        .filter(|span| span.span_type == "interline_equation");
+    let latex_of = |content: Option<&str>| {
+        content
+            .map(|content| normalize_latex(content, false))
+            .filter(|latex| !latex.is_empty())
+    };
+
+    if options.format == OutputFormat::Text {
+        let latex = spans
+            .clone()
+            .find_map(|span| span.content.as_deref())
+            .and_then(|content| latex_of(Some(content)));
+        return Ok(Some(DocNode::Equation { latex, image: None }));
+    }
+
+    for span in spans {
+        let latex = latex_of(span.content.as_deref());
+        if options.prefer_equation_latex && latex.is_some() {
+            return Ok(Some(DocNode::Equation { latex, image: None }));
+        }
+        let image = match &span.image_path {
+            Some(image_path) => Some(state.resolve_image(image_path, page_idx, options)?),
+            None => None,
+        };
+        // 没有截图也没有 LaTeX 的 span 不输出
+        if latex.is_some() || image.is_some() {
+            return Ok(Some(DocNode::Equation { latex, image }));
+        }
+    }
+    Ok(None)
+}
+
+/// 脚注、参考文献条目、注音、旁注等 MinerU 单独标注的文字
+fn small_print(block: &Block, options: &ConvertOptions) -> Option<DocNode> {
+    let text = block_text(block, options);
+    (!text.is_empty()).then_some(DocNode::SmallPrint { text })
+}
+
+/// 未识别的块类型：开启 strict_blocks 时报错，否则记录警告，由调用方按普通文本输出
+pub(crate) fn unknown_block(
+    block: &Block,
+    state: &mut RenderState,
+    options: &ConvertOptions,
+    page_idx: usize,
+) -> Result<(), ConvertError> {
+    if options.strict_blocks {
+        return Err(ConvertError::UnknownBlockType {
+            page_idx,
+            block_type: block.block_type.clone(),
+        });
+    }
+    state.warnings.push(Warning::UnknownBlockType {
+        page_idx,
+        block_type: block.block_type.clone(),
+    });
+    Ok(())
+}
+
+/// 一个块的节点；没有内容的块返回 `None`
+///
+/// 给出 `renderer` 时，标题、正文、图片、表格与行间公式交给它直接生成输出内容（[`DocNode::Rendered`]），
+/// 其余块仍整理为结构化的节点；JSON 输出不经过 `renderer`。
+fn analyze_block(
+    block: &Block,
+    state: &mut RenderState,
+    options: &ConvertOptions,
+    page_idx: usize,
+    renderer: Option<&dyn BlockRenderer>,
+) -> Result<Option<DocNode>, ConvertError> {
+    if block.block_type == "title" && options.format_references {
+        state.in_references =
+            is_references_title(&extract_text_from_block(block, options.dehyphenate));
+    }
+
+    if let Some(renderer) = renderer.filter(|_| options.format != OutputFormat::Json) {
+        let mut ctx = PageContext::new(state, options, page_idx);
+        let rendered = match block.block_type.as_str() {
+            "title" => Some(renderer.render_title(block, &mut ctx)),
+            "text" => Some((renderer.render_text(block, &mut ctx), None)),
+            "image" => Some((renderer.render_image(block, &mut ctx)?, None)),
+            "table" => Some((renderer.render_table(block, &mut ctx)?, None)),
+            "interline_equation" => Some((renderer.render_equation(block, &mut ctx)?, None)),
+            _ => None,
+        };
+        if let Some((content, toc_entry)) = rendered {
+            let empty = content.is_empty() && toc_entry.is_none();
+            return Ok((!empty).then_some(DocNode::Rendered { content, toc_entry }));
+        }
+    }
+
+    Ok(match block.block_type.as_str() {
+        "title" => title_node(block, state, options, page_idx),
+        "text" => analyze_text(block, state, options),
+        "code" => code_node(block, options),
+        "list" => {
+            let items = list_items(block, options);
+            (!items.is_empty()).then_some(DocNode::List { items })
+        }
+        "image" => analyze_image(block, state, options, page_idx)?,
+        "table" => analyze_table(block, state, options, page_idx)?,
+        "interline_equation" => analyze_equation(block, state, options, page_idx)?,
+        "index" => {
+            let lines = index_lines(block);
+            (!lines.is_empty()).then_some(DocNode::Index { lines })
+        }
+        "ref_text" if options.format_references => references_node(block, options),
+        "footnote" | "ref_text" | "phonetic" | "aside_text" => small_print(block, options),
+        _ => {
+            unknown_block(block, state, options, page_idx)?;
+            paragraph(block, options, None)
+        }
+    })
+}
+
+/// 参与分析的正文块：开启 dedupe_blocks 时先去掉重复识别的块，开启 merge_titles 时再合并被拆开的标题；
+/// 另返回去掉的重复块开头的文字
+fn page_blocks<'a>(
+    page: &'a PageInfo,
+    options: &ConvertOptions,
+) -> (Cow<'a, [Block]>, Vec<String>) {
+    let (mut blocks, duplicates) = if options.dedupe_blocks {
+        drop_duplicate_blocks(&page.para_blocks, options.dehyphenate)
+    } else {
+        (Cow::Borrowed(&page.para_blocks[..]), Vec::new())
+    };
+    if options.merge_titles
+        && let Cow::Owned(merged) = merge_split_titles(&blocks, options.dehyphenate)
+    {
+        blocks = Cow::Owned(merged);
+    }
+    (blocks, duplicates)
+}
+
+/// 把 `numbers` 推进到本页最后一个标题之后，用于预先求出各页开头的标题编号；
+/// 与分析时相同，先去掉重复的块、合并被拆开的标题
+pub(crate) fn advance_heading_numbers(
+    page: &PageInfo,
+    page_idx: usize,
+    numbers: &mut HeadingNumbers,
+    options: &ConvertOptions,
+) {
+    let (blocks, _) = page_blocks(page, options);
+    for block in blocks.iter().filter(|block| block.block_type == "title") {
+        title_entry(block, options, page_idx, numbers);
+    }
+}
+
+/// 把 `depths` 推进到本页最后一个标题之后，用于预先求出各页开头的章节层级
+pub(crate) fn advance_section_depths(
+    page: &PageInfo,
+    page_idx: usize,
+    depths: &mut SectionDepths,
+    options: &ConvertOptions,
+) {
+    let (blocks, _) = page_blocks(page, options);
+    for block in blocks.iter().filter(|block| block.block_type == "title") {
+        if let Some(entry) = title_entry(block, options, page_idx, &mut HeadingNumbers::default()) {
+            depths.next(entry.level);
+        }
+    }
+}
+
+// ==================== Discarded Blocks ====================
+
+fn categorize_discarded_blocks(blocks: &[Block]) -> DiscardedBlocksCategory<'_> {
+    let mut category = DiscardedBlocksCategory {
+        headers: Vec::new(),
+        footers: Vec::new(),
+        footnotes: Vec::new(),
+        page_numbers: Vec::new(),
+        others: Vec::new(),
+    };
+
+    for block in blocks {
+        match block.block_type.as_str() {
+            "header" => category.headers.push(block),
+            "footer" => category.footers.push(block),
+            "page_footnote" => category.footnotes.push(block),
+            "page_number" => category.page_numbers.push(block),
+            _ => category.others.push(block),
+        }
+    }
+
+    category
+}
+
+fn block_texts(blocks: &[&Block], options: &ConvertOptions) -> Vec<String> {
+    blocks
+        .iter()
+        .map(|block| {
+            extract_text_from_block(block, options.dehyphenate)
+                .trim()
+                .to_string()
+        })
+        .collect()
+}
+
+/// 页面上印刷的页码：第一个有文字的 `page_number` 块，如 `vii`、`12`
+fn printed_page_number(blocks: &[&Block], options: &ConvertOptions) -> Option<String> {
+    block_texts(blocks, options)
+        .into_iter()
+        .find(|text| !text.is_empty())
+}
+
+fn header_text(blocks: &[&Block], options: &ConvertOptions) -> String {
+    block_texts(blocks, options)
+        .into_iter()
+        .filter(|text| !text.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// 开启 dedupe_headers 时需要隐藏页眉、页脚的页面（按 pages 中的位置）
+#[derive(Debug, Default)]
+pub(crate) struct RepeatedMargins {
+    pub headers: HashSet<usize>,
+    pub footers: HashSet<usize>,
+}
+
+/// 页眉、页脚分别连续超过三页完全相同时，只保留这一段的第一页
+pub(crate) fn repeated_margins(pages: &[&PageInfo], options: &ConvertOptions) -> RepeatedMargins {
+    let (headers, footers): (Vec<String>, Vec<String>) = pages
+        .iter()
+        .map(|page| {
+            let categorized = categorize_discarded_blocks(&page.discarded_blocks);
+            (
+                header_text(&categorized.headers, options),
+                header_text(&categorized.footers, options),
+            )
+        })
+        .unzip();
+    RepeatedMargins {
+        headers: repeated_runs(&headers),
+        footers: repeated_runs(&footers),
+    }
+}
+
+fn repeated_runs(texts: &[String]) -> HashSet<usize> {
+    let mut hidden = HashSet::new();
+    let mut run_start = 0;
+    for i in 1..=texts.len() {
+        if i < texts.len() && texts[i] == texts[run_start] {
+            continue;
+        }
+        if i - run_start > 3 && !texts[run_start].is_empty() {
+            hidden.extend(run_start + 1..i);
+        }
+        run_start = i;
+    }
+    hidden
+}
+
+// ==================== 页面分析 ====================
+
+/// 页眉、页脚的节点，每个有文字的块一个
+fn margin_nodes(
+    blocks: &[&Block],
+    options: &ConvertOptions,
+    node: fn(String) -> DocNode,
+) -> impl Iterator<Item = PageNode> {
+    block_texts(blocks, options)
+        .into_iter()
+        .filter(|text| !text.is_empty())
+        .map(move |text| PageNode {
+            node: node(text),
+            source: None,
+        })
+}
+
+/// 分析一页：页眉、正文、页面脚注、页脚，开启 page_dividers 时最后是分页线。
+/// `page_idx` 为页面在输出文档中的页码（合并多个输入时已加上偏移），取代 `page.page_idx`
+///
+/// 纯文本输出只包含正文块，不含页眉、页脚与页面脚注；LaTeX 与 JSON 输出不含页眉、页脚。
+/// 没有节点的正文块在此计为 skipped，其余块在写出时按输出是否为空计入统计。
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn analyze_page(
+    page: &PageInfo,
+    page_idx: usize,
+    document: usize,
+    state: &mut RenderState,
+    options: &ConvertOptions,
+    hide_headers: bool,
+    hide_footers: bool,
+    renderer: Option<&dyn BlockRenderer>,
+) -> Result<PageNodes, ConvertError> {
+    let mut nodes = Vec::new();
+
+    // 分类 discarded blocks
+    let mut categorized = categorize_discarded_blocks(&page.discarded_blocks);
+    let printed_page = printed_page_number(&categorized.page_numbers, options);
+    for block in &categorized.others {
+        *state
+            .stats
+            .other_discarded
+            .entry(block.block_type.clone())
+            .or_default() += 1;
+    }
+    state.stats.pages += 1;
+    let malformed = page.malformed_bboxes();
+    if malformed > 0 {
+        state.warnings.push(Warning::MalformedBBox {
+            page_idx,
+            count: malformed,
+        });
+    }
+    let mut unknown_spans: BTreeMap<&str, usize> = BTreeMap::new();
+    for span in page.para_blocks.iter().flat_map(Block::spans) {
+        if !is_known_span(span) {
+            *unknown_spans.entry(&span.span_type).or_default() += 1;
+        }
+    }
+    for (span_type, count) in unknown_spans {
+        state.warnings.push(Warning::UnknownSpanType {
+            page_idx,
+            span_type: span_type.to_string(),
+            count,
+        });
+    }
+    if matches!(
+        page.page_size_source,
+        PageSizeSource::Blocks | PageSizeSource::A4
+    ) {
+        state.warnings.push(Warning::InferredPageSize {
+            page_idx,
+            source: page.page_size_source,
+        });
+    }
+
+    let text_output = options.format == OutputFormat::Text;
+    let margins = !matches!(
+        options.format,
+        OutputFormat::Text
+            | OutputFormat::Latex
+            | OutputFormat::Json
+            | OutputFormat::Asciidoc
+            | OutputFormat::Rst
+            | OutputFormat::Org
+    );
+
+    // 页眉
+    if !options.skip_headers && !hide_headers && margins {
+        nodes.extend(margin_nodes(&categorized.headers, options, |text| {
+            DocNode::HeaderNote { text }
+        }));
+    } else {
+        state.stats.headers_dropped += categorized.headers.len();
+    }
+
+    // 主要内容块
+    let (mut blocks, duplicates) = page_blocks(page, options);
+    state.stats.duplicate_blocks += duplicates.len();
+    state.warnings.extend(
+        duplicates
+            .into_iter()
+            .map(|text| Warning::DuplicateBlock { page_idx, text }),
+    );
+    if options.detect_lists
+        && let Cow::Owned(grouped) = group_list_runs(&blocks, options.dehyphenate)
+    {
+        blocks = Cow::Owned(grouped);
+    }
+    let width = page_width(page);
+    if let Some(fraction) = options.detect_quotes
+        && let Cow::Owned(marked) = mark_indented_quotes(&blocks, width, fraction)
+    {
+        blocks = Cow::Owned(marked);
+    }
+
+    // 页面脚注按栏位与纵坐标排列；开启 footnotes_inline 时尽量紧接在同一栏上方的段落之后
+    let columns: Vec<_> = blocks
+        .iter()
+        .map(|block| column_of(&block.bbox, width))
+        .collect();
+    let two_columns = is_two_column(&columns);
+    if categorized
+        .footnotes
+        .iter()
+        .all(|block| !block.bbox.is_empty())
+    {
+        categorized
+            .footnotes
+            .sort_by(|a, b| compare_column_then_top(&a.bbox, &b.bbox, width, two_columns));
+    }
+    let footnotes_shown = !(options.skip_footnotes || text_output);
+    let mut inline_footnotes: Vec<Vec<String>> = vec![Vec::new(); blocks.len()];
+    let mut page_footnotes = Vec::new();
+    if footnotes_shown {
+        let texts = block_texts(&categorized.footnotes, options);
+        for (footnote, text) in categorized.footnotes.iter().zip(texts) {
+            let above = block_above(&footnote.bbox, &blocks, width, two_columns)
+                .filter(|&i| options.footnotes_inline && blocks[i].block_type == "text");
+            match above {
+                Some(i) if !text.is_empty() => {
+                    inline_footnotes[i].push(text);
+                    // 留下空白的脚注占住编号，页面底部的其余脚注编号不变
+                    page_footnotes.push(String::new());
+                }
+                _ => page_footnotes.push(text),
+            }
+        }
+        let inlined = inline_footnotes.iter().any(|notes| !notes.is_empty());
+        if inlined && page_footnotes.iter().all(String::is_empty) {
+            page_footnotes.clear();
+        }
+    }
+
+    for (block, footnotes) in blocks.iter().zip(&mut inline_footnotes) {
+        match analyze_block(block, state, options, page_idx, renderer)? {
+            Some(node) => nodes.push(PageNode {
+                node,
+                source: Some(BlockSource {
+                    block_type: block.block_type.clone(),
+                    bbox: block.bbox,
+                    index: block.index,
+                }),
+            }),
+            None => state.stats.count_block(&block.block_type, false),
+        }
+        nodes.extend(footnotes.drain(..).map(|text| PageNode {
+            node: DocNode::SmallPrint { text },
+            source: None,
+        }));
+    }
+
+    // 页面脚注；空白的脚注也保留，以免打乱按出现顺序分配的编号
+    if footnotes_shown {
+        nodes.extend(page_footnotes.into_iter().map(|text| PageNode {
+            node: DocNode::FootNote { text },
+            source: None,
+        }));
+    } else {
+        state.stats.footnotes_dropped += categorized.footnotes.len();
+    }
+
+    // 页脚
+    if !options.skip_footers && !hide_footers && margins {
+        nodes.extend(margin_nodes(&categorized.footers, options, |text| {
+            DocNode::FooterNote { text }
+        }));
+    } else {
+        state.stats.footers_dropped += categorized.footers.len();
+    }
+
+    if options.page_dividers {
+        nodes.push(PageNode {
+            node: DocNode::PageBreak {
+                page: page_idx + 1,
+                printed_page: printed_page.clone(),
+            },
+            source: None,
+        });
+    }
+
+    Ok(PageNodes {
+        page_idx,
+        document,
+        printed_page,
+        nodes,
+        continued: state.continued,
+        warnings: std::mem::take(&mut state.warnings),
+        figures: std::mem::take(&mut state.figures),
+        stats: std::mem::take(&mut state.stats),
+    })
+}
@@ -0,0 +1,202 @@
+//! AsciiDoc 输出：正文转义、章节标题、区块，以及每个输出文件开头的文档属性
+
+// ==================== 转义 ====================
+
+/// 转义 AsciiDoc 正文：行内格式标记、属性引用、反斜杠与会组成宏、锚点、交叉引用或
+/// 描述列表的字符写成数值字符引用（`&#42;`），其余字符原样保留；
+/// `line_start` 时在会被当作区块标记的行首（`.`、`=`、`- `、`1. `、`NOTE: ` 等）之前加 `{empty}`
+pub(crate) fn escape_asciidoc(text: &str, line_start: bool) -> String {
+    let mut out = String::with_capacity(text.len() + 8);
+    if line_start && starts_block_markup(text) {
+        out.push_str("{empty}");
+    }
+    let mut prev: Option<char> = None;
+    // 当前这个词（自上一个空白起）中是否出现过冒号；`name:target[` 形式的宏由此识别
+    let mut word_has_colon = false;
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        let escape = match c {
+            '*' | '_' | '`' | '#' | '^' | '~' | '+' | '{' | '\\' => true,
+            '[' => prev == Some('[') || word_has_colon,
+            '<' => prev == Some('<'),
+            ':' | ';' => prev == Some(c) && chars.peek().is_none_or(|next| next.is_whitespace()),
+            _ => false,
+        };
+        if escape {
+            out.push_str(&format!("&#{};", c as u32));
+        } else {
+            out.push(c);
+        }
+        if c.is_whitespace() {
+            word_has_colon = false;
+        } else if c == ':' {
+            word_has_colon = true;
+        }
+        prev = Some(c);
+    }
+    out
+}
+
+/// 行首是否会被解析为区块标记：标点开头（章节标题、块标题、列表、注释、分隔线、属性行等），
+/// 有序列表编号（`1. `、`a. `、`iv) `），或提示段落（`NOTE: `）
+fn starts_block_markup(text: &str) -> bool {
+    if text.starts_with(|c: char| c.is_ascii_punctuation()) {
+        return true;
+    }
+    let marker_end = text
+        .find(|c: char| !c.is_ascii_alphanumeric())
+        .unwrap_or(text.len());
+    let ordered = marker_end > 0
+        && text[marker_end..].starts_with(['.', ')'])
+        && text[marker_end + 1..].starts_with(char::is_whitespace);
+    let admonition = ["NOTE", "TIP", "IMPORTANT", "WARNING", "CAUTION"]
+        .iter()
+        .any(|label| {
+            text.strip_prefix(label)
+                .is_some_and(|rest| rest.starts_with(": "))
+        });
+    ordered || admonition
+}
+
+/// 宏的方括号之内的文字（脚注、交叉引用、链接文字）：在正文转义之外再转义 `]`
+pub(crate) fn macro_text(text: &str) -> String {
+    escape_asciidoc(text, false).replace(']', "\\]")
+}
+
+/// 属性列表中用双引号包围的值，如图片的替代文字
+pub(crate) fn quoted_attribute(text: &str) -> String {
+    format!("\"{}\"", text.replace('"', "\\\""))
+}
+
+/// 注释：每行加上 `// ` 前缀
+pub(crate) fn comment(text: &str) -> String {
+    text.lines()
+        .map(|line| format!("{}\n", format!("// {}", line).trim_end()))
+        .collect()
+}
+
+// ==================== 标题与区块 ====================
+
+/// 标题层级对应的章节标记；文档标题（层级 0）与一级标题都用 `==`，
+/// `=` 只能出现在文档开头
+pub(crate) fn section_marker(level: usize) -> String {
+    "=".repeat(level.clamp(1, 5) + 1)
+}
+
+/// 块标题行 `.标题`，放在图片、表格、代码块之前
+pub(crate) fn block_title(title: &str) -> String {
+    format!(".{}\n", escape_asciidoc(title, true))
+}
+
+/// 分隔区块，如代码块的 `----`；分隔线比内容中同样字符组成的最长一行更长，至少四个
+pub(crate) fn delimited_block(delimiter: char, content: &str) -> String {
+    let longest = content
+        .lines()
+        .filter(|line| !line.is_empty() && line.chars().all(|c| c == delimiter))
+        .map(str::len)
+        .max()
+        .unwrap_or(0);
+    let fence = delimiter.to_string().repeat((longest + 1).max(4));
+    format!("{}\n{}\n{}\n", fence, content, fence)
+}
+
+/// 块图片 `image::src["alt"]`
+pub(crate) fn block_image(src: &str, alt: &str) -> String {
+    format!("image::{}[{}]\n", src, quoted_attribute(alt))
+}
+
+/// 小字段落，用于脚注、参考文献条目、图表脚注等
+pub(crate) fn small_print(text: &str) -> String {
+    format!("[.small]\n{}\n", text)
+}
+
+// ==================== 文档属性 ====================
+
+/// 每个输出文件开头的文档属性：公式按 LaTeX 解析；图注、表注已带有原文的编号，
+/// 不再由 AsciiDoc 补 "Figure 1." 之类的前缀
+pub(crate) fn asciidoc_document_head() -> String {
+    ":stem: latexmath\n:figure-caption!:\n:table-caption!:\n\n".to_string()
+}
+
+/// 在正文之前加上文档属性，正文末尾只保留一个换行
+pub(crate) fn wrap_asciidoc_document(body: &str) -> String {
+    let mut asciidoc = asciidoc_document_head();
+    asciidoc.push_str(body.trim_end());
+    asciidoc.push('\n');
+    asciidoc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn block_markup_at_line_start_is_neutralized() {
+        for text in [
+            "= Not a title",
+            ". Not a block title",
+            "- not a list item",
+            "* not a list item",
+            "1. not an item",
+            "iv) not an item",
+            "NOTE: not an admonition",
+            "// not a comment",
+            "'''",
+        ] {
+            assert!(
+                escape_asciidoc(text, true).starts_with("{empty}"),
+                "{}",
+                text
+            );
+            assert!(!escape_asciidoc(text, false).starts_with("{empty}"));
+        }
+        for text in ["Plain text", "1.5 is a number", "NOTE without a colon"] {
+            assert!(
+                !escape_asciidoc(text, true).starts_with("{empty}"),
+                "{}",
+                text
+            );
+        }
+    }
+
+    #[test]
+    fn inline_markup_macros_and_attributes_are_escaped() {
+        assert_eq!(
+            escape_asciidoc("*bold* _it_ `code` #mark# x^2^ H~2~O", false),
+            "&#42;bold&#42; &#95;it&#95; &#96;code&#96; &#35;mark&#35; x&#94;2&#94; H&#126;2&#126;O"
+        );
+        assert_eq!(
+            escape_asciidoc("{attr} a\\b 1+1", false),
+            "&#123;attr} a&#92;b 1&#43;1"
+        );
+        // 宏、锚点与交叉引用只在组成标记时转义
+        assert_eq!(
+            escape_asciidoc("see link:x[y] [1]", false),
+            "see link:x&#91;y] [1]"
+        );
+        assert_eq!(
+            escape_asciidoc("[[id]] <<ref>> a < b", false),
+            "[&#91;id]] <&#60;ref>> a < b"
+        );
+        assert_eq!(
+            escape_asciidoc("term:: def; x;; y", false),
+            "term:&#58; def; x;&#59; y"
+        );
+        assert_eq!(escape_asciidoc("10:30 a::b", false), "10:30 a::b");
+        assert_eq!(macro_text("a [b] c"), "a [b\\] c");
+        assert_eq!(quoted_attribute("say \"hi\""), "\"say \\\"hi\\\"\"");
+    }
+
+    #[test]
+    fn section_markers_and_delimited_blocks() {
+        assert_eq!(section_marker(0), "==");
+        assert_eq!(section_marker(3), "====");
+        assert_eq!(section_marker(9), "======");
+        assert_eq!(delimited_block('-', "code"), "----\ncode\n----\n");
+        assert_eq!(
+            delimited_block('-', "a\n------\nb"),
+            "-------\na\n------\nb\n-------\n"
+        );
+        assert_eq!(comment("one\n\ntwo"), "// one\n//\n// two\n");
+    }
+}
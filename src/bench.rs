@@ -0,0 +1,256 @@
+//! 基准测试用的入口：在内存中合成 layout.json，并暴露几个内部的热点函数
+//!
+//! 只供 `benches/` 使用，不属于稳定的公开接口。合成的内容是确定的，同样的参数每次得到相同的页面。
+
+use std::io;
+use std::path::Path;
+
+use image::{Rgb, RgbImage};
+use serde_json::{Value, json};
+
+use crate::types::{Block, LayoutJson};
+use crate::{ConvertOptions, text};
+
+/// 合成页面引用的图片数量；文件由 [`write_fixture_images`] 写出
+pub const FIXTURE_IMAGES: usize = 4;
+
+const WORDS: [&str; 16] = [
+    "layout",
+    "analysis",
+    "recovers",
+    "reading",
+    "order",
+    "from",
+    "scanned",
+    "pages",
+    "while",
+    "tables",
+    "and",
+    "formulas",
+    "keep",
+    "their",
+    "structure",
+    "intact",
+];
+
+/// 由词表拼出的 `words` 个词；`seed` 不同时词序不同
+fn sentence(seed: usize, words: usize) -> String {
+    let mut text = String::new();
+    for n in 0..words {
+        if n > 0 {
+            text.push(' ');
+        }
+        text.push_str(WORDS[(seed * 7 + n * 3) % WORDS.len()]);
+    }
+    text
+}
+
+fn line(spans: Vec<Value>) -> Value {
+    json!({ "bbox": [50, 0, 550, 12], "spans": spans })
+}
+
+fn text_span(content: &str) -> Value {
+    json!({ "type": "text", "bbox": [50, 0, 550, 12], "content": content })
+}
+
+fn block(block_type: &str, index: usize, lines: Vec<Value>) -> Value {
+    let top = index * 40;
+    json!({
+        "type": block_type,
+        "bbox": [50, top, 550, top + 36],
+        "index": index,
+        "lines": lines,
+    })
+}
+
+fn paragraph(page: usize, n: usize, index: usize) -> Value {
+    let seed = page * 13 + n;
+    // 第三行以连字符断词，去连字符时与下一行接上
+    let lines = (0..5)
+        .map(|i| match i {
+            1 => line(vec![
+                text_span(&format!("{} ", sentence(seed + i, 6))),
+                json!({ "type": "inline_equation", "content": format!("x_{{{}}} + y^2", i) }),
+                text_span(" holds for"),
+            ]),
+            2 => line(vec![text_span(&format!(
+                "{} struc-",
+                sentence(seed + i, 8)
+            ))]),
+            _ => line(vec![text_span(&format!("{}.", sentence(seed + i, 10)))]),
+        })
+        .collect();
+    block("text", index, lines)
+}
+
+fn list(page: usize, index: usize) -> Value {
+    let items: Vec<Value> = (0..4)
+        .map(|i| {
+            json!({
+                "type": "list_item",
+                "lines": [line(vec![text_span(&format!("{}. {}", i + 1, sentence(page + i, 5)))])],
+            })
+        })
+        .collect();
+    let mut list = block("list", index, Vec::new());
+    list["blocks"] = Value::Array(items);
+    list
+}
+
+fn table(page: usize, index: usize) -> Value {
+    let mut html = String::from("<table><tr><th>Item</th><th>Value</th><th>Share</th></tr>");
+    for row in 0..8 {
+        html.push_str(&format!(
+            "<tr><td>{}</td><td>{}</td><td>{}%</td></tr>",
+            WORDS[(page + row) % WORDS.len()],
+            page * 100 + row,
+            row * 12
+        ));
+    }
+    html.push_str("</table>");
+    let mut table = block("table", index, Vec::new());
+    table["blocks"] = json!([
+        {"type": "table_caption", "lines": [line(vec![text_span(&format!("Table {}: results", page))])]},
+        {"type": "table_body", "lines": [line(vec![json!({"type": "table", "content": html})])]},
+    ]);
+    table
+}
+
+fn figure(page: usize, index: usize) -> Value {
+    let image_path = format!("figure_{}.png", page % FIXTURE_IMAGES);
+    let mut figure = block("image", index, Vec::new());
+    figure["blocks"] = json!([
+        {"type": "image_body", "lines": [line(vec![json!({"type": "image", "image_path": image_path})])]},
+        {"type": "image_caption", "lines": [line(vec![text_span(&format!("Figure {}: overview", page))])]},
+    ]);
+    figure
+}
+
+/// 一页合成内容：每十页一个一级标题，其余为二级标题；正文段落带行内公式与断词，
+/// 隔页穿插列表、表格、图片与行间公式，另有页眉与页码
+fn page(page_idx: usize) -> Value {
+    let mut blocks = Vec::new();
+    let title = if page_idx.is_multiple_of(10) {
+        json!({"level": 1, "text": format!("Chapter {}", page_idx / 10 + 1)})
+    } else {
+        json!({"level": 2, "text": format!("Section {}.{}", page_idx / 10 + 1, page_idx % 10)})
+    };
+    let mut heading = block(
+        "title",
+        0,
+        vec![line(vec![text_span(
+            title["text"].as_str().unwrap_or_default(),
+        )])],
+    );
+    heading["level"] = title["level"].clone();
+    blocks.push(heading);
+    for n in 0..4 {
+        blocks.push(paragraph(page_idx, n, blocks.len()));
+    }
+    match page_idx % 4 {
+        0 => blocks.push(list(page_idx, blocks.len())),
+        1 => blocks.push(table(page_idx, blocks.len())),
+        2 => blocks.push(figure(page_idx, blocks.len())),
+        _ => blocks.push(block(
+            "interline_equation",
+            blocks.len(),
+            vec![line(vec![json!({
+                "type": "interline_equation",
+                "content": format!("\\sum_{{i=1}}^{{{}}} a_i = \\frac{{n}}{{2}}", page_idx + 1),
+            })])],
+        )),
+    }
+    json!({
+        "page_idx": page_idx,
+        "page_size": [612, 792],
+        "para_blocks": blocks,
+        "discarded_blocks": [
+            block("header", 0, vec![line(vec![text_span("Synthetic Report")])]),
+            block("page_number", 1, vec![line(vec![text_span(&(page_idx + 1).to_string())])]),
+        ],
+    })
+}
+
+/// 合成 `pages` 页的 layout.json 文本
+pub fn synthetic_layout_json(pages: usize) -> String {
+    let pages: Vec<Value> = (0..pages).map(page).collect();
+    json!({ "pdf_info": pages, "_backend": "pipeline", "_version_name": "2.1.0" }).to_string()
+}
+
+/// 合成 `pages` 页的 layout.json，不经过 JSON 文本
+pub fn synthetic_layout(pages: usize) -> LayoutJson {
+    let pages: Vec<Value> = (0..pages).map(page).collect();
+    serde_json::from_value(json!({ "pdf_info": pages }))
+        .expect("synthetic layout matches the layout.json schema")
+}
+
+/// 在 `dir` 中写出合成页面引用的 [`FIXTURE_IMAGES`] 张 PNG
+pub fn write_fixture_images(dir: &Path) -> io::Result<()> {
+    for n in 0..FIXTURE_IMAGES {
+        let (width, height) = (320 + n as u32 * 40, 240);
+        let image = RgbImage::from_fn(width, height, |x, y| {
+            Rgb([
+                (x * 255 / width) as u8,
+                (y * 255 / height) as u8,
+                (n * 60) as u8,
+            ])
+        });
+        image
+            .save(dir.join(format!("figure_{}.png", n)))
+            .map_err(io::Error::other)?;
+    }
+    Ok(())
+}
+
+/// 一个文本块，`spans` 个 span 分布在每行 8 个的多行中，粗体、斜体、上下标与行内公式交替出现
+pub fn span_heavy_block(spans: usize) -> Block {
+    let lines: Vec<Value> = (0..spans.div_ceil(8))
+        .map(|row| {
+            let spans = (row * 8..(row * 8 + 8).min(spans))
+                .map(|n| match n % 5 {
+                    0 => json!({"type": "text", "content": WORDS[n % WORDS.len()], "bold": true}),
+                    1 => json!({"type": "text", "content": WORDS[n % WORDS.len()], "italic": true}),
+                    2 => json!({"type": "inline_equation", "content": format!("a_{{{}}}", n)}),
+                    3 => json!({"type": "text", "content": "2", "superscript": true}),
+                    _ => json!({"type": "text", "content": format!("{} *or* <b>", WORDS[n % WORDS.len()])}),
+                })
+                .collect();
+            line(spans)
+        })
+        .collect();
+    serde_json::from_value(block("text", 0, lines)).expect("span-heavy block is a valid block")
+}
+
+/// 嵌套 `depth` 层的块，每层有 `lines` 行文字与一个子块
+pub fn nested_block(depth: usize, lines: usize) -> Block {
+    let mut value = block("text", 0, Vec::new());
+    for level in (0..depth).rev() {
+        let own = (0..lines)
+            .map(|n| {
+                line(vec![text_span(&format!(
+                    "{} exam-",
+                    sentence(level + n, 6)
+                ))])
+            })
+            .collect();
+        let mut parent = block(if level == 0 { "image" } else { "list_item" }, 0, own);
+        parent["blocks"] = json!([value]);
+        value = parent;
+    }
+    serde_json::from_value(value).expect("nested block is a valid block")
+}
+
+/// 按图片格式生成 data URI
+pub fn image_to_base64(path: &Path, data: &[u8]) -> String {
+    crate::image::image_to_base64(path, data)
+}
+
+/// 块中的纯文本，与渲染时取图注等文字的方式相同
+pub fn extract_text_from_block(block: &Block, dehyphenate: bool) -> String {
+    text::extract_text_from_block(block, dehyphenate)
+}
+
+/// 按输出格式渲染块中的样式文字与行内公式
+pub fn render_rich_text(block: &Block, options: &ConvertOptions) -> String {
+    text::render_inlines(&text::rich_inlines(block, options), options)
+}
@@ -0,0 +1,245 @@
+//! 识别分数：统计低分的文本 span，开启 drop_below 时以 `[?]` 代替
+
+use std::borrow::Cow;
+
+use crate::ConvertOptions;
+use crate::doc::InlineStyle;
+use crate::stats::{ConvertStats, ScoredSpan};
+use crate::types::{Block, PageInfo, Span};
+
+/// 丢弃的文字的占位符
+const PLACEHOLDER: &str = "[?]";
+
+/// 报告中 span 文字的最大字符数
+const REPORT_TEXT_CHARS: usize = 40;
+
+/// 有文字、识别分数低于 `threshold` 的文本 span
+fn is_low(span: &Span, threshold: f64) -> bool {
+    span.score.is_some_and(|score| score < threshold)
+        && InlineStyle::of(span).is_some()
+        && span
+            .content
+            .as_deref()
+            .is_some_and(|text| !text.trim().is_empty())
+}
+
+/// 块及其子块中低于 `threshold` 的 span，按出现顺序
+fn low_spans<'a>(block: &'a Block, threshold: f64, out: &mut Vec<&'a Span>) {
+    out.extend(
+        block
+            .lines
+            .iter()
+            .flatten()
+            .flat_map(|line| &line.spans)
+            .filter(|span| is_low(span, threshold)),
+    );
+    for child in block.blocks.iter().flatten() {
+        low_spans(child, threshold, out);
+    }
+}
+
+/// 报告中的 span 文字：合并空白，过长时截断并加省略号
+fn report_text(text: &str) -> String {
+    let words: Vec<&str> = text.split_whitespace().collect();
+    let text = words.join(" ");
+    if text.chars().count() <= REPORT_TEXT_CHARS {
+        return text;
+    }
+    let mut short: String = text.chars().take(REPORT_TEXT_CHARS - 1).collect();
+    short.push('…');
+    short
+}
+
+/// 统计页面正文中低于 `min_confidence` 或 `drop_below`（取较高者）的 span；都未设置时不统计
+pub(crate) fn record_low_scores(
+    page: &PageInfo,
+    page_idx: usize,
+    options: &ConvertOptions,
+    stats: &mut ConvertStats,
+) {
+    let Some(threshold) = [options.min_confidence, options.drop_below]
+        .into_iter()
+        .flatten()
+        .reduce(f64::max)
+    else {
+        return;
+    };
+    let mut spans = Vec::new();
+    for block in &page.para_blocks {
+        low_spans(block, threshold, &mut spans);
+    }
+    stats.low_confidence_spans += spans.len();
+    stats.record_scores(spans.into_iter().map(|span| ScoredSpan {
+        page_idx,
+        score: span.score.unwrap_or(threshold),
+        text: report_text(span.content.as_deref().unwrap_or("")),
+    }));
+}
+
+// ==================== 丢弃低分文字 ====================
+
+/// 代替 `low` 的占位符，保留其首尾的空白，使占位符与前后的文字照常隔开
+fn placeholder(low: &Span) -> Span {
+    let text = low.content.as_deref().unwrap_or("");
+    let leading = &text[..text.len() - text.trim_start().len()];
+    let trailing = &text[text.trim_end().len()..];
+    Span {
+        bbox: low.bbox,
+        span_type: "text".to_string(),
+        content: Some(format!("{}{}{}", leading, PLACEHOLDER, trailing)),
+        image_path: None,
+        bold: false,
+        italic: false,
+        superscript: false,
+        subscript: false,
+        score: None,
+    }
+}
+
+/// 以占位符代替块中低于 `threshold` 的 span，同一行中相邻的只留一个；返回块是否应整个去掉：
+/// 自身的 span 都低于阈值且没有剩下的子块，或者只有子块、子块都已去掉
+fn drop_in_block(block: &mut Block, threshold: f64) -> bool {
+    let had_children = block
+        .blocks
+        .as_ref()
+        .is_some_and(|blocks| !blocks.is_empty());
+    if let Some(children) = &mut block.blocks {
+        children.retain_mut(|child| !drop_in_block(child, threshold));
+    }
+    let children_left = block
+        .blocks
+        .as_ref()
+        .is_some_and(|blocks| !blocks.is_empty());
+
+    let mut spans = 0;
+    let mut low = 0;
+    for line in block.lines.iter_mut().flatten() {
+        let mut kept = Vec::with_capacity(line.spans.len());
+        let mut after_placeholder = false;
+        for span in line.spans.drain(..) {
+            let blank = span
+                .content
+                .as_deref()
+                .is_none_or(|text| text.trim().is_empty());
+            if blank && span.image_path.is_none() {
+                kept.push(span);
+                continue;
+            }
+            spans += 1;
+            if !is_low(&span, threshold) {
+                after_placeholder = false;
+                kept.push(span);
+                continue;
+            }
+            low += 1;
+            if !after_placeholder {
+                kept.push(placeholder(&span));
+                after_placeholder = true;
+            } else if let Some(content) = kept.last_mut().and_then(|last| last.content.as_mut()) {
+                // 相邻的低分 span 只留一个占位符，其后的空白取最后一个 span 的
+                let text = span.content.as_deref().unwrap_or("");
+                content.truncate(content.trim_end().len());
+                content.push_str(&text[text.trim_end().len()..]);
+            }
+        }
+        line.spans = kept;
+    }
+    let all_low = spans > 0 && low == spans;
+    (all_low || (spans == 0 && had_children)) && !children_left
+}
+
+/// 以 `[?]` 代替页面中低于 `threshold` 的 span，去掉文字都低于阈值的块
+/// （见 [`ConvertOptions::drop_below`]）；没有低分的 span 时不复制页面
+pub(crate) fn drop_low_confidence(page: Cow<'_, PageInfo>, threshold: f64) -> Cow<'_, PageInfo> {
+    let mut spans = Vec::new();
+    for block in page.para_blocks.iter().chain(&page.discarded_blocks) {
+        low_spans(block, threshold, &mut spans);
+    }
+    if spans.is_empty() {
+        return page;
+    }
+    let mut page = page.into_owned();
+    page.para_blocks
+        .retain_mut(|block| !drop_in_block(block, threshold));
+    page.discarded_blocks
+        .retain_mut(|block| !drop_in_block(block, threshold));
+    Cow::Owned(page)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::testing::{bare_options, page_layout};
+    use crate::{ConvertOptions, convert_layout_to_markdown};
+    use serde_json::json;
+    use std::path::Path;
+
+    fn scored_block(top: f64, spans: &[(&str, f64)]) -> serde_json::Value {
+        let bbox = [50.0, top, 550.0, top + 40.0];
+        let spans: Vec<serde_json::Value> = spans
+            .iter()
+            .map(|(content, score)| {
+                json!({"type": "text", "bbox": bbox, "content": content, "score": score})
+            })
+            .collect();
+        json!({"type": "text", "bbox": bbox, "lines": [{"bbox": bbox, "spans": spans}]})
+    }
+
+    #[test]
+    fn low_scores_are_reported_and_optionally_dropped() {
+        let layout = page_layout(json!([
+            scored_block(
+                100.0,
+                &[
+                    ("Clear text ", 0.99),
+                    ("smudged ", 0.4),
+                    ("words ", 0.3),
+                    ("remain.", 0.95)
+                ]
+            ),
+            scored_block(200.0, &[("#@!", 0.1), ("%%", 0.2)]),
+            scored_block(300.0, &[("Fine.", 0.9)]),
+        ]));
+        let convert = |options: &ConvertOptions| {
+            convert_layout_to_markdown(&layout, Path::new("."), options).unwrap()
+        };
+
+        // 低分的文字标出分数，仍然输出
+        let reported = convert(&ConvertOptions {
+            min_confidence: Some(0.5),
+            ..bare_options()
+        });
+        assert!(
+            reported.markdown.starts_with(
+                "Clear text <mark title=\"score 0.40\">smudged</mark> \
+                 <mark title=\"score 0.30\">words</mark> remain.\n"
+            ),
+            "{}",
+            reported.markdown
+        );
+        assert_eq!(reported.stats.low_confidence_spans, 4);
+        let lowest: Vec<(f64, &str)> = reported
+            .stats
+            .lowest_scores
+            .iter()
+            .map(|span| (span.score, span.text.as_str()))
+            .collect();
+        assert_eq!(lowest[0], (0.1, "#@!"));
+        assert_eq!(lowest.len(), 4);
+
+        let dropped = convert(&ConvertOptions {
+            drop_below: Some(0.5),
+            ..bare_options()
+        });
+        // 相邻的低分 span 只留一个占位符，与后面的文字照常隔开；文字都低于阈值的块去掉
+        assert_eq!(dropped.markdown, "Clear text [?] remain.\n\nFine.\n\n");
+        assert_eq!(dropped.stats.low_confidence_spans, 4);
+
+        let untouched = convert(&bare_options());
+        assert_eq!(untouched.stats.low_confidence_spans, 0);
+        assert!(
+            untouched.markdown.contains("#@!%%"),
+            "{}",
+            untouched.markdown
+        );
+    }
+}
@@ -0,0 +1,420 @@
+//! MinerU content_list.json：扁平的内容数组，转换为与 middle.json 相同的页面结构后复用渲染流程
+
+use serde::de::IgnoredAny;
+use serde::{Deserialize, Deserializer};
+use serde_json::value::RawValue;
+use std::collections::BTreeMap;
+use std::fmt;
+
+use crate::error::{ConvertError, Warning};
+use crate::types::{
+    BBox, Block, LayoutJson, Line, PageInfo, PageSizeSource, Span, deserialize_page_idx,
+};
+
+// ==================== 类型定义 ====================
+
+/// content_list.json 中的一个条目
+#[derive(Debug, Deserialize, Clone)]
+pub struct ContentListEntry {
+    #[serde(rename = "type")]
+    pub entry_type: String,
+    pub text: Option<String>,
+    pub text_level: Option<u32>,
+    pub img_path: Option<String>,
+    #[serde(default, deserialize_with = "deserialize_page_idx")]
+    pub page_idx: usize,
+    #[serde(default)]
+    pub bbox: BBox,
+    /// MinerU 1.x 中为 `img_caption`
+    #[serde(default, alias = "img_caption")]
+    pub image_caption: Vec<String>,
+    /// MinerU 1.x 中为 `img_footnote`
+    #[serde(default, alias = "img_footnote")]
+    pub image_footnote: Vec<String>,
+    pub table_body: Option<String>,
+    #[serde(default)]
+    pub table_caption: Vec<String>,
+    #[serde(default)]
+    pub table_footnote: Vec<String>,
+    #[serde(default)]
+    pub list_items: Vec<String>,
+    pub code_body: Option<String>,
+    #[serde(default)]
+    pub code_caption: Vec<String>,
+    pub guess_lang: Option<String>,
+}
+
+// ==================== 格式识别 ====================
+
+/// 输入 JSON 的格式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum InputFormat {
+    /// 根据顶层结构自动识别：数组为 content_list.json，对象为 middle.json
+    #[default]
+    Auto,
+    /// 带 pdf_info 的 middle.json / layout.json
+    Middle,
+    /// 扁平数组形式的 content_list.json
+    ContentList,
+}
+
+impl fmt::Display for InputFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InputFormat::Auto => write!(f, "auto"),
+            InputFormat::Middle => write!(f, "middle.json"),
+            InputFormat::ContentList => write!(f, "content_list.json"),
+        }
+    }
+}
+
+fn detect_format(json: &str) -> InputFormat {
+    match json
+        .trim_start_matches('\u{feff}')
+        .trim_start()
+        .chars()
+        .next()
+    {
+        Some('[') => InputFormat::ContentList,
+        _ => InputFormat::Middle,
+    }
+}
+
+/// 解析 middle.json 或 content_list.json，统一得到 LayoutJson
+///
+/// middle.json 中某一页无法解析时，错误中会指明是哪一页。
+pub fn parse_layout(json: &str, format: InputFormat) -> Result<LayoutJson, ConvertError> {
+    parse(json, format, false).map(|(layout, _)| layout)
+}
+
+/// 与 [`parse_layout`] 相同，但跳过无法解析的页面，并为每个跳过的页面返回一条警告
+pub fn parse_layout_lenient(
+    json: &str,
+    format: InputFormat,
+) -> Result<(LayoutJson, Vec<Warning>), ConvertError> {
+    parse(json, format, true)
+}
+
+fn parse(
+    json: &str,
+    format: InputFormat,
+    lenient: bool,
+) -> Result<(LayoutJson, Vec<Warning>), ConvertError> {
+    let format = match format {
+        InputFormat::Auto => detect_format(json),
+        other => other,
+    };
+
+    match format {
+        InputFormat::ContentList => serde_json::from_str::<Vec<ContentListEntry>>(json)
+            .map(|entries| (content_list_to_layout(entries), Vec::new()))
+            .map_err(|source| ConvertError::Parse { format, source }),
+        _ => parse_middle(json, lenient),
+    }
+}
+
+/// 只切分出各页原文的顶层结构
+#[derive(Deserialize)]
+struct RawLayout<'a> {
+    #[serde(borrow)]
+    pdf_info: Vec<&'a RawValue>,
+    #[serde(rename = "_backend")]
+    backend: Option<String>,
+    #[serde(rename = "_version_name")]
+    version_name: Option<String>,
+}
+
+/// 页面中用于推测 MinerU 版本的字段
+#[derive(Deserialize)]
+struct RawPageFields {
+    para_blocks: Option<IgnoredAny>,
+    preproc_blocks: Option<IgnoredAny>,
+}
+
+/// 按顶层与页面的字段推测输入来自哪个版本的 MinerU：2.x 等新版本记有 `_version_name` 与 `_backend`，
+/// 0.9 之前的版本只有 preproc_blocks
+pub(crate) fn guess_schema(json: &str, format: InputFormat) -> String {
+    let format = match format {
+        InputFormat::Auto => detect_format(json),
+        other => other,
+    };
+    if format == InputFormat::ContentList {
+        return "MinerU content_list.json".to_string();
+    }
+    let Ok(raw) = serde_json::from_str::<RawLayout>(json) else {
+        return "unknown".to_string();
+    };
+    let preproc_only = || {
+        raw.pdf_info.iter().any(|page| {
+            serde_json::from_str::<RawPageFields>(page.get())
+                .is_ok_and(|page| page.para_blocks.is_none() && page.preproc_blocks.is_some())
+        })
+    };
+    match (&raw.version_name, &raw.backend) {
+        (Some(version), Some(backend)) => format!("MinerU {} ({} backend)", version, backend),
+        (Some(version), None) => format!("MinerU {}", version),
+        (None, Some(backend)) => format!("MinerU ({} backend, version not recorded)", backend),
+        (None, None) if preproc_only() => "MinerU before 0.9 (preproc_blocks)".to_string(),
+        (None, None) => "MinerU 0.9 to 1.x (no version fields)".to_string(),
+    }
+}
+
+#[derive(Deserialize)]
+struct RawPageIndex {
+    #[serde(default, deserialize_with = "deserialize_optional_page_idx")]
+    page_idx: Option<usize>,
+}
+
+fn deserialize_optional_page_idx<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<usize>, D::Error> {
+    deserialize_page_idx(deserializer).map(Some)
+}
+
+/// 去掉 serde_json 错误信息末尾的位置；逐页解析时的位置是相对于该页的，没有参考价值
+fn error_message(error: &serde_json::Error) -> String {
+    let message = error.to_string();
+    let position = format!(" at line {} column {}", error.line(), error.column());
+    message
+        .strip_suffix(&position)
+        .map(str::to_string)
+        .unwrap_or(message)
+}
+
+fn parse_middle(json: &str, lenient: bool) -> Result<(LayoutJson, Vec<Warning>), ConvertError> {
+    let error = match serde_json::from_str::<LayoutJson>(json) {
+        Ok(layout) => return Ok((layout, Vec::new())),
+        Err(error) => error,
+    };
+    let parse_error = |source| ConvertError::Parse {
+        format: InputFormat::Middle,
+        source,
+    };
+
+    // 整体解析失败时再逐页解析，找出出错的页面
+    let Ok(raw) = serde_json::from_str::<RawLayout>(json) else {
+        return Err(parse_error(error));
+    };
+    let mut pages = Vec::new();
+    let mut warnings = Vec::new();
+    for (position, raw_page) in raw.pdf_info.iter().enumerate() {
+        let page_error = match serde_json::from_str::<PageInfo>(raw_page.get()) {
+            Ok(page) => {
+                pages.push(page);
+                continue;
+            }
+            Err(page_error) => page_error,
+        };
+        let page_idx = serde_json::from_str::<RawPageIndex>(raw_page.get())
+            .ok()
+            .and_then(|raw| raw.page_idx)
+            .unwrap_or(position);
+        if !lenient {
+            return Err(ConvertError::InvalidPage {
+                page_idx,
+                source: error,
+            });
+        }
+        warnings.push(Warning::SkippedPage {
+            page_idx,
+            reason: error_message(&page_error),
+        });
+    }
+
+    // 每一页都能解析，说明问题出在页面之外
+    if warnings.is_empty() {
+        return Err(parse_error(error));
+    }
+    let layout = LayoutJson {
+        pdf_info: pages,
+        backend: raw.backend,
+        version_name: raw.version_name,
+    };
+    Ok((layout, warnings))
+}
+
+// ==================== 结构转换 ====================
+
+fn span(span_type: &str, content: Option<String>, image_path: Option<String>) -> Span {
+    Span {
+        bbox: BBox::default(),
+        span_type: span_type.to_string(),
+        content,
+        image_path,
+        bold: false,
+        italic: false,
+        superscript: false,
+        subscript: false,
+        score: None,
+    }
+}
+
+fn block(block_type: &str, bbox: BBox) -> Block {
+    Block {
+        bbox,
+        block_type: block_type.to_string(),
+        angle: None,
+        lines: None,
+        blocks: None,
+        index: None,
+        sub_type: None,
+        level: None,
+        guess_lang: None,
+    }
+}
+
+fn with_spans(mut block: Block, spans: Vec<Span>) -> Block {
+    block.lines = Some(vec![Line {
+        bbox: block.bbox,
+        spans,
+    }]);
+    block
+}
+
+fn text_block(block_type: &str, text: &str, bbox: BBox) -> Block {
+    with_spans(
+        block(block_type, bbox),
+        vec![span("text", Some(text.to_string()), None)],
+    )
+}
+
+fn caption_blocks(block_type: &str, texts: &[String], bbox: BBox) -> Vec<Block> {
+    texts
+        .iter()
+        .filter(|text| !text.trim().is_empty())
+        .map(|text| text_block(block_type, text, bbox))
+        .collect()
+}
+
+/// 返回转换后的块，以及它是否属于 discarded_blocks（页眉、页脚等）
+fn entry_to_block(entry: ContentListEntry) -> (Block, bool) {
+    let bbox = entry.bbox;
+    let text = entry.text.unwrap_or_default();
+
+    let converted = match entry.entry_type.as_str() {
+        "text" if entry.text_level.is_some_and(|level| level > 0) => {
+            let mut title = text_block("title", &text, bbox);
+            title.level = entry.text_level;
+            title
+        }
+        "image" => {
+            let mut image = block("image", bbox);
+            let mut children = vec![with_spans(
+                block("image_body", bbox),
+                vec![span("image", None, entry.img_path)],
+            )];
+            children.extend(caption_blocks("image_caption", &entry.image_caption, bbox));
+            children.extend(caption_blocks(
+                "image_footnote",
+                &entry.image_footnote,
+                bbox,
+            ));
+            image.blocks = Some(children);
+            image
+        }
+        "table" => {
+            let mut table = block("table", bbox);
+            let mut children = caption_blocks("table_caption", &entry.table_caption, bbox);
+            children.push(with_spans(
+                block("table_body", bbox),
+                vec![span("table", entry.table_body, entry.img_path)],
+            ));
+            children.extend(caption_blocks(
+                "table_footnote",
+                &entry.table_footnote,
+                bbox,
+            ));
+            table.blocks = Some(children);
+            table
+        }
+        "equation" => with_spans(
+            block("interline_equation", bbox),
+            vec![span(
+                "interline_equation",
+                Some(text).filter(|t| !t.trim().is_empty()),
+                entry.img_path,
+            )],
+        ),
+        "list" => {
+            let mut list = block("list", bbox);
+            list.blocks = Some(
+                entry
+                    .list_items
+                    .iter()
+                    .map(|item| text_block("list_item", item, bbox))
+                    .collect(),
+            );
+            list
+        }
+        "code" => {
+            let mut code = block("code", bbox);
+            code.guess_lang = entry.guess_lang;
+            let mut children = caption_blocks("code_caption", &entry.code_caption, bbox);
+            let mut body = block("code_body", bbox);
+            body.lines = Some(
+                entry
+                    .code_body
+                    .unwrap_or_default()
+                    .lines()
+                    .map(|line| Line {
+                        bbox,
+                        spans: vec![span("text", Some(line.to_string()), None)],
+                    })
+                    .collect(),
+            );
+            children.push(body);
+            code.blocks = Some(children);
+            code
+        }
+        "header" | "footer" | "page_number" | "page_footnote" | "aside_text" => {
+            return (text_block(&entry.entry_type, &text, bbox), true);
+        }
+        _ => text_block("text", &text, bbox),
+    };
+
+    (converted, false)
+}
+
+/// 连续缺失不超过该页数时补上空页；更长的空缺多半来自错误的 page_idx，不逐页补齐
+const MAX_MISSING_PAGES: usize = 1000;
+
+fn empty_page(page_idx: usize) -> PageInfo {
+    PageInfo {
+        para_blocks: Vec::new(),
+        discarded_blocks: Vec::new(),
+        page_size: (0.0, 0.0),
+        page_size_source: PageSizeSource::Unknown,
+        page_idx,
+    }
+}
+
+/// 按 page_idx 把条目分组成页面；中间缺失的页面保留为空页，保证页码连续
+pub fn content_list_to_layout(entries: Vec<ContentListEntry>) -> LayoutJson {
+    let mut used: BTreeMap<usize, PageInfo> = BTreeMap::new();
+    for entry in entries {
+        let page_idx = entry.page_idx;
+        let (converted, discarded) = entry_to_block(entry);
+        let page = used.entry(page_idx).or_insert_with(|| empty_page(page_idx));
+        if discarded {
+            page.discarded_blocks.push(converted);
+        } else {
+            page.para_blocks.push(converted);
+        }
+    }
+
+    let mut pages = Vec::with_capacity(used.len());
+    let mut next = 0;
+    for (page_idx, page) in used {
+        if page_idx - next <= MAX_MISSING_PAGES {
+            pages.extend((next..page_idx).map(empty_page));
+        }
+        pages.push(page);
+        next = page_idx + 1;
+    }
+
+    LayoutJson {
+        pdf_info: pages,
+        backend: None,
+        version_name: None,
+    }
+}
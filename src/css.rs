@@ -0,0 +1,189 @@
+//! Styled 风格与 HTML 输出中元素的样式：按 [`CssMode`] 写成内联的 `style` 属性，
+//! 或 `mineru-*` 类名加上在样式块中定义这些类的规则
+//!
+//! 每个类的样式只在 [`STYLES`] 中写一次，内联样式与样式块中的规则都由它生成，两种写法的显示效果相同。
+
+use std::borrow::Cow;
+
+use crate::{ConvertOptions, CssMode};
+
+/// 各个类的样式声明；元素可以带多个类（如 `mineru-figcaption mineru-figcaption-below`），
+/// 内联时各类的声明按顺序以空格连接
+const STYLES: &[(&str, &str)] = &[
+    ("mineru-figure", "margin: 1.5em 0; text-align: center;"),
+    (
+        "mineru-figure-image",
+        "max-width: 100%; height: auto; display: block; margin: 0 auto;",
+    ),
+    (
+        "mineru-figcaption",
+        "text-align: center; font-size: 0.9em; color: #666;",
+    ),
+    ("mineru-figcaption-above", "margin-bottom: 0.5em;"),
+    ("mineru-figcaption-below", "margin-top: 0.5em;"),
+    (
+        "mineru-figure-note",
+        "text-align: center; font-size: 0.8em; color: #888;",
+    ),
+    ("mineru-figure-note-above", "margin-bottom: 0.3em;"),
+    ("mineru-figure-note-below", "margin-top: 0.3em;"),
+    (
+        "mineru-panels",
+        "display: flex; flex-wrap: wrap; justify-content: center; align-items: flex-start; gap: 1em;",
+    ),
+    ("mineru-panel", "margin: 0; max-width: 100%;"),
+    (
+        "mineru-panel-caption",
+        "text-align: center; font-size: 0.85em; color: #666; margin-top: 0.3em;",
+    ),
+    (
+        "mineru-missing-image",
+        "border: 1px dashed #d9a0a0; color: #b55; padding: 0.8em 1em; margin: 0 auto; font-size: 0.85em; text-align: center;",
+    ),
+    ("mineru-table-wrap", "margin: 1.5em 0; overflow-x: auto;"),
+    ("mineru-table-caption", "font-weight: bold;"),
+    ("mineru-table-caption-above", "margin-bottom: 0.5em;"),
+    ("mineru-table-caption-below", "margin-top: 0.5em;"),
+    ("mineru-table-note", "font-size: 0.85em; color: #666;"),
+    ("mineru-table-note-above", "margin-bottom: 0.5em;"),
+    ("mineru-table-note-below", "margin-top: 0.5em;"),
+    ("mineru-equation", "margin: 1em 0; text-align: center;"),
+    ("mineru-equation-image", "max-width: 100%; height: auto;"),
+    (
+        "mineru-small-print",
+        "font-size: 0.85em; color: #666; margin: 0.5em 0;",
+    ),
+    (
+        "mineru-header-note",
+        "background: #fafafa; padding: 0.5em 1em; margin-bottom: 1em; border-radius: 4px; font-size: 0.85em; color: #888;",
+    ),
+    (
+        "mineru-footnotes",
+        "background: #f8f8f8; padding: 0.8em 1em; margin-top: 1.5em; border-left: 3px solid #ddd; border-radius: 0 4px 4px 0; font-size: 0.85em; color: #666;",
+    ),
+    ("mineru-footnote", "margin: 0.3em 0;"),
+    (
+        "mineru-page-divider",
+        "display: flex; align-items: center; margin: 2.5em 0; gap: 1em;",
+    ),
+    (
+        "mineru-page-divider-line",
+        "flex: 1; height: 1px; background: #ddd;",
+    ),
+    (
+        "mineru-page-divider-label",
+        "color: #888; font-size: 0.85em;",
+    ),
+    (
+        "mineru-page-divider-compact",
+        "border-top: 1px solid #eee; margin: 1.5em 0; text-align: right; color: #999; font-size: 0.85em;",
+    ),
+    (
+        "mineru-section-rule",
+        "border: none; height: 1px; background: #ddd; margin: 2em 0;",
+    ),
+    (
+        "mineru-back-to-top",
+        "text-align: right; font-size: 0.85em;",
+    ),
+    (
+        "mineru-footer-rule",
+        "border: none; height: 1px; background: #ddd; margin: 3em 0;",
+    ),
+    (
+        "mineru-footer",
+        "text-align: center; color: #999; font-size: 0.85em; padding: 1em 0;",
+    ),
+];
+
+/// 一个类的样式声明；公式截图设置 equation_max_height 时改为限高
+fn declarations(class: &str, options: &ConvertOptions) -> Cow<'static, str> {
+    if class == "mineru-equation-image"
+        && let Some(height) = options.equation_max_height
+    {
+        // 带上 width / height 属性后需要 `width: auto`，限高时才能保持宽高比
+        return Cow::Owned(format!(
+            "max-width: 100%; max-height: {}px; width: auto;",
+            height
+        ));
+    }
+    STYLES
+        .iter()
+        .find(|(name, _)| *name == class)
+        .map(|(_, style)| Cow::Borrowed(*style))
+        .unwrap_or_default()
+}
+
+/// 元素的样式属性：`Inline` 时为 ` style="…"`，否则为 ` class="…"`；`classes` 为以空格分隔的类名
+pub(crate) fn style_attr(classes: &str, options: &ConvertOptions) -> String {
+    match options.css_mode {
+        CssMode::Inline => {
+            let style: Vec<Cow<str>> = classes
+                .split_whitespace()
+                .map(|class| declarations(class, options))
+                .collect();
+            format!(" style=\"{}\"", style.join(" "))
+        }
+        CssMode::Classes | CssMode::None => format!(" class=\"{}\"", classes),
+    }
+}
+
+/// `Classes` 时样式块中定义各个类的规则，与样式块中其余的规则格式相同；其余写法为空
+pub(crate) fn class_rules(options: &ConvertOptions) -> String {
+    if options.css_mode != CssMode::Classes {
+        return String::new();
+    }
+    STYLES
+        .iter()
+        .map(|(class, _)| format!("  .{} {{ {} }}\n", class, declarations(class, options)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn with_mode(css_mode: CssMode) -> ConvertOptions {
+        ConvertOptions {
+            css_mode,
+            ..ConvertOptions::default()
+        }
+    }
+
+    #[test]
+    fn inline_styles_join_the_declarations_of_each_class() {
+        assert_eq!(
+            style_attr(
+                "mineru-figcaption mineru-figcaption-below",
+                &with_mode(CssMode::Inline)
+            ),
+            " style=\"text-align: center; font-size: 0.9em; color: #666; margin-top: 0.5em;\""
+        );
+        for mode in [CssMode::Classes, CssMode::None] {
+            assert_eq!(
+                style_attr(
+                    "mineru-figcaption mineru-figcaption-below",
+                    &with_mode(mode)
+                ),
+                " class=\"mineru-figcaption mineru-figcaption-below\""
+            );
+        }
+    }
+
+    #[test]
+    fn only_the_classes_mode_defines_rules() {
+        let rules = class_rules(&with_mode(CssMode::Classes));
+        assert_eq!(rules.lines().count(), STYLES.len());
+        assert!(rules.contains("  .mineru-footer { text-align: center;"));
+        assert!(class_rules(&with_mode(CssMode::Inline)).is_empty());
+        assert!(class_rules(&with_mode(CssMode::None)).is_empty());
+
+        let capped = ConvertOptions {
+            equation_max_height: Some(80),
+            ..with_mode(CssMode::Classes)
+        };
+        assert!(class_rules(&capped).contains(
+            "  .mineru-equation-image { max-width: 100%; max-height: 80px; width: auto; }"
+        ));
+    }
+}
@@ -0,0 +1,256 @@
+//! 文档模型：版面块经一遍分析整理为与输出格式无关的节点，各输出格式的写出都只读取节点
+//!
+//! [`crate::analyze_documents`] 得到 [`DocumentModel`] 后，可以在写出之前增删、修改其中的节点，
+//! 再交给 [`crate::write_document`] 输出。
+
+use serde::Serialize;
+
+use crate::error::Warning;
+use crate::figures::FigureEntry;
+use crate::stats::ConvertStats;
+use crate::types::{BBox, TocEntry};
+
+// ==================== 节点 ====================
+
+/// 文档中的一个节点；文字均未转义，转义与样式由写出时的输出格式决定
+#[derive(Debug, Clone, PartialEq)]
+pub enum DocNode {
+    /// 标题；`level` 为 0 时是文档标题，1–5 为各级章节标题
+    Heading {
+        level: usize,
+        /// 标题文字；其中的行内公式为不带分隔符的 LaTeX，用于目录与锚点
+        text: String,
+        /// 去重之前的锚点
+        anchor: String,
+        /// 标题含有行内公式时按出现顺序的文字与公式，输出标题时代替 `text`；不含公式时为空
+        inlines: Vec<Inline>,
+    },
+    /// 正文段落；`rotation` 为旋转 90° / 270° 的文字块的角度
+    Paragraph {
+        inlines: Vec<Inline>,
+        rotation: Option<u32>,
+    },
+    /// 代码块，`code` 已去掉首尾空行，保留缩进
+    Code {
+        language: Option<String>,
+        caption: Option<String>,
+        code: String,
+    },
+    List {
+        items: Vec<ListItem>,
+    },
+    /// 图片；`image` 为 `None` 时不读取图片（纯文本输出）。`anchor` 为开启图表编号时的锚点，
+    /// 编号已写入第一个图注
+    Figure {
+        image: Option<ImageSource>,
+        /// 由多张图片组成的组图的各个分图，按出现顺序，`image` 为第一张输出的图片；只有一张图片时为空
+        panels: Vec<Panel>,
+        /// 图注与图片脚注，按出现顺序
+        captions: Vec<Caption>,
+        anchor: Option<String>,
+        rotation: Option<u32>,
+    },
+    /// 表格；`body` 为 `None` 时不读取表格内容（纯文本输出）。`anchor` 与图片相同，编号已写入第一个表注
+    Table {
+        body: Option<TableBody>,
+        /// 表注与表格脚注，按出现顺序
+        captions: Vec<Caption>,
+        anchor: Option<String>,
+        rotation: Option<u32>,
+        /// 开启 table_export 时指向导出的 CSV 文件的链接
+        csv: Option<String>,
+    },
+    /// 行间公式；同时有 LaTeX 与截图时由选项决定输出哪一个
+    Equation {
+        latex: Option<String>,
+        image: Option<ImageSource>,
+    },
+    /// 参考文献一节中的段落，已拆分为逐条
+    References {
+        entries: Vec<String>,
+    },
+    /// 索引、目录页等逐行排列的内容
+    Index {
+        lines: Vec<String>,
+    },
+    /// 引文；`lines` 为按原文硬换行分开的各行，没有硬换行时只有一行
+    Quote {
+        lines: Vec<Vec<Inline>>,
+    },
+    /// 脚注、注音、旁注等正文中的小字段落
+    SmallPrint {
+        text: String,
+    },
+    /// 页眉
+    HeaderNote {
+        text: String,
+    },
+    /// 页脚
+    FooterNote {
+        text: String,
+    },
+    /// 页面脚注；一页的脚注在正文之后集中输出，文字可能为空（编号仍按出现顺序计算）
+    FootNote {
+        text: String,
+    },
+    /// 页面末尾的分页线；`page` 从 1 开始，`printed_page` 为原书印刷的页码
+    PageBreak {
+        page: usize,
+        printed_page: Option<String>,
+    },
+    /// 由 [`crate::BlockRenderer`] 直接生成的内容，原样写出；标题块附带目录条目
+    Rendered {
+        content: String,
+        toc_entry: Option<TocEntry>,
+    },
+}
+
+/// 段落中的一段文字或行内公式
+#[derive(Debug, Clone, PartialEq)]
+pub enum Inline {
+    Text {
+        text: String,
+        style: InlineStyle,
+    },
+    /// 行内公式的 LaTeX，不含分隔符
+    Math {
+        latex: String,
+    },
+}
+
+/// 文本 span 的行内样式，来自 bold / italic 等标记或 `text_strong`、`sup` 等 span 类型
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct InlineStyle {
+    pub bold: bool,
+    pub italic: bool,
+    pub superscript: bool,
+    pub subscript: bool,
+    /// 低于 `min_confidence` 的识别分数，输出时高亮；不低于阈值或没有分数时为 `None`
+    pub low_confidence: Option<f64>,
+}
+
+/// 列表项目；JSON 输出中子项目放在 `items` 中
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ListItem {
+    /// 有序列表中的编号；`None` 为无序列表的项目
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub number: Option<u32>,
+    /// 已去掉编号或项目符号的文字；为空时只输出子项目
+    pub text: String,
+    #[serde(rename = "items", skip_serializing_if = "Vec::is_empty")]
+    pub children: Vec<ListItem>,
+}
+
+impl ListItem {
+    pub fn is_ordered(&self) -> bool {
+        self.number.is_some()
+    }
+}
+
+/// 图注、表注或图表脚注
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Caption {
+    pub kind: CaptionKind,
+    pub text: String,
+    pub rotation: Option<u32>,
+    /// 输出在图片或表格的上方
+    pub above: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CaptionKind {
+    Caption,
+    Footnote,
+}
+
+/// 组图中的一个分图，`caption` 为紧跟在这张图片之后的分图说明，如 `(a) 原图`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Panel {
+    pub image: ImageSource,
+    pub caption: Option<String>,
+}
+
+/// 图片的像素尺寸，只解析文件头得到
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct ImageSize {
+    pub width: u32,
+    pub height: u32,
+}
+
+/// 解析后的图片
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ImageSource {
+    /// 内联的 data URI 或写入目录后的相对路径；`size` 为输出的图片的尺寸，无法解析文件头时为 `None`
+    Src {
+        src: String,
+        size: Option<ImageSize>,
+    },
+    /// 图片缺失或超出大小上限而未输出，值为代替图片的占位提示文字
+    Unavailable(String),
+}
+
+impl ImageSource {
+    pub fn src(&self) -> Option<&str> {
+        match self {
+            ImageSource::Src { src, .. } => Some(src),
+            ImageSource::Unavailable(_) => None,
+        }
+    }
+
+    pub fn size(&self) -> Option<ImageSize> {
+        match self {
+            ImageSource::Src { size, .. } => *size,
+            ImageSource::Unavailable(_) => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TableBody {
+    /// MinerU 识别出的表格 HTML（已去掉 `<style>`）
+    Html(String),
+    /// 表格截图
+    Image(ImageSource),
+}
+
+// ==================== 页面与文档 ====================
+
+/// 节点来自的版面块，用于统计与来源注释
+#[derive(Debug, Clone, PartialEq)]
+pub struct BlockSource {
+    pub block_type: String,
+    pub bbox: BBox,
+    pub index: Option<i32>,
+}
+
+/// 页面中的一个节点；页眉、页脚、脚注与分页线没有来源块
+#[derive(Debug, Clone, PartialEq)]
+pub struct PageNode {
+    pub node: DocNode,
+    pub source: Option<BlockSource>,
+}
+
+/// 一页的节点：页眉、正文、页面脚注、页脚，最后是分页线
+#[derive(Debug, Clone)]
+pub struct PageNodes {
+    /// 输出文档中的页码（从 0 开始，合并多个输入时已加上偏移）
+    pub page_idx: usize,
+    /// 所属输入文档的序号（从 0 开始）
+    pub document: usize,
+    /// 原书印刷的页码，写入本页的目录条目
+    pub printed_page: Option<String>,
+    pub nodes: Vec<PageNode>,
+    /// 本页开头的续文已并入上一页；此时正文为空不算空白页
+    pub(crate) continued: bool,
+    pub(crate) warnings: Vec<Warning>,
+    pub(crate) figures: Vec<FigureEntry>,
+    pub(crate) stats: ConvertStats,
+}
+
+/// 分析完成、尚未写出的整篇文档
+#[derive(Debug, Clone)]
+pub struct DocumentModel {
+    pub pages: Vec<PageNodes>,
+    /// 按整篇文档统计的图片数量，写出时并入统计
+    pub(crate) image_stats: ConvertStats,
+}
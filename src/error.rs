@@ -0,0 +1,285 @@
+//! 转换错误与非致命警告
+
+use std::fmt;
+use std::io;
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::content_list::InputFormat;
+use crate::pages::PageSelection;
+use crate::stats::ConvertStats;
+use crate::types::{OutlineEntry, PageSizeSource};
+
+// ==================== 错误类型 ====================
+
+/// 导致转换无法继续的错误
+#[derive(Debug, Error)]
+pub enum ConvertError {
+    /// 输入 JSON 无法按指定（或识别出的）格式解析
+    #[error("failed to parse input as {format}: {source}")]
+    Parse {
+        format: InputFormat,
+        #[source]
+        source: serde_json::Error,
+    },
+    /// middle.json 中某一页无法解析；`source` 中的行列号相对于整个文件
+    #[error("failed to parse page {}: {source}", page_idx + 1)]
+    InvalidPage {
+        page_idx: usize,
+        #[source]
+        source: serde_json::Error,
+    },
+    /// 读取输入或读写图片失败
+    #[error("{}: {source}", path.display())]
+    Io {
+        path: PathBuf,
+        #[source]
+        source: io::Error,
+    },
+    /// 页码范围超出文档页数
+    #[error("page selection '{selection}' is out of range: valid pages are 1-{page_count}")]
+    PageOutOfRange {
+        selection: PageSelection,
+        page_count: usize,
+    },
+    /// 页面的 page_idx（多个文档时加上前面各文档的页数）超出可处理的范围
+    #[error("page_idx {page_idx} is out of range")]
+    PageIndexOutOfRange { page_idx: usize },
+    /// 页码范围没有选中任何页面
+    #[error("page selection '{selection}' does not match any page")]
+    EmptyPageSelection { selection: PageSelection },
+    /// 启用 `fail_on_missing_images` 时引用的图片不存在
+    #[error("image not found on page {}: {path}", page_idx + 1)]
+    MissingImage { page_idx: usize, path: String },
+    /// 启用 `strict_blocks` 时遇到未识别的块类型
+    #[error("unknown block type '{block_type}' on page {}", page_idx + 1)]
+    UnknownBlockType { page_idx: usize, block_type: String },
+    /// 所选风格不支持的功能，如 Obsidian 风格的拆分输出
+    #[error("{feature} cannot be used with the {flavor} flavor")]
+    UnsupportedFlavor {
+        feature: &'static str,
+        flavor: &'static str,
+    },
+    /// 所选输出格式无法内联图片，需要写入图片目录，如 LaTeX 输出
+    #[error("{format} output cannot embed images; write them to an images directory")]
+    EmbeddedImages { format: &'static str },
+    /// 无法按 `threads` 创建渲染线程池
+    #[error("failed to start render threads: {0}")]
+    ThreadPool(#[from] rayon::ThreadPoolBuildError),
+    /// 流式输出时写入目标失败
+    #[error("writing output: {0}")]
+    Write(#[source] io::Error),
+    /// 继续写出时检查点记录的页数超出文档的页数
+    #[error("checkpoint after page {pages} does not fit a document of {page_count} pages")]
+    CheckpointOutOfRange { pages: usize, page_count: usize },
+}
+
+/// 无法构成 [`crate::BBox`] 的坐标
+#[derive(Debug, Clone, PartialEq, Error)]
+pub enum BBoxError {
+    #[error("expected 4 coordinates, found {0}")]
+    Length(usize),
+    #[error("coordinate {0} is not a finite number")]
+    NotFinite(f64),
+}
+
+// ==================== 警告 ====================
+
+/// 不影响输出但值得告知用户的问题
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Warning {
+    /// image_path 指向的文件不存在
+    MissingImage { page_idx: usize, path: String },
+    /// 未识别的块类型，已按普通文本输出
+    UnknownBlockType { page_idx: usize, block_type: String },
+    /// 页面没有渲染出任何正文内容
+    EmptyPage { page_idx: usize },
+    /// 表格无法转换为管道表格或 `tabular`（嵌套表格、复杂的合并单元格等），已输出原始 HTML
+    /// （LaTeX 输出中为注释）
+    TableKeptAsHtml { page_idx: usize, reason: String },
+    /// 宽松模式下无法解析而被跳过的页面
+    SkippedPage { page_idx: usize, reason: String },
+    /// image / table 块中找不到图片或表格内容，已跳过
+    EmptyFigure { page_idx: usize, block_type: String },
+    /// 页面中有 bbox 不是四个有限的坐标，已当作零矩形
+    MalformedBBox { page_idx: usize, count: usize },
+    /// 页面正文中有未识别类型的 span，段落中不包含它们的文字；每页每种类型一条
+    UnknownSpanType {
+        page_idx: usize,
+        span_type: String,
+        count: usize,
+    },
+    /// 页面缺少 page_size 或宽高不是正数，已按 bbox 推断或按 A4 纸处理
+    InferredPageSize {
+        page_idx: usize,
+        source: PageSizeSource,
+    },
+    /// 输入中没有任何页面，只输出了最简的文档
+    EmptyDocument,
+    /// 与同页另一块重叠且文字几乎相同、已去掉的重复块；`text` 为其开头的文字
+    DuplicateBlock { page_idx: usize, text: String },
+    /// 开启 table_export 时无法转换为 CSV 的表格（嵌套表格、行列数对不齐等），未导出
+    TableNotExported { page_idx: usize, reason: String },
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Warning::MissingImage { page_idx, path } => {
+                write!(f, "page {}: image not found: {}", page_idx + 1, path)
+            }
+            Warning::UnknownBlockType {
+                page_idx,
+                block_type,
+            } => write!(
+                f,
+                "page {}: unknown block type '{}' rendered as text",
+                page_idx + 1,
+                block_type
+            ),
+            Warning::EmptyPage { page_idx } => write!(f, "page {}: no content", page_idx + 1),
+            Warning::TableKeptAsHtml { page_idx, reason } => {
+                write!(f, "page {}: table kept as HTML ({})", page_idx + 1, reason)
+            }
+            Warning::SkippedPage { page_idx, reason } => {
+                write!(
+                    f,
+                    "page {}: skipped, could not be parsed: {}",
+                    page_idx + 1,
+                    reason
+                )
+            }
+            Warning::EmptyFigure {
+                page_idx,
+                block_type,
+            } => write!(
+                f,
+                "page {}: {} block has no image or table content, skipped",
+                page_idx + 1,
+                block_type
+            ),
+            Warning::MalformedBBox { page_idx, count } => write!(
+                f,
+                "page {}: {} malformed bbox(es) treated as empty",
+                page_idx + 1,
+                count
+            ),
+            Warning::UnknownSpanType {
+                page_idx,
+                span_type,
+                count,
+            } => write!(
+                f,
+                "page {}: {} span(s) of unknown type '{}' left out of paragraphs",
+                page_idx + 1,
+                count,
+                span_type
+            ),
+            Warning::InferredPageSize { page_idx, source } => write!(
+                f,
+                "page {}: page_size missing or not positive, {}",
+                page_idx + 1,
+                match source {
+                    PageSizeSource::A4 => "assumed A4",
+                    _ => "inferred from block extents",
+                }
+            ),
+            Warning::EmptyDocument => write!(f, "input has no pages"),
+            Warning::DuplicateBlock { page_idx, text } => write!(
+                f,
+                "page {}: dropped duplicate block \"{}\"",
+                page_idx + 1,
+                text
+            ),
+            Warning::TableNotExported { page_idx, reason } => {
+                write!(
+                    f,
+                    "page {}: table not exported as CSV ({})",
+                    page_idx + 1,
+                    reason
+                )
+            }
+        }
+    }
+}
+
+impl Warning {
+    /// 全部警告类别，与 [`Warning::category`] 的取值一一对应
+    pub const CATEGORIES: &[&str] = &[
+        "missing_image",
+        "unknown_block",
+        "empty_page",
+        "table_kept_as_html",
+        "skipped_page",
+        "empty_figure",
+        "bbox_invalid",
+        "unknown_span",
+        "inferred_page_size",
+        "empty_document",
+        "duplicate_block",
+        "table_not_exported",
+    ];
+
+    /// 警告的类别，如 `missing_image`、`unknown_block`；用于转换报告与按类别判断是否失败
+    pub fn category(&self) -> &'static str {
+        match self {
+            Warning::MissingImage { .. } => "missing_image",
+            Warning::UnknownBlockType { .. } => "unknown_block",
+            Warning::EmptyPage { .. } => "empty_page",
+            Warning::TableKeptAsHtml { .. } => "table_kept_as_html",
+            Warning::SkippedPage { .. } => "skipped_page",
+            Warning::EmptyFigure { .. } => "empty_figure",
+            Warning::MalformedBBox { .. } => "bbox_invalid",
+            Warning::UnknownSpanType { .. } => "unknown_span",
+            Warning::InferredPageSize { .. } => "inferred_page_size",
+            Warning::EmptyDocument => "empty_document",
+            Warning::DuplicateBlock { .. } => "duplicate_block",
+            Warning::TableNotExported { .. } => "table_not_exported",
+        }
+    }
+
+    /// 警告所在的页码（从 0 开始）；不属于某一页的警告为 `None`
+    pub fn page_idx(&self) -> Option<usize> {
+        match self {
+            Warning::MissingImage { page_idx, .. }
+            | Warning::UnknownBlockType { page_idx, .. }
+            | Warning::EmptyPage { page_idx }
+            | Warning::TableKeptAsHtml { page_idx, .. }
+            | Warning::SkippedPage { page_idx, .. }
+            | Warning::EmptyFigure { page_idx, .. }
+            | Warning::MalformedBBox { page_idx, .. }
+            | Warning::UnknownSpanType { page_idx, .. }
+            | Warning::InferredPageSize { page_idx, .. }
+            | Warning::DuplicateBlock { page_idx, .. }
+            | Warning::TableNotExported { page_idx, .. } => Some(*page_idx),
+            Warning::EmptyDocument => None,
+        }
+    }
+}
+
+// ==================== 转换结果 ====================
+
+/// 转换得到的 Markdown、过程中收集到的警告及统计数据
+#[derive(Debug, Clone)]
+pub struct ConvertOutput {
+    /// 转换结果；[`crate::OutputFormat::Html`] 时为完整的 HTML 文档，[`crate::OutputFormat::Text`] 时为纯文本，
+    /// [`crate::OutputFormat::Latex`] 时为 LaTeX 正文（或完整文档），[`crate::OutputFormat::Json`] 时为 JSON 文档，
+    /// [`crate::OutputFormat::Asciidoc`] 时为 AsciiDoc 文档，[`crate::OutputFormat::Rst`] 时为 reStructuredText 文档，
+    /// [`crate::OutputFormat::Org`] 时为 Org 文档
+    pub markdown: String,
+    pub warnings: Vec<Warning>,
+    pub stats: ConvertStats,
+    /// 各标题及其在 `markdown` 中的位置，顺序与目录相同
+    pub outline: Vec<OutlineEntry>,
+}
+
+/// 流式写出时的转换结果：内容已写入目标，只返回警告与统计数据
+#[derive(Debug, Clone)]
+pub struct WriteOutput {
+    pub warnings: Vec<Warning>,
+    pub stats: ConvertStats,
+    /// 各标题及其在写出内容中的位置，见 [`ConvertOutput::outline`]
+    pub outline: Vec<OutlineEntry>,
+}
@@ -0,0 +1,189 @@
+//! 只导出图片：把 image / table / interline_equation 引用的图片复制到目录，并记录来源
+
+use serde::Serialize;
+use std::borrow::Cow;
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::path::{Path, PathBuf};
+
+use crate::ConvertOptions;
+use crate::analyze::child_captions;
+use crate::doc::CaptionKind;
+use crate::error::{ConvertError, Warning};
+use crate::image::{copy_image, locate_image};
+use crate::order::reading_order;
+use crate::types::{BBox, Block, LayoutJson};
+
+// ==================== 导出结果 ====================
+
+/// manifest.json 中的一条记录
+#[derive(Debug, Clone, Serialize)]
+pub struct ExtractedImage {
+    /// 复制后的文件名（相对于输出目录）
+    pub file: String,
+    /// JSON 中的原始 image_path
+    pub source: String,
+    pub page_idx: usize,
+    pub block_type: String,
+    pub bbox: BBox,
+    pub caption: Option<String>,
+}
+
+/// 导出的图片及过程中的警告
+#[derive(Debug, Clone)]
+pub struct ExtractOutput {
+    pub images: Vec<ExtractedImage>,
+    pub warnings: Vec<Warning>,
+    /// 按原样拼接的路径不存在、在后备位置找到的图片：image_path 与实际复制的文件
+    pub relocated: BTreeMap<String, PathBuf>,
+}
+
+// ==================== 图片导出 ====================
+
+/// 文件名重复时加上页码前缀 `p3-`，仍然重复再追加 `-2`、`-3`……
+fn output_name(source: &Path, page_idx: usize, used_names: &HashSet<String>) -> String {
+    let file_name = source
+        .file_name()
+        .and_then(|s| s.to_str())
+        .unwrap_or("image")
+        .to_string();
+    if !used_names.contains(&file_name) {
+        return file_name;
+    }
+
+    let prefixed = format!("p{}-{}", page_idx + 1, file_name);
+    let (stem, ext) = match prefixed.rsplit_once('.') {
+        Some((stem, ext)) => (stem.to_string(), format!(".{}", ext)),
+        None => (prefixed.clone(), String::new()),
+    };
+    let mut name = prefixed;
+    let mut n = 2;
+    while used_names.contains(&name) {
+        name = format!("{}-{}{}", stem, n, ext);
+        n += 1;
+    }
+    name
+}
+
+/// 将图片、表格截图和公式图片复制到 `out_dir`，同一源文件只复制一次
+///
+/// `options` 中的 `reorder_blocks`、`column_order`、`dehyphenate` 决定记录顺序和说明文字的提取方式。
+pub fn extract_images(
+    layout_json: &LayoutJson,
+    base_path: &Path,
+    out_dir: &Path,
+    options: &ConvertOptions,
+) -> Result<ExtractOutput, ConvertError> {
+    let mut images = Vec::new();
+    let mut warnings = Vec::new();
+    let mut copied: HashSet<PathBuf> = HashSet::new();
+    let mut used_names = HashSet::new();
+    let mut relocated = BTreeMap::new();
+
+    for page in &layout_json.pdf_info {
+        let page = if options.reorder_blocks {
+            reading_order(page, options.column_order)
+        } else {
+            Cow::Borrowed(page)
+        };
+
+        for block in &page.para_blocks {
+            let caption_type = match block.block_type.as_str() {
+                "image" => Some("image_caption"),
+                "table" => Some("table_caption"),
+                "interline_equation" => None,
+                _ => continue,
+            };
+            let caption = caption_type
+                .map(|caption_type| {
+                    child_captions(block, &[(caption_type, CaptionKind::Caption)], options)
+                        .into_iter()
+                        .map(|caption| caption.text)
+                        .collect::<Vec<_>>()
+                        .join(" ")
+                })
+                .filter(|caption| !caption.is_empty());
+
+            for image_path in block.image_paths() {
+                let (source, fallback) = locate_image(base_path, image_path);
+                if fallback {
+                    relocated.insert(image_path.to_string(), source.clone());
+                }
+                if copied.contains(&source) {
+                    continue;
+                }
+                if !source.exists() {
+                    warnings.push(Warning::MissingImage {
+                        page_idx: page.page_idx,
+                        path: image_path.to_string(),
+                    });
+                    continue;
+                }
+
+                let name = output_name(&source, page.page_idx, &used_names);
+                copy_image(&source, &out_dir.join(&name)).map_err(|err| ConvertError::Io {
+                    path: source.clone(),
+                    source: err,
+                })?;
+                used_names.insert(name.clone());
+                copied.insert(source);
+
+                images.push(ExtractedImage {
+                    file: name,
+                    source: image_path.to_string(),
+                    page_idx: page.page_idx,
+                    block_type: block.block_type.clone(),
+                    bbox: block.bbox,
+                    caption: caption.clone(),
+                });
+            }
+        }
+    }
+
+    Ok(ExtractOutput {
+        images,
+        warnings,
+        relocated,
+    })
+}
+
+/// 正文块与弃用块引用的图片实际对应的文件（含后备位置上找到的），去重并排序；
+/// 不存在的图片给出原样拼接的路径。可用来判断之前的转换结果是否仍然有效
+pub fn referenced_images(layout_json: &LayoutJson, base_path: &Path) -> Vec<PathBuf> {
+    let files: BTreeSet<PathBuf> = layout_json
+        .pdf_info
+        .iter()
+        .flat_map(|page| page.para_blocks.iter().chain(&page.discarded_blocks))
+        .flat_map(Block::image_paths)
+        .map(|image_path| locate_image(base_path, image_path).0)
+        .collect();
+    files.into_iter().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{TempDir, image_block, page_layout};
+    use serde_json::json;
+
+    #[test]
+    fn extracting_into_the_source_directory_keeps_the_images() {
+        let tmp = TempDir::new();
+        let source = tmp.write("images/a.png", b"image data");
+        let layout = page_layout(json!([image_block(
+            [50.0, 50.0, 550.0, 200.0],
+            "images/a.png"
+        )]));
+
+        let extracted = extract_images(
+            &layout,
+            tmp.path(),
+            &tmp.path().join("images"),
+            &ConvertOptions::default(),
+        )
+        .unwrap();
+
+        assert_eq!(std::fs::read(&source).unwrap(), b"image data");
+        assert_eq!(extracted.images.len(), 1);
+        assert_eq!(extracted.images[0].file, "a.png");
+    }
+}
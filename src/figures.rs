@@ -0,0 +1,375 @@
+//! 图表编号：为图注、表注补上 "Figure N." / "Table N." 前缀，并生成图表目录
+
+use serde::{Deserialize, Serialize};
+
+use crate::analyze::numbered_caption;
+use crate::text::{AnchorFiles, anchor_href, escape_html, obsidian_block_link, toc_link_text};
+use crate::types::{Block, PageInfo, Span};
+use crate::{ConvertOptions, Flavor, OutputFormat};
+
+// ==================== 编号 ====================
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) enum FigureKind {
+    Figure,
+    Table,
+}
+
+impl FigureKind {
+    fn label(self) -> &'static str {
+        match self {
+            FigureKind::Figure => "Figure",
+            FigureKind::Table => "Table",
+        }
+    }
+
+    fn anchor_prefix(self) -> &'static str {
+        match self {
+            FigureKind::Figure => "fig",
+            FigureKind::Table => "tab",
+        }
+    }
+
+    /// 图注开头已有的编号写法，按长度从长到短排列
+    fn caption_prefixes(self) -> &'static [&'static str] {
+        match self {
+            FigureKind::Figure => &["figure", "fig.", "fig", "图"],
+            FigureKind::Table => &["table", "tab.", "tab", "表"],
+        }
+    }
+}
+
+/// 已经编到的图、表序号；各页从前面所有页面编到的序号开始计数
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct FigureCounters {
+    pub figures: usize,
+    pub tables: usize,
+}
+
+impl FigureCounters {
+    /// 为一个图或表分配序号：说明文字已带编号且大于已编到的序号时沿用原文的编号，
+    /// 之后的图表接着它编号；没有编号、编号重复或倒退（如续表）的取下一个序号
+    fn next(&mut self, kind: FigureKind, caption: &str) -> usize {
+        let counter = match kind {
+            FigureKind::Figure => &mut self.figures,
+            FigureKind::Table => &mut self.tables,
+        };
+        *counter = match caption_number(caption, kind) {
+            Some(number) if number > *counter => number,
+            _ => *counter + 1,
+        };
+        *counter
+    }
+}
+
+/// 图表目录中的一项
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct FigureEntry {
+    pub kind: FigureKind,
+    pub anchor_id: String,
+    /// 加上编号后的说明文字
+    pub caption: String,
+    /// 页码（从 1 开始）
+    pub page_idx: usize,
+}
+
+/// 说明文字开头的编号写法（"Figure"、"图" 等）之后的文字
+fn after_number_prefix(caption: &str, kind: FigureKind) -> Option<&str> {
+    kind.caption_prefixes().iter().find_map(|prefix| {
+        caption
+            .get(..prefix.len())
+            .filter(|head| head.eq_ignore_ascii_case(prefix))
+            .map(|_| caption[prefix.len()..].trim_start())
+    })
+}
+
+fn starts_number(text: &str) -> bool {
+    text.starts_with(|c: char| c.is_ascii_digit() || CHINESE_DIGITS.contains(c))
+}
+
+/// 中文数字：`十` 之外依次为 1 到 9
+const CHINESE_DIGITS: &str = "一二三四五六七八九十";
+
+/// 说明文字是否已带编号，如 "Figure 3"、"Fig. 2b"、"图 3"、"表三"、"Figure S2"
+fn has_number_prefix(caption: &str, kind: FigureKind) -> bool {
+    after_number_prefix(caption, kind)
+        .is_some_and(|rest| starts_number(rest.strip_prefix(['S', 's']).unwrap_or(rest)))
+}
+
+/// 说明文字中已有的编号，如 "Figure 3" 与 "图 3" 为 3、"表十二" 为 12；
+/// 附录中的 "Figure S2" 自成一套编号，不算在内
+fn caption_number(caption: &str, kind: FigureKind) -> Option<usize> {
+    let rest = after_number_prefix(caption, kind)?;
+    let digits: String = rest.chars().take_while(char::is_ascii_digit).collect();
+    if !digits.is_empty() {
+        return digits.parse().ok();
+    }
+    let numerals: Vec<usize> = rest
+        .chars()
+        .map_while(|c| CHINESE_DIGITS.chars().position(|digit| digit == c))
+        .map(|i| i + 1)
+        .collect();
+    // `十` 为 10：`十二` 为 12，`二十` 为 20，`二十三` 为 23
+    match numerals.as_slice() {
+        [] => None,
+        [digit] => Some(*digit),
+        [10, unit] if *unit < 10 => Some(10 + unit),
+        [tens, 10] if *tens < 10 => Some(tens * 10),
+        [tens, 10, unit] if *tens < 10 && *unit < 10 => Some(tens * 10 + unit),
+        _ => None,
+    }
+}
+
+/// 为一个图或表分配编号与锚点；已带编号的说明文字保持原样，锚点沿用原文的编号
+pub(crate) fn number_caption(
+    caption: &str,
+    kind: FigureKind,
+    counters: &mut FigureCounters,
+    page_idx: usize,
+) -> FigureEntry {
+    let number = counters.next(kind, caption);
+    let caption = if has_number_prefix(caption, kind) {
+        caption.to_string()
+    } else {
+        format!("{} {}. {}", kind.label(), number, caption)
+    };
+    FigureEntry {
+        kind,
+        anchor_id: format!("{}-{}", kind.anchor_prefix(), number),
+        caption,
+        page_idx: page_idx + 1,
+    }
+}
+
+// ==================== 预先计数 ====================
+
+/// 图表主体中的 span：`body_type`（`image_body` / `table_body`）子块各行的 span；
+/// 没有这种子块时（如 VLM 后端）取块自身各行的 span
+pub(crate) fn body_spans<'a>(block: &'a Block, body_type: &str) -> Vec<&'a Span> {
+    let bodies: Vec<&Block> = block
+        .blocks
+        .iter()
+        .flatten()
+        .filter(|sub_block| sub_block.block_type == body_type)
+        .collect();
+    let lines = if bodies.is_empty() {
+        block.lines.iter().flatten().collect::<Vec<_>>()
+    } else {
+        bodies
+            .iter()
+            .flat_map(|body| body.lines.iter().flatten())
+            .collect()
+    };
+    lines.into_iter().flat_map(|line| &line.spans).collect()
+}
+
+pub(crate) fn has_image_span(block: &Block) -> bool {
+    body_spans(block, "image_body")
+        .into_iter()
+        .any(|span| span.span_type == "image" && span.image_path.is_some())
+}
+
+pub(crate) fn has_table_span(block: &Block, options: &ConvertOptions) -> bool {
+    body_spans(block, "table_body").into_iter().any(|span| {
+        span.span_type == "table"
+            && (span.image_path.is_some()
+                || (!options.tables_as_image
+                    && span
+                        .content
+                        .as_deref()
+                        .is_some_and(|content| !content.trim().is_empty())))
+    })
+}
+
+/// 把页面中会被编号的图、表计入 `counters`：有图注且会输出内容的 image / table 块，
+/// 按 [`number_caption`] 的规则沿用说明文字中已有的编号
+///
+/// 各页并行渲染前先按顺序累加，使每页的编号从正确的位置开始。
+pub(crate) fn count_numbered(
+    page: &PageInfo,
+    options: &ConvertOptions,
+    counters: &mut FigureCounters,
+) {
+    for block in &page.para_blocks {
+        let kind = match block.block_type.as_str() {
+            "image" if has_image_span(block) => FigureKind::Figure,
+            "table" if has_table_span(block, options) => FigureKind::Table,
+            _ => continue,
+        };
+        if let Some(caption) = numbered_caption(block, kind, options) {
+            counters.next(kind, &caption);
+        }
+    }
+}
+
+// ==================== 图表目录 ====================
+
+fn generate_list(
+    title: &str,
+    id: &str,
+    entries: &[&FigureEntry],
+    options: &ConvertOptions,
+    files: &AnchorFiles,
+) -> String {
+    if entries.is_empty() {
+        return String::new();
+    }
+    match options.format {
+        OutputFormat::Markdown
+        | OutputFormat::Text
+        | OutputFormat::Latex
+        | OutputFormat::Json
+        | OutputFormat::Asciidoc
+        | OutputFormat::Rst
+        | OutputFormat::Org => {
+            let mut list = format!("**{}**\n\n", title);
+            for entry in entries {
+                // Obsidian 风格链接到说明文字末尾的块标识
+                let link = match options.flavor {
+                    Flavor::Obsidian => obsidian_block_link(&entry.anchor_id, &entry.caption),
+                    Flavor::Styled | Flavor::Gfm => format!(
+                        "[{}]({})",
+                        toc_link_text(&entry.caption, options),
+                        anchor_href(&entry.anchor_id, files)
+                    ),
+                };
+                list.push_str(&format!("- {} · 第 {} 页\n", link, entry.page_idx));
+            }
+            list.push('\n');
+            list
+        }
+        OutputFormat::Html => {
+            let mut list = format!(
+                "<nav id=\"{}\">\n<p><strong>{}</strong></p>\n<ul>\n",
+                id, title
+            );
+            for entry in entries {
+                list.push_str(&format!(
+                    "<li><a href=\"{}\">{}</a> · 第 {} 页</li>\n",
+                    anchor_href(&entry.anchor_id, files),
+                    escape_html(&entry.caption),
+                    entry.page_idx
+                ));
+            }
+            list.push_str("</ul>\n</nav>\n\n");
+            list
+        }
+    }
+}
+
+/// 目录之后的 "List of Figures" 与 "List of Tables"；没有条目的一节不输出
+pub(crate) fn generate_figure_lists(
+    entries: &[FigureEntry],
+    options: &ConvertOptions,
+    files: &AnchorFiles,
+) -> String {
+    let figures: Vec<&FigureEntry> = entries
+        .iter()
+        .filter(|entry| entry.kind == FigureKind::Figure)
+        .collect();
+    let tables: Vec<&FigureEntry> = entries
+        .iter()
+        .filter(|entry| entry.kind == FigureKind::Table)
+        .collect();
+    let mut lists = generate_list(
+        "List of Figures",
+        "list-of-figures",
+        &figures,
+        options,
+        files,
+    );
+    lists.push_str(&generate_list(
+        "List of Tables",
+        "list-of-tables",
+        &tables,
+        options,
+        files,
+    ));
+    lists
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::convert_layout_to_markdown;
+    use crate::testing::{bare_options, image_block, layout, text_block};
+    use serde_json::{Value, json};
+    use std::path::Path;
+
+    fn figure(top: f64, caption: &str) -> Value {
+        let bbox = [100.0, top, 500.0, top + 80.0];
+        let mut figure = image_block(bbox, "missing.png");
+        figure["blocks"].as_array_mut().unwrap().push(text_block(
+            "image_caption",
+            [100.0, top + 85.0, 500.0, top + 100.0],
+            caption,
+        ));
+        figure
+    }
+
+    fn table(top: f64, caption: &str) -> Value {
+        let bbox = [100.0, top, 500.0, top + 80.0];
+        json!({"type": "table", "bbox": bbox, "blocks": [
+            text_block("table_caption", [100.0, top - 20.0, 500.0, top - 5.0], caption),
+            {"type": "table_body", "bbox": bbox, "lines": [{"bbox": bbox, "spans": [
+                {"type": "table", "bbox": bbox, "content": "<table><tr><td>1</td></tr></table>"}
+            ]}]}
+        ]})
+    }
+
+    #[test]
+    fn numbers_already_in_captions_are_parsed() {
+        let figure = FigureKind::Figure;
+        assert_eq!(caption_number("Figure 3. Setup", figure), Some(3));
+        assert_eq!(caption_number("Fig. 12b", figure), Some(12));
+        assert_eq!(caption_number("图 3 示意图", figure), Some(3));
+        assert_eq!(caption_number("图十二", figure), Some(12));
+        assert_eq!(caption_number("图二十三", figure), Some(23));
+        assert_eq!(caption_number("Figure S2", figure), None);
+        assert_eq!(caption_number("A plot", figure), None);
+        assert_eq!(caption_number("表 4", FigureKind::Table), Some(4));
+        assert!(has_number_prefix("Figure S2", figure));
+    }
+
+    #[test]
+    fn anchors_follow_the_document_numbering() {
+        let layout = layout(json!({"pdf_info": [
+            {"page_idx": 0, "page_size": [600, 800], "para_blocks": [
+                figure(50.0, "图 3 系统结构"),
+                figure(250.0, "A plot"),
+                table(500.0, "Table 2. Results"),
+                table(650.0, "Table 2 (continued)"),
+            ]},
+            {"page_idx": 1, "page_size": [600, 800], "para_blocks": [
+                figure(50.0, "Another plot"),
+                table(300.0, "Totals"),
+            ]}
+        ]}));
+        let options = ConvertOptions {
+            number_figures: true,
+            list_of_figures: true,
+            flavor: Flavor::Gfm,
+            ..bare_options()
+        };
+        let output = convert_layout_to_markdown(&layout, Path::new("."), &options).unwrap();
+        let entries: Vec<&str> = output
+            .markdown
+            .lines()
+            .filter(|line| {
+                line.starts_with("- [") && (line.contains("](#fig-") || line.contains("](#tab-"))
+            })
+            .collect();
+        // 第 2 页从第 1 页编到的序号之后开始；续表的编号重复，取下一个序号作锚点
+        assert_eq!(
+            entries,
+            [
+                "- [图 3 系统结构](#fig-3) · 第 1 页",
+                "- [Figure 4. A plot](#fig-4) · 第 1 页",
+                "- [Figure 5. Another plot](#fig-5) · 第 2 页",
+                "- [Table 2. Results](#tab-2) · 第 1 页",
+                "- [Table 2 (continued)](#tab-3) · 第 1 页",
+                "- [Table 4. Totals](#tab-4) · 第 2 页",
+            ]
+        );
+    }
+}
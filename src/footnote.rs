@@ -0,0 +1,340 @@
+//! Markdown 脚注：把页面脚注转换为 `[^label]: text` 定义，并改写正文中的引用标记；
+//! AsciiDoc 输出中改为就地的 `footnote:[text]`，reStructuredText 输出中为 `[#label]_` 引用与 `.. [#label]` 定义，
+//! Org 输出中为 `[fn:label]` 引用与定义
+
+use std::ops::Range;
+
+use crate::asciidoc::macro_text;
+use crate::org::escape_org;
+use crate::rst::{self, escape_rst};
+use crate::text::{escape_html, escape_markdown};
+
+// ==================== 脚注编号 ====================
+
+const SUPERSCRIPT_DIGITS: [char; 10] = ['⁰', '¹', '²', '³', '⁴', '⁵', '⁶', '⁷', '⁸', '⁹'];
+
+fn superscript_value(c: char) -> Option<u32> {
+    SUPERSCRIPT_DIGITS
+        .iter()
+        .position(|&d| d == c)
+        .map(|i| i as u32)
+}
+
+fn to_superscript(n: u32) -> String {
+    n.to_string()
+        .chars()
+        .filter_map(|c| c.to_digit(10))
+        .map(|d| SUPERSCRIPT_DIGITS[d as usize])
+        .collect()
+}
+
+/// 解析脚注文本开头的编号（`[3]`、`3`、`³`），返回编号与去掉编号后的正文
+fn parse_leading_marker(text: &str) -> (Option<u32>, &str) {
+    let (digits, rest) = if let Some(inner) = text.strip_prefix('[') {
+        match inner.find(']') {
+            Some(end) if end > 0 && inner[..end].chars().all(|c| c.is_ascii_digit()) => {
+                (inner[..end].to_string(), &inner[end + 1..])
+            }
+            _ => return (None, text),
+        }
+    } else if text.starts_with(|c: char| superscript_value(c).is_some()) {
+        let end = text
+            .char_indices()
+            .find(|(_, c)| superscript_value(*c).is_none())
+            .map_or(text.len(), |(i, _)| i);
+        let digits = text[..end]
+            .chars()
+            .filter_map(superscript_value)
+            .map(|d| char::from_digit(d, 10).unwrap_or('0'))
+            .collect();
+        (digits, &text[end..])
+    } else {
+        let end = text
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(text.len());
+        // 数字后必须跟空白或标点，避免把 "2020年" 之类的正文当作编号
+        let next = text[end..].chars().next();
+        if end == 0 || next.is_some_and(|c| c.is_alphanumeric()) {
+            return (None, text);
+        }
+        (text[..end].to_string(), &text[end..])
+    };
+
+    if digits.is_empty() || digits.len() > 3 {
+        return (None, text);
+    }
+    let rest = rest.trim_start_matches(['.', ')', ' ', '\t']);
+    (digits.parse().ok(), rest)
+}
+
+// ==================== 页面脚注 ====================
+
+pub(crate) struct PageFootnote {
+    pub label: String,
+    pub number: Option<u32>,
+    pub text: String,
+}
+
+/// 为本页脚注分配 `p{页码}-{编号}` 标签；没有编号的脚注按出现顺序编号（空白的脚注也占一个序号）
+pub(crate) fn collect_page_footnotes(notes: &[&str], page_idx: usize) -> Vec<PageFootnote> {
+    let mut footnotes: Vec<PageFootnote> = Vec::new();
+
+    for (i, note) in notes.iter().enumerate() {
+        let (number, text) = parse_leading_marker(note.trim());
+        if text.trim().is_empty() {
+            continue;
+        }
+
+        let mut key = number.unwrap_or(i as u32 + 1).to_string();
+        while footnotes
+            .iter()
+            .any(|f| f.label.ends_with(&format!("-{}", key)))
+        {
+            key.push('a');
+        }
+        footnotes.push(PageFootnote {
+            label: format!("p{}-{}", page_idx + 1, key),
+            number,
+            text: text.trim().to_string(),
+        });
+    }
+
+    footnotes
+}
+
+/// 正文中第一个完整的编号标记的位置
+fn find_marker(content: &str, marker: &str) -> Option<usize> {
+    let mut search = 0;
+    while let Some(pos) = content[search..].find(marker).map(|i| search + i) {
+        let end = pos + marker.len();
+        // 上标数字必须是完整的一段（¹ 不能匹配 ¹² 的开头）
+        let superscript_run = |c: char| superscript_value(c).is_some();
+        let joined = marker.starts_with(superscript_run)
+            && (content[..pos].ends_with(superscript_run)
+                || content[end..].starts_with(superscript_run));
+        if !joined {
+            return Some(pos);
+        }
+        search = end;
+    }
+    None
+}
+
+/// 编号 `n` 在正文中可能的写法，按优先级排列：`sup` span 写出的上标（HTML 与 Markdown 的 `<sup>`、
+/// AsciiDoc 的 `^n^`、reStructuredText 的 `:sup:` 角色、Org 的 `^{n}`，内容可带方括号），其次是 `[n]` 与上标数字。
+/// 上标整体替换，不留下只包着引用的 `<sup>`
+fn marker_forms(n: u32) -> [String; 10] {
+    [
+        format!("<sup>{}</sup>", n),
+        format!("<sup>[{}]</sup>", n),
+        format!("^{}^", n),
+        format!("^[{}]^", n),
+        format!(":sup:`{}`", n),
+        format!(":sup:`[{}]`", n),
+        format!("^{{{}}}", n),
+        format!("^{{[{}]}}", n),
+        format!("[{}]", n),
+        to_superscript(n),
+    ]
+}
+
+/// 正文中编号 `n` 首次出现的位置，按 [`marker_forms`] 的优先级查找
+fn find_numbered_marker(content: &str, n: u32) -> Option<Range<usize>> {
+    marker_forms(n)
+        .iter()
+        .find_map(|marker| find_marker(content, marker).map(|pos| pos..pos + marker.len()))
+}
+
+fn replace_first_marker(content: &mut String, n: u32, replacement: &str) -> bool {
+    let Some(range) = find_numbered_marker(content, n) else {
+        return false;
+    };
+    content.replace_range(range, replacement);
+    true
+}
+
+/// 把正文中的上标、`[n]` / 上标数字改写为对应脚注的引用；每个脚注只改写首次出现的位置。
+/// 没有对应脚注的上标保持原样
+pub(crate) fn link_footnote_markers(content: &mut String, footnotes: &[PageFootnote]) {
+    for footnote in footnotes {
+        if let Some(n) = footnote.number {
+            replace_first_marker(content, n, &format!("[^{}]", footnote.label));
+        }
+    }
+}
+
+/// 输出脚注定义；正文中没有匹配到引用的脚注同样输出，避免内容丢失
+pub(crate) fn render_markdown_footnotes(footnotes: &[PageFootnote], escape: bool) -> String {
+    if footnotes.is_empty() {
+        return String::new();
+    }
+
+    let mut markdown: String = footnotes
+        .iter()
+        .map(|f| {
+            let text = escape_html(&f.text);
+            let text = if escape {
+                escape_markdown(&text, false)
+            } else {
+                text
+            };
+            format!("[^{}]: {}\n", f.label, text)
+        })
+        .collect();
+    markdown.push('\n');
+    markdown
+}
+
+// ==================== AsciiDoc 脚注 ====================
+
+/// AsciiDoc 脚注：正文中每个脚注首次出现的上标、`[n]` / 上标数字改写为 `footnote:[text]`；
+/// 正文中没有匹配到引用的脚注集中为正文之后的一个段落，避免内容丢失
+pub(crate) fn render_asciidoc_footnotes(
+    content: &mut String,
+    footnotes: &[PageFootnote],
+) -> String {
+    let mut unlinked = Vec::new();
+    for footnote in footnotes {
+        let macro_call = format!("footnote:[{}]", macro_text(&footnote.text));
+        let linked = footnote
+            .number
+            .is_some_and(|n| replace_first_marker(content, n, &macro_call));
+        if !linked {
+            unlinked.push(macro_call);
+        }
+    }
+    if unlinked.is_empty() {
+        String::new()
+    } else {
+        format!("{}\n\n", unlinked.join(" "))
+    }
+}
+
+// ==================== reStructuredText 脚注 ====================
+
+/// reStructuredText 脚注：正文中每个脚注首次出现的上标、`[n]` / 上标数字改写为自动编号的引用 `[#label]_`，
+/// 定义 `.. [#label] text` 集中在正文之后；没有匹配到引用的脚注同样输出定义，避免内容丢失
+pub(crate) fn render_rst_footnotes(content: &mut String, footnotes: &[PageFootnote]) -> String {
+    if footnotes.is_empty() {
+        return String::new();
+    }
+    let mut definitions = String::new();
+    for footnote in footnotes {
+        let reference = format!("[#{}]_", footnote.label);
+        if let Some(range) = footnote
+            .number
+            .and_then(|n| find_numbered_marker(content, n))
+        {
+            rst::replace_with_markup(content, range, &reference);
+        }
+        definitions.push_str(&format!(
+            ".. [#{}] {}\n",
+            footnote.label,
+            escape_rst(&footnote.text, true).replace('\n', "\n   ")
+        ));
+    }
+    definitions.push('\n');
+    definitions
+}
+
+// ==================== Org 脚注 ====================
+
+/// Org 脚注：正文中每个脚注首次出现的上标、`[n]` / 上标数字改写为引用 `[fn:label]`，
+/// 定义 `[fn:label] text` 集中在正文之后；没有匹配到引用的脚注同样输出定义，避免内容丢失。
+/// 定义在下一个定义、标题或连续两个空行处结束，末尾多留一个空行，分页线不会并入最后一条定义
+pub(crate) fn render_org_footnotes(content: &mut String, footnotes: &[PageFootnote]) -> String {
+    if footnotes.is_empty() {
+        return String::new();
+    }
+    let mut definitions = String::new();
+    for footnote in footnotes {
+        let reference = format!("[fn:{}]", footnote.label);
+        if let Some(n) = footnote.number {
+            replace_first_marker(content, n, &reference);
+        }
+        definitions.push_str(&format!(
+            "{} {}\n",
+            reference,
+            escape_org(&footnote.text, true)
+        ));
+    }
+    definitions.push_str("\n\n");
+    definitions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::bare_options;
+    use crate::{ConvertOptions, FootnoteStyle, InputFormat, OutputFormat, parse_layout};
+    use std::path::Path;
+
+    /// tests/fixtures/footnotes.json：正文中三个 `sup` 标记，页面脚注只有 1 和 2
+    fn footnotes_output(options: &ConvertOptions) -> String {
+        let layout = parse_layout(
+            include_str!("../tests/fixtures/footnotes.json"),
+            InputFormat::Auto,
+        )
+        .unwrap();
+        crate::convert_layout_to_markdown(&layout, Path::new("."), options)
+            .unwrap()
+            .markdown
+    }
+
+    #[test]
+    fn leading_markers_are_parsed_in_every_form() {
+        assert_eq!(parse_leading_marker("[3] Note"), (Some(3), "Note"));
+        assert_eq!(parse_leading_marker("12. Note"), (Some(12), "Note"));
+        assert_eq!(parse_leading_marker("¹² Note"), (Some(12), "Note"));
+        // 年份等正文中的数字不是编号
+        assert_eq!(parse_leading_marker("2020年的数据"), (None, "2020年的数据"));
+        assert_eq!(parse_leading_marker("1234 items"), (None, "1234 items"));
+    }
+
+    #[test]
+    fn footnotes_get_page_labels_and_unique_keys() {
+        let footnotes = collect_page_footnotes(&["1 First", "1 Again", "Unnumbered", "  "], 4);
+        let labels: Vec<&str> = footnotes.iter().map(|f| f.label.as_str()).collect();
+        assert_eq!(labels, ["p5-1", "p5-1a", "p5-3"]);
+        assert_eq!(footnotes[1].text, "Again");
+    }
+
+    #[test]
+    fn each_footnote_links_its_first_marker() {
+        let footnotes = collect_page_footnotes(&["1 One", "2 Two"], 0);
+        let mut content = "a¹² b¹ c<sup>2</sup> d[2] e<sup>3</sup>".to_string();
+        link_footnote_markers(&mut content, &footnotes);
+        assert_eq!(content, "a¹² b[^p1-1] c[^p1-2] d[2] e<sup>3</sup>");
+    }
+
+    #[test]
+    fn sup_spans_link_to_matching_footnotes_and_stay_superscript_otherwise() {
+        let div = footnotes_output(&bare_options());
+        assert!(
+            div.contains("recurrence<sup>1</sup> and scale with data<sup>2</sup>, though not always<sup>3</sup>."),
+            "{}",
+            div
+        );
+
+        let options = ConvertOptions {
+            footnote_style: FootnoteStyle::Markdown,
+            ..bare_options()
+        };
+        assert_eq!(
+            footnotes_output(&options).trim(),
+            "Transformers replaced recurrence[^p1-1] and scale with data[^p1-2], \
+             though not always<sup>3</sup>.\n\n\
+             [^p1-1]: Vaswani et al., 2017.\n\
+             [^p1-2]: Kaplan et al., 2020."
+        );
+
+        let options = ConvertOptions {
+            format: OutputFormat::Asciidoc,
+            ..bare_options()
+        };
+        assert!(footnotes_output(&options).contains(
+            "recurrencefootnote:[Vaswani et al., 2017.] and scale with \
+             datafootnote:[Kaplan et al., 2020.], though not always^3^."
+        ));
+    }
+}
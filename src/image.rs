@@ -0,0 +1,994 @@
+//! 图片解析：以 Base64 内联，或复制到输出目录并以相对路径引用
+
+use ::image::codecs::jpeg::JpegEncoder;
+use ::image::codecs::png::{CompressionType, FilterType as PngFilter, PngEncoder};
+use ::image::imageops::FilterType;
+use ::image::{DynamicImage, ImageReader, ImageResult};
+use base64::{Engine as _, engine::general_purpose};
+use std::collections::hash_map::{DefaultHasher, Entry};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::io::{self, Cursor};
+use std::path::{Component, Path, PathBuf};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+use crate::ConvertOptions;
+use crate::doc::ImageSize;
+use crate::error::ConvertError;
+use crate::stats::ConvertStats;
+use crate::types::{Block, PageInfo};
+
+// ==================== 工具函数 ====================
+
+/// 按扩展名确定 MIME 类型；没有扩展名或扩展名未知时按文件头识别，仍无法识别时按 JPEG 处理
+fn mime_type(full_path: &Path, data: &[u8]) -> &'static str {
+    let ext = full_path
+        .extension()
+        .and_then(|s| s.to_str())
+        .map(str::to_lowercase);
+    match ext.as_deref() {
+        Some("png") => "image/png",
+        Some("jpg" | "jpeg" | "jpe" | "jfif") => "image/jpeg",
+        Some("gif") => "image/gif",
+        Some("webp") => "image/webp",
+        Some("svg") => "image/svg+xml",
+        Some("bmp") => "image/bmp",
+        Some("tif" | "tiff") => "image/tiff",
+        Some("avif") => "image/avif",
+        _ => sniff_mime_type(data).unwrap_or("image/jpeg"),
+    }
+}
+
+/// 根据文件开头的特征字节识别图片格式
+fn sniff_mime_type(data: &[u8]) -> Option<&'static str> {
+    if data.starts_with(b"\x89PNG\r\n\x1a\n") {
+        Some("image/png")
+    } else if data.starts_with(&[0xFF, 0xD8, 0xFF]) {
+        Some("image/jpeg")
+    } else if data.starts_with(b"GIF87a") || data.starts_with(b"GIF89a") {
+        Some("image/gif")
+    } else if data.starts_with(b"RIFF") && data.get(8..12) == Some(b"WEBP") {
+        Some("image/webp")
+    } else if data.starts_with(b"BM") {
+        Some("image/bmp")
+    } else if data.starts_with(b"II*\0") || data.starts_with(b"MM\0*") {
+        Some("image/tiff")
+    } else if matches!(data.get(4..12), Some(b"ftypavif" | b"ftypavis")) {
+        Some("image/avif")
+    } else if is_svg(data) {
+        Some("image/svg+xml")
+    } else {
+        None
+    }
+}
+
+/// 文件开头（可带 XML 声明、注释或 DOCTYPE）出现 `<svg` 标签
+fn is_svg(data: &[u8]) -> bool {
+    let head = String::from_utf8_lossy(&data[..data.len().min(1024)]);
+    let head = head.trim_start_matches('\u{feff}').trim_start();
+    head.starts_with("<svg")
+        || (head.starts_with('<') && !head.starts_with("<html") && head.contains("<svg"))
+}
+
+/// image_path 指向的文件：先按原样拼在 `base_path` 之后；不存在时依次尝试反斜杠改为 `/` 的路径、
+/// `base_path` 下的 `images/<文件名>` 与上一级目录中的 `images/<文件名>`（JSON 位于 `auto/` 之类的子目录时）。
+/// 返回找到的文件，以及它是否来自上述后备位置；都不存在时返回原样拼接的路径
+pub(crate) fn locate_image(base_path: &Path, image_path: &str) -> (PathBuf, bool) {
+    let direct = base_path.join(image_path);
+    if direct.exists() {
+        return (direct, false);
+    }
+    let normalized = image_path.replace('\\', "/");
+    let mut candidates = vec![base_path.join(&normalized)];
+    if let Some(name) = Path::new(&normalized).file_name() {
+        candidates.push(base_path.join("images").join(name));
+        candidates.push(base_path.join("..").join("images").join(name));
+    }
+    candidates
+        .into_iter()
+        .find(|candidate| *candidate != direct && candidate.exists())
+        .map_or((direct, false), |found| (found, true))
+}
+
+/// 把图片复制到 `dest`，返回文件的字节数。`dest` 与 `source` 是同一个文件时
+/// （如图片目录就是 MinerU 写出图片的目录）不复制，直接原地使用：`fs::copy` 会先清空目标文件
+pub(crate) fn copy_image(source: &Path, dest: &Path) -> io::Result<u64> {
+    if same_file(source, dest) {
+        return Ok(fs::metadata(source)?.len());
+    }
+    fs::copy(source, dest)
+}
+
+fn same_file(a: &Path, b: &Path) -> bool {
+    matches!((a.canonicalize(), b.canonicalize()), (Ok(a), Ok(b)) if a == b)
+}
+
+pub(crate) fn image_to_base64(full_path: &Path, data: &[u8]) -> String {
+    data_uri(mime_type(full_path, data), data)
+}
+
+fn data_uri(mime: &str, data: &[u8]) -> String {
+    format!(
+        "data:{};base64,{}",
+        mime,
+        general_purpose::STANDARD.encode(data)
+    )
+}
+
+/// 便于阅读的文件大小，如 `40.2 MB`、`812 KB`
+pub(crate) fn format_size(bytes: usize) -> String {
+    const UNITS: [&str; 3] = ["KB", "MB", "GB"];
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit + 1 < UNITS.len() {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{:.0} {}", size, UNITS[unit])
+    } else {
+        format!("{:.1} {}", size, UNITS[unit])
+    }
+}
+
+/// 内联为 data URI 的 SVG 还原为可直接放进文档的 `<svg>` 标记
+///
+/// 去掉 XML 声明与 DOCTYPE，并删除空行，避免 Markdown 在空行处提前结束 HTML 块。
+/// 其他图片（包括写入目录的 SVG）返回 `None`。
+pub(crate) fn inline_svg_markup(src: &str) -> Option<String> {
+    let encoded = src.strip_prefix("data:image/svg+xml;base64,")?;
+    let data = general_purpose::STANDARD.decode(encoded).ok()?;
+    let svg = String::from_utf8(data).ok()?;
+    let start = svg.find("<svg")?;
+    let markup: Vec<&str> = svg[start..]
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .collect();
+    Some(markup.join("\n"))
+}
+
+/// 只解析文件头得到的像素尺寸，不解码图像数据；格式无法识别（如 SVG）或文件头损坏时为 `None`
+fn image_size(data: &[u8]) -> Option<ImageSize> {
+    size_of(
+        ImageReader::new(Cursor::new(data))
+            .with_guessed_format()
+            .ok()?,
+    )
+}
+
+/// 与 [`image_size`] 相同，但直接从文件读取文件头，用于不读入整个文件的复制
+fn file_image_size(path: &Path) -> Option<ImageSize> {
+    size_of(ImageReader::open(path).ok()?.with_guessed_format().ok()?)
+}
+
+fn size_of<R: io::BufRead + io::Seek>(reader: ImageReader<R>) -> Option<ImageSize> {
+    let (width, height) = reader.into_dimensions().ok()?;
+    Some(ImageSize { width, height })
+}
+
+/// 图片内容的指纹（长度与 64 位哈希）；MinerU 有时把同一张图提取为不同文件名，去重只看内容
+type ContentKey = (usize, u64);
+
+fn content_key(data: &[u8]) -> ContentKey {
+    let mut hasher = DefaultHasher::new();
+    data.hash(&mut hasher);
+    (data.len(), hasher.finish())
+}
+
+// ==================== 大小上限 ====================
+
+/// 内联图片超出 [`ConvertOptions::max_image_bytes`] 时的处理方式
+#[derive(Debug, Clone, Default)]
+pub enum OversizePolicy {
+    /// 重新编码并按需缩小最长边，直到不超过上限：有透明通道时为 PNG，否则为 JPEG（默认）。
+    /// 无法解码的图片（如 SVG）按 `Skip` 处理
+    #[default]
+    Downscale,
+    /// 写入 `dir` 并以 `link_prefix/文件名` 的相对路径引用，不再内联
+    Link { dir: PathBuf, link_prefix: String },
+    /// 以注明原始大小的占位提示代替
+    Skip,
+}
+
+/// 缩小时最长边的下限；缩到这一尺寸仍超出上限时使用这一尺寸的结果
+const MIN_DOWNSCALED_SIDE: u32 = 64;
+
+/// 重新编码为不超过 `max_bytes` 的图片，返回编码结果与 MIME 类型；无法解码时返回 `None`
+///
+/// 原尺寸重新编码仍超出上限时，按超出的比例估算缩小后的最长边，每次至少缩小四分之一。
+fn downscale(data: &[u8], max_bytes: usize, quality: u8) -> Option<(Vec<u8>, &'static str)> {
+    let original = ::image::load_from_memory(data).ok()?;
+    let alpha = original.color().has_alpha();
+    let full_side = original.width().max(original.height());
+    let mut side = full_side;
+    loop {
+        let encoded = if side < full_side {
+            encode(
+                &original.resize(side, side, FilterType::Triangle),
+                alpha,
+                quality,
+            )
+        } else {
+            encode(&original, alpha, quality)
+        }
+        .ok()?;
+        if encoded.len() <= max_bytes || side <= MIN_DOWNSCALED_SIDE {
+            let mime = if alpha { "image/png" } else { "image/jpeg" };
+            return Some((encoded, mime));
+        }
+        let estimate = side as f64 * (max_bytes as f64 / encoded.len() as f64).sqrt();
+        side = (estimate as u32).min(side * 3 / 4).max(MIN_DOWNSCALED_SIDE);
+    }
+}
+
+/// 有透明通道时编码为 PNG 以保留透明度，否则编码为给定质量的 JPEG
+fn encode(image: &DynamicImage, alpha: bool, quality: u8) -> ImageResult<Vec<u8>> {
+    let mut encoded = Vec::new();
+    if alpha {
+        let encoder =
+            PngEncoder::new_with_quality(&mut encoded, CompressionType::Best, PngFilter::Adaptive);
+        image.write_with_encoder(encoder)?;
+    } else {
+        let encoder = JpegEncoder::new_with_quality(&mut encoded, quality);
+        DynamicImage::from(image.to_rgb8()).write_with_encoder(encoder)?;
+    }
+    Ok(encoded)
+}
+
+/// 超出大小上限的图片如何处理的结果
+#[derive(Debug, Clone, Copy)]
+enum Oversize {
+    /// 重新编码后的字节数
+    Downscaled(usize),
+    Linked,
+    Skipped,
+}
+
+/// 超出大小上限的图片的统计，每张不同的图片只计一次
+#[derive(Debug, Default)]
+struct OversizeCounts {
+    downscaled: usize,
+    linked: usize,
+    skipped: usize,
+    original_bytes: usize,
+    embedded_bytes: usize,
+}
+
+impl OversizeCounts {
+    fn record(&mut self, outcome: Oversize, original_bytes: usize) {
+        self.original_bytes += original_bytes;
+        match outcome {
+            Oversize::Downscaled(bytes) => {
+                self.downscaled += 1;
+                self.embedded_bytes += bytes;
+            }
+            Oversize::Linked => self.linked += 1,
+            Oversize::Skipped => self.skipped += 1,
+        }
+    }
+}
+
+// ==================== 图片输出 ====================
+
+/// 图片在输出文档中的呈现方式
+#[derive(Debug, Clone, Default)]
+pub enum ImageOutput {
+    /// 以 Base64 data URI 内联（默认）
+    #[default]
+    Embed,
+    /// 复制到 `dir`，并以 `link_prefix/文件名` 的相对路径引用
+    Directory { dir: PathBuf, link_prefix: String },
+}
+
+/// 解析 image_path 的结果
+#[derive(Debug, Clone)]
+pub(crate) enum Resolved {
+    /// 可直接放入 src 的地址，以及输出的图片的尺寸
+    Src(String, Option<ImageSize>),
+    /// 文件不存在
+    Missing,
+    /// 超出大小上限而未输出，值为原始字节数
+    Omitted(usize),
+}
+
+/// 内联一张图片的结果
+#[derive(Debug, Clone)]
+enum Embedded {
+    Src(String, Option<ImageSize>),
+    Omitted(usize),
+}
+
+/// 内联图片的缓存：同一路径不再重复读取，内容相同的图片只编码一次
+struct EmbedCache {
+    by_path: Mutex<HashMap<PathBuf, Embedded>>,
+    by_content: Mutex<HashMap<ContentKey, Embedded>>,
+    /// 内联图片的大小上限，超出时按 `policy` 处理
+    max_bytes: Option<usize>,
+    policy: OversizePolicy,
+    /// 缩小重新编码时的 JPEG 质量
+    quality: u8,
+    /// [`OversizePolicy::Link`] 时写入超出上限的图片的目录
+    linked: Option<ImageDirectory>,
+    oversize: Mutex<OversizeCounts>,
+}
+
+/// 图片写入目录时的状态：文件名在渲染前按文档顺序统一分配，
+/// 并行渲染时同名文件的编号不受页面完成先后的影响；内容相同的图片共用一个文件
+struct ImageDirectory {
+    dir: PathBuf,
+    link_prefix: String,
+    /// 只写出大于该字节数的图片（[`OversizePolicy::Link`] 与 `embed_threshold`）；`None` 时写出全部图片
+    only_above: Option<usize>,
+    assigned: HashMap<PathBuf, String>,
+    /// 已写出的文件名
+    copied: Mutex<HashSet<String>>,
+    /// 已写出的文件的字节数之和
+    copied_bytes: AtomicUsize,
+}
+
+enum ImageMode {
+    Embed(Box<EmbedCache>),
+    /// 写入目录；开启 `embed_threshold` 时另有内联不超过阈值的图片的缓存
+    Directory(ImageDirectory, Option<Box<EmbedCache>>),
+    /// 不读取也不写出图片，一律解析为空地址；只用于预先收集目录
+    Outline,
+    /// 只检查图片文件是否存在，存在的解析为空地址；用于校验输入
+    Check,
+}
+
+/// 将 MinerU 中的 image_path 解析为可直接放入 src 的地址（data URI 或相对路径）
+///
+/// 缓存只在一次转换内有效，随解析器一起释放。
+pub(crate) struct ImageResolver {
+    mode: ImageMode,
+    /// 成功解析的图片引用次数
+    resolved: AtomicUsize,
+    /// 读取、编码与复制图片所用的纳秒数，各线程之和
+    busy_nanos: AtomicU64,
+    /// 在后备位置找到的图片：image_path 与实际使用的文件
+    relocated: Mutex<BTreeMap<String, PathBuf>>,
+}
+
+impl ImageResolver {
+    /// `pages` 为将要渲染的页面及其图片根目录，写入目录时据此按顺序预先分配文件名
+    pub(crate) fn new(options: &ConvertOptions, pages: &[(&Path, &PageInfo)]) -> Self {
+        let mode = match &options.images {
+            ImageOutput::Embed => {
+                let linked = match (&options.oversize_policy, options.max_image_bytes) {
+                    (OversizePolicy::Link { dir, link_prefix }, Some(max_bytes)) => Some(
+                        ImageDirectory::plan(dir, link_prefix, Some(max_bytes), pages),
+                    ),
+                    _ => None,
+                };
+                ImageMode::Embed(Box::new(EmbedCache::new(
+                    options,
+                    options.max_image_bytes,
+                    linked,
+                )))
+            }
+            // 阈值为 0 时全部写入目录；内联的小图片不受 max_image_bytes 限制
+            ImageOutput::Directory { dir, link_prefix } => {
+                let threshold = options.embed_threshold.filter(|&threshold| threshold > 0);
+                let small = options
+                    .embed_threshold
+                    .map(|_| Box::new(EmbedCache::new(options, None, None)));
+                ImageMode::Directory(
+                    ImageDirectory::plan(dir, link_prefix, threshold, pages),
+                    small,
+                )
+            }
+        };
+        ImageResolver {
+            mode,
+            resolved: AtomicUsize::new(0),
+            busy_nanos: AtomicU64::new(0),
+            relocated: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    /// 不访问图片文件的解析器，所有图片都解析为空地址
+    pub(crate) fn outline() -> Self {
+        ImageResolver {
+            mode: ImageMode::Outline,
+            resolved: AtomicUsize::new(0),
+            busy_nanos: AtomicU64::new(0),
+            relocated: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    /// 只检查图片是否存在、不读取文件的解析器，见 [`crate::validate_layout`]
+    pub(crate) fn check() -> Self {
+        ImageResolver {
+            mode: ImageMode::Check,
+            resolved: AtomicUsize::new(0),
+            busy_nanos: AtomicU64::new(0),
+            relocated: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    /// 复用了此前已编码或已写出的图片的引用次数
+    ///
+    /// 按引用总数减去不同图片的数量计算，与并行渲染时各页完成的先后无关。
+    pub(crate) fn deduplicated(&self) -> usize {
+        let distinct = match &self.mode {
+            ImageMode::Embed(cache) => cache.by_content.lock().unwrap().len(),
+            ImageMode::Directory(images, small) => {
+                images.copied.lock().unwrap().len()
+                    + small
+                        .as_ref()
+                        .map_or(0, |cache| cache.by_content.lock().unwrap().len())
+            }
+            ImageMode::Outline | ImageMode::Check => return 0,
+        };
+        self.resolved
+            .load(Ordering::Relaxed)
+            .saturating_sub(distinct)
+    }
+
+    /// 把超出大小上限的图片的处理结果记入统计
+    pub(crate) fn record_oversize(&self, stats: &mut ConvertStats) {
+        if let ImageMode::Embed(cache) = &self.mode {
+            let counts = cache.oversize.lock().unwrap();
+            stats.images_downscaled = counts.downscaled;
+            stats.images_linked = counts.linked;
+            stats.images_omitted = counts.skipped;
+            stats.oversize_original_bytes = counts.original_bytes;
+            stats.oversize_embedded_bytes = counts.embedded_bytes;
+        }
+    }
+
+    /// 开启 `embed_threshold` 时把内联与写入目录的图片记入统计
+    pub(crate) fn record_threshold(&self, stats: &mut ConvertStats) {
+        if let ImageMode::Directory(images, Some(small)) = &self.mode {
+            let copied_bytes = images.copied_bytes.load(Ordering::Relaxed);
+            stats.images_below_threshold = small.by_content.lock().unwrap().len();
+            stats.images_above_threshold = images.copied.lock().unwrap().len();
+            stats.threshold_bytes_saved = 4 * copied_bytes.div_ceil(3);
+        }
+    }
+
+    /// 把在后备位置找到的图片记入统计
+    pub(crate) fn record_relocated(&self, stats: &mut ConvertStats) {
+        stats.images_relocated = self.relocated.lock().unwrap().clone();
+    }
+
+    /// 读取、编码与复制图片所用的时间，并行渲染时为各线程之和
+    pub(crate) fn busy(&self) -> Duration {
+        Duration::from_nanos(self.busy_nanos.load(Ordering::Relaxed))
+    }
+
+    /// 图片文件不存在时返回 [`Resolved::Missing`]，读取、复制或写出失败时返回错误
+    pub(crate) fn resolve(
+        &self,
+        base_path: &Path,
+        image_path: &str,
+    ) -> Result<Resolved, ConvertError> {
+        if matches!(self.mode, ImageMode::Outline) {
+            return Ok(Resolved::Src(String::new(), None));
+        }
+        let (full_path, relocated) = locate_image(base_path, image_path);
+        if relocated {
+            self.relocated
+                .lock()
+                .unwrap()
+                .insert(image_path.to_string(), full_path.clone());
+        }
+        let started = Instant::now();
+        let result = match &self.mode {
+            ImageMode::Embed(cache) => cache.resolve(&full_path),
+            ImageMode::Directory(images, Some(small)) if images.embeds(&full_path) => {
+                small.resolve(&full_path)
+            }
+            ImageMode::Directory(images, _) => images.copy_in(&full_path).map(|src| match src {
+                Some(src) => Resolved::Src(src, file_image_size(&full_path)),
+                None => Resolved::Missing,
+            }),
+            ImageMode::Check if full_path.exists() => Ok(Resolved::Src(String::new(), None)),
+            ImageMode::Check => Ok(Resolved::Missing),
+            ImageMode::Outline => unreachable!("outline resolver returns before reading files"),
+        };
+        let elapsed = u64::try_from(started.elapsed().as_nanos()).unwrap_or(u64::MAX);
+        self.busy_nanos.fetch_add(elapsed, Ordering::Relaxed);
+        let resolved = result.map_err(|source| ConvertError::Io {
+            path: full_path,
+            source,
+        })?;
+        if !matches!(resolved, Resolved::Missing) {
+            self.resolved.fetch_add(1, Ordering::Relaxed);
+        }
+        Ok(resolved)
+    }
+}
+
+impl EmbedCache {
+    /// 超出 `max_bytes` 的图片按选项中的 oversize_policy 处理；
+    /// `linked` 为 [`OversizePolicy::Link`] 时写入超出上限的图片的目录
+    fn new(
+        options: &ConvertOptions,
+        max_bytes: Option<usize>,
+        linked: Option<ImageDirectory>,
+    ) -> Self {
+        EmbedCache {
+            by_path: Mutex::new(HashMap::new()),
+            by_content: Mutex::new(HashMap::new()),
+            max_bytes,
+            policy: options.oversize_policy.clone(),
+            quality: options.image_quality,
+            linked,
+            oversize: Mutex::new(OversizeCounts::default()),
+        }
+    }
+
+    fn resolve(&self, full_path: &Path) -> io::Result<Resolved> {
+        Ok(match self.embed(full_path)? {
+            Some(Embedded::Src(src, size)) => Resolved::Src(src, size),
+            Some(Embedded::Omitted(bytes)) => Resolved::Omitted(bytes),
+            None => Resolved::Missing,
+        })
+    }
+
+    fn embed(&self, full_path: &Path) -> io::Result<Option<Embedded>> {
+        if let Some(embedded) = self.by_path.lock().unwrap().get(full_path) {
+            return Ok(Some(embedded.clone()));
+        }
+        if !full_path.exists() {
+            return Ok(None);
+        }
+        let data = fs::read(full_path)?;
+        let key = content_key(&data);
+        let cached = self.by_content.lock().unwrap().get(&key).cloned();
+        // 编码在锁外进行；两个线程同时编码同一内容时保留先写入的结果，也只统计这一次
+        let embedded = match cached {
+            Some(embedded) => embedded,
+            None => {
+                let (embedded, oversize) = self.encode(full_path, &data)?;
+                match self.by_content.lock().unwrap().entry(key) {
+                    Entry::Occupied(entry) => entry.get().clone(),
+                    Entry::Vacant(entry) => {
+                        if let Some(oversize) = oversize {
+                            self.oversize.lock().unwrap().record(oversize, data.len());
+                        }
+                        entry.insert(embedded).clone()
+                    }
+                }
+            }
+        };
+        self.by_path
+            .lock()
+            .unwrap()
+            .insert(full_path.to_path_buf(), embedded.clone());
+        Ok(Some(embedded))
+    }
+
+    /// 不超过大小上限的图片直接编码为 data URI，超出的按 `policy` 处理
+    fn encode(&self, full_path: &Path, data: &[u8]) -> io::Result<(Embedded, Option<Oversize>)> {
+        let max_bytes = match self.max_bytes {
+            Some(max_bytes) if data.len() > max_bytes => max_bytes,
+            _ => {
+                let src = image_to_base64(full_path, data);
+                return Ok((Embedded::Src(src, image_size(data)), None));
+            }
+        };
+        let omitted = (Embedded::Omitted(data.len()), Some(Oversize::Skipped));
+        match &self.policy {
+            OversizePolicy::Downscale => Ok(match downscale(data, max_bytes, self.quality) {
+                Some((encoded, mime)) => (
+                    Embedded::Src(data_uri(mime, &encoded), image_size(&encoded)),
+                    Some(Oversize::Downscaled(encoded.len())),
+                ),
+                None => omitted,
+            }),
+            OversizePolicy::Link { .. } => {
+                let linked = match &self.linked {
+                    Some(images) => images.copy_in(full_path)?,
+                    None => None,
+                };
+                // 渲染前未分配文件名（文件在此期间变大）时按 Skip 处理
+                Ok(match linked {
+                    Some(src) => (Embedded::Src(src, image_size(data)), Some(Oversize::Linked)),
+                    None => omitted,
+                })
+            }
+            OversizePolicy::Skip => Ok(omitted),
+        }
+    }
+}
+
+impl ImageDirectory {
+    /// 已在 `dir` 中的图片先按原名分配，原地引用，不会被其他目录中的同名图片覆盖
+    fn plan(
+        dir: &Path,
+        link_prefix: &str,
+        only_above: Option<usize>,
+        pages: &[(&Path, &PageInfo)],
+    ) -> Self {
+        let mut images = ImageDirectory {
+            dir: dir.to_path_buf(),
+            link_prefix: link_prefix.to_string(),
+            only_above,
+            assigned: HashMap::new(),
+            copied: Mutex::new(HashSet::new()),
+            copied_bytes: AtomicUsize::new(0),
+        };
+        let mut sources: Vec<PathBuf> = pages
+            .iter()
+            .flat_map(|(base_path, page)| {
+                page.para_blocks
+                    .iter()
+                    .flat_map(Block::image_paths)
+                    .map(|image_path| locate_image(base_path, image_path).0)
+            })
+            .collect();
+        let canonical_dir = dir.canonicalize().ok();
+        sources.sort_by_key(|source| {
+            canonical_dir.is_none()
+                || source
+                    .parent()
+                    .and_then(|parent| parent.canonicalize().ok())
+                    != canonical_dir
+        });
+        let mut used_names = HashSet::new();
+        let mut by_content = HashMap::new();
+        for source in sources {
+            images.plan_image(source, &mut used_names, &mut by_content);
+        }
+        images
+    }
+
+    /// 内容与已分配的图片相同时直接复用其文件名；读取失败的文件按路径单独分配，
+    /// 错误留到复制时报告
+    fn plan_image(
+        &mut self,
+        source: PathBuf,
+        used_names: &mut HashSet<String>,
+        by_content: &mut HashMap<ContentKey, String>,
+    ) {
+        if self.assigned.contains_key(&source) || !source.exists() {
+            return;
+        }
+        if let Some(max_bytes) = self.only_above
+            && fs::metadata(&source).is_ok_and(|metadata| metadata.len() <= max_bytes as u64)
+        {
+            return;
+        }
+        let key = fs::read(&source).ok().map(|data| content_key(&data));
+        if let Some(name) = key.and_then(|key| by_content.get(&key)) {
+            self.assigned.insert(source, name.clone());
+            return;
+        }
+        let name = unique_name(&source, used_names);
+        used_names.insert(name.clone());
+        if let Some(key) = key {
+            by_content.insert(key, name.clone());
+        }
+        self.assigned.insert(source, name);
+    }
+
+    fn copy_in(&self, source: &Path) -> io::Result<Option<String>> {
+        let Some(name) = self.assigned.get(source) else {
+            return Ok(None);
+        };
+        // 同一文件名只写一次；锁只保护登记，复制本身在锁外进行
+        let first_use = self.copied.lock().unwrap().insert(name.clone());
+        if first_use {
+            let bytes = copy_image(source, &self.dir.join(name))?;
+            self.copied_bytes.fetch_add(
+                usize::try_from(bytes).unwrap_or(usize::MAX),
+                Ordering::Relaxed,
+            );
+        }
+        Ok(Some(self.link(name)))
+    }
+
+    /// 开启 `embed_threshold` 时未分配文件名的图片（不超过阈值或不存在）改为内联
+    fn embeds(&self, source: &Path) -> bool {
+        self.only_above.is_some() && !self.assigned.contains_key(source)
+    }
+
+    fn link(&self, name: &str) -> String {
+        if self.link_prefix.is_empty() {
+            name.to_string()
+        } else {
+            format!("{}/{}", self.link_prefix, name)
+        }
+    }
+}
+
+fn unique_name(source: &Path, used_names: &HashSet<String>) -> String {
+    let stem = source
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or("image");
+    let ext = source
+        .extension()
+        .and_then(|s| s.to_str())
+        .map(|e| format!(".{}", e))
+        .unwrap_or_default();
+
+    let mut name = format!("{}{}", stem, ext);
+    let mut n = 2;
+    while used_names.contains(&name) {
+        name = format!("{}-{}{}", stem, n, ext);
+        n += 1;
+    }
+    name
+}
+
+/// 计算图片目录相对于输出文件所在目录的引用前缀；图片目录不在输出目录之下时为图片目录本身，
+/// 绝对路径保留开头的 `/`
+pub fn relative_link_prefix(images_dir: &Path, output_path: &Path) -> String {
+    let output_dir = output_path
+        .parent()
+        .filter(|p| !p.as_os_str().is_empty())
+        .unwrap_or(Path::new("."));
+
+    let relative = match (images_dir.canonicalize(), output_dir.canonicalize()) {
+        (Ok(images), Ok(output)) => images
+            .strip_prefix(&output)
+            .map(Path::to_path_buf)
+            .unwrap_or_else(|_| images_dir.to_path_buf()),
+        _ => images_dir.to_path_buf(),
+    };
+
+    // 根目录本身写作 `/`，之后的第一段不再加分隔符，以免得到 `//tmp/images`
+    let mut link = String::new();
+    for component in relative.components() {
+        if component == Component::RootDir {
+            link.push('/');
+            continue;
+        }
+        if !link.is_empty() && !link.ends_with('/') {
+            link.push('/');
+        }
+        link.push_str(&component.as_os_str().to_string_lossy());
+    }
+    link
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::convert_layout_to_markdown;
+    use crate::testing::{TempDir, bare_options, encoded, image_block, page_layout, png};
+    use ::image::ImageFormat;
+    use serde_json::json;
+
+    const SVG: &[u8] = b"<?xml version=\"1.0\"?>\n<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"4\" height=\"3\">\n\n<rect width=\"4\" height=\"3\"/>\n</svg>\n";
+
+    /// 每种格式的一张图片、常用扩展名与 MIME 类型
+    fn samples() -> Vec<(Vec<u8>, &'static str, &'static str)> {
+        let mut samples: Vec<_> = [
+            (ImageFormat::Png, "png", "image/png"),
+            (ImageFormat::Jpeg, "jpg", "image/jpeg"),
+            (ImageFormat::Gif, "gif", "image/gif"),
+            (ImageFormat::WebP, "webp", "image/webp"),
+            (ImageFormat::Bmp, "bmp", "image/bmp"),
+            (ImageFormat::Tiff, "tiff", "image/tiff"),
+        ]
+        .into_iter()
+        .map(|(format, ext, mime)| (encoded(4, 3, 90, format), ext, mime))
+        .collect();
+        samples.push((SVG.to_vec(), "svg", "image/svg+xml"));
+        // 只有 AVIF 的文件头，内容不需要能解码
+        samples.push((b"\0\0\0\x1cftypavif\0\0\0\0".to_vec(), "avif", "image/avif"));
+        samples
+    }
+
+    #[test]
+    fn mime_types_follow_the_extension() {
+        for (data, ext, mime) in samples() {
+            let path = PathBuf::from(format!("figure.{}", ext.to_uppercase()));
+            assert_eq!(mime_type(&path, &data), mime, "{}", ext);
+        }
+        assert_eq!(mime_type(Path::new("scan.tif"), &[]), "image/tiff");
+    }
+
+    #[test]
+    fn unknown_or_missing_extensions_are_sniffed() {
+        for (data, ext, mime) in samples() {
+            assert_eq!(mime_type(Path::new("figure.bin"), &data), mime, "{}", ext);
+            assert_eq!(mime_type(Path::new("figure"), &data), mime, "{}", ext);
+        }
+        assert_eq!(
+            mime_type(Path::new("figure"), b"not an image"),
+            "image/jpeg"
+        );
+    }
+
+    #[test]
+    fn svg_is_embedded_as_svg_or_inlined_raw() {
+        let dir = TempDir::new();
+        dir.write("eq", SVG);
+        let layout = page_layout(json!([image_block([0.0, 0.0, 100.0, 100.0], "eq")]));
+        let convert = |options: &ConvertOptions| {
+            convert_layout_to_markdown(&layout, dir.path(), options)
+                .unwrap()
+                .markdown
+        };
+        assert!(convert(&bare_options()).contains("src=\"data:image/svg+xml;base64,"));
+
+        let options = ConvertOptions {
+            inline_svg: true,
+            ..bare_options()
+        };
+        let inlined = convert(&options);
+        assert!(inlined.contains("<svg xmlns"), "{}", inlined);
+        assert!(!inlined.contains("<?xml"));
+        assert!(!inlined.contains("base64"));
+    }
+
+    fn directory_options(dir: PathBuf) -> ConvertOptions {
+        ConvertOptions {
+            images: ImageOutput::Directory {
+                dir,
+                link_prefix: "images".to_string(),
+            },
+            ..ConvertOptions::default()
+        }
+    }
+
+    #[test]
+    fn images_dir_holding_the_source_images_links_them_in_place() {
+        let tmp = TempDir::new();
+        let image = png(4, 4, 0);
+        let source = tmp.write("images/a.png", &image);
+        let layout = page_layout(json!([image_block(
+            [50.0, 50.0, 550.0, 200.0],
+            "images/a.png"
+        )]));
+
+        let options = directory_options(tmp.path().join("images"));
+        let output = convert_layout_to_markdown(&layout, tmp.path(), &options).unwrap();
+
+        assert_eq!(fs::read(&source).unwrap(), image);
+        assert!(output.markdown.contains("src=\"images/a.png\""));
+        assert_eq!(fs::read_dir(tmp.path().join("images")).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn copied_images_do_not_overwrite_same_named_images_in_the_directory() {
+        let tmp = TempDir::new();
+        let other = png(2, 2, 0);
+        let in_place = png(4, 4, 255);
+        tmp.write("other/a.png", &other);
+        let source = tmp.write("images/a.png", &in_place);
+        let layout = page_layout(json!([
+            image_block([50.0, 50.0, 550.0, 200.0], "other/a.png"),
+            image_block([50.0, 250.0, 550.0, 400.0], "images/a.png"),
+        ]));
+
+        let options = directory_options(tmp.path().join("images"));
+        let output = convert_layout_to_markdown(&layout, tmp.path(), &options).unwrap();
+
+        assert_eq!(fs::read(&source).unwrap(), in_place);
+        assert_eq!(fs::read(tmp.path().join("images/a-2.png")).unwrap(), other);
+        let copied = output.markdown.find("src=\"images/a-2.png\"").unwrap();
+        let linked = output.markdown.find("src=\"images/a.png\"").unwrap();
+        assert!(copied < linked);
+    }
+
+    #[test]
+    fn copy_image_leaves_the_same_file_untouched() {
+        let tmp = TempDir::new();
+        let source = tmp.write("images/a.png", b"not empty");
+        let dest = tmp.path().join("images/./a.png");
+
+        assert_eq!(copy_image(&source, &dest).unwrap(), 9);
+        assert_eq!(fs::read(&source).unwrap(), b"not empty");
+    }
+
+    #[test]
+    fn link_prefixes_are_relative_to_the_output_or_absolute() {
+        let dir = TempDir::new();
+        dir.write("out/images/a.png", b"");
+        let out = dir.path().join("out");
+        assert_eq!(
+            relative_link_prefix(&out.join("images"), &out.join("doc.md")),
+            "images"
+        );
+        // 图片目录不在输出目录之下时为绝对路径，开头只有一个 `/`
+        let elsewhere = TempDir::new();
+        let prefix = relative_link_prefix(elsewhere.path(), &out.join("doc.md"));
+        assert!(
+            prefix.starts_with('/') && !prefix.starts_with("//"),
+            "{}",
+            prefix
+        );
+        assert_eq!(Path::new(&prefix), elsewhere.path().canonicalize().unwrap());
+        // 尚未创建的目录原样使用
+        assert_eq!(
+            relative_link_prefix(Path::new("missing/img"), Path::new("doc.md")),
+            "missing/img"
+        );
+    }
+
+    #[test]
+    fn images_up_to_the_embed_threshold_are_inlined() {
+        let tmp = TempDir::new();
+        let small = png(2, 2, 0);
+        let large = encoded(64, 64, 128, ImageFormat::Bmp);
+        assert!(large.len() > small.len());
+        tmp.write("src/small.png", &small);
+        tmp.write("src/large.bmp", &large);
+        let layout = page_layout(json!([
+            image_block([50.0, 50.0, 550.0, 200.0], "src/small.png"),
+            image_block([50.0, 250.0, 550.0, 400.0], "src/large.bmp"),
+        ]));
+        let convert = |embed_threshold: Option<usize>| {
+            let images = tmp.path().join(format!("images-{:?}", embed_threshold));
+            fs::create_dir_all(&images).unwrap();
+            let options = ConvertOptions {
+                embed_threshold,
+                ..directory_options(images.clone())
+            };
+            let output = convert_layout_to_markdown(&layout, tmp.path(), &options).unwrap();
+            let mut copied: Vec<String> = fs::read_dir(&images)
+                .unwrap()
+                .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+                .collect();
+            copied.sort();
+            (output, copied)
+        };
+
+        // 与阈值相等的图片仍然内联
+        let (output, copied) = convert(Some(small.len()));
+        assert_eq!(copied, ["large.bmp"]);
+        assert!(output.markdown.contains("src=\"data:image/png;base64,"));
+        assert!(output.markdown.contains("src=\"images/large.bmp\""));
+        assert_eq!(output.stats.images_below_threshold, 1);
+        assert_eq!(output.stats.images_above_threshold, 1);
+        assert_eq!(
+            output.stats.threshold_bytes_saved,
+            4 * large.len().div_ceil(3)
+        );
+
+        // 阈值为 0 时全部写入目录
+        let (output, copied) = convert(Some(0));
+        assert_eq!(copied, ["large.bmp", "small.png"]);
+        assert!(!output.markdown.contains("data:image/"));
+        assert_eq!(output.stats.images_below_threshold, 0);
+        assert_eq!(output.stats.images_above_threshold, 2);
+
+        let (output, copied) = convert(None);
+        assert_eq!(copied.len(), 2);
+        assert_eq!(output.stats.images_above_threshold, 0);
+    }
+
+    #[test]
+    fn identical_images_are_written_and_embedded_once() {
+        // MinerU 把同一张图提取为两个文件名
+        let tmp = TempDir::new();
+        tmp.write("src/a.png", &png(4, 4, 0));
+        tmp.write("src/b.png", &png(4, 4, 0));
+        tmp.write("src/c.png", &png(4, 4, 255));
+        let layout = page_layout(json!([
+            image_block([50.0, 50.0, 550.0, 200.0], "src/a.png"),
+            image_block([50.0, 250.0, 550.0, 400.0], "src/b.png"),
+            image_block([50.0, 450.0, 550.0, 600.0], "src/c.png"),
+            image_block([50.0, 650.0, 550.0, 750.0], "src/a.png"),
+        ]));
+
+        fs::create_dir_all(tmp.path().join("images")).unwrap();
+        let options = directory_options(tmp.path().join("images"));
+        let output = convert_layout_to_markdown(&layout, tmp.path(), &options).unwrap();
+        let mut written: Vec<String> = fs::read_dir(tmp.path().join("images"))
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .collect();
+        written.sort();
+        assert_eq!(written, ["a.png", "c.png"]);
+        assert_eq!(output.markdown.matches("src=\"images/a.png\"").count(), 3);
+        assert_eq!(output.stats.images_deduplicated, 2);
+
+        let embedded =
+            convert_layout_to_markdown(&layout, tmp.path(), &ConvertOptions::default()).unwrap();
+        assert_eq!(embedded.stats.images_included, 4);
+        assert_eq!(embedded.stats.images_deduplicated, 2);
+    }
+}
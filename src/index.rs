@@ -0,0 +1,258 @@
+//! 索引块：书末索引、目录页等按行排列的内容，逐行输出而不合并为一段
+
+use crate::asciidoc::escape_asciidoc;
+use crate::latex::escape_latex;
+use crate::org::escape_org;
+use crate::rst::escape_rst;
+use crate::text::{escape_html, escape_text};
+use crate::types::Block;
+use crate::{ConvertOptions, IndexStyle, OutputFormat};
+
+// ==================== 行与条目 ====================
+
+/// 块内各行的文字：同一行的 span 直接拼接，保留行内的点线引导符与页码列
+pub(crate) fn index_lines(block: &Block) -> Vec<String> {
+    block
+        .lines
+        .iter()
+        .flatten()
+        .map(|line| {
+            line.spans
+                .iter()
+                .filter_map(|span| span.content.as_deref())
+                .collect::<String>()
+        })
+        .map(|line| line.trim().to_string())
+        .filter(|line| !line.is_empty())
+        .collect()
+}
+
+/// 是否为页码：`45`、`112–115`、`88f`、`xii`
+fn is_page_ref(text: &str) -> bool {
+    let text = text.trim();
+    let text = text
+        .strip_suffix("ff")
+        .or_else(|| text.strip_suffix('f'))
+        .unwrap_or(text);
+    let roman = !text.is_empty() && text.chars().all(|c| "ivxlcdm".contains(c));
+    let range = text
+        .split(['-', '–', '—'])
+        .all(|part| !part.is_empty() && part.chars().all(|c| c.is_ascii_digit()));
+    roman || range
+}
+
+/// "词条, 页码, 页码" 形式的一行拆为词条与页码，如 `Widgets, frobnicated, 88` →
+/// (`Widgets, frobnicated`, `88`)；不是这种形式时返回 `None`
+fn split_entry(line: &str) -> Option<(&str, &str)> {
+    let parts: Vec<&str> = line.split(',').collect();
+    let pages = parts
+        .iter()
+        .rev()
+        .take_while(|part| is_page_ref(part))
+        .count();
+    if pages == 0 || pages == parts.len() {
+        return None;
+    }
+    let split = parts[..parts.len() - pages]
+        .iter()
+        .map(|part| part.len() + 1)
+        .sum::<usize>();
+    Some((line[..split - 1].trim(), line[split..].trim()))
+}
+
+// ==================== 渲染 ====================
+
+/// 索引块逐行输出：Markdown 以行尾两个空格换行，HTML 以 `<br>` 换行，LaTeX 以 `\\` 换行，
+/// AsciiDoc 以行尾的 ` +` 换行，Org 以行尾的 `\\` 换行，纯文本每行一行
+///
+/// 选择 [`IndexStyle::Definitions`] 且每一行都是 "词条, 页码" 形式时输出为词条列表。
+pub(crate) fn render_index(lines: &[String], options: &ConvertOptions) -> String {
+    if lines.is_empty() {
+        return String::new();
+    }
+    if options.index_style == IndexStyle::Definitions
+        && options.format != OutputFormat::Text
+        && let Some(entries) = lines
+            .iter()
+            .map(|line| split_entry(line))
+            .collect::<Option<Vec<_>>>()
+    {
+        return render_definitions(&entries, options);
+    }
+
+    match options.format {
+        OutputFormat::Markdown => {
+            let lines: Vec<String> = lines
+                .iter()
+                .map(|line| escape_text(line, true, options))
+                .collect();
+            format!("{}\n\n", lines.join("  \n"))
+        }
+        OutputFormat::Html => {
+            let lines: Vec<String> = lines
+                .iter()
+                .map(|line| escape_text(line, false, options))
+                .collect();
+            format!("<p>{}</p>\n\n", lines.join("<br>\n"))
+        }
+        OutputFormat::Latex => {
+            let lines: Vec<String> = lines.iter().map(|line| escape_latex(line)).collect();
+            format!("{}\n\n", lines.join("\\\\\n"))
+        }
+        OutputFormat::Asciidoc => {
+            let lines: Vec<String> = lines
+                .iter()
+                .map(|line| escape_asciidoc(line, true))
+                .collect();
+            format!("{}\n\n", lines.join(" +\n"))
+        }
+        OutputFormat::Rst => {
+            let lines: Vec<String> = lines
+                .iter()
+                .map(|line| format!("| {}", escape_rst(line, false)))
+                .collect();
+            format!("{}\n\n", lines.join("\n"))
+        }
+        OutputFormat::Org => {
+            let lines: Vec<String> = lines.iter().map(|line| escape_org(line, true)).collect();
+            format!("{}\n\n", lines.join("\\\\\n"))
+        }
+        OutputFormat::Text | OutputFormat::Json => format!("{}\n\n", lines.join("\n")),
+    }
+}
+
+fn render_definitions(entries: &[(&str, &str)], options: &ConvertOptions) -> String {
+    match options.format {
+        OutputFormat::Html => {
+            let mut html = String::from("<dl>\n");
+            for (term, pages) in entries {
+                html.push_str(&format!(
+                    "<dt>{}</dt><dd>{}</dd>\n",
+                    escape_html(term),
+                    escape_html(pages)
+                ));
+            }
+            html.push_str("</dl>\n\n");
+            html
+        }
+        OutputFormat::Latex => {
+            let mut latex = String::from("\\begin{description}\n");
+            for (term, pages) in entries {
+                latex.push_str(&format!(
+                    "\\item[{{{}}}] {}\n",
+                    escape_latex(term),
+                    escape_latex(pages)
+                ));
+            }
+            latex.push_str("\\end{description}\n\n");
+            latex
+        }
+        OutputFormat::Asciidoc => {
+            let mut asciidoc = String::new();
+            for (term, pages) in entries {
+                asciidoc.push_str(&format!(
+                    "{}:: {}\n",
+                    escape_asciidoc(term, true),
+                    escape_asciidoc(pages, false)
+                ));
+            }
+            asciidoc.push('\n');
+            asciidoc
+        }
+        OutputFormat::Rst => {
+            let mut rst = String::new();
+            for (term, pages) in entries {
+                rst.push_str(&format!(
+                    "{}\n   {}\n",
+                    escape_rst(term, true),
+                    escape_rst(pages, true)
+                ));
+            }
+            rst.push('\n');
+            rst
+        }
+        OutputFormat::Org => {
+            let mut org = String::new();
+            for (term, pages) in entries {
+                org.push_str(&format!(
+                    "- {} :: {}\n",
+                    escape_org(term, false),
+                    escape_org(pages, false)
+                ));
+            }
+            org.push('\n');
+            org
+        }
+        OutputFormat::Markdown | OutputFormat::Text | OutputFormat::Json => {
+            let mut markdown = String::new();
+            for (term, pages) in entries {
+                let (term, pages) = (
+                    escape_text(term, false, options),
+                    escape_text(pages, false, options),
+                );
+                markdown.push_str(&format!("- **{}**: {}\n", term, pages));
+            }
+            markdown.push('\n');
+            markdown
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{bare_options, markdown};
+    use crate::{InputFormat, parse_layout};
+
+    /// 目录页（点线引导符与页码列）与书末索引各一页
+    const FIXTURE: &str = include_str!("../tests/fixtures/index.json");
+
+    fn convert(options: &ConvertOptions) -> String {
+        markdown(&parse_layout(FIXTURE, InputFormat::Auto).unwrap(), options)
+    }
+
+    #[test]
+    fn index_blocks_keep_one_line_per_line() {
+        assert_eq!(
+            convert(&bare_options()),
+            "1 Introduction . . . . . . . . 1  \n\
+             2 Methods . . . . . . . . . . 17\n\n\
+             Widgets, 45, 112  \n\
+             Widgets, frobnicated, 88  \n\
+             Wombats, 3–7, 19f  \n\
+             Xylography, xii, 201ff\n\n"
+        );
+    }
+
+    #[test]
+    fn definitions_style_applies_only_to_term_and_page_entries() {
+        let options = ConvertOptions {
+            index_style: IndexStyle::Definitions,
+            ..bare_options()
+        };
+        assert_eq!(
+            convert(&options),
+            "1 Introduction . . . . . . . . 1  \n\
+             2 Methods . . . . . . . . . . 17\n\n\
+             - **Widgets**: 45, 112\n\
+             - **Widgets, frobnicated**: 88\n\
+             - **Wombats**: 3–7, 19f\n\
+             - **Xylography**: xii, 201ff\n\n"
+        );
+    }
+
+    #[test]
+    fn entries_split_at_the_trailing_page_references() {
+        assert_eq!(
+            split_entry("Widgets, frobnicated, 88"),
+            Some(("Widgets, frobnicated", "88"))
+        );
+        assert_eq!(
+            split_entry("Wombats, 3–7, 19f"),
+            Some(("Wombats", "3–7, 19f"))
+        );
+        assert_eq!(split_entry("Kings, xiv"), Some(("Kings", "xiv")));
+        assert_eq!(split_entry("45, 112"), None);
+        assert_eq!(split_entry("Widgets and gadgets"), None);
+    }
+}
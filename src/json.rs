@@ -0,0 +1,310 @@
+//! JSON 输出：把文档节点整理为扁平的内容条目，供下游分块等处理
+//!
+//! 文档为 `{"schema_version": 1, "items": [...]}`，每个条目独占一行。条目的 `kind`
+//! 为 `heading`、`paragraph`、`list`、`code`、`figure`、`table`、`equation` 或 `footnote`，
+//! 都带有 `page_idx`（输出文档中的页码，从 0 开始）；没有值的字段不输出。
+//! 字段只增不改，含义变化时递增 [`SCHEMA_VERSION`]。
+
+use serde::Serialize;
+
+use crate::ConvertOptions;
+use crate::doc::{
+    BlockSource, Caption, CaptionKind, DocNode, ImageSource, ListItem, Panel, TableBody,
+};
+use crate::text::render_inlines;
+
+/// JSON 输出的结构版本，写在文档的 `schema_version` 字段中
+pub const SCHEMA_VERSION: u32 = 1;
+
+// ==================== 内容条目 ====================
+
+/// 一个内容条目；图片按 [`ConvertOptions::images`] 解析，内联时为 `data_uri`，写入目录时为 `image_path`
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub(crate) enum ContentItem {
+    Heading {
+        page_idx: usize,
+        /// 0 为文档标题，1–5 为各级标题
+        level: usize,
+        text: String,
+    },
+    /// 正文段落；行内公式保留为 `$…$` 包围的 LaTeX
+    Paragraph { page_idx: usize, text: String },
+    List {
+        page_idx: usize,
+        ordered: bool,
+        items: Vec<ListItem>,
+    },
+    Code {
+        page_idx: usize,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        language: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        caption: Option<String>,
+        text: String,
+    },
+    /// 组图的 `image_path` / `data_uri` 为第一张输出的图片，各个分图在 `panels` 中
+    Figure {
+        page_idx: usize,
+        #[serde(flatten)]
+        image: ImageRef,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        caption: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        footnote: Option<String>,
+        #[serde(skip_serializing_if = "Vec::is_empty")]
+        panels: Vec<PanelRef>,
+    },
+    Table {
+        page_idx: usize,
+        /// MinerU 识别出的表格 HTML；没有时为表格截图
+        #[serde(skip_serializing_if = "Option::is_none")]
+        html: Option<String>,
+        #[serde(flatten)]
+        image: ImageRef,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        caption: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        footnote: Option<String>,
+        /// 开启 table_export 时导出的 CSV 文件的路径
+        #[serde(skip_serializing_if = "Option::is_none")]
+        csv: Option<String>,
+    },
+    Equation {
+        page_idx: usize,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        latex: Option<String>,
+        #[serde(flatten)]
+        image: ImageRef,
+    },
+    /// 页面脚注与正文中的脚注块
+    Footnote { page_idx: usize, text: String },
+}
+
+/// 图片引用；图片缺失或超出大小上限时两者都没有
+#[derive(Debug, Clone, Default, Serialize)]
+pub(crate) struct ImageRef {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    image_path: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    data_uri: Option<String>,
+}
+
+/// 组图中的一个分图
+#[derive(Debug, Clone, Serialize)]
+pub(crate) struct PanelRef {
+    #[serde(flatten)]
+    image: ImageRef,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    caption: Option<String>,
+}
+
+impl PanelRef {
+    fn from_panel(panel: &Panel) -> Self {
+        PanelRef {
+            image: ImageRef::from_source(Some(&panel.image)),
+            caption: panel.caption.clone(),
+        }
+    }
+}
+
+impl ImageRef {
+    fn from_source(image: Option<&ImageSource>) -> Self {
+        match image.and_then(ImageSource::src) {
+            Some(src) if src.starts_with("data:") => ImageRef {
+                image_path: None,
+                data_uri: Some(src.to_string()),
+            },
+            Some(src) => ImageRef {
+                image_path: Some(src.to_string()),
+                data_uri: None,
+            },
+            None => ImageRef::default(),
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.image_path.is_none() && self.data_uri.is_none()
+    }
+}
+
+/// 条目序列化为一行 JSON；页面内容即由这样的行组成
+fn item_line(item: &ContentItem) -> String {
+    // 条目只含字符串与数字字段，序列化不会失败
+    let mut line = serde_json::to_string(item).expect("content items serialize");
+    line.push('\n');
+    line
+}
+
+// ==================== 节点 ====================
+
+/// 一个节点的条目；没有内容的节点没有条目。段落、参考文献与索引为 `paragraph`，
+/// 正文中的脚注块为 `footnote`；[`DocNode::Rendered`] 不是结构化的内容，不输出
+pub(crate) fn write_node(
+    node: &DocNode,
+    source: Option<&BlockSource>,
+    page_idx: usize,
+    options: &ConvertOptions,
+) -> String {
+    let text_item =
+        |text: String| (!text.is_empty()).then_some(ContentItem::Paragraph { page_idx, text });
+    let item = match node {
+        // 含有行内公式的标题与段落一样保留 `$…$`
+        DocNode::Heading {
+            level,
+            text,
+            inlines,
+            ..
+        } => Some(ContentItem::Heading {
+            page_idx,
+            level: *level,
+            text: if inlines.is_empty() {
+                text.clone()
+            } else {
+                render_inlines(inlines, options).trim().to_string()
+            },
+        }),
+        DocNode::Paragraph { inlines, .. } => {
+            text_item(render_inlines(inlines, options).trim().to_string())
+        }
+        DocNode::Code {
+            language,
+            caption,
+            code,
+        } => Some(ContentItem::Code {
+            page_idx,
+            language: language.clone(),
+            caption: caption.clone(),
+            text: code.clone(),
+        }),
+        DocNode::List { items } => (!items.is_empty()).then(|| ContentItem::List {
+            page_idx,
+            ordered: items.first().is_some_and(ListItem::is_ordered),
+            items: items.clone(),
+        }),
+        DocNode::Figure {
+            image,
+            panels,
+            captions,
+            ..
+        } => {
+            let (captions, footnotes): (Vec<&Caption>, Vec<&Caption>) = captions
+                .iter()
+                .partition(|caption| caption.kind == CaptionKind::Caption);
+            Some(ContentItem::Figure {
+                page_idx,
+                image: ImageRef::from_source(image.as_ref()),
+                caption: joined(&captions),
+                footnote: joined(&footnotes),
+                panels: panels.iter().map(PanelRef::from_panel).collect(),
+            })
+        }
+        DocNode::Table {
+            body,
+            captions,
+            csv,
+            ..
+        } => {
+            let (captions, footnotes): (Vec<&Caption>, Vec<&Caption>) = captions
+                .iter()
+                .partition(|caption| caption.kind == CaptionKind::Caption);
+            let (html, image) = match body {
+                Some(TableBody::Html(html)) => (Some(html.clone()), ImageRef::default()),
+                Some(TableBody::Image(image)) => (None, ImageRef::from_source(Some(image))),
+                None => (None, ImageRef::default()),
+            };
+            Some(ContentItem::Table {
+                page_idx,
+                html,
+                image,
+                caption: joined(&captions),
+                footnote: joined(&footnotes),
+                csv: csv.clone(),
+            })
+        }
+        DocNode::Equation { latex, image } => {
+            let image = ImageRef::from_source(image.as_ref());
+            (latex.is_some() || !image.is_empty()).then(|| ContentItem::Equation {
+                page_idx,
+                latex: latex.clone(),
+                image,
+            })
+        }
+        DocNode::References { entries } => text_item(entries.join("\n")),
+        DocNode::Index { lines } => text_item(lines.join("\n")),
+        DocNode::Quote { lines } => {
+            let lines: Vec<String> = lines
+                .iter()
+                .map(|line| render_inlines(line, options).trim().to_string())
+                .filter(|line| !line.is_empty())
+                .collect();
+            text_item(lines.join("\n"))
+        }
+        DocNode::SmallPrint { text }
+            if source.is_some_and(|source| source.block_type == "footnote") =>
+        {
+            (!text.is_empty()).then(|| ContentItem::Footnote {
+                page_idx,
+                text: text.clone(),
+            })
+        }
+        DocNode::SmallPrint { text } => text_item(text.clone()),
+        DocNode::HeaderNote { .. }
+        | DocNode::FooterNote { .. }
+        | DocNode::FootNote { .. }
+        | DocNode::PageBreak { .. }
+        | DocNode::Rendered { .. } => None,
+    };
+    item.as_ref().map(item_line).unwrap_or_default()
+}
+
+/// 页面脚注，每个有文字的脚注一个条目
+pub(crate) fn write_footnotes(notes: &[&str], page_idx: usize) -> String {
+    notes
+        .iter()
+        .filter(|text| !text.is_empty())
+        .map(|text| {
+            item_line(&ContentItem::Footnote {
+                page_idx,
+                text: text.to_string(),
+            })
+        })
+        .collect()
+}
+
+/// 多段说明文字以换行连接
+fn joined(captions: &[&Caption]) -> Option<String> {
+    (!captions.is_empty()).then(|| {
+        captions
+            .iter()
+            .map(|caption| caption.text.as_str())
+            .collect::<Vec<_>>()
+            .join("\n")
+    })
+}
+
+// ==================== 文档 ====================
+
+pub(crate) const JSON_DOCUMENT_TAIL: &str = "\n]}\n";
+
+/// 文档开头，之后依次是各条目
+pub(crate) fn json_document_head() -> String {
+    format!("{{\"schema_version\": {}, \"items\": [", SCHEMA_VERSION)
+}
+
+/// 把页面内容中的条目行接到 `out` 之后，条目之间以逗号分隔；`first` 记录是否还没有写出条目
+pub(crate) fn push_items(out: &mut String, content: &str, first: &mut bool) {
+    for line in content.lines().filter(|line| !line.is_empty()) {
+        out.push_str(if *first { "\n" } else { ",\n" });
+        out.push_str(line);
+        *first = false;
+    }
+}
+
+/// 把拼接后的页面内容包装为完整的 JSON 文档
+pub(crate) fn wrap_json_document(body: &str) -> String {
+    let mut json = json_document_head();
+    push_items(&mut json, body, &mut true);
+    json.push_str(JSON_DOCUMENT_TAIL);
+    json
+}
@@ -0,0 +1,191 @@
+//! LaTeX 输出：正文转义、分节命令、图表浮动体，以及可直接编译的独立文档
+
+use crate::figures::FigureKind;
+use crate::text::is_cjk;
+
+// ==================== 转义 ====================
+
+/// 转义 LaTeX 正文中的特殊字符 `\ { } % & _ # $ ~ ^`；公式内容不经过这里
+pub(crate) fn escape_latex(text: &str) -> String {
+    let mut out = String::with_capacity(text.len() + 8);
+    for c in text.chars() {
+        match c {
+            '\\' => out.push_str("\\textbackslash{}"),
+            '~' => out.push_str("\\textasciitilde{}"),
+            '^' => out.push_str("\\textasciicircum{}"),
+            '{' | '}' | '%' | '&' | '_' | '#' | '$' => {
+                out.push('\\');
+                out.push(c);
+            }
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+/// 链接地址：hyperref 的 `\url`、`\href` 中只需转义 `%` 与 `#`
+pub(crate) fn escape_url(url: &str) -> String {
+    url.replace('%', "\\%").replace('#', "\\#")
+}
+
+/// 注释：每行加上 `% ` 前缀
+pub(crate) fn comment(text: &str) -> String {
+    text.lines()
+        .map(|line| format!("{}\n", format!("% {}", line).trim_end()))
+        .collect()
+}
+
+/// 小字段落，用于脚注、参考文献条目等
+pub(crate) fn small_print(text: &str) -> String {
+    format!("{{\\small {}\\par}}\n", text)
+}
+
+// ==================== 分节与浮动体 ====================
+
+/// 标题层级对应的分节命令；文档标题（层级 0）与一级标题都用 `\section`
+pub(crate) fn sectioning_command(level: usize) -> &'static str {
+    match level {
+        0 | 1 => "section",
+        2 => "subsection",
+        3 => "subsubsection",
+        4 => "paragraph",
+        _ => "subparagraph",
+    }
+}
+
+/// 插图命令：不超过版心宽度和 0.8 倍版心高度，保持宽高比
+pub(crate) fn include_graphics(src: &str) -> String {
+    format!(
+        "\\includegraphics[width=\\linewidth,height=0.8\\textheight,keepaspectratio]{{{}}}",
+        src
+    )
+}
+
+/// 图片缺失时在原位置输出的方框，`note` 为占位提示文字
+pub(crate) fn missing_graphic(note: &str) -> String {
+    format!("\\fbox{{{}}}", escape_latex(note))
+}
+
+/// 图或表的浮动体：`caption_above` 时 `\caption` 在图表上方，否则在下方，其余说明文字以小字附在最后
+///
+/// 编号由 LaTeX 的 `\caption` 生成，因此 LaTeX 输出不再补 "Figure N." 前缀。
+pub(crate) fn float(
+    kind: FigureKind,
+    body: &str,
+    caption: Option<&str>,
+    caption_above: bool,
+    notes: &[&str],
+) -> String {
+    let environment = match kind {
+        FigureKind::Figure => "figure",
+        FigureKind::Table => "table",
+    };
+    let caption = caption
+        .map(|caption| format!("\\caption{{{}}}\n", escape_latex(caption)))
+        .unwrap_or_default();
+    let mut latex = format!("\\begin{{{}}}[htbp]\n\\centering\n", environment);
+    if caption_above {
+        latex.push_str(&caption);
+    }
+    latex.push_str(body);
+    latex.push('\n');
+    if !caption_above {
+        latex.push_str(&caption);
+    }
+    for note in notes {
+        latex.push_str(&format!("{{\\footnotesize {}\\par}}\n", escape_latex(note)));
+    }
+    latex.push_str(&format!("\\end{{{}}}\n\n", environment));
+    latex
+}
+
+// ==================== 独立文档 ====================
+
+/// 独立文档的导言区：公式、插图与超链接所需的宏包
+const PREAMBLE: &str = "\\documentclass{article}\n\\usepackage[utf8]{inputenc}\n\\usepackage[T1]{fontenc}\n\\usepackage{amsmath}\n\\usepackage{amssymb}\n\\usepackage{graphicx}\n\\usepackage{hyperref}\n";
+
+/// 含中日韩文字的独立文档的导言区：pdflatex 无法排版这些文字，改用 xeCJK，以 xelatex 编译
+const CJK_PREAMBLE: &str = "% Contains CJK text: compile with xelatex.\n\\documentclass{article}\n\\usepackage{xeCJK}\n\\usepackage{amsmath}\n\\usepackage{amssymb}\n\\usepackage{graphicx}\n\\usepackage{hyperref}\n";
+
+/// 正文中是否有中日韩文字或全角标点，有时独立文档使用 [`CJK_PREAMBLE`]
+pub(crate) fn has_cjk(text: &str) -> bool {
+    text.chars().any(is_cjk)
+}
+
+/// 包装为可直接编译的完整文档；导言区按正文中是否有中日韩文字选择
+pub(crate) fn wrap_latex_document(body: &str) -> String {
+    let mut latex = latex_document_head(has_cjk(body));
+    latex.push_str(body);
+    if !latex.ends_with('\n') {
+        latex.push('\n');
+    }
+    latex.push_str(LATEX_DOCUMENT_TAIL);
+    latex
+}
+
+pub(crate) const LATEX_DOCUMENT_TAIL: &str = "\\end{document}\n";
+
+/// 导言区与 `\begin{document}`；`cjk` 见 [`has_cjk`]
+pub(crate) fn latex_document_head(cjk: bool) -> String {
+    let preamble = if cjk { CJK_PREAMBLE } else { PREAMBLE };
+    format!("{}\n\\begin{{document}}\n\n", preamble)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{TempDir, bare_options, page_layout, text_block};
+    use crate::{
+        ConvertOptions, Document, ImageOutput, OutputFormat, convert_documents,
+        convert_documents_to_writer,
+    };
+    use serde_json::json;
+    use std::path::Path;
+
+    #[test]
+    fn cjk_text_switches_the_preamble_to_xecjk() {
+        let latin = wrap_latex_document("Hello.\n");
+        assert!(latin.starts_with("\\documentclass{article}\n\\usepackage[utf8]{inputenc}"));
+        assert!(!latin.contains("xeCJK"));
+
+        let cjk = wrap_latex_document("速度为 $v$。\n");
+        assert!(
+            cjk.starts_with("% Contains CJK text: compile with xelatex.\n\\documentclass{article}\n\\usepackage{xeCJK}\n"),
+            "{}",
+            cjk
+        );
+        assert!(!cjk.contains("inputenc") && !cjk.contains("fontenc"));
+        assert!(cjk.ends_with("速度为 $v$。\n\\end{document}\n"));
+        assert!(has_cjk("全角，标点") && has_cjk("かな") && !has_cjk("naïve café"));
+    }
+
+    #[test]
+    fn streamed_and_whole_documents_pick_the_same_preamble() {
+        let dir = TempDir::new();
+        let options = ConvertOptions {
+            format: OutputFormat::Latex,
+            latex_standalone: true,
+            images: ImageOutput::Directory {
+                dir: dir.path().to_path_buf(),
+                link_prefix: "images".to_string(),
+            },
+            ..bare_options()
+        };
+        for (content, cjk) in [("Plain text.", false), ("第一章 概述", true)] {
+            let layout = page_layout(json!([text_block(
+                "text",
+                [50.0, 100.0, 550.0, 200.0],
+                content
+            )]));
+            let documents = [Document {
+                layout: &layout,
+                base_path: Path::new("."),
+            }];
+            let whole = convert_documents(&documents, &options).unwrap().markdown;
+            let mut streamed = Vec::new();
+            convert_documents_to_writer(&documents, &mut streamed, &options).unwrap();
+            assert_eq!(String::from_utf8(streamed).unwrap(), whole);
+            assert_eq!(whole.contains("\\usepackage{xeCJK}"), cjk, "{}", whole);
+        }
+    }
+}
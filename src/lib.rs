@@ -0,0 +1,2425 @@
+//! 将 MinerU 输出的 layout.json 转换为 Markdown
+//!
+//! ```no_run
+//! use mineru_json_to_md::{ConvertOptions, LayoutJson, convert_layout_to_markdown};
+//! use std::path::Path;
+//!
+//! let json = std::fs::read_to_string("layout.json").unwrap();
+//! let layout: LayoutJson = serde_json::from_str(&json).unwrap();
+//! let output = convert_layout_to_markdown(&layout, Path::new("."), &ConvertOptions::default())?;
+//! for warning in &output.warnings {
+//!     eprintln!("warning: {}", warning);
+//! }
+//! # Ok::<(), mineru_json_to_md::ConvertError>(())
+//! ```
+
+mod analyze;
+mod asciidoc;
+#[doc(hidden)]
+pub mod bench;
+mod confidence;
+mod content_list;
+mod css;
+mod doc;
+mod error;
+mod extract;
+mod figures;
+mod footnote;
+mod image;
+mod index;
+mod json;
+mod latex;
+mod links;
+mod list;
+mod merge;
+mod numbering;
+mod order;
+mod org;
+mod pages;
+mod quote;
+mod references;
+mod render;
+mod renderer;
+mod rst;
+mod split;
+mod stats;
+mod stream;
+mod table;
+#[cfg(test)]
+mod testing;
+mod text;
+mod types;
+mod validate;
+
+use std::borrow::Cow;
+use std::fs;
+use std::ops::Range;
+use std::path::Path;
+use std::time::Instant;
+
+use rayon::prelude::*;
+use rayon::{ThreadPool, ThreadPoolBuilder};
+use serde::{Deserialize, Serialize};
+
+use analyze::{
+    RenderState, RepeatedMargins, advance_heading_numbers, advance_section_depths, analyze_page,
+    repeated_margins,
+};
+use asciidoc::{asciidoc_document_head, escape_asciidoc, macro_text, wrap_asciidoc_document};
+use css::style_attr;
+use figures::{FigureCounters, FigureEntry, generate_figure_lists};
+use image::ImageResolver;
+use json::wrap_json_document;
+use latex::{latex_document_head, wrap_latex_document};
+use numbering::HeadingNumbers;
+use order::reading_order;
+use org::{org_document_head, wrap_org_document};
+use render::write_page;
+use rst::{SectionDepths, escape_rst};
+use text::{
+    AnchorFiles, AnchorRegistry, anchor_href, anchor_marker, escape_html, escape_link_text,
+    github_anchors, heading_positions, obsidian_heading_link, toc_link_text,
+};
+
+pub use content_list::{
+    ContentListEntry, InputFormat, content_list_to_layout, parse_layout, parse_layout_lenient,
+};
+pub use doc::{
+    BlockSource, Caption, CaptionKind, DocNode, DocumentModel, ImageSize, ImageSource, Inline,
+    InlineStyle, ListItem, PageNode, PageNodes, Panel, TableBody,
+};
+pub use error::{BBoxError, ConvertError, ConvertOutput, Warning, WriteOutput};
+pub use extract::{ExtractOutput, ExtractedImage, extract_images, referenced_images};
+pub use image::{ImageOutput, OversizePolicy, relative_link_prefix};
+pub use json::SCHEMA_VERSION;
+pub use pages::{PageLabel, PageSelection};
+pub use renderer::{BlockRenderer, DefaultRenderer, PageContext};
+pub use split::{OutputFile, PageFile, SplitLevel, SplitOutput, convert_documents_split};
+pub use stats::{
+    BlockCount, ConvertStats, LargestImage, PageProgress, ProgressCallback, ScoredSpan, Timings,
+};
+pub use stream::{
+    Checkpoint, convert_documents_to_writer, convert_documents_to_writer_resumable,
+    convert_to_writer,
+};
+pub use table::TableExport;
+pub use types::{
+    BBox, Block, LayoutJson, Line, OutlineEntry, PageInfo, PageSizeSource, Span, TocEntry,
+};
+pub use validate::{MissingImageRef, Validation, validate_layout};
+
+// ==================== 转换选项 ====================
+
+/// 输出的 Markdown 风格
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Flavor {
+    /// 带内联样式的 HTML 与 Markdown 混排（默认）
+    #[default]
+    Styled,
+    /// 不含样式块和 HTML 包装的 GitHub 风格 Markdown
+    Gfm,
+    /// Obsidian：图片以 `![[文件名]]` 嵌入（需配合写入图片目录），页眉与页面脚注为 callout，
+    /// 目录链接使用 `[[#标题]]`，标题前不加 `<a id>` 锚点；不支持拆分输出与返回目录链接
+    Obsidian,
+}
+
+/// 输出文档的格式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// Markdown（默认），具体风格由 [`Flavor`] 决定
+    #[default]
+    Markdown,
+    /// 完整的 HTML5 文档：标题、段落、列表均输出为 HTML 标签，忽略 `flavor` 与 `footnote_style`
+    Html,
+    /// 纯文本：段落之间以空行分隔，不含样式、目录、图片、分页线和页脚；页眉与页面脚注不输出
+    Text,
+    /// LaTeX 正文：标题为 `\section` 等分节命令，图表为浮动体，分页线为 `\clearpage`；
+    /// 图片必须写入目录（[`ImageOutput::Directory`]），不输出页眉、目录与页脚
+    Latex,
+    /// 结构化的 JSON：带 `schema_version` 的扁平内容条目（标题、段落、列表、图表、公式、脚注），
+    /// 供下游分块使用；不含样式、目录、分页线、页眉与页脚，不经过 [`BlockRenderer`]
+    Json,
+    /// AsciiDoc：标题为 `==` 等章节标记，锚点为 `[[id]]`，图片为 `image::`，表格为 `|===`，
+    /// 公式为 `stem:[…]` 与 `[stem]` 块，页面脚注为 `footnote:[…]`；不输出页眉、页脚、样式与图表编号
+    Asciidoc,
+    /// reStructuredText：标题为带下划线的章节标题，锚点为 `.. _id:`，图片为 `.. figure::`，
+    /// 表格保留为 `.. raw:: html`，公式为 `:math:` 与 `.. math::`，页面脚注为 `[#label]_`；
+    /// 不输出页眉、页脚、样式与图表编号
+    Rst,
+    /// Org：标题为 `*` 星号标题，锚点为 `CUSTOM_ID` 属性，图片为 `[[file:…]]` 链接与 `#+CAPTION:`，
+    /// 表格为 Org 表格，公式为 `$…$` 与 `\[…\]`，页面脚注为 `[fn:label]`；图片必须写入目录，
+    /// 不输出目录（由 Org 导出时生成）、页眉、页脚、样式与图表编号
+    Org,
+}
+
+impl OutputFormat {
+    /// 输出文件的默认扩展名
+    pub fn extension(self) -> &'static str {
+        match self {
+            OutputFormat::Markdown => "md",
+            OutputFormat::Html => "html",
+            OutputFormat::Text => "txt",
+            OutputFormat::Latex => "tex",
+            // 与 MinerU 的 JSON 输入区分开，避免默认输出覆盖输入文件
+            OutputFormat::Json => "doc.json",
+            OutputFormat::Asciidoc => "adoc",
+            OutputFormat::Rst => "rst",
+            OutputFormat::Org => "org",
+        }
+    }
+}
+
+/// MinerU 识别出表格 HTML 时的输出形式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TableFormat {
+    /// 原样输出表格 HTML（默认）
+    #[default]
+    Html,
+    /// 转换为 GitHub 风格的管道表格；无法转换时退回 HTML 并记录警告
+    Markdown,
+}
+
+/// Markdown 输出中图片与表格的写法
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum FigureStyle {
+    /// Styled 风格为带样式的 `<figure>`、`<div>`，Gfm 与 Obsidian 为图片加说明文字段落（默认）
+    #[default]
+    Html,
+    /// Pandoc 原生的写法：图片为 `![图注](path){#fig-N}`，表格为管道表格加 `Table:` 表注；
+    /// 无法转换的表格与缺失的图片放进带 `caption` 属性的 `:::` 块
+    Pandoc,
+}
+
+/// Styled 风格与 HTML 输出中图表、分页线、页眉、脚注框等元素的样式写法
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CssMode {
+    /// 每个元素带内联的 `style` 属性（默认）
+    #[default]
+    Inline,
+    /// 元素带 `mineru-figure`、`mineru-page-divider` 等类名，样式块中定义这些类
+    Classes,
+    /// 元素带同样的类名，但不输出样式块，由使用者提供样式表
+    None,
+}
+
+/// 分页线的样式；只影响 Markdown 与 HTML 输出
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DividerStyle {
+    /// 居中的页码标签，两侧为分隔线（默认）
+    #[default]
+    Full,
+    /// 一条细线，页码以上标写在右侧
+    Compact,
+    /// 只有 `<!-- page 13 -->` 注释，渲染后不可见，保留页码供其他工具使用
+    Margin,
+}
+
+/// 页面脚注的输出形式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum FootnoteStyle {
+    /// 页面底部的样式化 `<div>`（默认）
+    #[default]
+    Div,
+    /// Markdown 脚注语法 `[^p3-1]: text`，正文中的引用标记同步改写
+    Markdown,
+}
+
+/// 公式的分隔符
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum MathDelimiters {
+    /// 行内 `$…$`，行间公式前后空一行的 `$$` 块（默认）
+    #[default]
+    Dollars,
+    /// 行内 `\(…\)`，行间 `\[…\]`（Typora 等）
+    Latex,
+    /// 行内 `$…$`，行间 `$$` 各占一行、前面不额外空行（MkDocs 等）
+    Brackets,
+}
+
+/// Markdown 输出中行内公式的写法
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum InlineMathStyle {
+    /// 按 math_delimiters 包围（默认）；`$` 紧挨字母、数字或汉字时在外侧补一个空格，Obsidian 风格除外
+    #[default]
+    Delimiters,
+    /// `<span class="math">\(…\)</span>`，不依赖渲染器对 `$` 的识别
+    Html,
+}
+
+/// 索引块（书末索引、目录页）的输出形式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum IndexStyle {
+    /// 逐行输出，保留原有的换行（默认）
+    #[default]
+    Lines,
+    /// 每一行都是 "词条, 页码" 形式时输出为词条列表，否则仍逐行输出
+    Definitions,
+}
+
+/// Markdown 标题锚点的生成方式；Obsidian 风格与 HTML 输出不受影响
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum AnchorStyle {
+    /// 标题前输出 `<a id="toc-N-slug">`，目录链接到这些锚点（默认）
+    #[default]
+    Explicit,
+    /// 不输出 `<a id>`，目录链接到 GitHub、GitLab 根据标题文字自动生成的锚点：
+    /// 小写、空格换成 `-`、去掉标点，重复的标题依次追加 `-1`、`-2`……
+    Github,
+}
+
+/// 缺少 index 字段时如何推断块的阅读顺序
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ColumnOrder {
+    /// 按 bbox 识别双栏页面，先左栏后右栏；单栏页面自上而下（默认）
+    #[default]
+    Auto,
+    /// 保持 JSON 数组中的顺序
+    Off,
+}
+
+/// 图注、表注相对于图片和表格的位置
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CaptionPosition {
+    /// 与 MinerU 子块的顺序一致，在图片或表格之前的说明文字输出在上方（默认）
+    #[default]
+    Auto,
+    /// 图注、表注一律在上方，图表脚注仍在下方
+    Above,
+    /// 图注、表注与图表脚注一律在下方
+    Below,
+}
+
+/// 默认的文档页脚文字
+pub const DEFAULT_FOOTER: &str = "Generated by MinerU JSON to Markdown Converter";
+
+/// 控制转换输出的选项
+#[derive(Debug, Clone)]
+pub struct ConvertOptions {
+    /// 图片内联为 Base64 还是写入目录
+    pub images: ImageOutput,
+    /// 图片写入目录时，不超过该字节数的图片仍以 Base64 内联；`Some(0)` 与 `None` 都不内联任何图片，
+    /// `Some` 时统计两种方式的图片数。LaTeX 与 Org 输出无法内联图片，忽略此项
+    pub embed_threshold: Option<usize>,
+    /// 内联图片的大小上限（字节），超出时按 `oversize_policy` 处理；`None` 表示不限制。
+    /// 写入目录的图片不受影响
+    pub max_image_bytes: Option<usize>,
+    /// 内联图片超出 `max_image_bytes` 时的处理方式
+    pub oversize_policy: OversizePolicy,
+    /// 超出上限的图片缩小后重新编码为 JPEG 时的质量（1–100）
+    pub image_quality: u8,
+    /// 内联图片时把 SVG 直接写成 `<svg>` 标记，而不是 Base64 data URI；只影响 Styled 风格与 HTML 输出
+    pub inline_svg: bool,
+    /// 是否在每页末尾插入分页线
+    pub page_dividers: bool,
+    /// 连续的空白页（没有内容，或只有未输出的页眉页脚）只输出一条注明页码范围与“空白页”的分页线，
+    /// 而不是每页一条；需同时开启 `page_dividers`
+    pub collapse_empty_pages: bool,
+    /// 分页线上的页码文字；`None` 时 Styled 为 `第 {n} 页`，Gfm 为 `Page {n}`
+    pub page_label: Option<PageLabel>,
+    /// 分页线的样式；LaTeX、AsciiDoc、reStructuredText 与 Org 输出不受影响
+    pub divider_style: DividerStyle,
+    /// 是否在文档开头插入 `<style>` 样式块
+    pub style_header: bool,
+    /// 元素的样式写成内联的 `style` 属性还是类名；`CssMode::None` 时不输出样式块
+    pub css_mode: CssMode,
+    /// 文档末尾的署名文字（原样输出）；`None` 表示不输出页脚
+    pub footer: Option<String>,
+    /// 忽略 MinerU 识别出的表格 HTML，始终使用表格截图
+    pub tables_as_image: bool,
+    /// 表格 HTML 原样输出还是转换为管道表格；只影响 Markdown 输出
+    pub table_format: TableFormat,
+    /// 把有表格 HTML 的表格另外导出为 CSV 文件，表格下方加上指向该文件的链接；`None` 时不导出。
+    /// 纯文本输出不读取表格内容，不导出
+    pub table_export: Option<TableExport>,
+    /// 图片与表格的写法；只影响 Markdown 输出，`Pandoc` 时表格总是尝试转换为管道表格、
+    /// 行间公式优先使用 LaTeX
+    pub figure_style: FigureStyle,
+    /// 图注、表注输出在图表的上方还是下方
+    pub caption_position: CaptionPosition,
+    /// 行间公式截图的最大高度（像素）；`None` 时按原始尺寸显示，只限制不超过页面宽度
+    pub equation_max_height: Option<u32>,
+    /// 行间公式同时有截图和 LaTeX 时输出 `$$` 公式，而不是截图
+    pub prefer_equation_latex: bool,
+    /// 公式的分隔符；纯文本输出始终使用 `$…$`
+    pub math_delimiters: MathDelimiters,
+    /// Markdown 输出中行内公式的写法；其他输出格式忽略
+    pub inline_math_style: InlineMathStyle,
+    /// 不输出页眉
+    pub skip_headers: bool,
+    /// 不输出页面脚注
+    pub skip_footnotes: bool,
+    /// 不输出页脚
+    pub skip_footers: bool,
+    /// 连续超过三页相同的页眉（以及页脚）只输出一次
+    pub dedupe_headers: bool,
+    /// 整理标题中 OCR 留下的空白：合并连续空格，去掉标点之前与开头编号内部的空格（`3 . 2` → `3.2`）；
+    /// 正文、目录与锚点使用整理后的文字
+    pub normalize_headings: bool,
+    /// 开启 `normalize_headings` 时，全大写的拉丁字母标题改为标题大小写；
+    /// 不超过四个字母的缩写、罗马数字与中日韩文字保持原样
+    pub title_case_headings: bool,
+    /// 为标题补上 "1."、"1.1"、"1.1.1" 之类的编号，目录中的标题同样带有编号；
+    /// 已经以编号开头的标题、第一个一级标题之前的标题，以及摘要、参考文献等一级标题及其下属标题不编号
+    pub number_headings: bool,
+    /// 为图注、表注补上 "Figure N." / "Table N." 编号，并为每个图表生成 `fig-N` / `tab-N` 锚点；
+    /// 已带编号的说明文字沿用原文的编号，之后的图表接着它编号
+    pub number_figures: bool,
+    /// 在目录之后输出图表目录（List of Figures / List of Tables），需同时开启 `number_figures`
+    pub list_of_figures: bool,
+    /// 丢弃旋转 90° / 270° 的文本块（通常是水印或书脊文字）
+    pub drop_rotated_text: bool,
+    /// 合并行尾被连字符断开的英文单词（`transfor-` + `mation`）
+    pub dehyphenate: bool,
+    /// 规范化正文 span 中的空白：换行与换页变为空格，删除其他控制字符（制表符除外）与零宽字符，
+    /// 不换行空格等特殊空格变为普通空格。代码块、公式与表格不受影响
+    pub normalize_whitespace: bool,
+    /// 转义正文中会被当作 Markdown 标记的字符（`*`、`_`、`|`、行首的 `#` 等）；
+    /// 原文已含有意写入的 Markdown 时关闭。只影响 Markdown 输出
+    pub escape_markdown: bool,
+    /// 保留正文 span 中的 HTML 标签与字符实体（`<br>`、`<i>`、`&amp;`）；关闭时 HTML 输出与
+    /// 不转义 Markdown 时都会转义 `<`、`>`、`&`，避免识别出的 `</div>` 之类破坏文档结构。
+    /// 不影响公式与图注
+    pub allow_inline_html: bool,
+    /// 参考文献（`ref_text` 块及 "References" 等标题之后的正文）逐条输出，并为 DOI 和网址加上链接
+    pub format_references: bool,
+    /// 正文段落中的网址（`http(s)://`、`www.`）、邮箱与 DOI（`10.xxxx/…`）写成链接，
+    /// 排版时在行尾断开的网址先接回；不影响行内公式、代码块与带样式的文字
+    pub autolink: bool,
+    /// 在中日韩文字与拉丁字母、数字相接处补一个空格（"使用GPU加速" → "使用 GPU 加速"），
+    /// 作用于正文、标题、图表说明、列表项与脚注等文字；不影响公式与代码块
+    pub pangu_spacing: bool,
+    /// 在每个输出的正文块前插入注明页码、bbox、块类型与 index 的 HTML 注释，便于追溯识别结果
+    pub provenance: bool,
+    /// 识别分数（span 的 `score`）低于该值的正文文字高亮显示：Markdown 与 HTML 中为带分数提示的
+    /// `<mark>`，Gfm 风格为 `==…==`，AsciiDoc 为 `##…##`；纯文本、JSON、LaTeX 与 reStructuredText
+    /// 输出不高亮。低分的 span 计入 [`ConvertStats::low_confidence_spans`]，没有分数的 span 不受影响
+    pub min_confidence: Option<f64>,
+    /// 识别分数低于该值的文本 span 以 `[?]` 代替（同一行中相邻的只留一个），
+    /// 所有文字都低于该值的块整个不输出
+    pub drop_below: Option<f64>,
+    /// 按块的 index 字段恢复阅读顺序
+    pub reorder_blocks: bool,
+    /// 页面上的块都没有 index 时，是否按 bbox 推断分栏的阅读顺序（需开启 `reorder_blocks`）
+    pub column_order: ColumnOrder,
+    /// 页面末尾未以句末标点结束、下一页以小写字母或中日韩文字接续的段落合并为一段，
+    /// 分页线移到合并后的段落之后
+    pub merge_cross_page: bool,
+    /// 同一页中类型相同、bbox 交并比超过 0.8 且文字相似度超过 0.95 的块视为重复识别，
+    /// 只保留文字较长的一个并记录警告；少于 20 个字符的块不参与比较
+    pub dedupe_blocks: bool,
+    /// 同一页上连续、上下紧挨且前一块未以句末标点结束的 title 块视为折行的同一标题，
+    /// 合并为一个标题与一个目录条目
+    pub merge_titles: bool,
+    /// 连续三个以上、以相同项目符号或逐一递增的编号开头的文本块合并为一个列表，
+    /// 去掉开头的项目符号或编号
+    pub detect_lists: bool,
+    /// 左边距超出本栏页边距（最多文本块对齐的左边距）该比例页宽以上的文本块视为引文，如 `0.05`；
+    /// `None` 时只有 sub_type 为 quote 的文本块是引文
+    pub detect_quotes: Option<f64>,
+    /// 页面脚注的输出形式
+    pub footnote_style: FootnoteStyle,
+    /// 页面脚注紧接在同一栏中位于其正上方的段落之后，以小字输出；横跨两栏、上方不是段落
+    /// 或无法确定位置的脚注仍在页面底部输出
+    pub footnotes_inline: bool,
+    /// 索引块的输出形式
+    pub index_style: IndexStyle,
+    /// Markdown 标题锚点的生成方式
+    pub anchors: AnchorStyle,
+    /// 输出的 Markdown 风格
+    pub flavor: Flavor,
+    /// 输出 Markdown 还是完整的 HTML 文档
+    pub format: OutputFormat,
+    /// HTML 输出时在 `<head>` 中引入 MathJax 渲染公式
+    pub mathjax: bool,
+    /// LaTeX 输出时加上导言区与 `document` 环境，得到可直接编译的文档；否则只输出正文，
+    /// 供 `\input` 到已有模板中。正文中有中日韩文字时导言区改用 xeCJK，需以 xelatex 编译
+    pub latex_standalone: bool,
+    /// 纯文本输出时在标题前标出层级，如 `[2] Methods`
+    pub text_title_levels: bool,
+    /// 纯文本输出时以 `[figure: 图注]`、`[table: 表注]`、`[equation: LaTeX]` 代替图表和行间公式，
+    /// 否则直接跳过
+    pub text_placeholders: bool,
+    /// 图片缺失时返回 [`ConvertError::MissingImage`]，而不是记录警告后继续
+    pub fail_on_missing_images: bool,
+    /// 遇到未识别的块类型时返回 [`ConvertError::UnknownBlockType`]，而不是按普通文本输出
+    pub strict_blocks: bool,
+    /// 只转换选中的页面；页码、目录仍使用原始页码
+    pub pages: Option<PageSelection>,
+    /// 每个一级标题的章节末尾插入返回目录的链接，值为链接文字；`None` 表示不插入
+    pub back_to_top: Option<String>,
+    /// 合并多个输入文档时插入在文档之间的内容（原样输出）
+    pub chunk_separator: Option<String>,
+    /// 并行渲染页面的线程数；`None` 使用 rayon 默认值，`Some(1)` 在当前线程顺序渲染
+    pub threads: Option<usize>,
+    /// 每页渲染完成时调用，用于显示进度；库本身从不向终端输出
+    pub progress: Option<ProgressCallback>,
+}
+
+impl Default for ConvertOptions {
+    fn default() -> Self {
+        ConvertOptions {
+            images: ImageOutput::Embed,
+            embed_threshold: None,
+            max_image_bytes: None,
+            oversize_policy: OversizePolicy::Downscale,
+            image_quality: 85,
+            inline_svg: false,
+            page_dividers: true,
+            collapse_empty_pages: false,
+            page_label: None,
+            divider_style: DividerStyle::Full,
+            style_header: true,
+            css_mode: CssMode::Inline,
+            footer: Some(DEFAULT_FOOTER.to_string()),
+            tables_as_image: false,
+            table_format: TableFormat::Html,
+            table_export: None,
+            figure_style: FigureStyle::Html,
+            caption_position: CaptionPosition::Auto,
+            equation_max_height: None,
+            prefer_equation_latex: false,
+            math_delimiters: MathDelimiters::Dollars,
+            inline_math_style: InlineMathStyle::Delimiters,
+            skip_headers: false,
+            skip_footnotes: false,
+            skip_footers: false,
+            dedupe_headers: false,
+            normalize_headings: false,
+            title_case_headings: false,
+            number_headings: false,
+            number_figures: false,
+            list_of_figures: false,
+            drop_rotated_text: false,
+            dehyphenate: true,
+            normalize_whitespace: true,
+            escape_markdown: true,
+            allow_inline_html: false,
+            format_references: true,
+            autolink: false,
+            pangu_spacing: false,
+            provenance: false,
+            min_confidence: None,
+            drop_below: None,
+            reorder_blocks: true,
+            column_order: ColumnOrder::Auto,
+            merge_cross_page: false,
+            dedupe_blocks: false,
+            merge_titles: true,
+            detect_lists: false,
+            detect_quotes: None,
+            footnote_style: FootnoteStyle::Div,
+            footnotes_inline: false,
+            index_style: IndexStyle::Lines,
+            anchors: AnchorStyle::Explicit,
+            flavor: Flavor::Styled,
+            format: OutputFormat::Markdown,
+            mathjax: false,
+            latex_standalone: false,
+            text_title_levels: false,
+            text_placeholders: false,
+            fail_on_missing_images: false,
+            strict_blocks: false,
+            pages: None,
+            back_to_top: None,
+            chunk_separator: None,
+            threads: None,
+            progress: None,
+        }
+    }
+}
+
+// ==================== 样式与 HTML 文档 ====================
+
+/// 文档样式；Markdown 输出时作为开头的 `<style>` 块，HTML 输出时放进 `<head>`。
+/// `CssMode::Classes` 时其后再加上各个类的规则
+const STYLESHEET: &str = "  body { font-family: -apple-system, BlinkMacSystemFont, \"Segoe UI\", Roboto, \"Helvetica Neue\", Arial, sans-serif; }\n  img { border-radius: 4px; }\n  code { background: #f4f4f4; padding: 0.2em 0.4em; border-radius: 3px; font-size: 0.9em; }\n  pre { background: #f8f8f8; padding: 1em; border-radius: 6px; overflow-x: auto; }\n";
+
+/// MathJax 默认不识别 `$…$`，需在加载前配置行内公式分隔符；`\(…\)` 与 `\[…\]` 同时保留
+const MATHJAX_SCRIPT: &str = "<script>\n  window.MathJax = {\n    tex: { inlineMath: [['$', '$'], ['\\\\(', '\\\\)']], displayMath: [['$$', '$$'], ['\\\\[', '\\\\]']] }\n  };\n</script>\n<script id=\"MathJax-script\" async src=\"https://cdn.jsdelivr.net/npm/mathjax@3/es5/tex-chtml.js\"></script>\n";
+
+/// 包装为完整的 HTML5 文档，`title` 通常取第一个标题
+fn wrap_html_document(body: &str, title: Option<&str>, options: &ConvertOptions) -> String {
+    let mut html = html_document_head(title, options);
+    html.push_str(body);
+    if !html.ends_with('\n') {
+        html.push('\n');
+    }
+    html.push_str(HTML_DOCUMENT_TAIL);
+    html
+}
+
+const HTML_DOCUMENT_TAIL: &str = "</body>\n</html>\n";
+
+/// HTML 文档开头到 `<body>` 为止的部分
+fn html_document_head(title: Option<&str>, options: &ConvertOptions) -> String {
+    let title = title.unwrap_or("Document");
+
+    let mut html = String::from("<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n");
+    html.push_str("<meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\n");
+    html.push_str(&format!("<title>{}</title>\n", escape_html(title)));
+    if options.style_header && options.css_mode != CssMode::None {
+        html.push_str(&format!(
+            "<style>\n{}{}</style>\n",
+            STYLESHEET,
+            css::class_rules(options)
+        ));
+    }
+    if options.mathjax {
+        html.push_str(MATHJAX_SCRIPT);
+    }
+    html.push_str("</head>\n<body>\n");
+    html
+}
+
+// ==================== 目录生成 ====================
+
+fn generate_toc(toc_entries: &[TocEntry], options: &ConvertOptions, files: &AnchorFiles) -> String {
+    if toc_entries.is_empty() {
+        return String::new();
+    }
+    if options.format == OutputFormat::Html {
+        return generate_html_toc(toc_entries, files);
+    }
+    if options.format == OutputFormat::Asciidoc {
+        return generate_asciidoc_toc(toc_entries, files);
+    }
+    if options.format == OutputFormat::Rst {
+        return generate_rst_toc(toc_entries);
+    }
+
+    if options.flavor == Flavor::Obsidian {
+        return generate_obsidian_toc(toc_entries);
+    }
+
+    // GitHub 会去掉 HTML 元素的 id，返回目录的链接改用 `#top`
+    let mut toc = if github_anchors(options) {
+        String::new()
+    } else {
+        String::from("<div id=\"toc-top\"></div>\n\n")
+    };
+
+    for (entry, depth) in toc_entries.iter().zip(toc_depths(toc_entries)) {
+        toc.push_str(&format!(
+            "{}- [{}]({}) · {}\n",
+            "  ".repeat(depth),
+            toc_link_text(&entry.title, options),
+            anchor_href(&entry.anchor_id, files),
+            toc_page(entry)
+        ));
+    }
+
+    // 空行与后面的 <hr> 分隔，保证严格的 CommonMark 解析器能正确结束列表
+    toc.push('\n');
+    toc
+}
+
+/// AsciiDoc 目录：嵌套的 `*` 列表，条目为链接到 `[[id]]` 锚点的 `xref:` 宏；
+/// 不使用 AsciiDoc 自带的 `:toc:`，这样目录同样带有页码，拆分输出时也能链接到各文件
+fn generate_asciidoc_toc(toc_entries: &[TocEntry], files: &AnchorFiles) -> String {
+    let mut toc = String::new();
+    for (entry, depth) in toc_entries.iter().zip(toc_depths(toc_entries)) {
+        toc.push_str(&format!(
+            "{} xref:{}[{}] · {}\n",
+            "*".repeat(depth + 1),
+            anchor_href(&entry.anchor_id, files).trim_start_matches('#'),
+            macro_text(&entry.title),
+            escape_asciidoc(&toc_page(entry), false)
+        ));
+    }
+    toc.push('\n');
+    toc
+}
+
+/// reStructuredText 目录：嵌套的项目列表，条目为链接到 `.. _id:` 目标的 `:ref:` 角色；
+/// 目标名在整个文档集中有效，拆分输出时不需要文件名
+fn generate_rst_toc(toc_entries: &[TocEntry]) -> String {
+    let mut toc = String::new();
+    for (entry, depth) in toc_entries.iter().zip(toc_depths(toc_entries)) {
+        toc.push_str(&format!(
+            "{}- :ref:`{} <{}>` · {}\n\n",
+            "  ".repeat(depth),
+            rst::interpreted_text(&entry.title),
+            entry.anchor_id,
+            escape_rst(&toc_page(entry), false)
+        ));
+    }
+    toc
+}
+
+/// Obsidian 目录直接链接到标题文字 `[[#标题|标题]]`，不需要锚点
+fn generate_obsidian_toc(toc_entries: &[TocEntry]) -> String {
+    let mut toc = String::new();
+    for (entry, depth) in toc_entries.iter().zip(toc_depths(toc_entries)) {
+        toc.push_str(&format!(
+            "{}- {} · {}\n",
+            "  ".repeat(depth),
+            obsidian_heading_link(&entry.title),
+            toc_page(entry)
+        ));
+    }
+    toc.push('\n');
+    toc
+}
+
+/// 目录条目后的页码，有印刷页码时一并列出：`第 12 页 (原书页码 vii)`
+fn toc_page(entry: &TocEntry) -> String {
+    match &entry.printed_page {
+        Some(printed) => format!("第 {} 页 (原书页码 {})", entry.page_idx, printed),
+        None => format!("第 {} 页", entry.page_idx),
+    }
+}
+
+/// 二级条目嵌套在前一个一级条目之下，缩进深度不超过上一条目深度 + 1
+fn toc_depths(toc_entries: &[TocEntry]) -> Vec<usize> {
+    let mut depths: Vec<usize> = Vec::with_capacity(toc_entries.len());
+    for entry in toc_entries {
+        let wanted = entry.level.saturating_sub(1);
+        let depth = match depths.last() {
+            Some(prev) => wanted.min(prev + 1),
+            None => 0,
+        };
+        depths.push(depth);
+    }
+    depths
+}
+
+/// HTML 目录使用嵌套的 `<ul>`，子列表放在上一条目的 `<li>` 内
+fn generate_html_toc(toc_entries: &[TocEntry], files: &AnchorFiles) -> String {
+    let mut toc = String::from("<nav id=\"toc-top\">\n<ul>\n");
+    let mut current = 0;
+    for (i, (entry, depth)) in toc_entries.iter().zip(toc_depths(toc_entries)).enumerate() {
+        if i > 0 {
+            if depth > current {
+                toc.push_str("\n<ul>\n");
+            } else {
+                toc.push_str("</li>\n");
+                for _ in depth..current {
+                    toc.push_str("</ul>\n</li>\n");
+                }
+            }
+        }
+        current = depth;
+        toc.push_str(&format!(
+            "<li><a href=\"{}\">{}</a> · {}",
+            anchor_href(&entry.anchor_id, files),
+            escape_html(&entry.title),
+            escape_html(&toc_page(entry))
+        ));
+    }
+    toc.push_str("</li>\n");
+    for _ in 0..current {
+        toc.push_str("</ul>\n</li>\n");
+    }
+    toc.push_str("</ul>\n</nav>\n\n");
+    toc
+}
+
+// ==================== 分页线 ====================
+
+/// 页面末尾分页线上的页码
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Divider {
+    /// 从 1 开始
+    page: usize,
+    /// 页面上印刷的页码
+    printed_page: Option<String>,
+}
+
+/// `first` 到 `last` 各页末尾的分页线；除合并的空白页外两者相同。
+/// 原书页码附在页码标签之后，如 `第 12 页 (原书页码 vii)`；`blank` 时再注明为空白页
+fn generate_page_divider(
+    first: &Divider,
+    last: &Divider,
+    blank: bool,
+    options: &ConvertOptions,
+) -> String {
+    if options.format == OutputFormat::Latex {
+        return "\\clearpage\n\n".to_string();
+    }
+    let range = first.page != last.page;
+    let pages = if range {
+        format!("{}–{}", first.page, last.page)
+    } else {
+        first.page.to_string()
+    };
+    let mut label = match &options.page_label {
+        Some(label) => label.render_range(first.page, last.page),
+        None => match options.flavor {
+            Flavor::Styled => format!("第 {} 页", pages),
+            Flavor::Gfm | Flavor::Obsidian if range => format!("Pages {}", pages),
+            Flavor::Gfm | Flavor::Obsidian => format!("Page {}", pages),
+        },
+    };
+    let printed_page = match (&first.printed_page, &last.printed_page) {
+        (Some(a), Some(b)) if range && a != b => Some(format!("{}–{}", a, b)),
+        (Some(a), Some(_)) => Some(a.clone()),
+        (printed, _) if !range => printed.clone(),
+        _ => None,
+    };
+    if let Some(printed) = &printed_page {
+        label.push_str(&match options.flavor {
+            Flavor::Styled => format!(" (原书页码 {})", printed),
+            Flavor::Gfm | Flavor::Obsidian => format!(" (printed page {})", printed),
+        });
+    }
+    if blank {
+        label.push_str(match options.flavor {
+            Flavor::Styled => "（空白页）",
+            Flavor::Gfm | Flavor::Obsidian => " (intentionally blank)",
+        });
+    }
+    if options.format == OutputFormat::Asciidoc {
+        return format!("\n'''\n\n{}\n\n", escape_asciidoc(&label, true));
+    }
+    // 分节线不能出现在章节的开头或结尾，改为带 `page-divider` 类的容器
+    if options.format == OutputFormat::Rst {
+        return format!(
+            "\n{}\n",
+            rst::directive("container", "page-divider", &[], &escape_rst(&label, true))
+        );
+    }
+    // 页码标签写在分隔线之后的注释中，导出后不显示
+    if options.format == OutputFormat::Org {
+        return format!("\n-----\n{}\n", org::comment(&label));
+    }
+    match options.divider_style {
+        DividerStyle::Full => match options.flavor {
+            Flavor::Styled => {
+                let line = style_attr("mineru-page-divider-line", options);
+                format!(
+                    "\n<div{}>\n  <div{}></div>\n  <span{}>{}</span>\n  <div{}></div>\n</div>\n\n",
+                    style_attr("mineru-page-divider", options),
+                    line,
+                    style_attr("mineru-page-divider-label", options),
+                    escape_html(&label),
+                    line
+                )
+            }
+            Flavor::Gfm | Flavor::Obsidian => format!("\n---\n\n{}\n\n", label),
+        },
+        DividerStyle::Compact => match options.flavor {
+            Flavor::Styled => format!(
+                "\n<div{}><sup>{}</sup></div>\n\n",
+                style_attr("mineru-page-divider-compact", options),
+                escape_html(&label)
+            ),
+            // Gfm 会过滤 style 属性，右对齐只能用 `align`
+            Flavor::Gfm | Flavor::Obsidian => format!(
+                "\n---\n\n<p align=\"right\"><sup>{}</sup></p>\n\n",
+                escape_html(&label)
+            ),
+        },
+        DividerStyle::Margin => {
+            // 注释的内容与渲染风格、页码标签无关，便于其他工具按固定格式查找
+            let mut note = format!("{} {}", if range { "pages" } else { "page" }, pages);
+            if let Some(printed) = &printed_page {
+                note.push_str(&format!(" printed={}", printed));
+            }
+            if blank {
+                note.push_str(" blank");
+            }
+            format!("\n<!-- {} -->\n\n", note.replace("--", "- -"))
+        }
+    }
+}
+
+// ==================== 锚点去重 ====================
+
+/// 单页的渲染结果，锚点尚未跨页去重
+struct RenderedPage {
+    page_idx: usize,
+    document: usize,
+    html: String,
+    toc_entries: Vec<TocEntry>,
+    warnings: Vec<Warning>,
+    figures: Vec<FigureEntry>,
+    stats: ConvertStats,
+    /// 页眉之后正文的起始位置
+    body_start: usize,
+    /// 页面末尾的分页线
+    divider: Option<Divider>,
+    /// 页面内容为空（不计已并入上一页的续文）；隐藏的页眉页脚、脚注不算内容
+    blank: bool,
+}
+
+/// 拼接页面时逐页求出分页线；开启 `collapse_empty_pages` 时一段连续的空白页只在最后一页之后
+/// 输出一条注明页码范围的分页线。整篇拼接与流式输出共用，保证两者结果相同
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct DividerWriter {
+    /// 当前这段空白页第一页的分页线
+    blank_start: Option<Divider>,
+}
+
+impl DividerWriter {
+    /// 一页末尾的分页线；`page` 与 `next` 为本页与下一页的 `(document, blank)`，
+    /// 最后一页时 `next` 为 `None`。空白页不跨输入文档合并
+    fn divider(
+        &mut self,
+        divider: Option<&Divider>,
+        page: (usize, bool),
+        next: Option<(usize, bool)>,
+        options: &ConvertOptions,
+    ) -> Option<String> {
+        let divider = divider?;
+        let (document, blank) = page;
+        if !(options.collapse_empty_pages && blank) {
+            return Some(generate_page_divider(divider, divider, false, options));
+        }
+        self.blank_start.get_or_insert_with(|| divider.clone());
+        if next == Some((document, true)) {
+            return None;
+        }
+        let first = self.blank_start.take()?;
+        Some(generate_page_divider(&first, divider, true, options))
+    }
+}
+
+/// 按页面顺序为重复的锚点追加 `-2`、`-3`……，并同步改写页面中的 `<a id>`、标题的 `id`
+/// 或 AsciiDoc 的 `[[id]]`；
+/// GitHub 风格的锚点与 GitHub 一样追加 `-1`、`-2`……，页面中没有需要改写的标记
+///
+/// 在页面渲染完成后按顺序执行，结果与渲染线程数无关；分批渲染时各批沿用同一个 `anchors`。
+fn dedupe_anchors(
+    pages: &mut [RenderedPage],
+    anchors: &mut AnchorRegistry,
+    options: &ConvertOptions,
+) {
+    let github = github_anchors(options);
+    for page in pages {
+        // 同一页内的锚点按出现顺序逐个查找，保证同名锚点各自改写
+        let mut cursor = 0;
+        for entry in &mut page.toc_entries {
+            if github {
+                entry.anchor_id = anchors.issue_github(entry.anchor_id.clone());
+                continue;
+            }
+            let unique = anchors.issue(entry.anchor_id.clone());
+            let tag = anchor_marker(&entry.anchor_id, options);
+            let Some(offset) = page.html[cursor..].find(&tag) else {
+                entry.anchor_id = unique;
+                continue;
+            };
+            let start = cursor + offset;
+            let replacement = anchor_marker(&unique, options);
+            page.html
+                .replace_range(start..start + tag.len(), &replacement);
+            cursor = start + replacement.len();
+            entry.anchor_id = unique;
+        }
+    }
+}
+
+// ==================== 主转换函数 ====================
+
+/// 参与转换的一个输入文档；多个文档按顺序合并为一篇 Markdown
+#[derive(Debug, Clone, Copy)]
+pub struct Document<'a> {
+    pub layout: &'a LayoutJson,
+    /// 解析该文档 `image_path` 时使用的根目录，通常是 JSON 文件所在目录
+    pub base_path: &'a Path,
+}
+
+/// 选中的一页及其在合并后文档中的位置
+struct SelectedPage<'a> {
+    page: &'a PageInfo,
+    /// 加上前面各文档页数后的页码（从 0 开始）
+    page_idx: usize,
+    document: usize,
+    base_path: &'a Path,
+}
+
+/// 各页 page_idx 须不超过 [`types::MAX_PAGE_IDX`]；解析得到的页面总是如此，直接构造的页面可能不是
+fn page_count(layout_json: &LayoutJson) -> Result<usize, ConvertError> {
+    layout_json.pdf_info.iter().try_fold(0, |count, page| {
+        if page.page_idx > types::MAX_PAGE_IDX {
+            return Err(ConvertError::PageIndexOutOfRange {
+                page_idx: page.page_idx,
+            });
+        }
+        Ok(count.max(page.page_idx + 1))
+    })
+}
+
+fn select_pages<'a>(
+    documents: &[Document<'a>],
+    options: &ConvertOptions,
+) -> Result<Vec<SelectedPage<'a>>, ConvertError> {
+    // 后一个文档的页码接在前一个文档之后
+    let mut pages = Vec::new();
+    let mut offset: usize = 0;
+    for (document, doc) in documents.iter().enumerate() {
+        let count = page_count(doc.layout)?;
+        let end = offset
+            .checked_add(count)
+            .ok_or(ConvertError::PageIndexOutOfRange { page_idx: count })?;
+        pages.extend(doc.layout.pdf_info.iter().map(|page| SelectedPage {
+            page,
+            page_idx: offset + page.page_idx,
+            document,
+            base_path: doc.base_path,
+        }));
+        offset = end;
+    }
+
+    let Some(selection) = &options.pages else {
+        return Ok(pages);
+    };
+
+    if selection.out_of_range(offset).is_some() {
+        return Err(ConvertError::PageOutOfRange {
+            selection: selection.clone(),
+            page_count: offset,
+        });
+    }
+
+    pages.retain(|page| selection.contains(page.page_idx));
+    if pages.is_empty() {
+        return Err(ConvertError::EmptyPageSelection {
+            selection: selection.clone(),
+        });
+    }
+    Ok(pages)
+}
+
+/// 将解析后的 layout.json 转换为 Markdown 文本
+///
+/// `base_path` 为解析 `image_path` 时使用的根目录，通常是 JSON 文件所在目录。
+/// 缺失的图片、未知块类型等非致命问题记录在 [`ConvertOutput::warnings`] 中。
+pub fn convert_layout_to_markdown(
+    layout_json: &LayoutJson,
+    base_path: &Path,
+    options: &ConvertOptions,
+) -> Result<ConvertOutput, ConvertError> {
+    convert_documents(
+        &[Document {
+            layout: layout_json,
+            base_path,
+        }],
+        options,
+    )
+}
+
+/// 与 [`convert_layout_to_markdown`] 相同，但标题、正文、图片、表格与行间公式交给 `renderer` 渲染
+pub fn convert_layout_with_renderer(
+    layout_json: &LayoutJson,
+    base_path: &Path,
+    options: &ConvertOptions,
+    renderer: &dyn BlockRenderer,
+) -> Result<ConvertOutput, ConvertError> {
+    convert_documents_with_renderer(
+        &[Document {
+            layout: layout_json,
+            base_path,
+        }],
+        options,
+        renderer,
+    )
+}
+
+/// 渲染完成、锚点已去重的全部页面
+struct RenderedDocument {
+    pages: Vec<RenderedPage>,
+    toc_entries: Vec<TocEntry>,
+    figures: Vec<FigureEntry>,
+    warnings: Vec<Warning>,
+    /// 各页统计之和；输出大小由调用方在拼接完成后补上
+    stats: ConvertStats,
+}
+
+/// HTML 输出沿用 Styled 风格的块渲染，脚注保持为 <div>；
+/// 纯文本输出不读取图片，也不输出样式块、分页线和页脚；
+/// LaTeX 输出不含样式块与页脚，章节与图表编号交给 `\section`、`\caption`，行间公式总是优先使用 LaTeX，
+/// 与 Org 输出一样不内联任何图片；
+/// JSON 输出只有内容条目，不含任何页面装饰；
+/// Pandoc 写法的 Markdown 输出中表格转换为管道表格、行间公式优先使用 LaTeX，均为 Pandoc 能识别的结构
+fn output_options(options: &ConvertOptions) -> Cow<'_, ConvertOptions> {
+    match options.format {
+        OutputFormat::Markdown if options.figure_style == FigureStyle::Pandoc => {
+            Cow::Owned(ConvertOptions {
+                table_format: TableFormat::Markdown,
+                prefer_equation_latex: true,
+                ..options.clone()
+            })
+        }
+        OutputFormat::Markdown => Cow::Borrowed(options),
+        OutputFormat::Html => Cow::Owned(ConvertOptions {
+            flavor: Flavor::Styled,
+            footnote_style: FootnoteStyle::Div,
+            ..options.clone()
+        }),
+        OutputFormat::Text => Cow::Owned(ConvertOptions {
+            images: ImageOutput::Embed,
+            style_header: false,
+            footer: None,
+            page_dividers: false,
+            fail_on_missing_images: false,
+            ..options.clone()
+        }),
+        OutputFormat::Latex => Cow::Owned(ConvertOptions {
+            flavor: Flavor::Gfm,
+            footnote_style: FootnoteStyle::Div,
+            table_format: TableFormat::Html,
+            style_header: false,
+            footer: None,
+            number_headings: false,
+            number_figures: false,
+            list_of_figures: false,
+            prefer_equation_latex: true,
+            embed_threshold: None,
+            ..options.clone()
+        }),
+        // AsciiDoc 为图表自动编号，原文的图注已带有编号
+        OutputFormat::Asciidoc => Cow::Owned(ConvertOptions {
+            flavor: Flavor::Gfm,
+            footnote_style: FootnoteStyle::Div,
+            table_format: TableFormat::Html,
+            anchors: AnchorStyle::Explicit,
+            allow_inline_html: false,
+            style_header: false,
+            footer: None,
+            number_figures: false,
+            list_of_figures: false,
+            back_to_top: None,
+            ..options.clone()
+        }),
+        // 图表编号与 AsciiDoc 相同地关闭，说明文字保留原文的编号
+        OutputFormat::Rst => Cow::Owned(ConvertOptions {
+            flavor: Flavor::Gfm,
+            footnote_style: FootnoteStyle::Div,
+            table_format: TableFormat::Html,
+            anchors: AnchorStyle::Explicit,
+            allow_inline_html: false,
+            style_header: false,
+            footer: None,
+            number_figures: false,
+            list_of_figures: false,
+            back_to_top: None,
+            ..options.clone()
+        }),
+        // 目录由 Org 导出时生成；行间公式与 LaTeX 输出相同地优先使用 LaTeX
+        OutputFormat::Org => Cow::Owned(ConvertOptions {
+            flavor: Flavor::Gfm,
+            footnote_style: FootnoteStyle::Div,
+            table_format: TableFormat::Html,
+            anchors: AnchorStyle::Explicit,
+            allow_inline_html: false,
+            style_header: false,
+            footer: None,
+            number_figures: false,
+            list_of_figures: false,
+            back_to_top: None,
+            prefer_equation_latex: true,
+            embed_threshold: None,
+            ..options.clone()
+        }),
+        OutputFormat::Json => Cow::Owned(ConvertOptions {
+            footnote_style: FootnoteStyle::Div,
+            style_header: false,
+            footer: None,
+            page_dividers: false,
+            list_of_figures: false,
+            back_to_top: None,
+            chunk_separator: None,
+            ..options.clone()
+        }),
+    }
+}
+
+/// 渲染前按文档顺序求出的跨页状态；有了它，任意一段页面都可以独立渲染
+struct PagePlan<'a> {
+    pages: Vec<SelectedPage<'a>>,
+    images: ImageResolver,
+    hidden_margins: RepeatedMargins,
+    figure_starts: Vec<FigureCounters>,
+    reference_starts: Vec<bool>,
+    heading_starts: Vec<HeadingNumbers>,
+    section_starts: Vec<SectionDepths>,
+    continuations: Vec<Option<Block>>,
+    /// 按 `threads` 创建的线程池；顺序渲染或使用全局线程池时为 `None`
+    pool: Option<ThreadPool>,
+}
+
+impl<'a> PagePlan<'a> {
+    /// `images` 为 `None` 时按选项解析图片；[`ImageResolver::outline`] 不读取、不写出任何图片，
+    /// 渲染结果只用于收集目录与图表目录
+    fn new(
+        documents: &[Document<'a>],
+        options: &ConvertOptions,
+        images: Option<ImageResolver>,
+    ) -> Result<Self, ConvertError> {
+        if matches!(options.images, ImageOutput::Embed) {
+            match options.format {
+                OutputFormat::Latex => {
+                    return Err(ConvertError::EmbeddedImages { format: "latex" });
+                }
+                OutputFormat::Org => return Err(ConvertError::EmbeddedImages { format: "org" }),
+                _ => {}
+            }
+        }
+        let pages = select_pages(documents, options)?;
+        let images = images.unwrap_or_else(|| {
+            let page_sources: Vec<(&Path, &PageInfo)> = pages
+                .iter()
+                .map(|page| (page.base_path, page.page))
+                .collect();
+            ImageResolver::new(options, &page_sources)
+        });
+
+        let hidden_margins = if options.dedupe_headers {
+            let page_infos: Vec<&PageInfo> = pages.iter().map(|page| page.page).collect();
+            repeated_margins(&page_infos, options)
+        } else {
+            RepeatedMargins::default()
+        };
+
+        // 图表编号需要连续，先按顺序累加出每页的起始编号
+        let figure_starts: Vec<FigureCounters> = if options.number_figures {
+            let mut total = FigureCounters::default();
+            pages
+                .iter()
+                .map(|page| {
+                    let start = total;
+                    figures::count_numbered(page.page, options, &mut total);
+                    start
+                })
+                .collect()
+        } else {
+            vec![FigureCounters::default(); pages.len()]
+        };
+
+        // 参考文献一节可能跨页，先按顺序求出每页开头是否处在该节中
+        let reference_starts: Vec<bool> = if options.format_references {
+            let mut inside = false;
+            pages
+                .iter()
+                .map(|selected| {
+                    let start = inside;
+                    inside = references::ends_inside(
+                        &ordered_page(selected.page, options),
+                        inside,
+                        options,
+                    );
+                    start
+                })
+                .collect()
+        } else {
+            vec![false; pages.len()]
+        };
+
+        // 标题编号在页面之间连续，同样先按顺序求出每页开头的编号
+        let heading_starts: Vec<HeadingNumbers> = if options.number_headings {
+            let mut numbers = HeadingNumbers::default();
+            pages
+                .iter()
+                .map(|selected| {
+                    let start = numbers;
+                    advance_heading_numbers(
+                        &ordered_page(selected.page, options),
+                        selected.page_idx,
+                        &mut numbers,
+                        options,
+                    );
+                    start
+                })
+                .collect()
+        } else {
+            vec![HeadingNumbers::default(); pages.len()]
+        };
+
+        // reStructuredText 的章节层级取决于前面的标题，也按顺序求出每页开头的状态
+        let section_starts: Vec<SectionDepths> = if options.format == OutputFormat::Rst {
+            let mut depths = SectionDepths::default();
+            pages
+                .iter()
+                .map(|selected| {
+                    let start = depths;
+                    advance_section_depths(
+                        &ordered_page(selected.page, options),
+                        selected.page_idx,
+                        &mut depths,
+                        options,
+                    );
+                    start
+                })
+                .collect()
+        } else {
+            vec![SectionDepths::default(); pages.len()]
+        };
+
+        // 跨页的段落需要看到下一页的开头，先按顺序找出每页末尾要并入的续文；
+        // 只在同一文档的相邻页之间合并
+        let mut continuations: Vec<Option<Block>> = vec![None; pages.len()];
+        if options.merge_cross_page {
+            let mut prev: Option<Cow<PageInfo>> = None;
+            for (i, selected) in pages.iter().enumerate() {
+                let page = ordered_page(selected.page, options);
+                if let Some(prev) = &prev
+                    && pages[i - 1].document == selected.document
+                    && pages[i - 1].page_idx + 1 == selected.page_idx
+                    // 整页只有一段续文时，它已并入上一页，不能再接续到下一页
+                    && !(i > 1 && continuations[i - 2].is_some() && prev.para_blocks.len() == 1)
+                    && merge::continues_onto(prev, &page, options.dehyphenate)
+                {
+                    continuations[i - 1] = page.para_blocks.first().cloned();
+                }
+                prev = Some(page);
+            }
+        }
+
+        let pool = match options.threads {
+            Some(threads) if threads != 1 => {
+                Some(ThreadPoolBuilder::new().num_threads(threads).build()?)
+            }
+            _ => None,
+        };
+
+        Ok(PagePlan {
+            pages,
+            images,
+            hidden_margins,
+            figure_starts,
+            reference_starts,
+            heading_starts,
+            section_starts,
+            continuations,
+            pool,
+        })
+    }
+
+    fn len(&self) -> usize {
+        self.pages.len()
+    }
+
+    fn analyze_page(
+        &self,
+        i: usize,
+        options: &ConvertOptions,
+        renderer: Option<&dyn BlockRenderer>,
+    ) -> Result<PageNodes, ConvertError> {
+        let selected = &self.pages[i];
+        let mut page = ordered_page(selected.page, options);
+        let mut state = RenderState::new(&self.images, selected.base_path);
+        confidence::record_low_scores(selected.page, selected.page_idx, options, &mut state.stats);
+        if i > 0 && self.continuations[i - 1].is_some() {
+            merge::remove_continuation(page.to_mut());
+            state.continued = true;
+        }
+        if let Some(continuation) = &self.continuations[i] {
+            merge::append_continuation(page.to_mut(), continuation);
+        }
+        state.counters = self.figure_starts[i];
+        state.in_references = self.reference_starts[i];
+        state.heading_numbers = self.heading_starts[i];
+        state.section_depths = self.section_starts[i];
+        analyze_page(
+            &page,
+            selected.page_idx,
+            selected.document,
+            &mut state,
+            options,
+            self.hidden_margins.headers.contains(&i),
+            self.hidden_margins.footers.contains(&i),
+            renderer,
+        )
+    }
+
+    /// 一页完成时报告进度；`stats` 为该页的统计
+    fn report_progress(&self, i: usize, stats: &ConvertStats, options: &ConvertOptions) {
+        if let Some(progress) = &options.progress {
+            progress.report(PageProgress {
+                page_idx: self.pages[i].page_idx,
+                total: self.pages.len(),
+                stats,
+            });
+        }
+    }
+
+    /// 对 `range` 中的每一页执行 `f`；各页互不依赖，可并行处理，结果按页面顺序返回
+    fn map_pages<T: Send>(
+        &self,
+        range: Range<usize>,
+        options: &ConvertOptions,
+        f: impl Fn(usize) -> Result<T, ConvertError> + Sync + Send,
+    ) -> Result<Vec<T>, ConvertError> {
+        let results: Vec<Result<T, ConvertError>> = match (options.threads, &self.pool) {
+            (Some(1), _) => range.map(f).collect(),
+            (_, Some(pool)) => pool.install(|| range.into_par_iter().map(f).collect()),
+            _ => range.into_par_iter().map(f).collect(),
+        };
+        // 多个页面出错时总是报告页码最小的那个
+        results.into_iter().collect()
+    }
+
+    /// 分析 `range` 中的页面
+    fn analyze(
+        &self,
+        range: Range<usize>,
+        options: &ConvertOptions,
+        renderer: Option<&dyn BlockRenderer>,
+    ) -> Result<Vec<PageNodes>, ConvertError> {
+        self.map_pages(range, options, |i| {
+            let nodes = self.analyze_page(i, options, renderer)?;
+            self.report_progress(i, &nodes.stats, options);
+            Ok(nodes)
+        })
+    }
+
+    /// 分析并写出 `range` 中的页面；锚点尚未去重
+    fn render(
+        &self,
+        range: Range<usize>,
+        options: &ConvertOptions,
+        renderer: Option<&dyn BlockRenderer>,
+    ) -> Result<Vec<RenderedPage>, ConvertError> {
+        self.map_pages(range, options, |i| {
+            let nodes = self.analyze_page(i, options, renderer)?;
+            let page = write_page(&nodes, options);
+            // 输出了内容的块在写出时才计数，写出之后报告的块数才完整
+            self.report_progress(i, &page.stats, options);
+            Ok(page)
+        })
+    }
+
+    /// 所有页面渲染完成后，补上按整篇文档统计的图片数量
+    fn record_images(&self, stats: &mut ConvertStats) {
+        stats.images_deduplicated = self.images.deduplicated();
+        self.images.record_oversize(stats);
+        self.images.record_threshold(stats);
+        self.images.record_relocated(stats);
+        stats.timings.images = self.images.busy();
+    }
+}
+
+fn render_documents(
+    documents: &[Document],
+    options: &ConvertOptions,
+    renderer: Option<&dyn BlockRenderer>,
+) -> Result<RenderedDocument, ConvertError> {
+    let started = Instant::now();
+    let plan = PagePlan::new(documents, options, None)?;
+    let pages = plan.render(0..plan.len(), options, renderer)?;
+    let mut image_stats = ConvertStats::default();
+    plan.record_images(&mut image_stats);
+    image_stats.timings.render = started.elapsed();
+    Ok(RenderedDocument::new(pages, &image_stats, options))
+}
+
+impl RenderedDocument {
+    /// 为各页去重锚点，汇总目录条目、图表、警告与统计；`image_stats` 为按整篇文档统计的图片数量
+    fn new(
+        mut pages: Vec<RenderedPage>,
+        image_stats: &ConvertStats,
+        options: &ConvertOptions,
+    ) -> Self {
+        dedupe_anchors(&mut pages, &mut AnchorRegistry::default(), options);
+        let toc_entries: Vec<TocEntry> = pages
+            .iter()
+            .flat_map(|page| page.toc_entries.iter().cloned())
+            .collect();
+        let figures: Vec<FigureEntry> = pages
+            .iter()
+            .flat_map(|page| page.figures.iter().cloned())
+            .collect();
+        let mut warnings: Vec<Warning> = pages
+            .iter_mut()
+            .flat_map(|page| page.warnings.drain(..))
+            .collect();
+        if pages.is_empty() {
+            warnings.push(Warning::EmptyDocument);
+        }
+        let mut stats = ConvertStats::default();
+        for page in &pages {
+            stats.merge(&page.stats);
+        }
+        stats.merge(image_stats);
+        RenderedDocument {
+            pages,
+            toc_entries,
+            figures,
+            warnings,
+            stats,
+        }
+    }
+}
+
+/// 按选项恢复阅读顺序、规范化空白、去掉低分文字后的页面
+fn ordered_page<'a>(page: &'a PageInfo, options: &ConvertOptions) -> Cow<'a, PageInfo> {
+    let page = if options.reorder_blocks {
+        reading_order(page, options.column_order)
+    } else {
+        Cow::Borrowed(page)
+    };
+    let page = if options.normalize_whitespace {
+        text::normalize_page_whitespace(page)
+    } else {
+        page
+    };
+    match options.drop_below {
+        Some(threshold) => confidence::drop_low_confidence(page, threshold),
+        None => page,
+    }
+}
+
+/// 文档头部样式；HTML 输出时放进 <head>
+fn style_block(options: &ConvertOptions) -> String {
+    if options.style_header
+        && options.css_mode != CssMode::None
+        && options.flavor == Flavor::Styled
+        && options.format == OutputFormat::Markdown
+    {
+        format!(
+            "<style>\n{}{}</style>\n\n",
+            STYLESHEET,
+            css::class_rules(options)
+        )
+    } else {
+        String::new()
+    }
+}
+
+/// 目录与图表目录；`files` 非空时链接指向锚点所在的拆分文件
+fn navigation(
+    toc_entries: &[TocEntry],
+    figures: &[FigureEntry],
+    options: &ConvertOptions,
+    files: &AnchorFiles,
+) -> String {
+    if matches!(
+        options.format,
+        OutputFormat::Text | OutputFormat::Latex | OutputFormat::Json | OutputFormat::Org
+    ) {
+        return String::new();
+    }
+    let mut nav = generate_toc(toc_entries, options, files);
+    if options.number_figures && options.list_of_figures {
+        nav.push_str(&generate_figure_lists(figures, options, files));
+    }
+    nav
+}
+
+fn section_rule(options: &ConvertOptions) -> String {
+    if options.format == OutputFormat::Asciidoc {
+        return "'''\n\n".to_string();
+    }
+    // 目录之后紧接章节标题时分节线不合法，不加分隔
+    if options.format == OutputFormat::Rst {
+        return String::new();
+    }
+    match options.flavor {
+        Flavor::Styled => format!("<hr{} />\n\n", style_attr("mineru-section-rule", options)),
+        Flavor::Gfm | Flavor::Obsidian => "---\n\n".to_string(),
+    }
+}
+
+/// 依次拼接各页正文，不同输入文档之间可插入分隔；同时返回每页内容在结果中的起始位置
+fn assemble_pages(pages: &[RenderedPage], options: &ConvertOptions) -> (String, Vec<usize>) {
+    let mut body = String::new();
+    let mut starts = Vec::with_capacity(pages.len());
+    let mut prev_document = None;
+    let mut dividers = DividerWriter::default();
+    for (i, page) in pages.iter().enumerate() {
+        if let Some(separator) = &options.chunk_separator
+            && prev_document.is_some_and(|prev| prev != page.document)
+        {
+            body.push_str(&format!("{}\n\n", separator));
+        }
+        prev_document = Some(page.document);
+        starts.push(body.len());
+        body.push_str(&page.html);
+        let next = pages.get(i + 1).map(|next| (next.document, next.blank));
+        if let Some(divider) = dividers.divider(
+            page.divider.as_ref(),
+            (page.document, page.blank),
+            next,
+            options,
+        ) {
+            body.push_str(&divider);
+        } else if !body.is_empty() && !body.ends_with("\n\n") {
+            // 没有分页线时至少保留一个空行，避免相邻两页的段落连在一起
+            let missing = if body.ends_with('\n') { "\n" } else { "\n\n" };
+            body.push_str(missing);
+        }
+    }
+    (body, starts)
+}
+
+// ==================== 返回目录 ====================
+
+/// 返回目录的链接；`toc_file` 为目录所在的文件，不拆分输出时为空。
+/// 纯文本输出没有目录、Obsidian 风格的目录没有锚点，返回 `None`。
+/// GitHub 风格锚点的目录前没有锚点，链接到浏览器约定的 `#top`（文档开头）
+fn back_to_top_link(options: &ConvertOptions, toc_file: &str) -> Option<String> {
+    let text = options.back_to_top.as_deref()?;
+    let target = if github_anchors(options) {
+        "top"
+    } else {
+        "toc-top"
+    };
+    let href = format!("{}#{}", toc_file, target);
+    match (options.format, options.flavor) {
+        (
+            OutputFormat::Text
+            | OutputFormat::Latex
+            | OutputFormat::Json
+            | OutputFormat::Asciidoc
+            | OutputFormat::Rst
+            | OutputFormat::Org,
+            _,
+        )
+        | (OutputFormat::Markdown, Flavor::Obsidian) => None,
+        (OutputFormat::Html, _) | (OutputFormat::Markdown, Flavor::Styled) => Some(format!(
+            "<div{}><a href=\"{}\">{}</a></div>\n\n",
+            style_attr("mineru-back-to-top", options),
+            href,
+            escape_html(text)
+        )),
+        (OutputFormat::Markdown, Flavor::Gfm) => {
+            Some(format!("[{}]({})\n\n", escape_link_text(text), href))
+        }
+    }
+}
+
+/// 每个一级标题章节的结束位置（在拼接后的正文中），即插入返回目录链接的位置
+///
+/// 章节在下一个一级标题处结束；下一个标题之前本页除页眉外没有其他内容时，
+/// 链接放在上一页末尾、分页线之前。
+/// 最后一个章节在最后一页的内容之后结束，链接因此总在页脚之前。
+fn back_to_top_positions(
+    rendered: &RenderedDocument,
+    page_starts: &[usize],
+    options: &ConvertOptions,
+) -> Vec<usize> {
+    let page_end = |i: usize| page_starts[i] + rendered.pages[i].html.len();
+    let mut headings = Vec::new();
+    for (i, page) in rendered.pages.iter().enumerate() {
+        let positions = heading_positions(&page.html, &page.toc_entries, options);
+        for (entry, position) in page.toc_entries.iter().zip(positions) {
+            if entry.level <= 1
+                && let Some(position) = position
+            {
+                headings.push((i, position));
+            }
+        }
+    }
+
+    let mut positions = Vec::with_capacity(headings.len());
+    for pair in headings.windows(2) {
+        let (page, position) = pair[1];
+        let rendered_page = &rendered.pages[page];
+        let before = rendered_page
+            .html
+            .get(rendered_page.body_start..position)
+            .unwrap_or_default();
+        if before.trim().is_empty() && page > 0 {
+            positions.push(page_end(page - 1));
+        } else {
+            positions.push(page_starts[page] + position);
+        }
+    }
+    if !headings.is_empty() {
+        positions.push(page_end(rendered.pages.len() - 1));
+    }
+    positions
+}
+
+/// 取出正文中的一段，并在其中 `(start, end]` 范围内的各位置插入返回目录的链接
+fn insert_back_to_top(body: &str, range: Range<usize>, positions: &[usize], link: &str) -> String {
+    insert_back_to_top_tracked(body, range, positions, link, &mut [])
+}
+
+/// 与 [`insert_back_to_top`] 相同，同时把 `tracked` 中落在这一段里的正文位置改写为结果中的位置；
+/// 与链接位置相同的位置在链接之后
+fn insert_back_to_top_tracked(
+    body: &str,
+    range: Range<usize>,
+    positions: &[usize],
+    link: &str,
+    tracked: &mut [Option<usize>],
+) -> String {
+    let mut out = String::with_capacity(range.len());
+    let mut cursor = range.start;
+    // 按改写前的位置判断，已改写的位置不会再落进后面的区间
+    let original = tracked.to_vec();
+    let mut relocate = |out: &str, from: usize, to: usize| {
+        for (position, &before) in tracked.iter_mut().zip(&original) {
+            if let Some(before) = before.filter(|before| (from..to).contains(before)) {
+                *position = Some(out.len() + before - from);
+            }
+        }
+    };
+    for &position in positions
+        .iter()
+        .filter(|&&position| position > range.start && position <= range.end)
+    {
+        relocate(&out, cursor, position);
+        out.push_str(&body[cursor..position]);
+        // 链接前至少空一行，避免并入上一段
+        if !out.ends_with("\n\n") {
+            out.push_str(if out.ends_with('\n') { "\n" } else { "\n\n" });
+        }
+        out.push_str(link);
+        cursor = position;
+    }
+    relocate(&out, cursor, range.end + 1);
+    out.push_str(&body[cursor..range.end]);
+    out
+}
+
+/// 各目录条目对应的标题在拼接后的正文中的位置，顺序与 [`RenderedDocument::toc_entries`] 一致
+fn body_heading_positions(
+    rendered: &RenderedDocument,
+    page_starts: &[usize],
+    options: &ConvertOptions,
+) -> Vec<Option<usize>> {
+    rendered
+        .pages
+        .iter()
+        .zip(page_starts)
+        .flat_map(|(page, &start)| {
+            heading_positions(&page.html, &page.toc_entries, options)
+                .into_iter()
+                .map(move |position| position.map(|position| start + position))
+        })
+        .collect()
+}
+
+/// 文档中按字节计的位置换算为按字符计；位置按从前到后的顺序时只需扫描一遍
+fn char_offsets(text: &str, positions: &[Option<usize>]) -> Vec<Option<usize>> {
+    let mut last = (0, 0);
+    positions
+        .iter()
+        .map(|position| {
+            let position = (*position)?;
+            let (from, chars) = if position >= last.0 { last } else { (0, 0) };
+            let chars = chars + text.get(from..position)?.chars().count();
+            last = (position, chars);
+            Some(chars)
+        })
+        .collect()
+}
+
+fn footer_block(options: &ConvertOptions) -> String {
+    let Some(footer) = &options.footer else {
+        return String::new();
+    };
+    match options.flavor {
+        Flavor::Styled => format!(
+            "\n<hr{} />\n<div{}>\n{}\n</div>\n",
+            style_attr("mineru-footer-rule", options),
+            style_attr("mineru-footer", options),
+            footer
+        ),
+        Flavor::Gfm | Flavor::Obsidian => format!("\n---\n\n{}\n", footer),
+    }
+}
+
+/// 将多个文档合并转换为一篇 Markdown
+///
+/// 页码、目录在文档之间连续编号，样式块与页脚只输出一次，
+/// 每个文档的图片相对于各自的 `base_path` 解析。
+pub fn convert_documents(
+    documents: &[Document],
+    options: &ConvertOptions,
+) -> Result<ConvertOutput, ConvertError> {
+    let options = &*output_options(options);
+    let rendered = render_documents(documents, options, None)?;
+    Ok(finish_document(rendered, options))
+}
+
+/// 与 [`convert_documents`] 相同，但标题、正文、图片、表格与行间公式交给 `renderer` 渲染
+pub fn convert_documents_with_renderer(
+    documents: &[Document],
+    options: &ConvertOptions,
+    renderer: &dyn BlockRenderer,
+) -> Result<ConvertOutput, ConvertError> {
+    let options = &*output_options(options);
+    let rendered = render_documents(documents, options, Some(renderer))?;
+    Ok(finish_document(rendered, options))
+}
+
+/// 分析多个文档，得到尚未写出的文档模型；选项与 [`convert_documents`] 相同
+///
+/// 模型中的节点可以增删、修改后再交给 [`write_document`]，例如去掉某类节点或改写标题：
+///
+/// ```
+/// use mineru_json_to_md::{
+///     ConvertOptions, DocNode, Document, InputFormat, analyze_documents, parse_layout,
+///     write_document,
+/// };
+/// use std::path::Path;
+///
+/// let json = r#"{"pdf_info": [{"page_idx": 0, "page_size": [612, 792], "para_blocks": [
+///     {"type": "title", "bbox": [0, 0, 100, 20],
+///      "lines": [{"spans": [{"type": "text", "content": "Draft"}]}]},
+///     {"type": "text", "bbox": [0, 30, 100, 50],
+///      "lines": [{"spans": [{"type": "text", "content": "hello"}]}]}]}]}"#;
+/// let layout = parse_layout(json, InputFormat::Auto)?;
+/// let documents = [Document { layout: &layout, base_path: Path::new(".") }];
+/// let options = ConvertOptions::default();
+///
+/// let mut model = analyze_documents(&documents, &options)?;
+/// for page in &mut model.pages {
+///     page.nodes.retain(|node| !matches!(node.node, DocNode::Heading { .. }));
+/// }
+/// let output = write_document(&model, &options)?;
+/// assert!(output.markdown.contains("hello"));
+/// assert!(!output.markdown.contains("Draft"));
+/// # Ok::<(), mineru_json_to_md::ConvertError>(())
+/// ```
+pub fn analyze_documents(
+    documents: &[Document],
+    options: &ConvertOptions,
+) -> Result<DocumentModel, ConvertError> {
+    let options = &*output_options(options);
+    let started = Instant::now();
+    let plan = PagePlan::new(documents, options, None)?;
+    let pages = plan.analyze(0..plan.len(), options, None)?;
+    let mut image_stats = ConvertStats::default();
+    plan.record_images(&mut image_stats);
+    image_stats.timings.render = started.elapsed();
+    Ok(DocumentModel { pages, image_stats })
+}
+
+/// 写出 [`analyze_documents`] 得到的文档模型；`options` 应与分析时相同
+pub fn write_document(
+    model: &DocumentModel,
+    options: &ConvertOptions,
+) -> Result<ConvertOutput, ConvertError> {
+    let options = &*output_options(options);
+    let started = Instant::now();
+    let pages = model
+        .pages
+        .iter()
+        .map(|page| write_page(page, options))
+        .collect();
+    let mut rendered = RenderedDocument::new(pages, &model.image_stats, options);
+    rendered.stats.timings.render += started.elapsed();
+    Ok(finish_document(rendered, options))
+}
+
+/// 在各页内容之前加上样式块与目录、之后加上页脚，并按输出格式包装为完整的文档；
+/// 没有任何页面时不加样式块与页脚，只按输出格式包装
+fn finish_document(rendered: RenderedDocument, options: &ConvertOptions) -> ConvertOutput {
+    let started = Instant::now();
+    let decorated = !rendered.pages.is_empty();
+    let mut markdown = String::new();
+    if decorated {
+        markdown.push_str(&style_block(options));
+        markdown.push_str(&navigation(
+            &rendered.toc_entries,
+            &rendered.figures,
+            options,
+            &AnchorFiles::new(),
+        ));
+    }
+
+    // 添加分隔线；前面既没有样式块也没有目录时直接从正文开始
+    if !markdown.is_empty() {
+        markdown.push_str(&section_rule(options));
+    }
+
+    let (body, page_starts) = assemble_pages(&rendered.pages, options);
+    let mut headings = body_heading_positions(&rendered, &page_starts, options);
+    let body_start = markdown.len();
+    match back_to_top_link(options, "") {
+        Some(link) => {
+            let positions = back_to_top_positions(&rendered, &page_starts, options);
+            markdown.push_str(&insert_back_to_top_tracked(
+                &body,
+                0..body.len(),
+                &positions,
+                &link,
+                &mut headings,
+            ));
+        }
+        _ => markdown.push_str(&body),
+    }
+    if decorated {
+        markdown.push_str(&footer_block(options));
+    }
+
+    // 包装后正文之前的文档开头的长度
+    let mut head = 0;
+    match options.format {
+        OutputFormat::Markdown => {}
+        OutputFormat::Html => {
+            let title = rendered
+                .toc_entries
+                .first()
+                .map(|entry| entry.title.as_str());
+            head = html_document_head(title, options).len();
+            markdown = wrap_html_document(&markdown, title, options);
+        }
+        // 各页之间保留的空行在文末去掉
+        OutputFormat::Text | OutputFormat::Rst => {
+            markdown.truncate(markdown.trim_end().len());
+            markdown.push('\n');
+        }
+        OutputFormat::Latex => {
+            markdown.truncate(markdown.trim_end().len());
+            markdown.push('\n');
+            if options.latex_standalone {
+                head = latex_document_head(latex::has_cjk(&markdown)).len();
+                markdown = wrap_latex_document(&markdown);
+            }
+        }
+        // JSON 输出中的条目不保留正文的位置
+        OutputFormat::Json => {
+            headings.fill(None);
+            markdown = wrap_json_document(&markdown);
+        }
+        OutputFormat::Asciidoc => {
+            head = asciidoc_document_head().len();
+            markdown = wrap_asciidoc_document(&markdown);
+        }
+        OutputFormat::Org => {
+            head = org_document_head().len();
+            markdown = wrap_org_document(&markdown);
+        }
+    }
+    for position in headings.iter_mut().flatten() {
+        *position += head + body_start;
+    }
+    let outline = rendered
+        .toc_entries
+        .iter()
+        .zip(char_offsets(&markdown, &headings))
+        .map(|(entry, offset)| OutlineEntry::new(entry, offset))
+        .collect();
+
+    let mut stats = rendered.stats;
+    stats.count_output(&markdown);
+    stats.timings.write = started.elapsed();
+    ConvertOutput {
+        markdown,
+        warnings: rendered.warnings,
+        stats,
+        outline,
+    }
+}
+
+/// 读取并转换一个 JSON 文件，图片相对于文件所在目录解析
+pub fn convert_file(
+    path: &Path,
+    format: InputFormat,
+    options: &ConvertOptions,
+) -> Result<ConvertOutput, ConvertError> {
+    let json = fs::read_to_string(path).map_err(|source| ConvertError::Io {
+        path: path.to_path_buf(),
+        source,
+    })?;
+    let layout_json = parse_layout(&json, format)?;
+    let base_path = path.parent().unwrap_or(Path::new("."));
+    convert_layout_to_markdown(&layout_json, base_path, options)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{
+        TempDir, bare_options, image_block, layout, lines_block, markdown, page_layout, png,
+        text_block,
+    };
+    use serde_json::json;
+
+    #[test]
+    fn converts_an_in_memory_layout() {
+        let layout = layout(json!({"pdf_info": [
+            {"page_idx": 0, "page_size": [600, 800], "para_blocks": [
+                text_block("title", [50.0, 50.0, 550.0, 80.0], "Introduction"),
+                text_block("text", [50.0, 100.0, 550.0, 200.0], "Hello world."),
+            ]},
+            {"page_idx": 1, "page_size": [600, 800], "para_blocks": [
+                text_block("text", [50.0, 100.0, 550.0, 200.0], "Second page."),
+            ]}
+        ]}));
+        let output = convert_layout_to_markdown(&layout, Path::new("."), &bare_options()).unwrap();
+        assert!(output.warnings.is_empty());
+        assert_eq!(output.stats.pages, 2);
+        assert!(
+            output
+                .markdown
+                .contains("## Introduction\n\nHello world.\n\nSecond page.\n"),
+            "{}",
+            output.markdown
+        );
+        assert!(
+            output
+                .markdown
+                .contains("- [Introduction](#toc-0-Introduction)")
+        );
+    }
+
+    #[test]
+    fn style_header_dividers_and_footer_follow_the_options() {
+        let layout = page_layout(json!([text_block(
+            "text",
+            [50.0, 100.0, 550.0, 200.0],
+            "Body"
+        )]));
+        let styled = markdown(&layout, &ConvertOptions::default());
+        assert!(styled.starts_with("<style>"));
+        assert!(styled.contains("第 1 页"));
+        assert!(
+            styled
+                .trim_end()
+                .ends_with(&format!("{}\n</div>", DEFAULT_FOOTER))
+        );
+
+        let bare = markdown(&layout, &bare_options());
+        assert_eq!(bare.trim(), "Body");
+    }
+
+    #[test]
+    fn images_are_embedded_relative_to_the_base_path() {
+        let dir = TempDir::new();
+        dir.write("images/a.png", &png(2, 2, 0));
+        let layout = page_layout(json!([image_block(
+            [50.0, 100.0, 550.0, 200.0],
+            "images/a.png"
+        )]));
+        let output = convert_layout_to_markdown(&layout, dir.path(), &bare_options()).unwrap();
+        assert!(output.warnings.is_empty());
+        assert!(output.markdown.contains("src=\"data:image/png;base64,"));
+        assert_eq!(output.stats.pages, 1);
+    }
+
+    #[test]
+    fn missing_images_are_warnings_not_errors() {
+        let layout = page_layout(json!([
+            image_block([50.0, 100.0, 550.0, 200.0], "images/missing.png"),
+            text_block("text", [50.0, 300.0, 550.0, 400.0], "After the image"),
+        ]));
+        let output = convert_layout_to_markdown(&layout, Path::new("."), &bare_options()).unwrap();
+        assert!(matches!(
+            output.warnings.as_slice(),
+            [Warning::MissingImage { page_idx: 0, path }] if path == "images/missing.png"
+        ));
+        assert!(output.markdown.contains("After the image"));
+    }
+
+    fn anchor_ids(output: &ConvertOutput) -> Vec<&str> {
+        output
+            .outline
+            .iter()
+            .map(|entry| entry.anchor_id.as_str())
+            .collect()
+    }
+
+    /// 目录中的每个链接都指向正文中的一个锚点
+    fn assert_toc_links_resolve(output: &ConvertOutput) {
+        for id in anchor_ids(output) {
+            assert!(output.markdown.contains(&format!("](#{})", id)), "{}", id);
+            assert!(
+                output.markdown.contains(&format!("<a id=\"{}\"></a>", id)),
+                "{}",
+                id
+            );
+        }
+    }
+
+    #[test]
+    fn repeated_titles_on_one_page_get_distinct_anchors() {
+        let layout = page_layout(json!([
+            text_block("title", [50.0, 50.0, 550.0, 80.0], "Introduction"),
+            text_block("text", [50.0, 90.0, 550.0, 100.0], "Running head"),
+            text_block("title", [50.0, 110.0, 550.0, 140.0], "Introduction"),
+            text_block("text", [50.0, 150.0, 550.0, 160.0], "Body"),
+            text_block("title", [50.0, 170.0, 550.0, 200.0], "Introduction 2"),
+        ]));
+        let output = convert_layout_to_markdown(&layout, Path::new("."), &bare_options()).unwrap();
+        assert_eq!(
+            anchor_ids(&output),
+            [
+                "toc-0-Introduction",
+                "toc-0-Introduction-2",
+                "toc-0-Introduction-2-2"
+            ]
+        );
+        assert_toc_links_resolve(&output);
+    }
+
+    #[test]
+    fn anchors_are_deduplicated_per_document() {
+        let layout = layout(json!({"pdf_info": [
+            {"page_idx": 0, "page_size": [600, 800], "para_blocks": [
+                text_block("title", [50.0, 50.0, 550.0, 80.0], "Introduction"),
+            ]},
+            {"page_idx": 1, "page_size": [600, 800], "para_blocks": [
+                text_block("title", [50.0, 50.0, 550.0, 80.0], "Introduction"),
+            ]}
+        ]}));
+        let output = convert_layout_to_markdown(&layout, Path::new("."), &bare_options()).unwrap();
+        assert_eq!(
+            anchor_ids(&output),
+            ["toc-0-Introduction", "toc-1-Introduction"]
+        );
+        assert_toc_links_resolve(&output);
+
+        // 每次转换重新开始，不沿用上一份文档已发出的锚点
+        let again = convert_layout_to_markdown(&layout, Path::new("."), &bare_options()).unwrap();
+        assert_eq!(anchor_ids(&again), anchor_ids(&output));
+    }
+
+    #[test]
+    fn github_anchors_follow_the_heading_text() {
+        let layout = page_layout(json!([
+            text_block(
+                "title",
+                [50.0, 50.0, 550.0, 80.0],
+                "Getting Started: Setup & Use"
+            ),
+            text_block("text", [50.0, 90.0, 550.0, 100.0], "Body"),
+            text_block("title", [50.0, 110.0, 550.0, 140.0], "第一章 概述"),
+            text_block("text", [50.0, 150.0, 550.0, 160.0], "Body"),
+            text_block(
+                "title",
+                [50.0, 170.0, 550.0, 200.0],
+                "Getting Started: Setup & Use"
+            ),
+        ]));
+        let options = ConvertOptions {
+            anchors: AnchorStyle::Github,
+            back_to_top: Some("Top".to_string()),
+            ..bare_options()
+        };
+        let output = convert_layout_to_markdown(&layout, Path::new("."), &options).unwrap();
+        assert_eq!(
+            anchor_ids(&output),
+            [
+                "getting-started-setup--use",
+                "第一章-概述",
+                "getting-started-setup--use-1"
+            ]
+        );
+        for id in anchor_ids(&output) {
+            assert!(output.markdown.contains(&format!("](#{})", id)), "{}", id);
+        }
+        // 标题前不再有 <a id>，返回目录链接到文档开头
+        assert!(!output.markdown.contains("<a id="), "{}", output.markdown);
+        assert!(!output.markdown.contains("toc-top"), "{}", output.markdown);
+        assert!(
+            output.markdown.contains("<a href=\"#top\">Top</a>"),
+            "{}",
+            output.markdown
+        );
+    }
+
+    #[test]
+    fn paragraphs_join_hyphenated_lines_unless_disabled() {
+        let layout = page_layout(json!([lines_block(
+            "text",
+            &["A transfor-", "mation of", "中文"]
+        )]));
+        assert_eq!(
+            markdown(&layout, &bare_options()).trim(),
+            "A transformation of中文"
+        );
+        let options = ConvertOptions {
+            dehyphenate: false,
+            ..bare_options()
+        };
+        assert_eq!(
+            markdown(&layout, &options).trim(),
+            "A transfor-mation of中文"
+        );
+    }
+
+    #[test]
+    fn page_idx_beyond_the_limit_is_a_parse_error() {
+        let json = json!({"pdf_info": [
+            {"page_idx": u64::MAX, "page_size": [600, 800], "para_blocks": []}
+        ]})
+        .to_string();
+        assert!(matches!(
+            parse_layout(&json, InputFormat::Middle),
+            Err(ConvertError::InvalidPage { page_idx: 0, .. })
+        ));
+
+        let (layout, warnings) = parse_layout_lenient(&json, InputFormat::Middle).unwrap();
+        assert!(layout.pdf_info.is_empty());
+        assert!(matches!(
+            warnings.as_slice(),
+            [Warning::SkippedPage { page_idx: 0, .. }]
+        ));
+        assert!(warnings[0].to_string().contains("out of range"));
+    }
+
+    #[test]
+    fn constructed_page_idx_beyond_the_limit_is_an_error() {
+        let mut layout = page_layout(json!([]));
+        layout.pdf_info[0].page_idx = usize::MAX;
+        let converted =
+            convert_layout_to_markdown(&layout, Path::new("."), &ConvertOptions::default());
+        assert!(matches!(
+            converted,
+            Err(ConvertError::PageIndexOutOfRange {
+                page_idx: usize::MAX
+            })
+        ));
+    }
+
+    #[test]
+    fn content_list_page_idx_beyond_the_limit_is_a_parse_error() {
+        let json = json!([{"type": "text", "text": "hello", "page_idx": u64::MAX}]).to_string();
+        assert!(parse_layout(&json, InputFormat::ContentList).is_err());
+    }
+
+    #[test]
+    fn each_divider_style_has_its_own_markup() {
+        let page = |page_idx: usize, blocks: serde_json::Value| json!({"page_idx": page_idx, "page_size": [600, 800], "para_blocks": blocks});
+        let body =
+            |content: &str| json!([text_block("text", [50.0, 100.0, 550.0, 200.0], content)]);
+        let layout = layout(json!({"pdf_info": [
+            page(0, body("One")),
+            page(1, json!([])),
+            page(2, json!([])),
+            page(3, body("Two")),
+        ]}));
+        let divided = |flavor: Flavor, divider_style: DividerStyle| {
+            markdown(
+                &layout,
+                &ConvertOptions {
+                    flavor,
+                    divider_style,
+                    page_dividers: true,
+                    collapse_empty_pages: true,
+                    ..bare_options()
+                },
+            )
+        };
+
+        let full = divided(Flavor::Styled, DividerStyle::Full);
+        assert!(full.contains("<span style=\"color: #888; font-size: 0.85em;\">第 1 页</span>"));
+        assert!(full.contains("第 2–3 页（空白页）"), "{}", full);
+        assert_eq!(
+            divided(Flavor::Gfm, DividerStyle::Full)
+                .matches("\n---\n\nPage 1\n")
+                .count(),
+            1
+        );
+
+        let compact = divided(Flavor::Styled, DividerStyle::Compact);
+        assert!(
+            compact.contains("\n<div style=\"border-top: 1px solid #eee;"),
+            "{}",
+            compact
+        );
+        assert!(
+            compact.contains("><sup>第 1 页</sup></div>\n"),
+            "{}",
+            compact
+        );
+        assert!(!compact.contains("mineru-page-divider-line"));
+        // Gfm 会过滤 style 属性，右对齐写在 `align` 中
+        let compact = divided(Flavor::Gfm, DividerStyle::Compact);
+        assert!(
+            compact.contains("\n---\n\n<p align=\"right\"><sup>Page 1</sup></p>\n"),
+            "{}",
+            compact
+        );
+
+        // 注释的内容在各种渲染风格下相同
+        let margin = divided(Flavor::Styled, DividerStyle::Margin);
+        assert_eq!(margin, divided(Flavor::Gfm, DividerStyle::Margin));
+        assert_eq!(
+            margin,
+            "One\n\n\n<!-- page 1 -->\n\n\n<!-- pages 2–3 blank -->\n\nTwo\n\n\n<!-- page 4 -->\n\n"
+        );
+    }
+
+    #[test]
+    fn heading_depth_follows_the_title_level_or_its_numbering() {
+        let mut leveled = text_block("title", [50.0, 50.0, 550.0, 70.0], "Overview");
+        leveled["level"] = json!(3);
+        let mut deep = text_block("title", [50.0, 250.0, 550.0, 270.0], "Too deep");
+        deep["level"] = json!(9);
+        let layout = page_layout(json!([
+            leveled,
+            text_block("title", [50.0, 100.0, 550.0, 120.0], "3.2.1 Replication"),
+            text_block("title", [50.0, 150.0, 550.0, 170.0], "4. Results"),
+            text_block("title", [50.0, 200.0, 550.0, 220.0], "2021 was a year"),
+            deep,
+        ]));
+        let output = convert_layout_to_markdown(&layout, Path::new("."), &bare_options()).unwrap();
+        let levels: Vec<(&str, usize)> = output
+            .outline
+            .iter()
+            .map(|entry| (entry.title.as_str(), entry.level))
+            .collect();
+        // MinerU 的 level 优先，其次是编号的层数；年份不是编号，按长度猜测
+        assert_eq!(
+            levels,
+            [
+                ("Overview", 3),
+                ("3.2.1 Replication", 3),
+                ("4. Results", 1),
+                ("2021 was a year", 1),
+                ("Too deep", 5),
+            ]
+        );
+        assert!(
+            output.markdown.contains("\n#### 3.2.1 Replication\n"),
+            "{}",
+            output.markdown
+        );
+        assert_eq!(text::numbering_depth("1.2.3.4.5.6.7 Deep"), None);
+        assert_eq!(text::numbering_depth("10.1. Setup"), Some(2));
+    }
+
+    #[test]
+    fn parallel_rendering_matches_sequential_rendering() {
+        // 重复标题的锚点编号与相同图片的去重都依赖各页之间按顺序传递的状态
+        let dir = TempDir::new();
+        dir.write("a.png", &png(4, 3, 10));
+        dir.write("b.png", &png(4, 3, 200));
+        let pages: Vec<serde_json::Value> = (0..24)
+            .map(|idx| {
+                let image = if idx % 3 == 0 { "b.png" } else { "a.png" };
+                json!({"page_idx": idx, "page_size": [600, 800], "para_blocks": [
+                    text_block("title", [50.0, 50.0, 550.0, 80.0], "Results"),
+                    text_block("text", [50.0, 100.0, 550.0, 200.0], &format!("Page {} text.", idx + 1)),
+                    image_block([50.0, 250.0, 550.0, 450.0], image),
+                ]})
+            })
+            .collect();
+        let layout = layout(json!({ "pdf_info": pages }));
+        let convert = |threads: Option<usize>| {
+            let options = ConvertOptions {
+                threads,
+                anchors: AnchorStyle::Github,
+                ..ConvertOptions::default()
+            };
+            convert_layout_to_markdown(&layout, dir.path(), &options).unwrap()
+        };
+
+        let sequential = convert(Some(1));
+        assert!(sequential.markdown.contains("(#results-23)"));
+        for threads in [Some(4), None] {
+            let parallel = convert(threads);
+            assert_eq!(parallel.markdown, sequential.markdown, "{:?}", threads);
+            assert_eq!(parallel.outline, sequential.outline);
+            assert_eq!(
+                parallel.stats.images_deduplicated,
+                sequential.stats.images_deduplicated
+            );
+        }
+    }
+
+    #[test]
+    fn several_documents_are_merged_with_continuous_page_numbers() {
+        // 两个文档引用同名的图片，各自相对于自己的目录解析
+        let (first_dir, second_dir) = (TempDir::new(), TempDir::new());
+        first_dir.write("fig.png", &png(4, 3, 10));
+        second_dir.write("fig.png", &png(4, 3, 200));
+        let document = |title: &str, pages: usize| {
+            layout(json!({"pdf_info": (0..pages)
+                .map(|idx| json!({"page_idx": idx, "page_size": [600, 800], "para_blocks": [
+                    text_block("title", [50.0, 50.0, 550.0, 80.0], &format!("{} {}", title, idx + 1)),
+                    image_block([50.0, 100.0, 550.0, 300.0], "fig.png"),
+                ]}))
+                .collect::<Vec<_>>()}))
+        };
+        let (first, second) = (document("Alpha", 2), document("Beta", 1));
+        let documents = [
+            Document {
+                layout: &first,
+                base_path: first_dir.path(),
+            },
+            Document {
+                layout: &second,
+                base_path: second_dir.path(),
+            },
+        ];
+        let options = ConvertOptions::default();
+        let output = convert_documents(&documents, &options).unwrap();
+
+        let outline: Vec<(&str, usize)> = output
+            .outline
+            .iter()
+            .map(|entry| (entry.title.as_str(), entry.page_idx))
+            .collect();
+        assert_eq!(outline, [("Alpha 1", 1), ("Alpha 2", 2), ("Beta 1", 3)]);
+        assert_eq!(output.stats.pages, 3);
+        assert_eq!(output.stats.images_missing, 0);
+        let markdown = &output.markdown;
+        assert_eq!(markdown.matches("<style").count(), 1);
+        assert_eq!(markdown.matches(DEFAULT_FOOTER).count(), 1);
+        let images: std::collections::HashSet<&str> = markdown
+            .split("src=\"data:")
+            .skip(1)
+            .map(|rest| &rest[..rest.find('"').unwrap()])
+            .collect();
+        assert_eq!(images.len(), 2, "{}", markdown);
+        assert!(markdown.contains("toc-2-Beta-1"), "{}", markdown);
+    }
+
+    #[test]
+    fn blank_page_runs_share_one_divider_within_each_document() {
+        let page = |page_idx: usize, blocks: serde_json::Value| json!({"page_idx": page_idx, "page_size": [600, 800], "para_blocks": blocks});
+        let body =
+            |content: &str| json!([text_block("text", [50.0, 100.0, 550.0, 200.0], content)]);
+        // 只有未输出的页眉的一页同样是空白页；空白页不跨文档合并
+        let first = layout(json!({"pdf_info": [
+            page(0, body("One")),
+            {"page_idx": 1, "page_size": [600, 800], "para_blocks": [], "discarded_blocks": [
+                text_block("header", [50.0, 10.0, 550.0, 30.0], "Running head"),
+            ]},
+            page(2, json!([])),
+        ]}));
+        let second = layout(json!({"pdf_info": [
+            page(0, json!([])),
+            page(1, body("Two")),
+            page(2, json!([])),
+        ]}));
+        let documents = [
+            Document {
+                layout: &first,
+                base_path: Path::new("."),
+            },
+            Document {
+                layout: &second,
+                base_path: Path::new("."),
+            },
+        ];
+        let options = ConvertOptions {
+            flavor: Flavor::Gfm,
+            page_dividers: true,
+            skip_headers: true,
+            collapse_empty_pages: true,
+            ..bare_options()
+        };
+        let output = convert_documents(&documents, &options).unwrap().markdown;
+        let labels: Vec<&str> = output
+            .lines()
+            .filter(|line| line.starts_with("Page"))
+            .collect();
+        assert_eq!(
+            labels,
+            [
+                "Page 1",
+                "Pages 2–3 (intentionally blank)",
+                "Page 4 (intentionally blank)",
+                "Page 5",
+                "Page 6 (intentionally blank)",
+            ]
+        );
+
+        let mut streamed = Vec::new();
+        convert_documents_to_writer(&documents, &mut streamed, &options).unwrap();
+        assert_eq!(String::from_utf8(streamed).unwrap(), output);
+
+        // 页眉照常输出时那一页不是空白页
+        let options = ConvertOptions {
+            skip_headers: false,
+            ..options
+        };
+        let output = convert_documents(&documents, &options).unwrap().markdown;
+        assert!(output.contains("\nPage 2\n"), "{}", output);
+        assert!(
+            output.contains("\nPage 3 (intentionally blank)\n"),
+            "{}",
+            output
+        );
+    }
+}
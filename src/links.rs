@@ -0,0 +1,313 @@
+//! 链接识别：参考文献条目中的网址与 DOI，以及开启 autolink 时正文中的网址、邮箱与 DOI
+
+/// 文字中的一段：普通文字，或文字与链接地址
+pub(crate) enum Piece<'a> {
+    Text(&'a str),
+    Link(&'a str, String),
+}
+
+/// 网址或 DOI 的结束位置：遇到空白为止，去掉末尾的标点和不成对的右括号；
+/// `prose` 时遇到 `<`、`>`、`"` 与非 ASCII 字符（如紧接的中文）同样结束，末尾的 `!`、`?`、`'` 也去掉
+fn link_end(text: &str, prose: bool) -> usize {
+    let stop = |c: char| c.is_whitespace() || (prose && (!c.is_ascii() || "<>\"".contains(c)));
+    let mut end = text.find(stop).unwrap_or(text.len());
+    loop {
+        let link = &text[..end];
+        let unbalanced = |open: char, close: char| {
+            link.ends_with(close) && link.matches(open).count() < link.matches(close).count()
+        };
+        if link.ends_with(['.', ',', ';', ':'])
+            || (prose && link.ends_with(['!', '?', '\'']))
+            || unbalanced('(', ')')
+            || unbalanced('[', ']')
+        {
+            end -= 1;
+        } else {
+            return end;
+        }
+    }
+}
+
+/// 找出参考文献条目中的 `http(s)://` 网址与 `doi:10.…` 形式的 DOI；DOI 链接到 doi.org
+pub(crate) fn link_pieces(entry: &str) -> Vec<Piece<'_>> {
+    let lower = entry.to_ascii_lowercase();
+    let mut pieces = Vec::new();
+    let mut rest = 0;
+    while rest < entry.len() {
+        let url = ["https://", "http://"]
+            .iter()
+            .filter_map(|scheme| lower[rest..].find(scheme))
+            .min()
+            .map(|i| (rest + i, rest + i));
+        // `doi:` 与编号之间可以有空格，链接文字只包含编号本身
+        let doi = lower[rest..].find("doi:").and_then(|i| {
+            let start = rest + i;
+            let number =
+                start + 4 + (entry[start + 4..].len() - entry[start + 4..].trim_start().len());
+            entry[number..]
+                .starts_with("10.")
+                .then_some((start, number))
+        });
+        let next = match (url, doi) {
+            (Some(url), Some(doi)) => Some(url.min(doi)),
+            (found, None) | (None, found) => found,
+        };
+        let Some((start, link_start)) = next else {
+            break;
+        };
+        let end = link_start + link_end(&entry[link_start..], false);
+        if end == link_start {
+            pieces.push(Piece::Text(&entry[rest..link_start + 1]));
+            rest = link_start + 1;
+            continue;
+        }
+        let link = &entry[link_start..end];
+        let href = if start != link_start {
+            format!("https://doi.org/{}", link)
+        } else {
+            link.to_string()
+        };
+        pieces.push(Piece::Text(&entry[rest..link_start]));
+        pieces.push(Piece::Link(link, href));
+        rest = end;
+    }
+    pieces.push(Piece::Text(&entry[rest..]));
+    pieces.retain(|piece| !matches!(piece, Piece::Text("")));
+    pieces
+}
+
+// ==================== 正文链接 ====================
+
+fn starts_with_ignore_case(text: &str, prefix: &str) -> bool {
+    text.len() >= prefix.len()
+        && text.as_bytes()[..prefix.len()].eq_ignore_ascii_case(prefix.as_bytes())
+}
+
+/// 邮箱地址 `user@example.org` 的长度；域名至少两段，顶级域名为两个以上字母
+fn email_len(text: &str) -> Option<usize> {
+    let local = text
+        .find(|c: char| !(c.is_ascii_alphanumeric() || "._%+-".contains(c)))
+        .unwrap_or(text.len());
+    if local == 0 || text.starts_with('.') || !text[local..].starts_with('@') {
+        return None;
+    }
+    let domain = &text[local + 1..];
+    let domain = &domain[..domain
+        .find(|c: char| !(c.is_ascii_alphanumeric() || ".-".contains(c)))
+        .unwrap_or(domain.len())];
+    let domain = domain.trim_end_matches(['.', '-']);
+    let labels: Vec<&str> = domain.split('.').collect();
+    let tld = labels.last().copied().unwrap_or("");
+    (labels.len() >= 2
+        && labels.iter().all(|label| !label.is_empty())
+        && tld.len() >= 2
+        && tld.chars().all(|c| c.is_ascii_alphabetic()))
+    .then_some(local + 1 + domain.len())
+}
+
+/// `10.1000/xyz` 形式的 DOI 编号：`10.` 之后为 4–9 位数字、`/` 与非空的后缀
+fn is_doi_number(text: &str) -> bool {
+    let Some(rest) = text.strip_prefix("10.") else {
+        return false;
+    };
+    let digits = rest.len() - rest.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+    (4..=9).contains(&digits) && rest[digits..].len() > 1 && rest[digits..].starts_with('/')
+}
+
+/// 从 `i` 开始的链接：返回链接文字的起止位置与链接地址。网址可以紧跟在标点之后
+/// （`Available:https://…`），`www.`、邮箱与 DOI 需要从一个词的开头开始
+fn link_at(text: &str, i: usize) -> Option<(usize, usize, String)> {
+    let rest = &text[i..];
+    let before = text[..i].chars().next_back();
+    if before.is_some_and(|c| c.is_ascii_alphanumeric()) {
+        return None;
+    }
+    if let Some(scheme) = ["https://", "http://"]
+        .into_iter()
+        .find(|scheme| starts_with_ignore_case(rest, scheme))
+    {
+        let end = link_end(rest, true);
+        return (end > scheme.len()).then(|| (i, i + end, rest[..end].to_string()));
+    }
+    if before.is_some_and(|c| "._%+-@/".contains(c)) {
+        return None;
+    }
+    if starts_with_ignore_case(rest, "www.") {
+        let end = link_end(rest, true);
+        let host = &rest[4..end.max(4)];
+        return (host.starts_with(|c: char| c.is_ascii_alphanumeric()) && host.contains('.'))
+            .then(|| (i, i + end, format!("https://{}", &rest[..end])));
+    }
+    // `doi:` 与编号之间可以有空格，链接文字只包含编号本身
+    if starts_with_ignore_case(rest, "doi:") {
+        let number = 4 + (rest[4..].len() - rest[4..].trim_start().len());
+        let end = number + link_end(&rest[number..], true);
+        return is_doi_number(&rest[number..end]).then(|| {
+            (
+                i + number,
+                i + end,
+                format!("https://doi.org/{}", &rest[number..end]),
+            )
+        });
+    }
+    if rest.starts_with("10.") {
+        let end = link_end(rest, true);
+        return is_doi_number(&rest[..end])
+            .then(|| (i, i + end, format!("https://doi.org/{}", &rest[..end])));
+    }
+    email_len(rest).map(|len| (i, i + len, format!("mailto:{}", &rest[..len])))
+}
+
+/// 找出正文中的 `http(s)://` 与 `www.` 网址、邮箱地址，以及 `10.xxxx/…`（可带 `doi:` 前缀）形式的 DOI；
+/// 链接不包含末尾的标点，`www.` 网址补上 `https://`，DOI 链接到 doi.org，邮箱链接为 `mailto:`
+pub(crate) fn autolink_pieces(text: &str) -> Vec<Piece<'_>> {
+    let mut pieces = Vec::new();
+    let mut rest = 0;
+    let mut i = 0;
+    while i < text.len() {
+        match link_at(text, i) {
+            Some((start, end, href)) => {
+                pieces.push(Piece::Text(&text[rest..start]));
+                pieces.push(Piece::Link(&text[start..end], href));
+                i = end;
+                rest = end;
+            }
+            None => i += text[i..].chars().next().map_or(1, char::len_utf8),
+        }
+    }
+    pieces.push(Piece::Text(&text[rest..]));
+    pieces.retain(|piece| !matches!(piece, Piece::Text("")));
+    pieces
+}
+
+/// 上一行末尾的网址或 DOI 是否在下一行接续（长网址排版时常在行尾断开）：两行相接处的两个词直接拼接后
+/// 是一个跨越接缝的链接，并且上一行以 `/`、`-`、`=` 等网址中的分隔符结尾、下一行第一个词本身像网址的
+/// 一部分（含有 `/`、`=`、`_` 等），或在句点处断开而下一行以数字开头（`https://arxiv.org/abs/2101.` + `01234`）
+pub(crate) fn continues_link(prev: &str, next: &str) -> bool {
+    let last_word = prev.rsplit(char::is_whitespace).next().unwrap_or("");
+    let first_word = next.split(char::is_whitespace).next().unwrap_or("");
+    if last_word.is_empty() || first_word.is_empty() || !first_word.is_ascii() {
+        return false;
+    }
+    let joined = format!("{}{}", last_word, first_word);
+    let mut offset = 0;
+    let spans_seam = autolink_pieces(&joined).into_iter().any(|piece| {
+        let (text, link) = match piece {
+            Piece::Text(text) => (text, false),
+            Piece::Link(text, _) => (text, true),
+        };
+        offset += text.len();
+        link && offset > last_word.len() && offset - text.len() < last_word.len()
+    });
+    if !spans_seam {
+        return false;
+    }
+    let first_word = first_word.trim_end_matches(['.', ',', ';', ':', ')']);
+    last_word.ends_with(['/', '-', '_', '=', '?', '&', '#', '%', '~'])
+        || first_word.contains(['/', '=', '_', '?', '&', '#', '%'])
+        || (last_word.ends_with('.') && first_word.starts_with(|c: char| c.is_ascii_digit()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ConvertOptions;
+    use crate::testing::{bare_options, markdown, page_layout};
+    use serde_json::json;
+
+    /// 链接写成 `[文字](地址)`，便于比较
+    fn linked(pieces: Vec<Piece<'_>>) -> String {
+        pieces
+            .into_iter()
+            .map(|piece| match piece {
+                Piece::Text(text) => text.to_string(),
+                Piece::Link(text, href) => format!("[{}]({})", text, href),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn urls_emails_and_dois_are_found_without_trailing_punctuation() {
+        assert_eq!(
+            linked(autolink_pieces("Visit https://example.org/docs.")),
+            "Visit [https://example.org/docs](https://example.org/docs)."
+        );
+        assert_eq!(
+            linked(autolink_pieces("(see www.example.net/a_(b)).")),
+            "(see [www.example.net/a_(b)](https://www.example.net/a_(b)))."
+        );
+        assert_eq!(
+            linked(autolink_pieces(
+                "doi: 10.1145/3292500.3330701, or mail a.b@example.com!"
+            )),
+            "doi: [10.1145/3292500.3330701](https://doi.org/10.1145/3292500.3330701), \
+             or mail [a.b@example.com](mailto:a.b@example.com)!"
+        );
+        assert_eq!(
+            linked(autolink_pieces("网址https://example.org。")),
+            "网址[https://example.org](https://example.org)。"
+        );
+        // 不是链接的相似文字保持原样
+        for text in [
+            "version 10.5/2",
+            "x@y",
+            "awww.example.org",
+            "file.10.1000/x",
+        ] {
+            assert_eq!(linked(autolink_pieces(text)), text);
+        }
+    }
+
+    #[test]
+    fn links_broken_at_the_end_of_a_line_are_rejoined() {
+        assert!(continues_link("See doi:10.1145/", "3292500.3330701 and"));
+        assert!(continues_link("at https://arxiv.org/abs/2101.", "01234."));
+        assert!(continues_link("https://example.org/search?", "q=rust"));
+        // 句末的网址与下一句之间不接
+        assert!(!continues_link("at https://example.org.", "Then we"));
+        assert!(!continues_link("plain text", "next line"));
+    }
+
+    #[test]
+    fn reference_entries_link_urls_and_doi_prefixes() {
+        assert_eq!(
+            linked(link_pieces(
+                "Smith. 2020. doi:10.1000/xyz. https://a.org/p)."
+            )),
+            "Smith. 2020. doi:[10.1000/xyz](https://doi.org/10.1000/xyz). \
+             [https://a.org/p](https://a.org/p))."
+        );
+    }
+
+    #[test]
+    fn autolink_rejoins_split_dois_and_skips_equations() {
+        let line = |y: f64, spans: serde_json::Value| json!({"bbox": [50.0, y, 550.0, y + 15.0], "spans": spans});
+        let text = |y: f64, content: &str| json!({"type": "text", "bbox": [50.0, y, 550.0, y + 15.0], "content": content});
+        let layout = page_layout(json!([{
+            "type": "text",
+            "bbox": [50.0, 100.0, 550.0, 135.0],
+            "lines": [
+                line(100.0, json!([text(100.0, "See doi:10.1145/")])),
+                line(120.0, json!([
+                    text(120.0, "3292500.3330701 and https://example.org/docs. "),
+                    {"type": "inline_equation", "bbox": [50.0, 120.0, 550.0, 135.0],
+                     "content": "x = http://y.z"}
+                ])),
+            ]
+        }]));
+        let options = ConvertOptions {
+            autolink: true,
+            ..bare_options()
+        };
+        assert_eq!(
+            markdown(&layout, &options).trim(),
+            "See doi:[10.1145/3292500.3330701](<https://doi.org/10.1145/3292500.3330701>) \
+             and <https://example.org/docs>. $x = http://y.z$"
+        );
+        // 默认不识别链接
+        assert_eq!(
+            markdown(&layout, &bare_options()).trim(),
+            "See doi:10.1145/ 3292500.3330701 and https://example.org/docs. $x = http://y.z$"
+        );
+    }
+}
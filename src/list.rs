@@ -0,0 +1,679 @@
+//! 列表：还原嵌套层级，识别有序列表的编号并去掉重复的项目符号
+
+use std::borrow::Cow;
+
+use crate::asciidoc::escape_asciidoc;
+use crate::doc::ListItem;
+use crate::latex::escape_latex;
+use crate::org;
+use crate::rst::escape_rst;
+use crate::text::{
+    escape_html, escape_text, extract_line_text, extract_text_from_block, pangu_spacing,
+};
+use crate::types::{BBox, Block};
+use crate::{ConvertOptions, OutputFormat};
+
+// ==================== 编号识别 ====================
+
+/// 项目文本开头常见的项目符号，输出时由 Markdown 的 `-` 代替
+const BULLET_GLYPHS: [char; 14] = [
+    '•', '‣', '◦', '▪', '▫', '●', '○', '■', '□', '◆', '◇', '·', '►', '➢',
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Counter {
+    Digit,
+    Letter,
+}
+
+/// 编号两侧的标点：`1.`、`1)`、`(1)`、`1、`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Delimiter {
+    Dot,
+    Paren,
+    Parens,
+    Ideographic,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Marker {
+    counter: Counter,
+    delimiter: Delimiter,
+    value: u32,
+}
+
+/// 解析项目开头的编号，返回编号与去掉编号后的正文
+fn parse_marker(text: &str) -> Option<(Marker, &str)> {
+    let (inner, rest, delimiter) = if let Some(after) = text.strip_prefix('(') {
+        let end = after.find(')')?;
+        (&after[..end], &after[end + 1..], Delimiter::Parens)
+    } else {
+        let end = text.find(['.', ')', '、'])?;
+        let punct = text[end..].chars().next()?;
+        let delimiter = match punct {
+            '.' => Delimiter::Dot,
+            ')' => Delimiter::Paren,
+            _ => Delimiter::Ideographic,
+        };
+        (&text[..end], &text[end + punct.len_utf8()..], delimiter)
+    };
+
+    // 编号后必须有空白（`、` 除外），避免把 "3.5 倍" 之类的正文当作编号
+    if delimiter != Delimiter::Ideographic && !rest.starts_with(char::is_whitespace) {
+        return None;
+    }
+
+    let marker =
+        if !inner.is_empty() && inner.len() <= 3 && inner.chars().all(|c| c.is_ascii_digit()) {
+            Marker {
+                counter: Counter::Digit,
+                delimiter,
+                value: inner.parse().ok()?,
+            }
+        } else {
+            let mut chars = inner.chars();
+            let letter = chars.next().filter(char::is_ascii_alphabetic)?;
+            if chars.next().is_some() {
+                return None;
+            }
+            Marker {
+                counter: Counter::Letter,
+                delimiter,
+                value: (letter.to_ascii_lowercase() as u32) - ('a' as u32) + 1,
+            }
+        };
+    Some((marker, rest.trim_start()))
+}
+
+/// 所有项目的编号风格一致时返回起始编号；有任何一项不带编号则按无序列表处理
+fn ordered_start(texts: &[String]) -> Option<u32> {
+    let markers: Vec<Marker> = texts
+        .iter()
+        .map(|text| parse_marker(text).map(|(marker, _)| marker))
+        .collect::<Option<_>>()?;
+    let first = markers.first()?;
+    let consistent = markers
+        .iter()
+        .all(|m| m.counter == first.counter && m.delimiter == first.delimiter);
+    if !consistent {
+        return None;
+    }
+    Some(match first.counter {
+        Counter::Digit => first.value,
+        Counter::Letter => 1,
+    })
+}
+
+fn strip_bullet(text: &str) -> &str {
+    let Some(first) = text.chars().next() else {
+        return text;
+    };
+    let rest = &text[first.len_utf8()..];
+    let is_bullet = BULLET_GLYPHS.contains(&first)
+        || (matches!(first, '-' | '*' | '+' | '–') && rest.starts_with(char::is_whitespace));
+    if is_bullet { rest.trim_start() } else { text }
+}
+
+// ==================== 散落的列表 ====================
+
+/// 至少这么多个连续的文本块以相同的项目符号或编号风格开头时，才合并为一个列表
+const MIN_LIST_RUN: usize = 3;
+
+/// 文本块开头的项目符号，或编号的计数方式与两侧标点
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Prefix {
+    Bullet(char),
+    Marker(Counter, Delimiter),
+}
+
+/// 普通文本块开头的项目符号或编号；编号块同时返回编号值，项目符号的值为 0
+fn block_prefix(block: &Block, dehyphenate: bool) -> Option<(Prefix, u32)> {
+    if block.block_type != "text"
+        || block.sub_type.as_deref() == Some("code")
+        || block.rotation().is_some()
+    {
+        return None;
+    }
+    let text = extract_text_from_block(block, dehyphenate);
+    let text = text.trim_start();
+    if strip_bullet(text).len() != text.len() {
+        return Some((Prefix::Bullet(text.chars().next()?), 0));
+    }
+    let (marker, _) = parse_marker(text)?;
+    Some((
+        Prefix::Marker(marker.counter, marker.delimiter),
+        marker.value,
+    ))
+}
+
+/// 把连续的、以相同项目符号或编号风格开头的文本块合并为一个 list 块，每个文本块成为一个项目。
+/// 遇到其他块即结束；编号必须逐一递增，以免把 "第 3 条"、"第 5 条" 之类的条文引用当作列表。
+/// 没有可合并的块时原样借用
+pub(crate) fn group_list_runs(blocks: &[Block], dehyphenate: bool) -> Cow<'_, [Block]> {
+    let prefixes: Vec<Option<(Prefix, u32)>> = blocks
+        .iter()
+        .map(|block| block_prefix(block, dehyphenate))
+        .collect();
+    let mut runs = Vec::new();
+    let mut start = 0;
+    while start < blocks.len() {
+        let Some((prefix, mut value)) = prefixes[start] else {
+            start += 1;
+            continue;
+        };
+        let mut end = start + 1;
+        while let Some(Some((next, next_value))) = prefixes.get(end)
+            && *next == prefix
+            && (matches!(prefix, Prefix::Bullet(_)) || *next_value == value + 1)
+        {
+            value = *next_value;
+            end += 1;
+        }
+        if end - start >= MIN_LIST_RUN {
+            runs.push(start..end);
+        }
+        start = end;
+    }
+    if runs.is_empty() {
+        return Cow::Borrowed(blocks);
+    }
+
+    let mut grouped = Vec::with_capacity(blocks.len());
+    let mut next = 0;
+    for run in runs {
+        grouped.extend_from_slice(&blocks[next..run.start]);
+        let items = &blocks[run.clone()];
+        grouped.push(Block {
+            bbox: items
+                .iter()
+                .skip(1)
+                .fold(items[0].bbox, |bbox: BBox, item| bbox.union(&item.bbox)),
+            block_type: "list".to_string(),
+            angle: None,
+            lines: None,
+            blocks: Some(
+                items
+                    .iter()
+                    .map(|item| Block {
+                        block_type: "list_item".to_string(),
+                        ..item.clone()
+                    })
+                    .collect(),
+            ),
+            index: items[0].index,
+            sub_type: None,
+            level: None,
+            guess_lang: None,
+        });
+        next = run.end;
+    }
+    grouped.extend_from_slice(&blocks[next..]);
+    Cow::Owned(grouped)
+}
+
+// ==================== 列表渲染 ====================
+
+/// 项目自身的文字：行内容加上非列表子块（嵌套列表单独渲染）
+fn item_text(item: &Block, options: &ConvertOptions) -> String {
+    let mut parts = vec![extract_line_text(item, options.dehyphenate)];
+    for child in item.blocks.iter().flatten() {
+        if !matches!(child.block_type.as_str(), "list" | "list_item") {
+            parts.push(extract_text_from_block(child, options.dehyphenate));
+        }
+    }
+    let text = parts
+        .iter()
+        .map(|part| part.trim())
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ");
+    if options.pangu_spacing {
+        pangu_spacing(&text)
+    } else {
+        text
+    }
+}
+
+/// 收集同一层级的项目；层级只由 list_item 的嵌套决定，嵌套的 list 块本身不增加缩进
+fn collect_items(blocks: &[Block], options: &ConvertOptions, out: &mut Vec<ListItem>) {
+    let texts: Vec<String> = blocks
+        .iter()
+        .filter(|block| block.block_type == "list_item")
+        .map(|item| item_text(item, options))
+        .collect();
+    let start = ordered_start(&texts);
+
+    let mut texts = texts.into_iter();
+    let mut number = start.unwrap_or(1);
+    for block in blocks {
+        match block.block_type.as_str() {
+            "list_item" => {
+                let text = texts.next().unwrap_or_default();
+                let (item_number, body) = match start {
+                    Some(_) => {
+                        let body = parse_marker(&text).map_or(text.as_str(), |(_, rest)| rest);
+                        number += 1;
+                        (Some(number - 1), body)
+                    }
+                    None => (None, strip_bullet(&text)),
+                };
+                let mut children = Vec::new();
+                if let Some(blocks) = &block.blocks {
+                    collect_items(blocks, options, &mut children);
+                }
+                out.push(ListItem {
+                    number: item_number,
+                    text: body.trim_end().to_string(),
+                    children,
+                });
+            }
+            "list" => {
+                if let Some(children) = &block.blocks {
+                    collect_items(children, options, out);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// 子列表缩进到上一项目正文的起始列（`- ` 后为两个空格，`12. ` 后为四个），CommonMark 才会视为嵌套；
+/// 没有文字的项目只输出其子项，同样缩进到上一项目正文的起始列
+fn push_markdown(
+    items: &[ListItem],
+    indent: usize,
+    options: &ConvertOptions,
+    out: &mut Vec<String>,
+) {
+    let pad = " ".repeat(indent);
+    let mut width = 2;
+    for item in items {
+        if !item.text.is_empty() {
+            let marker = match item.number {
+                Some(number) => format!("{}. ", number),
+                None => "- ".to_string(),
+            };
+            width = marker.len();
+            // 项目文字位于列表标记之后，行首的 `- `、`1. ` 同样会开始新的块
+            let text = escape_text(&item.text, true, options);
+            out.push(format!("{}{}{}", pad, marker, text));
+        }
+        push_markdown(&item.children, indent + width, options, out);
+    }
+}
+
+/// 纯文本输出中所有项目统一以 `- ` 开头，每级缩进两个空格
+fn push_text(items: &[ListItem], depth: usize, out: &mut Vec<String>) {
+    let indent = "  ".repeat(depth);
+    for item in items {
+        if !item.text.is_empty() {
+            out.push(format!("{}- {}", indent, item.text));
+        }
+        push_text(&item.children, depth + 1, out);
+    }
+}
+
+/// AsciiDoc 列表以重复的 `*`、`.` 表示层级；最外层的项目类型改变或编号不连续时，
+/// 以 `//` 注释隔开成为新的列表，不从 1 开始的有序列表加上 `[start=N]`
+fn push_asciidoc(items: &[ListItem], depth: usize, out: &mut Vec<String>) {
+    let mut open: Option<Option<u32>> = None;
+    for item in items {
+        if depth == 0 {
+            let continues = match (open, item.number) {
+                (Some(None), None) => true,
+                (Some(Some(prev)), Some(number)) => number == prev + 1,
+                _ => false,
+            };
+            if !continues {
+                if open.is_some() {
+                    out.extend([String::new(), "//".to_string(), String::new()]);
+                }
+                if let Some(start) = item.number.filter(|&number| number != 1) {
+                    out.push(format!("[start={}]", start));
+                }
+            }
+            open = Some(item.number);
+        }
+        if !item.text.is_empty() {
+            let marker = if item.is_ordered() { "." } else { "*" };
+            out.push(format!(
+                "{} {}",
+                marker.repeat(depth + 1),
+                escape_asciidoc(&item.text, false)
+            ));
+        }
+        push_asciidoc(&item.children, depth + 1, out);
+    }
+}
+
+/// reStructuredText 列表：子列表缩进到上一项目正文的起始列，前后各空一行；
+/// 编号不连续或有序、无序项目交替时以空注释 `..` 隔开，分为各自的列表
+fn push_rst(items: &[ListItem], indent: usize, out: &mut Vec<String>) {
+    let pad = " ".repeat(indent);
+    // 上一个项目的编号，只有写出了正文的项目才能由下一个项目接续
+    let mut open: Option<Option<u32>> = None;
+    let mut started = false;
+    for item in items {
+        let continues = match (open, item.number) {
+            (Some(None), None) => true,
+            (Some(Some(prev)), Some(number)) => number == prev + 1,
+            _ => false,
+        };
+        if started && !continues {
+            out.extend([String::new(), format!("{}..", pad), String::new()]);
+        }
+        started = true;
+        if item.text.is_empty() {
+            push_rst(&item.children, indent, out);
+            open = None;
+            continue;
+        }
+        open = Some(item.number);
+        let marker = match item.number {
+            Some(number) => format!("{}. ", number),
+            None => "- ".to_string(),
+        };
+        out.push(format!("{}{}{}", pad, marker, escape_rst(&item.text, true)));
+        if !item.children.is_empty() {
+            out.push(String::new());
+            push_rst(&item.children, indent + marker.len(), out);
+            if out.last().is_some_and(|line| !line.is_empty()) {
+                out.push(String::new());
+            }
+        }
+    }
+}
+
+/// Org 列表：子列表缩进到上一项目正文的起始列；编号不连续的项目以计数器 `[@N]` 接续编号，
+/// 有序、无序项目交替时以空注释 `#` 隔开，分为各自的列表
+fn push_org(items: &[ListItem], indent: usize, out: &mut Vec<String>) {
+    let pad = " ".repeat(indent);
+    // 上一个项目的编号，只有写出了正文的项目才能由下一个项目接续
+    let mut open: Option<Option<u32>> = None;
+    for item in items {
+        if open.is_some_and(|prev| prev.is_some() != item.number.is_some()) {
+            out.push(format!("{}#", pad));
+        }
+        if item.text.is_empty() {
+            push_org(&item.children, indent, out);
+            open = None;
+            continue;
+        }
+        let continues = match (open, item.number) {
+            (Some(Some(prev)), Some(number)) => number == prev + 1,
+            (_, number) => number.is_none_or(|number| number == 1),
+        };
+        open = Some(item.number);
+        let marker = match item.number {
+            Some(number) => format!("{}. ", number),
+            None => "- ".to_string(),
+        };
+        let counter = match item.number {
+            Some(number) if !continues => format!("[@{}] ", number),
+            _ => String::new(),
+        };
+        out.push(format!(
+            "{}{}{}{}",
+            pad,
+            marker,
+            counter,
+            org::item_text(&item.text)
+        ));
+        push_org(&item.children, indent + marker.len(), out);
+    }
+}
+
+/// 相邻且编号连续的项目放进同一个 `<ol>`，无序项目放进同一个 `<ul>`
+fn push_html(items: &[ListItem], out: &mut String) {
+    let mut open: Option<Option<u32>> = None;
+    for item in items {
+        let continues = match (open, item.number) {
+            (Some(None), None) => true,
+            (Some(Some(prev)), Some(number)) => number == prev + 1,
+            _ => false,
+        };
+        if !continues {
+            close_html_list(open, out);
+            match item.number {
+                Some(1) => out.push_str("<ol>\n"),
+                Some(start) => out.push_str(&format!("<ol start=\"{}\">\n", start)),
+                None => out.push_str("<ul>\n"),
+            }
+        }
+        open = Some(item.number);
+
+        out.push_str(&format!("<li>{}", escape_html(&item.text)));
+        if !item.children.is_empty() {
+            out.push('\n');
+            push_html(&item.children, out);
+        }
+        out.push_str("</li>\n");
+    }
+    close_html_list(open, out);
+}
+
+/// 各层 `enumerate` 的计数器
+const ENUM_COUNTERS: [&str; 4] = ["enumi", "enumii", "enumiii", "enumiv"];
+
+/// 与 HTML 相同的分组方式，有序列表为 `enumerate`，不从 1 开始时设置计数器；
+/// `depth` 为外层 `enumerate` 的层数
+fn push_latex(items: &[ListItem], depth: usize, out: &mut String) {
+    let mut open: Option<Option<u32>> = None;
+    for item in items {
+        let continues = match (open, item.number) {
+            (Some(None), None) => true,
+            (Some(Some(prev)), Some(number)) => number == prev + 1,
+            _ => false,
+        };
+        if !continues {
+            close_latex_list(open, out);
+            match item.number {
+                Some(1) => out.push_str("\\begin{enumerate}\n"),
+                Some(start) => out.push_str(&format!(
+                    "\\begin{{enumerate}}\n\\setcounter{{{}}}{{{}}}\n",
+                    ENUM_COUNTERS[depth.min(ENUM_COUNTERS.len() - 1)],
+                    start - 1
+                )),
+                None => out.push_str("\\begin{itemize}\n"),
+            }
+        }
+        open = Some(item.number);
+
+        out.push_str(&format!("\\item {}\n", escape_latex(&item.text)));
+        push_latex(
+            &item.children,
+            depth + usize::from(item.number.is_some()),
+            out,
+        );
+    }
+    close_latex_list(open, out);
+}
+
+fn close_latex_list(open: Option<Option<u32>>, out: &mut String) {
+    match open {
+        Some(Some(_)) => out.push_str("\\end{enumerate}\n"),
+        Some(None) => out.push_str("\\end{itemize}\n"),
+        None => {}
+    }
+}
+
+fn close_html_list(open: Option<Option<u32>>, out: &mut String) {
+    match open {
+        Some(Some(_)) => out.push_str("</ol>\n"),
+        Some(None) => out.push_str("</ul>\n"),
+        None => {}
+    }
+}
+
+/// 列表中的项目数，含嵌套的子项
+pub(crate) fn count_items(items: &[ListItem]) -> usize {
+    items
+        .iter()
+        .map(|item| 1 + count_items(&item.children))
+        .sum()
+}
+
+/// 列表中最外层的项目，嵌套的项目在各自的 `children` 中
+pub(crate) fn list_items(block: &Block, options: &ConvertOptions) -> Vec<ListItem> {
+    let mut items = Vec::new();
+    if let Some(blocks) = &block.blocks {
+        collect_items(blocks, options, &mut items);
+    }
+    items
+}
+
+pub(crate) fn render_list(items: &[ListItem], options: &ConvertOptions) -> String {
+    match options.format {
+        OutputFormat::Markdown | OutputFormat::Text | OutputFormat::Json => {
+            let mut lines = Vec::new();
+            if options.format != OutputFormat::Markdown {
+                push_text(items, 0, &mut lines);
+            } else {
+                push_markdown(items, 0, options, &mut lines);
+            }
+            if lines.is_empty() {
+                return String::new();
+            }
+            format!("{}\n\n", lines.join("\n"))
+        }
+        OutputFormat::Html => {
+            if items.is_empty() {
+                return String::new();
+            }
+            let mut html = String::new();
+            push_html(items, &mut html);
+            html.push('\n');
+            html
+        }
+        OutputFormat::Latex => {
+            if items.is_empty() {
+                return String::new();
+            }
+            let mut latex = String::new();
+            push_latex(items, 0, &mut latex);
+            latex.push('\n');
+            latex
+        }
+        OutputFormat::Asciidoc => {
+            let mut lines = Vec::new();
+            push_asciidoc(items, 0, &mut lines);
+            if lines.is_empty() {
+                return String::new();
+            }
+            format!("{}\n\n", lines.join("\n"))
+        }
+        OutputFormat::Rst => {
+            let mut lines = Vec::new();
+            push_rst(items, 0, &mut lines);
+            let rst = lines.join("\n");
+            if rst.trim().is_empty() {
+                return String::new();
+            }
+            format!("{}\n\n", rst.trim_end())
+        }
+        OutputFormat::Org => {
+            let mut lines = Vec::new();
+            push_org(items, 0, &mut lines);
+            if lines.is_empty() {
+                return String::new();
+            }
+            format!("{}\n\n", lines.join("\n"))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(number: Option<u32>, text: &str, children: Vec<ListItem>) -> ListItem {
+        ListItem {
+            number,
+            text: text.to_string(),
+            children,
+        }
+    }
+
+    #[test]
+    fn nested_items_are_indented_to_the_parent_text() {
+        let items = vec![
+            item(Some(1), "one", vec![]),
+            item(
+                Some(2),
+                "two",
+                vec![
+                    item(Some(1), "sub a", vec![item(None, "deep", vec![])]),
+                    item(Some(2), "sub b", vec![]),
+                ],
+            ),
+            item(Some(10), "ten", vec![item(None, "bullet", vec![])]),
+        ];
+        let options = ConvertOptions::default();
+        assert_eq!(
+            render_list(&items, &options),
+            "1. one\n2. two\n   1. sub a\n      - deep\n   2. sub b\n10. ten\n    - bullet\n\n"
+        );
+    }
+
+    #[test]
+    fn children_of_an_empty_item_follow_the_previous_marker() {
+        let items = vec![
+            item(Some(3), "three", vec![]),
+            item(None, "", vec![item(None, "nested", vec![])]),
+        ];
+        assert_eq!(
+            render_list(&items, &ConvertOptions::default()),
+            "3. three\n   - nested\n\n"
+        );
+    }
+
+    fn block_types(texts: &[&str]) -> Vec<String> {
+        let blocks: Vec<Block> = texts
+            .iter()
+            .enumerate()
+            .map(|(i, text)| {
+                let y = 100.0 + 30.0 * i as f64;
+                let block = crate::testing::text_block("text", [50.0, y, 550.0, y + 20.0], text);
+                serde_json::from_value(block).unwrap()
+            })
+            .collect();
+        group_list_runs(&blocks, true)
+            .iter()
+            .map(|block| match &block.blocks {
+                Some(items) => format!("list of {}", items.len()),
+                None => block.block_type.clone(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn markers_need_a_delimiter_and_a_space() {
+        let value = |text| parse_marker(text).map(|(marker, rest)| (marker.value, rest));
+        assert_eq!(value("3. Scope"), Some((3, "Scope")));
+        assert_eq!(value("(b) Second"), Some((2, "Second")));
+        assert_eq!(value("二、 标题"), None);
+        assert_eq!(value("2、标题"), Some((2, "标题")));
+        assert_eq!(value("3.5 倍"), None);
+        assert_eq!(value("1234. Too long"), None);
+        assert_eq!(strip_bullet("• item"), "item");
+        assert_eq!(strip_bullet("-5 degrees"), "-5 degrees");
+    }
+
+    #[test]
+    fn runs_of_three_matching_blocks_become_a_list() {
+        assert_eq!(
+            block_types(&["• a", "• b", "• c", "after", "– d", "– e"]),
+            ["list of 3", "text", "text", "text"]
+        );
+        // 符号不同的块结束当前的列表
+        assert_eq!(
+            block_types(&["• a", "• b", "▪ c", "▪ d", "▪ e"]),
+            ["text", "text", "list of 3"]
+        );
+        // 编号必须逐一递增
+        assert_eq!(
+            block_types(&["3. a", "5. b", "4. c", "1) d", "2) e", "3) f", "4) g"]),
+            ["text", "text", "text", "list of 4"]
+        );
+    }
+}
@@ -1,587 +1,3022 @@
-use base64::{Engine as _, engine::general_purpose};
-use serde::Deserialize;
+use clap::builder::RangedU64ValueParser;
+use clap::parser::ValueSource;
+use clap::{
+    Arg, ArgAction, ArgMatches, Args, CommandFactory, FromArgMatches, Parser, Subcommand, ValueEnum,
+};
+use indicatif::{ProgressBar, ProgressStyle};
+use mineru_json_to_md::{
+    AnchorStyle, BlockCount, CaptionPosition, Checkpoint, ColumnOrder, ConvertError,
+    ConvertOptions, ConvertStats, CssMode, DEFAULT_FOOTER, DividerStyle, Document, FigureStyle,
+    Flavor, FootnoteStyle, ImageOutput, IndexStyle, InlineMathStyle, InputFormat, LayoutJson,
+    MathDelimiters, OutlineEntry, OutputFile, OutputFormat, OversizePolicy, PageFile, PageLabel,
+    PageSelection, ProgressCallback, SplitLevel, TableExport, TableFormat, Timings, Validation,
+    Warning, WriteOutput, convert_documents, convert_documents_split,
+    convert_documents_to_writer_resumable, extract_images, parse_layout, parse_layout_lenient,
+    referenced_images, relative_link_prefix, validate_layout,
+};
+use notify::{EventKind, RecursiveMode, Watcher};
+use serde::{Deserialize, Serialize};
+use std::any::TypeId;
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::env;
+use std::ffi::OsString;
+use std::fmt;
 use std::fs;
+use std::hash::{DefaultHasher, Hasher};
+use std::io::{self, IsTerminal, Read, Seek, SeekFrom, Write};
 use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+// ==================== 命令行参数 ====================
+
+#[derive(Debug, Clone, Parser)]
+#[command(
+    version,
+    about = "Convert MinerU layout.json to Markdown",
+    args_conflicts_with_subcommands = true,
+    subcommand_negates_reqs = true,
+    args_override_self = true,
+    after_help = "Examples:\n  mineru-json-to-md layout.json output.md\n  mineru-json-to-md part1_middle.json part2_middle.json -o book.md"
+)]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    /// Print more while converting: -v lists each rendered page, -vv also its block counts
+    #[arg(short, long, action = ArgAction::Count, global = true)]
+    verbose: u8,
+
+    /// Print nothing but errors (no progress bar, warnings or summary)
+    #[arg(short, long, global = true, conflicts_with = "verbose")]
+    quiet: bool,
+
+    /// MinerU layout.json (middle.json) files to convert, or "-" to read from stdin; several
+    /// inputs are merged into one document. Without -o, a last argument that is not a .json
+    /// file is taken as the output Markdown file ("-" for stdout). The output defaults to the
+    /// first input with a .md extension, or stdout when reading from stdin
+    #[arg(value_name = "INPUT", required_unless_present = "print_config")]
+    inputs: Vec<PathBuf>,
+
+    /// Output file, or the output directory with --split-by
+    #[arg(short, long, visible_alias = "out-dir", value_name = "FILE")]
+    output: Option<PathBuf>,
+
+    /// Directory that image paths in the JSON are relative to, for every input (defaults to
+    /// each input's own directory, or the current directory when reading from stdin); images
+    /// not found there are also looked up in images/ and ../images/
+    #[arg(long, visible_alias = "image-root", value_name = "DIR")]
+    base_path: Option<PathBuf>,
+
+    /// Copy images into this directory and link them instead of embedding Base64 (defaults to
+    /// "attachments" next to the output file with --flavor obsidian)
+    #[arg(long, value_name = "DIR")]
+    images_dir: Option<PathBuf>,
+
+    /// With --images-dir, still embed images of at most this many bytes as Base64 and copy only
+    /// larger ones (65536 when given without a value; 0 copies every image)
+    #[arg(long, value_name = "BYTES", num_args = 0..=1, default_missing_value = "65536",
+          requires = "images_dir")]
+    embed_threshold: Option<usize>,
+
+    /// Largest image, in bytes, embedded as Base64; larger ones are handled per --oversize-policy
+    /// (default unlimited; images copied with --images-dir are not affected)
+    #[arg(long, value_name = "BYTES")]
+    max_image_bytes: Option<usize>,
+
+    /// What happens to embedded images above --max-image-bytes
+    #[arg(long, value_enum, default_value_t = OversizePolicyArg::Downscale)]
+    oversize_policy: OversizePolicyArg,
+
+    /// JPEG quality used when an oversize image is downscaled (PNG is kept for images with
+    /// transparency)
+    #[arg(long, value_name = "1-100", default_value_t = 85,
+          value_parser = clap::value_parser!(u8).range(1..=100))]
+    image_quality: u8,
+
+    /// Write embedded SVG images as inline <svg> markup instead of Base64 data URIs (smaller and
+    /// searchable; styled flavor and HTML output only)
+    #[arg(long, conflicts_with = "images_dir")]
+    inline_svg: bool,
+
+    /// Always embed the table screenshot instead of MinerU's table HTML
+    #[arg(long)]
+    tables_as_image: bool,
+
+    /// How recognized table HTML is written: as-is, or converted to Markdown pipe tables
+    /// (tables that cannot be converted keep their HTML, with a warning)
+    #[arg(long, value_enum, default_value_t = TableFormatArg::Html)]
+    tables: TableFormatArg,
+
+    /// Also write each table that has recognized HTML to DIR as a CSV file (page012_table01.csv,
+    /// with a .json note beside it) and link it under the table
+    #[arg(long, value_name = "DIR", conflicts_with = "tables_as_image")]
+    export_tables: Option<PathBuf>,
+
+    /// How figures and tables are written in Markdown output: styled HTML, or Pandoc-native
+    /// images with `{#fig-N}` ids, pipe tables with `Table:` captions and `:::` divs. Pandoc
+    /// style also converts tables to pipe tables and prefers LaTeX for display equations
+    #[arg(long, value_enum, default_value_t = FigureStyleArg::Html)]
+    figure_style: FigureStyleArg,
+
+    /// Where figure and table captions go relative to the image or table
+    #[arg(long, value_enum, default_value_t = CaptionPositionArg::Auto)]
+    caption_position: CaptionPositionArg,
+
+    /// Limit the height of display-equation images to this many pixels (by default they keep
+    /// their natural size, scaled down to the page width)
+    #[arg(long, value_name = "PX")]
+    equation_max_height: Option<u32>,
+
+    /// Write display equations as $$ LaTeX when MinerU provides both LaTeX and an image
+    #[arg(long)]
+    prefer_equation_latex: bool,
+
+    /// Delimiters written around inline and display equations
+    #[arg(long, value_enum, default_value_t = MathDelimitersArg::Dollars)]
+    math_delimiters: MathDelimitersArg,
+
+    /// How inline equations are written in Markdown output
+    #[arg(long, value_enum, default_value_t = InlineMathStyleArg::Delimiters)]
+    inline_math_style: InlineMathStyleArg,
+
+    /// Omit the <style> block at the top of the document
+    #[arg(long)]
+    no_style: bool,
+
+    /// How generated HTML (figures, page dividers, header notes, footnote boxes) is styled
+    #[arg(long, value_enum, default_value_t = CssModeArg::Inline)]
+    css_mode: CssModeArg,
+
+    /// Omit the "Generated by" footer at the end of the document
+    #[arg(long)]
+    no_footer: bool,
+
+    /// Text of the footer at the end of the document (written as-is)
+    #[arg(long, value_name = "TEXT", conflicts_with_all = ["no_footer", "bare"])]
+    footer_text: Option<String>,
+
+    /// Omit both the <style> block and the footer (same as --no-style --no-footer)
+    #[arg(long)]
+    bare: bool,
+
+    /// Omit the divider printed after every page
+    #[arg(long)]
+    no_page_dividers: bool,
+
+    /// Replace the dividers of consecutive empty pages (including pages whose only content is
+    /// a hidden header or footer) with a single "Pages 14–17 (intentionally blank)" divider
+    #[arg(long, conflicts_with = "no_page_dividers")]
+    collapse_empty_pages: bool,
+
+    /// Page divider label template: the letter n in curly braces is replaced with the page
+    /// number, so "Page" followed by that placeholder gives "Page 3"
+    #[arg(long, value_name = "TEMPLATE")]
+    page_label: Option<PageLabel>,
+
+    /// How page dividers look in Markdown and HTML output
+    #[arg(long, value_enum, default_value_t = DividerStyleArg::Full, conflicts_with = "no_page_dividers")]
+    divider_style: DividerStyleArg,
+
+    /// Omit running page headers
+    #[arg(long)]
+    skip_headers: bool,
+
+    /// Omit page footnotes
+    #[arg(long)]
+    skip_footnotes: bool,
+
+    /// Omit running page footers
+    #[arg(long)]
+    skip_footers: bool,
+
+    /// Print a header (or footer) only once when it repeats on more than three consecutive pages
+    #[arg(long)]
+    dedupe_headers: bool,
+
+    /// Tidy OCR spacing in headings: collapse repeated spaces and drop spaces before punctuation
+    /// and inside leading numbering ("3 . 2" becomes "3.2"). The TOC and anchors use the tidied
+    /// text
+    #[arg(long)]
+    normalize_headings: bool,
+
+    /// With --normalize-headings, turn ALL-CAPS Latin headings into title case; acronyms of up
+    /// to four letters, Roman numerals and CJK text are left alone
+    #[arg(long, requires = "normalize_headings")]
+    title_case_headings: bool,
+
+    /// Number headings as "1.", "1.1", "1.1.1" in the body and the table of contents (headings
+    /// already numbered, front matter such as "Abstract" and sections such as "References" are
+    /// left alone)
+    #[arg(long)]
+    number_headings: bool,
+
+    /// Prefix captions with "Figure N." / "Table N." (unless already numbered) and give each an
+    /// anchor (fig-N, tab-N); an existing number is kept for the anchor and later figures continue from it
+    #[arg(long)]
+    number_figures: bool,
+
+    /// Emit a List of Figures and List of Tables after the table of contents
+    #[arg(long, requires = "number_figures")]
+    list_of_figures: bool,
+
+    /// Drop text blocks rotated by 90 or 270 degrees (usually watermarks or spine text)
+    #[arg(long)]
+    drop_rotated_text: bool,
+
+    /// Keep hyphens at line ends instead of joining the split word
+    #[arg(long)]
+    no_dehyphenate: bool,
+
+    /// Keep newlines, control characters, zero-width characters and non-breaking or other
+    /// special spaces in text spans as-is instead of normalizing them to plain spaces
+    #[arg(long)]
+    no_normalize_whitespace: bool,
+
+    /// Write text spans as-is instead of escaping Markdown characters such as *, _, | and a
+    /// leading # (for MinerU output that already contains intentional Markdown); HTML special
+    /// characters are still escaped unless --allow-inline-html is given
+    #[arg(long)]
+    no_escape: bool,
+
+    /// Keep HTML tags and character entities found in text spans (such as <br>, <i> and &amp;)
+    /// instead of escaping them, for MinerU post-processing that inserts markup on purpose
+    #[arg(long)]
+    allow_inline_html: bool,
+
+    /// Put an HTML comment with the page, bounding box, type and index of the source block
+    /// before every rendered block, e.g. <!-- p12 bbox=[56.1,102.4,540.0,160.8] type=text idx=7 -->
+    #[arg(long)]
+    provenance: bool,
+
+    /// Highlight text spans whose recognition score is below this value: <mark> with the score
+    /// as a tooltip (==text== in the gfm flavor, ##text## in AsciiDoc); the summary reports their
+    /// count and the ten lowest-scoring spans. Spans without a score are not affected
+    #[arg(long, value_name = "SCORE")]
+    min_confidence: Option<f64>,
+
+    /// Replace text spans whose recognition score is below this value with [?], and drop blocks
+    /// whose text is all below it
+    #[arg(long, value_name = "SCORE")]
+    drop_below: Option<f64>,
+
+    /// Render reference lists (ref_text blocks and text under a "References" title) as-is,
+    /// instead of one entry per line with linked DOIs and URLs
+    #[arg(long)]
+    no_reference_formatting: bool,
+
+    /// Turn URLs (http(s)://, www.), email addresses and DOIs (10.xxxx/yyyy) in paragraphs into
+    /// links, rejoining URLs broken across lines
+    #[arg(long)]
+    autolink: bool,
+
+    /// Insert a space between CJK characters and adjacent Latin letters or digits
+    /// ("使用GPU加速" becomes "使用 GPU 加速")
+    #[arg(long)]
+    pangu_spacing: bool,
+
+    /// Keep blocks in JSON array order instead of sorting by their reading-order index
+    #[arg(long)]
+    no_reorder: bool,
+
+    /// How to order blocks on pages without reading-order indexes (older MinerU output): auto
+    /// detects two-column pages from the bounding boxes, off keeps JSON array order
+    #[arg(long, value_enum, default_value_t = ColumnOrderArg::Auto)]
+    column_order: ColumnOrderArg,
+
+    /// Join a paragraph that runs on from the end of one page to the start of the next (no
+    /// sentence-final punctuation before the break, a lowercase or CJK start after it) and put
+    /// the page divider after the joined paragraph
+    #[arg(long)]
+    merge_cross_page: bool,
+
+    /// Drop blocks that MinerU recognized twice: same type on the same page, bounding boxes
+    /// overlapping by more than 0.8 IoU and text over 95% similar. The longer one is kept and a
+    /// warning names the dropped text; blocks under 20 characters are never compared
+    #[arg(long)]
+    dedupe_blocks: bool,
+
+    /// Keep consecutive title blocks separate instead of joining a heading that wrapped onto a
+    /// second block (same level, vertically adjacent, no sentence-final punctuation)
+    #[arg(long)]
+    no_merge_titles: bool,
+
+    /// Turn runs of three or more consecutive text blocks that start with the same bullet (•, -,
+    /// –) or numbering style ("1.", "(a)") into one list, taking the prefixes off. Numbered runs
+    /// must count up one at a time
+    #[arg(long)]
+    detect_lists: bool,
+
+    /// Treat text blocks indented past the column's usual left margin by more than this fraction
+    /// of the page width as block quotes (0.05 when given without a value). Blocks MinerU marks
+    /// with sub_type "quote" are always block quotes
+    #[arg(long, value_name = "FRACTION", num_args = 0..=1, default_missing_value = "0.05")]
+    detect_quotes: Option<f64>,
+
+    /// How page footnotes are rendered
+    #[arg(long, value_enum, default_value_t = FootnoteStyleArg::Div)]
+    footnote_style: FootnoteStyleArg,
+
+    /// Put each page footnote in small print right after the paragraph directly above it in the
+    /// same column; footnotes spanning both columns or without a clear paragraph above stay at
+    /// the bottom of the page
+    #[arg(long)]
+    footnotes_inline: bool,
+
+    /// How index blocks (back-of-book indexes, contents pages) are rendered
+    #[arg(long, value_enum, default_value_t = IndexStyleArg::Lines)]
+    index_style: IndexStyleArg,
+
+    /// How Markdown heading anchors are generated (styled and gfm only)
+    #[arg(long, value_enum, default_value_t = AnchorsArg::Explicit)]
+    anchors: AnchorsArg,
+
+    /// Markdown flavor of the output
+    #[arg(long, value_enum, default_value_t = FlavorArg::Styled)]
+    flavor: FlavorArg,
+
+    /// Output format; html writes a standalone HTML5 document, text plain paragraphs, latex a
+    /// .tex body with images copied to "<output name>_images" next to the output unless
+    /// --images-dir is given, json a versioned list of content items (default output extension .html / .txt /
+    /// .tex / .doc.json)
+    #[arg(long, value_enum, default_value_t = FormatArg::Markdown)]
+    format: FormatArg,
+
+    /// Write one file per chapter into the output directory, plus an index with the table of
+    /// contents, or one file per page plus manifest.json (the output defaults to the first
+    /// input without its extension)
+    #[arg(long, value_enum, value_name = "LEVEL")]
+    split_by: Option<SplitByArg>,
+
+    /// Load MathJax in the HTML output so equations are typeset (only with --format html)
+    #[arg(long)]
+    mathjax: bool,
+
+    /// Wrap the LaTeX output in a preamble and document environment so it compiles on its own
+    /// (only with --format latex); documents with CJK text use xeCJK and need xelatex
+    #[arg(long)]
+    latex_standalone: bool,
+
+    /// Prefix titles with their level in brackets, e.g. "[2] Methods" (only with --format text)
+    #[arg(long)]
+    title_levels: bool,
+
+    /// Write "[figure: caption]", "[table: caption]" and "[equation: LaTeX]" lines instead of
+    /// skipping figures, tables and display equations (only with --format text)
+    #[arg(long)]
+    placeholders: bool,
+
+    /// Input JSON format (detected from the top-level structure by default)
+    #[arg(long, value_enum, default_value_t = InputFormatArg::Auto)]
+    input_format: InputFormatArg,
+
+    /// Skip pages that fail to parse (with a warning naming the page) instead of aborting
+    #[arg(long)]
+    lenient: bool,
+
+    /// Only convert these pages, e.g. "1-5,12,20-" (numbering starts at 1)
+    #[arg(long, value_name = "RANGES")]
+    pages: Option<PageSelection>,
+
+    /// Number of threads used to render pages (defaults to one per CPU; 1 renders sequentially)
+    #[arg(long, value_name = "N", value_parser = RangedU64ValueParser::<usize>::new().range(1..))]
+    threads: Option<usize>,
+
+    /// Put a right-aligned link back to the table of contents at the end of each level-1
+    /// section, with this text ("↑ 目录" when given without a value)
+    #[arg(long, value_name = "TEXT", num_args = 0..=1, default_missing_value = "↑ 目录")]
+    back_to_top: Option<String>,
+
+    /// Insert this text between merged input files (e.g. "---"; "---" when given without a value)
+    #[arg(long, value_name = "TEXT", num_args = 0..=1, default_missing_value = "---")]
+    chunk_separator: Option<String>,
+
+    /// Exit with an error status when any image is missing (output is still written)
+    #[arg(long)]
+    fail_on_missing_images: bool,
+
+    /// Fail the conversion when a block type the converter does not know appears, instead of
+    /// rendering it as plain text
+    #[arg(long)]
+    strict_blocks: bool,
+
+    /// Also write the end-of-run statistics to this file as JSON
+    #[arg(long, value_name = "FILE")]
+    stats: Option<PathBuf>,
+
+    /// Write a JSON conversion report to this file at the end: per-page block counts, warnings
+    /// by category, phase durations and the effective options. It is written even when the
+    /// conversion fails or skips pages, with "completed": false
+    #[arg(long, value_name = "FILE")]
+    report: Option<PathBuf>,
+
+    /// Exit with status 3 when a warning of one of these categories was reported (e.g.
+    /// missing_image, unknown_block, empty_page, bbox_invalid); "CATEGORY:N" allows up to N
+    #[arg(long, value_name = "CATEGORY[:N],...", value_delimiter = ',')]
+    fail_on: Vec<FailOn>,
+
+    /// Print how long each phase took: parsing the input, rendering pages, encoding images
+    /// (summed over worker threads) and writing the output
+    #[arg(long)]
+    timing: bool,
+
+    /// Also write the document outline to this file: every heading with its level, page, anchor
+    /// and character offset in the output
+    #[arg(long, value_name = "FILE", conflicts_with = "split_by")]
+    outline: Option<PathBuf>,
+
+    /// Format of the --outline file
+    #[arg(
+        long,
+        value_enum,
+        value_name = "FORMAT",
+        default_value = "json",
+        requires = "outline"
+    )]
+    outline_format: OutlineFormatArg,
+
+    /// Exit with status 3 when any warning was reported (output is still written)
+    #[arg(long)]
+    strict: bool,
+
+    /// Keep converted output in this directory and reuse it when the input JSON, the options,
+    /// the converter version and the referenced image files are all unchanged; conversions
+    /// that report warnings, --split-by output and images copied to a directory are not cached
+    #[arg(long, value_name = "DIR")]
+    cache_dir: Option<PathBuf>,
+
+    /// Always convert, without reading or writing --cache-dir (e.g. one set in a config file)
+    #[arg(long)]
+    no_cache: bool,
+
+    /// Delete the entries stored in --cache-dir before converting
+    #[arg(long, requires = "cache_dir")]
+    cache_clear: bool,
+
+    /// Record progress in <OUTPUT>.mineru-md.state every few seconds while converting, and
+    /// continue an interrupted conversion from the last recorded point instead of starting over. Refuses to resume when
+    /// the input, the options or the partial output changed since the interrupted run
+    #[arg(long, conflicts_with = "split_by")]
+    resume: bool,
+
+    /// Keep running and convert again whenever an input file or an image next to it changes
+    /// (Ctrl-C to stop)
+    #[arg(long)]
+    watch: bool,
+
+    /// Read default options from this TOML file instead of looking for mineru-md.toml next to
+    /// the first input and in the current directory; keys are the long flag names, and
+    /// options given on the command line win
+    #[arg(long, value_name = "FILE")]
+    config: Option<PathBuf>,
+
+    /// Print the effective options (config file merged with the command line) as TOML and exit;
+    /// defaults are commented out so the output can be used as a --config file
+    #[arg(long)]
+    print_config: bool,
+}
+
+#[derive(Debug, Clone, Subcommand)]
+enum Command {
+    /// Copy every referenced image into a directory and write manifest.json, without
+    /// generating Markdown
+    ExtractImages(ExtractImagesArgs),
+    /// Check that a JSON file converts cleanly, without embedding or copying images: reports
+    /// the guessed MinerU version, page and block counts, unknown block and span types,
+    /// missing images, malformed bboxes, empty pages and every conversion warning. Exits with
+    /// 0 when clean, 1 when there are warnings and 2 when the file or a page cannot be parsed
+    Validate(ValidateArgs),
+}
+
+#[derive(Debug, Clone, Args)]
+struct ValidateArgs {
+    /// MinerU layout.json (middle.json) or content_list.json, or "-" to read from stdin
+    input: PathBuf,
 
-// ==================== 类型定义 ====================
+    /// Directory that image paths in the JSON are relative to (defaults to the input's
+    /// directory, or the current directory when reading from stdin); images not found there
+    /// are also looked up in images/ and ../images/
+    #[arg(long, visible_alias = "image-root", value_name = "DIR")]
+    base_path: Option<PathBuf>,
 
-#[derive(Debug, Deserialize, Clone)]
-struct Span {
-    bbox: Vec<f64>,
-    #[serde(rename = "type")]
-    span_type: String,
-    content: Option<String>,
-    image_path: Option<String>,
+    /// Input JSON format (detected from the top-level structure by default)
+    #[arg(long, value_enum, default_value_t = InputFormatArg::Auto)]
+    input_format: InputFormatArg,
+
+    /// Print the findings as JSON instead of a report
+    #[arg(long)]
+    json: bool,
 }
 
-#[derive(Debug, Deserialize, Clone)]
-struct Line {
-    bbox: Vec<f64>,
-    spans: Vec<Span>,
+#[derive(Debug, Clone, Args)]
+struct ExtractImagesArgs {
+    /// MinerU layout.json (middle.json) or content_list.json, or "-" to read from stdin
+    input: PathBuf,
+
+    /// Directory the images and manifest.json are written to
+    #[arg(long, value_name = "DIR")]
+    out: PathBuf,
+
+    /// Directory that image paths in the JSON are relative to (defaults to the input's
+    /// directory, or the current directory when reading from stdin); images not found there
+    /// are also looked up in images/ and ../images/
+    #[arg(long, visible_alias = "image-root", value_name = "DIR")]
+    base_path: Option<PathBuf>,
+
+    /// Input JSON format (detected from the top-level structure by default)
+    #[arg(long, value_enum, default_value_t = InputFormatArg::Auto)]
+    input_format: InputFormatArg,
+
+    /// Skip pages that fail to parse (with a warning naming the page) instead of aborting
+    #[arg(long)]
+    lenient: bool,
+
+    /// Keep blocks in JSON array order instead of sorting by their reading-order index
+    #[arg(long)]
+    no_reorder: bool,
 }
 
-#[derive(Debug, Deserialize, Clone)]
-struct Block {
-    bbox: Vec<f64>,
-    #[serde(rename = "type")]
-    block_type: String,
-    angle: Option<f64>,
-    lines: Option<Vec<Line>>,
-    blocks: Option<Vec<Block>>,
-    index: Option<i32>,
-    sub_type: Option<String>,
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum InputFormatArg {
+    /// Array → content_list.json, object → middle.json
+    Auto,
+    /// middle.json / layout.json with pdf_info
+    Middle,
+    /// Flat content_list.json array
+    ContentList,
 }
 
-#[derive(Debug, Deserialize)]
-struct PageInfo {
-    para_blocks: Vec<Block>,
-    discarded_blocks: Vec<Block>,
-    page_size: (f64, f64),
-    page_idx: usize,
+impl From<InputFormatArg> for InputFormat {
+    fn from(format: InputFormatArg) -> Self {
+        match format {
+            InputFormatArg::Auto => InputFormat::Auto,
+            InputFormatArg::Middle => InputFormat::Middle,
+            InputFormatArg::ContentList => InputFormat::ContentList,
+        }
+    }
 }
 
-#[derive(Debug, Deserialize)]
-struct LayoutJson {
-    pdf_info: Vec<PageInfo>,
-    #[serde(rename = "_backend")]
-    backend: Option<String>,
-    #[serde(rename = "_version_name")]
-    version_name: Option<String>,
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum FlavorArg {
+    /// Markdown mixed with inline-styled HTML
+    Styled,
+    /// Plain GitHub-flavored Markdown without style blocks or HTML wrappers
+    Gfm,
+    /// Markdown for Obsidian: images written to an attachments folder and embedded with
+    /// ![[file]], headers and page footnotes as callouts, [[#heading]] links in the contents
+    Obsidian,
 }
 
-#[derive(Debug, Clone)]
-struct TocEntry {
-    title: String,
-    page_idx: usize,
-    anchor_id: String,
-    level: usize,
+impl From<FlavorArg> for Flavor {
+    fn from(flavor: FlavorArg) -> Self {
+        match flavor {
+            FlavorArg::Styled => Flavor::Styled,
+            FlavorArg::Gfm => Flavor::Gfm,
+            FlavorArg::Obsidian => Flavor::Obsidian,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum FormatArg {
+    /// Markdown in the selected --flavor
+    Markdown,
+    /// Standalone HTML5 document with real headings, lists and paragraphs
+    Html,
+    /// Plain text paragraphs for full-text indexing, without images, headers or footnotes
+    Text,
+    /// LaTeX body with \section headings, figure and table floats and \clearpage between
+    /// pages (--no-page-dividers drops them), for \input into an existing template
+    Latex,
+    /// Versioned JSON of flattened content items (headings, paragraphs, lists, figures,
+    /// tables, equations, footnotes) for downstream chunking; default extension .doc.json
+    Json,
+    /// AsciiDoc with == section titles, [[id]] anchors, image:: figures, |=== tables,
+    /// stem equations and footnote:[] page footnotes, for Antora and other AsciiDoc toolchains
+    Asciidoc,
+    /// reStructuredText with underlined section titles, .. _id: targets, .. figure:: images,
+    /// raw HTML tables, :math: equations and auto-numbered footnotes, for Sphinx and docutils
+    Rst,
+    /// Emacs Org with * headlines, CUSTOM_ID anchors, [[file:]] images with #+CAPTION, Org tables,
+    /// $…$ and \[…\] equations and [fn:] footnotes; images are written to a directory
+    Org,
 }
 
-struct DiscardedBlocksCategory {
-    headers: Vec<Block>,
-    footnotes: Vec<Block>,
+impl From<FormatArg> for OutputFormat {
+    fn from(format: FormatArg) -> Self {
+        match format {
+            FormatArg::Markdown => OutputFormat::Markdown,
+            FormatArg::Html => OutputFormat::Html,
+            FormatArg::Text => OutputFormat::Text,
+            FormatArg::Latex => OutputFormat::Latex,
+            FormatArg::Json => OutputFormat::Json,
+            FormatArg::Asciidoc => OutputFormat::Asciidoc,
+            FormatArg::Rst => OutputFormat::Rst,
+            FormatArg::Org => OutputFormat::Org,
+        }
+    }
 }
 
-// ==================== 工具函数 ====================
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum SplitByArg {
+    /// Start a new file at every level-1 heading
+    Heading1,
+    /// Start a new file at every level-1 or level-2 heading
+    Heading2,
+    /// Write each page to page_0001.md, page_0002.md, ... (prefixed 01-, 02-, ... when
+    /// merging several inputs) without the style header, table of contents or footer, and
+    /// list them in manifest.json: [{"page_idx": 0, "document": 0, "file": "page_0001.md",
+    /// "headings": ["..."], "chars": 1234}], where page_idx and document count from 0 and
+    /// chars is the character count of the file
+    Page,
+}
 
-fn image_to_base64(image_path: &str, base_path: &Path) -> Option<String> {
-    let full_path = base_path.join(image_path);
-    if !full_path.exists() {
-        return None;
+impl From<SplitByArg> for SplitLevel {
+    fn from(level: SplitByArg) -> Self {
+        match level {
+            SplitByArg::Heading1 => SplitLevel::Heading1,
+            SplitByArg::Heading2 => SplitLevel::Heading2,
+            SplitByArg::Page => SplitLevel::Page,
+        }
     }
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum TableFormatArg {
+    /// MinerU's table HTML, unchanged
+    Html,
+    /// GitHub pipe tables; merged cells are repeated
+    Markdown,
+}
 
-    match fs::read(&full_path) {
-        Ok(data) => {
-            let ext = full_path
-                .extension()
-                .and_then(|s| s.to_str())
-                .unwrap_or("jpg")
-                .to_lowercase();
-            let mime_type = match ext.as_str() {
-                "png" => "image/png",
-                "gif" => "image/gif",
-                "webp" => "image/webp",
-                _ => "image/jpeg",
-            };
-            let encoded = general_purpose::STANDARD.encode(&data);
-            Some(format!("data:{};base64,{}", mime_type, encoded))
+impl From<TableFormatArg> for TableFormat {
+    fn from(format: TableFormatArg) -> Self {
+        match format {
+            TableFormatArg::Html => TableFormat::Html,
+            TableFormatArg::Markdown => TableFormat::Markdown,
         }
-        Err(_) => None,
     }
 }
 
-fn escape_html(text: &str) -> String {
-    text.replace('&', "&amp;")
-        .replace('<', "&lt;")
-        .replace('>', "&gt;")
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum FigureStyleArg {
+    /// Styled <figure> HTML, or images with caption paragraphs for gfm/obsidian
+    Html,
+    /// Pandoc Markdown: ![caption](path){#fig-N}, `Table:` captions and fenced divs
+    Pandoc,
 }
 
-fn generate_anchor_id(title: &str, page_idx: usize) -> String {
-    let slug: String = title
-        .chars()
-        .map(|c| {
-            if c.is_alphanumeric()
-                || c.is_ascii_alphabetic()
-                || (c >= '\u{4e00}' && c <= '\u{9fa5}')
-            {
-                c
-            } else {
-                '-'
-            }
-        })
-        .collect::<String>()
-        .trim_matches('-')
-        .chars()
-        .take(50)
-        .collect();
+impl From<FigureStyleArg> for FigureStyle {
+    fn from(style: FigureStyleArg) -> Self {
+        match style {
+            FigureStyleArg::Html => FigureStyle::Html,
+            FigureStyleArg::Pandoc => FigureStyle::Pandoc,
+        }
+    }
+}
 
-    format!(
-        "toc-{}-{}",
-        page_idx,
-        if slug.is_empty() { "title" } else { &slug }
-    )
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum DividerStyleArg {
+    /// A centered page label between two rules
+    Full,
+    /// A single thin rule with the page number as a right-aligned superscript
+    Compact,
+    /// Only an invisible <!-- page 13 --> comment, kept for tooling
+    Margin,
 }
 
-// ==================== 内容提取 ====================
+impl From<DividerStyleArg> for DividerStyle {
+    fn from(style: DividerStyleArg) -> Self {
+        match style {
+            DividerStyleArg::Full => DividerStyle::Full,
+            DividerStyleArg::Compact => DividerStyle::Compact,
+            DividerStyleArg::Margin => DividerStyle::Margin,
+        }
+    }
+}
 
-fn extract_text_from_block(block: &Block) -> String {
-    let mut texts = Vec::new();
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum CssModeArg {
+    /// Inline style="…" attributes on every element
+    Inline,
+    /// mineru-* class names, defined once in the <style> block
+    Classes,
+    /// mineru-* class names and no <style> block, for your own stylesheet
+    None,
+}
 
-    if let Some(lines) = &block.lines {
-        for line in lines {
-            for span in &line.spans {
-                if let Some(content) = &span.content {
-                    texts.push(content.clone());
-                }
-            }
+impl From<CssModeArg> for CssMode {
+    fn from(mode: CssModeArg) -> Self {
+        match mode {
+            CssModeArg::Inline => CssMode::Inline,
+            CssModeArg::Classes => CssMode::Classes,
+            CssModeArg::None => CssMode::None,
         }
     }
+}
 
-    if let Some(blocks) = &block.blocks {
-        for sub_block in blocks {
-            texts.push(extract_text_from_block(sub_block));
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum MathDelimitersArg {
+    /// $...$ inline, $$ blocks set off by blank lines
+    Dollars,
+    /// \(...\) inline, \[...\] blocks (Typora and other LaTeX-style renderers)
+    Latex,
+    /// $...$ inline, $$ on their own lines without an extra blank line before (MkDocs)
+    Brackets,
+}
+
+impl From<MathDelimitersArg> for MathDelimiters {
+    fn from(delimiters: MathDelimitersArg) -> Self {
+        match delimiters {
+            MathDelimitersArg::Dollars => MathDelimiters::Dollars,
+            MathDelimitersArg::Latex => MathDelimiters::Latex,
+            MathDelimitersArg::Brackets => MathDelimiters::Brackets,
         }
     }
+}
 
-    texts.join("")
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum InlineMathStyleArg {
+    /// Wrap in --math-delimiters, with a space between $ and an adjacent letter, digit or CJK character
+    Delimiters,
+    /// <span class="math">\(...\)</span>, for renderers that do not pick up $ reliably
+    Html,
 }
 
-// ==================== 行内公式和文本处理 ====================
+impl From<InlineMathStyleArg> for InlineMathStyle {
+    fn from(style: InlineMathStyleArg) -> Self {
+        match style {
+            InlineMathStyleArg::Delimiters => InlineMathStyle::Delimiters,
+            InlineMathStyleArg::Html => InlineMathStyle::Html,
+        }
+    }
+}
 
-fn render_rich_text(block: &Block) -> (String, bool) {
-    let mut html = String::new();
-    let mut has_formula = false;
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum ColumnOrderArg {
+    /// Left column before right column on two-column pages, top to bottom otherwise
+    Auto,
+    /// Keep JSON array order
+    Off,
+}
 
-    if let Some(lines) = &block.lines {
-        for line in lines {
-            for span in &line.spans {
-                match span.span_type.as_str() {
-                    "inline_equation" => {
-                        has_formula = true;
-                        if let Some(content) = &span.content {
-                            html.push_str(&format!("${}", content));
-                            html.push('$');
-                        }
-                    }
-                    "text" => {
-                        if let Some(content) = &span.content {
-                            html.push_str(content);
-                        }
-                    }
-                    _ => {}
-                }
+impl From<ColumnOrderArg> for ColumnOrder {
+    fn from(order: ColumnOrderArg) -> Self {
+        match order {
+            ColumnOrderArg::Auto => ColumnOrder::Auto,
+            ColumnOrderArg::Off => ColumnOrder::Off,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum CaptionPositionArg {
+    /// Keep the order of the caption and body sub-blocks in the JSON
+    Auto,
+    /// Captions above, figure and table footnotes below
+    Above,
+    /// Captions and footnotes below
+    Below,
+}
+
+impl From<CaptionPositionArg> for CaptionPosition {
+    fn from(position: CaptionPositionArg) -> Self {
+        match position {
+            CaptionPositionArg::Auto => CaptionPosition::Auto,
+            CaptionPositionArg::Above => CaptionPosition::Above,
+            CaptionPositionArg::Below => CaptionPosition::Below,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum OversizePolicyArg {
+    /// Re-encode, shrinking the longest side until the image fits
+    Downscale,
+    /// Write the image to "<output name>_images" next to the output and link it by path
+    Link,
+    /// Replace the image with a placeholder noting its original size
+    Skip,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum AnchorsArg {
+    /// An <a id="toc-N-slug"> before each heading, linked from the contents
+    Explicit,
+    /// No <a id>; the contents link to the anchors GitHub and GitLab derive from heading text
+    Github,
+}
+
+impl From<AnchorsArg> for AnchorStyle {
+    fn from(anchors: AnchorsArg) -> Self {
+        match anchors {
+            AnchorsArg::Explicit => AnchorStyle::Explicit,
+            AnchorsArg::Github => AnchorStyle::Github,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum IndexStyleArg {
+    /// One output line per line of the index, with hard line breaks
+    Lines,
+    /// A list of terms and their pages when every line reads "term, pages"; lines otherwise
+    Definitions,
+}
+
+impl From<IndexStyleArg> for IndexStyle {
+    fn from(style: IndexStyleArg) -> Self {
+        match style {
+            IndexStyleArg::Lines => IndexStyle::Lines,
+            IndexStyleArg::Definitions => IndexStyle::Definitions,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum OutlineFormatArg {
+    Json,
+    Yaml,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum FootnoteStyleArg {
+    /// Styled box at the bottom of each page
+    Div,
+    /// Markdown footnotes ([^p3-1]) linked from markers in the text
+    Markdown,
+}
+
+impl From<FootnoteStyleArg> for FootnoteStyle {
+    fn from(style: FootnoteStyleArg) -> Self {
+        match style {
+            FootnoteStyleArg::Div => FootnoteStyle::Div,
+            FootnoteStyleArg::Markdown => FootnoteStyle::Markdown,
+        }
+    }
+}
+
+// ==================== 日志与进度 ====================
+
+/// 输出的详细程度，由 -q / -v / -vv 决定；所有输出都写到 stderr
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Verbosity {
+    /// 只输出错误
+    Quiet,
+    Normal,
+    /// 逐页打印渲染完成的页码
+    Verbose,
+    /// 另外打印每页各类块的数量
+    Debug,
+}
+
+static VERBOSITY: AtomicU8 = AtomicU8::new(Verbosity::Normal as u8);
+
+/// 正在显示的进度条；期间的日志先隐藏进度条再输出，避免两者交错
+static PROGRESS: Mutex<Option<ProgressBar>> = Mutex::new(None);
+
+fn verbosity() -> u8 {
+    VERBOSITY.load(Ordering::Relaxed)
+}
+
+fn log(level: Verbosity, message: fmt::Arguments) {
+    if level as u8 > verbosity() {
+        return;
+    }
+    match &*PROGRESS.lock().unwrap() {
+        Some(bar) => bar.suspend(|| eprintln!("{}", message)),
+        None => eprintln!("{}", message),
+    }
+}
+
+macro_rules! error {
+    ($($arg:tt)*) => { log(Verbosity::Quiet, format_args!($($arg)*)) };
+}
+
+macro_rules! warn {
+    ($($arg:tt)*) => { log(Verbosity::Normal, format_args!($($arg)*)) };
+}
+
+macro_rules! info {
+    ($($arg:tt)*) => { log(Verbosity::Normal, format_args!($($arg)*)) };
+}
+
+macro_rules! verbose {
+    ($($arg:tt)*) => { log(Verbosity::Verbose, format_args!($($arg)*)) };
+}
+
+macro_rules! debug {
+    ($($arg:tt)*) => { log(Verbosity::Debug, format_args!($($arg)*)) };
+}
+
+const PROGRESS_TEMPLATE: &str = "{bar:40} {pos}/{len} pages ({elapsed}, ETA {eta})";
+
+/// 开始显示逐页进度，返回交给转换的回调；给出 `page_blocks` 时同时记录各页的块数。
+/// 不需要任何进度输出、也不记录块数时返回 `None`
+///
+/// 进度条只在 stderr 是终端、输出不写到 stdout 且未指定 -q 时显示；-v / -vv 的逐页日志不受此限制。
+fn start_progress(to_stdout: bool, page_blocks: Option<PageBlocks>) -> Option<ProgressCallback> {
+    let show_bar =
+        verbosity() >= Verbosity::Normal as u8 && !to_stdout && io::stderr().is_terminal();
+    let bar = show_bar.then(|| {
+        let bar = ProgressBar::new(0);
+        if let Ok(style) = ProgressStyle::with_template(PROGRESS_TEMPLATE) {
+            bar.set_style(style);
+        }
+        bar
+    });
+    if bar.is_none() && page_blocks.is_none() && verbosity() < Verbosity::Verbose as u8 {
+        return None;
+    }
+    *PROGRESS.lock().unwrap() = bar.clone();
+    Some(ProgressCallback::new(move |progress| {
+        if let Some(bar) = &bar {
+            bar.set_length(progress.total as u64);
+            bar.inc(1);
+        }
+        verbose!("Rendered page {}", progress.page_idx + 1);
+        debug!("  blocks: {}", block_summary(progress.stats));
+        if let Some(page_blocks) = &page_blocks {
+            page_blocks
+                .lock()
+                .unwrap()
+                .insert(progress.page_idx, progress.stats.blocks.clone());
+        }
+    }))
+}
+
+/// 转换结束，清除进度条
+fn finish_progress() {
+    if let Some(bar) = PROGRESS.lock().unwrap().take() {
+        bar.finish_and_clear();
+    }
+}
+
+// ==================== CLI 入口 ====================
+
+/// 作为输入或输出路径时表示 stdin / stdout
+const STDIO_PATH: &str = "-";
+
+/// --strict 或 --fail-on 的条件成立时的退出码；与命令行用法错误（clap 使用 2）区分开
+const EXIT_WARNINGS: u8 = 3;
+
+fn main() -> ExitCode {
+    let (mut cli, matches, config) = match parse_cli() {
+        Ok(parsed) => parsed,
+        Err(message) => {
+            error!("Error: {}", message);
+            return ExitCode::FAILURE;
+        }
+    };
+    if cli.print_config {
+        return match print_config(&matches, config.as_deref()) {
+            Err(e) if e.kind() != io::ErrorKind::BrokenPipe => {
+                error!("Error: writing config: {}", e);
+                ExitCode::FAILURE
             }
+            _ => ExitCode::SUCCESS,
+        };
+    }
+    let level = match (cli.quiet, cli.verbose) {
+        (true, _) => Verbosity::Quiet,
+        (false, 0) => Verbosity::Normal,
+        (false, 1) => Verbosity::Verbose,
+        (false, _) => Verbosity::Debug,
+    };
+    VERBOSITY.store(level as u8, Ordering::Relaxed);
+    let result = match cli.command.take() {
+        Some(Command::ExtractImages(args)) => run_extract_images(args),
+        Some(Command::Validate(args)) => Ok(run_validate(args)),
+        None if cli.watch => watch(&cli, &matches),
+        None => run(&cli, &matches),
+    };
+    match result {
+        Ok(code) => code,
+        Err(message) => {
+            error!("Error: {}", message);
+            ExitCode::FAILURE
         }
     }
+}
 
-    (html, has_formula)
+/// 文件所在目录；从 stdin 读取时没有所在目录，使用当前目录
+fn input_dir(path: &Path) -> &Path {
+    if path == Path::new(STDIO_PATH) {
+        Path::new(".")
+    } else {
+        path.parent().unwrap_or(Path::new("."))
+    }
+}
+
+/// 从磁盘或 stdin 读取一个输入文件的内容
+fn read_source(path: &Path) -> Result<String, String> {
+    if path == Path::new(STDIO_PATH) {
+        info!("Reading: <stdin>");
+        let mut content = String::new();
+        io::stdin()
+            .read_to_string(&mut content)
+            .map_err(|e| format!("reading stdin: {}", e))?;
+        return Ok(content);
+    }
+    if !path.exists() {
+        return Err(format!("File not found: {}", path.display()));
+    }
+    info!("Reading: {}", path.display());
+    fs::read_to_string(path).map_err(|e| format!("reading file: {}", e))
 }
 
-// ==================== 块渲染 ====================
+/// 从磁盘或 stdin 读取并解析一个输入文件；`lenient` 时跳过无法解析的页面，
+/// 返回的警告已打印
+fn read_input(
+    path: &Path,
+    format: InputFormat,
+    lenient: bool,
+) -> Result<(LayoutJson, Vec<Warning>), String> {
+    parse_source(path, &read_source(path)?, format, lenient)
+}
 
-fn render_title(block: &Block, page_idx: usize) -> (String, Option<TocEntry>) {
-    let text = extract_text_from_block(block).trim().to_string();
-    if text.is_empty() {
-        return (String::new(), None);
+/// 解析读入的输入文件内容；`lenient` 时跳过无法解析的页面，返回的警告已打印
+fn parse_source(
+    path: &Path,
+    json_content: &str,
+    format: InputFormat,
+    lenient: bool,
+) -> Result<(LayoutJson, Vec<Warning>), String> {
+    let parsed = if lenient {
+        parse_layout_lenient(json_content, format)
+    } else {
+        parse_layout(json_content, format).map(|layout| (layout, Vec::new()))
+    };
+    let (layout, warnings) =
+        parsed.map_err(|e| format!("parsing JSON {}: {}", path.display(), e))?;
+    for warning in &warnings {
+        warn!("Warning: {}: {}", path.display(), warning);
     }
+    Ok((layout, warnings))
+}
 
-    let anchor_id = generate_anchor_id(&text, page_idx);
-    let level = if text.chars().count() > 20 { 2 } else { 1 };
+/// Obsidian 风格默认写入图片的目录，位于输出文件旁
+const OBSIDIAN_ATTACHMENTS: &str = "attachments";
 
-    let toc_entry = TocEntry {
-        title: text.clone(),
-        page_idx: page_idx + 1,
-        anchor_id: anchor_id.clone(),
-        level,
+/// 输出文件旁以输出文件名命名的图片目录，如 `paper.md` 的 `paper_images`；输出到 stdout 时为当前目录下的
+/// `output_images`。不与 MinerU 写出图片的 `images` 目录重名
+fn output_images_dir(output_path: &Path) -> PathBuf {
+    let (output_dir, stem) = match (output_path.parent(), output_path.file_stem()) {
+        (Some(dir), Some(stem)) if output_path != Path::new(STDIO_PATH) => {
+            (dir, stem.to_string_lossy())
+        }
+        _ => (Path::new(""), "output".into()),
     };
+    output_dir.join(format!("{}_images", stem))
+}
 
-    // 使用原生 Markdown 标题 + HTML 锚点作为隐藏导航元素
-    let heading_prefix = if level == 1 { "##" } else { "###" };
-    let html = format!(
-        "<a id=\"{}\"></a>\n{} {}\n\n",
-        anchor_id, heading_prefix, text
-    );
+/// 超出大小上限的内联图片写入的目录；只在内联图片且选择 `--oversize-policy link` 时存在
+fn linked_images_dir(cli: &Cli, output_path: &Path) -> Option<PathBuf> {
+    if cli.max_image_bytes.is_none()
+        || cli.oversize_policy != OversizePolicyArg::Link
+        || images_dir(cli, output_path).is_some()
+    {
+        return None;
+    }
+    Some(output_images_dir(output_path))
+}
 
-    (html, Some(toc_entry))
+/// 复制图片的目录：`--images-dir`，或 Obsidian 风格的 Markdown 输出默认的 `attachments`、
+/// LaTeX 与 Org 输出默认的 `<输出文件名>_images`（输出到 stdout 时在当前目录下）
+fn images_dir(cli: &Cli, output_path: &Path) -> Option<PathBuf> {
+    if cli.images_dir.is_some() {
+        return cli.images_dir.clone();
+    }
+    let default_dir = match (OutputFormat::from(cli.format), cli.flavor) {
+        (OutputFormat::Latex | OutputFormat::Org, _) => {
+            return Some(output_images_dir(output_path));
+        }
+        (OutputFormat::Markdown, FlavorArg::Obsidian) => OBSIDIAN_ATTACHMENTS,
+        _ => return None,
+    };
+    let output_dir = match output_path.parent() {
+        Some(dir) if output_path != Path::new(STDIO_PATH) => dir,
+        _ => Path::new(""),
+    };
+    Some(output_dir.join(default_dir))
 }
 
-fn render_text(block: &Block) -> String {
-    let (text, _has_formula) = render_rich_text(block);
-    if text.trim().is_empty() {
-        return String::new();
+/// 输入文件与输出路径；兼容 `input.json output.md` 的写法：未指定 -o 时，
+/// 最后一个非 .json 参数视为输出
+fn io_paths(cli: &Cli) -> (Vec<PathBuf>, PathBuf) {
+    let mut input_paths = cli.inputs.clone();
+    let mut output_path = cli.output.clone();
+    let last_is_output = input_paths.last().is_some_and(|path| {
+        !path
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("json"))
+    });
+    if output_path.is_none() && input_paths.len() > 1 && last_is_output {
+        output_path = input_paths.pop();
     }
-    format!("{}\n\n", text.trim())
+
+    let from_stdin = input_paths[0] == Path::new(STDIO_PATH);
+    let format = OutputFormat::from(cli.format);
+    // 拆分输出时输出路径是目录，默认为去掉扩展名的第一个输入文件
+    let output_path = output_path.unwrap_or_else(|| {
+        if from_stdin {
+            PathBuf::from(STDIO_PATH)
+        } else if cli.split_by.is_some() {
+            input_paths[0].with_extension("")
+        } else {
+            input_paths[0].with_extension(format.extension())
+        }
+    });
+    (input_paths, output_path)
 }
 
-fn render_list(block: &Block) -> String {
-    if let Some(blocks) = &block.blocks {
-        let mut items = Vec::new();
-        for sub_block in blocks {
-            if sub_block.block_type == "list_item" {
-                let text = extract_text_from_block(sub_block).trim().to_string();
-                if !text.is_empty() {
-                    items.push(format!("- {}", text));
-                }
+/// 转换命令行指定的输入；指定 --report 时无论成败都写出转换报告
+fn run(cli: &Cli, matches: &ArgMatches) -> Result<ExitCode, String> {
+    let Some(path) = &cli.report else {
+        return convert(cli, None);
+    };
+    let mut report = Report::new(cli, matches);
+    let result = convert(cli, Some(&mut report));
+    report.finish(&result);
+    let json = serde_json::to_string_pretty(&report).map_err(|e| e.to_string())?;
+    let written = fs::write(path, json + "\n").map_err(|e| format!("writing report: {}", e));
+    match result {
+        Ok(code) => written.map(|()| code),
+        Err(message) => {
+            if let Err(report_error) = written {
+                error!("Error: {}", report_error);
             }
+            Err(message)
         }
-        if !items.is_empty() {
-            return format!("{}\n\n", items.join("\n"));
-        }
-    }
-    String::new()
-}
-
-fn render_image(block: &Block, base_path: &Path) -> String {
-    let mut image_html = String::new();
-    let mut caption_html = String::new();
-
-    if let Some(blocks) = &block.blocks {
-        for sub_block in blocks {
-            if sub_block.block_type == "image_body" {
-                if let Some(lines) = &sub_block.lines {
-                    for line in lines {
-                        for span in &line.spans {
-                            if span.span_type == "image" {
-                                if let Some(image_path) = &span.image_path {
-                                    if let Some(base64) = image_to_base64(image_path, base_path) {
-                                        image_html = format!(
-                                            "<img src=\"{}\" alt=\"figure\" style=\"max-width: 100%; height: auto; display: block; margin: 0 auto;\" />",
-                                            base64
-                                        );
-                                    }
-                                }
-                            }
-                        }
-                    }
-                }
-            } else if sub_block.block_type == "image_caption"
-                || sub_block.block_type == "image_footnote"
-            {
-                let text = extract_text_from_block(sub_block).trim().to_string();
-                if !text.is_empty() {
-                    caption_html.push_str(&format!(
-                        "<figcaption style=\"text-align: center; font-size: 0.9em; color: #666; margin-top: 0.5em;\">{}</figcaption>",
-                        escape_html(&text)
-                    ));
-                }
-            }
+    }
+}
+
+/// 一次转换的结果，缓存、拆分与流式写出的各条路径都归结到这里
+#[derive(Default)]
+struct Converted {
+    /// 写到 stdout 的内容；已写入输出文件时为 `None`
+    markdown: Option<String>,
+    split_files: Vec<OutputFile>,
+    /// --split-by page 写入 manifest.json 的各页条目
+    split_pages: Vec<PageFile>,
+    warnings: Vec<Warning>,
+    stats: ConvertStats,
+    outline: Vec<OutlineEntry>,
+}
+
+/// 单独使用时合法、组合起来却不能执行的选项
+fn check_combinations(
+    cli: &Cli,
+    input_paths: &[PathBuf],
+    format: OutputFormat,
+    to_stdout: bool,
+) -> Result<(), String> {
+    let stdin_count = input_paths
+        .iter()
+        .filter(|path| *path == Path::new(STDIO_PATH))
+        .count();
+    if stdin_count > 1 {
+        return Err("stdin (\"-\") can only be used as one input".to_string());
+    }
+    // 按标题拆分依赖目录与锚点；按页拆分没有目录
+    let split_by_heading = cli
+        .split_by
+        .is_some_and(|level| !matches!(level, SplitByArg::Page));
+    if split_by_heading && format == OutputFormat::Text {
+        return Err("--split-by cannot be used with --format text".to_string());
+    }
+    if split_by_heading && format == OutputFormat::Latex {
+        return Err("--split-by cannot be used with --format latex".to_string());
+    }
+    if split_by_heading && format == OutputFormat::Json {
+        return Err("--split-by cannot be used with --format json".to_string());
+    }
+    if split_by_heading && format == OutputFormat::Org {
+        return Err("--split-by cannot be used with --format org".to_string());
+    }
+    if matches!(cli.split_by, Some(SplitByArg::Page)) && cli.back_to_top.is_some() {
+        return Err("--back-to-top cannot be used with --split-by page".to_string());
+    }
+    if matches!(cli.flavor, FlavorArg::Obsidian) && format == OutputFormat::Markdown {
+        if split_by_heading {
+            return Err("--split-by cannot be used with --flavor obsidian".to_string());
+        }
+        if cli.back_to_top.is_some() {
+            return Err("--back-to-top cannot be used with --flavor obsidian".to_string());
         }
     }
+    if to_stdout && cli.resume {
+        return Err(
+            "--resume records progress next to the output file; give one with -o".to_string(),
+        );
+    }
+    if to_stdout && cli.split_by.is_some() {
+        return Err(
+            "--split-by writes several files; give an output directory with -o".to_string(),
+        );
+    }
+    Ok(())
+}
 
-    if image_html.is_empty() {
-        return String::new();
+/// 命令行参数对应的转换选项；图片、表格等输出目录由 [`prepare_output_dirs`] 填写
+fn convert_options(cli: &Cli, format: OutputFormat) -> ConvertOptions {
+    ConvertOptions {
+        page_dividers: !cli.no_page_dividers,
+        collapse_empty_pages: cli.collapse_empty_pages,
+        page_label: cli.page_label.clone(),
+        divider_style: cli.divider_style.into(),
+        style_header: !(cli.no_style || cli.bare),
+        css_mode: cli.css_mode.into(),
+        footer: if cli.no_footer || cli.bare {
+            None
+        } else {
+            Some(
+                cli.footer_text
+                    .clone()
+                    .unwrap_or_else(|| DEFAULT_FOOTER.to_string()),
+            )
+        },
+        inline_svg: cli.inline_svg,
+        tables_as_image: cli.tables_as_image,
+        table_format: cli.tables.into(),
+        figure_style: cli.figure_style.into(),
+        caption_position: cli.caption_position.into(),
+        equation_max_height: cli.equation_max_height,
+        prefer_equation_latex: cli.prefer_equation_latex,
+        math_delimiters: cli.math_delimiters.into(),
+        inline_math_style: cli.inline_math_style.into(),
+        skip_headers: cli.skip_headers,
+        skip_footnotes: cli.skip_footnotes,
+        skip_footers: cli.skip_footers,
+        dedupe_headers: cli.dedupe_headers,
+        normalize_headings: cli.normalize_headings,
+        title_case_headings: cli.title_case_headings,
+        number_headings: cli.number_headings,
+        number_figures: cli.number_figures,
+        list_of_figures: cli.list_of_figures,
+        drop_rotated_text: cli.drop_rotated_text,
+        dehyphenate: !cli.no_dehyphenate,
+        normalize_whitespace: !cli.no_normalize_whitespace,
+        escape_markdown: !cli.no_escape,
+        allow_inline_html: cli.allow_inline_html,
+        provenance: cli.provenance,
+        min_confidence: cli.min_confidence,
+        drop_below: cli.drop_below,
+        format_references: !cli.no_reference_formatting,
+        autolink: cli.autolink,
+        pangu_spacing: cli.pangu_spacing,
+        reorder_blocks: !cli.no_reorder,
+        column_order: cli.column_order.into(),
+        merge_cross_page: cli.merge_cross_page,
+        dedupe_blocks: cli.dedupe_blocks,
+        merge_titles: !cli.no_merge_titles,
+        detect_lists: cli.detect_lists,
+        detect_quotes: cli.detect_quotes,
+        footnote_style: cli.footnote_style.into(),
+        footnotes_inline: cli.footnotes_inline,
+        index_style: cli.index_style.into(),
+        anchors: cli.anchors.into(),
+        flavor: cli.flavor.into(),
+        format,
+        mathjax: cli.mathjax,
+        latex_standalone: cli.latex_standalone,
+        text_title_levels: cli.title_levels,
+        text_placeholders: cli.placeholders,
+        strict_blocks: cli.strict_blocks,
+        pages: cli.pages.clone(),
+        back_to_top: cli.back_to_top.clone(),
+        chunk_separator: cli.chunk_separator.clone(),
+        threads: cli.threads,
+        embed_threshold: cli.embed_threshold,
+        max_image_bytes: cli.max_image_bytes,
+        image_quality: cli.image_quality,
+        oversize_policy: match cli.oversize_policy {
+            OversizePolicyArg::Downscale | OversizePolicyArg::Link => OversizePolicy::Downscale,
+            OversizePolicyArg::Skip => OversizePolicy::Skip,
+        },
+        ..ConvertOptions::default()
     }
+}
 
-    format!(
-        "<figure style=\"margin: 1.5em 0; text-align: center;\">\n{}\n{}\n</figure>\n\n",
-        image_html, caption_html
-    )
+/// 创建图片、超限图片与导出表格的目录，并在选项中记下相对于输出文件的链接前缀
+fn prepare_output_dirs(
+    cli: &Cli,
+    output_path: &Path,
+    options: &mut ConvertOptions,
+) -> Result<(), String> {
+    // 拆分出的文件都在输出目录中，图片链接相对于该目录
+    let link_from = match cli.split_by {
+        Some(_) => output_path.join("index.md"),
+        None => output_path.to_path_buf(),
+    };
+    if let Some(dir) = &images_dir(cli, output_path) {
+        fs::create_dir_all(dir).map_err(|e| format!("creating images directory: {}", e))?;
+        options.images = ImageOutput::Directory {
+            dir: dir.clone(),
+            link_prefix: relative_link_prefix(dir, &link_from),
+        };
+    }
+    if let Some(dir) = linked_images_dir(cli, output_path) {
+        fs::create_dir_all(&dir).map_err(|e| format!("creating images directory: {}", e))?;
+        let link_prefix = relative_link_prefix(&dir, &link_from);
+        options.oversize_policy = OversizePolicy::Link { dir, link_prefix };
+    }
+    if let Some(dir) = &cli.export_tables {
+        fs::create_dir_all(dir).map_err(|e| format!("creating tables directory: {}", e))?;
+        options.table_export = Some(TableExport {
+            dir: dir.clone(),
+            link_prefix: relative_link_prefix(dir, &link_from),
+        });
+    }
+    Ok(())
 }
 
-fn render_table(block: &Block, base_path: &Path) -> String {
-    let mut table_html = String::new();
-    let mut caption_html = String::new();
-    let mut footnote_html = String::new();
-
-    if let Some(blocks) = &block.blocks {
-        for sub_block in blocks {
-            if sub_block.block_type == "table_body" {
-                if let Some(lines) = &sub_block.lines {
-                    for line in lines {
-                        for span in &line.spans {
-                            if span.span_type == "table" {
-                                if let Some(image_path) = &span.image_path {
-                                    if let Some(base64) = image_to_base64(image_path, base_path) {
-                                        table_html = format!(
-                                            "<img src=\"{}\" alt=\"table\" style=\"max-width: 100%; height: auto; display: block; margin: 0 auto;\" />",
-                                            base64
-                                        );
-                                    }
-                                }
-                            }
-                        }
-                    }
-                }
-            } else if sub_block.block_type == "table_caption" {
-                let text = extract_text_from_block(sub_block).trim().to_string();
-                if !text.is_empty() {
-                    caption_html = format!(
-                        "<caption style=\"font-weight: bold; margin-bottom: 0.5em;\">{}</caption>",
-                        escape_html(&text)
-                    );
-                }
-            } else if sub_block.block_type == "table_footnote" {
-                let text = extract_text_from_block(sub_block).trim().to_string();
-                if !text.is_empty() {
-                    footnote_html = format!(
-                        "<p style=\"font-size: 0.85em; color: #666; margin-top: 0.5em;\">{}</p>",
-                        escape_html(&text)
-                    );
-                }
-            }
+/// 本次转换使用的缓存条目；拆分输出与复制出的图片不止一个文件，不缓存，原因记在状态中
+fn open_cache(
+    cli: &Cli,
+    options: &ConvertOptions,
+    input_paths: &[PathBuf],
+    sources: &[String],
+    documents: &[Document],
+) -> Result<(Option<CacheEntry>, Option<CacheStatus>), String> {
+    let Some(dir) = cli.cache_dir.as_deref().filter(|_| !cli.no_cache) else {
+        return Ok((None, None));
+    };
+    let unused = if cli.outline.is_some() {
+        "--outline needs a fresh conversion"
+    } else if cli.report.is_some() {
+        "--report needs a fresh conversion"
+    } else if cli.split_by.is_some() {
+        "--split-by writes several files"
+    } else if matches!(options.images, ImageOutput::Directory { .. }) {
+        "images are copied to a directory"
+    } else if matches!(options.oversize_policy, OversizePolicy::Link { .. }) {
+        "oversize images are written to a directory"
+    } else if options.table_export.is_some() {
+        "tables are exported to a directory"
+    } else {
+        let key = cache_key(cli, input_paths, sources, documents)?;
+        return Ok((Some(CacheEntry::new(dir, key)), None));
+    };
+    Ok((None, Some(CacheStatus::Unused(unused))))
+}
+
+/// 存入缓存；有警告的转换（缺失的图片、无法转换的表格等）不缓存，下次重新转换时照常报告
+fn store_in_cache(
+    entry: &CacheEntry,
+    converted: &Converted,
+    output_path: &Path,
+    has_warnings: bool,
+) -> CacheStatus {
+    if has_warnings {
+        return CacheStatus::NotStored("warnings were reported".to_string());
+    }
+    let stored = match &converted.markdown {
+        Some(markdown) => entry.store(markdown.as_bytes(), &converted.stats),
+        None => fs::read(output_path).and_then(|output| entry.store(&output, &converted.stats)),
+    };
+    match stored {
+        Ok(()) => CacheStatus::Stored,
+        Err(e) => CacheStatus::NotStored(format!("writing cache: {}", e)),
+    }
+}
+
+/// 按命中的缓存、拆分或流式写出转换；写到 stdout 时内容留在结果中，由调用方写出
+fn run_conversion(
+    cli: &Cli,
+    documents: &[Document],
+    options: &ConvertOptions,
+    output_path: &Path,
+    cached: Option<(String, ConvertStats)>,
+    resume: Option<Resume>,
+) -> Result<Converted, ConvertError> {
+    let to_stdout = output_path == Path::new(STDIO_PATH);
+    match (cached, cli.split_by) {
+        (Some((output, stats)), _) if to_stdout => Ok(Converted {
+            markdown: Some(output),
+            stats,
+            ..Converted::default()
+        }),
+        (Some((output, stats)), _) => write_atomically(output_path, output.as_bytes())
+            .map_err(|source| ConvertError::Io {
+                path: output_path.to_path_buf(),
+                source,
+            })
+            .map(|()| Converted {
+                stats,
+                ..Converted::default()
+            }),
+        (None, Some(level)) => {
+            convert_documents_split(documents, options, level.into()).map(|output| Converted {
+                split_files: output.files,
+                split_pages: output.pages,
+                warnings: output.warnings,
+                stats: output.stats,
+                ..Converted::default()
+            })
+        }
+        (None, None) if to_stdout => {
+            convert_documents(documents, options).map(|output| Converted {
+                markdown: Some(output.markdown),
+                warnings: output.warnings,
+                stats: output.stats,
+                outline: output.outline,
+                ..Converted::default()
+            })
+        }
+        (None, None) => {
+            write_streamed(documents, options, output_path, resume).map(|output| Converted {
+                warnings: output.warnings,
+                stats: output.stats,
+                outline: output.outline,
+                ..Converted::default()
+            })
         }
     }
+}
 
-    if table_html.is_empty() {
-        return String::new();
+/// 写出留在结果中的内容：stdout 的文本，或拆分出的各个文件与按页拆分的 manifest.json
+fn write_output(cli: &Cli, converted: &Converted, output_path: &Path) -> Result<(), String> {
+    match &converted.markdown {
+        Some(markdown) => io::stdout()
+            .write_all(markdown.as_bytes())
+            .map_err(|e| format!("writing stdout: {}", e))?,
+        None if cli.split_by.is_none() => info!("Output written to: {}", output_path.display()),
+        None => {
+            fs::create_dir_all(output_path)
+                .map_err(|e| format!("creating output directory: {}", e))?;
+            for file in &converted.split_files {
+                fs::write(output_path.join(&file.name), &file.content)
+                    .map_err(|e| format!("writing {}: {}", file.name, e))?;
+            }
+            if matches!(cli.split_by, Some(SplitByArg::Page)) {
+                let manifest = serde_json::to_string_pretty(&converted.split_pages)
+                    .map_err(|e| format!("serializing manifest: {}", e))?;
+                fs::write(output_path.join("manifest.json"), manifest)
+                    .map_err(|e| format!("writing manifest: {}", e))?;
+            }
+            info!(
+                "Output written to: {} ({} files)",
+                output_path.display(),
+                converted.split_files.len()
+            );
+        }
     }
+    Ok(())
+}
 
-    format!(
-        "<div style=\"margin: 1.5em 0; overflow-x: auto;\">\n{}\n{}\n{}\n</div>\n\n",
-        caption_html, table_html, footnote_html
-    )
+/// 缺失的图片与未识别的块类型，在统计摘要之前集中列出
+#[derive(Default)]
+struct WarningSummary<'w> {
+    missing_images: Vec<(usize, &'w str)>,
+    unknown_blocks: Vec<(&'w str, usize)>,
 }
 
-fn render_interline_equation(block: &Block, base_path: &Path) -> String {
-    if let Some(lines) = &block.lines {
-        for line in lines {
-            for span in &line.spans {
-                if span.span_type == "interline_equation" {
-                    if let Some(image_path) = &span.image_path {
-                        if let Some(base64) = image_to_base64(image_path, base_path) {
-                            return format!(
-                                "<div style=\"margin: 1em 0; text-align: center;\">\n<img src=\"{}\" alt=\"equation\" style=\"max-height: 80px;\" />\n</div>\n\n",
-                                base64
-                            );
-                        }
-                    }
-                    if let Some(latex) = &span.content {
-                        return format!("\n$$\n{}\n$$\n\n", latex);
+impl<'w> WarningSummary<'w> {
+    /// 逐条打印其余的警告，缺失的图片与未识别的块类型留待 [`WarningSummary::print`]
+    fn collect(warnings: &'w [Warning]) -> Self {
+        let mut summary = WarningSummary::default();
+        for warning in warnings {
+            match warning {
+                Warning::MissingImage { page_idx, path } => {
+                    summary.missing_images.push((*page_idx, path))
+                }
+                Warning::UnknownBlockType { block_type, .. } => {
+                    match summary
+                        .unknown_blocks
+                        .iter_mut()
+                        .find(|(seen, _)| seen == block_type)
+                    {
+                        Some((_, count)) => *count += 1,
+                        None => summary.unknown_blocks.push((block_type, 1)),
                     }
                 }
+                _ => warn!("Warning: {}", warning),
+            }
+        }
+        summary
+    }
+
+    fn print(&self) {
+        if !self.missing_images.is_empty() {
+            warn!("Missing images ({}):", self.missing_images.len());
+            for (page_idx, path) in &self.missing_images {
+                warn!("  page {}: {}", page_idx + 1, path);
+            }
+        }
+        if !self.unknown_blocks.is_empty() {
+            warn!("Unknown block types, rendered as text:");
+            for (block_type, count) in &self.unknown_blocks {
+                warn!("  {}: {}", block_type, count);
             }
         }
     }
-    String::new()
 }
 
-fn render_index(block: &Block) -> String {
-    let text = extract_text_from_block(block).trim().to_string();
-    if text.is_empty() {
-        return String::new();
+/// 写出 --stats 与 --outline 文件
+fn write_side_files(cli: &Cli, converted: &Converted) -> Result<(), String> {
+    if let Some(path) = &cli.stats {
+        let json = serde_json::to_string_pretty(&converted.stats).map_err(|e| e.to_string())?;
+        fs::write(path, json + "\n").map_err(|e| format!("writing stats: {}", e))?;
     }
-    format!("{}\n\n", text)
+    if let Some(path) = &cli.outline {
+        let text = match cli.outline_format {
+            OutlineFormatArg::Json => {
+                serde_json::to_string_pretty(&converted.outline).map_err(|e| e.to_string())? + "\n"
+            }
+            OutlineFormatArg::Yaml => outline_yaml(&converted.outline),
+        };
+        fs::write(path, text).map_err(|e| format!("writing outline: {}", e))?;
+    }
+    Ok(())
 }
 
-// ==================== Discarded Blocks ====================
+fn convert(cli: &Cli, mut report: Option<&mut Report>) -> Result<ExitCode, String> {
+    let (input_paths, output_path) = io_paths(cli);
+    let format = OutputFormat::from(cli.format);
+    let to_stdout = output_path == Path::new(STDIO_PATH);
+    check_combinations(cli, &input_paths, format, to_stdout)?;
+
+    if cli.cache_clear
+        && let Some(dir) = &cli.cache_dir
+    {
+        let removed = clear_cache(dir).map_err(|e| format!("clearing cache: {}", e))?;
+        info!("Removed {} cache entries from {}", removed, dir.display());
+    }
 
-fn categorize_discarded_blocks(blocks: &[Block]) -> DiscardedBlocksCategory {
-    let mut headers = Vec::new();
-    let mut footnotes = Vec::new();
+    let mut sources = Vec::new();
+    let mut layouts = Vec::new();
+    let mut parse_warnings = Vec::new();
+    let parsing = Instant::now();
+    for path in &input_paths {
+        let source = read_source(path)?;
+        let (layout, warnings) = parse_source(path, &source, cli.input_format.into(), cli.lenient)?;
+        sources.push(source);
+        layouts.push(layout);
+        parse_warnings.extend(warnings);
+    }
+    let parse_time = parsing.elapsed();
+    if let Some(report) = report.as_mut() {
+        report.durations_ms.parse = ms(parse_time);
+        report.record_warnings(&parse_warnings);
+    }
+
+    // 从 stdin 读取时没有所在目录，图片默认相对于当前目录解析
+    let documents: Vec<Document> = input_paths
+        .iter()
+        .zip(&layouts)
+        .map(|(path, layout)| {
+            let base_path = cli.base_path.as_deref().unwrap_or_else(|| input_dir(path));
+            Document { layout, base_path }
+        })
+        .collect();
+
+    let mut options = convert_options(cli, format);
+    prepare_output_dirs(cli, &output_path, &mut options)?;
+    let (cache, mut cache_status) = open_cache(cli, &options, &input_paths, &sources, &documents)?;
+    let cached = cache.as_ref().and_then(CacheEntry::lookup);
+    if cached.is_some() {
+        cache_status = Some(CacheStatus::Hit);
+    }
+
+    // 继续上次中断的转换前先确认输入、选项与已写出的内容都没有变化
+    let resume = match cli.resume && cached.is_none() {
+        true => Some(prepare_resume(
+            cache_key(cli, &input_paths, &sources, &documents)?,
+            &output_path,
+        )?),
+        false => None,
+    };
+
+    let page_total: usize = layouts.iter().map(|layout| layout.pdf_info.len()).sum();
+    info!("Processing {} pages...", page_total);
+
+    let page_blocks = report.as_ref().map(|_| PageBlocks::default());
+    options.progress = start_progress(to_stdout, page_blocks.clone());
+    let converted = run_conversion(cli, &documents, &options, &output_path, cached, resume);
+    finish_progress();
+    if let (Some(report), Some(page_blocks)) = (report.as_mut(), page_blocks) {
+        report.record_pages(&page_blocks);
+    }
+    let converted = converted.map_err(|e| e.to_string())?;
+    log_relocated_images(&converted.stats.images_relocated);
+
+    let has_warnings = !parse_warnings.is_empty() || !converted.warnings.is_empty();
+    if let Some(entry) = &cache
+        && !matches!(cache_status, Some(CacheStatus::Hit))
+    {
+        cache_status = Some(store_in_cache(
+            entry,
+            &converted,
+            &output_path,
+            has_warnings,
+        ));
+    }
+
+    let summary = WarningSummary::collect(&converted.warnings);
+    let writing = Instant::now();
+    write_output(cli, &converted, &output_path)?;
+    let write_time = converted.stats.timings.write + writing.elapsed();
+    summary.print();
+    print_stats(&converted.stats);
+    if let Some(status) = &cache_status {
+        info!("  cache: {}", status);
+    }
+    if cli.timing {
+        print_timings(parse_time, &converted.stats.timings, write_time);
+    }
+    write_side_files(cli, &converted)?;
+    info!("Done!");
+
+    let failed: Vec<String> = cli
+        .fail_on
+        .iter()
+        .filter(|fail_on| fail_on.exceeded(parse_warnings.iter().chain(&converted.warnings)))
+        .map(|fail_on| fail_on.category.clone())
+        .collect();
+    if let Some(report) = report.as_mut() {
+        report.record_conversion(&converted, write_time, &failed);
+    }
+    if !failed.is_empty() {
+        warn!("Warnings of a --fail-on category: {}", failed.join(", "));
+    }
 
-    for block in blocks {
-        match block.block_type.as_str() {
-            "header" => headers.push(block.clone()),
-            "page_footnote" => footnotes.push(block.clone()),
-            _ => {}
+    if cli.fail_on_missing_images && !summary.missing_images.is_empty() {
+        return Ok(ExitCode::FAILURE);
+    }
+    if !failed.is_empty() || (cli.strict && has_warnings) {
+        return Ok(ExitCode::from(EXIT_WARNINGS));
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+/// 大纲写成 YAML 的条目列表；字符串写成双引号形式（与 JSON 字符串相同）
+fn outline_yaml(outline: &[OutlineEntry]) -> String {
+    if outline.is_empty() {
+        return "[]\n".to_string();
+    }
+    let quoted = |text: &str| serde_json::to_string(text).unwrap_or_default();
+    let mut yaml = String::new();
+    for entry in outline {
+        yaml.push_str(&format!("- title: {}\n", quoted(&entry.title)));
+        yaml.push_str(&format!("  level: {}\n", entry.level));
+        yaml.push_str(&format!("  page_idx: {}\n", entry.page_idx));
+        yaml.push_str(&format!("  anchor_id: {}\n", quoted(&entry.anchor_id)));
+        if let Some(printed_page) = &entry.printed_page {
+            yaml.push_str(&format!("  printed_page: {}\n", quoted(printed_page)));
+        }
+        match entry.offset {
+            Some(offset) => yaml.push_str(&format!("  offset: {}\n", offset)),
+            None => yaml.push_str("  offset: null\n"),
         }
     }
+    yaml
+}
 
-    DiscardedBlocksCategory { headers, footnotes }
+/// 输出文件旁加上后缀的文件，如 `.partial`
+fn sibling_path(output_path: &Path, suffix: &str) -> PathBuf {
+    let mut path = output_path.as_os_str().to_owned();
+    path.push(suffix);
+    PathBuf::from(path)
 }
 
-fn render_discarded_headers(blocks: &[Block]) -> String {
-    blocks
-        .iter()
-        .filter_map(|block| {
-            let text = extract_text_from_block(block).trim().to_string();
-            if text.is_empty() {
-                None
+/// `--resume` 时记录进度的文件
+fn state_path(output_path: &Path) -> PathBuf {
+    sibling_path(output_path, ".mineru-md.state")
+}
+
+/// `--resume` 时写出期间每隔几秒保存的进度
+#[derive(Serialize, Deserialize)]
+struct ResumeState {
+    /// 与缓存键相同：转换器版本、影响输出的选项、输入与图片文件；不同时拒绝继续
+    key: String,
+    /// `.partial` 文件前 `checkpoint.bytes` 个字节的摘要
+    output_digest: String,
+    checkpoint: Checkpoint,
+}
+
+/// 开启 `--resume` 的一次转换
+struct Resume {
+    key: String,
+    /// 从上次中断处继续时，已截到检查点处的 `.partial` 文件、其内容的摘要与检查点
+    from: Option<(fs::File, Digest, Checkpoint)>,
+}
+
+/// 读取上次中断时留下的进度，确认输入、选项与已写出的内容都没有变化后把 `.partial` 文件截到
+/// 检查点处；没有进度文件时从头开始
+fn prepare_resume(key: String, output_path: &Path) -> Result<Resume, String> {
+    let state_path = state_path(output_path);
+    let partial = sibling_path(output_path, ".partial");
+    let text = match fs::read_to_string(&state_path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Resume { key, from: None }),
+        Err(e) => return Err(format!("reading {}: {}", state_path.display(), e)),
+    };
+    let refuse = |reason: String| {
+        format!(
+            "cannot resume: {}; delete {} to start over",
+            reason,
+            state_path.display()
+        )
+    };
+    let state: ResumeState = serde_json::from_str(&text).map_err(|e| {
+        refuse(format!(
+            "{} is not a valid state file ({})",
+            state_path.display(),
+            e
+        ))
+    })?;
+    if state.key != key {
+        return Err(refuse(
+            "the input, the referenced images or the options differ from the interrupted run"
+                .to_string(),
+        ));
+    }
+
+    let mut file = fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(&partial)
+        .map_err(|e| refuse(format!("{}: {}", partial.display(), e)))?;
+    let bytes = state.checkpoint.bytes as u64;
+    let len = file
+        .metadata()
+        .map_err(|e| refuse(format!("{}: {}", partial.display(), e)))?
+        .len();
+    let mut digest = Digest::new();
+    let mut prefix = (&file).take(bytes);
+    let mut buffer = vec![0; 64 * 1024];
+    loop {
+        let n = prefix
+            .read(&mut buffer)
+            .map_err(|e| refuse(format!("reading {}: {}", partial.display(), e)))?;
+        if n == 0 {
+            break;
+        }
+        digest.update(&buffer[..n]);
+    }
+    if len < bytes || digest.hex() != state.output_digest {
+        return Err(refuse(format!(
+            "{} was changed since the interrupted run",
+            partial.display()
+        )));
+    }
+    // 检查点之后写了一半的页面丢弃
+    file.set_len(bytes)
+        .and_then(|()| file.seek(SeekFrom::End(0)))
+        .map_err(|e| refuse(format!("{}: {}", partial.display(), e)))?;
+    Ok(Resume {
+        key,
+        from: Some((file, digest, state.checkpoint)),
+    })
+}
+
+/// 写入时同时计算摘要，供检查点记录已写出的内容
+struct DigestWriter<'d, W: Write> {
+    inner: W,
+    digest: &'d RefCell<Digest>,
+}
+
+impl<W: Write> Write for DigestWriter<'_, W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.inner.write(buf)?;
+        self.digest.borrow_mut().update(&buf[..n]);
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// 逐页写入输出文件：先写到同目录下的 `.partial` 文件，转换成功后再替换输出文件，
+/// 出错时保留原有的输出（`--watch` 时即上一次的结果）。开启 `--resume` 时每隔几秒记录一次进度，
+/// 出错时保留 `.partial` 文件与进度，完成后删除进度文件
+fn write_streamed(
+    documents: &[Document],
+    options: &ConvertOptions,
+    output_path: &Path,
+    resume: Option<Resume>,
+) -> Result<WriteOutput, ConvertError> {
+    let partial = sibling_path(output_path, ".partial");
+    let state_path = state_path(output_path);
+    let io_error = |path: &Path| {
+        let path = path.to_path_buf();
+        move |source| ConvertError::Io { path, source }
+    };
+
+    let resuming = resume.is_some();
+    let (key, file, digest, checkpoint) = match resume {
+        Some(Resume {
+            key,
+            from: Some((file, digest, checkpoint)),
+        }) => {
+            if checkpoint.in_outline() {
+                info!("Resuming the table of contents pass");
             } else {
-                Some(format!(
-                    "<div style=\"background: #fafafa; padding: 0.5em 1em; margin-bottom: 1em; border-radius: 4px; font-size: 0.85em; color: #888;\">\n<span>{}</span>\n</div>\n\n",
-                    escape_html(&text)
-                ))
+                info!("Resuming after page {}", checkpoint.pages);
             }
-        })
-        .collect()
+            (Some(key), file, digest, Some(checkpoint))
+        }
+        resume => {
+            let file = fs::File::create(&partial).map_err(io_error(&partial))?;
+            (resume.map(|resume| resume.key), file, Digest::new(), None)
+        }
+    };
+    let digest = RefCell::new(digest);
+    let mut writer = DigestWriter {
+        inner: io::BufWriter::new(file),
+        digest: &digest,
+    };
+    let mut save = |checkpoint: &Checkpoint| -> Result<(), ConvertError> {
+        let Some(key) = &key else {
+            return Ok(());
+        };
+        let state = ResumeState {
+            key: key.clone(),
+            output_digest: digest.borrow().hex(),
+            checkpoint: checkpoint.clone(),
+        };
+        let json = serde_json::to_vec(&state).map_err(|e| ConvertError::Io {
+            path: state_path.clone(),
+            source: io::Error::other(e),
+        })?;
+        write_atomically(&state_path, &json).map_err(io_error(&state_path))
+    };
+    let converted = convert_documents_to_writer_resumable(
+        documents,
+        &mut writer,
+        options,
+        checkpoint,
+        &mut save,
+    );
+    drop(writer);
+    match converted {
+        Ok(output) => {
+            fs::rename(&partial, output_path).map_err(io_error(output_path))?;
+            if resuming {
+                match fs::remove_file(&state_path) {
+                    Err(e) if e.kind() != io::ErrorKind::NotFound => {
+                        return Err(io_error(&state_path)(e));
+                    }
+                    _ => {}
+                }
+            }
+            Ok(output)
+        }
+        Err(e) if resuming => {
+            if state_path.exists() {
+                info!(
+                    "Progress kept in {}; rerun with --resume to continue",
+                    state_path.display()
+                );
+            }
+            Err(e)
+        }
+        Err(e) => {
+            let _ = fs::remove_file(&partial);
+            Err(e)
+        }
+    }
+}
+
+/// 各类块的数量，如 `text 12, title 3 (1 skipped)`
+fn block_summary(stats: &ConvertStats) -> String {
+    let count = |n: usize, skipped: usize| match skipped {
+        0 => n.to_string(),
+        _ => format!("{} ({} skipped)", n, skipped),
+    };
+    let blocks: Vec<String> = stats
+        .blocks
+        .iter()
+        .map(|(block_type, n)| format!("{} {}", block_type, count(n.rendered, n.skipped)))
+        .collect();
+    blocks.join(", ")
 }
 
-fn render_discarded_footnotes(blocks: &[Block]) -> String {
-    if blocks.is_empty() {
-        return String::new();
+/// 在后备位置找到的图片逐条列出（-v），便于用户调整目录结构或指定 --image-root
+fn log_relocated_images(relocated: &BTreeMap<String, PathBuf>) {
+    for (image_path, found) in relocated {
+        verbose!("Image {} found at {}", image_path, found.display());
     }
+}
 
-    let mut html = String::from(
-        "<div style=\"background: #f8f8f8; padding: 0.8em 1em; margin-top: 1.5em; border-left: 3px solid #ddd; border-radius: 0 4px 4px 0; font-size: 0.85em; color: #666;\">\n",
-    );
+/// 结束时打印的统计摘要
+fn print_stats(stats: &ConvertStats) {
+    let headings: Vec<String> = stats
+        .headings
+        .iter()
+        .map(|(level, n)| format!("level {}: {}", level, n))
+        .collect();
 
-    for block in blocks {
-        let text = extract_text_from_block(block).trim().to_string();
-        if !text.is_empty() {
-            html.push_str(&format!(
-                "<p style=\"margin: 0.3em 0;\">{}</p>\n",
-                escape_html(&text)
-            ));
+    info!("Summary:");
+    info!("  pages: {}", stats.pages);
+    if !stats.blocks.is_empty() {
+        info!("  blocks: {}", block_summary(stats));
+    }
+    if !headings.is_empty() {
+        info!("  headings: {}", headings.join(", "));
+    }
+    if stats.list_items > 0 {
+        info!("  list items: {}", stats.list_items);
+    }
+    info!(
+        "  images: {} included, {} missing, {} deduplicated",
+        stats.images_included, stats.images_missing, stats.images_deduplicated
+    );
+    if let Some(largest) = &stats.largest_image {
+        info!(
+            "  image pixels: {} total, largest {}x{} ({} on page {})",
+            stats.total_pixels,
+            largest.width,
+            largest.height,
+            largest.image_path,
+            largest.page_idx + 1
+        );
+    }
+    if stats.images_downscaled + stats.images_linked + stats.images_omitted > 0 {
+        info!(
+            "  oversize images: {} downscaled, {} linked, {} omitted ({} bytes, {} bytes after downscaling)",
+            stats.images_downscaled,
+            stats.images_linked,
+            stats.images_omitted,
+            stats.oversize_original_bytes,
+            stats.oversize_embedded_bytes
+        );
+    }
+    if stats.images_below_threshold + stats.images_above_threshold > 0 {
+        info!(
+            "  embed threshold: {} embedded, {} copied ({} bytes saved)",
+            stats.images_below_threshold, stats.images_above_threshold, stats.threshold_bytes_saved
+        );
+    }
+    if stats.tables_exported + stats.tables_image_only > 0 {
+        info!(
+            "  tables exported as CSV: {} ({} image-only tables skipped)",
+            stats.tables_exported, stats.tables_image_only
+        );
+    }
+    info!(
+        "  dropped: {} headers, {} footers, {} footnotes",
+        stats.headers_dropped, stats.footers_dropped, stats.footnotes_dropped
+    );
+    if stats.duplicate_blocks > 0 {
+        info!("  duplicate blocks dropped: {}", stats.duplicate_blocks);
+    }
+    if !stats.other_discarded.is_empty() {
+        let others: Vec<String> = stats
+            .other_discarded
+            .iter()
+            .map(|(block_type, count)| format!("{} {}", block_type, count))
+            .collect();
+        info!("  other discarded blocks: {}", others.join(", "));
+    }
+    if stats.low_confidence_spans > 0 {
+        info!(
+            "  low-confidence spans: {} (lowest {}):",
+            stats.low_confidence_spans,
+            stats.lowest_scores.len()
+        );
+        for span in &stats.lowest_scores {
+            info!(
+                "    page {}: {:.2} \"{}\"",
+                span.page_idx + 1,
+                span.score,
+                span.text
+            );
         }
     }
+    info!(
+        "  output: {} characters, {} bytes",
+        stats.characters, stats.output_bytes
+    );
+}
 
-    html.push_str("</div>\n\n");
-    html
+/// `--timing` 打印的各阶段耗时；`write` 含写入 stdout 或拆分出的文件的时间
+/// 以毫秒计的时长
+fn ms(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
 }
 
-// ==================== 页面渲染 ====================
+fn print_timings(parse: Duration, timings: &Timings, write: Duration) {
+    info!("Timing:");
+    info!("  parse: {:.1} ms", ms(parse));
+    info!("  render pages: {:.1} ms", ms(timings.render));
+    info!(
+        "  image encoding: {:.1} ms (all threads)",
+        ms(timings.images)
+    );
+    info!("  write: {:.1} ms", ms(write));
+}
 
-fn render_block(block: &Block, base_path: &Path, page_idx: usize) -> (String, Option<TocEntry>) {
-    match block.block_type.as_str() {
-        "title" => render_title(block, page_idx),
-        "text" => (render_text(block), None),
-        "list" => (render_list(block), None),
-        "image" => (render_image(block, base_path), None),
-        "table" => (render_table(block, base_path), None),
-        "interline_equation" => (render_interline_equation(block, base_path), None),
-        "index" => (render_index(block), None),
-        _ => (render_text(block), None),
+fn run_extract_images(args: ExtractImagesArgs) -> Result<ExitCode, String> {
+    let (layout_json, _) = read_input(&args.input, args.input_format.into(), args.lenient)?;
+    let base_path = args
+        .base_path
+        .as_deref()
+        .unwrap_or_else(|| input_dir(&args.input));
+    fs::create_dir_all(&args.out).map_err(|e| format!("creating output directory: {}", e))?;
+
+    let options = ConvertOptions {
+        reorder_blocks: !args.no_reorder,
+        ..ConvertOptions::default()
+    };
+    let output =
+        extract_images(&layout_json, base_path, &args.out, &options).map_err(|e| e.to_string())?;
+    for warning in &output.warnings {
+        warn!("Warning: {}", warning);
     }
+    log_relocated_images(&output.relocated);
+
+    let manifest_path = args.out.join("manifest.json");
+    let manifest = serde_json::to_string_pretty(&output.images)
+        .map_err(|e| format!("serializing manifest: {}", e))?;
+    fs::write(&manifest_path, manifest).map_err(|e| format!("writing manifest: {}", e))?;
+
+    info!(
+        "Extracted {} images to: {}",
+        output.images.len(),
+        args.out.display()
+    );
+    Ok(ExitCode::SUCCESS)
 }
 
-fn render_page(page: &PageInfo, base_path: &Path) -> (String, Vec<TocEntry>) {
-    let mut toc_entries = Vec::new();
-    let mut content_html = String::new();
+// ==================== 转换报告 ====================
 
-    // 分类 discarded blocks
-    let categorized = categorize_discarded_blocks(&page.discarded_blocks);
+/// --report 文件的结构版本；字段只增不改，含义变化时递增
+const REPORT_VERSION: u32 = 1;
 
-    // 添加页眉
-    content_html.push_str(&render_discarded_headers(&categorized.headers));
+/// 转换过程中各页的块数，由进度回调在各工作线程中写入，按页码排列
+type PageBlocks = Arc<Mutex<BTreeMap<usize, BTreeMap<String, BlockCount>>>>;
 
-    // 渲染主要内容块
-    for block in &page.para_blocks {
-        let (html, toc_entry) = render_block(block, base_path, page.page_idx);
-        content_html.push_str(&html);
-        if let Some(entry) = toc_entry {
-            toc_entries.push(entry);
+/// --fail-on 的一项：某一类警告超过 `limit` 条时转换以退出码 3 结束
+#[derive(Debug, Clone)]
+struct FailOn {
+    category: String,
+    limit: usize,
+}
+
+impl FromStr for FailOn {
+    type Err = String;
+
+    /// `CATEGORY` 或 `CATEGORY:N`
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let (category, limit) = match value.split_once(':') {
+            Some((category, limit)) => (
+                category,
+                limit
+                    .parse()
+                    .map_err(|_| format!("invalid limit '{}' (expected a number)", limit))?,
+            ),
+            None => (value, 0),
+        };
+        if !Warning::CATEGORIES.contains(&category) {
+            return Err(format!(
+                "unknown category '{}' (expected one of {})",
+                category,
+                Warning::CATEGORIES.join(", ")
+            ));
         }
+        Ok(FailOn {
+            category: category.to_string(),
+            limit,
+        })
     }
+}
 
-    // 添加脚注
-    content_html.push_str(&render_discarded_footnotes(&categorized.footnotes));
+impl FailOn {
+    fn exceeded<'a>(&self, warnings: impl Iterator<Item = &'a Warning>) -> bool {
+        warnings
+            .filter(|warning| warning.category() == self.category)
+            .count()
+            > self.limit
+    }
+}
 
-    (content_html, toc_entries)
+/// 各阶段的耗时（毫秒），与 --timing 打印的相同
+#[derive(Debug, Default, Serialize)]
+struct ReportDurations {
+    parse: f64,
+    render: f64,
+    images: f64,
+    write: f64,
 }
 
-// ==================== 目录生成 ====================
+/// 报告中的一条警告
+#[derive(Debug, Serialize)]
+struct ReportWarning {
+    category: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    page_idx: Option<usize>,
+    message: String,
+}
 
-fn generate_toc(toc_entries: &[TocEntry]) -> String {
-    if toc_entries.is_empty() {
-        return String::new();
-    }
+/// 报告中一页的块数
+#[derive(Debug, Serialize)]
+struct ReportPage {
+    page_idx: usize,
+    blocks: BTreeMap<String, BlockCount>,
+}
 
-    let toc = String::from("<div id=\"toc-top\"></div>\n\n");
-    toc
+/// --report 写出的转换报告
+///
+/// 转换出错时也会写出，此时 `completed` 为 `false`，`error` 为错误信息，尚未得到的部分为空；
+/// 宽松模式下跳过了页面时 `completed` 同样为 `false`。
+#[derive(Debug, Serialize)]
+struct Report {
+    report_version: u32,
+    converter_version: &'static str,
+    /// 转换完整结束：没有出错，也没有跳过无法解析的页面
+    completed: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+    inputs: Vec<PathBuf>,
+    output: PathBuf,
+    /// 合并配置文件后生效的选项，与 --print-config 列出的相同
+    options: BTreeMap<String, toml::Value>,
+    /// 各类警告的条数
+    warning_counts: BTreeMap<&'static str, usize>,
+    warnings: Vec<ReportWarning>,
+    /// 本次渲染的各页（--resume 时不含已在上次写出的页面）
+    pages: Vec<ReportPage>,
+    durations_ms: ReportDurations,
+    /// 超出 --fail-on 限制的警告类别
+    failed_categories: Vec<String>,
+    /// 与 --stats 相同的统计；转换未完成时为 `null`
+    stats: Option<ConvertStats>,
 }
 
-// ==================== 分页线 ====================
+impl Report {
+    fn new(cli: &Cli, matches: &ArgMatches) -> Self {
+        let (inputs, output) = io_paths(cli);
+        Report {
+            report_version: REPORT_VERSION,
+            converter_version: env!("CARGO_PKG_VERSION"),
+            completed: false,
+            error: None,
+            inputs,
+            output,
+            options: effective_options(matches)
+                .into_iter()
+                .map(|(long, value, _)| (long, value))
+                .collect(),
+            warning_counts: BTreeMap::new(),
+            warnings: Vec::new(),
+            pages: Vec::new(),
+            durations_ms: ReportDurations::default(),
+            failed_categories: Vec::new(),
+            stats: None,
+        }
+    }
 
-fn generate_page_divider(page_num: usize) -> String {
-    format!(
-        "\n<div style=\"display: flex; align-items: center; margin: 2.5em 0; gap: 1em;\">\n  <div style=\"flex: 1; height: 1px; background: #ddd;\"></div>\n  <span style=\"color: #888; font-size: 0.85em;\">第 {} 页</span>\n  <div style=\"flex: 1; height: 1px; background: #ddd;\"></div>\n</div>\n\n",
-        page_num
-    )
+    fn record_warnings(&mut self, warnings: &[Warning]) {
+        for warning in warnings {
+            *self.warning_counts.entry(warning.category()).or_default() += 1;
+            self.warnings.push(ReportWarning {
+                category: warning.category(),
+                page_idx: warning.page_idx(),
+                message: warning.to_string(),
+            });
+        }
+    }
+
+    fn record_pages(&mut self, page_blocks: &PageBlocks) {
+        self.pages = std::mem::take(&mut *page_blocks.lock().unwrap())
+            .into_iter()
+            .map(|(page_idx, blocks)| ReportPage { page_idx, blocks })
+            .collect();
+    }
+
+    /// 记录转换阶段的警告、耗时、统计数据与超出 --fail-on 的类别
+    fn record_conversion(
+        &mut self,
+        converted: &Converted,
+        write_time: Duration,
+        failed: &[String],
+    ) {
+        self.record_warnings(&converted.warnings);
+        self.durations_ms.render = ms(converted.stats.timings.render);
+        self.durations_ms.images = ms(converted.stats.timings.images);
+        self.durations_ms.write = ms(write_time);
+        self.stats = Some(converted.stats.clone());
+        self.failed_categories = failed.to_vec();
+    }
+
+    /// 按转换结果填写 `completed` 与 `error`
+    fn finish(&mut self, result: &Result<ExitCode, String>) {
+        match result {
+            Ok(_) => self.completed = !self.warning_counts.contains_key("skipped_page"),
+            Err(message) => self.error = Some(message.clone()),
+        }
+    }
 }
 
-// ==================== 主转换函数 ====================
+// ==================== 输入校验 ====================
+
+/// validate 发现警告、发现错误时的退出码
+const EXIT_INVALID_WARNINGS: u8 = 1;
+const EXIT_INVALID_ERRORS: u8 = 2;
+
+fn run_validate(args: ValidateArgs) -> ExitCode {
+    let base_path = args
+        .base_path
+        .as_deref()
+        .unwrap_or_else(|| input_dir(&args.input));
+    let result = read_source(&args.input).and_then(|json| {
+        validate_layout(
+            &json,
+            args.input_format.into(),
+            base_path,
+            &ConvertOptions::default(),
+        )
+        .map_err(|e| format!("parsing JSON {}: {}", args.input.display(), e))
+    });
+    let validation = match result {
+        Ok(validation) => validation,
+        Err(message) => Validation {
+            schema: "unknown".to_string(),
+            errors: vec![message],
+            ..Validation::default()
+        },
+    };
 
-fn convert_layout_to_markdown(layout_json: &LayoutJson, base_path: &Path) -> String {
-    let mut markdown = String::new();
-    let mut all_toc_entries = Vec::new();
+    if args.json {
+        match serde_json::to_string_pretty(&validation) {
+            Ok(json) => println!("{}", json),
+            Err(e) => error!("Error: serializing findings: {}", e),
+        }
+    } else {
+        print_validation(&validation);
+    }
+    if !validation.errors.is_empty() {
+        ExitCode::from(EXIT_INVALID_ERRORS)
+    } else if !validation.warnings.is_empty() {
+        ExitCode::from(EXIT_INVALID_WARNINGS)
+    } else {
+        ExitCode::SUCCESS
+    }
+}
+
+/// `type count` 列表，没有时为 none
+fn type_counts(counts: &BTreeMap<String, usize>) -> String {
+    if counts.is_empty() {
+        return "none".to_string();
+    }
+    let counts: Vec<String> = counts
+        .iter()
+        .map(|(name, count)| format!("{} {}", name, count))
+        .collect();
+    counts.join(", ")
+}
 
-    // 文档头部样式
-    markdown.push_str(
-        "<style>\n  body { font-family: -apple-system, BlinkMacSystemFont, \"Segoe UI\", Roboto, \"Helvetica Neue\", Arial, sans-serif; }\n  img { border-radius: 4px; }\n  code { background: #f4f4f4; padding: 0.2em 0.4em; border-radius: 3px; font-size: 0.9em; }\n  pre { background: #f8f8f8; padding: 1em; border-radius: 6px; overflow-x: auto; }\n</style>\n\n",
+fn print_validation(validation: &Validation) {
+    let empty_pages: Vec<String> = validation
+        .empty_pages
+        .iter()
+        .map(|page_idx| (page_idx + 1).to_string())
+        .collect();
+    println!("Schema: {}", validation.schema);
+    println!("Pages: {}", validation.pages);
+    println!("Blocks: {}", type_counts(&validation.blocks));
+    println!(
+        "Unknown block types: {}",
+        type_counts(&validation.unknown_block_types)
+    );
+    println!(
+        "Unknown span types: {}",
+        type_counts(&validation.unknown_span_types)
     );
+    println!(
+        "Images: {} referenced, {} found, {} missing",
+        validation.images_referenced,
+        validation.images_found,
+        validation.missing_images.len()
+    );
+    for missing in &validation.missing_images {
+        println!("  page {}: {}", missing.page_idx + 1, missing.path);
+    }
+    println!("Malformed bboxes: {}", validation.malformed_bboxes);
+    match empty_pages.is_empty() {
+        true => println!("Empty pages: none"),
+        false => println!("Empty pages: {}", empty_pages.join(", ")),
+    }
+    for (label, messages) in [
+        ("Errors", &validation.errors),
+        ("Warnings", &validation.warnings),
+    ] {
+        if !messages.is_empty() {
+            println!("{}:", label);
+            for message in messages {
+                println!("  {}", message);
+            }
+        }
+    }
+    println!(
+        "Result: {}",
+        match (validation.errors.len(), validation.warnings.len()) {
+            (0, 0) => "clean".to_string(),
+            (0, warnings) => format!("{} warning(s)", warnings),
+            (errors, warnings) => format!("{} error(s), {} warning(s)", errors, warnings),
+        }
+    );
+}
+
+// ==================== 转换缓存 ====================
 
-    // 先收集所有目录条目
-    let mut page_contents = Vec::new();
+/// 缓存条目的两个文件：转换结果，以及记录缓存键、结果校验值与统计数据的元数据
+const CACHE_OUTPUT_SUFFIX: &str = ".out";
+const CACHE_META_SUFFIX: &str = ".meta.json";
+
+/// 本次转换与缓存的关系，打印在统计摘要中
+enum CacheStatus {
+    Hit,
+    Stored,
+    NotStored(String),
+    Unused(&'static str),
+}
 
-    for page in &layout_json.pdf_info {
-        let (html, toc_entries) = render_page(page, base_path);
-        page_contents.push(html);
-        all_toc_entries.extend(toc_entries);
+impl fmt::Display for CacheStatus {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CacheStatus::Hit => write!(f, "hit"),
+            CacheStatus::Stored => write!(f, "miss, stored"),
+            CacheStatus::NotStored(reason) => write!(f, "miss, not stored ({})", reason),
+            CacheStatus::Unused(reason) => write!(f, "not used ({})", reason),
+        }
     }
+}
+
+#[derive(Serialize, Deserialize)]
+struct CacheMeta {
+    key: String,
+    output_bytes: usize,
+    output_digest: String,
+    stats: ConvertStats,
+}
 
-    // 生成目录
-    markdown.push_str(&generate_toc(&all_toc_entries));
+/// 内容摘要：两个不同前缀的 SipHash 拼成的 128 位十六进制串
+fn digest(data: &[u8]) -> String {
+    let mut digest = Digest::new();
+    digest.update(data);
+    digest.hex()
+}
 
-    // 添加分隔线
-    markdown.push_str(
-        "<hr style=\"border: none; height: 1px; background: #ddd; margin: 2em 0;\" />\n\n",
-    );
+/// 可以分段写入的 [`digest`]，分段方式不影响结果
+#[derive(Clone)]
+struct Digest([DefaultHasher; 2]);
+
+impl Digest {
+    fn new() -> Self {
+        let half = |seed: u8| {
+            let mut hasher = DefaultHasher::new();
+            hasher.write_u8(seed);
+            hasher
+        };
+        Digest([half(0), half(1)])
+    }
 
-    // 渲染各页内容
-    for (i, content) in page_contents.iter().enumerate() {
-        markdown.push_str(content);
-        markdown.push_str(&generate_page_divider(i + 1));
+    fn update(&mut self, data: &[u8]) {
+        for hasher in &mut self.0 {
+            hasher.write(data);
+        }
     }
 
-    // 文档尾部
-    markdown.push_str(
-        "\n<hr style=\"border: none; height: 1px; background: #ddd; margin: 3em 0;\" />\n",
-    );
-    markdown.push_str(
-        "<div style=\"text-align: center; color: #999; font-size: 0.85em; padding: 1em 0;\">\n",
+    fn hex(&self) -> String {
+        format!("{:016x}{:016x}", self.0[0].finish(), self.0[1].finish())
+    }
+}
+
+/// 缓存键：转换器版本、当前目录、影响输出的选项、各输入文件的摘要，以及引用的每个图片文件的
+/// 大小与修改时间（不存在的图片记为 missing）。元数据中保存完整的键，命中时逐字比较
+fn cache_key(
+    cli: &Cli,
+    input_paths: &[PathBuf],
+    sources: &[String],
+    documents: &[Document],
+) -> Result<String, String> {
+    // 只影响日志、退出码与缓存本身的选项不参与；图片不写入目录时输出路径也不影响内容
+    let mut options = cli.clone();
+    options.inputs = input_paths.to_vec();
+    options.output = None;
+    options.verbose = 0;
+    options.quiet = false;
+    options.stats = None;
+    options.report = None;
+    options.fail_on = Vec::new();
+    options.strict = false;
+    options.fail_on_missing_images = false;
+    options.watch = false;
+    options.config = None;
+    options.cache_dir = None;
+    options.no_cache = false;
+    options.cache_clear = false;
+    options.resume = false;
+
+    let cwd = env::current_dir().map_err(|e| format!("reading current directory: {}", e))?;
+    let mut key = format!(
+        "{} {}\ncwd: {}\noptions: {:?}\n",
+        env!("CARGO_PKG_NAME"),
+        env!("CARGO_PKG_VERSION"),
+        cwd.display(),
+        options
     );
-    markdown.push_str("Generated by MinerU JSON to Markdown Converter\n");
-    markdown.push_str("</div>\n");
+    for (source, document) in sources.iter().zip(documents) {
+        key.push_str(&format!(
+            "input: {} bytes, {}\n",
+            source.len(),
+            digest(source.as_bytes())
+        ));
+        for image in referenced_images(document.layout, document.base_path) {
+            let modified = fs::metadata(&image).and_then(|meta| Ok((meta.len(), meta.modified()?)));
+            key.push_str(&match modified {
+                Ok((len, modified)) => {
+                    let since = modified.duration_since(UNIX_EPOCH).unwrap_or_default();
+                    format!(
+                        "image {}: {} bytes, modified {}.{:09}\n",
+                        image.display(),
+                        len,
+                        since.as_secs(),
+                        since.subsec_nanos()
+                    )
+                }
+                Err(_) => format!("image {}: missing\n", image.display()),
+            });
+        }
+    }
+    Ok(key)
+}
 
-    markdown
+/// 写到同目录下的 `.partial` 文件后再替换目标，中途失败不会留下写了一半的文件
+fn write_atomically(path: &Path, content: &[u8]) -> io::Result<()> {
+    let mut partial = path.as_os_str().to_owned();
+    partial.push(".partial");
+    let partial = PathBuf::from(partial);
+    fs::write(&partial, content)
+        .and_then(|()| fs::rename(&partial, path))
+        .inspect_err(|_| {
+            let _ = fs::remove_file(&partial);
+        })
 }
 
-// ==================== CLI 入口 ====================
+/// 一个缓存条目，文件名为缓存键的摘要
+struct CacheEntry {
+    output: PathBuf,
+    meta: PathBuf,
+    key: String,
+}
 
-fn main() {
-    let args: Vec<String> = std::env::args().collect();
+impl CacheEntry {
+    fn new(dir: &Path, key: String) -> Self {
+        let name = digest(key.as_bytes());
+        CacheEntry {
+            output: dir.join(name.clone() + CACHE_OUTPUT_SUFFIX),
+            meta: dir.join(name + CACHE_META_SUFFIX),
+            key,
+        }
+    }
 
-    if args.len() < 2 {
-        eprintln!("Usage: mineru-json-to-md <path-to-json-file> [output-file]");
-        eprintln!("Example: mineru-json-to-md layout.json output.md");
-        std::process::exit(1);
+    /// 缓存的输出与统计数据；条目不存在、键不一致、文件不完整或无法读取时都当作未命中
+    fn lookup(&self) -> Option<(String, ConvertStats)> {
+        let meta: CacheMeta = serde_json::from_str(&fs::read_to_string(&self.meta).ok()?).ok()?;
+        if meta.key != self.key {
+            return None;
+        }
+        let output = fs::read(&self.output).ok()?;
+        if output.len() != meta.output_bytes || digest(&output) != meta.output_digest {
+            verbose!(
+                "Cache entry {} is incomplete, converting again",
+                self.output.display()
+            );
+            return None;
+        }
+        String::from_utf8(output)
+            .ok()
+            .map(|output| (output, meta.stats))
     }
 
-    let input_path = PathBuf::from(&args[1]);
-    let output_path = if args.len() > 2 {
-        PathBuf::from(&args[2])
-    } else {
-        input_path.with_extension("md")
-    };
+    /// 先写输出再写元数据；元数据中的校验值保证两者不一致时不会命中
+    fn store(&self, output: &[u8], stats: &ConvertStats) -> io::Result<()> {
+        if let Some(dir) = self.meta.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let meta = CacheMeta {
+            key: self.key.clone(),
+            output_bytes: output.len(),
+            output_digest: digest(output),
+            stats: stats.clone(),
+        };
+        let meta = serde_json::to_string(&meta).map_err(io::Error::other)?;
+        write_atomically(&self.output, output)?;
+        write_atomically(&self.meta, meta.as_bytes())
+    }
+}
 
-    if !input_path.exists() {
-        eprintln!("Error: File not found: {}", input_path.display());
-        std::process::exit(1);
+/// 删除缓存目录中的条目（只删除本工具写出的文件），返回删除的条目数
+fn clear_cache(dir: &Path) -> io::Result<usize> {
+    let entries = match fs::read_dir(dir) {
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(0),
+        entries => entries?,
+    };
+    let mut removed = 0;
+    for entry in entries {
+        let path = entry?.path();
+        let Some(name) = path.file_name().and_then(|name| name.to_str()) else {
+            continue;
+        };
+        // 每个条目按元数据文件计数，写了一半的 `.partial` 文件一并删除
+        let (name, partial) = match name.strip_suffix(".partial") {
+            Some(name) => (name, true),
+            None => (name, false),
+        };
+        let (digest, meta) = match name.strip_suffix(CACHE_META_SUFFIX) {
+            Some(digest) => (digest, true),
+            None => (name.strip_suffix(CACHE_OUTPUT_SUFFIX).unwrap_or(""), false),
+        };
+        if digest.len() == 32 && digest.chars().all(|c| c.is_ascii_hexdigit()) {
+            fs::remove_file(&path)?;
+            removed += usize::from(meta && !partial);
+        }
     }
+    Ok(removed)
+}
 
-    println!("Reading: {}", input_path.display());
+// ==================== 配置文件 ====================
 
-    let json_content = match fs::read_to_string(&input_path) {
-        Ok(content) => content,
-        Err(e) => {
-            eprintln!("Error reading file: {}", e);
-            std::process::exit(1);
+/// 自动查找的配置文件名：先在第一个输入文件所在目录，再在当前目录
+const CONFIG_FILE_NAME: &str = "mineru-md.toml";
+
+/// 不能写在配置文件中的参数
+const CONFIG_EXCLUDED: &[&str] = &["config", "print-config", "help", "version"];
+
+/// 要读取的配置文件：`--config` 指定的文件，或自动找到的第一个 `mineru-md.toml`
+fn config_path(cli: &Cli) -> Option<PathBuf> {
+    if cli.config.is_some() {
+        return cli.config.clone();
+    }
+    let input_dir = cli
+        .inputs
+        .first()
+        .filter(|path| *path != Path::new(STDIO_PATH))
+        .map(|path| input_dir(path).to_path_buf());
+    [input_dir, Some(PathBuf::new())]
+        .into_iter()
+        .flatten()
+        .map(|dir| dir.join(CONFIG_FILE_NAME))
+        .find(|path| path.is_file())
+}
+
+/// 把配置文件中的各项转换为对应的命令行参数，如 `flavor = "gfm"` → `--flavor=gfm`
+///
+/// 键为参数的长名称（`_` 与 `-` 等价）；开关型参数取布尔值，只有 `true` 生效；
+/// 其余参数取字符串或数字，可选值的参数（如 `back-to-top`）也可以取 `true` 使用默认值。
+fn config_args(path: &Path) -> Result<Vec<OsString>, String> {
+    let content = fs::read_to_string(path)
+        .map_err(|e| format!("reading config {}: {}", path.display(), e))?;
+    let table: toml::Table = content
+        .parse()
+        .map_err(|e| format!("parsing config {}: {}", path.display(), e))?;
+    let command = Cli::command();
+
+    let mut args = Vec::new();
+    for (key, value) in &table {
+        let long = key.replace('_', "-");
+        let Some(arg) = command
+            .get_arguments()
+            .find(|arg| arg.get_long() == Some(long.as_str()))
+            .filter(|_| !CONFIG_EXCLUDED.contains(&long.as_str()))
+        else {
+            return Err(format!(
+                "unknown key '{}' in config {}",
+                key,
+                path.display()
+            ));
+        };
+        let flag = format!("--{}", long);
+        let invalid = |expected: &str| {
+            format!(
+                "key '{}' in config {} must be {}",
+                key,
+                path.display(),
+                expected
+            )
+        };
+        match (arg.get_action(), value) {
+            (ArgAction::SetTrue, toml::Value::Boolean(set)) => {
+                if *set {
+                    args.push(OsString::from(flag));
+                }
+            }
+            (ArgAction::SetTrue, _) => return Err(invalid("true or false")),
+            (ArgAction::Count, toml::Value::Integer(count)) if *count >= 0 => {
+                args.extend((0..*count).map(|_| OsString::from(&flag)));
+            }
+            (ArgAction::Count, _) => return Err(invalid("a non-negative integer")),
+            (_, toml::Value::Boolean(set))
+                if arg.get_num_args().is_some_and(|n| n.min_values() == 0) =>
+            {
+                if *set {
+                    args.push(OsString::from(flag));
+                }
+            }
+            (_, toml::Value::String(text)) => args.push(format!("{}={}", flag, text).into()),
+            (_, toml::Value::Integer(n)) => args.push(format!("{}={}", flag, n).into()),
+            (_, toml::Value::Float(n)) => args.push(format!("{}={}", flag, n).into()),
+            _ => return Err(invalid("a string or a number")),
         }
+    }
+    Ok(args)
+}
+
+/// 解析命令行，并合并配置文件；返回解析结果、合并后的参数与读取的配置文件
+///
+/// 配置项转换为命令行参数插在用户参数之前，同一参数后出现的生效，因此命令行覆盖配置文件、
+/// 配置文件覆盖默认值。使用子命令时不读取配置文件。
+fn parse_cli() -> Result<(Cli, ArgMatches, Option<PathBuf>), String> {
+    let args: Vec<OsString> = env::args_os().collect();
+    let matches = Cli::command().get_matches_from(&args);
+    let cli = Cli::from_arg_matches(&matches).map_err(|e| e.to_string())?;
+    let config = match &cli.command {
+        Some(_) => None,
+        None => config_path(&cli),
+    };
+    let Some(path) = config else {
+        return Ok((cli, matches, None));
     };
 
-    let layout_json: LayoutJson = match serde_json::from_str(&json_content) {
-        Ok(data) => data,
-        Err(e) => {
-            eprintln!("Error parsing JSON: {}", e);
-            std::process::exit(1);
+    let mut merged = args[..1].to_vec();
+    merged.extend(config_args(&path)?);
+    merged.extend_from_slice(&args[1..]);
+    let matches = Cli::command().try_get_matches_from(merged).map_err(|e| {
+        // 去掉 clap 错误信息中的用法与提示，其余各行（如缺少的参数）合并为一行
+        let message = e.to_string();
+        let lines: Vec<&str> = message
+            .lines()
+            .map(str::trim)
+            .take_while(|line| !line.is_empty() && !line.starts_with("Usage:"))
+            .collect();
+        format!(
+            "with config {}: {}",
+            path.display(),
+            lines.join(" ").trim_start_matches("error: ")
+        )
+    })?;
+    let cli = Cli::from_arg_matches(&matches).map_err(|e| e.to_string())?;
+    Ok((cli, matches, Some(path)))
+}
+
+/// 以 TOML 打印合并后的全部选项；输入、命令行或配置文件中给出的选项可以原样用作 `--config`，
+/// 取默认值的选项注释掉，只供查看
+fn print_config(matches: &ArgMatches, config: Option<&Path>) -> io::Result<()> {
+    let mut out = io::stdout().lock();
+    match config {
+        Some(path) => writeln!(out, "# merged with {}", path.display())?,
+        None => writeln!(out, "# no config file")?,
+    }
+    for (long, value, explicit) in effective_options(matches) {
+        let comment = if explicit { "" } else { "# " };
+        writeln!(out, "{}{} = {}", comment, long, value)?;
+    }
+    out.flush()
+}
+
+/// 合并后的全部选项及其是否不是默认值，按参数定义的顺序；未设置、没有默认值的选项不列出
+fn effective_options(matches: &ArgMatches) -> Vec<(String, toml::Value, bool)> {
+    let mut options = Vec::new();
+    for arg in Cli::command().get_arguments() {
+        let (Some(long), id) = (arg.get_long(), arg.get_id().as_str()) else {
+            continue;
+        };
+        if CONFIG_EXCLUDED.contains(&long) {
+            continue;
         }
+        let value = match arg.get_action() {
+            ArgAction::SetTrue => toml::Value::Boolean(matches.get_flag(id)),
+            ArgAction::Count => toml::Value::Integer(matches.get_count(id).into()),
+            // 以逗号分隔的多个值（如 --fail-on）合并为一个字符串，与配置文件中的写法相同
+            _ => match matches.get_raw(id) {
+                Some(raw) if raw.len() > 0 => config_value(
+                    arg,
+                    raw.map(|value| value.to_string_lossy())
+                        .collect::<Vec<_>>()
+                        .join(","),
+                ),
+                _ => continue,
+            },
+        };
+        let explicit = matches
+            .value_source(id)
+            .is_some_and(|source| source != ValueSource::DefaultValue);
+        options.push((long.to_string(), value, explicit));
+    }
+    options
+}
+
+/// 按参数的类型写出配置值：整数与小数参数为 TOML 数字，其余为字符串
+fn config_value(arg: &Arg, raw: String) -> toml::Value {
+    let type_id = arg.get_value_parser().type_id();
+    let integers = [
+        TypeId::of::<u8>(),
+        TypeId::of::<u16>(),
+        TypeId::of::<u32>(),
+        TypeId::of::<u64>(),
+        TypeId::of::<usize>(),
+    ];
+    if integers.iter().any(|integer| type_id == *integer)
+        && let Ok(n) = raw.parse()
+    {
+        return toml::Value::Integer(n);
+    }
+    if type_id == TypeId::of::<f64>()
+        && let Ok(n) = raw.parse()
+    {
+        return toml::Value::Float(n);
+    }
+    toml::Value::String(raw)
+}
+
+// ==================== 监视模式 ====================
+
+/// 最后一次文件变动之后等待这么久再转换，避免读到写了一半的文件
+const WATCH_DEBOUNCE: Duration = Duration::from_millis(300);
+
+/// 作为输入旁图片、变动时需要重新转换的扩展名
+const IMAGE_EXTENSIONS: &[&str] = &[
+    "png", "jpg", "jpeg", "gif", "webp", "bmp", "svg", "tif", "tiff", "avif",
+];
+
+enum WatchSignal {
+    Changed,
+    Error(String),
+    Interrupted,
+}
+
+/// 路径的绝对形式；文件不存在（已删除或尚未写出）时规范化其所在目录
+fn absolute_path(path: &Path) -> PathBuf {
+    if let Ok(path) = fs::canonicalize(path) {
+        return path;
+    }
+    let parent = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
     };
+    match (fs::canonicalize(parent), path.file_name()) {
+        (Ok(parent), Some(name)) => parent.join(name),
+        _ => path.to_path_buf(),
+    }
+}
 
-    let base_path = input_path.parent().unwrap_or(Path::new("."));
+/// 当前时刻（UTC），用于每次重新转换时打印的时间戳
+fn timestamp() -> String {
+    let secs = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs());
+    format!(
+        "{:02}:{:02}:{:02}",
+        secs / 3600 % 24,
+        secs / 60 % 60,
+        secs % 60
+    )
+}
+
+/// 转换一次并打印结果；出错时只打印，之前的输出保持不变
+fn rebuild(cli: &Cli, matches: &ArgMatches) {
+    let started = Instant::now();
+    match run(cli, matches) {
+        Ok(_) => info!(
+            "[{} UTC] Rebuilt in {:.2}s",
+            timestamp(),
+            started.elapsed().as_secs_f64()
+        ),
+        Err(message) => error!("[{} UTC] Rebuild failed: {}", timestamp(), message),
+    }
+}
 
-    println!("Processing {} pages...", layout_json.pdf_info.len());
+/// 先转换一次，之后输入文件或其目录下的图片每次变动都重新转换，直到 Ctrl-C
+///
+/// 输入文件所在目录与图片目录都会被监视（编辑器保存时常常是替换整个文件，单独监视文件会丢失事件）；
+/// 输出文件、`--images-dir`、`--stats` 与 `--report` 写出的文件不触发转换。
+fn watch(cli: &Cli, matches: &ArgMatches) -> Result<ExitCode, String> {
+    let (input_paths, output_path) = io_paths(cli);
+    if input_paths.iter().any(|path| path == Path::new(STDIO_PATH)) {
+        return Err("--watch cannot read from stdin".to_string());
+    }
+    if output_path == Path::new(STDIO_PATH) {
+        return Err("--watch needs an output file; give one with -o".to_string());
+    }
 
-    let markdown = convert_layout_to_markdown(&layout_json, base_path);
+    let inputs: Vec<PathBuf> = input_paths.iter().map(|path| absolute_path(path)).collect();
+    let ignored: Vec<PathBuf> = [
+        Some(output_path.clone()),
+        images_dir(cli, &output_path),
+        linked_images_dir(cli, &output_path),
+        cli.stats.clone(),
+        cli.report.clone(),
+    ]
+    .into_iter()
+    .flatten()
+    .map(|path| absolute_path(&path))
+    .collect();
+    // 图片所在目录递归监视；同一目录重复出现时递归优先
+    let mut watched: Vec<(PathBuf, RecursiveMode)> = Vec::new();
+    for path in &input_paths {
+        let image_dir = absolute_path(cli.base_path.as_deref().unwrap_or_else(|| input_dir(path)));
+        let input_dir = absolute_path(input_dir(path));
+        for (dir, mode) in [
+            (image_dir, RecursiveMode::Recursive),
+            (input_dir, RecursiveMode::NonRecursive),
+        ] {
+            match watched.iter_mut().find(|(seen, _)| *seen == dir) {
+                Some((_, seen_mode)) if mode == RecursiveMode::Recursive => *seen_mode = mode,
+                Some(_) => {}
+                None => watched.push((dir, mode)),
+            }
+        }
+    }
 
-    match fs::write(&output_path, markdown) {
-        Ok(_) => {
-            println!("Output written to: {}", output_path.display());
-            println!("Done!");
+    let relevant = move |path: &Path| {
+        if inputs.iter().any(|input| input == path) {
+            return true;
         }
-        Err(e) => {
-            eprintln!("Error writing output: {}", e);
-            std::process::exit(1);
+        !ignored.iter().any(|ignored| path.starts_with(ignored))
+            && path.extension().is_some_and(|ext| {
+                IMAGE_EXTENSIONS
+                    .iter()
+                    .any(|image| ext.eq_ignore_ascii_case(image))
+            })
+    };
+    let (sender, receiver) = mpsc::channel();
+    let event_sender = sender.clone();
+    let mut watcher = notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+        let signal = match event {
+            // 读取文件产生的访问事件不算变动，否则每次转换都会触发下一次
+            Ok(event) if matches!(event.kind, EventKind::Access(_)) => return,
+            Ok(event) if event.paths.iter().any(|path| relevant(path)) => WatchSignal::Changed,
+            Ok(_) => return,
+            Err(e) => WatchSignal::Error(e.to_string()),
+        };
+        let _ = event_sender.send(signal);
+    })
+    .map_err(|e| format!("starting file watcher: {}", e))?;
+    for (dir, mode) in &watched {
+        watcher
+            .watch(dir, *mode)
+            .map_err(|e| format!("watching {}: {}", dir.display(), e))?;
+    }
+    ctrlc::set_handler(move || {
+        let _ = sender.send(WatchSignal::Interrupted);
+    })
+    .map_err(|e| format!("installing Ctrl-C handler: {}", e))?;
+
+    rebuild(cli, matches);
+    info!("Watching for changes (Ctrl-C to stop)...");
+    'watch: loop {
+        match receiver.recv() {
+            Ok(WatchSignal::Changed) => {}
+            Ok(WatchSignal::Error(message)) => {
+                warn!("Warning: file watcher: {}", message);
+                continue;
+            }
+            Ok(WatchSignal::Interrupted) | Err(_) => break,
+        }
+        // 等到一段时间内没有新的变动再转换
+        loop {
+            match receiver.recv_timeout(WATCH_DEBOUNCE) {
+                Ok(WatchSignal::Changed) => {}
+                Ok(WatchSignal::Error(message)) => {
+                    warn!("Warning: file watcher: {}", message);
+                }
+                Ok(WatchSignal::Interrupted) | Err(RecvTimeoutError::Disconnected) => {
+                    break 'watch;
+                }
+                Err(RecvTimeoutError::Timeout) => break,
+            }
+        }
+        rebuild(cli, matches);
+    }
+    info!("Stopped watching.");
+    Ok(ExitCode::SUCCESS)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Cli {
+        Cli::try_parse_from(std::iter::once("mineru-json-to-md").chain(args.iter().copied()))
+            .unwrap()
+    }
+
+    #[test]
+    fn linked_images_default_to_a_directory_named_after_the_output() {
+        let cli = parse(&[
+            "layout.json",
+            "--max-image-bytes",
+            "10",
+            "--oversize-policy",
+            "link",
+        ]);
+        assert_eq!(
+            linked_images_dir(&cli, Path::new("out/paper.md")),
+            Some(PathBuf::from("out/paper_images"))
+        );
+        assert_eq!(
+            linked_images_dir(&cli, Path::new(STDIO_PATH)),
+            Some(PathBuf::from("output_images"))
+        );
+    }
+
+    #[test]
+    fn latex_and_org_images_default_to_a_directory_named_after_the_output() {
+        let cli = parse(&["layout.json", "--format", "latex"]);
+        assert_eq!(
+            images_dir(&cli, Path::new("out/paper.tex")),
+            Some(PathBuf::from("out/paper_images"))
+        );
+        let cli = parse(&["layout.json", "--format", "org"]);
+        assert_eq!(
+            images_dir(&cli, Path::new("notes.org")),
+            Some(PathBuf::from("notes_images"))
+        );
+        let cli = parse(&[
+            "layout.json",
+            "--format",
+            "latex",
+            "--images-dir",
+            "figures",
+        ]);
+        assert_eq!(
+            images_dir(&cli, Path::new("out/paper.tex")),
+            Some(PathBuf::from("figures"))
+        );
+    }
+
+    #[test]
+    fn printed_config_loads_back_with_typed_values() {
+        let args = [
+            "mineru-json-to-md",
+            "layout.json",
+            "--image-quality",
+            "70",
+            "--flavor",
+            "gfm",
+            "--images-dir",
+            "figures",
+            "--embed-threshold",
+            "--fail-on",
+            "missing_image,empty_page",
+        ];
+        let matches = Cli::command().get_matches_from(args);
+        let options = effective_options(&matches);
+        let explicit: toml::Table = options
+            .iter()
+            .filter(|(_, _, explicit)| *explicit)
+            .map(|(long, value, _)| (long.clone(), value.clone()))
+            .collect();
+        assert_eq!(explicit["image-quality"], toml::Value::Integer(70));
+        assert_eq!(explicit["embed-threshold"], toml::Value::Integer(65536));
+        assert_eq!(
+            explicit["fail-on"],
+            toml::Value::String("missing_image,empty_page".to_string())
+        );
+        // 取默认值的 outline-format 需要 --outline，不能写入配置
+        assert!(!explicit.contains_key("outline-format"));
+        assert!(
+            options
+                .iter()
+                .any(|(long, _, explicit)| long == "outline-format" && !explicit)
+        );
+
+        let path = env::temp_dir().join(format!("mineru-md-config-{}.toml", std::process::id()));
+        fs::write(&path, toml::to_string(&explicit).unwrap()).unwrap();
+        let mut reloaded = vec![OsString::from("mineru-json-to-md"), "layout.json".into()];
+        reloaded.extend(config_args(&path).unwrap());
+        fs::remove_file(&path).unwrap();
+        let reloaded = Cli::command().try_get_matches_from(reloaded).unwrap();
+        let options_again: Vec<_> = effective_options(&reloaded);
+        assert_eq!(options_again, options);
+    }
+
+    #[test]
+    fn quality_gates_exit_with_a_code_distinct_from_usage_errors() {
+        let usage = Cli::try_parse_from(["mineru-json-to-md", "--no-such-flag"]).unwrap_err();
+        assert_ne!(i32::from(EXIT_WARNINGS), usage.exit_code());
+        assert_ne!(ExitCode::from(EXIT_WARNINGS), ExitCode::FAILURE);
+    }
+
+    #[test]
+    fn help_texts_do_not_contain_line_break_placeholders() {
+        // clap 把帮助中的 {n} 当作换行输出
+        let command = Cli::command();
+        let commands = std::iter::once(&command).chain(command.get_subcommands());
+        for arg in commands.flat_map(|command| command.get_arguments()) {
+            for help in [arg.get_help(), arg.get_long_help()].into_iter().flatten() {
+                assert!(!help.to_string().contains("{n}"), "{:?}", arg.get_id());
+            }
         }
     }
 }
@@ -0,0 +1,514 @@
+//! 跨页段落：页面末尾未结束的段落与下一页开头的续文合并为一段；
+//! 折行后被拆成两个 title 块的长标题合并为一个标题；版面检测重叠时重复识别的同一块只保留一个
+
+use std::borrow::Cow;
+
+use crate::analyze::heading_level;
+use crate::numbering::{is_unnumbered_title, section_name};
+use crate::text::{extract_text_from_block, is_cjk};
+use crate::types::{Block, PageInfo};
+
+// ==================== 识别 ====================
+
+/// 句末标点；段落以这些字符（及其后的右引号、右括号）结尾时视为已经结束
+const SENTENCE_END: &[char] = &['.', '。', '?', '？', '!', '！', ':', '：'];
+
+/// 句末标点之后可能跟着的右引号与右括号
+const CLOSING: &[char] = &['"', '\'', '”', '’', ')', '）', '」', '』'];
+
+/// 可以参与合并的块：普通文本段落，不含代码块、引文和旋转的文字
+fn is_paragraph(block: &Block) -> bool {
+    block.block_type == "text"
+        && !matches!(block.sub_type.as_deref(), Some("code" | "quote"))
+        && block.rotation().is_none()
+}
+
+/// 段落是否在句子中间结束
+fn ends_mid_sentence(block: &Block, dehyphenate: bool) -> bool {
+    let text = extract_text_from_block(block, dehyphenate);
+    let text = text.trim_end().trim_end_matches(CLOSING);
+    !text.is_empty() && !text.ends_with(SENTENCE_END)
+}
+
+/// 段落是否像是上一段的续文：以小写字母或中日韩文字（不含标点）开头
+fn starts_continuation(block: &Block, dehyphenate: bool) -> bool {
+    extract_text_from_block(block, dehyphenate)
+        .trim_start()
+        .starts_with(|c: char| c.is_lowercase() || (is_cjk(c) && c.is_alphabetic()))
+}
+
+/// 下一页的第一个块是否接续本页的最后一个块；标题、列表、表格和图片从不合并
+///
+/// 各页并行渲染前先按顺序判断每个分页处，两页都应已按阅读顺序排列。
+pub(crate) fn continues_onto(page: &PageInfo, next: &PageInfo, dehyphenate: bool) -> bool {
+    match (page.para_blocks.last(), next.para_blocks.first()) {
+        (Some(last), Some(first)) => {
+            is_paragraph(last)
+                && is_paragraph(first)
+                && ends_mid_sentence(last, dehyphenate)
+                && starts_continuation(first, dehyphenate)
+        }
+        _ => false,
+    }
+}
+
+// ==================== 合并 ====================
+
+/// 把下一页开头的续文接到本页最后一个段落之后，分页线因此落在合并后的段落之后
+pub(crate) fn append_continuation(page: &mut PageInfo, continuation: &Block) {
+    if let Some(last) = page.para_blocks.last_mut() {
+        last.lines
+            .get_or_insert_with(Vec::new)
+            .extend(continuation.lines.iter().flatten().cloned());
+    }
+}
+
+/// 去掉已经并入上一页的续文
+pub(crate) fn remove_continuation(page: &mut PageInfo) {
+    if !page.para_blocks.is_empty() {
+        page.para_blocks.remove(0);
+    }
+}
+
+// ==================== 标题 ====================
+
+/// 两个标题块之间的空隙不超过前一块行高的这一倍数时视为同一标题折行
+const TITLE_GAP_RATIO: f64 = 0.8;
+
+/// 块的平均行高；没有 bbox 时为 `None`
+fn line_height(block: &Block) -> Option<f64> {
+    let lines = block.lines.as_ref().map_or(0, Vec::len).max(1);
+    Some(block.bbox.height() / lines as f64).filter(|height| *height > 0.0)
+}
+
+/// 除摘要、参考文献等不编号的标题外，本身就是完整标题、不会折行到下一块的常见章节名
+/// （比较方式同 [`section_name`]）
+const STANDALONE_TITLES: &[&str] = &[
+    "introduction",
+    "background",
+    "related work",
+    "discussion",
+    "conclusion",
+    "conclusions",
+    "appendix",
+    "引言",
+    "绪论",
+    "结论",
+    "附录",
+];
+
+/// 标题是否是独立的章节名，如 "Abstract"、"References"、"引言"
+fn is_standalone_title(title: &str) -> bool {
+    is_unnumbered_title(title) || STANDALONE_TITLES.contains(&section_name(title).as_str())
+}
+
+/// 后一个标题块是否是前一个的折行：两块求出的标题层级相同，前一块未以句末标点结束、
+/// 也不是独立的章节名，且上下紧挨（空隙小于行高的 [`TITLE_GAP_RATIO`] 倍，允许少许重叠）
+fn continues_title(title: &Block, next: &Block, dehyphenate: bool) -> bool {
+    if next.block_type != "title" || next.bbox.is_empty() {
+        return false;
+    }
+    let Some(height) = line_height(title) else {
+        return false;
+    };
+    let (title_box, next_box) = (&title.bbox, &next.bbox);
+    let gap = next_box.top() - title_box.bottom();
+    let overlaps = next_box.left() < title_box.right() && title_box.left() < next_box.right();
+    if !overlaps || gap >= height * TITLE_GAP_RATIO || gap <= -height / 2.0 {
+        return false;
+    }
+    let text = extract_text_from_block(title, dehyphenate);
+    let next_text = extract_text_from_block(next, dehyphenate);
+    heading_level(title, &text) == heading_level(next, &next_text)
+        && ends_mid_sentence(title, dehyphenate)
+        && !is_standalone_title(&text)
+}
+
+/// 合并页面中连续的、被折行拆开的标题块；中间隔着其他块的标题不合并。
+/// 没有需要合并的标题时原样借用
+pub(crate) fn merge_split_titles(blocks: &[Block], dehyphenate: bool) -> Cow<'_, [Block]> {
+    let split = blocks.windows(2).any(|pair| {
+        pair[0].block_type == "title" && continues_title(&pair[0], &pair[1], dehyphenate)
+    });
+    if !split {
+        return Cow::Borrowed(blocks);
+    }
+
+    let mut merged: Vec<Block> = Vec::with_capacity(blocks.len());
+    for block in blocks {
+        if let Some(title) = merged.last_mut()
+            && title.block_type == "title"
+            && continues_title(title, block, dehyphenate)
+        {
+            title
+                .lines
+                .get_or_insert_with(Vec::new)
+                .extend(block.lines.iter().flatten().cloned());
+            title.bbox = title.bbox.union(&block.bbox);
+            continue;
+        }
+        merged.push(block.clone());
+    }
+    Cow::Owned(merged)
+}
+
+// ==================== 重复的块 ====================
+
+/// 两个块的 bbox 交并比超过这一比例时视为同一区域
+const DUPLICATE_IOU: f64 = 0.8;
+
+/// 规范化后的文字相似度超过这一比例时视为同一内容
+const DUPLICATE_SIMILARITY: f64 = 0.95;
+
+/// 规范化后少于这么多字符的块不参与去重，以免表头、图注等短文字在不同位置合理地重复时被误删
+const MIN_DUPLICATE_CHARS: usize = 20;
+
+/// 警告中引用的被去掉的块开头的字符数
+const DUPLICATE_PREFIX_CHARS: usize = 40;
+
+/// 比较用的文字：忽略大小写，去掉所有空白
+fn normalized_chars(block: &Block, dehyphenate: bool) -> Vec<char> {
+    extract_text_from_block(block, dehyphenate)
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+/// 按字符计的编辑距离求出的相似度，1 为完全相同
+fn similarity(a: &[char], b: &[char]) -> f64 {
+    let longest = a.len().max(b.len());
+    if longest == 0 {
+        return 1.0;
+    }
+    // 长度相差过多时不必计算编辑距离
+    if (a.len().min(b.len()) as f64) < longest as f64 * DUPLICATE_SIMILARITY {
+        return 0.0;
+    }
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    let mut current = vec![0; b.len() + 1];
+    for (i, ca) in a.iter().enumerate() {
+        current[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != cb);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        std::mem::swap(&mut previous, &mut current);
+    }
+    1.0 - previous[b.len()] as f64 / longest as f64
+}
+
+/// 同一页中类型相同、bbox 大部分重叠且文字几乎相同的块只保留文字较长的一个（一样长时保留先出现的），
+/// 返回保留的块与被去掉的各块开头的文字。没有重复的块时原样借用
+pub(crate) fn drop_duplicate_blocks(
+    blocks: &[Block],
+    dehyphenate: bool,
+) -> (Cow<'_, [Block]>, Vec<String>) {
+    let texts: Vec<Vec<char>> = blocks
+        .iter()
+        .map(|block| normalized_chars(block, dehyphenate))
+        .collect();
+    let mut dropped = vec![false; blocks.len()];
+    for i in 0..blocks.len() {
+        for j in i + 1..blocks.len() {
+            if dropped[i] || dropped[j] {
+                continue;
+            }
+            let (a, b) = (&blocks[i], &blocks[j]);
+            let duplicate = a.block_type == b.block_type
+                && !a.bbox.is_empty()
+                && !b.bbox.is_empty()
+                && texts[i].len().min(texts[j].len()) >= MIN_DUPLICATE_CHARS
+                && a.bbox.iou(&b.bbox) > DUPLICATE_IOU
+                && similarity(&texts[i], &texts[j]) > DUPLICATE_SIMILARITY;
+            if duplicate {
+                if texts[j].len() > texts[i].len() {
+                    dropped[i] = true;
+                } else {
+                    dropped[j] = true;
+                }
+            }
+        }
+    }
+    if !dropped.contains(&true) {
+        return (Cow::Borrowed(blocks), Vec::new());
+    }
+
+    let prefixes = blocks
+        .iter()
+        .zip(&dropped)
+        .filter(|(_, dropped)| **dropped)
+        .map(|(block, _)| {
+            let text = extract_text_from_block(block, dehyphenate);
+            let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+            match text.char_indices().nth(DUPLICATE_PREFIX_CHARS) {
+                Some((end, _)) => format!("{}…", &text[..end]),
+                None => text,
+            }
+        })
+        .collect();
+    let kept = blocks
+        .iter()
+        .zip(&dropped)
+        .filter(|(_, dropped)| !**dropped)
+        .map(|(block, _)| block.clone())
+        .collect();
+    (Cow::Owned(kept), prefixes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{bare_options, layout, page_layout, text_block};
+    use crate::{ConvertOptions, ConvertOutput, Flavor, LayoutJson, convert_layout_to_markdown};
+    use serde_json::json;
+    use std::path::Path;
+
+    fn convert(layout: &LayoutJson, options: &ConvertOptions) -> ConvertOutput {
+        convert_layout_to_markdown(layout, Path::new("."), options).unwrap()
+    }
+
+    fn titles(output: &ConvertOutput) -> Vec<&str> {
+        output
+            .outline
+            .iter()
+            .map(|entry| entry.title.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn a_title_wrapped_onto_two_blocks_becomes_one_heading() {
+        let split = layout(
+            serde_json::from_str(include_str!("../tests/fixtures/split_title.json")).unwrap(),
+        );
+        let output = convert(&split, &bare_options());
+        assert_eq!(
+            titles(&output),
+            ["Chapter 3: Advanced Methods for Distributed Systems"]
+        );
+        assert_eq!(output.markdown.matches("<a id=").count(), 1);
+        assert!(
+            output
+                .markdown
+                .contains("\n## Chapter 3: Advanced Methods for Distributed Systems\n"),
+            "{}",
+            output.markdown
+        );
+
+        let options = ConvertOptions {
+            merge_titles: false,
+            ..bare_options()
+        };
+        assert_eq!(
+            titles(&convert(&split, &options)),
+            ["Chapter 3: Advanced Methods for", "Distributed Systems"]
+        );
+    }
+
+    #[test]
+    fn titles_apart_or_ending_a_sentence_stay_separate() {
+        let finished = page_layout(json!([
+            text_block("title", [72.0, 96.0, 468.0, 118.0], "Part I:"),
+            text_block("title", [72.0, 121.0, 468.0, 143.0], "Foundations"),
+        ]));
+        assert_eq!(
+            titles(&convert(&finished, &bare_options())),
+            ["Part I:", "Foundations"]
+        );
+        assert!(matches!(
+            merge_split_titles(&finished.pdf_info[0].para_blocks, true),
+            Cow::Borrowed(_)
+        ));
+
+        let distant = page_layout(json!([
+            text_block("title", [72.0, 96.0, 468.0, 118.0], "Methods for"),
+            text_block("title", [72.0, 300.0, 468.0, 322.0], "Distributed Systems"),
+        ]));
+        assert_eq!(titles(&convert(&distant, &bare_options())).len(), 2);
+
+        let interrupted = page_layout(json!([
+            text_block("title", [72.0, 96.0, 468.0, 118.0], "Methods for"),
+            text_block("text", [72.0, 119.0, 468.0, 120.0], "·"),
+            text_block("title", [72.0, 121.0, 468.0, 143.0], "Distributed Systems"),
+        ]));
+        assert_eq!(titles(&convert(&interrupted, &bare_options())).len(), 2);
+    }
+
+    #[test]
+    fn titles_are_not_merged_across_pages() {
+        let layout = layout(json!({"pdf_info": [
+            {"page_idx": 0, "page_size": [612, 792], "para_blocks": [
+                text_block("title", [72.0, 750.0, 468.0, 772.0], "Methods for"),
+            ]},
+            {"page_idx": 1, "page_size": [612, 792], "para_blocks": [
+                text_block("title", [72.0, 20.0, 468.0, 42.0], "Distributed Systems"),
+            ]}
+        ]}));
+        assert_eq!(
+            titles(&convert(&layout, &bare_options())),
+            ["Methods for", "Distributed Systems"]
+        );
+    }
+
+    #[test]
+    fn standalone_section_names_and_different_levels_stay_separate() {
+        // 摘要紧挨在折行的章标题之上，层级相同，也未以句末标点结束
+        let mut json: serde_json::Value =
+            serde_json::from_str(include_str!("../tests/fixtures/split_title.json")).unwrap();
+        let mut heading = text_block("title", [72.0, 71.0, 200.0, 93.0], "Abstract");
+        heading["level"] = json!(1);
+        json["pdf_info"][0]["para_blocks"]
+            .as_array_mut()
+            .unwrap()
+            .insert(0, heading);
+        assert_eq!(
+            titles(&convert(&layout(json), &bare_options())),
+            [
+                "Abstract",
+                "Chapter 3: Advanced Methods for Distributed Systems"
+            ]
+        );
+
+        let chinese = page_layout(json!([
+            text_block("title", [72.0, 96.0, 468.0, 118.0], "引言"),
+            text_block("title", [72.0, 121.0, 468.0, 143.0], "研究背景"),
+        ]));
+        assert_eq!(
+            titles(&convert(&chinese, &bare_options())),
+            ["引言", "研究背景"]
+        );
+
+        // 原始层级都缺失时按编号求出的层级不同
+        let numbered = page_layout(json!([
+            text_block("title", [72.0, 96.0, 468.0, 118.0], "3 Methods for"),
+            text_block(
+                "title",
+                [72.0, 121.0, 468.0, 143.0],
+                "3.1 Distributed Systems"
+            ),
+        ]));
+        assert_eq!(titles(&convert(&numbered, &bare_options())).len(), 2);
+        assert!(matches!(
+            merge_split_titles(&numbered.pdf_info[0].para_blocks, true),
+            Cow::Borrowed(_)
+        ));
+    }
+
+    #[test]
+    fn paragraphs_split_by_a_page_break_are_joined() {
+        let page = |idx: usize, blocks: serde_json::Value| json!({"page_idx": idx, "page_size": [612, 792], "para_blocks": blocks});
+        let layout = layout(json!({"pdf_info": [
+            page(0, json!([
+                text_block("text", [72.0, 700.0, 540.0, 760.0], "The network was"),
+            ])),
+            page(1, json!([
+                text_block("text", [72.0, 40.0, 540.0, 80.0], "partitioned for an hour."),
+                text_block("text", [72.0, 700.0, 540.0, 760.0], "Replicas then"),
+            ])),
+            page(2, json!([
+                text_block("text", [72.0, 40.0, 540.0, 80.0], "Recovered on their own."),
+                text_block("text", [72.0, 700.0, 540.0, 760.0], "The log kept"),
+            ])),
+            page(3, json!([
+                text_block("text", [72.0, 40.0, 540.0, 80.0], "growing while"),
+            ])),
+            page(4, json!([
+                text_block("text", [72.0, 40.0, 540.0, 80.0], "nobody compacted it."),
+            ])),
+        ]}));
+        let options = ConvertOptions {
+            merge_cross_page: true,
+            page_dividers: true,
+            flavor: Flavor::Gfm,
+            ..bare_options()
+        };
+        // 大写字母开头的段落不是续文；整页只有一段续文时，下一页不再接续到这一页。
+        // 分页线移到合并后的段落之后
+        assert_eq!(
+            convert(&layout, &options).markdown,
+            "The network was partitioned for an hour.\n\n\n---\n\nPage 1\n\n\
+             Replicas then\n\n\n---\n\nPage 2\n\n\
+             Recovered on their own.\n\nThe log kept growing while\n\n\n---\n\nPage 3\n\n\
+             \n---\n\nPage 4\n\n\
+             nobody compacted it.\n\n\n---\n\nPage 5\n\n"
+        );
+
+        let options = ConvertOptions {
+            merge_cross_page: false,
+            ..options
+        };
+        let output = convert(&layout, &options).markdown;
+        assert!(output.starts_with("The network was\n"), "{}", output);
+    }
+
+    #[test]
+    fn duplicate_blocks_are_dropped_keeping_the_longer_text() {
+        let layout = page_layout(json!([
+            text_block(
+                "text",
+                [72.0, 100.0, 540.0, 160.0],
+                "Consensus needs a quorum of replicas"
+            ),
+            text_block(
+                "text",
+                [73.0, 101.0, 540.0, 161.0],
+                "Consensus needs a quorum of replicas."
+            ),
+            // 同一位置的短文字、不同类型或不重叠的相同文字都保留
+            text_block("text", [72.0, 200.0, 540.0, 220.0], "Table 1"),
+            text_block("text", [72.0, 201.0, 540.0, 221.0], "Table 1"),
+            text_block(
+                "title",
+                [72.0, 300.0, 540.0, 360.0],
+                "Consensus needs a quorum of replicas."
+            ),
+            text_block(
+                "text",
+                [72.0, 500.0, 540.0, 560.0],
+                "Consensus needs a quorum of replicas."
+            ),
+        ]));
+        let options = ConvertOptions {
+            dedupe_blocks: true,
+            ..bare_options()
+        };
+        let output = convert(&layout, &options);
+        // 另有一处出现在目录中
+        assert_eq!(
+            output
+                .markdown
+                .matches("Consensus needs a quorum of replicas")
+                .count(),
+            4,
+            "{}",
+            output.markdown
+        );
+        assert!(
+            output
+                .markdown
+                .contains("Consensus needs a quorum of replicas.\n\nTable 1"),
+            "{}",
+            output.markdown
+        );
+        assert_eq!(output.markdown.matches("Table 1").count(), 2);
+        assert_eq!(output.stats.duplicate_blocks, 1);
+        assert_eq!(
+            output
+                .warnings
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>(),
+            ["page 1: dropped duplicate block \"Consensus needs a quorum of replicas\""]
+        );
+
+        let output = convert(&layout, &bare_options());
+        assert_eq!(
+            output
+                .markdown
+                .matches("Consensus needs a quorum of replicas")
+                .count(),
+            5
+        );
+        assert!(output.warnings.is_empty());
+    }
+}
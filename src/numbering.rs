@@ -0,0 +1,206 @@
+//! 标题编号：开启 number_headings 时为各级标题补上 "1."、"1.1"、"1.1.1" 之类的编号
+
+/// 不参与编号的前言、附属部分标题（比较时忽略大小写与末尾的冒号）；
+/// 这样的一级标题之下的各级标题同样不编号
+const UNNUMBERED_TITLES: &[&str] = &[
+    "abstract",
+    "summary",
+    "executive summary",
+    "preface",
+    "foreword",
+    "contents",
+    "table of contents",
+    "acknowledgements",
+    "acknowledgments",
+    "references",
+    "bibliography",
+    "glossary",
+    "index",
+    "摘要",
+    "内容提要",
+    "前言",
+    "序言",
+    "目录",
+    "致谢",
+    "参考文献",
+    "索引",
+];
+
+/// 比较用的章节名：去掉首尾空白与末尾的冒号、句点，转为小写
+pub(crate) fn section_name(title: &str) -> String {
+    title
+        .trim()
+        .trim_end_matches([':', '：', '.'])
+        .trim_end()
+        .to_lowercase()
+}
+
+pub(crate) fn is_unnumbered_title(title: &str) -> bool {
+    UNNUMBERED_TITLES.contains(&section_name(title).as_str())
+}
+
+/// 标题是否已经以编号开头，如 `3 `、`2.1 `、`4.2.1. `、`5) `
+fn has_leading_number(title: &str) -> bool {
+    let mut rest = title;
+    loop {
+        let digits = rest.len() - rest.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+        if digits == 0 {
+            return false;
+        }
+        rest = &rest[digits..];
+        match rest.strip_prefix('.') {
+            Some(after) if after.starts_with(|c: char| c.is_ascii_digit()) => rest = after,
+            _ => break,
+        }
+    }
+    let rest = rest.strip_prefix(['.', ')']).unwrap_or(rest);
+    rest.starts_with(char::is_whitespace)
+}
+
+/// 各级标题的计数；按页面顺序逐个标题推进，各页开头的状态由调用方预先求出
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct HeadingNumbers {
+    /// 一级到五级标题当前的编号
+    counters: [usize; 5],
+    /// 已经遇到过编号的一级标题；之前的标题（前言等）都不编号
+    started: bool,
+    /// 当前处在不编号的一级标题（参考文献等）之下
+    unnumbered: bool,
+}
+
+impl HeadingNumbers {
+    /// 为一个标题分配编号，返回加在标题文字之前的前缀，如 `"1. "`、`"2.3 "`；
+    /// 文档标题（层级 0）、已经带有编号的标题与不编号的标题返回 `None`，也不推进计数
+    pub(crate) fn next(&mut self, title: &str, level: usize) -> Option<String> {
+        if level == 0 || has_leading_number(title) {
+            return None;
+        }
+        if is_unnumbered_title(title) {
+            if level == 1 {
+                self.unnumbered = true;
+            }
+            return None;
+        }
+        if level == 1 {
+            self.started = true;
+            self.unnumbered = false;
+        } else if !self.started || self.unnumbered {
+            return None;
+        }
+
+        let depth = level.min(self.counters.len());
+        self.counters[depth - 1] += 1;
+        // 跳过的中间层级从 1 开始，避免出现 "1.0.1"
+        for counter in &mut self.counters[..depth - 1] {
+            *counter = (*counter).max(1);
+        }
+        for counter in &mut self.counters[depth..] {
+            *counter = 0;
+        }
+
+        let numbers: Vec<String> = self.counters[..depth]
+            .iter()
+            .map(usize::to_string)
+            .collect();
+        Some(match depth {
+            1 => format!("{}. ", numbers[0]),
+            _ => format!("{} ", numbers.join(".")),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{bare_options, layout, text_block};
+    use crate::{ConvertOptions, convert_layout_to_markdown};
+    use serde_json::json;
+    use std::path::Path;
+
+    #[test]
+    fn headings_are_numbered_by_level() {
+        let mut numbers = HeadingNumbers::default();
+        let prefixes: Vec<Option<String>> = [
+            ("Abstract", 1),
+            ("Scope", 2),
+            ("Introduction", 1),
+            ("Background", 2),
+            ("Details", 4),
+            ("Motivation", 2),
+            ("3 Already numbered", 1),
+            ("Methods", 1),
+            ("Document title", 0),
+            ("References:", 1),
+            ("Books", 2),
+            ("Appendix", 1),
+        ]
+        .into_iter()
+        .map(|(title, level)| numbers.next(title, level))
+        .collect();
+        // 前言不编号；跳过的层级从 1 开始；参考文献及其下属标题不编号，之后继续编号
+        assert_eq!(
+            prefixes,
+            [
+                None,
+                None,
+                Some("1. ".to_string()),
+                Some("1.1 ".to_string()),
+                Some("1.1.1.1 ".to_string()),
+                Some("1.2 ".to_string()),
+                None,
+                Some("2. ".to_string()),
+                None,
+                None,
+                None,
+                Some("3. ".to_string()),
+            ]
+        );
+        assert!(has_leading_number("4.2.1. Setup"));
+        assert!(has_leading_number("5) Setup"));
+        assert!(!has_leading_number("1.5x faster"));
+        assert!(!has_leading_number("3D printing"));
+    }
+
+    #[test]
+    fn numbering_continues_across_pages() {
+        let page = |idx: usize, titles: &[(&str, u32)]| {
+            let blocks: Vec<serde_json::Value> = titles
+                .iter()
+                .enumerate()
+                .map(|(i, (title, level))| {
+                    let top = 50.0 + 100.0 * i as f64;
+                    let mut block = text_block("title", [50.0, top, 550.0, top + 30.0], title);
+                    block["level"] = json!(level);
+                    block
+                })
+                .collect();
+            json!({"page_idx": idx, "page_size": [600, 800], "para_blocks": blocks})
+        };
+        let layout = layout(json!({"pdf_info": [
+            page(0, &[("Introduction", 1), ("Goals", 2)]),
+            page(1, &[("Scope", 2)]),
+            page(2, &[("Design", 1), ("Storage", 2)]),
+        ]}));
+        let options = ConvertOptions {
+            number_headings: true,
+            threads: Some(3),
+            ..bare_options()
+        };
+        let output = convert_layout_to_markdown(&layout, Path::new("."), &options).unwrap();
+        let titles: Vec<&str> = output
+            .outline
+            .iter()
+            .map(|entry| entry.title.as_str())
+            .collect();
+        assert_eq!(
+            titles,
+            [
+                "1. Introduction",
+                "1.1 Goals",
+                "1.2 Scope",
+                "2. Design",
+                "2.1 Storage"
+            ]
+        );
+    }
+}
@@ -0,0 +1,294 @@
+//! 阅读顺序：按 MinerU 给出的 index 重新排列版面块；没有 index 时按 bbox 推断分栏顺序。
+//! 判断栏位、按位置比较块的函数也供脚注排序等其他按版面位置处理的功能使用
+
+use std::borrow::Cow;
+use std::cmp::Ordering;
+
+use crate::ColumnOrder;
+use crate::types::{BBox, Block, PageInfo};
+
+// ==================== 阅读顺序 ====================
+
+/// 排序键：index 优先，相同时按 bbox 的纵坐标
+fn compare_reading_order(a: &Block, b: &Block) -> Ordering {
+    a.index
+        .cmp(&b.index)
+        .then_with(|| compare_top(&a.bbox, &b.bbox))
+}
+
+fn is_reading_ordered(blocks: &[Block]) -> bool {
+    let mut indexed = blocks.iter().filter(|block| block.index.is_some());
+    if let Some(mut prev) = indexed.next() {
+        for block in indexed {
+            if compare_reading_order(prev, block) == Ordering::Greater {
+                return false;
+            }
+            prev = block;
+        }
+    }
+
+    blocks
+        .iter()
+        .all(|block| block.blocks.as_deref().is_none_or(is_reading_ordered))
+}
+
+/// 只重排带 index 的块，它们在数组中占据的位置不变；没有 index 的块保持原位
+fn sort_blocks(blocks: &mut [Block]) {
+    let positions: Vec<usize> = (0..blocks.len())
+        .filter(|&i| blocks[i].index.is_some())
+        .collect();
+
+    let mut indexed: Vec<Block> = positions.iter().map(|&i| blocks[i].clone()).collect();
+    indexed.sort_by(compare_reading_order);
+    for (pos, block) in positions.into_iter().zip(indexed) {
+        blocks[pos] = block;
+    }
+
+    for block in blocks.iter_mut() {
+        if let Some(children) = &mut block.blocks {
+            sort_blocks(children);
+        }
+    }
+}
+
+/// 返回按阅读顺序排列的页面；已经有序时直接借用原页面，不做任何复制
+///
+/// 旧版 MinerU 的块没有 index，`column_order` 为 [`ColumnOrder::Auto`] 时按 bbox 推断顺序。
+pub(crate) fn reading_order(page: &PageInfo, column_order: ColumnOrder) -> Cow<'_, PageInfo> {
+    if column_order == ColumnOrder::Auto
+        && page.para_blocks.iter().all(|block| block.index.is_none())
+    {
+        return geometric_order(page);
+    }
+    if is_reading_ordered(&page.para_blocks) {
+        return Cow::Borrowed(page);
+    }
+
+    let mut sorted = page.clone();
+    sort_blocks(&mut sorted.para_blocks);
+    Cow::Owned(sorted)
+}
+
+// ==================== 版面位置 ====================
+
+/// 块的水平位置相对于页面中线的偏差小于页宽的该比例时，仍视为落在一栏之内
+const COLUMN_TOLERANCE: f64 = 0.05;
+
+/// 块在双栏页面中的位置
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) enum Column {
+    Left,
+    Right,
+    /// 横跨两栏，如通栏的标题与图片
+    Spanning,
+}
+
+pub(crate) fn column_of(bbox: &BBox, page_width: f64) -> Column {
+    let middle = page_width / 2.0;
+    let tolerance = page_width * COLUMN_TOLERANCE;
+    if bbox.right() <= middle + tolerance {
+        Column::Left
+    } else if bbox.left() >= middle - tolerance {
+        Column::Right
+    } else {
+        Column::Spanning
+    }
+}
+
+/// 划分栏位用的页宽：页面尺寸未知时取正文块 bbox 的最大右边
+pub(crate) fn page_width(page: &PageInfo) -> f64 {
+    match page.page_size.0 {
+        width if width > 0.0 => width,
+        _ => page
+            .para_blocks
+            .iter()
+            .map(|block| block.bbox.right())
+            .fold(0.0, f64::max),
+    }
+}
+
+/// 左右两栏都有块时视为双栏页面
+pub(crate) fn is_two_column(columns: &[Column]) -> bool {
+    columns.contains(&Column::Left) && columns.contains(&Column::Right)
+}
+
+/// 按 bbox 的上边比较；坐标无法比较时视为相同
+pub(crate) fn compare_top(a: &BBox, b: &BBox) -> Ordering {
+    a.top().partial_cmp(&b.top()).unwrap_or(Ordering::Equal)
+}
+
+/// 双栏页面上先左栏、再右栏、最后通栏，各栏内自上而下；单栏页面只按纵坐标
+pub(crate) fn compare_column_then_top(
+    a: &BBox,
+    b: &BBox,
+    page_width: f64,
+    two_columns: bool,
+) -> Ordering {
+    let by_column = if two_columns {
+        column_of(a, page_width).cmp(&column_of(b, page_width))
+    } else {
+        Ordering::Equal
+    };
+    by_column.then_with(|| compare_top(a, b))
+}
+
+/// 与 `target` 同一栏、位于其上方且下边最靠近它的块；双栏页面上横跨两栏的 `target`、
+/// 缺少 bbox，或有两个块同样靠近时返回 `None`
+pub(crate) fn block_above(
+    target: &BBox,
+    blocks: &[Block],
+    page_width: f64,
+    two_columns: bool,
+) -> Option<usize> {
+    if target.is_empty() {
+        return None;
+    }
+    let column = column_of(target, page_width);
+    if two_columns && column == Column::Spanning {
+        return None;
+    }
+    let mut best: Option<(usize, f64)> = None;
+    let mut tied = false;
+    for (i, block) in blocks.iter().enumerate() {
+        let bbox = &block.bbox;
+        if bbox.is_empty()
+            || bbox.bottom() > target.top()
+            || (two_columns && column_of(bbox, page_width) != column)
+        {
+            continue;
+        }
+        match best {
+            Some((_, bottom)) if bbox.bottom() == bottom => tied = true,
+            Some((_, bottom)) if bbox.bottom() < bottom => {}
+            _ => {
+                best = Some((i, bbox.bottom()));
+                tied = false;
+            }
+        }
+    }
+    best.filter(|_| !tied).map(|(i, _)| i)
+}
+
+// ==================== 按版面位置排序 ====================
+
+/// 按 bbox 排列没有 index 的块
+///
+/// 左右两栏都有块时视为双栏页面：通栏的块把页面分为若干段，每段先输出左栏、再输出右栏，各栏内自上而下；
+/// 否则整页自上而下排列。有块缺少 bbox 时无法判断，保持原有顺序。
+fn geometric_order(page: &PageInfo) -> Cow<'_, PageInfo> {
+    let blocks = &page.para_blocks;
+    if blocks.iter().any(|block| block.bbox.is_empty()) {
+        return Cow::Borrowed(page);
+    }
+    let page_width = page_width(page);
+    let columns: Vec<Column> = blocks
+        .iter()
+        .map(|block| column_of(&block.bbox, page_width))
+        .collect();
+    // 稳定排序：纵坐标相同时保持原有顺序
+    let mut by_top: Vec<usize> = (0..blocks.len()).collect();
+    by_top.sort_by(|&a, &b| compare_top(&blocks[a].bbox, &blocks[b].bbox));
+
+    let two_columns = is_two_column(&columns);
+    let order: Vec<usize> = if two_columns {
+        let mut order = Vec::with_capacity(blocks.len());
+        let mut right = Vec::new();
+        for i in by_top {
+            match columns[i] {
+                Column::Left => order.push(i),
+                Column::Right => right.push(i),
+                Column::Spanning => {
+                    order.append(&mut right);
+                    order.push(i);
+                }
+            }
+        }
+        order.append(&mut right);
+        order
+    } else {
+        by_top
+    };
+
+    if order.iter().enumerate().all(|(position, &i)| position == i) {
+        return Cow::Borrowed(page);
+    }
+    let mut sorted = page.clone();
+    sorted.para_blocks = order.into_iter().map(|i| blocks[i].clone()).collect();
+    Cow::Owned(sorted)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{page_layout, text_block};
+    use crate::text::extract_text_from_block;
+    use serde_json::json;
+
+    fn order(page: &PageInfo, column_order: ColumnOrder) -> Vec<String> {
+        reading_order(page, column_order)
+            .para_blocks
+            .iter()
+            .map(|block| extract_text_from_block(block, false))
+            .collect()
+    }
+
+    #[test]
+    fn two_column_pages_are_read_column_by_column() {
+        // 页宽 600：通栏的标题与图注把页面分成两段，每段先左栏后右栏
+        let layout = page_layout(json!([
+            text_block("text", [320.0, 100.0, 560.0, 200.0], "right 1"),
+            text_block("text", [40.0, 210.0, 280.0, 300.0], "left 2"),
+            text_block("title", [40.0, 40.0, 560.0, 80.0], "title"),
+            text_block("text", [40.0, 100.0, 280.0, 200.0], "left 1"),
+            text_block("text", [320.0, 210.0, 560.0, 300.0], "right 2"),
+            text_block("text", [40.0, 320.0, 560.0, 360.0], "spanning caption"),
+            text_block("text", [320.0, 380.0, 560.0, 480.0], "right 3"),
+            text_block("text", [40.0, 380.0, 290.0, 480.0], "left 3"),
+        ]));
+        let page = &layout.pdf_info[0];
+        assert_eq!(
+            order(page, ColumnOrder::Auto),
+            [
+                "title",
+                "left 1",
+                "left 2",
+                "right 1",
+                "right 2",
+                "spanning caption",
+                "left 3",
+                "right 3"
+            ]
+        );
+        assert_eq!(order(page, ColumnOrder::Off)[0], "right 1");
+    }
+
+    #[test]
+    fn single_column_indexed_and_unplaceable_pages() {
+        let single = page_layout(json!([
+            text_block("text", [40.0, 300.0, 560.0, 400.0], "second"),
+            text_block("text", [40.0, 100.0, 560.0, 200.0], "first"),
+        ]));
+        assert_eq!(
+            order(&single.pdf_info[0], ColumnOrder::Auto),
+            ["first", "second"]
+        );
+
+        // 带 index 的页面按 index 排列，不再按位置推断
+        let mut indexed = single.pdf_info[0].clone();
+        for (block, index) in indexed.para_blocks.iter_mut().zip([0, 1]) {
+            block.index = Some(index);
+        }
+        assert_eq!(order(&indexed, ColumnOrder::Auto), ["second", "first"]);
+
+        // 有块缺少 bbox 时保持原有顺序，并直接借用原页面
+        let unplaced = page_layout(json!([
+            text_block("text", [40.0, 300.0, 560.0, 400.0], "second"),
+            {"type": "text", "lines": [{"spans": [{"type": "text", "content": "no box"}]}]},
+            text_block("text", [40.0, 100.0, 560.0, 200.0], "first"),
+        ]));
+        assert!(matches!(
+            reading_order(&unplaced.pdf_info[0], ColumnOrder::Auto),
+            Cow::Borrowed(_)
+        ));
+    }
+}
@@ -0,0 +1,307 @@
+//! Org 输出：正文转义、行内标记的边界、标题、区块与链接，以及每个输出文件开头的导出选项
+
+// ==================== 转义 ====================
+
+/// 零宽空格：Org 没有通用的转义字符，插在标记字符旁边使其不再组成标记，导出后不可见
+const ZERO_WIDTH_SPACE: char = '\u{200b}';
+
+/// 强调、等宽、删除线等行内标记的字符
+const EMPHASIS_MARKERS: &str = "*/_=~+";
+
+/// 行内标记之前可以紧接的字符
+pub(crate) fn opens_markup(c: char) -> bool {
+    c.is_whitespace() || "-('\"{".contains(c)
+}
+
+/// 行内标记之后可以紧接的字符
+pub(crate) fn closes_markup(c: char) -> bool {
+    c.is_whitespace() || "-.,;:!?')}[\"\\".contains(c)
+}
+
+/// 转义 Org 正文：能开始或结束强调的标记字符、`$`、后接字母的 `\`（实体与 LaTeX 片段），
+/// 以及组成链接、脚注、时间戳、宏与目标的 `[[`、`[fn:`、`<<`、`{{{`、`@@`，都以零宽空格隔开；
+/// `line_start` 时在每行会被当作结构的行首（标题的 `*`、注释与关键字的 `#`、列表、表格、固定宽度行，
+/// `1. ` 等编号）之前插入零宽空格
+pub(crate) fn escape_org(text: &str, line_start: bool) -> String {
+    let mut out = String::with_capacity(text.len() + 8);
+    let mut at_line_start = line_start;
+    let mut prev: Option<char> = None;
+    let mut chars = text.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        let next = chars.peek().map(|&(_, next)| next);
+        let rest = &text[i + c.len_utf8()..];
+        if at_line_start && !matches!(c, ' ' | '\t') {
+            if c.is_ascii_punctuation() || ordered_marker(&text[i..]) {
+                out.push(ZERO_WIDTH_SPACE);
+            }
+            at_line_start = false;
+        }
+        let marker = EMPHASIS_MARKERS.contains(c);
+        // 文字的两端之外可能紧接任何字符，按能组成标记处理
+        let opens = marker
+            && prev.is_none_or(opens_markup)
+            && next.is_none_or(|next| !next.is_whitespace());
+        let closes = marker
+            && prev.is_none_or(|prev| !prev.is_whitespace())
+            && next.is_none_or(closes_markup);
+        if opens && !out.ends_with(ZERO_WIDTH_SPACE) {
+            out.push(ZERO_WIDTH_SPACE);
+        }
+        out.push(c);
+        let separate = closes
+            || match c {
+                '$' => true,
+                '\\' => next.is_none_or(|next| next.is_ascii_alphabetic() || "\\([".contains(next)),
+                '_' | '^' | '{' => next == Some('{'),
+                '[' => {
+                    ["[", "fn:", "cite:", "cite/"]
+                        .iter()
+                        .any(|prefix| rest.starts_with(prefix))
+                        || starts_timestamp(rest)
+                }
+                '<' => next == Some('<') || starts_timestamp(rest),
+                '@' => next == Some('@'),
+                _ => false,
+            };
+        if separate {
+            out.push(ZERO_WIDTH_SPACE);
+        }
+        if c == '\n' {
+            at_line_start = line_start;
+        }
+        prev = Some(c);
+    }
+    out
+}
+
+/// 行首的有序列表编号（`1. `、`a) `）
+fn ordered_marker(text: &str) -> bool {
+    let end = text
+        .find(|c: char| !c.is_ascii_alphanumeric())
+        .unwrap_or(text.len());
+    end > 0
+        && text[end..].starts_with(['.', ')'])
+        && text[end + 1..]
+            .chars()
+            .next()
+            .is_none_or(char::is_whitespace)
+}
+
+/// `[`、`<` 之后是否为时间戳的日期 `2024-01-31`
+fn starts_timestamp(rest: &str) -> bool {
+    let bytes = rest.as_bytes();
+    bytes.len() >= 5 && bytes[..4].iter().all(u8::is_ascii_digit) && bytes[4] == b'-'
+}
+
+// ==================== 行内标记 ====================
+
+/// 行内公式：内容与其后的字符满足 `$…$` 的要求时写成 `$…$`，否则写成 `\(…\)`；
+/// `next` 为公式之后的第一个字符，在段落末尾时为 `None`
+pub(crate) fn inline_math(latex: &str, next: Option<char>) -> String {
+    let dollars = !latex.is_empty()
+        && !latex.contains('$')
+        && !latex.starts_with([' ', ',', ';', '.'])
+        && !latex.ends_with([' ', ',', '.'])
+        && next.is_none_or(|next| next.is_whitespace() || "-.,?;:'\")".contains(next));
+    if dollars {
+        format!("${}$", latex)
+    } else {
+        format!("\\({}\\)", latex)
+    }
+}
+
+/// 链接 `[[href][text]]`；文字与地址相同时只写地址。地址中的方括号加反斜杠，
+/// 文字中会提前结束链接的 `]` 之后插入零宽空格
+pub(crate) fn link(text: &str, href: &str) -> String {
+    let target = href.replace('[', "\\[").replace(']', "\\]");
+    if text == href {
+        return format!("[[{}]]", target);
+    }
+    let escaped = escape_org(text, false);
+    let mut description = String::with_capacity(escaped.len());
+    let mut chars = escaped.chars().peekable();
+    while let Some(c) = chars.next() {
+        description.push(c);
+        if c == ']' && chars.peek().is_none_or(|&next| next == ']') {
+            description.push(ZERO_WIDTH_SPACE);
+        }
+    }
+    format!("[[{}][{}]]", target, description)
+}
+
+/// 列表项目的文字：在正文转义之外，开头的 `[`（复选框 `[ ]`、计数器 `[@3]`）与
+/// 描述列表的分隔符 ` :: ` 以零宽空格隔开
+pub(crate) fn item_text(text: &str) -> String {
+    let mut out = escape_org(text, false);
+    if out.starts_with('[') {
+        out.insert(0, ZERO_WIDTH_SPACE);
+    }
+    out.replace(" :: ", &format!(" :{}: ", ZERO_WIDTH_SPACE))
+}
+
+/// 斜体 `/text/`，用于缺失图片等提示段落
+pub(crate) fn italic(text: &str) -> String {
+    format!("/{}/", escape_org(text.trim(), false))
+}
+
+// ==================== 标题与区块 ====================
+
+/// 标题行：层级 0 与 1 都为一颗星，标题中的换行合并为空格；开头的 `TODO`、`DONE`、`COMMENT`、
+/// 优先级 `[#A]` 与末尾的 `:标签:` 以零宽空格隔开，不会被当作标题的属性
+pub(crate) fn headline(level: usize, title: &str) -> String {
+    let title = title.split_whitespace().collect::<Vec<_>>().join(" ");
+    headline_markup(level, &escape_org(&title, false))
+}
+
+/// 同 [`headline`]，`title` 为已转义的标记（如含有行内公式的标题）
+pub(crate) fn headline_markup(level: usize, title: &str) -> String {
+    let mut text = title.split_whitespace().collect::<Vec<_>>().join(" ");
+    let keyword = ["TODO", "DONE", "COMMENT"].iter().any(|keyword| {
+        text.strip_prefix(keyword)
+            .is_some_and(|rest| rest.is_empty() || rest.starts_with(' '))
+    });
+    if keyword || text.starts_with("[#") {
+        text.insert(0, ZERO_WIDTH_SPACE);
+    }
+    if text.ends_with(':') {
+        text.insert(text.len() - 1, ZERO_WIDTH_SPACE);
+    }
+    format!("{} {}\n", "*".repeat(level.max(1)), text)
+}
+
+/// 标题之后的属性抽屉，以 `CUSTOM_ID` 作为链接与导出时的锚点
+pub(crate) fn custom_id(anchor_id: &str) -> String {
+    format!(":PROPERTIES:\n:CUSTOM_ID: {}\n:END:\n", anchor_id)
+}
+
+/// 关键字行 `#+NAME: value`，如图表之前的 `#+CAPTION:`；值中的换行合并为空格
+pub(crate) fn keyword(name: &str, value: &str) -> String {
+    format!(
+        "#+{}: {}\n",
+        name,
+        value.split_whitespace().collect::<Vec<_>>().join(" ")
+    )
+}
+
+/// 原样保留内容的区块，如 `#+BEGIN_SRC rust` … `#+END_SRC`；内容中以 `*`、`#+` 开头
+/// （含已加逗号的）的行前加逗号，不会结束区块或成为标题，导出时逗号被去掉
+pub(crate) fn block(name: &str, argument: &str, content: &str) -> String {
+    let mut out = format!("#+BEGIN_{}", name);
+    if !argument.is_empty() {
+        out.push(' ');
+        out.push_str(argument);
+    }
+    out.push('\n');
+    for line in content.trim_end_matches('\n').lines() {
+        let body = line.trim_start_matches([' ', '\t']);
+        let indent = &line[..line.len() - body.len()];
+        let unescaped = body.trim_start_matches(',');
+        out.push_str(indent);
+        if unescaped.starts_with('*') || unescaped.starts_with("#+") {
+            out.push(',');
+        }
+        out.push_str(body);
+        out.push('\n');
+    }
+    out.push_str(&format!("#+END_{}\n", name));
+    out
+}
+
+/// 注释：每行加上 `# ` 前缀
+pub(crate) fn comment(text: &str) -> String {
+    text.lines()
+        .map(|line| format!("{}\n", format!("# {}", line).trim_end()))
+        .collect()
+}
+
+/// 小字段落，放在 `small` 特殊区块中（HTML 导出为 `<div class="small">`，LaTeX 导出为 `small` 环境），
+/// 用于脚注、参考文献条目、图表脚注等；内容为已转义的 Org 正文
+pub(crate) fn small_print(text: &str) -> String {
+    format!("#+BEGIN_small\n{}\n#+END_small\n", text.trim_end())
+}
+
+/// 行间公式 `\[…\]`；公式中的空行会结束所在的段落，一并去掉，以 `*` 开头的行缩进一格，不会成为标题
+pub(crate) fn math_block(latex: &str) -> String {
+    let lines: Vec<String> = latex
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| {
+            if line.starts_with('*') {
+                format!(" {}", line)
+            } else {
+                line.to_string()
+            }
+        })
+        .collect();
+    format!("\\[\n{}\n\\]\n", lines.join("\n"))
+}
+
+/// 图片 `[[file:src]]`，单独成段时导出为行内图片
+pub(crate) fn image(src: &str) -> String {
+    format!("[[file:{}]]\n", src.replace('[', "\\[").replace(']', "\\]"))
+}
+
+// ==================== 导出选项 ====================
+
+/// 每个输出文件开头的导出选项：只有 `_{…}`、`^{…}` 才是下标、上标，正文中的 `a_b`、`x^2` 保持原样
+pub(crate) fn org_document_head() -> String {
+    "#+OPTIONS: ^:{}\n\n".to_string()
+}
+
+/// 在正文之前加上导出选项，正文末尾只保留一个换行
+pub(crate) fn wrap_org_document(body: &str) -> String {
+    let mut org = org_document_head();
+    org.push_str(body.trim_end());
+    org.push('\n');
+    org
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 转义结果，零宽空格显示为 `|`
+    fn escaped(text: &str, line_start: bool) -> String {
+        escape_org(text, line_start).replace(ZERO_WIDTH_SPACE, "|")
+    }
+
+    #[test]
+    fn structure_at_the_start_of_a_line_is_broken_up() {
+        assert_eq!(escaped("* heading", true), "|*| heading");
+        assert_eq!(escaped("# comment", true), "|# comment");
+        assert_eq!(escaped("- item", true), "|- item");
+        assert_eq!(escaped("1. first", true), "|1. first");
+        assert_eq!(escaped("a) first", true), "|a) first");
+        assert_eq!(escaped("first\n# second", true), "first\n|# second");
+        // 不在行首时只处理标记字符
+        assert_eq!(escaped("# comment", false), "# comment");
+        assert_eq!(escaped("1. first", false), "1. first");
+        assert_eq!(escaped("1.5 times", true), "1.5 times");
+    }
+
+    #[test]
+    fn emphasis_markers_are_separated_only_at_word_boundaries() {
+        for marker in EMPHASIS_MARKERS.chars() {
+            assert_eq!(
+                escaped(&format!("a {m}b{m} c", m = marker), false),
+                format!("a |{m}b{m}| c", m = marker)
+            );
+        }
+        assert_eq!(escaped("snake_case_name", false), "snake_case_name");
+        assert_eq!(escaped("2*3=6 and a+b", false), "2*3=6 and a+b");
+        assert_eq!(escaped("(/usr/bin)", false), "(|/usr/bin)");
+    }
+
+    #[test]
+    fn links_footnotes_math_and_entities_are_separated() {
+        assert_eq!(escaped("see [[target]]", false), "see [|[target]]");
+        assert_eq!(escaped("note [fn:1]", false), "note [|fn:1]");
+        assert_eq!(
+            escaped("<<target>> {{{macro}}}", false),
+            "<|<target>> {|{|{macro}}}"
+        );
+        assert_eq!(escaped("costs $5", false), "costs $|5");
+        assert_eq!(escaped("\\alpha and a\\1", false), "\\|alpha and a\\1");
+        assert_eq!(escaped("x_{1}", false), "x_|{1}");
+    }
+}
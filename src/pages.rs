@@ -0,0 +1,183 @@
+//! 页码范围选择，如 `1-5,12,20-`；分页线上的页码标签模板
+
+use std::fmt;
+use std::str::FromStr;
+
+// ==================== 页码范围 ====================
+
+/// 以 1 开始的页码范围集合；`20-` 表示第 20 页到最后一页
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PageSelection {
+    spec: String,
+    ranges: Vec<(usize, Option<usize>)>,
+}
+
+impl PageSelection {
+    /// `page_idx` 从 0 开始
+    pub fn contains(&self, page_idx: usize) -> bool {
+        let page_num = page_idx + 1;
+        self.ranges
+            .iter()
+            .any(|&(start, end)| page_num >= start && end.is_none_or(|end| page_num <= end))
+    }
+
+    /// 返回第一个起始页超出 `page_count` 的范围
+    pub(crate) fn out_of_range(&self, page_count: usize) -> Option<usize> {
+        self.ranges
+            .iter()
+            .map(|&(start, end)| end.unwrap_or(start).max(start))
+            .find(|&last| last > page_count)
+    }
+}
+
+impl fmt::Display for PageSelection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.spec)
+    }
+}
+
+fn parse_page_number(text: &str) -> Result<usize, String> {
+    match text.trim().parse::<usize>() {
+        Ok(0) => Err("page numbers start at 1".to_string()),
+        Ok(n) => Ok(n),
+        Err(_) => Err(format!("invalid page number '{}'", text.trim())),
+    }
+}
+
+impl FromStr for PageSelection {
+    type Err = String;
+
+    fn from_str(spec: &str) -> Result<Self, Self::Err> {
+        let mut ranges = Vec::new();
+        for part in spec.split(',').map(str::trim) {
+            if part.is_empty() {
+                return Err(format!("empty range in '{}'", spec));
+            }
+            let range = match part.split_once('-') {
+                Some((start, "")) => (parse_page_number(start)?, None),
+                Some((start, end)) => {
+                    let (start, end) = (parse_page_number(start)?, parse_page_number(end)?);
+                    if end < start {
+                        return Err(format!("range '{}' ends before it starts", part));
+                    }
+                    (start, Some(end))
+                }
+                None => {
+                    let page = parse_page_number(part)?;
+                    (page, Some(page))
+                }
+            };
+            ranges.push(range);
+        }
+
+        Ok(PageSelection {
+            spec: spec.to_string(),
+            ranges,
+        })
+    }
+}
+
+// ==================== 页码标签 ====================
+
+/// 分页线上的页码文字，`{n}` 替换为页码，如 `Page {n}`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PageLabel {
+    template: String,
+}
+
+impl PageLabel {
+    /// `page_num` 从 1 开始
+    pub fn render(&self, page_num: usize) -> String {
+        self.template.replace("{n}", &page_num.to_string())
+    }
+
+    /// 连续几页共用的标签，`{n}` 替换为页码范围，如 `Page 14–17`
+    pub(crate) fn render_range(&self, first: usize, last: usize) -> String {
+        if first == last {
+            return self.render(first);
+        }
+        self.template.replace("{n}", &format!("{}–{}", first, last))
+    }
+}
+
+impl fmt::Display for PageLabel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.template)
+    }
+}
+
+impl FromStr for PageLabel {
+    type Err = String;
+
+    /// 模板中缺少 `{n}` 时每页的标签都会相同，直接报错
+    fn from_str(template: &str) -> Result<Self, Self::Err> {
+        if !template.contains("{n}") {
+            return Err(format!(
+                "page label '{}' must contain {{n}} for the page number",
+                template
+            ));
+        }
+        Ok(PageLabel {
+            template: template.to_string(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{bare_options, layout, text_block};
+    use crate::{ConvertError, ConvertOptions, convert_layout_to_markdown};
+    use serde_json::json;
+    use std::path::Path;
+
+    #[test]
+    fn page_selections_are_parsed_and_matched() {
+        let selection: PageSelection = "1-2, 5,8-".parse().unwrap();
+        let selected: Vec<usize> = (0..10).filter(|&idx| selection.contains(idx)).collect();
+        assert_eq!(selected, [0, 1, 4, 7, 8, 9]);
+        assert_eq!(selection.to_string(), "1-2, 5,8-");
+        assert_eq!(selection.out_of_range(8), None);
+        assert_eq!(selection.out_of_range(4), Some(5));
+
+        for (spec, error) in [
+            ("0", "page numbers start at 1"),
+            ("3-1", "range '3-1' ends before it starts"),
+            ("1,,2", "empty range in '1,,2'"),
+            ("a-3", "invalid page number 'a'"),
+        ] {
+            assert_eq!(spec.parse::<PageSelection>().unwrap_err(), error);
+        }
+    }
+
+    #[test]
+    fn only_selected_pages_are_converted_with_their_original_numbers() {
+        let layout = layout(json!({"pdf_info": (0..4)
+            .map(|idx| json!({"page_idx": idx, "page_size": [600, 800], "para_blocks": [
+                text_block("title", [50.0, 50.0, 550.0, 80.0], &format!("Chapter {}", idx + 1)),
+            ]}))
+            .collect::<Vec<_>>()}));
+        let convert = |pages: &str| {
+            let options = ConvertOptions {
+                pages: Some(pages.parse().unwrap()),
+                ..bare_options()
+            };
+            convert_layout_to_markdown(&layout, Path::new("."), &options)
+        };
+
+        let output = convert("2,4").unwrap();
+        let outline: Vec<(&str, usize)> = output
+            .outline
+            .iter()
+            .map(|entry| (entry.title.as_str(), entry.page_idx))
+            .collect();
+        assert_eq!(outline, [("Chapter 2", 2), ("Chapter 4", 4)]);
+        assert_eq!(output.stats.pages, 2);
+        assert!(!output.markdown.contains("Chapter 1"));
+
+        assert!(matches!(
+            convert("3-6"),
+            Err(ConvertError::PageOutOfRange { page_count: 4, .. })
+        ));
+    }
+}
@@ -0,0 +1,293 @@
+//! 引文：sub_type 为 quote 的文本块，以及开启 detect_quotes 时相对本栏页边距明显缩进的文本块。
+//! 原文中的硬换行（诗歌的分行）保留为引文中的各行
+
+use std::borrow::Cow;
+
+use crate::doc::Inline;
+use crate::order::{Column, column_of};
+use crate::rst;
+use crate::text::render_inlines;
+use crate::types::{Block, Line};
+use crate::{ConvertOptions, OutputFormat};
+
+// ==================== 识别 ====================
+
+/// 左边距与页边距相差不超过页宽的该比例时视为对齐
+const MARGIN_TOLERANCE: f64 = 0.01;
+
+/// 参与判断页边距的块：有 bbox、未标注 sub_type、未旋转的普通文本块
+fn is_body_text(block: &Block) -> bool {
+    block.block_type == "text"
+        && block.sub_type.is_none()
+        && block.rotation().is_none()
+        && !block.bbox.is_empty()
+}
+
+/// 一栏中最多文本块对齐的左边距；至少两个块对齐时才有页边距，否则返回 `None`。
+/// 对齐的块数相同时取靠左的
+fn modal_left(lefts: &[f64], tolerance: f64) -> Option<f64> {
+    let aligned = |left: f64| {
+        lefts
+            .iter()
+            .filter(|other| (**other - left).abs() <= tolerance)
+            .count()
+    };
+    let (left, count) = lefts.iter().map(|&left| (left, aligned(left))).fold(
+        None,
+        |best: Option<(f64, usize)>, (left, count)| match best {
+            Some((best_left, best_count))
+                if best_count > count || (best_count == count && best_left <= left) =>
+            {
+                best
+            }
+            _ => Some((left, count)),
+        },
+    )?;
+    (count >= 2).then_some(left)
+}
+
+/// 把左边距超出本栏页边距 `fraction` 倍页宽以上的普通文本块标为引文（sub_type 设为 `quote`）。
+/// 页边距按栏位分别取最多文本块对齐的左边距；一栏中没有两个对齐的文本块时不做判断。
+/// 没有可标记的块时原样借用
+pub(crate) fn mark_indented_quotes(
+    blocks: &[Block],
+    page_width: f64,
+    fraction: f64,
+) -> Cow<'_, [Block]> {
+    let tolerance = page_width * MARGIN_TOLERANCE;
+    let columns: Vec<Option<Column>> = blocks
+        .iter()
+        .map(|block| is_body_text(block).then(|| column_of(&block.bbox, page_width)))
+        .collect();
+    let margin = |column: Column| {
+        let lefts: Vec<f64> = blocks
+            .iter()
+            .zip(&columns)
+            .filter(|(_, other)| **other == Some(column))
+            .map(|(block, _)| block.bbox.left())
+            .collect();
+        modal_left(&lefts, tolerance)
+    };
+    let margins = [Column::Left, Column::Right, Column::Spanning].map(margin);
+    let margin_of = |column: Column| match column {
+        Column::Left => margins[0],
+        Column::Right => margins[1],
+        Column::Spanning => margins[2],
+    };
+
+    let quotes: Vec<bool> = blocks
+        .iter()
+        .zip(&columns)
+        .map(|(block, column)| {
+            column
+                .and_then(margin_of)
+                .is_some_and(|margin| block.bbox.left() - margin > page_width * fraction)
+        })
+        .collect();
+    if !quotes.contains(&true) {
+        return Cow::Borrowed(blocks);
+    }
+    Cow::Owned(
+        blocks
+            .iter()
+            .zip(quotes)
+            .map(|(block, quote)| {
+                let mut block = block.clone();
+                if quote {
+                    block.sub_type = Some("quote".to_string());
+                }
+                block
+            })
+            .collect(),
+    )
+}
+
+// ==================== 分行 ====================
+
+/// 引文按硬换行分成的各组行，每组合并为引文中的一行。
+///
+/// 行的右端比块内最长的行短一个行高以上（约两个字的宽度）时，视为在此处硬换行；
+/// 除最后一行外至少一半的行都这样提前结束时视为诗歌，每一行都单独成行。
+/// 行没有 bbox 时无法判断，整块为一行
+pub(crate) fn quote_line_groups(block: &Block) -> Vec<Vec<Line>> {
+    let lines: Vec<&Line> = block.lines.iter().flatten().collect();
+    if lines.len() < 2 || lines.iter().any(|line| line.bbox.is_empty()) {
+        return vec![lines.into_iter().cloned().collect()];
+    }
+    let right = lines
+        .iter()
+        .map(|line| line.bbox.right())
+        .fold(f64::MIN, f64::max);
+    let short: Vec<bool> = lines[..lines.len() - 1]
+        .iter()
+        .map(|line| right - line.bbox.right() > line.bbox.height())
+        .collect();
+    let verse = short.iter().filter(|short| **short).count() * 2 >= short.len();
+
+    let mut groups: Vec<Vec<Line>> = vec![Vec::new()];
+    for (i, line) in lines.iter().enumerate() {
+        if let Some(group) = groups.last_mut() {
+            group.push((*line).clone());
+        }
+        if short.get(i).is_some_and(|short| verse || *short) {
+            groups.push(Vec::new());
+        }
+    }
+    groups
+}
+
+// ==================== 渲染 ====================
+
+/// 引文：Markdown 为 `> ` 引用块，各行之间以行尾两个空格换行；HTML 为 `<blockquote>`，以 `<br>` 换行；
+/// 有多行时 LaTeX 为 `verse` 环境、AsciiDoc 为 `[verse]` 块、reStructuredText 为缩进的行块、
+/// Org 为 `#+BEGIN_VERSE` 块，只有一行时分别为 `quote` 环境、`____` 块、缩进的段落与 `#+BEGIN_QUOTE` 块；
+/// 纯文本与 JSON 每行一行
+pub(crate) fn render_quote(lines: &[Vec<Inline>], options: &ConvertOptions) -> String {
+    let lines: Vec<String> = lines
+        .iter()
+        .map(|line| render_inlines(line, options).trim().to_string())
+        .filter(|line| !line.is_empty())
+        .collect();
+    if lines.is_empty() {
+        return String::new();
+    }
+    let verse = lines.len() > 1;
+    match options.format {
+        OutputFormat::Markdown => {
+            let quoted: Vec<String> = lines.iter().map(|line| format!("> {}", line)).collect();
+            format!("{}\n\n", quoted.join("  \n"))
+        }
+        OutputFormat::Html => format!(
+            "<blockquote>\n<p>{}</p>\n</blockquote>\n\n",
+            lines.join("<br>\n")
+        ),
+        OutputFormat::Latex => {
+            let environment = if verse { "verse" } else { "quote" };
+            format!(
+                "\\begin{{{env}}}\n{}\n\\end{{{env}}}\n\n",
+                lines.join("\\\\\n"),
+                env = environment
+            )
+        }
+        OutputFormat::Asciidoc if verse => format!("[verse]\n____\n{}\n____\n\n", lines.join("\n")),
+        OutputFormat::Asciidoc => format!("____\n{}\n____\n\n", lines[0]),
+        OutputFormat::Rst => rst::block_quote(&lines),
+        OutputFormat::Org => {
+            let block = if verse { "VERSE" } else { "QUOTE" };
+            format!(
+                "#+BEGIN_{block}\n{}\n#+END_{block}\n\n",
+                lines.join("\n"),
+                block = block
+            )
+        }
+        OutputFormat::Text | OutputFormat::Json => format!("{}\n\n", lines.join("\n")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{TempDir, bare_options, markdown, text_block};
+    use crate::{Flavor, ImageOutput, InputFormat, LayoutJson, parse_layout};
+
+    const SONNET: &str = "> Shall I compare thee to a summer's day?  \n\
+        > Thou art more lovely and more temperate:  \n\
+        > Rough winds do shake the darling buds of May,  \n\
+        > And summer's lease hath all too short a date.\n\n";
+
+    fn poem() -> LayoutJson {
+        parse_layout(
+            include_str!("../tests/fixtures/poem.json"),
+            InputFormat::Auto,
+        )
+        .unwrap()
+    }
+
+    fn options(detect_quotes: Option<f64>) -> ConvertOptions {
+        ConvertOptions {
+            flavor: Flavor::Gfm,
+            detect_quotes,
+            ..bare_options()
+        }
+    }
+
+    #[test]
+    fn indented_blocks_are_quotes_only_when_detection_is_on() {
+        let layout = poem();
+        // sub_type 为 quote 的块总是引文，两端对齐的散文合为一行
+        let prose = "> Whereof one cannot speak, thereof one must be silent.\n\n";
+
+        let plain = markdown(&layout, &options(None));
+        assert!(!plain.contains(SONNET), "{}", plain);
+        assert!(plain.contains("summer's day? Thou art"), "{}", plain);
+        assert!(plain.ends_with(prose), "{}", plain);
+
+        let detected = markdown(&layout, &options(Some(0.05)));
+        assert!(detected.contains(SONNET), "{}", detected);
+        assert!(detected.ends_with(prose), "{}", detected);
+        assert!(detected.starts_with("The sonnet below is quoted in full."));
+
+        // 缩进不足该比例页宽时不是引文
+        assert_eq!(markdown(&layout, &options(Some(0.2))), plain);
+    }
+
+    #[test]
+    fn verse_keeps_its_lines_in_each_format() {
+        let layout = poem();
+        let dir = TempDir::new();
+        let latex = markdown(
+            &layout,
+            &ConvertOptions {
+                format: OutputFormat::Latex,
+                images: ImageOutput::Directory {
+                    dir: dir.path().to_path_buf(),
+                    link_prefix: "images".to_string(),
+                },
+                ..options(Some(0.05))
+            },
+        );
+        assert!(
+            latex.contains(
+                "\\begin{verse}\nShall I compare thee to a summer's day?\\\\\n\
+                 Thou art more lovely and more temperate:\\\\\n"
+            ),
+            "{}",
+            latex
+        );
+        assert!(
+            latex.contains("\\begin{quote}\nWhereof one cannot speak"),
+            "{}",
+            latex
+        );
+
+        let html = markdown(
+            &layout,
+            &ConvertOptions {
+                format: OutputFormat::Html,
+                ..options(Some(0.05))
+            },
+        );
+        assert!(
+            html.contains("<blockquote>\n<p>Shall I compare thee to a summer's day?<br>\n"),
+            "{}",
+            html
+        );
+    }
+
+    #[test]
+    fn a_column_without_aligned_blocks_has_no_margin() {
+        let blocks: Vec<Block> = [
+            text_block("text", [50.0, 100.0, 550.0, 120.0], "Body"),
+            text_block("text", [120.0, 140.0, 550.0, 160.0], "Indented"),
+        ]
+        .into_iter()
+        .map(|block| serde_json::from_value(block).unwrap())
+        .collect();
+        assert!(matches!(
+            mark_indented_quotes(&blocks, 600.0, 0.05),
+            Cow::Borrowed(_)
+        ));
+        assert_eq!(modal_left(&[50.0, 120.0, 50.5], 6.0), Some(50.0));
+        assert_eq!(modal_left(&[50.0, 120.0], 6.0), None);
+    }
+}
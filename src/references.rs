@@ -0,0 +1,354 @@
+//! 参考文献：识别参考文献一节，把 MinerU 合并在一起的条目拆开逐条输出，并为 DOI 和网址加上链接
+
+use crate::asciidoc::{escape_asciidoc, macro_text};
+use crate::css::style_attr;
+use crate::latex::{escape_latex, escape_url, small_print};
+use crate::links::{Piece, link_pieces};
+use crate::org::{self, escape_org};
+use crate::rst::{self, escape_rst};
+use crate::text::{
+    escape_html, escape_markdown, escape_text, extract_text_from_block, is_cjk, push_joined,
+};
+use crate::types::{Block, PageInfo};
+use crate::{ConvertOptions, Flavor, OutputFormat};
+
+// ==================== 识别 ====================
+
+/// 标题是否为参考文献一节，如 "References"、"7 Bibliography"、"参考文献"
+pub(crate) fn is_references_title(title: &str) -> bool {
+    let title = title.trim();
+    if title.chars().count() > 40 {
+        return false;
+    }
+    let lower = title.to_lowercase();
+    ["references", "bibliography", "参考文献"]
+        .iter()
+        .any(|word| lower.contains(word))
+}
+
+/// 页面结束时是否处在参考文献一节：由页内最后一个标题决定，没有标题时沿用 `inside`
+///
+/// 各页并行渲染前先按顺序求出每页开头的状态，使跨页的参考文献一节保持连续。
+pub(crate) fn ends_inside(page: &PageInfo, inside: bool, options: &ConvertOptions) -> bool {
+    page.para_blocks
+        .iter()
+        .rfind(|block| block.block_type == "title")
+        .map_or(inside, |title| {
+            is_references_title(&extract_text_from_block(title, options.dehyphenate))
+        })
+}
+
+// ==================== 拆分条目 ====================
+
+/// 开头是否为条目编号：`[12]`、`12.`、`12)`
+fn starts_with_marker(text: &str) -> bool {
+    if let Some(rest) = text.strip_prefix('[') {
+        let digits = rest.chars().take_while(char::is_ascii_digit).count();
+        return (1..=4).contains(&digits) && rest[digits..].starts_with(']');
+    }
+    let digits = text.chars().take_while(char::is_ascii_digit).count();
+    (1..=3).contains(&digits)
+        && text[digits..].starts_with(['.', ')'])
+        && text[digits + 1..].starts_with(char::is_whitespace)
+}
+
+/// 是否以出版年份结尾，如 `…, 2008.`、`(2019).`、`2020a.`
+fn ends_with_year(text: &str) -> bool {
+    let text = text.trim_end().trim_end_matches(['.', ')', ']', ';', ',']);
+    let text = text
+        .strip_suffix(|c: char| c.is_ascii_lowercase())
+        .unwrap_or(text);
+    let digits = text.chars().rev().take_while(char::is_ascii_digit).count();
+    digits == 4
+        && ["19", "20"]
+            .iter()
+            .any(|century| text[text.len() - 4..].starts_with(century))
+}
+
+/// 把一行在行中的 `[n]` 编号处切开；除第一段外每段都以编号开头
+fn split_at_markers(line: &str) -> Vec<&str> {
+    let mut pieces = Vec::new();
+    let mut start = 0;
+    for (i, _) in line.match_indices('[') {
+        if i > start && line[..i].ends_with(char::is_whitespace) && starts_with_marker(&line[i..]) {
+            pieces.push(line[start..i].trim_end());
+            start = i;
+        }
+    }
+    pieces.push(&line[start..]);
+    pieces
+}
+
+/// 块内的各条参考文献
+///
+/// 有编号时每个编号开始一条；没有编号时，上一条以年份结尾、下一行以大写字母或中日韩文字开头
+/// 才视为新的一条（作者-年份格式）。
+pub(crate) fn split_entries(block: &Block, dehyphenate: bool) -> Vec<String> {
+    let lines: Vec<String> = block
+        .lines
+        .iter()
+        .flatten()
+        .map(|line| {
+            line.spans
+                .iter()
+                .filter_map(|span| span.content.as_deref())
+                .collect::<String>()
+        })
+        .map(|line| line.trim().to_string())
+        .filter(|line| !line.is_empty())
+        .collect();
+    let numbered = lines.iter().any(|line| {
+        split_at_markers(line)
+            .iter()
+            .any(|piece| starts_with_marker(piece))
+    });
+
+    let mut entries: Vec<String> = Vec::new();
+    for line in &lines {
+        for (i, piece) in split_at_markers(line).into_iter().enumerate() {
+            let new_entry = match entries.last() {
+                None => true,
+                Some(_) if i > 0 => true,
+                Some(_) if numbered => starts_with_marker(piece),
+                Some(last) => {
+                    ends_with_year(last)
+                        && piece.starts_with(|c: char| c.is_uppercase() || is_cjk(c))
+                }
+            };
+            match entries.last_mut() {
+                Some(entry) if !new_entry => push_joined(entry, piece, dehyphenate),
+                _ => entries.push(piece.to_string()),
+            }
+        }
+    }
+    entries
+}
+
+// ==================== 链接 ====================
+
+fn markdown_entry(entry: &str, options: &ConvertOptions) -> String {
+    let mut out = String::new();
+    for piece in link_pieces(entry) {
+        match piece {
+            Piece::Text(text) => out.push_str(&escape_text(text, out.is_empty(), options)),
+            // 网址用自动链接，DOI 保留编号作为链接文字
+            Piece::Link(text, href) if text == href => out.push_str(&format!("<{}>", href)),
+            Piece::Link(text, href) => out.push_str(&format!(
+                "[{}](<{}>)",
+                escape_markdown(text, false)
+                    .replace('[', "\\[")
+                    .replace(']', "\\]"),
+                href
+            )),
+        }
+    }
+    out
+}
+
+/// 网址用 `\url`，DOI 用 `\href` 链接到 doi.org、保留编号作为链接文字；需要 hyperref 宏包
+fn latex_entry(entry: &str) -> String {
+    link_pieces(entry)
+        .into_iter()
+        .map(|piece| match piece {
+            Piece::Text(text) => escape_latex(text),
+            Piece::Link(text, href) if text == href => format!("\\url{{{}}}", escape_url(&href)),
+            Piece::Link(text, href) => {
+                format!("\\href{{{}}}{{{}}}", escape_url(&href), escape_latex(text))
+            }
+        })
+        .collect()
+}
+
+/// 链接用 `link:++…++[]` 宏，网址中的 `_`、`*` 等不会被当作格式标记；DOI 保留编号作为链接文字
+fn asciidoc_entry(entry: &str) -> String {
+    link_pieces(entry)
+        .into_iter()
+        .map(|piece| match piece {
+            Piece::Text(text) => escape_asciidoc(text, false),
+            Piece::Link(text, href) if text == href => format!("link:++{}++[]", href),
+            Piece::Link(text, href) => format!("link:++{}++[{}]", href, macro_text(text)),
+        })
+        .collect()
+}
+
+/// 链接为匿名超链接，紧贴文字时以转义空格隔开；条目开头的编号（`1. `）转义，不会成为嵌套的列表
+fn rst_entry(entry: &str) -> String {
+    let mut out = String::new();
+    for piece in link_pieces(entry) {
+        match piece {
+            Piece::Text(text) => {
+                let text = escape_rst(text, out.is_empty());
+                rst::push_text(&mut out, &text);
+            }
+            Piece::Link(text, href) => rst::push_markup(&mut out, &rst::hyperlink(text, &href)),
+        }
+    }
+    out
+}
+
+/// 链接为 `[[href][text]]`；条目开头的 `[`、文字中的 ` :: ` 转义，不会成为复选框或描述列表
+fn org_entry(entry: &str) -> String {
+    let mut out = String::new();
+    for piece in link_pieces(entry) {
+        match piece {
+            Piece::Text(text) if out.is_empty() => out.push_str(&org::item_text(text)),
+            Piece::Text(text) => out.push_str(&escape_org(text, false)),
+            Piece::Link(text, href) => out.push_str(&org::link(text, &href)),
+        }
+    }
+    out
+}
+
+fn html_entry(entry: &str) -> String {
+    link_pieces(entry)
+        .into_iter()
+        .map(|piece| match piece {
+            Piece::Text(text) => escape_html(text),
+            Piece::Link(text, href) => format!(
+                "<a href=\"{}\">{}</a>",
+                escape_html(&href).replace('"', "&quot;"),
+                escape_html(text)
+            ),
+        })
+        .collect()
+}
+
+// ==================== 渲染 ====================
+
+/// 参考文献块逐条输出：Styled 风格、HTML 与 LaTeX 为逐条的小字段落，GFM、AsciiDoc、reStructuredText 与 Org 为列表，
+/// 纯文本每条一行
+pub(crate) fn render_references(entries: &[String], options: &ConvertOptions) -> String {
+    if entries.is_empty() {
+        return String::new();
+    }
+    let mut out: String = match (options.format, options.flavor) {
+        (OutputFormat::Text | OutputFormat::Json, _) => {
+            entries.iter().map(|entry| format!("{}\n", entry)).collect()
+        }
+        (OutputFormat::Latex, _) => entries
+            .iter()
+            .map(|entry| small_print(&latex_entry(entry)))
+            .collect(),
+        (OutputFormat::Asciidoc, _) => entries
+            .iter()
+            .map(|entry| format!("* {}\n", asciidoc_entry(entry)))
+            .collect(),
+        (OutputFormat::Rst, _) => entries
+            .iter()
+            .map(|entry| format!("- {}\n", rst_entry(entry)))
+            .collect(),
+        (OutputFormat::Org, _) => entries
+            .iter()
+            .map(|entry| format!("- {}\n", org_entry(entry)))
+            .collect(),
+        (_, Flavor::Styled) => entries
+            .iter()
+            .map(|entry| {
+                // 与脚注等小字段落的样式一致
+                format!(
+                    "<p{}>{}</p>\n",
+                    style_attr("mineru-small-print", options),
+                    html_entry(entry)
+                )
+            })
+            .collect(),
+        (_, Flavor::Gfm | Flavor::Obsidian) => entries
+            .iter()
+            .map(|entry| format!("- {}\n", markdown_entry(entry, options)))
+            .collect(),
+    };
+    out.push('\n');
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{bare_options, layout, lines_block, markdown, page_layout, text_block};
+    use serde_json::json;
+
+    fn entries(lines: &[&str]) -> Vec<String> {
+        let layout = page_layout(json!([lines_block("ref_text", lines)]));
+        split_entries(&layout.pdf_info[0].para_blocks[0], true)
+    }
+
+    #[test]
+    fn reference_titles_are_recognized() {
+        assert!(is_references_title("References"));
+        assert!(is_references_title("7 Bibliography"));
+        assert!(is_references_title("参考文献"));
+        assert!(!is_references_title("Results"));
+        assert!(!is_references_title(
+            "A survey of how papers cite their references in practice"
+        ));
+    }
+
+    #[test]
+    fn numbered_and_author_year_entries_are_split() {
+        assert_eq!(
+            entries(&[
+                "[1] A. Author. A title that wraps",
+                "onto a second line. 2008. [2] B. Writer. Inline.",
+                "pp. 12-30 [3] C. Third.",
+                "4) D. Fourth.",
+            ]),
+            [
+                "[1] A. Author. A title that wraps onto a second line. 2008.",
+                "[2] B. Writer. Inline. pp. 12-30",
+                "[3] C. Third.",
+                "4) D. Fourth.",
+            ]
+        );
+        assert_eq!(
+            entries(&[
+                "Lamport, L. Time, clocks, and the ordering of",
+                "events. CACM, 1978.",
+                "Ongaro, D. In search of an understandable consensus algorithm. 2014a.",
+                "Zhang, Q. A study. ",
+            ]),
+            [
+                "Lamport, L. Time, clocks, and the ordering of events. CACM, 1978.",
+                "Ongaro, D. In search of an understandable consensus algorithm. 2014a.",
+                "Zhang, Q. A study.",
+            ]
+        );
+    }
+
+    #[test]
+    fn reference_sections_continue_across_pages_until_the_next_title() {
+        let layout = layout(json!({"pdf_info": [
+            {"page_idx": 0, "page_size": [600, 800], "para_blocks": [
+                text_block("title", [50.0, 50.0, 550.0, 80.0], "References"),
+                text_block("text", [50.0, 100.0, 550.0, 140.0],
+                    "[1] A. Author. On *things*. doi:10.1000/xyz [2] B. Writer. https://example.org/paper"),
+            ]},
+            {"page_idx": 1, "page_size": [600, 800], "para_blocks": [
+                text_block("text", [50.0, 100.0, 550.0, 140.0], "[3] C. Third. 2020."),
+                text_block("title", [50.0, 200.0, 550.0, 230.0], "Appendix"),
+                text_block("text", [50.0, 250.0, 550.0, 290.0], "[4] is cited here."),
+            ]}
+        ]}));
+        let options = ConvertOptions {
+            flavor: Flavor::Gfm,
+            ..bare_options()
+        };
+        let output = markdown(&layout, &options);
+        assert!(
+            output.contains(
+                "- [1] A. Author. On \\*things\\*. doi:[10.1000/xyz](<https://doi.org/10.1000/xyz>)\n\
+                 - [2] B. Writer. <https://example.org/paper>\n"
+            ),
+            "{}",
+            output
+        );
+        assert!(output.contains("- [3] C. Third. 2020.\n"), "{}", output);
+        // 下一个标题结束参考文献一节
+        assert!(output.contains("\n[4] is cited here.\n"), "{}", output);
+
+        let unformatted = ConvertOptions {
+            format_references: false,
+            ..options
+        };
+        assert!(!markdown(&layout, &unformatted).contains("- [1]"));
+    }
+}
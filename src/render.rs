@@ -0,0 +1,2093 @@
+//! 写出：把文档节点（[`DocNode`]）转换为 Markdown / HTML / LaTeX / AsciiDoc / reStructuredText / 纯文本片段
+
+use crate::analyze::{
+    RenderState, analyze_equation, analyze_image, analyze_table, analyze_text, heading_entry,
+};
+use crate::asciidoc::{
+    self, block_image, block_title, delimited_block, escape_asciidoc, section_marker,
+};
+use crate::css::style_attr;
+use crate::doc::{
+    BlockSource, Caption, CaptionKind, DocNode, ImageSize, ImageSource, Inline, PageNode,
+    PageNodes, Panel, TableBody,
+};
+use crate::error::{ConvertError, Warning};
+use crate::figures::FigureKind;
+use crate::footnote::{
+    collect_page_footnotes, link_footnote_markers, render_asciidoc_footnotes,
+    render_markdown_footnotes, render_org_footnotes, render_rst_footnotes,
+};
+use crate::image::inline_svg_markup;
+use crate::index::render_index;
+use crate::json;
+use crate::latex::{
+    comment, escape_latex, float, include_graphics, missing_graphic, sectioning_command,
+    small_print,
+};
+use crate::list::{count_items, render_list};
+use crate::org::{self, escape_org};
+use crate::quote::render_quote;
+use crate::references::render_references;
+use crate::rst::{self, escape_rst};
+use crate::table::{
+    html_table_to_asciidoc, html_table_to_latex, html_table_to_markdown, html_table_to_org,
+};
+use crate::text::{
+    alt_text, display_math, emphasize, escape_html, escape_text, github_anchors, link_markup,
+    markdown_image, obsidian_image, render_inlines, strong,
+};
+use crate::types::{Block, TocEntry};
+use crate::{
+    ConvertOptions, Divider, FigureStyle, Flavor, FootnoteStyle, OutputFormat, RenderedPage,
+    TableFormat,
+};
+
+// ==================== 默认渲染 ====================
+
+// [`crate::BlockRenderer`] 的默认实现：分析一个块后立即写出
+
+pub(crate) fn render_title(
+    block: &Block,
+    state: &mut RenderState,
+    options: &ConvertOptions,
+    page_idx: usize,
+) -> (String, Option<TocEntry>) {
+    match heading_entry(block, state, options, page_idx) {
+        Some((entry, inlines)) => (
+            write_heading(
+                entry.level,
+                &entry.title,
+                &inlines,
+                &entry.anchor_id,
+                options,
+            ),
+            Some(entry),
+        ),
+        None => (String::new(), None),
+    }
+}
+
+pub(crate) fn render_text_block(
+    block: &Block,
+    state: &mut RenderState,
+    options: &ConvertOptions,
+    page_idx: usize,
+) -> String {
+    let node = analyze_text(block, state, options);
+    write_analyzed(node, state, options, page_idx)
+}
+
+pub(crate) fn render_image(
+    block: &Block,
+    state: &mut RenderState,
+    options: &ConvertOptions,
+    page_idx: usize,
+) -> Result<String, ConvertError> {
+    let node = analyze_image(block, state, options, page_idx)?;
+    Ok(write_analyzed(node, state, options, page_idx))
+}
+
+pub(crate) fn render_table(
+    block: &Block,
+    state: &mut RenderState,
+    options: &ConvertOptions,
+    page_idx: usize,
+) -> Result<String, ConvertError> {
+    let node = analyze_table(block, state, options, page_idx)?;
+    Ok(write_analyzed(node, state, options, page_idx))
+}
+
+pub(crate) fn render_interline_equation(
+    block: &Block,
+    state: &mut RenderState,
+    options: &ConvertOptions,
+    page_idx: usize,
+) -> Result<String, ConvertError> {
+    let node = analyze_equation(block, state, options, page_idx)?;
+    Ok(write_analyzed(node, state, options, page_idx))
+}
+
+fn write_analyzed(
+    node: Option<DocNode>,
+    state: &mut RenderState,
+    options: &ConvertOptions,
+    page_idx: usize,
+) -> String {
+    node.map(|node| write_node(&node, None, page_idx, options, &mut state.warnings).0)
+        .unwrap_or_default()
+}
+
+// ==================== 节点写出 ====================
+
+/// 图片缺失或超出大小上限而未输出时，在原位置输出的占位提示
+fn missing_image_placeholder(note: &str, options: &ConvertOptions) -> String {
+    match options.flavor {
+        Flavor::Styled => format!(
+            "<div{}>{}</div>",
+            style_attr("mineru-missing-image", options),
+            escape_html(note)
+        ),
+        Flavor::Gfm | Flavor::Obsidian => emphasize(note),
+    }
+}
+
+/// AsciiDoc 输出中代替缺失图片的提示段落
+fn missing_asciidoc_image(note: &str) -> String {
+    format!("NOTE: {}\n", escape_asciidoc(note, false))
+}
+
+/// reStructuredText 输出中代替缺失图片的提示；没有提示文字时为空
+fn missing_rst_image(note: &str) -> String {
+    if note.is_empty() {
+        return String::new();
+    }
+    rst::directive("note", "", &[], &escape_rst(note, true))
+}
+
+/// Org 输出中代替缺失图片的斜体提示段落；没有提示文字时为空
+fn missing_org_image(note: &str) -> String {
+    if note.trim().is_empty() {
+        return String::new();
+    }
+    format!("{}\n", org::italic(note))
+}
+
+/// Markdown 风格中的图片：Obsidian 以 `![[文件名]]` 嵌入，GFM 为 `![alt](src)`
+fn flavor_image(alt: &str, src: &str, flavor: Flavor) -> String {
+    match flavor {
+        Flavor::Obsidian => obsidian_image(alt, src),
+        Flavor::Styled | Flavor::Gfm => markdown_image(alt, src),
+    }
+}
+
+/// Styled 风格中的 `<img>`；开启 inline_svg 时内联的 SVG 直接输出为 `<svg>` 标记。
+/// 已知尺寸时带上 `width` / `height`，浏览器在图片解码前即可留出位置；图片一律延迟加载、异步解码，
+/// 样式为 `class` 类（如 `mineru-figure-image`）的样式
+fn image_tag(
+    src: &str,
+    size: Option<ImageSize>,
+    alt: &str,
+    class: &str,
+    options: &ConvertOptions,
+) -> String {
+    if options.inline_svg
+        && let Some(markup) = inline_svg_markup(src)
+    {
+        return markup;
+    }
+    let size_attrs = size
+        .map(|size| format!(" width=\"{}\" height=\"{}\"", size.width, size.height))
+        .unwrap_or_default();
+    format!(
+        "<img src=\"{}\" alt=\"{}\"{} loading=\"lazy\" decoding=\"async\"{} />",
+        src,
+        escape_html(alt).replace('"', "&quot;"),
+        size_attrs,
+        style_attr(class, options)
+    )
+}
+
+/// 图片的替代文字：有说明文字（图注、表注或公式的 LaTeX）时使用其单行形式，
+/// 否则为 "figure on page N" 之类的描述
+fn figure_alt(text: Option<&str>, kind: &str, page_idx: usize) -> String {
+    match text.map(alt_text).filter(|alt| !alt.is_empty()) {
+        Some(alt) => alt,
+        None => format!("{} on page {}", kind, page_idx + 1),
+    }
+}
+
+/// 写出一个节点，标题节点同时返回目录条目；`source` 为节点来自的块，只用于 JSON 输出。
+/// 页眉、页脚、页面脚注与分页线属于整页，由 [`write_page`] 写出
+pub(crate) fn write_node(
+    node: &DocNode,
+    source: Option<&BlockSource>,
+    page_idx: usize,
+    options: &ConvertOptions,
+    warnings: &mut Vec<Warning>,
+) -> (String, Option<TocEntry>) {
+    let toc_entry = match node {
+        DocNode::Heading {
+            level,
+            text,
+            anchor,
+            ..
+        } => Some(TocEntry {
+            title: text.clone(),
+            page_idx: page_idx + 1,
+            anchor_id: anchor.clone(),
+            level: *level,
+            printed_page: None,
+        }),
+        DocNode::Rendered { toc_entry, .. } => toc_entry.clone(),
+        _ => None,
+    };
+    if options.format == OutputFormat::Json {
+        return (json::write_node(node, source, page_idx, options), toc_entry);
+    }
+
+    let html = match node {
+        DocNode::Heading {
+            level,
+            text,
+            anchor,
+            inlines,
+        } => write_heading(*level, text, inlines, anchor, options),
+        DocNode::Paragraph { inlines, rotation } => write_paragraph(inlines, *rotation, options),
+        DocNode::Code {
+            language,
+            caption,
+            code,
+        } => write_code(code, language.as_deref(), caption.as_deref(), options),
+        DocNode::List { items } => render_list(items, options),
+        DocNode::Figure {
+            image,
+            panels,
+            captions,
+            anchor,
+            rotation,
+        } => write_figure(
+            image.as_ref(),
+            panels,
+            captions,
+            anchor.as_deref(),
+            *rotation,
+            options,
+            page_idx,
+        ),
+        DocNode::Table {
+            body,
+            captions,
+            anchor,
+            rotation,
+            csv,
+        } => {
+            let mut table = write_table(
+                body.as_ref(),
+                captions,
+                anchor.as_deref(),
+                *rotation,
+                options,
+                page_idx,
+                warnings,
+            );
+            if let Some(csv) = csv {
+                table.push_str(&csv_link(csv, options));
+            }
+            table
+        }
+        DocNode::Equation { latex, image } => {
+            write_equation(latex.as_deref(), image.as_ref(), options, page_idx)
+        }
+        DocNode::References { entries } => render_references(entries, options),
+        DocNode::Index { lines } => render_index(lines, options),
+        DocNode::Quote { lines } => render_quote(lines, options),
+        DocNode::SmallPrint { text } => write_small_print(text, options),
+        DocNode::Rendered { content, .. } => content.clone(),
+        DocNode::HeaderNote { .. }
+        | DocNode::FooterNote { .. }
+        | DocNode::FootNote { .. }
+        | DocNode::PageBreak { .. } => String::new(),
+    };
+    (html, toc_entry)
+}
+
+/// 使用原生 Markdown 标题 + HTML 锚点作为隐藏导航元素；层级 0 为文档标题 `#`。
+/// Obsidian 的目录直接链接到标题文字，不需要锚点。
+/// 标题含有行内公式（`inlines` 不为空）时按段落的写法输出其中的文字与公式
+fn write_heading(
+    level: usize,
+    text: &str,
+    inlines: &[Inline],
+    anchor_id: &str,
+    options: &ConvertOptions,
+) -> String {
+    let rendered =
+        (!inlines.is_empty()).then(|| render_inlines(inlines, options).trim().to_string());
+    let title = |escape: &dyn Fn(&str) -> String| match &rendered {
+        Some(rendered) => rendered.clone(),
+        None => escape(text),
+    };
+    match options.format {
+        OutputFormat::Markdown => {
+            let heading_prefix = "#".repeat(level + 1);
+            let text = title(&|text| escape_text(text, false, options));
+            match options.flavor {
+                Flavor::Obsidian => format!("{} {}\n\n", heading_prefix, text),
+                Flavor::Styled | Flavor::Gfm if github_anchors(options) => {
+                    format!("{} {}\n\n", heading_prefix, text)
+                }
+                Flavor::Styled | Flavor::Gfm => format!(
+                    "<a id=\"{}\"></a>\n{} {}\n\n",
+                    anchor_id, heading_prefix, text
+                ),
+            }
+        }
+        OutputFormat::Html => format!(
+            "<h{level} id=\"{}\">{}</h{level}>\n\n",
+            anchor_id,
+            title(&escape_html),
+            level = level + 1
+        ),
+        OutputFormat::Latex => format!(
+            "\\{}{{{}}}\n\n",
+            sectioning_command(level),
+            title(&escape_latex)
+        ),
+        OutputFormat::Asciidoc => format!(
+            "[[{}]]\n{} {}\n\n",
+            anchor_id,
+            section_marker(level),
+            title(&|text| escape_asciidoc(text, false))
+        ),
+        OutputFormat::Rst => format!(
+            "{}{}\n",
+            rst::label(anchor_id),
+            match &rendered {
+                Some(rendered) => rst::section_title_markup(level, rendered),
+                None => rst::section_title(level, text),
+            }
+        ),
+        OutputFormat::Org => format!(
+            "{}{}\n",
+            match &rendered {
+                Some(rendered) => org::headline_markup(level, rendered),
+                None => org::headline(level, text),
+            },
+            org::custom_id(anchor_id)
+        ),
+        OutputFormat::Text | OutputFormat::Json if options.text_title_levels => {
+            format!("[{}] {}\n\n", level, title(&str::to_string))
+        }
+        OutputFormat::Text | OutputFormat::Json => format!("{}\n\n", title(&str::to_string)),
+    }
+}
+
+/// 旋转的段落在 Styled 风格中放进带 `data-rotation` 属性的 `<div>`
+fn write_paragraph(inlines: &[Inline], rotation: Option<u32>, options: &ConvertOptions) -> String {
+    let text = render_inlines(inlines, options);
+    let text = text.trim();
+    if text.is_empty() {
+        return String::new();
+    }
+    let html = match options.format {
+        OutputFormat::Markdown
+        | OutputFormat::Text
+        | OutputFormat::Latex
+        | OutputFormat::Json
+        | OutputFormat::Asciidoc
+        | OutputFormat::Rst
+        | OutputFormat::Org => format!("{}\n\n", text),
+        OutputFormat::Html => format!("<p>{}</p>\n\n", text),
+    };
+    match (rotation, options.flavor) {
+        (Some(_), Flavor::Styled) if options.format != OutputFormat::Text => {
+            format!("<div{}>\n\n{}</div>\n\n", rotation_attr(rotation), html)
+        }
+        _ => html,
+    }
+}
+
+fn write_code(
+    code: &str,
+    language: Option<&str>,
+    caption: Option<&str>,
+    options: &ConvertOptions,
+) -> String {
+    let lang = language.unwrap_or("");
+
+    if options.format == OutputFormat::Text {
+        return format!("{}\n\n", code);
+    }
+    if options.format == OutputFormat::Html {
+        let mut html = String::new();
+        if let Some(caption) = caption {
+            html.push_str(&format!("<p><em>{}</em></p>\n", escape_html(caption)));
+        }
+        let class = if lang.is_empty() {
+            String::new()
+        } else {
+            format!(" class=\"language-{}\"", escape_html(lang))
+        };
+        html.push_str(&format!(
+            "<pre><code{}>{}\n</code></pre>\n\n",
+            class,
+            escape_html(code)
+        ));
+        return html;
+    }
+    if options.format == OutputFormat::Latex {
+        let mut latex = String::new();
+        if let Some(caption) = caption {
+            latex.push_str(&format!("\\emph{{{}}}\n\n", escape_latex(caption)));
+        }
+        latex.push_str(&format!(
+            "\\begin{{verbatim}}\n{}\n\\end{{verbatim}}\n\n",
+            code
+        ));
+        return latex;
+    }
+    if options.format == OutputFormat::Asciidoc {
+        let mut asciidoc = caption.map(block_title).unwrap_or_default();
+        if !lang.is_empty() {
+            asciidoc.push_str(&format!("[source,{}]\n", lang));
+        }
+        asciidoc.push_str(&delimited_block('-', code));
+        asciidoc.push('\n');
+        return asciidoc;
+    }
+    if options.format == OutputFormat::Rst {
+        let mut rst = caption.map(rst_rubric).unwrap_or_default();
+        rst.push_str(&rst::directive("code", lang, &[], code));
+        rst.push('\n');
+        return rst;
+    }
+    // 标明语言的代码为 `#+BEGIN_SRC`，其余为 `#+BEGIN_EXAMPLE`
+    if options.format == OutputFormat::Org {
+        let mut org = caption
+            .map(|caption| org::keyword("CAPTION", &escape_org(caption, false)))
+            .unwrap_or_default();
+        org.push_str(&if lang.is_empty() {
+            org::block("EXAMPLE", "", code)
+        } else {
+            org::block("SRC", lang, code)
+        });
+        org.push('\n');
+        return org;
+    }
+
+    // 围栏比代码中最长的连续反引号多一个，至少三个
+    let longest_run = code.split(|c| c != '`').map(str::len).max().unwrap_or(0);
+    let fence = "`".repeat((longest_run + 1).max(3));
+
+    let mut md = String::new();
+    if let Some(caption) = caption {
+        md.push_str(&format!("{}\n\n", emphasize(caption)));
+    }
+    md.push_str(&format!("{}{}\n{}\n{}\n\n", fence, lang, code, fence));
+    md
+}
+
+/// 编号图表的锚点：除 Obsidian 外放在图表之前；Obsidian 风格改为在编号的说明文字末尾加块标识
+fn anchor_html(anchor: Option<&str>, options: &ConvertOptions) -> String {
+    match anchor {
+        Some(anchor) if options.flavor != Flavor::Obsidian => {
+            format!("<a id=\"{}\"></a>\n", anchor)
+        }
+        _ => String::new(),
+    }
+}
+
+/// Obsidian 风格中编号的图注、表注末尾的块标识（`^fig-N`），供图表目录链接
+fn block_id<'a>(anchor: Option<&'a str>, options: &ConvertOptions) -> Option<&'a str> {
+    anchor.filter(|_| options.flavor == Flavor::Obsidian)
+}
+
+/// Markdown 风格中的说明文字段落，编号的说明文字后接块标识
+fn caption_markdown(text: &str, block_id: Option<&str>, style: fn(&str) -> String) -> String {
+    match block_id {
+        Some(block_id) => format!("{} ^{}\n\n", style(text), block_id),
+        None => format!("{}\n\n", style(text)),
+    }
+}
+
+/// 纯文本输出中代替图表的一行，如 `[figure: Figure 1. Overview]`；未开启 text_placeholders 时跳过
+fn text_placeholder(label: &str, caption: Option<&Caption>, options: &ConvertOptions) -> String {
+    if !options.text_placeholders {
+        return String::new();
+    }
+    match caption {
+        Some(caption) => format!("[{}: {}]\n\n", label, caption.text),
+        None => format!("[{}]\n\n", label),
+    }
+}
+
+/// 旋转块在 HTML 中以 `data-rotation` 属性标注
+fn rotation_attr(rotation: Option<u32>) -> String {
+    rotation
+        .map(|degrees| format!(" data-rotation=\"{}\"", degrees))
+        .unwrap_or_default()
+}
+
+/// 没有图片时（图片缺失或超出大小上限）的占位提示文字
+fn unavailable_note(image: Option<&ImageSource>) -> &str {
+    match image {
+        Some(ImageSource::Unavailable(note)) => note,
+        Some(ImageSource::Src { .. }) | None => "",
+    }
+}
+
+/// 编号、用作块标题与替代文字的说明文字：第一个图注或表注
+fn numbered_caption(captions: &[Caption]) -> Option<usize> {
+    captions
+        .iter()
+        .position(|caption| caption.kind == CaptionKind::Caption)
+}
+
+/// LaTeX 的 `\caption` 与 AsciiDoc 的块标题只有一个：各图注、表注（如续行）以空格连成一行
+fn joined_caption(captions: &[Caption]) -> Option<String> {
+    let texts: Vec<&str> = captions
+        .iter()
+        .filter(|caption| caption.kind == CaptionKind::Caption)
+        .map(|caption| caption.text.as_str())
+        .collect();
+    (!texts.is_empty()).then(|| texts.join(" "))
+}
+
+/// 图片、表格脚注的文字，按出现顺序
+fn footnote_texts(captions: &[Caption]) -> Vec<&str> {
+    captions
+        .iter()
+        .filter(|caption| caption.kind == CaptionKind::Footnote)
+        .map(|caption| caption.text.as_str())
+        .collect()
+}
+
+/// AsciiDoc 中的图片、表格脚注，以小字段落放在图表之前（`above`）或之后
+fn asciidoc_footnotes(captions: &[Caption], above: bool) -> String {
+    captions
+        .iter()
+        .filter(|caption| caption.kind == CaptionKind::Footnote && caption.above == above)
+        .map(|caption| asciidoc::small_print(&escape_asciidoc(&caption.text, true)) + "\n")
+        .collect()
+}
+
+/// reStructuredText 中的图片、表格脚注，以小字容器放在图表之前（`above`）或之后
+fn rst_footnotes(captions: &[Caption], above: bool) -> String {
+    captions
+        .iter()
+        .filter(|caption| caption.kind == CaptionKind::Footnote && caption.above == above)
+        .map(|caption| rst::small_print(&escape_rst(&caption.text, true)) + "\n")
+        .collect()
+}
+
+/// reStructuredText 中不属于图片指令的说明文字，如表注与代码块的标题
+fn rst_rubric(caption: &str) -> String {
+    format!(
+        "{}\n",
+        rst::directive("rubric", &escape_rst(caption, false), &[], "")
+    )
+}
+
+/// Org 中的图片、表格脚注，以小字区块放在图表之前（`above`）或之后
+fn org_footnotes(captions: &[Caption], above: bool) -> String {
+    captions
+        .iter()
+        .filter(|caption| caption.kind == CaptionKind::Footnote && caption.above == above)
+        .map(|caption| org::small_print(&escape_org(&caption.text, true)) + "\n")
+        .collect()
+}
+
+/// Org 中的图表：说明文字连成一行作为 `#+CAPTION:`，锚点为 `#+NAME:`，图表脚注附在之前或之后；
+/// 没有可写出的 `body` 时说明文字与缺失提示 `missing` 各为一个段落
+fn org_figure(
+    captions: &[Caption],
+    anchor: Option<&str>,
+    body: Option<&str>,
+    missing: &str,
+) -> String {
+    let mut org = org_footnotes(captions, true);
+    let caption = caption_line(captions);
+    match body {
+        Some(body) => {
+            if let Some(caption) = caption {
+                org.push_str(&org::keyword("CAPTION", &escape_org(&caption, false)));
+            }
+            if let Some(anchor) = anchor {
+                org.push_str(&org::keyword("NAME", anchor));
+            }
+            org.push_str(body);
+            org.push('\n');
+        }
+        None => {
+            if let Some(caption) = caption {
+                org.push_str(&format!("{}\n\n", escape_org(&caption, true)));
+            }
+            let missing = missing_org_image(missing);
+            if !missing.is_empty() {
+                org.push_str(&missing);
+                org.push('\n');
+            }
+        }
+    }
+    org.push_str(&org_footnotes(captions, false));
+    org
+}
+
+/// GFM / Obsidian 风格中图表之前（`above`）或之后的说明文字，各为一个斜体段落；编号的说明文字后接块标识
+fn markdown_captions(
+    captions: &[Caption],
+    numbered: Option<usize>,
+    anchor: Option<&str>,
+    above: bool,
+    options: &ConvertOptions,
+) -> String {
+    captions
+        .iter()
+        .enumerate()
+        .filter(|(_, caption)| caption.above == above)
+        .map(|(i, caption)| {
+            let block_id = block_id(anchor, options).filter(|_| numbered == Some(i));
+            caption_markdown(&caption.text, block_id, emphasize)
+        })
+        .collect()
+}
+
+/// Markdown 输出是否使用 Pandoc 写法的图表
+fn pandoc_figures(options: &ConvertOptions) -> bool {
+    options.format == OutputFormat::Markdown && options.figure_style == FigureStyle::Pandoc
+}
+
+/// 图注、表注连成的一行，空白与换行合并为一个空格；与替代文字不同，不截断
+fn caption_line(captions: &[Caption]) -> Option<String> {
+    joined_caption(captions).map(|caption| caption.split_whitespace().collect::<Vec<_>>().join(" "))
+}
+
+/// Pandoc 写法中图表之前（`above`）或之后的图表脚注，各为一个斜体段落
+fn pandoc_footnotes(captions: &[Caption], above: bool) -> String {
+    captions
+        .iter()
+        .filter(|caption| caption.kind == CaptionKind::Footnote && caption.above == above)
+        .map(|caption| format!("{}\n\n", emphasize(&caption.text)))
+        .collect()
+}
+
+/// Pandoc 的 `:::` 块：锚点为标识，说明文字连成一行放进 `caption` 属性
+fn pandoc_div(anchor: Option<&str>, class: &str, captions: &[Caption], content: &str) -> String {
+    let mut attributes: Vec<String> = anchor
+        .map(|anchor| format!("#{}", anchor))
+        .into_iter()
+        .collect();
+    attributes.push(format!(".{}", class));
+    if let Some(caption) = caption_line(captions) {
+        attributes.push(format!(
+            "caption=\"{}\"",
+            caption.replace('\\', "\\\\").replace('"', "\\\"")
+        ));
+    }
+    format!("::: {{{}}}\n{}:::\n\n", attributes.join(" "), content)
+}
+
+// ==================== 组图 ====================
+
+/// 分图的替代文字：有分图说明时使用其单行形式，否则为整个图的替代文字
+fn panel_alt(panel: &Panel, alt: &str) -> String {
+    panel
+        .caption
+        .as_deref()
+        .map(alt_text)
+        .filter(|panel_alt| !panel_alt.is_empty())
+        .unwrap_or_else(|| alt.to_string())
+}
+
+/// Styled 风格中组图的各个分图：横向排列，放不下时换行，分图说明在各自的图片下方
+fn panels_html(panels: &[Panel], alt: &str, options: &ConvertOptions) -> String {
+    let items: Vec<String> = panels
+        .iter()
+        .map(|panel| {
+            let image = match panel.image.src() {
+                Some(src) => image_tag(
+                    src,
+                    panel.image.size(),
+                    &panel_alt(panel, alt),
+                    "mineru-figure-image",
+                    options,
+                ),
+                None => missing_image_placeholder(unavailable_note(Some(&panel.image)), options),
+            };
+            let caption = panel
+                .caption
+                .as_deref()
+                .map(|caption| {
+                    format!(
+                        "\n<figcaption{}>{}</figcaption>",
+                        style_attr("mineru-panel-caption", options),
+                        escape_html(caption)
+                    )
+                })
+                .unwrap_or_default();
+            format!(
+                "<figure{}>\n{}{}\n</figure>",
+                style_attr("mineru-panel", options),
+                image,
+                caption
+            )
+        })
+        .collect();
+    format!(
+        "<div{}>\n{}\n</div>",
+        style_attr("mineru-panels", options),
+        items.join("\n")
+    )
+}
+
+/// GFM / Obsidian 风格与 Pandoc 写法中组图的各个分图：每张图片一段，分图说明为其后的斜体段落。
+/// Pandoc 写法中分图说明作为图片的替代文字，每个分图成为带说明的子图
+fn panels_markdown(panels: &[Panel], alt: &str, options: &ConvertOptions) -> String {
+    panels
+        .iter()
+        .map(|panel| {
+            let alt = panel_alt(panel, alt);
+            match (panel.image.src(), panel.caption.as_deref()) {
+                (Some(src), Some(caption)) if pandoc_figures(options) => {
+                    format!("{}\n\n", markdown_image(&alt_text(caption), src))
+                }
+                (src, caption) => {
+                    let image = match src {
+                        Some(src) => flavor_image(&alt, src, options.flavor),
+                        None => {
+                            missing_image_placeholder(unavailable_note(Some(&panel.image)), options)
+                        }
+                    };
+                    let caption = caption
+                        .map(|caption| caption_markdown(caption, None, emphasize))
+                        .unwrap_or_default();
+                    format!("{}\n\n{}", image, caption)
+                }
+            }
+        })
+        .collect()
+}
+
+/// LaTeX 中组图的各个分图：等宽的 `minipage` 并排，`\hfill` 隔开，分图说明以小字放在图片之下
+fn panels_latex(panels: &[Panel]) -> String {
+    let width = 0.95 / panels.len() as f64;
+    panels
+        .iter()
+        .map(|panel| {
+            let graphic = match panel.image.src() {
+                Some(src) => include_graphics(src),
+                None => missing_graphic(unavailable_note(Some(&panel.image))),
+            };
+            let caption = panel
+                .caption
+                .as_deref()
+                .map(|caption| format!("\\\\\n{{\\small {}}}\n", escape_latex(caption)))
+                .unwrap_or_else(|| "\n".to_string());
+            format!(
+                "\\begin{{minipage}}[t]{{{:.2}\\linewidth}}\n\\centering\n{}{}\\end{{minipage}}",
+                width, graphic, caption
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n\\hfill\n")
+}
+
+/// AsciiDoc 中组图的各个分图：每张图片一个 `image::`，分图说明为其块标题
+fn panels_asciidoc(panels: &[Panel], alt: &str) -> String {
+    panels
+        .iter()
+        .map(|panel| {
+            let mut asciidoc = panel
+                .caption
+                .as_deref()
+                .map(block_title)
+                .unwrap_or_default();
+            asciidoc.push_str(&match panel.image.src() {
+                Some(src) => block_image(src, &panel_alt(panel, alt)),
+                None => missing_asciidoc_image(unavailable_note(Some(&panel.image))),
+            });
+            asciidoc
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// reStructuredText 中组图的各个分图：每张图片一个 `.. figure::`，分图说明为其标题
+fn panels_rst(panels: &[Panel], alt: &str) -> String {
+    panels
+        .iter()
+        .map(|panel| match panel.image.src() {
+            Some(src) => {
+                let caption = panel
+                    .caption
+                    .as_deref()
+                    .map(|caption| escape_rst(caption, true))
+                    .unwrap_or_default();
+                let figure =
+                    rst::directive("figure", src, &[("alt", &panel_alt(panel, alt))], &caption);
+                format!("{}\n", figure)
+            }
+            None => {
+                let mut rst = panel.caption.as_deref().map(rst_rubric).unwrap_or_default();
+                let missing = missing_rst_image(unavailable_note(Some(&panel.image)));
+                if !missing.is_empty() {
+                    rst.push_str(&missing);
+                    rst.push('\n');
+                }
+                rst
+            }
+        })
+        .collect()
+}
+
+/// Org 中组图的各个分图：每张图片一个带 `#+CAPTION:` 的图片链接，第一个分图带整个图的锚点 `#+NAME:`
+fn panels_org(panels: &[Panel], anchor: Option<&str>) -> String {
+    panels
+        .iter()
+        .enumerate()
+        .map(|(i, panel)| {
+            let caption: Vec<Caption> = panel
+                .caption
+                .iter()
+                .map(|text| Caption {
+                    kind: CaptionKind::Caption,
+                    text: text.clone(),
+                    rotation: None,
+                    above: false,
+                })
+                .collect();
+            let body = panel.image.src().map(org::image);
+            org_figure(
+                &caption,
+                anchor.filter(|_| i == 0),
+                body.as_deref(),
+                unavailable_note(Some(&panel.image)),
+            )
+        })
+        .collect()
+}
+
+fn write_figure(
+    image: Option<&ImageSource>,
+    panels: &[Panel],
+    captions: &[Caption],
+    anchor: Option<&str>,
+    rotation: Option<u32>,
+    options: &ConvertOptions,
+    page_idx: usize,
+) -> String {
+    let numbered = numbered_caption(captions);
+    if options.format == OutputFormat::Text {
+        return text_placeholder("figure", numbered.map(|i| &captions[i]), options);
+    }
+    let src = image.and_then(ImageSource::src);
+    let caption = numbered.map(|i| captions[i].text.as_str());
+    let caption_above = numbered.is_some_and(|i| captions[i].above);
+    let anchor_html = anchor_html(anchor, options);
+
+    if options.format == OutputFormat::Latex {
+        let graphic = match src {
+            _ if !panels.is_empty() => panels_latex(panels),
+            Some(src) => include_graphics(src),
+            None => missing_graphic(unavailable_note(image)),
+        };
+        let caption = joined_caption(captions);
+        return anchor_html
+            + &float(
+                FigureKind::Figure,
+                &graphic,
+                caption.as_deref(),
+                caption_above,
+                &footnote_texts(captions),
+            );
+    }
+
+    let alt = figure_alt(caption, "figure", page_idx);
+    // 图注连成一行作为块标题，图片脚注以小字附在图片之前或之后
+    if options.format == OutputFormat::Asciidoc {
+        let mut asciidoc = asciidoc_footnotes(captions, true);
+        asciidoc.push_str(
+            &joined_caption(captions)
+                .map(|caption| block_title(&caption))
+                .unwrap_or_default(),
+        );
+        asciidoc.push_str(&match src {
+            // 组图的各个分图放在开放块中，共用块标题
+            _ if !panels.is_empty() => format!("--\n{}--\n", panels_asciidoc(panels, &alt)),
+            Some(src) => block_image(src, &alt),
+            None => missing_asciidoc_image(unavailable_note(image)),
+        });
+        asciidoc.push('\n');
+        asciidoc.push_str(&asciidoc_footnotes(captions, false));
+        return asciidoc;
+    }
+    // 图注连成一段作为 `.. figure::` 的标题，图片脚注为其后的图例；图片缺失时图注改为小标题
+    if options.format == OutputFormat::Rst {
+        let mut rst = anchor.map(rst::label).unwrap_or_default();
+        let caption = joined_caption(captions).map(|caption| escape_rst(&caption, true));
+        match src {
+            // 组图的各个分图各为一个 `.. figure::`，整个图的图注为其前或其后的小标题
+            _ if !panels.is_empty() => {
+                let rubric = joined_caption(captions)
+                    .map(|caption| rst_rubric(&caption))
+                    .unwrap_or_default();
+                rst.push_str(&rst_footnotes(captions, true));
+                if caption_above {
+                    rst.push_str(&rubric);
+                }
+                rst.push_str(&panels_rst(panels, &alt));
+                if !caption_above {
+                    rst.push_str(&rubric);
+                }
+                rst.push_str(&rst_footnotes(captions, false));
+            }
+            Some(src) => {
+                let mut content = caption.unwrap_or_default();
+                let legend: Vec<String> = footnote_texts(captions)
+                    .iter()
+                    .map(|text| escape_rst(text, true))
+                    .collect();
+                if !legend.is_empty() {
+                    // 没有图注时以空注释占住标题的位置
+                    if content.is_empty() {
+                        content.push_str("..");
+                    }
+                    content.push_str("\n\n");
+                    content.push_str(&legend.join("\n\n"));
+                }
+                rst.push_str(&rst::directive("figure", src, &[("alt", &alt)], &content));
+                rst.push('\n');
+            }
+            None => {
+                rst.push_str(&rst_footnotes(captions, true));
+                rst.push_str(
+                    &joined_caption(captions)
+                        .map(|caption| rst_rubric(&caption))
+                        .unwrap_or_default(),
+                );
+                let missing = missing_rst_image(unavailable_note(image));
+                if !missing.is_empty() {
+                    rst.push_str(&missing);
+                    rst.push('\n');
+                }
+                rst.push_str(&rst_footnotes(captions, false));
+            }
+        }
+        return rst;
+    }
+    // 图片链接单独成段，导出为带图注的图片
+    // 组图的各个分图各为一个带图注的图片，整个图的图注为其前或其后的段落
+    if options.format == OutputFormat::Org {
+        if !panels.is_empty() {
+            let caption = caption_line(captions)
+                .map(|caption| format!("{}\n\n", escape_org(&caption, true)))
+                .unwrap_or_default();
+            let mut org = org_footnotes(captions, true);
+            if caption_above {
+                org.push_str(&caption);
+            }
+            org.push_str(&panels_org(panels, anchor));
+            if !caption_above {
+                org.push_str(&caption);
+            }
+            org.push_str(&org_footnotes(captions, false));
+            return org;
+        }
+        let body = src.map(org::image);
+        return org_figure(captions, anchor, body.as_deref(), unavailable_note(image));
+    }
+    // 图注作为图片的替代文字，Pandoc 将单独成段的图片识别为带图注的图；图片缺失时放进 `:::` 块
+    if pandoc_figures(options) {
+        // 组图放进 `:::` 块，每个分图为一个子图
+        if !panels.is_empty() {
+            return pandoc_div(
+                anchor,
+                "figure",
+                captions,
+                &format!(
+                    "{}{}{}",
+                    markdown_captions(captions, numbered, anchor, true, options),
+                    panels_markdown(panels, &alt, options),
+                    markdown_captions(captions, numbered, anchor, false, options)
+                ),
+            );
+        }
+        let id = anchor
+            .map(|anchor| format!("{{#{}}}", anchor))
+            .unwrap_or_default();
+        let figure = match src {
+            Some(src) => format!(
+                "{}{}\n\n",
+                markdown_image(&caption_line(captions).unwrap_or_default(), src),
+                id
+            ),
+            None => {
+                return pandoc_div(
+                    anchor,
+                    "figure",
+                    captions,
+                    &format!(
+                        "{}{}\n\n{}",
+                        markdown_captions(captions, numbered, anchor, true, options),
+                        missing_image_placeholder(unavailable_note(image), options),
+                        markdown_captions(captions, numbered, anchor, false, options)
+                    ),
+                );
+            }
+        };
+        return pandoc_footnotes(captions, true) + &figure + &pandoc_footnotes(captions, false);
+    }
+    let figure = match options.flavor {
+        Flavor::Styled => {
+            let image_html = match src {
+                _ if !panels.is_empty() => panels_html(panels, &alt, options),
+                Some(src) => image_tag(
+                    src,
+                    image.and_then(ImageSource::size),
+                    &alt,
+                    "mineru-figure-image",
+                    options,
+                ),
+                None => missing_image_placeholder(unavailable_note(image), options),
+            };
+            // 图片脚注的字号比图注更小、颜色更浅
+            let caption_html = |above: bool| -> String {
+                let side = if above { "above" } else { "below" };
+                captions
+                    .iter()
+                    .filter(|caption| caption.above == above)
+                    .map(|caption| match caption.kind {
+                        CaptionKind::Caption => format!(
+                            "<figcaption{}{}>{}</figcaption>",
+                            rotation_attr(caption.rotation),
+                            style_attr(
+                                &format!("mineru-figcaption mineru-figcaption-{}", side),
+                                options
+                            ),
+                            escape_html(&caption.text)
+                        ),
+                        CaptionKind::Footnote => format!(
+                            "<p{}{}>{}</p>",
+                            rotation_attr(caption.rotation),
+                            style_attr(
+                                &format!("mineru-figure-note mineru-figure-note-{}", side),
+                                options
+                            ),
+                            escape_html(&caption.text)
+                        ),
+                    })
+                    .collect()
+            };
+            let mut above_html = caption_html(true);
+            if !above_html.is_empty() {
+                above_html.push('\n');
+            }
+            format!(
+                "<figure{}{}>\n{}{}\n{}\n</figure>\n\n",
+                rotation_attr(rotation),
+                style_attr("mineru-figure", options),
+                above_html,
+                image_html,
+                caption_html(false)
+            )
+        }
+        Flavor::Gfm | Flavor::Obsidian => {
+            let image = match src {
+                _ if !panels.is_empty() => panels_markdown(panels, &alt, options),
+                Some(src) => format!("{}\n\n", flavor_image(&alt, src, options.flavor)),
+                None => format!(
+                    "{}\n\n",
+                    missing_image_placeholder(unavailable_note(image), options)
+                ),
+            };
+            format!(
+                "{}{}{}",
+                markdown_captions(captions, numbered, anchor, true, options),
+                image,
+                markdown_captions(captions, numbered, anchor, false, options)
+            )
+        }
+    };
+    anchor_html + &figure
+}
+
+/// 表格下方指向导出的 CSV 文件的链接，单独成段；Org 的相对路径需写成 `file:` 链接
+fn csv_link(href: &str, options: &ConvertOptions) -> String {
+    match options.format {
+        OutputFormat::Html => format!("<p>{}</p>\n\n", link_markup("CSV", href, options)),
+        OutputFormat::Org => format!("{}\n\n", org::link("CSV", &format!("file:{}", href))),
+        _ => format!("{}\n\n", link_markup("CSV", href, options)),
+    }
+}
+
+fn write_table(
+    body: Option<&TableBody>,
+    captions: &[Caption],
+    anchor: Option<&str>,
+    rotation: Option<u32>,
+    options: &ConvertOptions,
+    page_idx: usize,
+    warnings: &mut Vec<Warning>,
+) -> String {
+    let numbered = numbered_caption(captions);
+    if options.format == OutputFormat::Text {
+        return text_placeholder("table", numbered.map(|i| &captions[i]), options);
+    }
+    let caption = numbered.map(|i| captions[i].text.as_str());
+    let caption_above = numbered.is_some_and(|i| captions[i].above);
+    let anchor_html = anchor_html(anchor, options);
+    let (html, image) = match body {
+        Some(TableBody::Html(html)) => (Some(html.as_str()), None),
+        Some(TableBody::Image(image)) => (None, Some(image)),
+        None => (None, None),
+    };
+    let src = image.and_then(ImageSource::src);
+
+    // 无法转换为 tabular 的表格记录警告，原始 HTML 以注释保留
+    if options.format == OutputFormat::Latex {
+        let body = match (html, src) {
+            (Some(html), _) => match html_table_to_latex(html) {
+                Ok(tabular) => tabular,
+                Err(reason) => {
+                    warnings.push(Warning::TableKeptAsHtml {
+                        page_idx,
+                        reason: reason.to_string(),
+                    });
+                    comment(html)
+                }
+            },
+            (None, Some(src)) => include_graphics(src),
+            (None, None) => missing_graphic(unavailable_note(image)),
+        };
+        let caption = joined_caption(captions);
+        return anchor_html
+            + &float(
+                FigureKind::Table,
+                &body,
+                caption.as_deref(),
+                caption_above,
+                &footnote_texts(captions),
+            );
+    }
+
+    let alt = figure_alt(caption, "table", page_idx);
+
+    // 表注连成一行作为块标题，表格脚注以小字附在表格之前或之后；
+    // 无法转换的表格记录警告后以直通块保留 HTML
+    if options.format == OutputFormat::Asciidoc {
+        let mut asciidoc = asciidoc_footnotes(captions, true);
+        asciidoc.push_str(
+            &joined_caption(captions)
+                .map(|caption| block_title(&caption))
+                .unwrap_or_default(),
+        );
+        asciidoc.push_str(&match (html, src) {
+            (Some(html), _) => match html_table_to_asciidoc(html) {
+                Ok(table) => table,
+                Err(reason) => {
+                    warnings.push(Warning::TableKeptAsHtml {
+                        page_idx,
+                        reason: reason.to_string(),
+                    });
+                    delimited_block('+', html)
+                }
+            },
+            (None, Some(src)) => block_image(src, &alt),
+            (None, None) => missing_asciidoc_image(unavailable_note(image)),
+        });
+        asciidoc.push('\n');
+        asciidoc.push_str(&asciidoc_footnotes(captions, false));
+        return asciidoc;
+    }
+
+    // 表注连成一行作为小标题，表格脚注以小字容器附在表格之前或之后；
+    // 表格不转换为 reStructuredText 的表格语法，HTML 原样放进 `.. raw:: html`
+    if options.format == OutputFormat::Rst {
+        let mut rst = anchor.map(rst::label).unwrap_or_default();
+        rst.push_str(&rst_footnotes(captions, true));
+        rst.push_str(
+            &joined_caption(captions)
+                .map(|caption| rst_rubric(&caption))
+                .unwrap_or_default(),
+        );
+        let body = match (html, src) {
+            (Some(html), _) => rst::directive("raw", "html", &[], html),
+            (None, Some(src)) => rst::image(src, &alt),
+            (None, None) => missing_rst_image(unavailable_note(image)),
+        };
+        if !body.is_empty() {
+            rst.push_str(&body);
+            rst.push('\n');
+        }
+        rst.push_str(&rst_footnotes(captions, false));
+        return rst;
+    }
+
+    // 表格转换为 Org 表格；无法转换的表格记录警告后以 `#+BEGIN_EXPORT html` 保留 HTML
+    if options.format == OutputFormat::Org {
+        let body = match (html, src) {
+            (Some(html), _) => Some(match html_table_to_org(html) {
+                Ok(table) => table,
+                Err(reason) => {
+                    warnings.push(Warning::TableKeptAsHtml {
+                        page_idx,
+                        reason: reason.to_string(),
+                    });
+                    org::block("EXPORT", "html", html)
+                }
+            }),
+            (None, Some(src)) => Some(org::image(src)),
+            (None, None) => None,
+        };
+        return org_figure(captions, anchor, body.as_deref(), unavailable_note(image));
+    }
+
+    // 管道表格的表注为粗体行、表格脚注为斜体行；无法转换的表格记录警告后仍输出 HTML
+    if options.table_format == TableFormat::Markdown
+        && options.format == OutputFormat::Markdown
+        && let Some(html) = html
+    {
+        match html_table_to_markdown(html, options.escape_markdown) {
+            // Pandoc 写法的表注为表格之后的 `Table:` 段落，编号的表格放进带标识的 `:::` 块
+            Ok(table) if pandoc_figures(options) => {
+                let mut md = pandoc_footnotes(captions, true);
+                md.push_str(&table);
+                md.push('\n');
+                if let Some(caption) = caption_line(captions) {
+                    md.push_str(&format!("Table: {}\n\n", caption));
+                }
+                md.push_str(&pandoc_footnotes(captions, false));
+                return match anchor {
+                    Some(anchor) => format!("::: {{#{}}}\n{}:::\n\n", anchor, md),
+                    None => md,
+                };
+            }
+            Ok(table) => {
+                let notes = |above: bool| -> String {
+                    captions
+                        .iter()
+                        .enumerate()
+                        .filter(|(_, caption)| caption.above == above)
+                        .map(|(i, caption)| match caption.kind {
+                            CaptionKind::Caption => {
+                                let block_id =
+                                    block_id(anchor, options).filter(|_| numbered == Some(i));
+                                caption_markdown(&caption.text, block_id, strong)
+                            }
+                            CaptionKind::Footnote => format!("{}\n\n", emphasize(&caption.text)),
+                        })
+                        .collect()
+                };
+                return format!("{}{}{}\n{}", anchor_html, notes(true), table, notes(false));
+            }
+            Err(reason) => warnings.push(Warning::TableKeptAsHtml {
+                page_idx,
+                reason: reason.to_string(),
+            }),
+        }
+    }
+
+    // 表格 HTML、截图与说明文字段落照 Gfm 风格输出，外面包一层 `:::` 块
+    if pandoc_figures(options) {
+        let body = match (html, src) {
+            (Some(html), _) => html.to_string(),
+            (None, Some(src)) => markdown_image(&alt, src),
+            (None, None) => missing_image_placeholder(unavailable_note(image), options),
+        };
+        return pandoc_div(
+            anchor,
+            "table",
+            captions,
+            &format!(
+                "{}{}\n\n{}",
+                markdown_captions(captions, numbered, anchor, true, options),
+                body,
+                markdown_captions(captions, numbered, anchor, false, options)
+            ),
+        );
+    }
+
+    let table = match options.flavor {
+        Flavor::Styled => {
+            let body = match (html, src) {
+                (Some(html), _) => html.to_string(),
+                (None, Some(src)) => image_tag(
+                    src,
+                    image.and_then(ImageSource::size),
+                    &alt,
+                    "mineru-figure-image",
+                    options,
+                ),
+                (None, None) => missing_image_placeholder(unavailable_note(image), options),
+            };
+            let notes = |above: bool| -> String {
+                let side = if above { "above" } else { "below" };
+                captions
+                    .iter()
+                    .filter(|caption| caption.above == above)
+                    .map(|caption| match caption.kind {
+                        CaptionKind::Caption => format!(
+                            "<caption{}{}>{}</caption>",
+                            rotation_attr(caption.rotation),
+                            style_attr(
+                                &format!("mineru-table-caption mineru-table-caption-{}", side),
+                                options
+                            ),
+                            escape_html(&caption.text)
+                        ),
+                        CaptionKind::Footnote => format!(
+                            "<p{}{}>{}</p>",
+                            rotation_attr(caption.rotation),
+                            style_attr(
+                                &format!("mineru-table-note mineru-table-note-{}", side),
+                                options
+                            ),
+                            escape_html(&caption.text)
+                        ),
+                    })
+                    .collect()
+            };
+            format!(
+                "<div{}{}>\n{}\n{}\n{}\n</div>\n\n",
+                rotation_attr(rotation),
+                style_attr("mineru-table-wrap", options),
+                notes(true),
+                body,
+                notes(false)
+            )
+        }
+        Flavor::Gfm | Flavor::Obsidian => {
+            let body = match (html, src) {
+                (Some(html), _) => html.to_string(),
+                (None, Some(src)) => flavor_image(&alt, src, options.flavor),
+                (None, None) => missing_image_placeholder(unavailable_note(image), options),
+            };
+            format!(
+                "{}{}\n\n{}",
+                markdown_captions(captions, numbered, anchor, true, options),
+                body,
+                markdown_captions(captions, numbered, anchor, false, options)
+            )
+        }
+    };
+    anchor_html + &table
+}
+
+/// 默认优先输出公式截图，prefer_equation_latex 时优先输出 LaTeX；两者都没有时输出占位提示。
+/// 纯文本输出中开启 text_placeholders 时为 `[equation: LaTeX]`，否则跳过
+fn write_equation(
+    latex: Option<&str>,
+    image: Option<&ImageSource>,
+    options: &ConvertOptions,
+    page_idx: usize,
+) -> String {
+    if options.format == OutputFormat::Text {
+        return match (options.text_placeholders, latex) {
+            (false, _) => String::new(),
+            (true, Some(latex)) => format!("[equation: {}]\n\n", latex),
+            (true, None) => "[equation]\n\n".to_string(),
+        };
+    }
+    if options.prefer_equation_latex
+        && let Some(latex) = latex
+    {
+        return display_math(latex, options);
+    }
+    match image {
+        Some(ImageSource::Src { src, .. }) if options.format == OutputFormat::Latex => {
+            return format!(
+                "\\begin{{center}}\n{}\n\\end{{center}}\n\n",
+                include_graphics(src)
+            );
+        }
+        Some(ImageSource::Src { src, .. }) if options.format == OutputFormat::Asciidoc => {
+            let alt = figure_alt(latex, "equation", page_idx);
+            return format!("{}\n", block_image(src, &alt));
+        }
+        Some(ImageSource::Src { src, .. }) if options.format == OutputFormat::Rst => {
+            let alt = figure_alt(latex, "equation", page_idx);
+            return format!("{}\n", rst::image(src, &alt));
+        }
+        Some(ImageSource::Src { src, .. }) if options.format == OutputFormat::Org => {
+            return format!("{}\n", org::image(src));
+        }
+        Some(ImageSource::Src { src, size }) => {
+            let alt = figure_alt(latex, "equation", page_idx);
+            return match options.flavor {
+                Flavor::Styled => format!(
+                    "<div{}>\n{}\n</div>\n\n",
+                    style_attr("mineru-equation", options),
+                    image_tag(src, *size, &alt, "mineru-equation-image", options)
+                ),
+                Flavor::Gfm | Flavor::Obsidian => {
+                    format!("{}\n\n", flavor_image(&alt, src, options.flavor))
+                }
+            };
+        }
+        _ => {}
+    }
+    if let Some(latex) = latex {
+        return display_math(latex, options);
+    }
+    // 没有 LaTeX 可以退回时才输出占位提示
+    match image {
+        Some(ImageSource::Unavailable(note)) if options.format == OutputFormat::Latex => {
+            format!("{}\n\n", missing_graphic(note))
+        }
+        Some(ImageSource::Unavailable(note)) if options.format == OutputFormat::Asciidoc => {
+            format!("{}\n", missing_asciidoc_image(note))
+        }
+        Some(ImageSource::Unavailable(note)) if options.format == OutputFormat::Rst => {
+            format!("{}\n", missing_rst_image(note))
+        }
+        Some(ImageSource::Unavailable(note)) if options.format == OutputFormat::Org => {
+            format!("{}\n", missing_org_image(note))
+        }
+        Some(ImageSource::Unavailable(note)) => {
+            format!("{}\n\n", missing_image_placeholder(note, options))
+        }
+        _ => String::new(),
+    }
+}
+
+/// 脚注、参考文献条目、注音、旁注等 MinerU 单独标注的文字，以小字段落输出
+fn write_small_print(text: &str, options: &ConvertOptions) -> String {
+    if text.is_empty() {
+        return String::new();
+    }
+    match (options.format, options.flavor) {
+        (OutputFormat::Text | OutputFormat::Json, _) => format!("{}\n\n", text),
+        (OutputFormat::Latex, _) => format!("{}\n", small_print(&escape_latex(text))),
+        (OutputFormat::Asciidoc, _) => {
+            format!("{}\n", asciidoc::small_print(&escape_asciidoc(text, true)))
+        }
+        (OutputFormat::Rst, _) => format!("{}\n", rst::small_print(&escape_rst(text, true))),
+        (OutputFormat::Org, _) => format!("{}\n", org::small_print(&escape_org(text, true))),
+        (_, Flavor::Styled) => format!(
+            "<p{}>{}</p>\n\n",
+            style_attr("mineru-small-print", options),
+            escape_html(text)
+        ),
+        (_, Flavor::Gfm | Flavor::Obsidian) => format!("{}\n\n", emphasize(text)),
+    }
+}
+
+// ==================== 页眉、页脚与页面脚注 ====================
+
+/// 页眉与页脚使用相同的样式
+fn render_margin(text: &str, options: &ConvertOptions) -> String {
+    if text.is_empty() {
+        return String::new();
+    }
+    match options.flavor {
+        Flavor::Styled => format!(
+            "<div{}>\n<span>{}</span>\n</div>\n\n",
+            style_attr("mineru-header-note", options),
+            escape_html(text)
+        ),
+        Flavor::Gfm => format!("{}\n\n", emphasize(text)),
+        Flavor::Obsidian => format!("> [!info]\n{}\n\n", callout_lines(text)),
+    }
+}
+
+/// 本页的脚注集中放在正文之后；Styled 风格只要本页有脚注块就输出外框
+fn render_discarded_footnotes(notes: &[&str], options: &ConvertOptions) -> String {
+    if notes.is_empty() {
+        return String::new();
+    }
+
+    let texts: Vec<&str> = notes
+        .iter()
+        .copied()
+        .filter(|text| !text.is_empty())
+        .collect();
+
+    if options.format == OutputFormat::Latex {
+        if texts.is_empty() {
+            return String::new();
+        }
+        let notes: String = texts
+            .iter()
+            .map(|text| small_print(&escape_latex(text)))
+            .collect();
+        return format!("{}\n", notes);
+    }
+    match options.flavor {
+        Flavor::Styled => {
+            let mut html = format!("<div{}>\n", style_attr("mineru-footnotes", options));
+            let note = style_attr("mineru-footnote", options);
+            for text in &texts {
+                html.push_str(&format!("<p{}>{}</p>\n", note, escape_html(text)));
+            }
+            html.push_str("</div>\n\n");
+            html
+        }
+        Flavor::Gfm => {
+            if texts.is_empty() {
+                return String::new();
+            }
+            let quoted: Vec<String> = texts.iter().map(|text| format!("> {}", text)).collect();
+            format!("{}\n\n", quoted.join("\n>\n"))
+        }
+        Flavor::Obsidian => {
+            if texts.is_empty() {
+                return String::new();
+            }
+            let quoted: Vec<String> = texts.iter().map(|text| callout_lines(text)).collect();
+            format!("> [!note]\n{}\n\n", quoted.join("\n>\n"))
+        }
+    }
+}
+
+/// callout 的正文：每行加上 `> ` 前缀
+fn callout_lines(text: &str) -> String {
+    text.lines()
+        .map(|line| format!("> {}", line).trim_end().to_string())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+// ==================== 页面写出 ====================
+
+/// 块来源的 HTML 注释，如 `<!-- p12 bbox=[56.1,102.4,540.0,160.8] type=text idx=7 -->`，
+/// LaTeX 输出中为 `%` 注释、AsciiDoc 输出中为 `//` 注释、reStructuredText 输出中为 `..` 注释、Org 输出中为 `#` 注释；块类型中的非字母数字字符替换为 `_`，注释内不会出现 `--`
+fn provenance_comment(source: &BlockSource, page_idx: usize, options: &ConvertOptions) -> String {
+    let bbox: Vec<String> = if source.bbox.is_empty() {
+        Vec::new()
+    } else {
+        source
+            .bbox
+            .coordinates()
+            .iter()
+            .map(|n| format!("{:.1}", n))
+            .collect()
+    };
+    let block_type: String = source
+        .block_type
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    let index = source
+        .index
+        .map(|index| format!(" idx={}", index))
+        .unwrap_or_default();
+    let provenance = format!(
+        "p{} bbox=[{}] type={}{}",
+        page_idx + 1,
+        bbox.join(","),
+        block_type,
+        index
+    );
+    match options.format {
+        OutputFormat::Latex => comment(&provenance),
+        OutputFormat::Asciidoc => asciidoc::comment(&provenance),
+        OutputFormat::Rst => rst::comment(&provenance),
+        OutputFormat::Org => org::comment(&provenance),
+        _ => format!("<!-- {} -->\n", provenance),
+    }
+}
+
+/// 写出一页：页眉、正文、页面脚注、页脚依次排列，不论节点在 `page.nodes` 中的位置；
+/// 分页线记入 [`RenderedPage::divider`]，由拼接页面时输出
+///
+/// 有来源块的节点按块类型记入统计：输出为空的块计为 skipped。
+pub(crate) fn write_page(page: &PageNodes, options: &ConvertOptions) -> RenderedPage {
+    let page_idx = page.page_idx;
+    let mut warnings = page.warnings.clone();
+    let mut stats = page.stats.clone();
+    let mut toc_entries = Vec::new();
+    let mut headers = String::new();
+    let mut body_html = String::new();
+    let mut footers = String::new();
+    let mut footnotes: Vec<&str> = Vec::new();
+    let mut divider = None;
+
+    for PageNode { node, source } in &page.nodes {
+        match node {
+            DocNode::HeaderNote { text } => headers.push_str(&render_margin(text, options)),
+            DocNode::FooterNote { text } => footers.push_str(&render_margin(text, options)),
+            DocNode::FootNote { text } => footnotes.push(text),
+            DocNode::PageBreak { page, printed_page } => {
+                divider = Some(Divider {
+                    page: *page,
+                    printed_page: printed_page.clone(),
+                })
+            }
+            _ => {
+                let (mut html, toc_entry) =
+                    write_node(node, source.as_ref(), page_idx, options, &mut warnings);
+                let rendered = !html.is_empty();
+                // 渲染为空的块不输出注释；纯文本输出不含任何标记
+                if rendered
+                    && options.provenance
+                    && !matches!(options.format, OutputFormat::Text | OutputFormat::Json)
+                    && let Some(source) = source
+                {
+                    html.insert_str(0, &provenance_comment(source, page_idx, options));
+                }
+                if let Some(source) = source {
+                    stats.count_block(&source.block_type, rendered);
+                }
+                if let Some(entry) = toc_entry {
+                    *stats.headings.entry(entry.level).or_default() += 1;
+                    toc_entries.push(entry);
+                }
+                if rendered && let DocNode::List { items } = node {
+                    stats.list_items += count_items(items);
+                }
+                body_html.push_str(&html);
+            }
+        }
+    }
+
+    let empty_body = body_html.trim().is_empty() && !page.continued;
+    if empty_body {
+        warnings.push(Warning::EmptyPage { page_idx });
+    }
+
+    let mut html = headers;
+    let body_start = html.len();
+    match options.format {
+        // 纯文本输出不含页面脚注
+        OutputFormat::Text => html.push_str(&body_html),
+        OutputFormat::Json => {
+            html.push_str(&body_html);
+            html.push_str(&json::write_footnotes(&footnotes, page_idx));
+        }
+        OutputFormat::Asciidoc => {
+            let footnotes = collect_page_footnotes(&footnotes, page_idx);
+            let unlinked = render_asciidoc_footnotes(&mut body_html, &footnotes);
+            html.push_str(&body_html);
+            html.push_str(&unlinked);
+        }
+        OutputFormat::Rst => {
+            let footnotes = collect_page_footnotes(&footnotes, page_idx);
+            let definitions = render_rst_footnotes(&mut body_html, &footnotes);
+            html.push_str(&body_html);
+            html.push_str(&definitions);
+        }
+        OutputFormat::Org => {
+            let footnotes = collect_page_footnotes(&footnotes, page_idx);
+            let definitions = render_org_footnotes(&mut body_html, &footnotes);
+            html.push_str(&body_html);
+            html.push_str(&definitions);
+        }
+        _ => match options.footnote_style {
+            FootnoteStyle::Div => {
+                html.push_str(&body_html);
+                html.push_str(&render_discarded_footnotes(&footnotes, options));
+            }
+            FootnoteStyle::Markdown => {
+                let footnotes = collect_page_footnotes(&footnotes, page_idx);
+                link_footnote_markers(&mut body_html, &footnotes);
+                html.push_str(&body_html);
+                html.push_str(&render_markdown_footnotes(
+                    &footnotes,
+                    options.escape_markdown,
+                ));
+            }
+        },
+    }
+    html.push_str(&footers);
+    let blank = empty_body && html.trim().is_empty();
+
+    for entry in &mut toc_entries {
+        entry.printed_page = page.printed_page.clone();
+    }
+    RenderedPage {
+        page_idx,
+        document: page.document,
+        html,
+        toc_entries,
+        warnings,
+        figures: page.figures.clone(),
+        stats,
+        body_start,
+        divider,
+        blank,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ConvertOptions;
+    use crate::testing::{TempDir, bare_options, image_block, page_layout, png, text_block};
+    use base64::{Engine as _, engine::general_purpose};
+    use serde_json::json;
+
+    fn equation_layout(image_path: Option<&str>) -> crate::LayoutJson {
+        let bbox = [100.0, 100.0, 500.0, 140.0];
+        let mut span = json!({"type": "interline_equation", "bbox": bbox, "content": "E = mc^2"});
+        if let Some(path) = image_path {
+            span["image_path"] = json!(path);
+        }
+        page_layout(json!([{
+            "type": "interline_equation",
+            "bbox": bbox,
+            "lines": [{"bbox": bbox, "spans": [span]}]
+        }]))
+    }
+
+    #[test]
+    fn equation_images_win_over_latex_unless_latex_is_preferred() {
+        let dir = TempDir::new();
+        dir.write("eq.png", &png(40, 30, 0));
+        let layout = equation_layout(Some("eq.png"));
+        let convert = |options: &ConvertOptions| {
+            crate::convert_layout_to_markdown(&layout, dir.path(), options)
+                .unwrap()
+                .markdown
+        };
+
+        let image = convert(&bare_options());
+        assert!(image.contains("alt=\"E = mc^2\""), "{}", image);
+        assert!(!image.contains("$$"));
+        // 默认不限制高度
+        assert!(!image.contains("max-height"));
+
+        let options = ConvertOptions {
+            prefer_equation_latex: true,
+            ..bare_options()
+        };
+        let latex = convert(&options);
+        assert_eq!(latex.trim(), "$$\nE = mc^2\n$$");
+        assert!(!latex.contains("<img"));
+    }
+
+    #[test]
+    fn equation_height_cap_is_opt_in() {
+        let dir = TempDir::new();
+        dir.write("eq.png", &png(40, 30, 0));
+        let layout = equation_layout(Some("eq.png"));
+        let options = ConvertOptions {
+            equation_max_height: Some(120),
+            ..bare_options()
+        };
+        let capped = crate::convert_layout_to_markdown(&layout, dir.path(), &options)
+            .unwrap()
+            .markdown;
+        assert!(capped.contains("max-height: 120px"), "{}", capped);
+    }
+
+    #[test]
+    fn equations_without_an_image_use_latex() {
+        let markdown = crate::testing::markdown(&equation_layout(None), &bare_options());
+        assert_eq!(markdown.trim(), "$$\nE = mc^2\n$$");
+    }
+
+    #[test]
+    fn rotated_captions_and_text_are_annotated() {
+        let dir = TempDir::new();
+        dir.write("figure.png", &png(4, 3, 0));
+        let mut figure = image_block([100.0, 160.0, 500.0, 380.0], "figure.png");
+        let mut caption = text_block("image_caption", [100.0, 385.0, 500.0, 400.0], "Sideways");
+        caption["angle"] = json!(89.7);
+        figure["blocks"].as_array_mut().unwrap().push(caption);
+        let mut watermark = text_block("text", [0.0, 500.0, 20.0, 700.0], "WATERMARK");
+        watermark["angle"] = json!(270.4);
+        let layout = page_layout(json!([
+            figure,
+            watermark,
+            text_block("text", [50.0, 710.0, 550.0, 730.0], "Body"),
+        ]));
+
+        let convert = |options: &ConvertOptions| {
+            crate::convert_layout_to_markdown(&layout, dir.path(), options)
+                .unwrap()
+                .markdown
+        };
+        let markdown = convert(&bare_options());
+        assert!(
+            markdown.contains("<figcaption data-rotation=\"90\""),
+            "{}",
+            markdown
+        );
+        assert!(markdown.contains("<div data-rotation=\"270\">\n\nWATERMARK\n\n</div>"));
+
+        // 丢弃旋转的正文，图注仍然保留
+        let options = ConvertOptions {
+            drop_rotated_text: true,
+            ..bare_options()
+        };
+        let markdown = convert(&options);
+        assert!(!markdown.contains("WATERMARK"));
+        assert!(markdown.contains(">Sideways</figcaption>"));
+        assert!(markdown.contains("Body"));
+    }
+
+    /// tests/fixtures/captions.json：一个表格带两条表注、两条表格脚注，一张图片带图注与两条图片脚注
+    fn captions_markdown(options: &ConvertOptions) -> String {
+        let layout = crate::testing::layout(
+            serde_json::from_str(include_str!("../tests/fixtures/captions.json")).unwrap(),
+        );
+        let images = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/images");
+        crate::convert_layout_to_markdown(&layout, &images, options)
+            .unwrap()
+            .markdown
+    }
+
+    /// `parts` 依次出现在 `text` 中
+    fn assert_in_order(text: &str, parts: &[&str]) {
+        let mut cursor = 0;
+        for part in parts {
+            let found = text[cursor..]
+                .find(part)
+                .unwrap_or_else(|| panic!("{:?} missing or out of order in\n{}", part, text));
+            cursor += found + part.len();
+        }
+    }
+
+    #[test]
+    fn every_caption_and_footnote_is_kept_in_source_order() {
+        let gfm = captions_markdown(&ConvertOptions {
+            flavor: crate::Flavor::Gfm,
+            ..bare_options()
+        });
+        assert_in_order(
+            &gfm,
+            &[
+                "*Table 2. Throughput of each configuration*\n\n*(continued from Table 1)*\n\n<table>",
+                "</table>\n\n*a Measured with caching disabled.*\n\n*b Mean of five runs.*",
+                "*Figure 3. Latency distribution*\n\n*Source: internal benchmark.*\n\n\
+                 *Outliers above p99 are clipped.*",
+            ],
+        );
+    }
+
+    #[test]
+    fn footnotes_are_styled_apart_from_captions() {
+        let styled = captions_markdown(&bare_options());
+        let figcaption = "<figcaption style=\"text-align: center; font-size: 0.9em; color: #666; \
+                          margin-top: 0.5em;\">Figure 3. Latency distribution</figcaption>";
+        let note =
+            "<p style=\"text-align: center; font-size: 0.8em; color: #888; margin-top: 0.3em;\">";
+        assert_in_order(
+            &styled,
+            &[
+                "Table 2. Throughput of each configuration</caption>",
+                "(continued from Table 1)</caption>",
+                "a Measured with caching disabled.</p>",
+                "b Mean of five runs.</p>",
+                figcaption,
+                &format!("{}Source: internal benchmark.</p>", note),
+                &format!("{}Outliers above p99 are clipped.</p>", note),
+            ],
+        );
+    }
+
+    #[test]
+    fn pandoc_figures_are_native_images_and_pipe_tables() {
+        let layout = crate::parse_layout(
+            include_str!("../tests/fixtures/sample.json"),
+            crate::InputFormat::Auto,
+        )
+        .unwrap();
+        let fixtures = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures");
+        let dir = TempDir::new();
+        let options = ConvertOptions {
+            figure_style: crate::FigureStyle::Pandoc,
+            number_figures: true,
+            images: crate::ImageOutput::Directory {
+                dir: dir.path().to_path_buf(),
+                link_prefix: "img".to_string(),
+            },
+            ..bare_options()
+        };
+        let markdown = crate::convert_layout_to_markdown(&layout, &fixtures, &options)
+            .unwrap()
+            .markdown;
+        assert!(
+            markdown.contains("\n![Figure 1. A red square](img/figure.png){#fig-1}\n"),
+            "{}",
+            markdown
+        );
+        assert!(
+            markdown.contains(
+                "::: {#tab-1}\n| a | b |\n| --- | --- |\n| 1 | 2 |\n\nTable: Table 1. Results\n\n:::\n"
+            ),
+            "{}",
+            markdown
+        );
+        assert!(markdown.contains("$$\nE = mc^2\n$$"));
+        assert!(!markdown.contains("<figure"));
+    }
+
+    #[test]
+    fn missing_pandoc_figures_keep_their_caption_in_a_div() {
+        let layout = page_layout(json!([{
+            "type": "image",
+            "bbox": [100.0, 100.0, 500.0, 300.0],
+            "blocks": [
+                image_block([100.0, 100.0, 500.0, 280.0], "gone.png")["blocks"][0].clone(),
+                text_block("image_caption", [100.0, 285.0, 500.0, 300.0], "A \"quoted\" caption"),
+            ]
+        }]));
+        let options = ConvertOptions {
+            figure_style: crate::FigureStyle::Pandoc,
+            ..bare_options()
+        };
+        let markdown = crate::testing::markdown(&layout, &options);
+        assert!(
+            markdown.starts_with("::: {.figure caption=\"A \\\"quoted\\\" caption\"}\n"),
+            "{}",
+            markdown
+        );
+        assert!(markdown.contains("figure missing: gone.png"));
+        assert!(markdown.trim_end().ends_with(":::"), "{}", markdown);
+    }
+
+    /// tests/fixtures/panels.json：三个分图各带分图说明，最后是整张图的图注
+    fn panels_markdown(options: &ConvertOptions) -> (String, Vec<String>) {
+        let dir = TempDir::new();
+        let uris = ["panel_a.png", "panel_b.png", "panel_c.png"]
+            .iter()
+            .enumerate()
+            .map(|(i, name)| {
+                let data = png(4, 3, 40 * i as u8);
+                dir.write(name, &data);
+                format!(
+                    "data:image/png;base64,{}",
+                    general_purpose::STANDARD.encode(&data)
+                )
+            })
+            .collect();
+        let layout = crate::parse_layout(
+            include_str!("../tests/fixtures/panels.json"),
+            crate::InputFormat::Auto,
+        )
+        .unwrap();
+        let markdown = crate::convert_layout_to_markdown(&layout, dir.path(), options)
+            .unwrap()
+            .markdown;
+        (markdown, uris)
+    }
+
+    #[test]
+    fn every_panel_of_a_composite_figure_is_kept_with_its_caption() {
+        let (styled, uris) = panels_markdown(&bare_options());
+        let row = "<div style=\"display: flex; flex-wrap: wrap; justify-content: center; \
+                   align-items: flex-start; gap: 1em;\">";
+        let panels: Vec<String> = uris
+            .iter()
+            .zip(["(a) Input", "(b) Mask", "(c) Output"])
+            .map(|(uri, caption)| format!("<img src=\"{}\" alt=\"{}\"", uri, caption))
+            .collect();
+        let mut parts = vec![row];
+        for (panel, caption) in panels
+            .iter()
+            .zip([">(a) Input<", ">(b) Mask<", ">(c) Output<"])
+        {
+            parts.push(panel);
+            parts.push(caption);
+        }
+        parts.push("</div>\n<figcaption");
+        parts.push(">Figure 4. Segmentation of a sample image</figcaption>\n</figure>");
+        assert_in_order(&styled, &parts);
+        assert_eq!(styled.matches("<img ").count(), 3);
+
+        let (gfm, uris) = panels_markdown(&ConvertOptions {
+            flavor: crate::Flavor::Gfm,
+            ..bare_options()
+        });
+        assert_eq!(
+            gfm.trim(),
+            format!(
+                "![(a) Input]({})\n\n*(a) Input*\n\n![(b) Mask]({})\n\n*(b) Mask*\n\n\
+                 ![(c) Output]({})\n\n*(c) Output*\n\n*Figure 4. Segmentation of a sample image*",
+                uris[0], uris[1], uris[2]
+            )
+        );
+    }
+
+    #[test]
+    fn headings_keep_inline_equations_but_anchors_use_plain_text() {
+        let title = |top: f64, spans: serde_json::Value| {
+            let bbox = [50.0, top, 550.0, top + 20.0];
+            json!({"type": "title", "bbox": bbox, "lines": [{"bbox": bbox, "spans": spans}]})
+        };
+        let span = |kind: &str, content: &str| json!({"type": kind, "bbox": [50.0, 0.0, 550.0, 20.0], "content": content});
+        let layout = page_layout(json!([
+            title(
+                50.0,
+                json!([
+                    span("text", "3.2 The "),
+                    span("inline_equation", "\\epsilon"),
+                    span("text", "-greedy policy"),
+                ])
+            ),
+            text_block("text", [50.0, 100.0, 550.0, 120.0], "Body."),
+            title(
+                150.0,
+                json!([
+                    span("text", "第二节 "),
+                    span("inline_equation", "L_2"),
+                    span("text", "范数"),
+                ])
+            ),
+            text_block("text", [50.0, 200.0, 550.0, 220.0], "正文。"),
+        ]));
+        let options = ConvertOptions {
+            flavor: crate::Flavor::Gfm,
+            ..bare_options()
+        };
+        let output =
+            crate::convert_layout_to_markdown(&layout, std::path::Path::new("."), &options)
+                .unwrap();
+        let titles: Vec<&str> = output
+            .outline
+            .iter()
+            .map(|entry| entry.title.as_str())
+            .collect();
+        assert_eq!(
+            titles,
+            ["3.2 The \\epsilon-greedy policy", "第二节 L_2范数"]
+        );
+        assert_in_order(
+            &output.markdown,
+            &[
+                "- [3.2 The \\\\epsilon-greedy policy](#toc-0-3-2-The--epsilon-greedy-policy)",
+                "- [第二节 L_2范数](#toc-0-第二节-L-2范数)",
+                "<a id=\"toc-0-3-2-The--epsilon-greedy-policy\"></a>\n### 3.2 The $\\epsilon$-greedy policy\n",
+                "<a id=\"toc-0-第二节-L-2范数\"></a>\n## 第二节 $L_2$ 范数\n",
+            ],
+        );
+
+        let github = ConvertOptions {
+            anchors: crate::AnchorStyle::Github,
+            ..options
+        };
+        let output =
+            crate::convert_layout_to_markdown(&layout, std::path::Path::new("."), &github).unwrap();
+        let anchors: Vec<&str> = output
+            .outline
+            .iter()
+            .map(|entry| entry.anchor_id.as_str())
+            .collect();
+        assert_eq!(anchors, ["32-the-epsilon-greedy-policy", "第二节-l_2范数"]);
+        assert!(
+            output
+                .markdown
+                .contains("\n### 3.2 The $\\epsilon$-greedy policy\n"),
+            "{}",
+            output.markdown
+        );
+    }
+
+    #[test]
+    fn code_blocks_are_fenced_with_their_language_and_indentation() {
+        let bbox = [50.0, 100.0, 550.0, 300.0];
+        let line = |text: &str| json!({"bbox": bbox, "spans": [{"type": "text", "bbox": bbox, "content": text}]});
+        let layout = page_layout(json!([
+            {
+                "type": "code",
+                "bbox": bbox,
+                "guess_lang": " python ",
+                "blocks": [
+                    {"type": "code_caption", "bbox": bbox, "lines": [line("Listing 1. Setup")]},
+                    {"type": "code_body", "bbox": bbox, "lines": [
+                        line(""),
+                        line("def f(x):"),
+                        line("    # *not* emphasis"),
+                        line(""),
+                        line("    return \"```\""),
+                        line("   "),
+                    ]},
+                ]
+            },
+            {
+                "type": "text",
+                "sub_type": "code",
+                "bbox": bbox,
+                "lines": [line("  ls -l <dir>")]
+            },
+        ]));
+        assert_eq!(
+            crate::testing::markdown(&layout, &bare_options()),
+            "*Listing 1. Setup*\n\n\
+             ````python\n\
+             def f(x):\n    # *not* emphasis\n\n    return \"```\"\n\
+             ````\n\n\
+             ```\n  ls -l <dir>\n```\n\n"
+        );
+    }
+}
@@ -0,0 +1,125 @@
+//! 可替换的块渲染：下游只需重写某一类块的输出，其余块沿用默认实现
+
+use crate::ConvertOptions;
+use crate::analyze::RenderState;
+use crate::error::{ConvertError, Warning};
+use crate::render::{
+    render_image, render_interline_equation, render_table, render_text_block, render_title,
+};
+use crate::types::{Block, TocEntry};
+
+// ==================== 页面上下文 ====================
+
+/// 渲染一个块时可用的页面信息与图片解析
+pub struct PageContext<'r, 'a> {
+    state: &'r mut RenderState<'a>,
+    options: &'r ConvertOptions,
+    page_idx: usize,
+}
+
+impl<'r, 'a> PageContext<'r, 'a> {
+    pub(crate) fn new(
+        state: &'r mut RenderState<'a>,
+        options: &'r ConvertOptions,
+        page_idx: usize,
+    ) -> Self {
+        PageContext {
+            state,
+            options,
+            page_idx,
+        }
+    }
+
+    /// 本次转换的选项（已按输出格式调整）
+    pub fn options(&self) -> &ConvertOptions {
+        self.options
+    }
+
+    /// 页面在输出文档中的页码（从 0 开始，合并多个输入时已加上偏移）
+    pub fn page_idx(&self) -> usize {
+        self.page_idx
+    }
+
+    /// 按 [`ConvertOptions::images`] 解析 `image_path`，返回内联的 data URI 或写入目录后的相对路径；
+    /// 图片缺失（记录警告，开启 `fail_on_missing_images` 时直接报错）或超出
+    /// `max_image_bytes` 而未输出时返回 `None`
+    pub fn resolve_image(&mut self, image_path: &str) -> Result<Option<String>, ConvertError> {
+        self.state
+            .resolve_image(image_path, self.page_idx, self.options)
+            .map(|image| image.src().map(str::to_string))
+    }
+
+    /// 记录一条警告，随转换结果一起返回
+    pub fn warn(&mut self, warning: Warning) {
+        self.state.warnings.push(warning);
+    }
+}
+
+// ==================== 渲染器 ====================
+
+/// 各类版面块的渲染方式；每个方法的默认实现即当前的输出，只重写需要定制的方法即可
+///
+/// 各页并行渲染，因此渲染器需要是 `Sync` 的。重写的方法中仍可调用
+/// [`DefaultRenderer`] 的同名方法，在默认输出的基础上修改。
+///
+/// ```
+/// use mineru_json_to_md::{
+///     Block, BlockRenderer, ConvertOptions, DefaultRenderer, InputFormat, PageContext,
+///     convert_layout_with_renderer, parse_layout,
+/// };
+/// use std::path::Path;
+///
+/// struct Shouting;
+///
+/// impl BlockRenderer for Shouting {
+///     fn render_text(&self, block: &Block, ctx: &mut PageContext) -> String {
+///         DefaultRenderer.render_text(block, ctx).to_uppercase()
+///     }
+/// }
+///
+/// let json = r#"{"pdf_info": [{"page_idx": 0, "page_size": [612, 792], "para_blocks": [
+///     {"type": "text", "bbox": [0, 0, 100, 20],
+///      "lines": [{"spans": [{"type": "text", "content": "hello"}]}]}]}]}"#;
+/// let layout = parse_layout(json, InputFormat::Auto)?;
+/// let output =
+///     convert_layout_with_renderer(&layout, Path::new("."), &ConvertOptions::default(), &Shouting)?;
+/// assert!(output.markdown.contains("HELLO"));
+/// # Ok::<(), mineru_json_to_md::ConvertError>(())
+/// ```
+pub trait BlockRenderer: Sync {
+    /// 标题块；返回的目录条目用于生成目录，为 `None` 时该标题不进入目录。
+    /// 重复的锚点在所有页面渲染完成后统一去重
+    fn render_title(&self, block: &Block, ctx: &mut PageContext) -> (String, Option<TocEntry>) {
+        render_title(block, ctx.state, ctx.options, ctx.page_idx)
+    }
+
+    /// 正文段落，包括代码、旋转的文字与参考文献一节中的段落
+    fn render_text(&self, block: &Block, ctx: &mut PageContext) -> String {
+        render_text_block(block, ctx.state, ctx.options, ctx.page_idx)
+    }
+
+    /// 图片块，连同图注与图片脚注
+    fn render_image(&self, block: &Block, ctx: &mut PageContext) -> Result<String, ConvertError> {
+        render_image(block, ctx.state, ctx.options, ctx.page_idx)
+    }
+
+    /// 表格块，连同表注与表格脚注
+    fn render_table(&self, block: &Block, ctx: &mut PageContext) -> Result<String, ConvertError> {
+        render_table(block, ctx.state, ctx.options, ctx.page_idx)
+    }
+
+    /// 行间公式块
+    fn render_equation(
+        &self,
+        block: &Block,
+        ctx: &mut PageContext,
+    ) -> Result<String, ConvertError> {
+        render_interline_equation(block, ctx.state, ctx.options, ctx.page_idx)
+    }
+}
+
+/// 默认的渲染方式
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DefaultRenderer;
+
+impl BlockRenderer for DefaultRenderer {}
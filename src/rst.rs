@@ -0,0 +1,329 @@
+//! reStructuredText 输出：正文转义、行内标记的边界、章节标题与指令
+
+use std::ops::Range;
+
+// ==================== 转义 ====================
+
+/// 转义 reStructuredText 正文：`\`、`*`、`` ` ``、`|` 与结束一个词的 `_`（会组成引用）前加反斜杠，
+/// 末尾（之后只有空白）的 `::` 写成 `:\:`，以免下一段被当作字面块；
+/// `line_start` 时在会被当作区块标记的行首（标点开头的列表、注释、指令、分节线，`1. `、`a) ` 等编号）加反斜杠
+pub(crate) fn escape_rst(text: &str, line_start: bool) -> String {
+    let mut out = String::with_capacity(text.len() + 8);
+    let ordered = line_start.then(|| ordered_marker(text)).flatten();
+    let mut chars = text.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        let next = chars.peek().map(|&(_, next)| next);
+        let escape = match c {
+            '\\' | '*' | '`' | '|' => true,
+            '_' => !next.is_some_and(char::is_alphanumeric),
+            ':' => text[i + 1..].trim_end().is_empty() && text[..i].ends_with(':'),
+            _ => ordered == Some(i),
+        } || (line_start && i == 0 && c.is_ascii_punctuation());
+        if escape {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+    out
+}
+
+/// 行首的有序列表编号（`1. `、`a) `、`iv. `）中 `.` 或 `)` 的位置
+fn ordered_marker(text: &str) -> Option<usize> {
+    let end = text
+        .find(|c: char| !c.is_ascii_alphanumeric())
+        .unwrap_or(text.len());
+    (end > 0
+        && text[end..].starts_with(['.', ')'])
+        && text[end + 1..].starts_with(char::is_whitespace))
+    .then_some(end)
+}
+
+// ==================== 行内标记 ====================
+
+/// 行内标记之前可以紧接的字符
+fn opens_markup(c: char) -> bool {
+    c.is_whitespace() || "-:/'\"<([{".contains(c)
+}
+
+/// 行内标记之后可以紧接的字符
+fn closes_markup(c: char) -> bool {
+    c.is_whitespace() || "-.,:;!?\\/'\")]}>".contains(c)
+}
+
+/// `out` 是否以行内标记结尾：末尾的 `*`、`` ` ``、`_` 前没有转义用的反斜杠
+fn ends_with_markup(out: &str) -> bool {
+    let Some(before) = out.strip_suffix(['*', '`', '_']) else {
+        return false;
+    };
+    let backslashes = before.len() - before.trim_end_matches('\\').len();
+    backslashes % 2 == 0
+}
+
+/// 追加已转义的文字；紧跟在行内标记之后、又不以空白或标点开头时先加转义空格 `\ `（它不出现在输出中）
+pub(crate) fn push_text(out: &mut String, text: &str) {
+    if ends_with_markup(out) && text.starts_with(|c: char| !closes_markup(c)) {
+        out.push_str("\\ ");
+    }
+    out.push_str(text);
+}
+
+/// 追加行内标记（强调、角色、链接、脚注引用）；前面紧贴文字或另一个标记时先加转义空格 `\ `
+pub(crate) fn push_markup(out: &mut String, markup: &str) {
+    if out.chars().next_back().is_some_and(|c| !opens_markup(c)) {
+        out.push_str("\\ ");
+    }
+    out.push_str(markup);
+}
+
+/// 以 `markup` 替换 `out` 中 `range` 处的文字，按需在两侧加转义空格，用于改写正文中的脚注编号
+pub(crate) fn replace_with_markup(out: &mut String, range: Range<usize>, markup: &str) {
+    let mut replacement = String::new();
+    if out[..range.start]
+        .chars()
+        .next_back()
+        .is_some_and(|c| !opens_markup(c))
+    {
+        replacement.push_str("\\ ");
+    }
+    replacement.push_str(markup);
+    if out[range.end..].starts_with(|c: char| !closes_markup(c)) {
+        replacement.push_str("\\ ");
+    }
+    out.replace_range(range, &replacement);
+}
+
+/// 解释文本（角色的内容、链接文字）：在正文转义之外再转义 `<`，以免被当作内嵌的链接地址
+pub(crate) fn interpreted_text(text: &str) -> String {
+    escape_rst(text, false).replace('<', "\\<")
+}
+
+/// 匿名超链接 `` `text <href>`__ ``；文字与地址相同时只写地址
+pub(crate) fn hyperlink(text: &str, href: &str) -> String {
+    if text == href {
+        format!("`<{}>`__", href)
+    } else {
+        format!("`{} <{}>`__", interpreted_text(text), href)
+    }
+}
+
+// ==================== 标题与区块 ====================
+
+/// 各层级章节标题的下划线字符；reStructuredText 按首次出现的顺序确定层级，
+/// 层级由 [`SectionDepths`] 整理为从第一层开始、不跳级
+const SECTION_ADORNMENTS: [char; 5] = ['=', '-', '~', '^', '"'];
+
+/// 章节标题与下划线；下划线不短于标题的显示宽度，非 ASCII 字符按两列计
+pub(crate) fn section_title(level: usize, title: &str) -> String {
+    section_title_markup(level, &escape_rst(title, true))
+}
+
+/// 同 [`section_title`]，`title` 为已转义的标记（如含有 `:math:` 角色的标题）
+pub(crate) fn section_title_markup(level: usize, title: &str) -> String {
+    let width: usize = title
+        .chars()
+        .map(|c| if c.is_ascii() { 1 } else { 2 })
+        .sum();
+    let adornment = SECTION_ADORNMENTS[level.saturating_sub(1).min(SECTION_ADORNMENTS.len() - 1)];
+    format!("{}\n{}\n", title, adornment.to_string().repeat(width))
+}
+
+/// 按文档顺序整理的章节层级：不深于上一个标题的下一层，第一个标题为第一层
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct SectionDepths {
+    previous: Option<usize>,
+}
+
+impl SectionDepths {
+    /// 标题层级（0–5）对应的章节层级，从 1 开始
+    pub(crate) fn next(&mut self, level: usize) -> usize {
+        let wanted = level.saturating_sub(1);
+        let depth = match self.previous {
+            Some(prev) => wanted.min(prev + 1),
+            None => 0,
+        };
+        self.previous = Some(depth);
+        depth + 1
+    }
+}
+
+/// 超链接目标 `.. _id:`，放在标题、图表之前
+pub(crate) fn label(anchor_id: &str) -> String {
+    format!(".. _{}:\n\n", anchor_id)
+}
+
+/// 指令 `.. name:: argument`；选项与内容缩进三个空格，内容与选项之间空一行
+pub(crate) fn directive(
+    name: &str,
+    argument: &str,
+    options: &[(&str, &str)],
+    content: &str,
+) -> String {
+    let mut out = format!(".. {}::", name);
+    if !argument.is_empty() {
+        out.push(' ');
+        out.push_str(argument);
+    }
+    out.push('\n');
+    for (option, value) in options {
+        let value = value.replace('\n', " ");
+        out.push_str(format!("   :{}: {}", option, value).trim_end());
+        out.push('\n');
+    }
+    if !content.is_empty() {
+        out.push('\n');
+        out.push_str(&indent(content));
+    }
+    out
+}
+
+/// 每个非空行缩进三个空格，末尾保留一个换行
+fn indent(content: &str) -> String {
+    content
+        .trim_end()
+        .lines()
+        .map(|line| {
+            if line.trim().is_empty() {
+                "\n".to_string()
+            } else {
+                format!("   {}\n", line)
+            }
+        })
+        .collect()
+}
+
+/// 引文：缩进的块引用，有多行时为行块 `| line`、保留各行的分行；之前的空注释 `..` 结束前面的列表等结构，
+/// 引文不会成为其中的一部分
+pub(crate) fn block_quote(lines: &[String]) -> String {
+    let content: Vec<String> = if lines.len() > 1 {
+        lines.iter().map(|line| format!("| {}", line)).collect()
+    } else {
+        lines.to_vec()
+    };
+    format!("..\n\n{}\n", indent(&content.join("\n")))
+}
+
+/// 注释 `.. text`，与下一个块之间空一行
+pub(crate) fn comment(text: &str) -> String {
+    let mut lines = text.lines();
+    let mut out = format!(".. {}\n", lines.next().unwrap_or(""));
+    for line in lines {
+        out.push_str(&format!("   {}\n", line).replace("   \n", "\n"));
+    }
+    out.push('\n');
+    out
+}
+
+/// 小字段落，放在带 `small` 类的容器中，用于脚注、参考文献条目、图表脚注等
+pub(crate) fn small_print(text: &str) -> String {
+    directive("container", "small", &[], text)
+}
+
+/// 行间公式 `.. math::`；公式中的空行会把它分成几个公式，一并去掉
+pub(crate) fn math_block(latex: &str) -> String {
+    let latex: Vec<&str> = latex
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .collect();
+    directive("math", "", &[], &latex.join("\n"))
+}
+
+/// 图片 `.. image:: src`，带替代文字
+pub(crate) fn image(src: &str, alt: &str) -> String {
+    directive("image", src, &[("alt", alt)], "")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{bare_options, markdown, page_layout, text_block};
+    use crate::{ConvertOptions, OutputFormat};
+    use serde_json::json;
+
+    #[test]
+    fn rst_markup_characters_are_escaped() {
+        assert_eq!(escape_rst("foo_ and foo_bar", false), "foo\\_ and foo_bar");
+        assert_eq!(
+            escape_rst("*args | `x` \\n", false),
+            "\\*args \\| \\`x\\` \\\\n"
+        );
+        // 段落末尾的 `::` 会让下一段成为字面块，之后有空白时也一样
+        assert_eq!(escape_rst("as follows::", false), "as follows:\\:");
+        assert_eq!(escape_rst("as follows:: ", false), "as follows:\\: ");
+        assert_eq!(escape_rst("a::b", false), "a::b");
+    }
+
+    #[test]
+    fn block_markers_are_escaped_only_at_the_start_of_a_line() {
+        assert_eq!(escape_rst("1. Not a list", true), "1\\. Not a list");
+        assert_eq!(escape_rst("iv) item", true), "iv\\) item");
+        assert_eq!(escape_rst("- dash", true), "\\- dash");
+        assert_eq!(escape_rst(".. not a comment", true), "\\.. not a comment");
+        assert_eq!(escape_rst("- dash", false), "- dash");
+        assert_eq!(escape_rst("1.5 times", true), "1.5 times");
+    }
+
+    #[test]
+    fn section_underlines_cover_the_display_width() {
+        assert_eq!(section_title(1, "Overview"), "Overview\n========\n");
+        assert_eq!(section_title(2, "第一章 A"), "第一章 A\n--------\n");
+        assert_eq!(section_title(9, "Deep"), "Deep\n\"\"\"\"\n");
+
+        // 跳级的标题收紧为下一层，第一个标题总是第一层
+        let mut depths = SectionDepths::default();
+        let levels: Vec<usize> = [2, 4, 3, 1].iter().map(|&l| depths.next(l)).collect();
+        assert_eq!(levels, [1, 2, 3, 1]);
+    }
+
+    #[test]
+    fn markup_next_to_words_gets_escaped_spaces() {
+        let mut out = "and".to_string();
+        push_markup(&mut out, ":math:`x`");
+        push_text(&mut out, "as");
+        push_text(&mut out, ", done");
+        assert_eq!(out, "and\\ :math:`x`\\ as, done");
+
+        assert_eq!(
+            hyperlink("docs <v2>", "https://a.org"),
+            "`docs \\<v2> <https://a.org>`__"
+        );
+        assert_eq!(
+            hyperlink("https://a.org", "https://a.org"),
+            "`<https://a.org>`__"
+        );
+    }
+
+    #[test]
+    fn rst_output_uses_labels_roles_and_footnotes() {
+        let bbox = [50.0, 100.0, 550.0, 120.0];
+        let mut layout = page_layout(json!([
+            text_block("title", [50.0, 50.0, 550.0, 70.0], "第一章 Overview"),
+            {"type": "text", "bbox": bbox, "lines": [{"bbox": bbox, "spans": [
+                {"type": "text", "bbox": bbox, "content": "Uses foo_ and"},
+                {"type": "inline_equation", "bbox": bbox, "content": "x^2"},
+                {"type": "text", "bbox": bbox, "content": "as shown¹ in:: "}
+            ]}]},
+            text_block("text", [50.0, 130.0, 550.0, 150.0], "1. Not a list"),
+        ]));
+        layout.pdf_info[0].discarded_blocks = vec![
+            serde_json::from_value(text_block(
+                "page_footnote",
+                [50.0, 700.0, 550.0, 720.0],
+                "¹ A footnote.",
+            ))
+            .unwrap(),
+        ];
+        let options = ConvertOptions {
+            format: OutputFormat::Rst,
+            ..bare_options()
+        };
+        assert_eq!(
+            markdown(&layout, &options),
+            "- :ref:`第一章 Overview <toc-0-第一章-Overview>` · 第 1 页\n\n\
+             .. _toc-0-第一章-Overview:\n\n\
+             第一章 Overview\n===============\n\n\
+             Uses foo\\_ and\\ :math:`x^2`\\ as shown\\ [#p1-1]_ in:\\:\n\n\
+             1\\. Not a list\n\n\
+             .. [#p1-1] A footnote.\n"
+        );
+    }
+}
@@ -0,0 +1,383 @@
+//! 按章节拆分输出：每个一级（或二级）标题开始一个新文件，另生成链接到各文件的 index；
+//! 或者每页一个文件，另生成 manifest
+
+use std::ops::Range;
+use std::time::Instant;
+
+use serde::{Deserialize, Serialize};
+
+use crate::asciidoc::wrap_asciidoc_document;
+use crate::error::{ConvertError, Warning};
+use crate::json::wrap_json_document;
+use crate::latex::wrap_latex_document;
+use crate::org::wrap_org_document;
+use crate::stats::ConvertStats;
+use crate::text::{AnchorFiles, anchor_position, heading_positions};
+use crate::{
+    ConvertOptions, Document, Flavor, OutputFormat, assemble_pages, back_to_top_link,
+    back_to_top_positions, footer_block, insert_back_to_top, navigation, output_options,
+    render_documents, style_block, wrap_html_document,
+};
+
+// ==================== 拆分选项与结果 ====================
+
+/// 在哪一级标题处开始新文件
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SplitLevel {
+    /// 每个一级标题（以及文档标题）
+    #[default]
+    Heading1,
+    /// 每个一级或二级标题
+    Heading2,
+    /// 每页一个文件（`page_0001.md`……），不生成 index，改为 manifest
+    Page,
+}
+
+/// 拆分出的一个文件
+#[derive(Debug, Clone)]
+pub struct OutputFile {
+    /// 文件名（不含目录），如 `01-introduction.md`
+    pub name: String,
+    pub content: String,
+}
+
+/// 按页拆分时 manifest.json 中的一条记录
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PageFile {
+    /// JSON 中的 page_idx（从 0 开始）
+    pub page_idx: usize,
+    /// 所属输入文档的序号（从 0 开始）
+    pub document: usize,
+    /// 文件名（相对于输出目录）
+    pub file: String,
+    /// 本页中的标题，按出现顺序
+    pub headings: Vec<String>,
+    /// 文件内容的字符数
+    pub chars: usize,
+}
+
+/// 拆分后的各章节文件（第一个为 index）及转换过程中的警告
+#[derive(Debug, Clone)]
+pub struct SplitOutput {
+    pub files: Vec<OutputFile>,
+    /// 按页拆分时每个文件对应的页面，与 `files` 一一对应；按标题拆分时为空
+    pub pages: Vec<PageFile>,
+    pub warnings: Vec<Warning>,
+    /// 输出大小为所有文件之和
+    pub stats: ConvertStats,
+}
+
+// ==================== 拆分 ====================
+
+/// 文件名中的标题部分：小写，非字母数字的字符合并为 `-`
+fn file_slug(title: &str) -> String {
+    let mut slug = String::new();
+    for c in title.chars().flat_map(char::to_lowercase) {
+        if c.is_alphanumeric() {
+            slug.push(c);
+        } else if !slug.is_empty() && !slug.ends_with('-') {
+            slug.push('-');
+        }
+        if slug.chars().count() >= 60 {
+            break;
+        }
+    }
+    let slug = slug.trim_end_matches('-');
+    if slug.is_empty() {
+        "section".to_string()
+    } else {
+        slug.to_string()
+    }
+}
+
+/// 将文档按标题拆分为多个文件，并生成链接到各文件锚点的 `index`
+///
+/// 第一个标题之前的内容写入 `00-frontmatter`；样式块在每个文件中重复，页脚只出现在 index 中。
+/// 锚点在整个文档内唯一，因此在每个文件内也唯一。Obsidian 风格没有锚点，无法按标题拆分。
+///
+/// [`SplitLevel::Page`] 时每页的正文单独成为一个文件，不含样式块、目录、分页线与页脚。
+pub fn convert_documents_split(
+    documents: &[Document],
+    options: &ConvertOptions,
+    level: SplitLevel,
+) -> Result<SplitOutput, ConvertError> {
+    let options = &*output_options(options);
+    let max_level = match level {
+        SplitLevel::Heading1 => 1,
+        SplitLevel::Heading2 => 2,
+        SplitLevel::Page => return split_pages(documents, options),
+    };
+    if options.flavor == Flavor::Obsidian {
+        return Err(ConvertError::UnsupportedFlavor {
+            feature: "splitting",
+            flavor: "obsidian",
+        });
+    }
+    let rendered = render_documents(documents, options, None)?;
+    let started = Instant::now();
+    let (body, page_starts) = assemble_pages(&rendered.pages, options);
+    let extension = options.format.extension();
+
+    // 每个拆分点：在正文中的位置与对应的标题
+    let mut cuts: Vec<(usize, &str)> = Vec::new();
+    // 各锚点在正文中的位置，用于确定它们落在哪个文件里
+    let mut anchors: Vec<(usize, &str)> = Vec::new();
+    for (page, start) in rendered.pages.iter().zip(&page_starts) {
+        let positions = heading_positions(&page.html, &page.toc_entries, options);
+        for (entry, position) in page.toc_entries.iter().zip(positions) {
+            let Some(position) = position else {
+                continue;
+            };
+            anchors.push((start + position, &entry.anchor_id));
+            if entry.level <= max_level {
+                cuts.push((start + position, &entry.title));
+            }
+        }
+        for figure in &page.figures {
+            if let Some(position) = anchor_position(&page.html, &figure.anchor_id, options) {
+                anchors.push((start + position, &figure.anchor_id));
+            }
+        }
+    }
+
+    let mut sections: Vec<(String, Option<&str>, Range<usize>)> = Vec::new();
+    let first_cut = cuts.first().map_or(body.len(), |(position, _)| *position);
+    if !body[..first_cut].trim().is_empty() {
+        sections.push((format!("00-frontmatter.{}", extension), None, 0..first_cut));
+    }
+    for (i, (start, title)) in cuts.iter().enumerate() {
+        let end = cuts.get(i + 1).map_or(body.len(), |(next, _)| *next);
+        sections.push((
+            format!("{:02}-{}.{}", i + 1, file_slug(title), extension),
+            Some(*title),
+            *start..end,
+        ));
+    }
+
+    let mut files = AnchorFiles::new();
+    for (position, anchor_id) in anchors {
+        // 落在第几个拆分点之后；没有前言文件时各章节的下标整体前移一位
+        let after = cuts.partition_point(|(start, _)| *start <= position);
+        let section = if sections.len() > cuts.len() {
+            after
+        } else {
+            after.saturating_sub(1)
+        };
+        if let Some((name, _, _)) = sections.get(section) {
+            files.insert(anchor_id.to_string(), name.clone());
+        }
+    }
+
+    let wrap = |content: String, title: Option<&str>| match options.format {
+        OutputFormat::Html => wrap_html_document(&content, title, options),
+        OutputFormat::Latex if options.latex_standalone => wrap_latex_document(&content),
+        OutputFormat::Json => wrap_json_document(&content),
+        OutputFormat::Asciidoc => wrap_asciidoc_document(&content),
+        OutputFormat::Org => wrap_org_document(&content),
+        OutputFormat::Markdown | OutputFormat::Text | OutputFormat::Latex | OutputFormat::Rst => {
+            content
+        }
+    };
+
+    let mut index = style_block(options);
+    index.push_str(&navigation(
+        &rendered.toc_entries,
+        &rendered.figures,
+        options,
+        &files,
+    ));
+    index.push_str(&footer_block(options));
+    let title = rendered
+        .toc_entries
+        .first()
+        .map(|entry| entry.title.as_str());
+    let mut output = vec![OutputFile {
+        name: format!("index.{}", extension),
+        content: wrap(index, title),
+    }];
+    // 返回目录的链接指向 index 中的目录
+    let back_to_top = back_to_top_link(options, &format!("index.{}", extension));
+    let links = back_to_top_positions(&rendered, &page_starts, options);
+    for (name, title, range) in sections {
+        let mut file = style_block(options);
+        match &back_to_top {
+            Some(link) => file.push_str(&insert_back_to_top(&body, range, &links, link)),
+            None => file.push_str(&body[range]),
+        }
+        output.push(OutputFile {
+            name,
+            content: wrap(file, title),
+        });
+    }
+
+    let mut stats = rendered.stats;
+    for file in &output {
+        stats.count_output(&file.content);
+    }
+    stats.timings.write = started.elapsed();
+    Ok(SplitOutput {
+        files: output,
+        pages: Vec::new(),
+        warnings: rendered.warnings,
+        stats,
+    })
+}
+
+/// 每页一个文件：`page_0001.md` 对应 page_idx 0，合并多个输入时加上文档序号 `02-page_0001.md`
+fn split_pages(
+    documents: &[Document],
+    options: &ConvertOptions,
+) -> Result<SplitOutput, ConvertError> {
+    let rendered = render_documents(documents, options, None)?;
+    let started = Instant::now();
+    let extension = options.format.extension();
+
+    let mut files = Vec::with_capacity(rendered.pages.len());
+    let mut pages = Vec::with_capacity(rendered.pages.len());
+    for page in &rendered.pages {
+        let name = if documents.len() > 1 {
+            format!(
+                "{:02}-page_{:04}.{}",
+                page.document + 1,
+                page.page_idx + 1,
+                extension
+            )
+        } else {
+            format!("page_{:04}.{}", page.page_idx + 1, extension)
+        };
+        let headings: Vec<String> = page
+            .toc_entries
+            .iter()
+            .map(|entry| entry.title.clone())
+            .collect();
+        let mut content = page.html.trim_end().to_string();
+        content.push('\n');
+        match options.format {
+            OutputFormat::Html => {
+                content =
+                    wrap_html_document(&content, headings.first().map(String::as_str), options);
+            }
+            OutputFormat::Latex if options.latex_standalone => {
+                content = wrap_latex_document(&content);
+            }
+            OutputFormat::Json => content = wrap_json_document(&content),
+            OutputFormat::Asciidoc => content = wrap_asciidoc_document(&content),
+            OutputFormat::Org => content = wrap_org_document(&content),
+            _ => {}
+        }
+        pages.push(PageFile {
+            page_idx: page.page_idx,
+            document: page.document,
+            file: name.clone(),
+            headings,
+            chars: content.chars().count(),
+        });
+        files.push(OutputFile { name, content });
+    }
+
+    let mut stats = rendered.stats;
+    for file in &files {
+        stats.count_output(&file.content);
+    }
+    stats.timings.write = started.elapsed();
+    Ok(SplitOutput {
+        files,
+        pages,
+        warnings: rendered.warnings,
+        stats,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{TempDir, image_block, layout, png, text_block};
+    use crate::{ImageOutput, LayoutJson};
+    use serde_json::json;
+
+    fn two_pages() -> LayoutJson {
+        layout(json!({"pdf_info": [
+            {"page_idx": 0, "page_size": [600, 800], "para_blocks": [
+                text_block("title", [50.0, 50.0, 550.0, 80.0], "Results"),
+                image_block([100.0, 100.0, 500.0, 300.0], "figure.png"),
+            ]},
+            {"page_idx": 1, "page_size": [600, 800], "para_blocks": [
+                text_block("text", [50.0, 100.0, 550.0, 200.0], "Second page."),
+            ]}
+        ]}))
+    }
+
+    fn split(dir: &TempDir, options: &ConvertOptions) -> SplitOutput {
+        let layout = two_pages();
+        let documents = [Document {
+            layout: &layout,
+            base_path: dir.path(),
+        }];
+        convert_documents_split(&documents, options, SplitLevel::Page).unwrap()
+    }
+
+    #[test]
+    fn each_page_gets_a_file_and_a_manifest_entry() {
+        let dir = TempDir::new();
+        dir.write("figure.png", &png(4, 3, 0));
+        let output = split(&dir, &ConvertOptions::default());
+
+        let names: Vec<&str> = output.files.iter().map(|file| file.name.as_str()).collect();
+        assert_eq!(names, ["page_0001.md", "page_0002.md"]);
+        for file in &output.files {
+            assert!(!file.content.contains("<style>"));
+            assert!(!file.content.contains(crate::DEFAULT_FOOTER));
+        }
+        assert!(output.files[0].content.contains("## Results"));
+        assert!(
+            output.files[0]
+                .content
+                .contains("src=\"data:image/png;base64,")
+        );
+        assert_eq!(output.files[1].content.trim(), "Second page.");
+
+        let headings: Vec<&[String]> = output.pages.iter().map(|page| &page.headings[..]).collect();
+        assert_eq!(headings, [&["Results".to_string()][..], &[]]);
+        for (page, file) in output.pages.iter().zip(&output.files) {
+            assert_eq!(page.file, file.name);
+            assert_eq!(page.chars, file.content.chars().count());
+        }
+    }
+
+    #[test]
+    fn manifest_round_trips_through_json() {
+        let dir = TempDir::new();
+        let output = split(&dir, &ConvertOptions::default());
+        let manifest = serde_json::to_string_pretty(&output.pages).unwrap();
+        let parsed: Vec<PageFile> = serde_json::from_str(&manifest).unwrap();
+        assert_eq!(parsed, output.pages);
+        let value: serde_json::Value = serde_json::from_str(&manifest).unwrap();
+        assert_eq!(
+            value[1],
+            json!({
+                "page_idx": 1,
+                "document": 0,
+                "file": "page_0002.md",
+                "headings": [],
+                "chars": output.files[1].content.chars().count()
+            })
+        );
+    }
+
+    #[test]
+    fn images_copied_to_a_directory_are_linked_from_the_page_files() {
+        let dir = TempDir::new();
+        dir.write("figure.png", &png(4, 3, 0));
+        std::fs::create_dir_all(dir.path().join("out/img")).unwrap();
+        let options = ConvertOptions {
+            images: ImageOutput::Directory {
+                dir: dir.path().join("out/img"),
+                link_prefix: "img".to_string(),
+            },
+            ..ConvertOptions::default()
+        };
+        let output = split(&dir, &options);
+        assert!(output.files[0].content.contains("src=\"img/figure.png\""));
+        assert!(dir.path().join("out/img/figure.png").exists());
+    }
+}
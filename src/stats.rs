@@ -0,0 +1,263 @@
+//! 转换统计：页数、各类块的数量、图片与输出大小，以及逐页的渲染进度
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::doc::ImageSize;
+
+// ==================== 统计数据 ====================
+
+/// 某一类块的数量：输出了内容的与渲染为空（空标题、被丢弃的旋转文字等）的分开计数
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BlockCount {
+    pub rendered: usize,
+    pub skipped: usize,
+}
+
+/// 识别分数低的一个文本 span
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScoredSpan {
+    /// 页码（从 0 开始，多个文档时接续编号）
+    pub page_idx: usize,
+    pub score: f64,
+    /// span 的文字，过长时截断
+    pub text: String,
+}
+
+/// 像素最多的一张图片
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LargestImage {
+    /// 页码（从 0 开始，多个文档时接续编号）
+    pub page_idx: usize,
+    pub image_path: String,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl LargestImage {
+    pub fn pixels(&self) -> u64 {
+        u64::from(self.width) * u64::from(self.height)
+    }
+}
+
+/// 转换各阶段所用的时间；每次运行都不同，不写入统计文件
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Timings {
+    /// 分析与渲染各页，含图片编码
+    pub render: Duration,
+    /// 读取、编码、缩小与复制图片；并行渲染时为各线程之和，可能超过 `render`
+    pub images: Duration,
+    /// 拼接各页、包装为完整文档并写出；流式写出时为写入目标的时间
+    pub write: Duration,
+}
+
+/// 报告中保留的分数最低的 span 个数
+pub(crate) const LOWEST_SCORES_KEPT: usize = 10;
+
+/// 一次转换的统计数据
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ConvertStats {
+    /// 转换的页数
+    pub pages: usize,
+    /// 按块类型（`title`、`text`、`table`、`interline_equation` 等）统计的正文块
+    pub blocks: BTreeMap<String, BlockCount>,
+    /// 输出的标题按层级计数，层级 0 为文档标题
+    pub headings: BTreeMap<usize, usize>,
+    /// 输出的列表项（含嵌套的子项）
+    pub list_items: usize,
+    /// 内联或写入目录的图片引用（含表格截图和公式截图）
+    pub images_included: usize,
+    /// 文件缺失的图片引用
+    pub images_missing: usize,
+    /// 复用了同一路径或相同内容的图片、无需再次编码或写出的引用
+    pub images_deduplicated: usize,
+    /// 按原样拼接的路径不存在、在后备位置（`images/<文件名>` 等）找到的图片：image_path 与实际使用的文件
+    pub images_relocated: BTreeMap<String, PathBuf>,
+    /// 像素最多的图片引用；尺寸只读取文件头，无法解析文件头的图片不计
+    pub largest_image: Option<LargestImage>,
+    /// 图片引用的像素数之和（缩小后内联的图片按缩小后的尺寸）
+    pub total_pixels: u64,
+    /// 超出 `max_image_bytes` 而缩小后内联的图片（不同图片只计一次，下同）
+    pub images_downscaled: usize,
+    /// 超出 `max_image_bytes` 而写入目录、以路径引用的图片
+    pub images_linked: usize,
+    /// 超出 `max_image_bytes` 而以占位提示代替的图片
+    pub images_omitted: usize,
+    /// 超出 `max_image_bytes` 的图片的原始字节数之和
+    pub oversize_original_bytes: usize,
+    /// 缩小后内联的图片重新编码后的字节数之和
+    pub oversize_embedded_bytes: usize,
+    /// 开启 `table_export` 时导出为 CSV 文件的表格
+    pub tables_exported: usize,
+    /// 开启 `table_export` 时只有截图、没有表格 HTML 而未导出的表格
+    pub tables_image_only: usize,
+    /// 开启 `embed_threshold` 时不超过阈值而内联的图片（不同图片只计一次，下同）
+    pub images_below_threshold: usize,
+    /// 开启 `embed_threshold` 时超过阈值而写入目录、以路径引用的图片
+    pub images_above_threshold: usize,
+    /// 超过阈值的图片以路径引用而不内联所节省的输出大小（字节），按 Base64 编码后的长度计
+    pub threshold_bytes_saved: usize,
+    /// 因选项或输出格式未输出的页眉块
+    pub headers_dropped: usize,
+    /// 因选项或输出格式未输出的页脚块
+    pub footers_dropped: usize,
+    /// 因选项或输出格式未输出的页面脚注块
+    pub footnotes_dropped: usize,
+    /// 开启 `dedupe_blocks` 时作为重复识别去掉的正文块
+    pub duplicate_blocks: usize,
+    /// 按类型统计的其余 discarded_blocks（如 `aside_text`），这些块不输出
+    pub other_discarded: BTreeMap<String, usize>,
+    /// 识别分数低于 `min_confidence`（或 `drop_below`，取较高者）的正文 span
+    pub low_confidence_spans: usize,
+    /// 其中分数最低的至多十个，按分数从低到高
+    pub lowest_scores: Vec<ScoredSpan>,
+    /// 输出的字符数（拆分输出时为所有文件之和）
+    pub characters: usize,
+    /// 输出的字节数（拆分输出时为所有文件之和）
+    pub output_bytes: usize,
+    /// 各阶段的耗时
+    #[serde(skip)]
+    pub timings: Timings,
+}
+
+impl ConvertStats {
+    pub(crate) fn count_block(&mut self, block_type: &str, rendered: bool) {
+        let count = self.blocks.entry(block_type.to_string()).or_default();
+        if rendered {
+            count.rendered += 1;
+        } else {
+            count.skipped += 1;
+        }
+    }
+
+    /// 某一类块的数量；没有出现过时为零
+    pub fn block(&self, block_type: &str) -> BlockCount {
+        self.blocks.get(block_type).copied().unwrap_or_default()
+    }
+
+    /// 合并另一页的统计
+    pub(crate) fn merge(&mut self, other: &ConvertStats) {
+        self.pages += other.pages;
+        for (block_type, count) in &other.blocks {
+            let total = self.blocks.entry(block_type.clone()).or_default();
+            total.rendered += count.rendered;
+            total.skipped += count.skipped;
+        }
+        for (level, count) in &other.headings {
+            *self.headings.entry(*level).or_default() += count;
+        }
+        self.list_items += other.list_items;
+        self.images_included += other.images_included;
+        self.images_missing += other.images_missing;
+        self.images_deduplicated += other.images_deduplicated;
+        self.images_relocated.extend(
+            other
+                .images_relocated
+                .iter()
+                .map(|(image_path, found)| (image_path.clone(), found.clone())),
+        );
+        if let Some(image) = &other.largest_image {
+            self.record_largest(image.clone());
+        }
+        self.total_pixels += other.total_pixels;
+        self.images_downscaled += other.images_downscaled;
+        self.images_linked += other.images_linked;
+        self.images_omitted += other.images_omitted;
+        self.oversize_original_bytes += other.oversize_original_bytes;
+        self.oversize_embedded_bytes += other.oversize_embedded_bytes;
+        self.images_below_threshold += other.images_below_threshold;
+        self.images_above_threshold += other.images_above_threshold;
+        self.threshold_bytes_saved += other.threshold_bytes_saved;
+        self.tables_exported += other.tables_exported;
+        self.tables_image_only += other.tables_image_only;
+        self.headers_dropped += other.headers_dropped;
+        self.footers_dropped += other.footers_dropped;
+        self.footnotes_dropped += other.footnotes_dropped;
+        self.duplicate_blocks += other.duplicate_blocks;
+        for (block_type, count) in &other.other_discarded {
+            *self.other_discarded.entry(block_type.clone()).or_default() += count;
+        }
+        self.low_confidence_spans += other.low_confidence_spans;
+        self.record_scores(other.lowest_scores.iter().cloned());
+        self.characters += other.characters;
+        self.output_bytes += other.output_bytes;
+        self.timings.render += other.timings.render;
+        self.timings.images += other.timings.images;
+        self.timings.write += other.timings.write;
+    }
+
+    /// 记录一次图片引用的尺寸
+    pub(crate) fn record_image_size(&mut self, page_idx: usize, image_path: &str, size: ImageSize) {
+        let image = LargestImage {
+            page_idx,
+            image_path: image_path.to_string(),
+            width: size.width,
+            height: size.height,
+        };
+        self.total_pixels += image.pixels();
+        self.record_largest(image);
+    }
+
+    /// 像素数更多时替换最大的图片；相同时保留先出现的
+    fn record_largest(&mut self, image: LargestImage) {
+        if self
+            .largest_image
+            .as_ref()
+            .is_none_or(|largest| image.pixels() > largest.pixels())
+        {
+            self.largest_image = Some(image);
+        }
+    }
+
+    /// 加入低分的 span，只保留分数最低的 [`LOWEST_SCORES_KEPT`] 个；分数相同时先出现的在前
+    pub(crate) fn record_scores(&mut self, spans: impl IntoIterator<Item = ScoredSpan>) {
+        self.lowest_scores.extend(spans);
+        self.lowest_scores
+            .sort_by(|a, b| a.score.total_cmp(&b.score));
+        self.lowest_scores.truncate(LOWEST_SCORES_KEPT);
+    }
+
+    /// 记录输出文档的大小
+    pub(crate) fn count_output(&mut self, output: &str) {
+        self.characters += output.chars().count();
+        self.output_bytes += output.len();
+    }
+}
+
+// ==================== 进度 ====================
+
+/// 一页渲染完成时报告的进度
+#[derive(Debug, Clone, Copy)]
+pub struct PageProgress<'a> {
+    /// 页码（从 0 开始，多个文档时接续编号）
+    pub page_idx: usize,
+    /// 本次转换要渲染的页数
+    pub total: usize,
+    /// 该页的统计
+    pub stats: &'a ConvertStats,
+}
+
+/// 每页渲染完成时调用的回调；并行渲染时在工作线程中调用，页面完成的顺序不定
+#[derive(Clone)]
+pub struct ProgressCallback(Arc<dyn Fn(PageProgress) + Send + Sync>);
+
+impl ProgressCallback {
+    pub fn new(callback: impl Fn(PageProgress) + Send + Sync + 'static) -> Self {
+        ProgressCallback(Arc::new(callback))
+    }
+
+    pub(crate) fn report(&self, progress: PageProgress) {
+        (self.0)(progress)
+    }
+}
+
+impl fmt::Debug for ProgressCallback {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("ProgressCallback")
+    }
+}
@@ -0,0 +1,855 @@
+//! 流式输出：页面分批渲染后直接写入 `Write`，内存中不保留整篇文档；
+//! 每写完一批页面可以记下检查点，中断后从检查点继续写出
+
+use std::io::Write;
+use std::mem;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+use crate::asciidoc::asciidoc_document_head;
+use crate::error::{ConvertError, Warning, WriteOutput};
+use crate::figures::FigureEntry;
+use crate::image::ImageResolver;
+use crate::json::{JSON_DOCUMENT_TAIL, json_document_head, push_items};
+use crate::latex::{LATEX_DOCUMENT_TAIL, has_cjk, latex_document_head};
+use crate::org::org_document_head;
+use crate::stats::ConvertStats;
+use crate::text::{AnchorFiles, AnchorRegistry, heading_positions};
+use crate::types::{LayoutJson, OutlineEntry, TocEntry};
+use crate::{
+    ConvertOptions, Divider, DividerWriter, Document, HTML_DOCUMENT_TAIL, OutputFormat, PagePlan,
+    RenderedPage, back_to_top_link, dedupe_anchors, footer_block, html_document_head, navigation,
+    output_options, section_rule, style_block,
+};
+
+/// 每批并行渲染的页数；同时留在内存中的只有这一批页面
+const BATCH_PAGES: usize = 16;
+
+/// 两次检查点之间至少相隔的时间。检查点带有此前全部的目录、警告与统计，
+/// 每批都记下时保存的数据随页数平方增长
+const CHECKPOINT_INTERVAL: Duration = Duration::from_secs(2);
+
+/// 按 [`CHECKPOINT_INTERVAL`] 决定写完一批页面后是否记下检查点
+struct CheckpointTimer {
+    interval: Duration,
+    last: Instant,
+}
+
+impl CheckpointTimer {
+    fn new(interval: Duration) -> Self {
+        CheckpointTimer {
+            interval,
+            last: Instant::now(),
+        }
+    }
+
+    fn due(&mut self) -> bool {
+        if self.last.elapsed() < self.interval {
+            return false;
+        }
+        self.last = Instant::now();
+        true
+    }
+}
+
+// ==================== 写出目标 ====================
+
+/// 包装写出目标：末尾的空白暂缓写出，以便纯文本与 LaTeX 输出去掉文末空白；同时统计输出大小
+struct Sink<'w, W: Write> {
+    writer: &'w mut W,
+    state: SinkState,
+}
+
+/// [`Sink`] 中除写出目标以外的状态，随检查点保存
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct SinkState {
+    /// 尚未写出的末尾空白
+    trailing: String,
+    /// 已写出内容的最后一个字节
+    last: Option<u8>,
+    characters: usize,
+    bytes: usize,
+}
+
+impl<'w, W: Write> Sink<'w, W> {
+    fn new(writer: &'w mut W, state: SinkState) -> Self {
+        Sink { writer, state }
+    }
+
+    fn write(&mut self, text: &str) -> Result<(), ConvertError> {
+        let content = text.trim_end();
+        if content.is_empty() {
+            self.state.trailing.push_str(text);
+            return Ok(());
+        }
+        self.flush_trailing()?;
+        self.emit(content)?;
+        self.state.trailing.push_str(&text[content.len()..]);
+        Ok(())
+    }
+
+    fn flush_trailing(&mut self) -> Result<(), ConvertError> {
+        let trailing = mem::take(&mut self.state.trailing);
+        self.emit(&trailing)
+    }
+
+    /// 丢弃末尾的空白，只保留一个换行
+    fn trim_end(&mut self) -> Result<(), ConvertError> {
+        self.state.trailing.clear();
+        self.emit("\n")
+    }
+
+    /// 按字符计的当前位置，包括尚未写出的末尾空白
+    fn position(&self) -> usize {
+        self.state.characters + self.state.trailing.chars().count()
+    }
+
+    fn ends_with_newline(&self) -> bool {
+        match self.state.trailing.as_bytes().last() {
+            Some(&last) => last == b'\n',
+            None => self.state.last == Some(b'\n'),
+        }
+    }
+
+    fn emit(&mut self, text: &str) -> Result<(), ConvertError> {
+        if text.is_empty() {
+            return Ok(());
+        }
+        self.writer
+            .write_all(text.as_bytes())
+            .map_err(ConvertError::Write)?;
+        self.state.characters += text.chars().count();
+        self.state.bytes += text.len();
+        self.state.last = text.as_bytes().last().copied();
+        Ok(())
+    }
+}
+
+// ==================== 正文 ====================
+
+/// 已写出内容、分页线尚未写出的上一页
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct PendingPage {
+    divider: Option<Divider>,
+    document: usize,
+    blank: bool,
+    /// 页面内容结束处在正文中的位置（不计返回目录链接）
+    end: usize,
+}
+
+/// 逐页写出正文，结果与整篇拼接（`assemble_pages`）后再插入返回目录链接
+/// （`insert_back_to_top`）逐字节相同
+///
+/// 一级标题前本页没有其他内容时，链接要放在上一页末尾、分页线之前，
+/// 因此每页的分页线等到下一页渲染完成后才写出。
+struct BodyWriter<'o> {
+    options: &'o ConvertOptions,
+    link: Option<String>,
+    state: BodyState,
+}
+
+/// [`BodyWriter`] 中随检查点保存的状态
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct BodyState {
+    /// 已写出的正文长度，不计返回目录链接
+    assembled: usize,
+    /// 不计链接的正文的最后两个字节，决定没有分页线时补几个换行
+    assembled_tail: Vec<u8>,
+    /// 实际写出的正文的最后两个字节，决定链接前补几个换行
+    written_tail: Vec<u8>,
+    prev_document: Option<usize>,
+    /// 是否已经遇到第一个一级标题；第一个标题之前不插入链接
+    seen_heading: bool,
+    pending: Option<PendingPage>,
+    dividers: DividerWriter,
+    /// 已写出的标题及其位置
+    outline: Vec<OutlineEntry>,
+}
+
+fn update_tail(tail: &mut Vec<u8>, text: &str) {
+    tail.extend_from_slice(&text.as_bytes()[text.len().saturating_sub(2)..]);
+    let excess = tail.len().saturating_sub(2);
+    tail.drain(..excess);
+}
+
+/// 补到以空行结束所需的换行
+fn missing_newlines(tail: &[u8]) -> &'static str {
+    if tail.ends_with(b"\n\n") {
+        ""
+    } else if tail.ends_with(b"\n") {
+        "\n"
+    } else {
+        "\n\n"
+    }
+}
+
+impl<'o> BodyWriter<'o> {
+    fn new(options: &'o ConvertOptions, state: BodyState) -> Self {
+        BodyWriter {
+            options,
+            link: back_to_top_link(options, ""),
+            state,
+        }
+    }
+
+    /// 写出正文内容
+    fn push<W: Write>(&mut self, sink: &mut Sink<W>, text: &str) -> Result<(), ConvertError> {
+        self.state.assembled += text.len();
+        update_tail(&mut self.state.assembled_tail, text);
+        update_tail(&mut self.state.written_tail, text);
+        sink.write(text)
+    }
+
+    /// 在当前位置插入返回目录的链接，链接前至少空一行
+    fn push_link<W: Write>(&mut self, sink: &mut Sink<W>) -> Result<(), ConvertError> {
+        let Some(link) = &self.link else {
+            return Ok(());
+        };
+        let text = format!("{}{}", missing_newlines(&self.state.written_tail), link);
+        update_tail(&mut self.state.written_tail, &text);
+        sink.write(&text)
+    }
+
+    fn push_page<W: Write>(
+        &mut self,
+        sink: &mut Sink<W>,
+        page: &RenderedPage,
+    ) -> Result<(), ConvertError> {
+        // 本页一级标题处的链接：标题前本页除页眉外没有内容时移到上一页末尾
+        let positions = heading_positions(&page.html, &page.toc_entries, self.options);
+        let mut inside = Vec::new();
+        let mut moved = 0;
+        if self.link.is_some() {
+            for (entry, position) in page.toc_entries.iter().zip(&positions) {
+                let Some(position) = position.filter(|_| entry.level <= 1) else {
+                    continue;
+                };
+                if !mem::replace(&mut self.state.seen_heading, true) {
+                    continue;
+                }
+                let before = page.html.get(page.body_start..position).unwrap_or_default();
+                if before.trim().is_empty() && self.state.pending.is_some() {
+                    moved += 1;
+                } else {
+                    inside.push(position);
+                }
+            }
+        }
+        self.finish_page(sink, moved, Some((page.document, page.blank)))?;
+
+        if let Some(separator) = &self.options.chunk_separator
+            && self
+                .state
+                .prev_document
+                .is_some_and(|prev| prev != page.document)
+        {
+            self.push(sink, &format!("{}\n\n", separator))?;
+        }
+        self.state.prev_document = Some(page.document);
+
+        // 依次写到各链接与标题的位置；链接与标题位置相同时先写链接
+        let start = self.state.assembled;
+        let mut stops: Vec<(usize, Option<usize>)> = inside
+            .into_iter()
+            .filter(|&position| start + position > 0)
+            .map(|position| (position, None))
+            .collect();
+        stops.extend(
+            positions
+                .iter()
+                .enumerate()
+                .filter_map(|(i, position)| position.map(|position| (position, Some(i)))),
+        );
+        stops.sort();
+        let mut offsets = vec![None; page.toc_entries.len()];
+        let mut cursor = 0;
+        for (position, heading) in stops {
+            self.push(sink, &page.html[cursor..position])?;
+            cursor = position;
+            match heading {
+                Some(i) => offsets[i] = Some(sink.position()),
+                None => self.push_link(sink)?,
+            }
+        }
+        self.push(sink, &page.html[cursor..])?;
+        self.state.outline.extend(
+            page.toc_entries
+                .iter()
+                .zip(offsets)
+                .map(|(entry, offset)| OutlineEntry::new(entry, offset)),
+        );
+        self.state.pending = Some(PendingPage {
+            divider: page.divider.clone(),
+            document: page.document,
+            blank: page.blank,
+            end: self.state.assembled,
+        });
+        Ok(())
+    }
+
+    /// 写出上一页末尾的 `links` 个链接与分页线；`next` 为下一页的 `(document, blank)`
+    fn finish_page<W: Write>(
+        &mut self,
+        sink: &mut Sink<W>,
+        links: usize,
+        next: Option<(usize, bool)>,
+    ) -> Result<(), ConvertError> {
+        let Some(page) = self.state.pending.take() else {
+            return Ok(());
+        };
+        if page.end > 0 {
+            for _ in 0..links {
+                self.push_link(sink)?;
+            }
+        }
+        let divider = self.state.dividers.divider(
+            page.divider.as_ref(),
+            (page.document, page.blank),
+            next,
+            self.options,
+        );
+        if let Some(divider) = &divider {
+            self.push(sink, divider)?;
+        } else if self.state.assembled > 0 {
+            // 没有分页线时至少保留一个空行，避免相邻两页的段落连在一起
+            let missing = missing_newlines(&self.state.assembled_tail);
+            self.push(sink, missing)?;
+        }
+        Ok(())
+    }
+
+    /// 最后一个章节在最后一页的内容之后结束
+    fn finish<W: Write>(&mut self, sink: &mut Sink<W>) -> Result<(), ConvertError> {
+        let links = usize::from(self.state.seen_heading);
+        self.finish_page(sink, links, None)
+    }
+}
+
+// ==================== 转换 ====================
+
+/// 第一遍收集到的目录条目与图表，以及正文中是否有中日韩文字；第一遍中断时随检查点保存
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct OutlineState {
+    /// 已渲染的页数
+    pages: usize,
+    anchors: AnchorRegistry,
+    toc_entries: Vec<TocEntry>,
+    figures: Vec<FigureEntry>,
+    /// 已渲染的页面中有中日韩文字，LaTeX 独立文档据此选择导言区
+    #[serde(default)]
+    cjk: bool,
+}
+
+/// 第一遍：不读取图片，只渲染出去重后的目录条目与图表；从 `state` 记录的页数之后继续
+fn outline(
+    documents: &[Document],
+    options: &ConvertOptions,
+    mut state: OutlineState,
+    timer: &mut CheckpointTimer,
+    checkpoint: &mut dyn FnMut(&Checkpoint) -> Result<(), ConvertError>,
+) -> Result<OutlineState, ConvertError> {
+    let options = ConvertOptions {
+        progress: None,
+        ..options.clone()
+    };
+    let plan = PagePlan::new(documents, &options, Some(ImageResolver::outline()))?;
+    check_resume(state.pages, plan.len())?;
+    for start in (state.pages..plan.len()).step_by(BATCH_PAGES) {
+        let end = (start + BATCH_PAGES).min(plan.len());
+        let mut pages = plan.render(start..end, &options, None)?;
+        dedupe_anchors(&mut pages, &mut state.anchors, &options);
+        for page in pages {
+            state.cjk |= has_cjk(&page.html);
+            state.toc_entries.extend(page.toc_entries);
+            state.figures.extend(page.figures);
+        }
+        state.pages = end;
+        if end < plan.len() && timer.due() {
+            checkpoint(&Checkpoint {
+                pages: 0,
+                bytes: 0,
+                sink: SinkState::default(),
+                body: BodyState::default(),
+                first_item: true,
+                anchors: AnchorRegistry::default(),
+                warnings: Vec::new(),
+                stats: ConvertStats::default(),
+                outline: Some(state.clone()),
+            })?;
+        }
+    }
+    Ok(state)
+}
+
+/// 流式写出的检查点：前 `pages` 页已完整写出，从这里继续写出所需的内部状态随之保存
+///
+/// 由 [`convert_documents_to_writer_resumable`] 在写完一批页面、写出目标已 `flush` 之后给出，
+/// 两次之间至少相隔两秒；收集目录的第一遍中也会给出（此时 `pages` 与 `bytes` 均为 0）。
+/// 可以序列化保存，中断后连同写出目标的前 `bytes` 个字节一起用于继续写出。
+/// 只对同样的输入与选项有效，调用方需自行确认两者没有变化。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Checkpoint {
+    /// 已完整写出的页数
+    pub pages: usize,
+    /// 已写入目标的字节数；此后写入的内容不属于检查点，继续前应截去
+    pub bytes: usize,
+    sink: SinkState,
+    body: BodyState,
+    /// JSON 输出中尚未写出任何条目
+    first_item: bool,
+    anchors: AnchorRegistry,
+    warnings: Vec<Warning>,
+    stats: ConvertStats,
+    /// 第一遍尚未完成时已收集的目录；此时正文还没有开始写出
+    #[serde(default)]
+    outline: Option<OutlineState>,
+}
+
+impl Checkpoint {
+    /// 是否在收集目录的第一遍中记下，即正文还没有开始写出
+    pub fn in_outline(&self) -> bool {
+        self.outline.is_some()
+    }
+}
+
+/// 将解析后的 layout.json 转换后直接写入 `writer`
+///
+/// 输出与 [`crate::convert_layout_to_markdown`] 逐字节相同，但页面分批渲染、写出，
+/// 内存中只保留一批页面，适合内联大量图片的文档。目录在正文之前，因此先不读取图片
+/// 渲染一遍收集目录条目。出错时 `writer` 中可能已写入部分内容。
+pub fn convert_to_writer(
+    layout_json: &LayoutJson,
+    base_path: &Path,
+    writer: &mut impl Write,
+    options: &ConvertOptions,
+) -> Result<WriteOutput, ConvertError> {
+    convert_documents_to_writer(
+        &[Document {
+            layout: layout_json,
+            base_path,
+        }],
+        writer,
+        options,
+    )
+}
+
+/// 与 [`convert_to_writer`] 相同，但像 [`crate::convert_documents`] 一样合并多个文档
+pub fn convert_documents_to_writer(
+    documents: &[Document],
+    writer: &mut impl Write,
+    options: &ConvertOptions,
+) -> Result<WriteOutput, ConvertError> {
+    convert_documents_to_writer_resumable(documents, writer, options, None, &mut |_| Ok(()))
+}
+
+/// 与 [`convert_documents_to_writer`] 相同，但写完一批页面后不时调用 `checkpoint`（见 [`Checkpoint`]）；
+/// `checkpoint` 返回错误时转换随之中止
+///
+/// 给出 `resume` 时从检查点之后的一页继续：`writer` 应位于检查点记录的 `bytes` 处，
+/// 文档开头、目录与之前各页不再写出，文末的页脚等照常在最后一页之后写出。
+/// 统计数据中按整篇文档计的图片复用、缩小等数目只包括继续之后的页面。
+pub fn convert_documents_to_writer_resumable(
+    documents: &[Document],
+    writer: &mut impl Write,
+    options: &ConvertOptions,
+    resume: Option<Checkpoint>,
+    checkpoint: &mut dyn FnMut(&Checkpoint) -> Result<(), ConvertError>,
+) -> Result<WriteOutput, ConvertError> {
+    write_resumable(
+        documents,
+        writer,
+        options,
+        resume,
+        CheckpointTimer::new(CHECKPOINT_INTERVAL),
+        checkpoint,
+    )
+}
+
+fn write_resumable(
+    documents: &[Document],
+    writer: &mut impl Write,
+    options: &ConvertOptions,
+    resume: Option<Checkpoint>,
+    mut timer: CheckpointTimer,
+    checkpoint: &mut dyn FnMut(&Checkpoint) -> Result<(), ConvertError>,
+) -> Result<WriteOutput, ConvertError> {
+    let options = &*output_options(options);
+    if options.format == OutputFormat::Json {
+        return write_json(documents, writer, options, resume, timer, checkpoint);
+    }
+    let started = Instant::now();
+    let mut render = Duration::ZERO;
+    let plan = PagePlan::new(documents, options, None)?;
+    // 没有任何页面时不写样式块与页脚，与 convert_documents 相同
+    let decorated = plan.len() > 0;
+
+    let (resume, outlined) = match resume {
+        Some(Checkpoint {
+            outline: Some(outlined),
+            ..
+        }) => (None, outlined),
+        resume => (resume, OutlineState::default()),
+    };
+    let (start_page, mut sink, mut body, mut anchors, mut warnings, mut stats) = match resume {
+        Some(resume) => {
+            check_resume(resume.pages, plan.len())?;
+            (
+                resume.pages,
+                Sink::new(writer, resume.sink),
+                BodyWriter::new(options, resume.body),
+                resume.anchors,
+                resume.warnings,
+                resume.stats,
+            )
+        }
+        None => {
+            // 目录需要预先分析一遍各页，计入渲染的时间
+            let outlined = outline(documents, options, outlined, &mut timer, checkpoint)?;
+            render = started.elapsed();
+            let mut sink = Sink::new(writer, SinkState::default());
+            write_head(
+                &mut sink,
+                &outlined.toc_entries,
+                &outlined.figures,
+                outlined.cjk,
+                decorated,
+                options,
+            )?;
+            (
+                0,
+                sink,
+                BodyWriter::new(options, BodyState::default()),
+                AnchorRegistry::default(),
+                Vec::new(),
+                ConvertStats::default(),
+            )
+        }
+    };
+
+    for start in (start_page..plan.len()).step_by(BATCH_PAGES) {
+        let end = (start + BATCH_PAGES).min(plan.len());
+        let rendering = Instant::now();
+        let mut pages = plan.render(start..end, options, None)?;
+        render += rendering.elapsed();
+        dedupe_anchors(&mut pages, &mut anchors, options);
+        for mut page in pages {
+            body.push_page(&mut sink, &page)?;
+            warnings.append(&mut page.warnings);
+            stats.merge(&page.stats);
+        }
+        if end < plan.len() && timer.due() {
+            sink.writer.flush().map_err(ConvertError::Write)?;
+            checkpoint(&Checkpoint {
+                pages: end,
+                bytes: sink.state.bytes,
+                sink: sink.state.clone(),
+                body: body.state.clone(),
+                first_item: false,
+                anchors: anchors.clone(),
+                warnings: warnings.clone(),
+                stats: stats.clone(),
+                outline: None,
+            })?;
+        }
+    }
+    body.finish(&mut sink)?;
+    if decorated {
+        sink.write(&footer_block(options))?;
+    } else {
+        warnings.push(Warning::EmptyDocument);
+    }
+
+    match options.format {
+        OutputFormat::Markdown => sink.flush_trailing()?,
+        OutputFormat::Html => {
+            sink.flush_trailing()?;
+            if !sink.ends_with_newline() {
+                sink.emit("\n")?;
+            }
+            sink.emit(HTML_DOCUMENT_TAIL)?;
+        }
+        // 各页之间保留的空行在文末去掉
+        OutputFormat::Text | OutputFormat::Asciidoc | OutputFormat::Rst | OutputFormat::Org => {
+            sink.trim_end()?
+        }
+        OutputFormat::Latex => {
+            sink.trim_end()?;
+            if options.latex_standalone {
+                sink.emit(LATEX_DOCUMENT_TAIL)?;
+            }
+        }
+        OutputFormat::Json => unreachable!("JSON output is written by write_json"),
+    }
+    sink.writer.flush().map_err(ConvertError::Write)?;
+
+    plan.record_images(&mut stats);
+    stats.characters += sink.state.characters;
+    stats.output_bytes += sink.state.bytes;
+    stats.timings.render = render;
+    stats.timings.write = started.elapsed().saturating_sub(render);
+    Ok(WriteOutput {
+        warnings,
+        stats,
+        outline: body.state.outline,
+    })
+}
+
+/// 检查点记录的页数不能超出文档
+fn check_resume(pages: usize, page_count: usize) -> Result<(), ConvertError> {
+    if pages > page_count {
+        return Err(ConvertError::CheckpointOutOfRange { pages, page_count });
+    }
+    Ok(())
+}
+
+/// 文档开头、样式块与目录；继续写出时已在检查点之前写出。
+/// `cjk` 为正文中有中日韩文字，LaTeX 独立文档据此选择导言区
+fn write_head<W: Write>(
+    sink: &mut Sink<W>,
+    toc_entries: &[TocEntry],
+    figures: &[FigureEntry],
+    cjk: bool,
+    decorated: bool,
+    options: &ConvertOptions,
+) -> Result<(), ConvertError> {
+    // 文档开头不参与文末空白的去除，直接写出
+    match options.format {
+        OutputFormat::Html => {
+            let title = toc_entries.first().map(|entry| entry.title.as_str());
+            sink.emit(&html_document_head(title, options))?;
+        }
+        OutputFormat::Latex if options.latex_standalone => sink.emit(&latex_document_head(cjk))?,
+        OutputFormat::Asciidoc => sink.emit(&asciidoc_document_head())?,
+        OutputFormat::Org => sink.emit(&org_document_head())?,
+        OutputFormat::Markdown
+        | OutputFormat::Text
+        | OutputFormat::Latex
+        | OutputFormat::Json
+        | OutputFormat::Rst => {}
+    }
+    let mut head = String::new();
+    if decorated {
+        head.push_str(&style_block(options));
+        head.push_str(&navigation(
+            toc_entries,
+            figures,
+            options,
+            &AnchorFiles::new(),
+        ));
+    }
+    // 添加分隔线；前面既没有样式块也没有目录时直接从正文开始
+    if !head.is_empty() {
+        head.push_str(&section_rule(options));
+    }
+    sink.write(&head)
+}
+
+/// JSON 输出没有目录，也不需要调整空白，各页的条目渲染后直接写出
+fn write_json(
+    documents: &[Document],
+    writer: &mut impl Write,
+    options: &ConvertOptions,
+    resume: Option<Checkpoint>,
+    mut timer: CheckpointTimer,
+    checkpoint: &mut dyn FnMut(&Checkpoint) -> Result<(), ConvertError>,
+) -> Result<WriteOutput, ConvertError> {
+    let started = Instant::now();
+    let mut render = Duration::ZERO;
+    let plan = PagePlan::new(documents, options, None)?;
+    let (start_page, mut sink, mut first, mut anchors, mut outline, mut warnings, mut stats) =
+        match resume {
+            Some(resume) => {
+                check_resume(resume.pages, plan.len())?;
+                (
+                    resume.pages,
+                    Sink::new(writer, resume.sink),
+                    resume.first_item,
+                    resume.anchors,
+                    resume.body.outline,
+                    resume.warnings,
+                    resume.stats,
+                )
+            }
+            None => {
+                let mut sink = Sink::new(writer, SinkState::default());
+                sink.emit(&json_document_head())?;
+                (
+                    0,
+                    sink,
+                    true,
+                    AnchorRegistry::default(),
+                    Vec::new(),
+                    Vec::new(),
+                    ConvertStats::default(),
+                )
+            }
+        };
+    for start in (start_page..plan.len()).step_by(BATCH_PAGES) {
+        let end = (start + BATCH_PAGES).min(plan.len());
+        let rendering = Instant::now();
+        let mut pages = plan.render(start..end, options, None)?;
+        render += rendering.elapsed();
+        dedupe_anchors(&mut pages, &mut anchors, options);
+        for mut page in pages {
+            // JSON 输出中的条目不保留正文的位置
+            outline.extend(
+                page.toc_entries
+                    .iter()
+                    .map(|entry| OutlineEntry::new(entry, None)),
+            );
+            let mut items = String::new();
+            push_items(&mut items, &page.html, &mut first);
+            sink.emit(&items)?;
+            warnings.append(&mut page.warnings);
+            stats.merge(&page.stats);
+        }
+        if end < plan.len() && timer.due() {
+            sink.writer.flush().map_err(ConvertError::Write)?;
+            checkpoint(&Checkpoint {
+                pages: end,
+                bytes: sink.state.bytes,
+                sink: sink.state.clone(),
+                body: BodyState {
+                    outline: outline.clone(),
+                    ..BodyState::default()
+                },
+                first_item: first,
+                anchors: anchors.clone(),
+                warnings: warnings.clone(),
+                stats: stats.clone(),
+                outline: None,
+            })?;
+        }
+    }
+    sink.emit(JSON_DOCUMENT_TAIL)?;
+    if plan.len() == 0 {
+        warnings.push(Warning::EmptyDocument);
+    }
+    sink.writer.flush().map_err(ConvertError::Write)?;
+
+    plan.record_images(&mut stats);
+    stats.characters += sink.state.characters;
+    stats.output_bytes += sink.state.bytes;
+    stats.timings.render = render;
+    stats.timings.write = started.elapsed().saturating_sub(render);
+    Ok(WriteOutput {
+        warnings,
+        stats,
+        outline,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::convert_documents;
+    use crate::testing::{layout, text_block};
+    use serde_json::json;
+
+    /// 每页一个标题与一段正文的文档
+    fn long_layout(pages: usize) -> LayoutJson {
+        let pages: Vec<_> = (0..pages)
+            .map(|page_idx| {
+                json!({
+                    "page_idx": page_idx,
+                    "page_size": [600, 800],
+                    "para_blocks": [
+                        text_block("title", [50.0, 50.0, 550.0, 80.0], &format!("Chapter {}", page_idx)),
+                        text_block("text", [50.0, 100.0, 550.0, 200.0], "Body text."),
+                    ]
+                })
+            })
+            .collect();
+        layout(json!({ "pdf_info": pages }))
+    }
+
+    /// 记下第一个满足 `pick` 的检查点后中断，再从该检查点继续；返回继续前后拼接的输出
+    fn interrupt_and_resume(
+        documents: &[Document],
+        options: &ConvertOptions,
+        pick: fn(&Checkpoint) -> bool,
+    ) -> String {
+        let mut written = Vec::new();
+        let mut saved = None;
+        let interrupted = write_resumable(
+            documents,
+            &mut written,
+            options,
+            None,
+            CheckpointTimer::new(Duration::ZERO),
+            &mut |checkpoint| {
+                if !pick(checkpoint) {
+                    return Ok(());
+                }
+                saved = Some(serde_json::to_string(checkpoint).unwrap());
+                Err(ConvertError::Write(std::io::Error::other("interrupted")))
+            },
+        );
+        assert!(interrupted.is_err());
+        let checkpoint: Checkpoint = serde_json::from_str(&saved.unwrap()).unwrap();
+        written.truncate(checkpoint.bytes);
+        write_resumable(
+            documents,
+            &mut written,
+            options,
+            Some(checkpoint),
+            CheckpointTimer::new(Duration::ZERO),
+            &mut |_| Ok(()),
+        )
+        .unwrap();
+        String::from_utf8(written).unwrap()
+    }
+
+    #[test]
+    fn resuming_from_a_body_checkpoint_matches_a_full_conversion() {
+        let layout = long_layout(40);
+        let documents = [Document {
+            layout: &layout,
+            base_path: Path::new("."),
+        }];
+        let options = ConvertOptions::default();
+        let expected = convert_documents(&documents, &options).unwrap().markdown;
+        let resumed = interrupt_and_resume(&documents, &options, |checkpoint| {
+            !checkpoint.in_outline() && checkpoint.pages == 16
+        });
+        assert_eq!(resumed, expected);
+    }
+
+    #[test]
+    fn resuming_from_an_outline_checkpoint_matches_a_full_conversion() {
+        let layout = long_layout(40);
+        let documents = [Document {
+            layout: &layout,
+            base_path: Path::new("."),
+        }];
+        let options = ConvertOptions::default();
+        let expected = convert_documents(&documents, &options).unwrap().markdown;
+        let resumed = interrupt_and_resume(&documents, &options, Checkpoint::in_outline);
+        assert_eq!(resumed, expected);
+    }
+
+    #[test]
+    fn checkpoints_are_spaced_by_the_interval() {
+        let layout = long_layout(40);
+        let documents = [Document {
+            layout: &layout,
+            base_path: Path::new("."),
+        }];
+        let mut count = 0;
+        write_resumable(
+            &documents,
+            &mut Vec::new(),
+            &ConvertOptions::default(),
+            None,
+            CheckpointTimer::new(Duration::from_secs(3600)),
+            &mut |_| {
+                count += 1;
+                Ok(())
+            },
+        )
+        .unwrap();
+        assert_eq!(count, 0);
+    }
+}
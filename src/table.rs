@@ -0,0 +1,589 @@
+//! 表格：把 MinerU 识别出的表格 HTML 转换为 GitHub 风格的管道表格、LaTeX 的 `tabular`、
+//! AsciiDoc 的 `|===` 表格或 Org 表格，或导出为 CSV 文件
+
+use std::borrow::Cow;
+use std::fs;
+use std::path::PathBuf;
+
+use serde::Serialize;
+
+use crate::asciidoc::escape_asciidoc;
+use crate::error::{ConvertError, Warning};
+use crate::latex::escape_latex;
+use crate::org::escape_org;
+use crate::text::escape_markdown;
+
+// ==================== HTML 解析 ====================
+
+/// 解析出的一个单元格
+struct Cell {
+    text: String,
+    colspan: usize,
+    rowspan: usize,
+}
+
+/// 跨行、跨列不超过该值时按重复单元格展开，更大的合并单元格退回 HTML
+const MAX_SPAN: usize = 20;
+
+/// 读取标签属性中的正整数，如 `colspan="2"`
+fn span_attr(tag: &str, name: &str) -> usize {
+    let lower = tag.to_ascii_lowercase();
+    let Some(start) = lower.find(name) else {
+        return 1;
+    };
+    let rest = lower[start + name.len()..].trim_start();
+    let Some(rest) = rest.strip_prefix('=') else {
+        return 1;
+    };
+    let digits: String = rest
+        .trim_start()
+        .trim_start_matches(['"', '\''])
+        .chars()
+        .take_while(char::is_ascii_digit)
+        .collect();
+    digits.parse().ok().filter(|&n| n > 0).unwrap_or(1)
+}
+
+/// 解码常见的 HTML 实体
+fn decode_entities(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        out.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let decoded = rest.find(';').filter(|&end| end <= 10).and_then(|end| {
+            let entity = &rest[1..end];
+            let c = match entity {
+                "amp" => Some('&'),
+                "lt" => Some('<'),
+                "gt" => Some('>'),
+                "quot" => Some('"'),
+                "apos" | "#39" => Some('\''),
+                "nbsp" => Some(' '),
+                _ => entity
+                    .strip_prefix("#x")
+                    .or_else(|| entity.strip_prefix("#X"))
+                    .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+                    .or_else(|| entity.strip_prefix('#').and_then(|dec| dec.parse().ok()))
+                    .and_then(char::from_u32),
+            };
+            c.map(|c| (c, end))
+        });
+        match decoded {
+            Some((c, end)) => {
+                out.push(c);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                out.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+/// 把表格 HTML 切分为行和单元格；遇到嵌套表格返回错误
+fn parse_rows(html: &str) -> Result<Vec<Vec<Cell>>, &'static str> {
+    let mut rows: Vec<Vec<Cell>> = Vec::new();
+    let mut cell: Option<Cell> = None;
+    let mut depth = 0;
+    let mut rest = html;
+
+    while !rest.is_empty() {
+        let Some(start) = rest.find('<') else {
+            if let Some(cell) = &mut cell {
+                cell.text.push_str(rest);
+            }
+            break;
+        };
+        if let Some(cell) = &mut cell {
+            cell.text.push_str(&rest[..start]);
+        }
+        let Some(end) = rest[start..].find('>').map(|end| start + end) else {
+            break;
+        };
+        let tag = &rest[start + 1..end];
+        rest = &rest[end + 1..];
+
+        let closing = tag.starts_with('/');
+        let name: String = tag
+            .trim_start_matches('/')
+            .chars()
+            .take_while(char::is_ascii_alphanumeric)
+            .collect::<String>()
+            .to_ascii_lowercase();
+        match (name.as_str(), closing) {
+            ("table", false) => {
+                depth += 1;
+                if depth > 1 {
+                    return Err("nested table");
+                }
+            }
+            ("table", true) => depth -= 1,
+            ("tr", false) => {
+                if let Some(cell) = cell.take()
+                    && let Some(row) = rows.last_mut()
+                {
+                    row.push(cell);
+                }
+                rows.push(Vec::new());
+            }
+            ("td" | "th", false) => {
+                if rows.is_empty() {
+                    rows.push(Vec::new());
+                }
+                if let Some(cell) = cell.take()
+                    && let Some(row) = rows.last_mut()
+                {
+                    row.push(cell);
+                }
+                cell = Some(Cell {
+                    text: String::new(),
+                    colspan: span_attr(tag, "colspan"),
+                    rowspan: span_attr(tag, "rowspan"),
+                });
+            }
+            ("td" | "th", true) | ("tr", true) => {
+                if let Some(cell) = cell.take()
+                    && let Some(row) = rows.last_mut()
+                {
+                    row.push(cell);
+                }
+            }
+            // 单元格内的换行与段落以空格代替
+            ("br" | "p" | "div", _) => {
+                if let Some(cell) = &mut cell {
+                    cell.text.push(' ');
+                }
+            }
+            _ => {}
+        }
+    }
+    if let Some(cell) = cell.take()
+        && let Some(row) = rows.last_mut()
+    {
+        row.push(cell);
+    }
+    rows.retain(|row| !row.is_empty());
+    Ok(rows)
+}
+
+// ==================== 管道表格 ====================
+
+/// 单元格中的纯文字：解码实体并合并空白
+fn plain_cell_text(raw: &str) -> String {
+    decode_entities(raw)
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// 管道表格的单元格文字：转义管道符；`<` 保持转义，避免解码后变成 HTML 标签。
+/// `escape` 时其余 Markdown 字符一并转义
+fn cell_text(raw: &str, escape: bool) -> String {
+    let text = plain_cell_text(raw);
+    if escape {
+        text.split('<')
+            .map(|part| escape_markdown(part, false))
+            .collect::<Vec<_>>()
+            .join("&lt;")
+    } else {
+        text.replace('|', "\\|").replace('<', "&lt;")
+    }
+}
+
+/// 展开合并单元格，得到每行列数相同的网格，单元格文字由 `cell_text` 转换；
+/// 无法可靠展开时返回原因
+fn build_grid(
+    rows: Vec<Vec<Cell>>,
+    cell_text: impl Fn(&str) -> String,
+) -> Result<Vec<Vec<String>>, &'static str> {
+    let mut grid: Vec<Vec<Option<String>>> = Vec::new();
+    for (r, row) in rows.into_iter().enumerate() {
+        if grid.len() <= r {
+            grid.push(Vec::new());
+        }
+        let mut col = 0;
+        for cell in row {
+            if cell.colspan > MAX_SPAN || cell.rowspan > MAX_SPAN {
+                return Err("cell span too large");
+            }
+            let text = cell_text(&cell.text);
+            while grid[r].get(col).is_some_and(Option::is_some) {
+                col += 1;
+            }
+            for dr in 0..cell.rowspan {
+                while grid.len() <= r + dr {
+                    grid.push(Vec::new());
+                }
+                let target = &mut grid[r + dr];
+                if target.len() < col + cell.colspan {
+                    target.resize(col + cell.colspan, None);
+                }
+                for slot in &mut target[col..col + cell.colspan] {
+                    if slot.is_some() {
+                        return Err("overlapping cell spans");
+                    }
+                    *slot = Some(text.clone());
+                }
+            }
+            col += cell.colspan;
+        }
+    }
+
+    let width = grid.first().map_or(0, Vec::len);
+    if width == 0 {
+        return Err("empty table");
+    }
+    grid.into_iter()
+        .map(|row| {
+            if row.len() != width || row.iter().any(Option::is_none) {
+                return Err("rows have different numbers of cells");
+            }
+            Ok(row.into_iter().flatten().collect())
+        })
+        .collect()
+}
+
+/// 将表格 HTML 转换为管道表格，第一行作为表头；合并单元格按重复的值展开
+///
+/// 嵌套表格、行列数对不齐等无法转换的情况返回原因，由调用方退回 HTML。
+pub(crate) fn html_table_to_markdown(html: &str, escape: bool) -> Result<String, &'static str> {
+    let grid = build_grid(parse_rows(html)?, |raw| cell_text(raw, escape))?;
+    let width = grid[0].len();
+
+    let mut markdown = String::new();
+    for (i, row) in grid.iter().enumerate() {
+        markdown.push_str(&format!("| {} |\n", row.join(" | ")));
+        if i == 0 {
+            markdown.push_str(&format!("|{}\n", " --- |".repeat(width)));
+        }
+    }
+    Ok(markdown)
+}
+
+// ==================== LaTeX 表格 ====================
+
+/// 将表格 HTML 转换为 `tabular`，第一行之后加一条横线；合并单元格同样按重复的值展开
+pub(crate) fn html_table_to_latex(html: &str) -> Result<String, &'static str> {
+    let grid = build_grid(parse_rows(html)?, |raw| escape_latex(&plain_cell_text(raw)))?;
+    let width = grid[0].len();
+
+    let mut latex = format!("\\begin{{tabular}}{{{}}}\n\\hline\n", "l".repeat(width));
+    for (i, row) in grid.iter().enumerate() {
+        latex.push_str(&format!("{} \\\\\n", row.join(" & ")));
+        if i == 0 {
+            latex.push_str("\\hline\n");
+        }
+    }
+    latex.push_str("\\hline\n\\end{tabular}");
+    Ok(latex)
+}
+
+// ==================== AsciiDoc 表格 ====================
+
+/// 将表格 HTML 转换为 `|===` 表格，第一行作为表头；合并单元格同样按重复的值展开
+pub(crate) fn html_table_to_asciidoc(html: &str) -> Result<String, &'static str> {
+    let grid = build_grid(parse_rows(html)?, |raw| {
+        escape_asciidoc(&plain_cell_text(raw), false).replace('|', "\\|")
+    })?;
+
+    let mut asciidoc = String::from("[%header]\n|===\n");
+    for row in &grid {
+        let cells: Vec<String> = row.iter().map(|cell| format!("| {}", cell)).collect();
+        asciidoc.push_str(&format!("{}\n", cells.join(" ").trim_end()));
+    }
+    asciidoc.push_str("|===\n");
+    Ok(asciidoc)
+}
+
+// ==================== Org 表格 ====================
+
+/// 将表格 HTML 转换为 Org 表格，第一行之后加一条 `|---+---|` 分隔线作为表头；
+/// 单元格中的管道符写成实体 `\vert{}`，合并单元格同样按重复的值展开
+pub(crate) fn html_table_to_org(html: &str) -> Result<String, &'static str> {
+    let grid = build_grid(parse_rows(html)?, |raw| {
+        escape_org(&plain_cell_text(raw), false).replace('|', "\\vert{}")
+    })?;
+    let width = grid[0].len();
+
+    let mut org = String::new();
+    for (i, row) in grid.iter().enumerate() {
+        org.push_str(&format!("| {} |\n", row.join(" | ")));
+        if i == 0 {
+            org.push_str(&format!("|{}|\n", vec!["---"; width].join("+")));
+        }
+    }
+    Ok(org)
+}
+
+// ==================== CSV 导出 ====================
+
+/// 把表格导出为 CSV 文件的目录，见 [`crate::ConvertOptions::table_export`]
+#[derive(Debug, Clone)]
+pub struct TableExport {
+    /// 写入 CSV 文件及其说明的目录
+    pub dir: PathBuf,
+    /// 输出文档中以 `link_prefix/文件名` 的相对路径链接到 CSV 文件
+    pub link_prefix: String,
+}
+
+/// 与 CSV 文件同名的 `.json` 说明
+#[derive(Serialize)]
+struct CsvMetadata<'a> {
+    /// 页码（从 1 开始）
+    page: usize,
+    /// 本页的第几个表格（从 1 开始）
+    table: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    caption: Option<&'a str>,
+    rows: usize,
+    columns: usize,
+    /// 按重复的值展开的合并单元格
+    warnings: Vec<String>,
+}
+
+/// RFC 4180 的字段：含逗号、引号或换行时整个字段加引号，其中的引号写两遍
+fn csv_field(text: &str) -> Cow<'_, str> {
+    if text.contains([',', '"', '\r', '\n']) {
+        Cow::Owned(format!("\"{}\"", text.replace('"', "\"\"")))
+    } else {
+        Cow::Borrowed(text)
+    }
+}
+
+/// 将表格 HTML 转换为 CSV 的各行；合并单元格与管道表格一样按重复的值展开，
+/// 同时返回对每个合并单元格的说明
+fn csv_rows(html: &str) -> Result<(Vec<Vec<String>>, Vec<String>), &'static str> {
+    let rows = parse_rows(html)?;
+    let mut notes = Vec::new();
+    for (r, row) in rows.iter().enumerate() {
+        for cell in row {
+            let mut spans = Vec::new();
+            if cell.colspan > 1 {
+                spans.push(format!("{} columns", cell.colspan));
+            }
+            if cell.rowspan > 1 {
+                spans.push(format!("{} rows", cell.rowspan));
+            }
+            if !spans.is_empty() {
+                notes.push(format!(
+                    "row {}: cell \"{}\" spans {}, repeated in each",
+                    r + 1,
+                    plain_cell_text(&cell.text),
+                    spans.join(" and ")
+                ));
+            }
+        }
+    }
+    Ok((build_grid(rows, plain_cell_text)?, notes))
+}
+
+/// 导出页面中的第 `index` 个表格（从 1 开始）：在 `export.dir` 中写出 UTF-8 编码、每行以 CRLF 结尾的
+/// `page012_table01.csv` 及同名的 `.json` 说明，返回输出文档中指向该 CSV 文件的链接。
+/// 无法转换的表格记录警告，不写出文件
+pub(crate) fn export_table(
+    html: &str,
+    page_idx: usize,
+    index: usize,
+    caption: Option<&str>,
+    export: &TableExport,
+    warnings: &mut Vec<Warning>,
+) -> Result<Option<String>, ConvertError> {
+    let (grid, notes) = match csv_rows(html) {
+        Ok(table) => table,
+        Err(reason) => {
+            warnings.push(Warning::TableNotExported {
+                page_idx,
+                reason: reason.to_string(),
+            });
+            return Ok(None);
+        }
+    };
+    let csv: String = grid
+        .iter()
+        .map(|row| {
+            let fields: Vec<Cow<str>> = row.iter().map(|cell| csv_field(cell)).collect();
+            format!("{}\r\n", fields.join(","))
+        })
+        .collect();
+    let stem = format!("page{:03}_table{:02}", page_idx + 1, index);
+    let write = |name: String, content: &str| {
+        let path = export.dir.join(name);
+        fs::write(&path, content).map_err(|source| ConvertError::Io { path, source })
+    };
+    write(format!("{}.csv", stem), &csv)?;
+    let metadata = CsvMetadata {
+        page: page_idx + 1,
+        table: index,
+        caption,
+        rows: grid.len(),
+        columns: grid[0].len(),
+        warnings: notes,
+    };
+    let json = serde_json::to_string_pretty(&metadata).expect("CSV metadata serializes");
+    write(format!("{}.json", stem), &(json + "\n"))?;
+
+    Ok(Some(if export.link_prefix.is_empty() {
+        format!("{}.csv", stem)
+    } else {
+        format!("{}/{}.csv", export.link_prefix, stem)
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{TempDir, bare_options};
+    use crate::{
+        ConvertOptions, Flavor, InputFormat, TableFormat, convert_layout_to_markdown, parse_layout,
+    };
+    use std::path::Path;
+
+    #[test]
+    fn csv_fields_are_quoted_only_when_needed() {
+        assert_eq!(csv_field("plain"), "plain");
+        assert_eq!(csv_field("1,20"), "\"1,20\"");
+        assert_eq!(csv_field("say \"hi\""), "\"say \"\"hi\"\"\"");
+        assert_eq!(csv_field("two\nlines"), "\"two\nlines\"");
+    }
+
+    #[test]
+    fn tables_are_exported_with_merged_cells_repeated() {
+        let layout = parse_layout(
+            include_str!("../tests/fixtures/tables.json"),
+            InputFormat::Auto,
+        )
+        .unwrap();
+        let dir = TempDir::new();
+        let options = ConvertOptions {
+            flavor: Flavor::Gfm,
+            table_export: Some(TableExport {
+                dir: dir.path().to_path_buf(),
+                link_prefix: "tables".to_string(),
+            }),
+            ..bare_options()
+        };
+        let output = convert_layout_to_markdown(&layout, Path::new("."), &options).unwrap();
+
+        let read = |name: &str| fs::read_to_string(dir.path().join(name)).unwrap();
+        assert_eq!(
+            read("page001_table01.csv"),
+            "Item,Item,Price\r\nFruit,Apple,\"1,20\"\r\nFruit,\"Pear \"\"Williams\"\"\",\"0,95\"\r\n"
+        );
+        let metadata: serde_json::Value =
+            serde_json::from_str(&read("page001_table01.json")).unwrap();
+        assert_eq!(metadata["caption"], "Table 1. Prices, in \"euros\"");
+        assert_eq!(
+            (metadata["rows"].as_u64(), metadata["columns"].as_u64()),
+            (Some(3), Some(3))
+        );
+        assert_eq!(metadata["warnings"].as_array().unwrap().len(), 2);
+        // 序号按页重新计数
+        assert_eq!(read("page002_table01.csv"), "Name,Score\r\nAda,10\r\n");
+
+        // 只有截图的表格与嵌套的表格不导出，后者给出警告
+        let mut files: Vec<String> = fs::read_dir(dir.path())
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .collect();
+        files.sort();
+        assert_eq!(
+            files,
+            [
+                "page001_table01.csv",
+                "page001_table01.json",
+                "page002_table01.csv",
+                "page002_table01.json"
+            ]
+        );
+        assert_eq!(output.stats.tables_exported, 2);
+        assert_eq!(output.stats.tables_image_only, 1);
+        assert!(output.warnings.iter().any(|warning| matches!(
+            warning,
+            Warning::TableNotExported { page_idx: 0, reason } if reason == "nested table"
+        )));
+
+        assert_eq!(
+            output.markdown.matches("[CSV](<tables/page00").count(),
+            2,
+            "{}",
+            output.markdown
+        );
+        assert!(
+            output
+                .markdown
+                .contains("</table>\n\n[CSV](<tables/page001_table01.csv>)\n"),
+            "{}",
+            output.markdown
+        );
+    }
+
+    #[test]
+    fn html_tables_become_pipe_tables_with_spans_expanded() {
+        let html = "<table><tr><th colspan=\"2\">Range</th><th>Note</th></tr>\
+                    <tr><td rowspan=\"2\">a | b</td><td>1 &amp; 2</td><td>&lt;b&gt;  bold\n</td></tr>\
+                    <tr><td>*x*</td><td></td></tr></table>";
+        assert_eq!(
+            html_table_to_markdown(html, false).unwrap(),
+            "| Range | Range | Note |\n\
+             | --- | --- | --- |\n\
+             | a \\| b | 1 & 2 | &lt;b> bold |\n\
+             | a \\| b | *x* |  |\n"
+        );
+        assert!(
+            html_table_to_markdown(html, true)
+                .unwrap()
+                .contains("| a \\| b | \\*x\\* |  |\n")
+        );
+
+        for (html, reason) in [
+            (
+                "<table><tr><td><table><tr><td>x</td></tr></table></td></tr></table>",
+                "nested table",
+            ),
+            (
+                "<table><tr><td>a</td><td>b</td></tr><tr><td>c</td></tr></table>",
+                "rows have different numbers of cells",
+            ),
+            ("<table></table>", "empty table"),
+        ] {
+            assert_eq!(html_table_to_markdown(html, false), Err(reason), "{}", html);
+        }
+    }
+
+    #[test]
+    fn markdown_tables_fall_back_to_html_with_a_warning() {
+        let layout = parse_layout(
+            include_str!("../tests/fixtures/tables.json"),
+            InputFormat::Auto,
+        )
+        .unwrap();
+        let options = ConvertOptions {
+            flavor: Flavor::Gfm,
+            table_format: TableFormat::Markdown,
+            ..bare_options()
+        };
+        let output = convert_layout_to_markdown(&layout, Path::new("."), &options).unwrap();
+        assert!(
+            output
+                .markdown
+                .contains("| Name | Score |\n| --- | --- |\n| Ada | 10 |\n"),
+            "{}",
+            output.markdown
+        );
+        // 嵌套的表格保留 HTML
+        assert_eq!(
+            output.markdown.matches("<table>").count(),
+            2,
+            "{}",
+            output.markdown
+        );
+        assert!(output.warnings.iter().any(|warning| matches!(
+            warning,
+            Warning::TableKeptAsHtml { page_idx: 0, reason } if reason == "nested table"
+        )));
+    }
+}
@@ -0,0 +1,129 @@
+//! 单元测试共用的工具：临时目录、测试图片与内存中的 layout.json
+
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::{env, fs, process};
+
+use ::image::{ImageFormat, RgbImage};
+use serde_json::{Value, json};
+
+use crate::{ConvertOptions, InputFormat, LayoutJson, convert_layout_to_markdown, parse_layout};
+
+/// 测试结束时连同其中的文件一起删除的临时目录
+pub(crate) struct TempDir(PathBuf);
+
+impl TempDir {
+    pub(crate) fn new() -> Self {
+        static NEXT: AtomicUsize = AtomicUsize::new(0);
+        let path = env::temp_dir().join(format!(
+            "mineru-md-test-{}-{}",
+            process::id(),
+            NEXT.fetch_add(1, Ordering::Relaxed)
+        ));
+        fs::create_dir_all(&path).unwrap();
+        TempDir(path)
+    }
+
+    pub(crate) fn path(&self) -> &Path {
+        &self.0
+    }
+
+    /// 写入目录中的文件，必要时创建上级目录
+    pub(crate) fn write(&self, name: &str, contents: &[u8]) -> PathBuf {
+        let path = self.0.join(name);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).unwrap();
+        }
+        fs::write(&path, contents).unwrap();
+        path
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+/// 给定尺寸、各像素颜色相同的 PNG 图片
+pub(crate) fn png(width: u32, height: u32, shade: u8) -> Vec<u8> {
+    encoded(width, height, shade, ImageFormat::Png)
+}
+
+/// 同 [`png`]，编码为 `format`
+pub(crate) fn encoded(width: u32, height: u32, shade: u8, format: ImageFormat) -> Vec<u8> {
+    let image = RgbImage::from_pixel(width, height, ::image::Rgb([shade; 3]));
+    let mut data = Cursor::new(Vec::new());
+    image.write_to(&mut data, format).unwrap();
+    data.into_inner()
+}
+
+/// 不输出样式头、页脚与分页线的选项，便于只比较正文
+pub(crate) fn bare_options() -> ConvertOptions {
+    ConvertOptions {
+        style_header: false,
+        footer: None,
+        page_dividers: false,
+        ..ConvertOptions::default()
+    }
+}
+
+/// 转换后的全文，图片相对于当前目录解析
+pub(crate) fn markdown(layout: &LayoutJson, options: &ConvertOptions) -> String {
+    convert_layout_to_markdown(layout, Path::new("."), options)
+        .unwrap()
+        .markdown
+}
+
+pub(crate) fn layout(json: Value) -> LayoutJson {
+    parse_layout(&json.to_string(), InputFormat::Auto).unwrap()
+}
+
+/// 只有一页（600×800）的文档，`blocks` 为该页的 para_blocks
+pub(crate) fn page_layout(blocks: Value) -> LayoutJson {
+    layout(json!({
+        "pdf_info": [{"page_idx": 0, "page_size": [600, 800], "para_blocks": blocks}]
+    }))
+}
+
+/// 只有一行一个 text span 的块，如 `text`、`title`
+pub(crate) fn text_block(block_type: &str, bbox: [f64; 4], content: &str) -> Value {
+    json!({
+        "type": block_type,
+        "bbox": bbox,
+        "lines": [{"bbox": bbox, "spans": [{"type": "text", "bbox": bbox, "content": content}]}]
+    })
+}
+
+/// 每行一个 text span 的块，各行自上而下排列
+pub(crate) fn lines_block(block_type: &str, lines: &[&str]) -> Value {
+    let lines: Vec<Value> = lines
+        .iter()
+        .enumerate()
+        .map(|(i, content)| {
+            let bbox = [
+                50.0,
+                100.0 + 20.0 * i as f64,
+                550.0,
+                115.0 + 20.0 * i as f64,
+            ];
+            json!({"bbox": bbox, "spans": [{"type": "text", "bbox": bbox, "content": content}]})
+        })
+        .collect();
+    let bottom = 115.0 + 20.0 * lines.len().saturating_sub(1) as f64;
+    json!({"type": block_type, "bbox": [50.0, 100.0, 550.0, bottom], "lines": lines})
+}
+
+/// 引用 `image_path` 的 image 块
+pub(crate) fn image_block(bbox: [f64; 4], image_path: &str) -> Value {
+    json!({
+        "type": "image",
+        "bbox": bbox,
+        "blocks": [{
+            "type": "image_body",
+            "bbox": bbox,
+            "lines": [{"bbox": bbox, "spans": [{"type": "image", "bbox": bbox, "image_path": image_path}]}]
+        }]
+    })
+}
@@ -0,0 +1,2219 @@
+//! 文本工具：转义、锚点生成、文本提取与行内公式处理
+
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+
+use serde::{Deserialize, Serialize};
+
+use crate::asciidoc::{delimited_block, escape_asciidoc, macro_text};
+use crate::doc::{Inline, InlineStyle};
+use crate::latex::{escape_latex, escape_url};
+use crate::links::{Piece, autolink_pieces, continues_link};
+use crate::org::{self, escape_org};
+use crate::rst::{self, escape_rst};
+use crate::types::{Block, PageInfo, Span, TocEntry};
+use crate::{AnchorStyle, ConvertOptions, Flavor, InlineMathStyle, MathDelimiters, OutputFormat};
+
+// ==================== 工具函数 ====================
+
+pub(crate) fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+/// 行首会使该行成为标题、引用、列表或分隔线的符号前需要插入 `\` 的位置
+fn block_marker_position(text: &str) -> Option<usize> {
+    let ends_marker = |rest: &str| rest.is_empty() || rest.starts_with(char::is_whitespace);
+    match text.chars().next()? {
+        '#' | '>' => Some(0),
+        '-' | '+' if ends_marker(&text[1..]) => Some(0),
+        '-' if text.chars().all(|c| c == '-' || c.is_whitespace())
+            && text.matches('-').count() >= 3 =>
+        {
+            Some(0)
+        }
+        c if c.is_ascii_digit() => {
+            let digits = text.chars().take_while(char::is_ascii_digit).count();
+            let delimiter = text[digits..].chars().next();
+            (digits <= 9
+                && matches!(delimiter, Some('.' | ')'))
+                && ends_marker(&text[digits + 1..]))
+            .then_some(digits)
+        }
+        _ => None,
+    }
+}
+
+/// `&` 之后的文字是否构成 HTML 实体，如 `amp;`、`#39;`
+fn is_entity(rest: &str) -> bool {
+    rest.find(';').is_some_and(|end| {
+        (1..=32).contains(&end)
+            && rest[..end]
+                .strip_prefix('#')
+                .unwrap_or(&rest[..end])
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric())
+    })
+}
+
+/// 转义纯文本中会被 CommonMark / GFM 当作标记的字符
+///
+/// `\`、`*`、`` ` ``、`|`、`~` 总是转义；`_` 夹在字母数字之间时不构成强调，保持原样；
+/// `<` 只在可能开始 HTML 标签或自动链接时转义，`]` 只在紧跟 `(` 时转义，`&` 只在构成实体时转义。`line_start` 表示文本位于一行开头，
+/// 此时还会转义 `#`、`>`、`- `、`1. ` 等块级标记。
+pub(crate) fn escape_markdown(text: &str, line_start: bool) -> String {
+    let leading = text.len() - text.trim_start().len();
+    let marker = if line_start {
+        block_marker_position(&text[leading..]).map(|position| leading + position)
+    } else {
+        None
+    };
+
+    let mut out = String::with_capacity(text.len() + 8);
+    let mut prev: Option<char> = None;
+    let mut chars = text.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        let next = chars.peek().map(|&(_, next)| next);
+        let escape = match c {
+            '\\' | '*' | '`' | '|' | '~' => true,
+            '_' => {
+                !(prev.is_some_and(char::is_alphanumeric)
+                    && next.is_some_and(char::is_alphanumeric))
+            }
+            '<' => next.is_some_and(|next| next.is_ascii_alphabetic() || "/!?".contains(next)),
+            // `](` 会把前面的方括号变成链接；单独的 `[1]` 保持原样，脚注引用依赖它
+            ']' => next == Some('('),
+            '&' => is_entity(&text[i + 1..]),
+            _ => marker == Some(i),
+        };
+        if escape {
+            out.push('\\');
+        }
+        out.push(c);
+        prev = Some(c);
+    }
+    out
+}
+
+/// 用 `*...*` 强调一段纯文本，文本内的标记字符先转义
+pub(crate) fn emphasize(text: &str) -> String {
+    format!("*{}*", escape_markdown(text, false))
+}
+
+/// 用 `**...**` 加粗一段纯文本，文本内的标记字符先转义
+pub(crate) fn strong(text: &str) -> String {
+    format!("**{}**", escape_markdown(text, false))
+}
+
+/// 替代文字（alt）的最大字符数，超出部分以省略号代替
+const ALT_MAX_CHARS: usize = 200;
+
+/// 图注、LaTeX 等用作替代文字时的单行形式：空白与换行合并为一个空格，过长时截断
+pub(crate) fn alt_text(text: &str) -> String {
+    let mut alt = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    if let Some((end, _)) = alt.char_indices().nth(ALT_MAX_CHARS) {
+        alt.truncate(end);
+        alt.push('…');
+    }
+    alt
+}
+
+/// 生成 Markdown 图片语法 `![alt](src)`，地址含空白时用尖括号包裹
+pub(crate) fn markdown_image(alt: &str, src: &str) -> String {
+    let alt = escape_link_text(&alt.replace('\n', " "));
+    if src.contains(char::is_whitespace) {
+        format!("![{}](<{}>)", alt, src)
+    } else {
+        format!("![{}]({})", alt, src)
+    }
+}
+
+/// Obsidian 的图片嵌入 `![[文件名]]`：按文件名在库中查找，只保留链接的最后一段；
+/// 内联的 data URI 没有文件名，仍输出为普通 Markdown 图片
+pub(crate) fn obsidian_image(alt: &str, src: &str) -> String {
+    if src.starts_with("data:") {
+        return markdown_image(alt, src);
+    }
+    format!("![[{}]]", src.rsplit('/').next().unwrap_or(src))
+}
+
+/// Obsidian 内部链接的显示文字中不能出现 `|`、`[`、`]`
+fn obsidian_alias(text: &str) -> String {
+    let text: String = text
+        .chars()
+        .map(|c| if matches!(c, '|' | '[' | ']') { ' ' } else { c })
+        .collect();
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// 链接到标题的 `[[#标题|标题]]`；Obsidian 匹配标题时忽略 `#`、`^` 等不能出现在链接中的字符
+pub(crate) fn obsidian_heading_link(title: &str) -> String {
+    let target: String = title
+        .chars()
+        .filter(|c| !matches!(c, '#' | '|' | '^' | '[' | ']'))
+        .collect();
+    format!(
+        "[[#{}|{}]]",
+        target.split_whitespace().collect::<Vec<_>>().join(" "),
+        obsidian_alias(title)
+    )
+}
+
+/// 链接到块标识 `^id` 的 `[[#^id|文字]]`
+pub(crate) fn obsidian_block_link(block_id: &str, text: &str) -> String {
+    format!("[[#^{}|{}]]", block_id, obsidian_alias(text))
+}
+
+/// 移除 HTML 片段中内嵌的 `<style>...</style>`，其余内容原样保留
+pub(crate) fn strip_style_tags(html: &str) -> String {
+    let lower = html.to_ascii_lowercase();
+    let mut result = String::with_capacity(html.len());
+    let mut pos = 0;
+
+    while let Some(start) = lower[pos..].find("<style").map(|i| pos + i) {
+        result.push_str(&html[pos..start]);
+        match lower[start..].find("</style>") {
+            Some(end) => pos = start + end + "</style>".len(),
+            None => {
+                pos = html.len();
+                break;
+            }
+        }
+    }
+    result.push_str(&html[pos..]);
+    result
+}
+
+/// 锚点 id → 所在文件名；不拆分输出时为空
+pub(crate) type AnchorFiles = HashMap<String, String>;
+
+/// 指向锚点的链接地址；锚点在拆分出的其他文件中时加上文件名
+pub(crate) fn anchor_href(anchor_id: &str, files: &AnchorFiles) -> String {
+    match files.get(anchor_id) {
+        Some(file) => format!("{}#{}", file, anchor_id),
+        None => format!("#{}", anchor_id),
+    }
+}
+
+/// 页面内容中标记锚点的文字：AsciiDoc 为 `[[id]]`，reStructuredText 为 `.. _id:`，
+/// Org 为标题属性中的 `:CUSTOM_ID: id` 一行，其余格式为元素的 `id` 属性
+pub(crate) fn anchor_marker(anchor_id: &str, options: &ConvertOptions) -> String {
+    match options.format {
+        OutputFormat::Asciidoc => format!("[[{}]]", anchor_id),
+        OutputFormat::Rst => format!(".. _{}:", anchor_id),
+        OutputFormat::Org => format!(":CUSTOM_ID: {}\n", anchor_id),
+        _ => format!(" id=\"{}\"", anchor_id),
+    }
+}
+
+/// 锚点所在元素（`<a id>`、HTML 标题、AsciiDoc 的 `[[id]]` 行、reStructuredText 的目标或 Org 的标题行）在页面内容中的起始位置；
+/// 紧挨着的上一行是来源注释时从注释开始，拆分文件、插入链接时注释与所属的块不会分开
+pub(crate) fn anchor_position(
+    html: &str,
+    anchor_id: &str,
+    options: &ConvertOptions,
+) -> Option<usize> {
+    let marker = html.find(&anchor_marker(anchor_id, options))?;
+    let start = match options.format {
+        OutputFormat::Asciidoc | OutputFormat::Rst => marker,
+        // 属性抽屉紧跟在标题行之后
+        OutputFormat::Org => {
+            let headline = html[..marker].strip_suffix(":PROPERTIES:\n")?;
+            let headline = headline.strip_suffix('\n')?;
+            headline.rfind('\n').map_or(0, |newline| newline + 1)
+        }
+        _ => html[..marker].rfind('<')?,
+    };
+    Some(with_provenance(html, start))
+}
+
+/// 紧挨着 `start` 的上一行是来源注释（HTML 注释、AsciiDoc 的 `//` 注释、Org 的 `#` 注释）时返回注释的起始位置；
+/// reStructuredText 的 `..` 注释与所属的块之间隔一个空行
+fn with_provenance(html: &str, start: usize) -> usize {
+    let Some(before) = html[..start].strip_suffix('\n') else {
+        return start;
+    };
+    let last_line = |text: &str| text.rfind('\n').map_or(0, |newline| newline + 1);
+    if let Some(rst) = before.strip_suffix('\n')
+        && rst[last_line(rst)..].starts_with(".. p")
+    {
+        return last_line(rst);
+    }
+    let line_start = last_line(before);
+    let line = &before[line_start..];
+    if (line.starts_with("<!--") && line.ends_with("-->"))
+        || line.starts_with("// p")
+        || line.starts_with("# p")
+    {
+        line_start
+    } else {
+        start
+    }
+}
+
+/// 是否使用 GitHub 风格的标题锚点；只有带锚点的 Markdown 风格（Styled、Gfm）受影响
+pub(crate) fn github_anchors(options: &ConvertOptions) -> bool {
+    options.anchors == AnchorStyle::Github
+        && options.format == OutputFormat::Markdown
+        && options.flavor != Flavor::Obsidian
+}
+
+/// 页面中各目录条目对应标题的起始位置，顺序与 `entries` 一致
+///
+/// GitHub 风格与 Obsidian 风格的标题前没有 `<a id>`，按渲染出的标题行自前向后逐个查找，同名标题各自对应。
+pub(crate) fn heading_positions(
+    html: &str,
+    entries: &[TocEntry],
+    options: &ConvertOptions,
+) -> Vec<Option<usize>> {
+    let obsidian = options.format == OutputFormat::Markdown && options.flavor == Flavor::Obsidian;
+    if !github_anchors(options) && !obsidian {
+        return entries
+            .iter()
+            .map(|entry| anchor_position(html, &entry.anchor_id, options))
+            .collect();
+    }
+    let mut cursor = 0;
+    entries
+        .iter()
+        .map(|entry| {
+            let line = format!(
+                "{} {}\n",
+                "#".repeat(entry.level + 1),
+                escape_text(&entry.title, false, options)
+            );
+            let start = html[cursor..]
+                .match_indices(&line)
+                .map(|(offset, _)| cursor + offset)
+                .find(|&start| start == 0 || html[..start].ends_with('\n'))?;
+            cursor = start + line.len();
+            Some(with_provenance(html, start))
+        })
+        .collect()
+}
+
+pub(crate) fn escape_link_text(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace('[', "\\[")
+        .replace(']', "\\]")
+}
+
+/// 目录、图表目录中的链接文字：开启 `escape_markdown` 时同时转义其余 Markdown 字符
+pub(crate) fn toc_link_text(text: &str, options: &ConvertOptions) -> String {
+    if options.escape_markdown {
+        escape_markdown(text, false)
+            .replace('[', "\\[")
+            .replace(']', "\\]")
+    } else {
+        escape_link_text(text)
+    }
+}
+
+pub(crate) fn generate_anchor_id(title: &str, page_idx: usize) -> String {
+    let slug: String = title
+        .chars()
+        .map(|c| {
+            if c.is_alphanumeric()
+                || c.is_ascii_alphabetic()
+                || ('\u{4e00}'..='\u{9fa5}').contains(&c)
+            {
+                c
+            } else {
+                '-'
+            }
+        })
+        .collect::<String>()
+        .trim_matches('-')
+        .chars()
+        .take(50)
+        .collect();
+
+    format!(
+        "toc-{}-{}",
+        page_idx,
+        if slug.is_empty() { "title" } else { &slug }
+    )
+}
+
+/// GitHub 根据标题文字生成的锚点：转为小写，去掉字母、数字、`_`、`-` 与空格以外的字符，
+/// 每个空格换成 `-`；中日韩文字及其他语言的字母原样保留。重复的锚点由
+/// [`AnchorRegistry::issue_github`] 追加编号
+pub(crate) fn github_slug(title: &str) -> String {
+    title
+        .trim()
+        .to_lowercase()
+        .chars()
+        .filter(|&c| c.is_alphanumeric() || matches!(c, '_' | '-' | ' '))
+        .map(|c| if c == ' ' { '-' } else { c })
+        .collect()
+}
+
+/// 识别标题开头的章节编号（`3`、`3.2`、`3.2.1.`），返回编号的层数
+pub(crate) fn numbering_depth(title: &str) -> Option<usize> {
+    let end = title
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .unwrap_or(title.len());
+    let numbering = &title[..end];
+    let rest = &title[end..];
+    if !rest.is_empty() && !rest.starts_with(char::is_whitespace) {
+        return None;
+    }
+
+    let parts: Vec<&str> = numbering.trim_end_matches('.').split('.').collect();
+    let valid = parts.iter().all(|part| !part.is_empty() && part.len() <= 3);
+    (valid && parts.len() <= 6).then_some(parts.len())
+}
+
+/// 记录已分配的锚点，重复时依次追加 `-2`、`-3`……
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub(crate) struct AnchorRegistry {
+    issued: HashSet<String>,
+}
+
+impl AnchorRegistry {
+    pub(crate) fn issue(&mut self, anchor_id: String) -> String {
+        self.issue_from(anchor_id, 2)
+    }
+
+    /// 与 GitHub 相同，第一个重复的锚点追加 `-1`
+    pub(crate) fn issue_github(&mut self, anchor_id: String) -> String {
+        self.issue_from(anchor_id, 1)
+    }
+
+    fn issue_from(&mut self, anchor_id: String, first: usize) -> String {
+        let mut unique = anchor_id.clone();
+        let mut n = first;
+        while self.issued.contains(&unique) {
+            unique = format!("{}-{}", anchor_id, n);
+            n += 1;
+        }
+        self.issued.insert(unique.clone());
+        unique
+    }
+}
+
+// ==================== 标题规范化 ====================
+
+/// 之前不留空格的标点
+const NO_SPACE_BEFORE: &[char] = &[
+    '.', ',', ';', ':', '!', '?', ')', ']', '}', '，', '。', '；', '：', '！', '？', '）', '、',
+];
+
+/// 之后不留空格的标点
+const NO_SPACE_AFTER: &[char] = &['(', '[', '{', '（'];
+
+/// 转为标题大小写时小写的虚词（作为第一个英文单词时仍首字母大写）
+const MINOR_WORDS: &[&str] = &[
+    "a", "an", "and", "as", "at", "by", "for", "from", "in", "into", "of", "on", "or", "the", "to",
+    "via", "vs", "with",
+];
+
+/// 不超过这么多字母的全大写单词视为缩写，保持原样
+const ACRONYM_MAX_LETTERS: usize = 4;
+
+/// 整理 OCR 标题中的空白：连续空白合并为一个空格，去掉标点之前、左括号之后以及开头编号内部的空格
+/// （`3 . 2 RESULTS` → `3.2 RESULTS`）；`title_case` 时全大写的拉丁字母标题改为标题大小写
+pub(crate) fn normalize_heading(title: &str, title_case: bool) -> String {
+    let mut text = String::with_capacity(title.len());
+    for c in title
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .chars()
+    {
+        if NO_SPACE_BEFORE.contains(&c) && text.ends_with(' ') {
+            text.pop();
+        }
+        if c == ' ' && text.ends_with(NO_SPACE_AFTER) {
+            continue;
+        }
+        text.push(c);
+    }
+
+    // 开头由数字、点与空格组成的编号中，点之后紧跟数字的空格是 OCR 多出来的
+    let end = text
+        .find(|c: char| !(c.is_ascii_digit() || c == '.' || c == ' '))
+        .unwrap_or(text.len());
+    let numbering: Vec<char> = text[..end].chars().collect();
+    let mut compact = String::with_capacity(text.len());
+    for (i, &c) in numbering.iter().enumerate() {
+        let inside = i > 0
+            && numbering[i - 1] == '.'
+            && numbering.get(i + 1).is_some_and(char::is_ascii_digit);
+        if !(c == ' ' && inside) {
+            compact.push(c);
+        }
+    }
+    compact.push_str(&text[end..]);
+
+    if title_case && is_shouting(&compact) {
+        compact = title_cased(&compact);
+    }
+    compact
+}
+
+/// 含拉丁字母且其中没有小写字母
+fn is_shouting(text: &str) -> bool {
+    let mut letters = text.chars().filter(char::is_ascii_alphabetic).peekable();
+    letters.peek().is_some() && letters.all(|c| c.is_ascii_uppercase())
+}
+
+/// 规范写法的罗马数字（`IV`、`XII`、`MCMXC`）
+fn is_roman_numeral(word: &str) -> bool {
+    const NUMERALS: [(usize, &str); 13] = [
+        (1000, "M"),
+        (900, "CM"),
+        (500, "D"),
+        (400, "CD"),
+        (100, "C"),
+        (90, "XC"),
+        (50, "L"),
+        (40, "XL"),
+        (10, "X"),
+        (9, "IX"),
+        (5, "V"),
+        (4, "IV"),
+        (1, "I"),
+    ];
+    let value = |c: char| match c {
+        'I' => Some(1),
+        'V' => Some(5),
+        'X' => Some(10),
+        'L' => Some(50),
+        'C' => Some(100),
+        'D' => Some(500),
+        'M' => Some(1000),
+        _ => None,
+    };
+    let Some(values) = word.chars().map(value).collect::<Option<Vec<usize>>>() else {
+        return false;
+    };
+    let mut total = 0;
+    for (i, &v) in values.iter().enumerate() {
+        match values.get(i + 1) {
+            Some(&next) if next > v => total -= v as isize,
+            _ => total += v as isize,
+        }
+    }
+    // 按数值重新写出，与原文相同才是规范写法，排除 `CIVIL` 这类恰好由罗马数字字母组成的单词
+    let mut rest = total.max(0) as usize;
+    let mut canonical = String::new();
+    for (amount, numeral) in NUMERALS {
+        while rest >= amount {
+            canonical.push_str(numeral);
+            rest -= amount;
+        }
+    }
+    !values.is_empty() && canonical == word
+}
+
+/// 全大写标题中的单词改为首字母大写；虚词小写（第一个英文单词除外，其前可以有章节编号、罗马数字或中文），
+/// 缩写、罗马数字、含数字或中日韩文字的单词不变
+fn title_cased(text: &str) -> String {
+    let kept = |word: &str| {
+        let letters: String = word.chars().filter(char::is_ascii_alphabetic).collect();
+        letters.is_empty()
+            || word.chars().any(|c| c.is_ascii_digit() || is_cjk(c))
+            || is_roman_numeral(&letters)
+    };
+    let first = text.split(' ').position(|word| !kept(word));
+    text.split(' ')
+        .enumerate()
+        .map(|(i, word)| {
+            let letters: String = word.chars().filter(char::is_ascii_alphabetic).collect();
+            let lower = word.to_ascii_lowercase();
+            if kept(word) {
+                word.to_string()
+            } else if Some(i) != first && MINOR_WORDS.contains(&lower.as_str()) {
+                lower
+            } else if letters.len() <= ACRONYM_MAX_LETTERS && !MINOR_WORDS.contains(&lower.as_str())
+            {
+                word.to_string()
+            } else {
+                // 连字符连接的各部分分别首字母大写，第一部分之后的虚词小写
+                lower
+                    .split('-')
+                    .enumerate()
+                    .map(|(j, part)| match j > 0 && MINOR_WORDS.contains(&part) {
+                        true => part.to_string(),
+                        false => capitalized(part),
+                    })
+                    .collect::<Vec<_>>()
+                    .join("-")
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// 第一个字母大写
+fn capitalized(word: &str) -> String {
+    match word.find(|c: char| c.is_ascii_alphabetic()) {
+        Some(i) => {
+            let (head, tail) = word.split_at(i);
+            let mut chars = tail.chars();
+            let first = chars.next().map(|c| c.to_ascii_uppercase());
+            format!(
+                "{}{}{}",
+                head,
+                first.into_iter().collect::<String>(),
+                chars.as_str()
+            )
+        }
+        None => word.to_string(),
+    }
+}
+
+// ==================== 空白与控制字符 ====================
+
+/// 不作为正文处理的 span：公式与表格的内容保留原有换行
+const RAW_SPAN_TYPES: &[&str] = &["inline_equation", "interline_equation", "table"];
+
+/// span 文字中需要规范化的字符：换行与其他控制字符（制表符除外）、特殊空格、零宽字符
+fn needs_normalizing(c: char) -> bool {
+    (c.is_control() && c != '\t')
+        || matches!(
+            c,
+            '\u{00a0}' | '\u{1680}' | '\u{2000}'
+                ..='\u{200d}'
+                    | '\u{2028}'
+                    | '\u{2029}'
+                    | '\u{202f}'
+                    | '\u{205f}'
+                    | '\u{2060}'
+                    | '\u{feff}'
+        )
+}
+
+/// 规范化一段 span 文字：换行、换页等分隔符（`\r\n` 算一个）变为空格，其余控制字符
+/// （制表符除外）与零宽字符、BOM 删除，不换行空格、窄空格等特殊空格变为普通空格；
+/// 全角空格 U+3000 保留
+fn normalize_whitespace(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\r' if chars.peek() == Some(&'\n') => {}
+            '\n' | '\r' | '\u{0b}' | '\u{0c}' | '\u{85}' | '\u{2028}' | '\u{2029}' => out.push(' '),
+            '\u{200b}'..='\u{200d}' | '\u{2060}' | '\u{feff}' => {}
+            '\u{00a0}' | '\u{1680}' | '\u{2000}'..='\u{200a}' | '\u{202f}' | '\u{205f}' => {
+                out.push(' ')
+            }
+            c if c.is_control() && c != '\t' => {}
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// 代码块保留原样：`code` 块（含子块）与 sub_type 为 code 的文本块
+fn is_code_block(block: &Block) -> bool {
+    block.block_type == "code" || block.sub_type.as_deref() == Some("code")
+}
+
+fn block_needs_normalizing(block: &Block) -> bool {
+    if is_code_block(block) {
+        return false;
+    }
+    let in_spans = block
+        .lines
+        .iter()
+        .flatten()
+        .flat_map(|line| &line.spans)
+        .any(|span| {
+            !RAW_SPAN_TYPES.contains(&span.span_type.as_str())
+                && span
+                    .content
+                    .as_deref()
+                    .is_some_and(|text| text.contains(needs_normalizing))
+        });
+    in_spans || block.blocks.iter().flatten().any(block_needs_normalizing)
+}
+
+fn normalize_block(block: &mut Block) {
+    if is_code_block(block) {
+        return;
+    }
+    for span in block
+        .lines
+        .iter_mut()
+        .flatten()
+        .flat_map(|line| &mut line.spans)
+    {
+        if RAW_SPAN_TYPES.contains(&span.span_type.as_str()) {
+            continue;
+        }
+        if let Some(content) = &mut span.content
+            && content.contains(needs_normalizing)
+        {
+            *content = normalize_whitespace(content);
+        }
+    }
+    block.blocks.iter_mut().flatten().for_each(normalize_block);
+}
+
+/// 规范化页面中各文本 span 的空白与控制字符（见 [`ConvertOptions::normalize_whitespace`]）；
+/// 没有需要处理的字符时不复制页面
+pub(crate) fn normalize_page_whitespace(page: Cow<'_, PageInfo>) -> Cow<'_, PageInfo> {
+    let dirty = page
+        .para_blocks
+        .iter()
+        .chain(&page.discarded_blocks)
+        .any(block_needs_normalizing);
+    if !dirty {
+        return page;
+    }
+    let mut page = page.into_owned();
+    page.para_blocks
+        .iter_mut()
+        .chain(&mut page.discarded_blocks)
+        .for_each(normalize_block);
+    Cow::Owned(page)
+}
+
+// ==================== 内容提取 ====================
+
+pub(crate) fn is_cjk(c: char) -> bool {
+    matches!(c,
+        '\u{3000}'..='\u{303f}'   // CJK 标点
+        | '\u{3040}'..='\u{30ff}' // 平假名、片假名
+        | '\u{3400}'..='\u{4dbf}'
+        | '\u{4e00}'..='\u{9fff}'
+        | '\u{ac00}'..='\u{d7af}' // 韩文音节
+        | '\u{f900}'..='\u{faff}'
+        | '\u{ff00}'..='\u{ffef}' // 全角符号
+    )
+}
+
+/// 两行相接处的处理方式
+enum Joint {
+    None,
+    Space,
+    DropHyphen,
+}
+
+/// 判断下一行如何接到已有文本之后
+///
+/// 行尾连字符后接小写字母时视为断词，`dehyphenate` 为真则去掉连字符直接拼接，
+/// 否则保留连字符；其余情况在两侧都不是中日韩文字时补一个空格。
+fn line_joint(prev: &str, next: &str, dehyphenate: bool) -> Joint {
+    let (Some(last), Some(first)) = (prev.chars().next_back(), next.chars().next()) else {
+        return Joint::None;
+    };
+    if last.is_whitespace() || first.is_whitespace() {
+        return Joint::None;
+    }
+
+    let before_hyphen = prev.chars().nth_back(1);
+    if last == '-' && before_hyphen.is_some_and(char::is_alphabetic) && first.is_lowercase() {
+        if dehyphenate {
+            Joint::DropHyphen
+        } else {
+            Joint::None
+        }
+    } else if last != '-' && !is_cjk(last) && !is_cjk(first) {
+        Joint::Space
+    } else {
+        Joint::None
+    }
+}
+
+/// 将块自身的各行接到 `text` 之后，span 内容直接追加，不为每行分配字符串
+///
+/// 跨行拼接只看本块已写入的部分，与前面其他块的文字无关。
+fn push_block_lines(text: &mut String, block: &Block, dehyphenate: bool) {
+    push_lines_with(text, block, dehyphenate, &mut |span| {
+        span.content.as_deref().map(Cow::Borrowed)
+    });
+}
+
+/// 同 [`push_block_lines`]，每个 span 的文字由 `span_text` 给出
+fn push_lines_with(
+    text: &mut String,
+    block: &Block,
+    dehyphenate: bool,
+    span_text: &mut impl FnMut(&Span) -> Option<Cow<'_, str>>,
+) {
+    let start = text.len();
+    for line in block.lines.iter().flatten() {
+        let mut contents = line
+            .spans
+            .iter()
+            .filter_map(&mut *span_text)
+            .filter(|content| !content.is_empty());
+        let Some(first) = contents.next() else {
+            continue;
+        };
+        match line_joint(&text[start..], &first, dehyphenate) {
+            Joint::DropHyphen => {
+                text.pop();
+            }
+            Joint::Space => text.push(' '),
+            Joint::None => {}
+        }
+        text.push_str(&first);
+        contents.for_each(|content| text.push_str(&content));
+    }
+}
+
+/// 把一行文字接到 `text` 之后，按 [`line_joint`] 的规则补空格或去掉断词连字符
+pub(crate) fn push_joined(text: &mut String, next: &str, dehyphenate: bool) {
+    match line_joint(text, next, dehyphenate) {
+        Joint::DropHyphen => {
+            text.pop();
+        }
+        Joint::Space => text.push(' '),
+        Joint::None => {}
+    }
+    text.push_str(next);
+}
+
+/// 只提取块自身的行，不含子块
+pub(crate) fn extract_line_text(block: &Block, dehyphenate: bool) -> String {
+    let mut text = String::new();
+    push_block_lines(&mut text, block, dehyphenate);
+    text
+}
+
+fn push_block_text(text: &mut String, block: &Block, dehyphenate: bool) {
+    push_text_with(text, block, dehyphenate, &mut |span| {
+        span.content.as_deref().map(Cow::Borrowed)
+    });
+}
+
+/// 块自身的行及所有子块的文字，子块之间直接相连
+pub(crate) fn extract_text_from_block(block: &Block, dehyphenate: bool) -> String {
+    let mut text = String::new();
+    push_block_text(&mut text, block, dehyphenate);
+    text
+}
+
+/// 标题、图表说明、脚注等整块输出的文字：去掉首尾空白，开启 pangu_spacing 时补上中西文间距
+pub(crate) fn block_text(block: &Block, options: &ConvertOptions) -> String {
+    let text = extract_text_from_block(block, options.dehyphenate);
+    if options.pangu_spacing {
+        pangu_spacing(text.trim())
+    } else {
+        text.trim().to_string()
+    }
+}
+
+/// 标题中代替行内公式的占位字符，公式的 LaTeX 另外保存
+const MATH_PLACEHOLDER: char = '\u{fffc}';
+
+/// 块自身的行及所有子块的文字接到 `text` 之后，每个 span 的文字由 `span_text` 给出
+fn push_text_with(
+    text: &mut String,
+    block: &Block,
+    dehyphenate: bool,
+    span_text: &mut impl FnMut(&Span) -> Option<Cow<'_, str>>,
+) {
+    push_lines_with(text, block, dehyphenate, span_text);
+    for sub_block in block.blocks.iter().flatten() {
+        push_text_with(text, sub_block, dehyphenate, span_text);
+    }
+}
+
+/// 同 [`block_text`]，但行内公式以占位字符代替，公式的 LaTeX（已去掉 `$` 分隔符）按出现顺序另外返回；
+/// 内容为空的公式直接去掉。文字由 [`split_math`] 还原
+pub(crate) fn block_text_with_math(
+    block: &Block,
+    options: &ConvertOptions,
+) -> (String, Vec<String>) {
+    let mut equations = Vec::new();
+    let mut text = String::new();
+    push_text_with(&mut text, block, options.dehyphenate, &mut |span| {
+        let content = span.content.as_deref()?;
+        if span.span_type != "inline_equation" {
+            return Some(if content.contains(MATH_PLACEHOLDER) {
+                Cow::Owned(content.replace(MATH_PLACEHOLDER, ""))
+            } else {
+                Cow::Borrowed(content)
+            });
+        }
+        let pieces = inline_equation_pieces(content);
+        if pieces.is_empty() {
+            return None;
+        }
+        let mut text = String::new();
+        for piece in pieces {
+            match piece {
+                Inline::Text { text: piece, .. } => {
+                    text.push_str(&piece.replace(MATH_PLACEHOLDER, ""))
+                }
+                Inline::Math { latex } => {
+                    equations.push(latex);
+                    text.push(MATH_PLACEHOLDER);
+                }
+            }
+        }
+        Some(Cow::Owned(text))
+    });
+    let text = if options.pangu_spacing {
+        pangu_spacing(text.trim())
+    } else {
+        text.trim().to_string()
+    };
+    (text, equations)
+}
+
+/// 把 [`block_text_with_math`] 的文字还原：`plain` 中的公式换成不带分隔符的 LaTeX，
+/// 用于目录与锚点；没有公式时 `inlines` 为空，否则为按出现顺序的文字与公式，用于输出标题
+pub(crate) fn split_math(text: &str, equations: &[String]) -> (String, Vec<Inline>) {
+    if equations.is_empty() {
+        return (text.to_string(), Vec::new());
+    }
+    let mut plain = String::with_capacity(text.len());
+    let mut inlines = Vec::new();
+    let mut equations = equations.iter();
+    for (i, piece) in text.split(MATH_PLACEHOLDER).enumerate() {
+        if i > 0
+            && let Some(latex) = equations.next()
+        {
+            plain.push_str(latex);
+            inlines.push(Inline::Math {
+                latex: latex.clone(),
+            });
+        }
+        plain.push_str(piece);
+        if !piece.is_empty() {
+            push_text(&mut inlines, InlineStyle::default(), piece);
+        }
+    }
+    (plain, inlines)
+}
+
+// ==================== 行内公式和文本处理 ====================
+
+impl InlineStyle {
+    /// 非文本类 span（图片、公式等）返回 `None`
+    pub(crate) fn of(span: &Span) -> Option<Self> {
+        let mut style = InlineStyle {
+            bold: span.bold,
+            italic: span.italic,
+            superscript: span.superscript,
+            subscript: span.subscript,
+            low_confidence: None,
+        };
+        match span.span_type.as_str() {
+            "text" => {}
+            "text_strong" | "bold" => style.bold = true,
+            "text_emphasis" | "italic" => style.italic = true,
+            "sup" | "superscript" => style.superscript = true,
+            "sub" | "subscript" => style.subscript = true,
+            _ => return None,
+        }
+        Some(style)
+    }
+}
+
+/// 转换时能识别的 span：文本与带样式的文本（见 [`InlineStyle::of`]）、公式、图片与表格；
+/// 其余类型的 span 不出现在段落中
+pub(crate) fn is_known_span(span: &Span) -> bool {
+    InlineStyle::of(span).is_some()
+        || matches!(
+            span.span_type.as_str(),
+            "inline_equation" | "interline_equation" | "image" | "table"
+        )
+}
+
+/// 用于判断跨行拼接方式的原始文本；行内公式输出时以 `$` 之类的分隔符开头和结尾，按 `$` 判断
+fn inline_raw(inline: &Inline) -> &str {
+    match inline {
+        Inline::Text { text, .. } => text,
+        Inline::Math { .. } => "$",
+    }
+}
+
+/// 追加一段文本；与前一段样式相同时直接合并，避免输出 `**a****b**`
+fn push_text(inlines: &mut Vec<Inline>, style: InlineStyle, text: &str) {
+    if let Some(Inline::Text {
+        text: prev,
+        style: prev_style,
+    }) = inlines.last_mut()
+        && *prev_style == style
+    {
+        prev.push_str(text);
+        return;
+    }
+    inlines.push(Inline::Text {
+        text: text.to_string(),
+        style,
+    });
+}
+
+/// 文本 span 中文字的转义方式
+#[derive(Debug, Clone, Copy)]
+struct SpanEscape {
+    /// HTML 输出
+    html: bool,
+    /// LaTeX 输出，只转义 LaTeX 特殊字符
+    latex: bool,
+    /// AsciiDoc 输出，只转义 AsciiDoc 标记字符
+    asciidoc: bool,
+    /// reStructuredText 输出，只转义 reStructuredText 标记字符
+    rst: bool,
+    /// Org 输出，只转义 Org 标记字符
+    org: bool,
+    /// Gfm 风格的 Markdown 输出，高亮写成 `==…==`
+    gfm: bool,
+    /// 转义 Markdown 标记字符
+    markdown: bool,
+    /// 保留文字中的 HTML 标签与字符实体
+    inline_html: bool,
+}
+
+impl SpanEscape {
+    fn of(options: &ConvertOptions) -> Self {
+        SpanEscape {
+            html: options.format == OutputFormat::Html,
+            latex: options.format == OutputFormat::Latex,
+            asciidoc: options.format == OutputFormat::Asciidoc,
+            rst: options.format == OutputFormat::Rst,
+            org: options.format == OutputFormat::Org,
+            gfm: options.format == OutputFormat::Markdown && options.flavor == Flavor::Gfm,
+            markdown: options.escape_markdown,
+            inline_html: options.allow_inline_html,
+        }
+    }
+
+    /// 无样式的文字：HTML 输出与不转义 Markdown 时转义 HTML 特殊字符，否则转义 Markdown 标记字符
+    fn plain(self, text: &str, line_start: bool) -> String {
+        if self.latex {
+            return escape_latex(text);
+        }
+        if self.asciidoc {
+            return escape_asciidoc(text, line_start);
+        }
+        if self.rst {
+            return escape_rst(text, line_start);
+        }
+        if self.org {
+            return escape_org(text, line_start);
+        }
+        if !self.inline_html {
+            return match (self.html, self.markdown) {
+                (false, true) => escape_markdown(text, line_start),
+                _ => escape_html(text),
+            };
+        }
+        let mut out = String::with_capacity(text.len());
+        for (markup, piece) in html_pieces(text) {
+            if markup || (!self.html && !self.markdown) {
+                out.push_str(piece);
+            } else if self.html {
+                out.push_str(&escape_html(piece));
+            } else {
+                out.push_str(&escape_markdown(piece, line_start && out.is_empty()));
+            }
+        }
+        out
+    }
+
+    /// 低分文字的高亮标记：Markdown 与 HTML 为带分数提示的 `<mark>`，Gfm 风格为 `==`，
+    /// AsciiDoc 为 `##`；LaTeX、reStructuredText 与 Org 不高亮
+    fn highlight(self, score: f64) -> Option<(String, String)> {
+        if self.latex || self.rst || self.org {
+            None
+        } else if self.asciidoc {
+            Some(("##".to_string(), "##".to_string()))
+        } else if self.gfm {
+            Some(("==".to_string(), "==".to_string()))
+        } else {
+            Some((
+                format!("<mark title=\"score {:.2}\">", score),
+                "</mark>".to_string(),
+            ))
+        }
+    }
+
+    /// 上标、下标的内容，放在 HTML 标签之内
+    fn tagged(self, text: &str) -> String {
+        if self.inline_html {
+            text.to_string()
+        } else {
+            escape_html(text)
+        }
+    }
+
+    /// 粗体、斜体的内容；不转义 Markdown 时只转义反斜杠与星号
+    fn emphasized(self, text: &str) -> String {
+        let body = self.plain(text, false);
+        if self.html || self.markdown {
+            body
+        } else {
+            body.replace('\\', "\\\\").replace('*', "\\*")
+        }
+    }
+}
+
+/// 正文文字按输出格式转义：Markdown 字符或 HTML 特殊字符，允许行内 HTML 时保留其中的标签
+pub(crate) fn escape_text(text: &str, line_start: bool, options: &ConvertOptions) -> String {
+    SpanEscape::of(options).plain(text, line_start)
+}
+
+/// 文字按 HTML 标签（`<i>`、`</div>`、`<!-- -->`）与字符实体（`&amp;`）切分，
+/// 返回各段及其是否为标记
+fn html_pieces(text: &str) -> Vec<(bool, &str)> {
+    let mut pieces = Vec::new();
+    let mut start = 0;
+    let mut i = 0;
+    while i < text.len() {
+        let rest = &text[i..];
+        let end = if let Some(tag) = rest.strip_prefix('<') {
+            tag.starts_with(|c: char| c.is_ascii_alphabetic() || "/!?".contains(c))
+                .then(|| tag.find(['<', '>']))
+                .flatten()
+                .filter(|&end| tag[end..].starts_with('>'))
+                .map(|end| end + 2)
+        } else if let Some(entity) = rest.strip_prefix('&') {
+            is_entity(entity).then(|| entity.find(';').map_or(0, |end| end + 2))
+        } else {
+            None
+        };
+        match end {
+            Some(end) => {
+                if start < i {
+                    pieces.push((false, &text[start..i]));
+                }
+                pieces.push((true, &rest[..end]));
+                i += end;
+                start = i;
+            }
+            None => i += rest.chars().next().map_or(1, char::len_utf8),
+        }
+    }
+    if start < text.len() {
+        pieces.push((false, &text[start..]));
+    }
+    pieces
+}
+
+/// 输出一段带样式的文本：首尾空白移到标记之外，Markdown 字符转义（`escape_markdown` 关闭时只转义星号）；
+/// 紧跟在另一个星号标记之后时改用 HTML 标签，避免 `**a***b*` 之类的歧义。
+/// HTML 输出时整段转义，粗体、斜体一律使用 HTML 标签；除非允许行内 HTML，文字中的 `<`、`&` 总会转义。
+/// 低分的文字放进高亮标记之内（见 [`ConvertOptions::min_confidence`]）
+fn push_styled(out: &mut String, style: InlineStyle, text: &str, escape: SpanEscape) {
+    let inner = text.trim();
+    if let Some(score) = style.low_confidence {
+        let style = InlineStyle {
+            low_confidence: None,
+            ..style
+        };
+        match escape.highlight(score).filter(|_| !inner.is_empty()) {
+            Some((open, close)) => {
+                out.push_str(&text[..text.len() - text.trim_start().len()]);
+                out.push_str(&open);
+                push_styled(out, style, inner, escape);
+                out.push_str(&close);
+                out.push_str(&text[text.trim_end().len()..]);
+            }
+            None => push_styled(out, style, text, escape),
+        }
+        return;
+    }
+    if style == InlineStyle::default() || inner.is_empty() {
+        let line_start = out.trim().is_empty() || out.ends_with('\n');
+        let text = escape.plain(text, line_start);
+        if escape.rst {
+            rst::push_text(out, &text);
+        } else {
+            out.push_str(&text);
+        }
+        return;
+    }
+    let leading = &text[..text.len() - text.trim_start().len()];
+    let trailing = &text[text.trim_end().len()..];
+
+    // LaTeX 的样式命令可以任意嵌套，不存在星号标记的歧义
+    if escape.latex {
+        let mut body = escape_latex(inner);
+        for (enabled, command) in [
+            (style.superscript, "textsuperscript"),
+            (style.subscript, "textsubscript"),
+            (style.italic, "emph"),
+            (style.bold, "textbf"),
+        ] {
+            if enabled {
+                body = format!("\\{}{{{}}}", command, body);
+            }
+        }
+        out.push_str(leading);
+        out.push_str(&body);
+        out.push_str(trailing);
+        return;
+    }
+
+    // AsciiDoc 的非受限标记（`**`、`__`）可以出现在词中；上标、下标内不能有空格，以 `{nbsp}` 代替
+    if escape.asciidoc {
+        let mut body = escape_asciidoc(inner, false);
+        if style.superscript || style.subscript {
+            let mark = if style.superscript { '^' } else { '~' };
+            let words: Vec<&str> = body.split_whitespace().collect();
+            body = format!("{}{}{}", mark, words.join("{nbsp}"), mark);
+        }
+        if style.italic {
+            body = format!("__{}__", body);
+        }
+        if style.bold {
+            body = format!("**{}**", body);
+        }
+        out.push_str(leading);
+        out.push_str(&body);
+        out.push_str(trailing);
+        return;
+    }
+
+    // reStructuredText 的强调不能嵌套，粗斜体只保留粗体；上标、下标为角色，优先于强调
+    if escape.rst {
+        let body = if style.superscript || style.subscript {
+            let role = if style.superscript { "sup" } else { "sub" };
+            format!(":{}:`{}`", role, rst::interpreted_text(inner))
+        } else if style.bold {
+            format!("**{}**", escape_rst(inner, false))
+        } else {
+            format!("*{}*", escape_rst(inner, false))
+        };
+        rst::push_text(out, leading);
+        rst::push_markup(out, &body);
+        out.push_str(trailing);
+        return;
+    }
+
+    let mut body = if style.superscript || style.subscript {
+        let tag = if style.superscript { "sup" } else { "sub" };
+        format!("<{}>{}</{}>", tag, escape.tagged(inner), tag)
+    } else {
+        escape.emphasized(inner)
+    };
+
+    let use_html = escape.html || (leading.is_empty() && out.ends_with('*'));
+    let (open, close) = match (style.bold, style.italic, use_html) {
+        (false, false, _) => ("", ""),
+        (true, true, false) => ("***", "***"),
+        (true, false, false) => ("**", "**"),
+        (false, true, false) => ("*", "*"),
+        (true, true, true) => ("<strong><em>", "</em></strong>"),
+        (true, false, true) => ("<strong>", "</strong>"),
+        (false, true, true) => ("<em>", "</em>"),
+    };
+    body = format!("{}{}{}", open, body, close);
+
+    out.push_str(leading);
+    out.push_str(&body);
+    out.push_str(trailing);
+}
+
+/// Org 中带样式的文本：强调的前后必须是空白或标点，上标 `^{…}`、下标 `_{…}` 之前必须紧接非空白字符，
+/// 不满足时（如词中的粗体）输出为无样式的文字；粗斜体为嵌套的 `*/…/*`，上标、下标优先于强调。
+/// `next` 为这段文字之后的第一个字符，在段落末尾时为 `None`
+fn push_org_styled(out: &mut String, style: InlineStyle, text: &str, next: Option<char>) {
+    let inner = text.trim();
+    let leading = &text[..text.len() - text.trim_start().len()];
+    let trailing = &text[text.trim_end().len()..];
+    let before = if leading.is_empty() {
+        out.chars().next_back()
+    } else {
+        Some(' ')
+    };
+    let after = if trailing.is_empty() { next } else { Some(' ') };
+    let body = if inner.is_empty() {
+        None
+    } else if style.superscript || style.subscript {
+        let mark = if style.superscript { '^' } else { '_' };
+        (before.is_some_and(|c| !c.is_whitespace()) && !inner.contains(['{', '}']))
+            .then(|| format!("{}{{{}}}", mark, escape_org(inner, false)))
+    } else if style.bold || style.italic {
+        (before.is_none_or(org::opens_markup) && after.is_none_or(org::closes_markup)).then(|| {
+            let mut body = escape_org(inner, false);
+            if style.italic {
+                body = format!("/{}/", body);
+            }
+            if style.bold {
+                body = format!("*{}*", body);
+            }
+            body
+        })
+    } else {
+        None
+    };
+    match body {
+        Some(body) => {
+            out.push_str(leading);
+            out.push_str(&body);
+            out.push_str(trailing);
+        }
+        None => {
+            let line_start = out.trim().is_empty() || out.ends_with('\n');
+            out.push_str(&escape_org(text, line_start));
+        }
+    }
+}
+
+/// 整理 MinerU 给出的 LaTeX：去掉首尾空白和部分版本自带的 `$$` / `$`（否则会输出 `$$$$x$$$$`）；
+/// 只去掉包围整个公式的一对分隔符，`$a$ + $b$` 这样的内容不变（行内公式见 [`inline_equation_pieces`]）。
+/// 行内公式的换行合并为空格
+pub(crate) fn normalize_latex(content: &str, inline: bool) -> String {
+    let mut latex = content.trim();
+    for delimiter in ["$$", "$"] {
+        if let Some(inner) = latex
+            .strip_prefix(delimiter)
+            .and_then(|rest| rest.strip_suffix(delimiter))
+            .filter(|inner| !inner.replace("\\$", "").contains('$'))
+        {
+            latex = inner.trim();
+            break;
+        }
+    }
+    if inline {
+        latex
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .collect::<Vec<_>>()
+            .join(" ")
+    } else {
+        latex.to_string()
+    }
+}
+
+/// 行内公式 span 的内容：通常为一个公式；内容本身由成对的 `$…$` 与其间的文字组成时
+/// （如 `$a$ + $b$`），拆成依次排列的各个公式与文字，避免再包一层分隔符变成 `$$a$ + $b$$`。
+/// 内容为空的公式不输出
+pub(crate) fn inline_equation_pieces(content: &str) -> Vec<Inline> {
+    let latex = normalize_latex(content, true);
+    // 按未转义的 `$` 切分；奇数段为公式
+    let mut parts = Vec::new();
+    let mut start = 0;
+    let mut escaped = false;
+    for (i, c) in latex.char_indices() {
+        match c {
+            '\\' => escaped = !escaped,
+            '$' if !escaped => {
+                parts.push(&latex[start..i]);
+                start = i + 1;
+            }
+            _ => escaped = false,
+        }
+    }
+    parts.push(&latex[start..]);
+    if parts.len() == 1 || parts.len() % 2 == 0 {
+        return if latex.is_empty() {
+            Vec::new()
+        } else {
+            vec![Inline::Math { latex }]
+        };
+    }
+    let mut inlines = Vec::new();
+    for (i, part) in parts.into_iter().enumerate() {
+        if i % 2 == 1 {
+            let latex = part.trim();
+            if !latex.is_empty() {
+                inlines.push(Inline::Math {
+                    latex: latex.to_string(),
+                });
+            }
+        } else if !part.is_empty() {
+            push_text(&mut inlines, InlineStyle::default(), part);
+        }
+    }
+    inlines
+}
+
+/// 按 math_delimiters 包围行内公式；纯文本与 LaTeX 输出始终使用 `$…$`，AsciiDoc 输出为 `stem:[…]`，
+/// reStructuredText 输出为 `:math:` 角色，Org 输出见 [`org::inline_math`]；
+/// Markdown 输出且 inline_math_style 为 Html 时见 [`markdown_math_span`]
+pub(crate) fn inline_math(latex: &str, options: &ConvertOptions) -> String {
+    if options.format == OutputFormat::Markdown
+        && options.inline_math_style == InlineMathStyle::Html
+    {
+        return markdown_math_span(latex);
+    }
+    let latex_delimiters = options.math_delimiters == MathDelimiters::Latex
+        && matches!(options.format, OutputFormat::Markdown | OutputFormat::Html);
+    let (open, close) = if latex_delimiters {
+        ("\\(", "\\)")
+    } else {
+        ("$", "$")
+    };
+    match options.format {
+        OutputFormat::Html => format!(
+            "<span class=\"math\">{}{}{}</span>",
+            open,
+            escape_html(latex),
+            close
+        ),
+        OutputFormat::Asciidoc => format!("stem:[{}]", latex.replace(']', "\\]")),
+        OutputFormat::Rst => format!(":math:`{}`", latex),
+        OutputFormat::Org => org::inline_math(latex, None),
+        OutputFormat::Markdown | OutputFormat::Text | OutputFormat::Latex | OutputFormat::Json => {
+            format!("{}{}{}", open, latex, close)
+        }
+    }
+}
+
+/// Markdown 中的 `<span class="math">\(…\)</span>`：span 内的文字仍按 Markdown 解析，
+/// 反斜杠、`*`、`_` 等标记字符先转义，`<`、`>`、`&` 写成实体
+fn markdown_math_span(latex: &str) -> String {
+    let mut out = String::from("<span class=\"math\">\\\\(");
+    for c in latex.chars() {
+        match c {
+            '\\' | '*' | '_' | '`' | '[' | ']' | '~' | '|' => {
+                out.push('\\');
+                out.push(c);
+            }
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '&' => out.push_str("&amp;"),
+            c => out.push(c),
+        }
+    }
+    out.push_str("\\\\)</span>");
+    out
+}
+
+/// Markdown 输出中行内公式的 `$` 紧挨字母、数字或汉字时是否在外侧补空格：GitHub 等渲染器
+/// 不把这样的 `$` 当作公式分隔符。`\(…\)` 分隔符、HTML 写法与 Obsidian 风格不受影响
+fn spaces_inline_math(options: &ConvertOptions) -> bool {
+    options.format == OutputFormat::Markdown
+        && options.flavor != Flavor::Obsidian
+        && options.math_delimiters != MathDelimiters::Latex
+        && options.inline_math_style == InlineMathStyle::Delimiters
+}
+
+/// 按 math_delimiters 输出行间公式块；LaTeX 与 Org 输出始终使用 `\[…\]`，AsciiDoc 输出为 `[stem]` 块，
+/// reStructuredText 输出为 `.. math::` 指令
+pub(crate) fn display_math(latex: &str, options: &ConvertOptions) -> String {
+    let (open, close) = match options.math_delimiters {
+        MathDelimiters::Dollars | MathDelimiters::Brackets => ("$$", "$$"),
+        MathDelimiters::Latex => ("\\[", "\\]"),
+    };
+    match options.format {
+        OutputFormat::Html => format!(
+            "<p><span class=\"math display\">{}\n{}\n{}</span></p>\n\n",
+            open,
+            escape_html(latex),
+            close
+        ),
+        OutputFormat::Latex => format!("\\[\n{}\n\\]\n\n", latex),
+        OutputFormat::Asciidoc => format!("[stem]\n{}\n", delimited_block('+', latex)),
+        OutputFormat::Rst => format!("{}\n", rst::math_block(latex)),
+        OutputFormat::Org => format!("{}\n", org::math_block(latex)),
+        _ if options.math_delimiters == MathDelimiters::Brackets => {
+            format!("{}\n{}\n{}\n\n", open, latex, close)
+        }
+        OutputFormat::Markdown | OutputFormat::Text | OutputFormat::Json => {
+            format!("\n{}\n{}\n{}\n\n", open, latex, close)
+        }
+    }
+}
+
+/// 段落中的文字与行内公式：相邻的同样式文字合并，跨行处按需补空格或去掉断词的连字符
+pub(crate) fn rich_inlines(block: &Block, options: &ConvertOptions) -> Vec<Inline> {
+    let mut inlines: Vec<Inline> = Vec::new();
+
+    for line in block.lines.iter().flatten() {
+        let mut line_inlines: Vec<Inline> = Vec::new();
+        for span in &line.spans {
+            let Some(content) = &span.content else {
+                continue;
+            };
+            if span.span_type == "inline_equation" {
+                for inline in inline_equation_pieces(content) {
+                    match inline {
+                        Inline::Text { text, style } => push_text(&mut line_inlines, style, &text),
+                        math => line_inlines.push(math),
+                    }
+                }
+            } else if let Some(mut style) = InlineStyle::of(span) {
+                style.low_confidence = options
+                    .min_confidence
+                    .and_then(|threshold| span.score.filter(|&score| score < threshold));
+                push_text(&mut line_inlines, style, content);
+            }
+        }
+
+        // 跨行拼接：断词时去掉上一行末尾的连字符，需要空格时以无样式空格连接；
+        // 开启 autolink 时在行尾断开的网址直接接回
+        let (Some(prev), Some(next)) = (inlines.last_mut(), line_inlines.first()) else {
+            inlines.extend(line_inlines);
+            continue;
+        };
+        let joint = if options.autolink && continues_link(inline_raw(prev), inline_raw(next)) {
+            Joint::None
+        } else {
+            line_joint(inline_raw(prev), inline_raw(next), options.dehyphenate)
+        };
+        match joint {
+            Joint::DropHyphen => {
+                if let Inline::Text { text, .. } = prev {
+                    text.pop();
+                }
+            }
+            Joint::Space => push_text(&mut inlines, InlineStyle::default(), " "),
+            Joint::None => {}
+        }
+        for inline in line_inlines {
+            match inline {
+                Inline::Text { text, style } => push_text(&mut inlines, style, &text),
+                math => inlines.push(math),
+            }
+        }
+    }
+    if options.pangu_spacing {
+        inlines = pangu_inlines(inlines);
+    }
+    inlines
+}
+
+// ==================== 中西文间距 ====================
+
+/// 中日韩文字，不含全角标点与全角字母、数字
+fn is_cjk_letter(c: char) -> bool {
+    is_cjk(c) && !matches!(c, '\u{3000}'..='\u{303f}' | '\u{ff00}'..='\u{ffef}')
+}
+
+/// 两个字符之间是否需要补空格：中日韩文字与拉丁字母、数字相接，或数字后的 `%` 与中日韩文字相接
+fn needs_pangu_space(prev: char, next: char) -> bool {
+    (is_cjk_letter(prev) && next.is_ascii_alphanumeric())
+        || ((prev.is_ascii_alphanumeric() || prev == '%') && is_cjk_letter(next))
+}
+
+/// 在中日韩文字与拉丁字母、数字相接处插入一个空格（"使用GPU加速" → "使用 GPU 加速"，
+/// "提升30%的" → "提升 30% 的"）；已有空格及全角标点两侧不加
+///
+/// autolink 识别的网址与邮箱只含 ASCII 字符、遇到中日韩文字即结束，空格只会出现在链接之外。
+pub(crate) fn pangu_spacing(text: &str) -> String {
+    let mut out = String::with_capacity(text.len() + 8);
+    let mut prev = None;
+    for c in text.chars() {
+        if prev.is_some_and(|prev| needs_pangu_space(prev, c)) {
+            out.push(' ');
+        }
+        out.push(c);
+        prev = Some(c);
+    }
+    out
+}
+
+/// 段落文字的中西文间距：各段文字内部补空格；不同样式的两段文字相接处补一个无样式空格，
+/// 以免空格落进加粗等标记之内。行内公式及其两侧不变
+fn pangu_inlines(inlines: Vec<Inline>) -> Vec<Inline> {
+    let mut spaced: Vec<Inline> = Vec::with_capacity(inlines.len());
+    for inline in inlines {
+        let Inline::Text { text, style } = inline else {
+            spaced.push(inline);
+            continue;
+        };
+        let text = pangu_spacing(&text);
+        if let Some(Inline::Text { text: prev, .. }) = spaced.last()
+            && let (Some(last), Some(first)) = (prev.chars().next_back(), text.chars().next())
+            && needs_pangu_space(last, first)
+        {
+            push_text(&mut spaced, InlineStyle::default(), " ");
+        }
+        push_text(&mut spaced, style, &text);
+    }
+    spaced
+}
+
+/// 输出带行内样式和行内公式的段落文字
+///
+/// HTML 输出时行内公式放进 `<span class="math">`，便于 MathJax 等脚本识别；
+/// 纯文本与 JSON 输出不加任何样式标记，行内公式保留 `$…$` 包围的 LaTeX；行内公式不做 Markdown 转义。
+/// 开启 autolink 时无样式文字中的网址、邮箱与 DOI 写成链接
+pub(crate) fn render_inlines(inlines: &[Inline], options: &ConvertOptions) -> String {
+    let escape = SpanEscape::of(options);
+    let mut html = String::new();
+    for (i, inline) in inlines.iter().enumerate() {
+        // Org 的行内标记是否成立取决于其后的字符；其后为公式时按 `$` 处理
+        let next = match inlines.get(i + 1) {
+            Some(Inline::Text { text, .. }) => text.chars().next(),
+            Some(Inline::Math { .. }) => Some('$'),
+            None => None,
+        };
+        match inline {
+            Inline::Text { text, .. }
+                if matches!(options.format, OutputFormat::Text | OutputFormat::Json) =>
+            {
+                html.push_str(text)
+            }
+            Inline::Text { text, style }
+                if options.autolink && *style == InlineStyle::default() =>
+            {
+                push_autolinked(&mut html, text, escape, options)
+            }
+            Inline::Text { text, style } if escape.org => {
+                push_org_styled(&mut html, *style, text, next)
+            }
+            Inline::Text { text, style } => push_styled(&mut html, *style, text, escape),
+            Inline::Math { latex } if options.format == OutputFormat::Rst => {
+                rst::push_markup(&mut html, &inline_math(latex, options))
+            }
+            Inline::Math { latex } if escape.org => html.push_str(&org::inline_math(latex, next)),
+            Inline::Math { latex } if spaces_inline_math(options) => {
+                if html.chars().last().is_some_and(char::is_alphanumeric) {
+                    html.push(' ');
+                }
+                html.push_str(&inline_math(latex, options));
+                // 紧接下一个公式时同样隔开，避免两个 `$` 连成 `$$`
+                if next.is_some_and(|c| c.is_alphanumeric() || c == '$') {
+                    html.push(' ');
+                }
+            }
+            Inline::Math { latex } => html.push_str(&inline_math(latex, options)),
+        }
+    }
+    html
+}
+
+// ==================== 自动链接 ====================
+
+/// 按输出格式写出链接；网址与邮箱使用各格式的自动链接写法，DOI 保留编号作为链接文字
+pub(crate) fn link_markup(text: &str, href: &str, options: &ConvertOptions) -> String {
+    let bare = href == text || href.strip_prefix("mailto:") == Some(text);
+    match options.format {
+        OutputFormat::Html => format!(
+            "<a href=\"{}\">{}</a>",
+            escape_html(href).replace('"', "&quot;"),
+            escape_html(text)
+        ),
+        OutputFormat::Latex if href == text => format!("\\url{{{}}}", escape_url(href)),
+        OutputFormat::Latex => format!("\\href{{{}}}{{{}}}", escape_url(href), escape_latex(text)),
+        OutputFormat::Asciidoc if href == text => format!("link:++{}++[]", href),
+        OutputFormat::Asciidoc => format!("link:++{}++[{}]", href, macro_text(text)),
+        OutputFormat::Rst => rst::hyperlink(text, href),
+        OutputFormat::Org => org::link(text, href),
+        OutputFormat::Markdown if bare => format!("<{}>", text),
+        OutputFormat::Markdown => format!(
+            "[{}](<{}>)",
+            escape_markdown(text, false)
+                .replace('[', "\\[")
+                .replace(']', "\\]"),
+            href
+        ),
+        OutputFormat::Text | OutputFormat::Json => text.to_string(),
+    }
+}
+
+/// 无样式文字中的链接写成 [`link_markup`]，其余文字照常转义；
+/// 允许行内 HTML 时标签原样保留，标签之内与 `<a>` 元素之中的文字不加链接
+fn push_autolinked(out: &mut String, text: &str, escape: SpanEscape, options: &ConvertOptions) {
+    let plain = InlineStyle::default();
+    let pieces = if escape.inline_html {
+        html_pieces(text)
+    } else {
+        vec![(false, text)]
+    };
+    let mut in_anchor = false;
+    for (markup, piece) in pieces {
+        if markup {
+            let tag = piece.to_ascii_lowercase();
+            if tag.starts_with("<a ") || tag == "<a>" {
+                in_anchor = true;
+            } else if tag.starts_with("</a") {
+                in_anchor = false;
+            }
+            out.push_str(piece);
+            continue;
+        }
+        if in_anchor {
+            push_styled(out, plain, piece, escape);
+            continue;
+        }
+        for piece in autolink_pieces(piece) {
+            match piece {
+                Piece::Text(text) => push_styled(out, plain, text, escape),
+                Piece::Link(text, href) if escape.rst => {
+                    rst::push_markup(out, &link_markup(text, &href, options))
+                }
+                Piece::Link(text, href) => out.push_str(&link_markup(text, &href, options)),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{
+        bare_options, image_block, lines_block, markdown, page_layout, text_block,
+    };
+    use serde_json::{Value, json};
+
+    /// 一个段落的输出，`spans` 依次放在同一行
+    fn paragraph(spans: Value) -> String {
+        paragraph_with(spans, &bare_options())
+    }
+
+    fn paragraph_with(spans: Value, options: &ConvertOptions) -> String {
+        let bbox = [50.0, 100.0, 550.0, 120.0];
+        let layout = page_layout(json!([{
+            "type": "text",
+            "bbox": bbox,
+            "lines": [{"bbox": bbox, "spans": spans}]
+        }]));
+        markdown(&layout, options).trim().to_string()
+    }
+
+    fn text(content: &str) -> Value {
+        span(content, json!({}))
+    }
+
+    fn span(content: &str, style: Value) -> Value {
+        let mut span =
+            json!({"type": "text", "bbox": [50.0, 100.0, 550.0, 120.0], "content": content});
+        span.as_object_mut()
+            .unwrap()
+            .extend(style.as_object().unwrap().clone());
+        span
+    }
+
+    #[test]
+    fn adjacent_spans_with_the_same_style_share_one_marker() {
+        assert_eq!(
+            paragraph(json!([
+                span("plain ", json!({})),
+                span("bold", json!({"bold": true})),
+                span(" text", json!({"bold": true})),
+                span(" after", json!({})),
+            ])),
+            "plain **bold text** after"
+        );
+    }
+
+    #[test]
+    fn mixed_bold_italic_runs_keep_spaces_outside_the_markers() {
+        assert_eq!(
+            paragraph(json!([
+                span("bold", json!({"bold": true})),
+                span(" both ", json!({"bold": true, "italic": true})),
+                span("italic", json!({"italic": true})),
+            ])),
+            "**bold** ***both*** *italic*"
+        );
+        // 紧接在星号标记之后没有空格时改用 HTML 标签
+        assert_eq!(
+            paragraph(json!([
+                span("bold", json!({"bold": true})),
+                span("italic", json!({"italic": true})),
+            ])),
+            "**bold**<em>italic</em>"
+        );
+    }
+
+    #[test]
+    fn styled_span_types_and_flags_are_recognized() {
+        assert_eq!(
+            paragraph(json!([
+                span("x", json!({})),
+                span("2", json!({"sup": true})),
+                span(" H", json!({})),
+                span("2", json!({"type": "sub"})),
+                span("O ", json!({})),
+                span("strong", json!({"type": "text_strong"})),
+                span(" ", json!({})),
+                span("emphasis", json!({"type": "text_emphasis"})),
+            ])),
+            "x<sup>2</sup> H<sub>2</sub>O **strong** *emphasis*"
+        );
+    }
+
+    #[test]
+    fn asterisks_inside_emphasis_are_escaped() {
+        assert_eq!(
+            paragraph(json!([span("a*b", json!({"bold": true}))])),
+            "**a\\*b**"
+        );
+    }
+
+    #[test]
+    fn markdown_control_characters_are_escaped_where_active() {
+        assert_eq!(
+            paragraph(json!([text("The C* radical, H_2O_2 and a\\b path")])),
+            "The C\\* radical, H_2O_2 and a\\\\b path"
+        );
+        assert_eq!(
+            paragraph(json!([text("# not a heading")])),
+            "\\# not a heading"
+        );
+        assert_eq!(paragraph(json!([text("1. not a list")])), "1\\. not a list");
+        assert_eq!(paragraph(json!([text("- not a list")])), "\\- not a list");
+    }
+
+    #[test]
+    fn inline_equations_are_not_escaped() {
+        assert_eq!(
+            paragraph(json!([
+                text("x "),
+                {"type": "inline_equation", "bbox": [0, 0, 1, 1], "content": "a*b_c"},
+                text(" y*"),
+            ])),
+            "x $a*b_c$ y\\*"
+        );
+    }
+
+    #[test]
+    fn no_escape_keeps_intentional_markdown() {
+        let options = ConvertOptions {
+            escape_markdown: false,
+            ..bare_options()
+        };
+        assert_eq!(
+            paragraph_with(json!([text("**bold** and # text")]), &options),
+            "**bold** and # text"
+        );
+    }
+
+    #[test]
+    fn pipes_and_asterisks_in_table_captions_are_escaped() {
+        let caption = json!({
+            "type": "table_caption",
+            "bbox": [100, 420, 500, 435],
+            "lines": [{"bbox": [100, 420, 500, 435], "spans": [text("Table 1. A | B of C*")]}]
+        });
+        let table = json!({
+            "type": "table",
+            "bbox": [100, 420, 500, 600],
+            "blocks": [caption, {
+                "type": "table_body",
+                "bbox": [100, 440, 500, 600],
+                "lines": [{"bbox": [100, 440, 500, 600], "spans": [{
+                    "type": "table",
+                    "bbox": [100, 440, 500, 600],
+                    "content": "<table><tr><td>a|b</td><td>2</td></tr></table>"
+                }]}]
+            }]
+        });
+        let options = ConvertOptions {
+            flavor: Flavor::Gfm,
+            table_format: crate::TableFormat::Markdown,
+            ..bare_options()
+        };
+        let markdown = markdown(&page_layout(json!([table])), &options);
+        assert!(
+            markdown.contains("Table 1. A \\| B of C\\*"),
+            "{}",
+            markdown
+        );
+        assert!(markdown.contains("| a\\|b | 2 |"), "{}", markdown);
+    }
+
+    #[test]
+    fn delimiters_already_in_the_content_are_stripped() {
+        assert_eq!(normalize_latex("$$x$$", false), "x");
+        assert_eq!(normalize_latex("  $$ E = mc^2 $$\n", false), "E = mc^2");
+        assert_eq!(normalize_latex("$a$", true), "a");
+        // 只去掉包围整个公式的分隔符
+        assert_eq!(normalize_latex("$a$ + $b$", true), "$a$ + $b$");
+        assert_eq!(normalize_latex("$\\$5$", true), "\\$5");
+        assert_eq!(normalize_latex("x^{$}", true), "x^{$}");
+    }
+
+    #[test]
+    fn inline_equations_with_several_segments_are_split() {
+        let spans = json!([text("由"), math("$a$ + $b$"), text("可得")]);
+        assert_eq!(paragraph(spans.clone()), "由 $a$ + $b$ 可得");
+        let plain = ConvertOptions {
+            format: OutputFormat::Text,
+            ..bare_options()
+        };
+        assert_eq!(paragraph_with(spans, &plain), "由$a$ + $b$可得");
+        // 转义的 `$` 或不成对的 `$` 不拆分
+        assert_eq!(
+            inline_equation_pieces("$\\$5 + \\$6$"),
+            [Inline::Math {
+                latex: "\\$5 + \\$6".to_string()
+            }]
+        );
+        assert_eq!(
+            inline_equation_pieces("x^{$}"),
+            [Inline::Math {
+                latex: "x^{$}".to_string()
+            }]
+        );
+        assert!(inline_equation_pieces(" $ $ ").is_empty());
+    }
+
+    #[test]
+    fn inline_equations_collapse_newlines() {
+        assert_eq!(normalize_latex("a +\n  b\n\n", true), "a + b");
+        assert_eq!(
+            normalize_latex("\\begin{cases}\nx\n\\end{cases}", false),
+            "\\begin{cases}\nx\n\\end{cases}"
+        );
+    }
+
+    #[test]
+    fn math_delimiters_apply_to_inline_and_display_equations() {
+        let bbox = [50.0, 100.0, 550.0, 120.0];
+        let layout = page_layout(json!([
+            {"type": "text", "bbox": bbox, "lines": [{"bbox": bbox, "spans": [
+                text("see "),
+                {"type": "inline_equation", "bbox": bbox, "content": "$a +\n b$"},
+                text(" here"),
+            ]}]},
+            {"type": "interline_equation", "bbox": [50, 200, 550, 240], "lines": [{"bbox": bbox, "spans": [
+                {"type": "interline_equation", "bbox": bbox, "content": "$$ E = mc^2 $$"}
+            ]}]},
+            {"type": "text", "bbox": [50, 300, 550, 320], "lines": [{"bbox": bbox, "spans": [text("after")]}]},
+        ]));
+        let convert = |math_delimiters| {
+            let options = ConvertOptions {
+                math_delimiters,
+                ..bare_options()
+            };
+            markdown(&layout, &options)
+        };
+        assert_eq!(
+            convert(MathDelimiters::Dollars),
+            "see $a + b$ here\n\n\n$$\nE = mc^2\n$$\n\nafter\n\n"
+        );
+        assert_eq!(
+            convert(MathDelimiters::Latex),
+            "see \\(a + b\\) here\n\n\n\\[\nE = mc^2\n\\]\n\nafter\n\n"
+        );
+        assert_eq!(
+            convert(MathDelimiters::Brackets),
+            "see $a + b$ here\n\n$$\nE = mc^2\n$$\n\nafter\n\n"
+        );
+    }
+
+    const RAW_HTML: &str = "code: </div> <script>alert(1)</script> Tom &amp; Jerry <i>it</i>";
+
+    #[test]
+    fn raw_html_in_text_spans_is_escaped() {
+        assert_eq!(
+            paragraph(json!([text(RAW_HTML)])),
+            "code: \\</div> \\<script>alert(1)\\</script> Tom \\&amp; Jerry \\<i>it\\</i>"
+        );
+        // 不转义 Markdown 时仍转为 HTML 实体，`&amp;` 原样显示
+        let options = ConvertOptions {
+            escape_markdown: false,
+            ..bare_options()
+        };
+        assert_eq!(
+            paragraph_with(json!([text(RAW_HTML)]), &options),
+            "code: &lt;/div&gt; &lt;script&gt;alert(1)&lt;/script&gt; Tom &amp;amp; Jerry &lt;i&gt;it&lt;/i&gt;"
+        );
+        let options = ConvertOptions {
+            format: OutputFormat::Html,
+            ..bare_options()
+        };
+        assert!(paragraph_with(json!([text(RAW_HTML)]), &options).contains(
+            "<p>code: &lt;/div&gt; &lt;script&gt;alert(1)&lt;/script&gt; Tom &amp;amp; Jerry"
+        ));
+    }
+
+    #[test]
+    fn allow_inline_html_passes_markup_through() {
+        let options = ConvertOptions {
+            allow_inline_html: true,
+            ..bare_options()
+        };
+        assert_eq!(paragraph_with(json!([text(RAW_HTML)]), &options), RAW_HTML);
+    }
+
+    #[test]
+    fn equations_and_captions_are_escaped_once() {
+        assert_eq!(
+            paragraph(json!([
+                text("x "),
+                {"type": "inline_equation", "bbox": [0, 0, 1, 1], "content": "a<b"},
+            ])),
+            "x $a<b$"
+        );
+        let mut figure = image_block([100.0, 160.0, 500.0, 380.0], "missing.png");
+        figure["blocks"].as_array_mut().unwrap().push(text_block(
+            "image_caption",
+            [100.0, 385.0, 500.0, 400.0],
+            "A < B &amp; C </div>",
+        ));
+        let markdown = markdown(&page_layout(json!([figure])), &bare_options());
+        assert!(
+            markdown.contains(">A &lt; B &amp;amp; C &lt;/div&gt;</figcaption>"),
+            "{}",
+            markdown
+        );
+        assert!(!markdown.contains("&amp;lt;"));
+    }
+
+    fn joined(lines: &[&str], dehyphenate: bool) -> String {
+        let block: Block = serde_json::from_value(lines_block("text", lines)).unwrap();
+        extract_text_from_block(&block, dehyphenate)
+    }
+
+    #[test]
+    fn hyphenated_line_breaks_are_joined() {
+        assert_eq!(
+            joined(&["the transfor-", "mation is"], true),
+            "the transformation is"
+        );
+        assert_eq!(
+            joined(&["the transfor-", "mation is"], false),
+            "the transfor-mation is"
+        );
+        // 下一行以大写字母开头时是真正的连字符
+        assert_eq!(joined(&["Jean-", "Paul"], true), "Jean-Paul");
+    }
+
+    #[test]
+    fn latin_lines_are_joined_with_one_space() {
+        assert_eq!(
+            joined(&["first line", "second line"], true),
+            "first line second line"
+        );
+        assert_eq!(
+            joined(&["ends with space ", "next"], true),
+            "ends with space next"
+        );
+    }
+
+    #[test]
+    fn cjk_lines_are_joined_without_spaces() {
+        assert_eq!(joined(&["中文第一行", "第二行"], true), "中文第一行第二行");
+        assert_eq!(joined(&["使用", "GPU 加速"], true), "使用GPU 加速");
+        assert_eq!(joined(&["GPU", "加速"], true), "GPU加速");
+    }
+
+    #[test]
+    fn github_slugs_keep_letters_of_every_script() {
+        assert_eq!(
+            github_slug("Getting Started: Setup & Use"),
+            "getting-started-setup--use"
+        );
+        assert_eq!(github_slug(" C++ Guide "), "c-guide");
+        assert_eq!(github_slug("第一章 概述（上）"), "第一章-概述上");
+        assert_eq!(github_slug("Über Café"), "über-café");
+        assert_eq!(github_slug("snake_case-name"), "snake_case-name");
+
+        let mut anchors = AnchorRegistry::default();
+        let issued: Vec<String> = ["intro", "intro", "intro"]
+            .iter()
+            .map(|slug| anchors.issue_github(slug.to_string()))
+            .collect();
+        assert_eq!(issued, ["intro", "intro-1", "intro-2"]);
+    }
+
+    #[test]
+    fn control_characters_and_exotic_spaces_are_normalized() {
+        assert_eq!(normalize_whitespace("a\u{0c}b\r\nc\nd"), "a b c d");
+        assert_eq!(
+            normalize_whitespace("x\u{00a0}y\u{2009}z\u{202f}w"),
+            "x y z w"
+        );
+        assert_eq!(
+            normalize_whitespace("中\u{200d}文\u{feff}字\u{0007}"),
+            "中文字"
+        );
+        // 制表符与全角空格保留
+        assert_eq!(normalize_whitespace("a\tb\u{3000}c"), "a\tb\u{3000}c");
+    }
+
+    #[test]
+    fn spans_with_form_feeds_and_nbsp_stay_in_one_paragraph() {
+        let spans = json!([
+            text("Scanned\u{0c}page\n\nbreak"),
+            text(" and\u{00a0}100\u{00a0}km\u{feff}.")
+        ]);
+        assert_eq!(paragraph(spans.clone()), "Scanned page  break and 100 km.");
+
+        let options = ConvertOptions {
+            normalize_whitespace: false,
+            ..bare_options()
+        };
+        assert!(
+            paragraph_with(spans, &options).contains('\u{0c}'),
+            "control characters pass through when normalization is off"
+        );
+    }
+
+    #[test]
+    fn code_blocks_and_equations_keep_their_newlines() {
+        let bbox = [50.0, 100.0, 550.0, 160.0];
+        let layout = page_layout(json!([
+            {"type": "text", "sub_type": "code", "bbox": bbox, "lines": [{"bbox": bbox, "spans": [
+                {"type": "text", "bbox": bbox, "content": "fn main() {\n\u{00a0}   run();\n}"}
+            ]}]},
+            {"type": "interline_equation", "bbox": [50.0, 200.0, 550.0, 240.0], "lines": [
+                {"bbox": [50.0, 200.0, 550.0, 240.0], "spans": [
+                    {"type": "interline_equation", "bbox": [50.0, 200.0, 550.0, 240.0],
+                     "content": "a = b\\\\\nc = d"}
+                ]}
+            ]}
+        ]));
+        let output = markdown(&layout, &bare_options());
+        assert!(
+            output.contains("fn main() {\n\u{00a0}   run();\n}"),
+            "{}",
+            output
+        );
+        assert!(output.contains("a = b\\\\\nc = d"), "{}", output);
+    }
+
+    #[test]
+    fn pangu_spacing_separates_cjk_from_latin_and_digits() {
+        assert_eq!(
+            pangu_spacing("使用GPU加速比CPU快10倍"),
+            "使用 GPU 加速比 CPU 快 10 倍"
+        );
+        assert_eq!(pangu_spacing("性能提升30%，"), "性能提升 30%，");
+        assert_eq!(pangu_spacing("需要5GB内存"), "需要 5GB 内存");
+        // 已有空格与全角标点两侧不加
+        assert_eq!(pangu_spacing("使用 GPU 加速"), "使用 GPU 加速");
+        assert_eq!(pangu_spacing("（GPU）与“CUDA”"), "（GPU）与“CUDA”");
+    }
+
+    #[test]
+    fn pangu_spacing_applies_across_spans_and_lines_but_not_inside_links() {
+        let bbox = [50.0, 100.0, 550.0, 140.0];
+        let line =
+            |y: f64, spans: Value| json!({"bbox": [50.0, y, 550.0, y + 15.0], "spans": spans});
+        let layout = page_layout(json!([{
+            "type": "text",
+            "bbox": bbox,
+            "lines": [
+                line(100.0, json!([
+                    text("使用"),
+                    span("GPU", json!({"bold": true})),
+                    text("加速，见https://a.org中"),
+                    {"type": "inline_equation", "bbox": bbox, "content": "x"},
+                    text("值提升"),
+                ])),
+                line(120.0, json!([text("30%。")])),
+            ]
+        }]));
+        let options = ConvertOptions {
+            pangu_spacing: true,
+            autolink: true,
+            ..bare_options()
+        };
+        assert_eq!(
+            markdown(&layout, &options).trim(),
+            "使用 **GPU** 加速，见 <https://a.org> 中 $x$ 值提升 30%。"
+        );
+        // 默认不加中西文间距；行内公式两侧的空格与此选项无关
+        assert_eq!(
+            markdown(&layout, &bare_options()).trim(),
+            "使用**GPU**加速，见https://a.org中 $x$ 值提升30%。"
+        );
+    }
+
+    #[test]
+    fn headings_are_tidied_and_title_cased() {
+        let tidy = |title| normalize_heading(title, true);
+        assert_eq!(
+            tidy("3 . 2   EXPERIMENTAL RESULTS"),
+            "3.2 Experimental Results"
+        );
+        assert_eq!(tidy("IEEE 802.11AX"), "IEEE 802.11AX");
+        assert_eq!(
+            tidy("IV. RESULTS AND THE OUTLOOK"),
+            "IV. Results and the Outlook"
+        );
+        assert_eq!(tidy("IV. THE OUTLOOK"), "IV. The Outlook");
+        assert_eq!(tidy("3.2 THE BASELINE"), "3.2 The Baseline");
+        assert_eq!(
+            tidy("第三章 THE DEEP-LEARNING MODEL"),
+            "第三章 The Deep-Learning Model"
+        );
+        assert_eq!(
+            tidy("A STUDY OF RNN , CNN ( GPU )"),
+            "A Study of RNN, CNN (GPU)"
+        );
+        // 不是全大写的标题、罗马数字字母组成的普通单词
+        assert_eq!(tidy("Mixed Case Stays"), "Mixed Case Stays");
+        assert_eq!(tidy("CIVIL ENGINEERING"), "Civil Engineering");
+        assert_eq!(
+            normalize_heading("3 . 2 EXPERIMENTAL", false),
+            "3.2 EXPERIMENTAL"
+        );
+    }
+
+    #[test]
+    fn normalized_headings_are_used_for_anchors_and_the_toc() {
+        let layout = page_layout(json!([
+            text_block(
+                "title",
+                [50.0, 50.0, 550.0, 70.0],
+                "3 . 2  EXPERIMENTAL RESULTS"
+            ),
+            text_block("text", [50.0, 80.0, 550.0, 90.0], "Body"),
+        ]));
+        let options = ConvertOptions {
+            normalize_headings: true,
+            title_case_headings: true,
+            ..bare_options()
+        };
+        let output =
+            crate::convert_layout_to_markdown(&layout, std::path::Path::new("."), &options)
+                .unwrap();
+        assert_eq!(output.outline[0].title, "3.2 Experimental Results");
+        let anchor = &output.outline[0].anchor_id;
+        assert_eq!(anchor, "toc-0-3-2-Experimental-Results");
+        assert!(
+            output
+                .markdown
+                .contains(&format!("- [3.2 Experimental Results](#{})", anchor))
+        );
+        assert!(output.markdown.contains(&format!(
+            "<a id=\"{}\"></a>\n### 3.2 Experimental Results\n",
+            anchor
+        )));
+    }
+
+    fn math(content: &str) -> Value {
+        json!({"type": "inline_equation", "bbox": [0, 0, 1, 1], "content": content})
+    }
+
+    #[test]
+    fn inline_equations_are_spaced_from_adjacent_words() {
+        let spans = json!([
+            text("速度为"),
+            math("v_0"),
+            text("时，"),
+            math("a"),
+            math("b"),
+            text("（"),
+            math("x"),
+            text("）与"),
+            math(""),
+            text("n"),
+        ]);
+        // 空公式不留下 `$$`，相邻的两个公式之间补空格，紧挨标点时不补
+        assert_eq!(
+            paragraph(spans.clone()),
+            "速度为 $v_0$ 时，$a$ $b$（$x$）与n"
+        );
+        let obsidian = ConvertOptions {
+            flavor: Flavor::Obsidian,
+            ..bare_options()
+        };
+        // Obsidian 不要求 `$` 外侧有空格
+        assert_eq!(
+            paragraph_with(spans.clone(), &obsidian),
+            "速度为$v_0$时，$a$$b$（$x$）与n"
+        );
+        let latex = ConvertOptions {
+            math_delimiters: MathDelimiters::Latex,
+            ..bare_options()
+        };
+        assert_eq!(
+            paragraph_with(spans, &latex),
+            "速度为\\(v_0\\)时，\\(a\\)\\(b\\)（\\(x\\)）与n"
+        );
+    }
+
+    #[test]
+    fn html_inline_math_is_escaped_for_markdown() {
+        let options = ConvertOptions {
+            inline_math_style: InlineMathStyle::Html,
+            ..bare_options()
+        };
+        assert_eq!(
+            paragraph_with(json!([text("当"), math("a_1 < b^*"), text("时")]), &options),
+            "当<span class=\"math\">\\\\(a\\_1 &lt; b^\\*\\\\)</span>时"
+        );
+    }
+}
@@ -0,0 +1,702 @@
+//! MinerU layout.json（middle.json）的反序列化类型
+
+use std::mem;
+
+use serde::de::Error as _;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde_json::value::RawValue;
+
+use crate::error::BBoxError;
+
+// ==================== 坐标 ====================
+
+/// 版面坐标 `[x0, y0, x1, y1]`，即左、上、右、下边
+///
+/// 由 `Vec<f64>` 经 [`TryFrom`] 构造：须恰好四个有限的坐标，左右或上下颠倒时交换。
+/// 反序列化时缺少 bbox、为 `null` 或 `[]` 得到零矩形；其余无法使用的 bbox 同样当作零矩形，
+/// 转换时记为 [`crate::Warning::MalformedBBox`]
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct BBox {
+    rect: [f64; 4],
+    malformed: bool,
+}
+
+impl BBox {
+    pub fn new(x0: f64, y0: f64, x1: f64, y1: f64) -> Result<Self, BBoxError> {
+        if let Some(&value) = [x0, y0, x1, y1].iter().find(|value| !value.is_finite()) {
+            return Err(BBoxError::NotFinite(value));
+        }
+        Ok(BBox {
+            rect: [x0.min(x1), y0.min(y1), x0.max(x1), y0.max(y1)],
+            malformed: false,
+        })
+    }
+
+    pub fn left(&self) -> f64 {
+        self.rect[0]
+    }
+
+    pub fn top(&self) -> f64 {
+        self.rect[1]
+    }
+
+    pub fn right(&self) -> f64 {
+        self.rect[2]
+    }
+
+    pub fn bottom(&self) -> f64 {
+        self.rect[3]
+    }
+
+    pub fn height(&self) -> f64 {
+        self.bottom() - self.top()
+    }
+
+    /// `[x0, y0, x1, y1]`
+    pub fn coordinates(&self) -> [f64; 4] {
+        self.rect
+    }
+
+    /// 零矩形：JSON 中没有 bbox，或 bbox 无法使用
+    pub fn is_empty(&self) -> bool {
+        self.rect == [0.0; 4]
+    }
+
+    /// JSON 中给出了 bbox，但不是四个有限的坐标
+    pub(crate) fn is_malformed(&self) -> bool {
+        self.malformed
+    }
+
+    /// 同时覆盖两个矩形的最小矩形
+    pub(crate) fn union(&self, other: &BBox) -> BBox {
+        BBox {
+            rect: [
+                self.left().min(other.left()),
+                self.top().min(other.top()),
+                self.right().max(other.right()),
+                self.bottom().max(other.bottom()),
+            ],
+            malformed: false,
+        }
+    }
+
+    /// 交并比：重叠部分的面积除以两个矩形覆盖的总面积；不重叠或面积为零时为 0
+    pub(crate) fn iou(&self, other: &BBox) -> f64 {
+        let width = self.right().min(other.right()) - self.left().max(other.left());
+        let height = self.bottom().min(other.bottom()) - self.top().max(other.top());
+        if width <= 0.0 || height <= 0.0 {
+            return 0.0;
+        }
+        let intersection = width * height;
+        let area = |bbox: &BBox| (bbox.right() - bbox.left()) * bbox.height();
+        intersection / (area(self) + area(other) - intersection)
+    }
+}
+
+impl TryFrom<Vec<f64>> for BBox {
+    type Error = BBoxError;
+
+    fn try_from(values: Vec<f64>) -> Result<Self, Self::Error> {
+        match values[..] {
+            [x0, y0, x1, y1] => BBox::new(x0, y0, x1, y1),
+            _ => Err(BBoxError::Length(values.len())),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for BBox {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let values = Option::<Vec<f64>>::deserialize(deserializer)?.unwrap_or_default();
+        if values.is_empty() {
+            return Ok(BBox::default());
+        }
+        Ok(BBox::try_from(values).unwrap_or(BBox {
+            rect: [0.0; 4],
+            malformed: true,
+        }))
+    }
+}
+
+impl Serialize for BBox {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(self.rect)
+    }
+}
+
+// ==================== 类型定义 ====================
+
+/// 行内最小单元：一段文本、行内公式或一张图片
+#[derive(Debug, Deserialize, Clone)]
+pub struct Span {
+    /// 部分旧版本的 span 不带 bbox
+    #[serde(default)]
+    pub bbox: BBox,
+    #[serde(rename = "type")]
+    pub span_type: String,
+    pub content: Option<String>,
+    pub image_path: Option<String>,
+    /// 行内样式标记，仅较新版本的 MinerU 输出
+    #[serde(default)]
+    pub bold: bool,
+    #[serde(default)]
+    pub italic: bool,
+    #[serde(default, alias = "sup")]
+    pub superscript: bool,
+    #[serde(default, alias = "sub")]
+    pub subscript: bool,
+    /// 识别分数，仅部分后端输出；用于 `min_confidence` 与 `drop_below`
+    #[serde(default)]
+    pub score: Option<f64>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct Line {
+    #[serde(default)]
+    pub bbox: BBox,
+    #[serde(default)]
+    pub spans: Vec<Span>,
+}
+
+/// 版面块，既可以直接包含行，也可以嵌套子块（如 image_body / image_caption）
+#[derive(Debug, Deserialize, Clone)]
+pub struct Block {
+    #[serde(default)]
+    pub bbox: BBox,
+    #[serde(rename = "type")]
+    pub block_type: String,
+    pub angle: Option<f64>,
+    pub lines: Option<Vec<Line>>,
+    pub blocks: Option<Vec<Block>>,
+    pub index: Option<i32>,
+    pub sub_type: Option<String>,
+    /// 标题层级（content_list.json 中为 text_level）
+    #[serde(alias = "text_level")]
+    pub level: Option<u32>,
+    /// 代码块的语言提示
+    #[serde(alias = "lang", alias = "language")]
+    pub guess_lang: Option<String>,
+}
+
+/// 角度与 90° 整数倍相差不超过该值时视为旋转了对应角度
+const ROTATION_TOLERANCE: f64 = 5.0;
+
+impl Block {
+    /// 块的旋转角度，吸附到最近的 90° 整数倍（如 89.7 → 90）；只返回竖排的 90 / 270
+    pub(crate) fn rotation(&self) -> Option<u32> {
+        let angle = self.angle?.rem_euclid(360.0);
+        let snapped = (angle / 90.0).round() * 90.0;
+        if (angle - snapped).abs() > ROTATION_TOLERANCE {
+            return None;
+        }
+        match snapped.rem_euclid(360.0) as u32 {
+            degrees @ (90 | 270) => Some(degrees),
+            _ => None,
+        }
+    }
+
+    /// 块及其子块中引用的图片路径，按出现顺序
+    pub(crate) fn image_paths(&self) -> Vec<&str> {
+        let mut paths: Vec<&str> = self
+            .lines
+            .iter()
+            .flatten()
+            .flat_map(|line| &line.spans)
+            .filter_map(|span| span.image_path.as_deref())
+            .collect();
+        for child in self.blocks.iter().flatten() {
+            paths.extend(child.image_paths());
+        }
+        paths
+    }
+
+    /// 块及其子块中的所有 span，按出现顺序
+    pub(crate) fn spans(&self) -> Vec<&Span> {
+        let mut spans: Vec<&Span> = self
+            .lines
+            .iter()
+            .flatten()
+            .flat_map(|line| &line.spans)
+            .collect();
+        for child in self.blocks.iter().flatten() {
+            spans.extend(child.spans());
+        }
+        spans
+    }
+
+    /// 块及其行、span、子块中无法使用的 bbox 个数
+    pub(crate) fn malformed_bboxes(&self) -> usize {
+        let lines: usize = self
+            .lines
+            .iter()
+            .flatten()
+            .map(|line| {
+                usize::from(line.bbox.is_malformed())
+                    + line
+                        .spans
+                        .iter()
+                        .filter(|span| span.bbox.is_malformed())
+                        .count()
+            })
+            .sum();
+        let children: usize = self
+            .blocks
+            .iter()
+            .flatten()
+            .map(Block::malformed_bboxes)
+            .sum();
+        usize::from(self.bbox.is_malformed()) + lines + children
+    }
+}
+
+#[derive(Debug, Deserialize, Clone)]
+#[serde(try_from = "RawPageInfo")]
+pub struct PageInfo {
+    pub para_blocks: Vec<Block>,
+    pub discarded_blocks: Vec<Block>,
+    /// 页面宽、高；未知时（如由 content_list.json 转换而来）为 `(0.0, 0.0)`
+    pub page_size: (f64, f64),
+    /// page_size 的来源
+    pub page_size_source: PageSizeSource,
+    pub page_idx: usize,
+}
+
+/// [`PageInfo::page_size`] 的来源
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PageSizeSource {
+    /// 取自 JSON
+    #[default]
+    Given,
+    /// 输入中没有页面尺寸（如 content_list.json），page_size 为 `(0.0, 0.0)`
+    Unknown,
+    /// JSON 中缺少 page_size 或不是正数，按页面上 bbox 的最大右边与下边推断
+    Blocks,
+    /// JSON 中缺少 page_size 或不是正数，页面上也没有 bbox，按 A4 纸的尺寸
+    A4,
+}
+
+/// A4 纸的宽、高（pt），无法推断页面尺寸时使用
+const A4_PAGE_SIZE: (f64, f64) = (595.28, 841.89);
+
+impl PageInfo {
+    /// 页面中无法使用的 bbox 个数
+    pub(crate) fn malformed_bboxes(&self) -> usize {
+        self.para_blocks
+            .iter()
+            .chain(&self.discarded_blocks)
+            .map(Block::malformed_bboxes)
+            .sum()
+    }
+}
+
+/// 各版本 MinerU 输出的页面结构：0.9 之前只有 preproc_blocks，部分版本缺少 discarded_blocks
+#[derive(Deserialize)]
+struct RawPageInfo {
+    para_blocks: Option<Vec<Block>>,
+    /// 只在没有 para_blocks 时才解析，新版本中两者同时存在
+    preproc_blocks: Option<Box<RawValue>>,
+    #[serde(default)]
+    discarded_blocks: Vec<Block>,
+    /// 缺少、为 `null` 或宽高不是正数时为 `None`，按 bbox 推断
+    #[serde(default, deserialize_with = "deserialize_page_size")]
+    page_size: Option<(f64, f64)>,
+    #[serde(deserialize_with = "deserialize_page_idx")]
+    page_idx: usize,
+}
+
+impl TryFrom<RawPageInfo> for PageInfo {
+    type Error = serde_json::Error;
+
+    fn try_from(raw: RawPageInfo) -> Result<Self, Self::Error> {
+        let para_blocks = match (raw.para_blocks, raw.preproc_blocks) {
+            (Some(blocks), _) => blocks,
+            (None, Some(preproc)) => serde_json::from_str(preproc.get())?,
+            (None, None) => Vec::new(),
+        };
+        let para_blocks =
+            attach_sibling_captions(split_display_equations(flatten_containers(para_blocks)));
+        let (page_size, page_size_source) = match raw.page_size {
+            Some(size) => (size, PageSizeSource::Given),
+            None => infer_page_size(para_blocks.iter().chain(&raw.discarded_blocks)),
+        };
+        Ok(PageInfo {
+            para_blocks,
+            discarded_blocks: raw.discarded_blocks,
+            page_size,
+            page_size_source,
+            page_idx: raw.page_idx,
+        })
+    }
+}
+
+/// 按块的 bbox 的最大右边与下边推断页面尺寸；没有可用的 bbox 时为 A4
+fn infer_page_size<'a>(blocks: impl Iterator<Item = &'a Block>) -> ((f64, f64), PageSizeSource) {
+    let (width, height) = blocks.fold((0.0, 0.0), |(width, height): (f64, f64), block| {
+        (
+            width.max(block.bbox.right()),
+            height.max(block.bbox.bottom()),
+        )
+    });
+    if width > 0.0 && height > 0.0 {
+        ((width, height), PageSizeSource::Blocks)
+    } else {
+        (A4_PAGE_SIZE, PageSizeSource::A4)
+    }
+}
+
+/// 容器块最多展开的嵌套层数，更深的块保持原样
+const MAX_NESTING_DEPTH: usize = 8;
+
+/// 子块有各自含义的块类型（如 image_body、table_caption），不作为容器展开
+const COMPOUND_TYPES: &[&str] = &["title", "image", "table", "code", "interline_equation"];
+
+/// 嵌套在 `container` 类型的块中时，`child` 类型的子块是否需要作为独立的块渲染：
+/// [`COMPOUND_TYPES`] 中的块，以及不在列表中的列表（列表中的列表是子列表）
+fn renders_alone(container: &str, child: &str) -> bool {
+    COMPOUND_TYPES.contains(&child) || (child == "list" && container != "list")
+}
+
+/// 部分 MinerU 输出把标题等块嵌套在文本、列表等容器块中；把这些子块提升为页面上的独立块，
+/// 以便生成锚点与目录条目。其余子块留在与容器同类型的块中：列表里相邻的列表项仍组成一个列表，
+/// 其他容器自身的行与相邻子块的行拼接为一段；没有 index 的子块沿用容器的 index。
+/// 标题、图片、表格等块的子块（image_body、table_caption 等）有各自的含义，不展开
+fn flatten_containers(blocks: Vec<Block>) -> Vec<Block> {
+    let mut flat = Vec::with_capacity(blocks.len());
+    for block in blocks {
+        flatten_block(block, 0, &mut flat);
+    }
+    flat
+}
+
+fn flatten_block(mut block: Block, depth: usize, out: &mut Vec<Block>) {
+    let nested = !COMPOUND_TYPES.contains(&block.block_type.as_str())
+        && depth < MAX_NESTING_DEPTH
+        && block
+            .blocks
+            .iter()
+            .flatten()
+            .any(|child| renders_alone(&block.block_type, &child.block_type));
+    if !nested {
+        out.push(block);
+        return;
+    }
+
+    let children = block.blocks.take().unwrap_or_default();
+    let mut lines = block.lines.take().unwrap_or_default();
+    let mut run = Vec::new();
+    // 尚未输出的行与子块所占的区域，按阅读顺序排序时用作它们的位置；容器自身的行在最前，取容器的 bbox
+    let mut area = (!lines.is_empty()).then_some(block.bbox);
+    for mut child in children {
+        if renders_alone(&block.block_type, &child.block_type) {
+            let pending = (mem::take(&mut lines), mem::take(&mut run));
+            push_run(&block, pending, area.take(), out);
+            child.index = child.index.or(block.index);
+            flatten_block(child, depth + 1, out);
+            continue;
+        }
+        if !child.bbox.is_empty() {
+            area = Some(area.map_or(child.bbox, |area| area.union(&child.bbox)));
+        }
+        if block.block_type == "list" {
+            run.push(child);
+        } else {
+            collect_lines(child, &mut lines);
+        }
+    }
+    push_run(&block, (lines, run), area, out);
+}
+
+/// 块自身及所有子块的行，按出现顺序
+fn collect_lines(block: Block, lines: &mut Vec<Line>) {
+    lines.extend(block.lines.into_iter().flatten());
+    for child in block.blocks.into_iter().flatten() {
+        collect_lines(child, lines);
+    }
+}
+
+/// 以容器的类型、index 等输出尚未输出的行与子块，bbox 为它们所占的区域；都为空时不输出
+fn push_run(
+    container: &Block,
+    (lines, run): (Vec<Line>, Vec<Block>),
+    area: Option<BBox>,
+    out: &mut Vec<Block>,
+) {
+    if run.is_empty() && lines.iter().all(|line| line.spans.is_empty()) {
+        return;
+    }
+    out.push(Block {
+        bbox: area.unwrap_or(container.bbox),
+        lines: (!lines.is_empty()).then_some(lines),
+        blocks: (!run.is_empty()).then_some(run),
+        ..container.clone()
+    });
+}
+
+/// 说明文字块所属的图表类型，以及它通常位于图表上方（`true`）还是下方
+fn caption_parent(block_type: &str) -> Option<(&'static str, bool)> {
+    match block_type {
+        "image_caption" | "image_footnote" => Some(("image", false)),
+        "table_caption" => Some(("table", true)),
+        "table_footnote" => Some(("table", false)),
+        _ => None,
+    }
+}
+
+/// 部分 MinerU 输出把行间公式作为 interline_equation span 放在文本块的行中；在公式处把文本块拆开，
+/// 公式成为独立的 interline_equation 块，前后的文字各成一段，不再拼接在一起。
+/// 拆出的块沿用原块的 bbox 与 index，排序时保持原有顺序；没有文字的部分不输出
+fn split_display_equations(blocks: Vec<Block>) -> Vec<Block> {
+    let mut split = Vec::with_capacity(blocks.len());
+    for mut block in blocks {
+        let has_equation = block.block_type == "text"
+            && block
+                .lines
+                .iter()
+                .flatten()
+                .flat_map(|line| &line.spans)
+                .any(|span| span.span_type == "interline_equation");
+        if !has_equation {
+            split.push(block);
+            continue;
+        }
+
+        let lines = block.lines.take().unwrap_or_default();
+        let mut text_lines: Vec<Line> = Vec::new();
+        for line in lines {
+            let mut spans = Vec::new();
+            for span in line.spans {
+                if span.span_type != "interline_equation" {
+                    spans.push(span);
+                    continue;
+                }
+                if !spans.is_empty() {
+                    text_lines.push(Line {
+                        bbox: line.bbox,
+                        spans: mem::take(&mut spans),
+                    });
+                }
+                push_text_part(&block, mem::take(&mut text_lines), &mut split);
+                split.push(Block {
+                    block_type: "interline_equation".to_string(),
+                    lines: Some(vec![Line {
+                        bbox: line.bbox,
+                        spans: vec![span],
+                    }]),
+                    ..block.clone()
+                });
+            }
+            if !spans.is_empty() {
+                text_lines.push(Line {
+                    bbox: line.bbox,
+                    spans,
+                });
+            }
+        }
+        push_text_part(&block, text_lines, &mut split);
+    }
+    split
+}
+
+/// 以 `block` 的类型、bbox 与 index 输出拆开的一段文字；只有空白时不输出
+fn push_text_part(block: &Block, lines: Vec<Line>, out: &mut Vec<Block>) {
+    let has_text = lines.iter().flat_map(|line| &line.spans).any(|span| {
+        span.image_path.is_some()
+            || span
+                .content
+                .as_deref()
+                .is_some_and(|text| !text.trim().is_empty())
+    });
+    if has_text {
+        out.push(Block {
+            lines: Some(lines),
+            ..block.clone()
+        });
+    }
+}
+
+/// 部分后端（如 VLM）把图注、表注输出为与图表块并列的块，而不是嵌套在其中；
+/// 把它们并入相邻的图表块，位于图表之前的排在原有子块前面，之后的排在后面
+///
+/// 相邻指中间只隔着同一图表的其他说明文字；两侧都有同类图表时按说明文字通常的位置选择，
+/// 找不到所属图表的说明文字保持原样。
+fn attach_sibling_captions(blocks: Vec<Block>) -> Vec<Block> {
+    if !blocks
+        .iter()
+        .any(|block| caption_parent(&block.block_type).is_some())
+    {
+        return blocks;
+    }
+
+    // 每个说明文字块要并入的图表块下标
+    let targets: Vec<Option<usize>> = (0..blocks.len())
+        .map(|i| {
+            let (parent, above) = caption_parent(&blocks[i].block_type)?;
+            let is_sibling = |j: usize| {
+                caption_parent(&blocks[j].block_type).is_some_and(|(other, _)| other == parent)
+            };
+            let before = (0..i)
+                .rev()
+                .find(|&j| !is_sibling(j))
+                .filter(|&j| blocks[j].block_type == parent);
+            let after = (i + 1..blocks.len())
+                .find(|&j| !is_sibling(j))
+                .filter(|&j| blocks[j].block_type == parent);
+            if above {
+                after.or(before)
+            } else {
+                before.or(after)
+            }
+        })
+        .collect();
+
+    let mut slots: Vec<Option<Block>> = blocks.into_iter().map(Some).collect();
+    let mut attached = Vec::with_capacity(slots.len());
+    for j in 0..slots.len() {
+        if targets[j].is_some() {
+            continue;
+        }
+        let Some(mut block) = slots[j].take() else {
+            continue;
+        };
+        let (before, after): (Vec<usize>, Vec<usize>) = (0..targets.len())
+            .filter(|&i| targets[i] == Some(j))
+            .partition(|&i| i < j);
+        if !before.is_empty() || !after.is_empty() {
+            let mut children: Vec<Block> = before.iter().filter_map(|&i| slots[i].take()).collect();
+            children.extend(block.blocks.take().into_iter().flatten());
+            children.extend(after.iter().filter_map(|&i| slots[i].take()));
+            block.blocks = Some(children);
+        }
+        attached.push(block);
+    }
+    attached
+}
+
+/// page_size 可能是 `[612.0, 792.0]`、`[612, 792]`，也可能带有多余的元素；
+/// 为 `null`、`[]` 或宽高不是正数时视为缺失，其余形式（只有一个数、不是数字）报错
+fn deserialize_page_size<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<(f64, f64)>, D::Error> {
+    let Some(values) = Option::<Vec<serde_json::Value>>::deserialize(deserializer)
+        .map_err(|error| D::Error::custom(format!("invalid page_size: {}", error)))?
+    else {
+        return Ok(None);
+    };
+    let numbers: Option<Vec<f64>> = values.iter().map(serde_json::Value::as_f64).collect();
+    let valid = |value: f64| value.is_finite() && value > 0.0;
+    match (numbers.as_deref(), values.len()) {
+        (_, 0) => Ok(None),
+        (Some(&[width, height, ..]), _) if valid(width) && valid(height) => {
+            Ok(Some((width, height)))
+        }
+        (Some(&[_, _, ..]), _) => Ok(None),
+        (Some(_), _) => Err(D::Error::custom(
+            "invalid page_size: expected page width and height, found a single value",
+        )),
+        (None, _) => Err(D::Error::custom(
+            "invalid page_size: page width and height must be numbers",
+        )),
+    }
+}
+
+/// page_idx 的上限；更大的页码多半来自损坏的输入，加上页数后还可能溢出
+pub(crate) const MAX_PAGE_IDX: usize = i32::MAX as usize;
+
+/// page_idx 须不超过 [`MAX_PAGE_IDX`]
+pub(crate) fn deserialize_page_idx<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<usize, D::Error> {
+    let page_idx = usize::deserialize(deserializer)?;
+    if page_idx > MAX_PAGE_IDX {
+        return Err(D::Error::custom(format!(
+            "page_idx {} is out of range (at most {})",
+            page_idx, MAX_PAGE_IDX
+        )));
+    }
+    Ok(page_idx)
+}
+
+/// layout.json 顶层结构
+#[derive(Debug, Deserialize)]
+pub struct LayoutJson {
+    pub pdf_info: Vec<PageInfo>,
+    #[serde(rename = "_backend")]
+    pub backend: Option<String>,
+    #[serde(rename = "_version_name")]
+    pub version_name: Option<String>,
+}
+
+/// 目录中的一个标题，由 [`crate::BlockRenderer::render_title`] 返回
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TocEntry {
+    /// 标题文字（未转义）
+    pub title: String,
+    /// 标题所在的页码（从 1 开始）
+    pub page_idx: usize,
+    /// 链接到标题的锚点；重复的锚点在所有页面渲染完成后追加编号
+    pub anchor_id: String,
+    /// 标题层级：0 为文档标题，1 起为各级章节标题
+    pub level: usize,
+    /// 原书印刷的页码（取自该页的 `page_number` 块，如 `vii`）；没有时为 `None`
+    pub printed_page: Option<String>,
+}
+
+/// 输出文档的大纲中的一个标题：目录条目及其在输出中的位置，见 [`crate::ConvertOutput::outline`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OutlineEntry {
+    pub title: String,
+    pub level: usize,
+    /// 标题所在的页码（从 1 开始）
+    pub page_idx: usize,
+    pub anchor_id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub printed_page: Option<String>,
+    /// 标题（连同其前的锚点与来源注释）在输出文档中的起始位置，按字符计、从 0 开始；
+    /// 输出中找不到标题的位置时（纯文本、LaTeX 与 JSON 输出）为 `None`
+    pub offset: Option<usize>,
+}
+
+impl OutlineEntry {
+    pub(crate) fn new(entry: &TocEntry, offset: Option<usize>) -> Self {
+        OutlineEntry {
+            title: entry.title.clone(),
+            level: entry.level,
+            page_idx: entry.page_idx,
+            anchor_id: entry.anchor_id.clone(),
+            printed_page: entry.printed_page.clone(),
+            offset,
+        }
+    }
+}
+
+/// 按类型分组的 discarded_blocks，只借用页面中的块
+pub(crate) struct DiscardedBlocksCategory<'a> {
+    pub headers: Vec<&'a Block>,
+    pub footers: Vec<&'a Block>,
+    pub footnotes: Vec<&'a Block>,
+    pub page_numbers: Vec<&'a Block>,
+    /// 其余类型（如 `aside_text`），不输出，只计入统计
+    pub others: Vec<&'a Block>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn rotation(angle: f64) -> Option<u32> {
+        let block: Block = serde_json::from_value(json!({"type": "text", "angle": angle})).unwrap();
+        block.rotation()
+    }
+
+    #[test]
+    fn rotation_snaps_to_the_nearest_quarter_turn() {
+        assert_eq!(rotation(89.7), Some(90));
+        assert_eq!(rotation(270.4), Some(270));
+        assert_eq!(rotation(-90.0), Some(270));
+        assert_eq!(rotation(449.0), Some(90));
+        // 横排与偏离过多的角度不算旋转
+        assert_eq!(rotation(0.0), None);
+        assert_eq!(rotation(180.0), None);
+        assert_eq!(rotation(80.0), None);
+    }
+}
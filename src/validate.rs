@@ -0,0 +1,120 @@
+//! 输入校验：按转换时的规则走一遍所有页面与块，汇总会产生的警告，不编码、不写出图片
+
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use serde::Serialize;
+
+use crate::content_list::{InputFormat, guess_schema, parse_layout_lenient};
+use crate::error::{ConvertError, Warning};
+use crate::image::ImageResolver;
+use crate::stats::ConvertStats;
+use crate::{ConvertOptions, Document, PagePlan, RenderedDocument, output_options};
+
+/// 引用了但不存在的图片
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct MissingImageRef {
+    /// 页码（从 0 开始）
+    pub page_idx: usize,
+    pub path: String,
+}
+
+/// [`validate_layout`] 的结果
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct Validation {
+    /// 推测的输入来源，如 `MinerU 2.1.0 (pipeline backend)`
+    pub schema: String,
+    /// 能够解析的页数
+    pub pages: usize,
+    /// 按块类型统计的正文块
+    pub blocks: BTreeMap<String, usize>,
+    /// 未识别的块类型及其个数，转换时按普通文本输出
+    pub unknown_block_types: BTreeMap<String, usize>,
+    /// 未识别的 span 类型及其个数，转换时段落中不包含它们的文字
+    pub unknown_span_types: BTreeMap<String, usize>,
+    /// 图片引用数（含表格截图和公式截图）
+    pub images_referenced: usize,
+    /// 其中图片文件存在的引用数
+    pub images_found: usize,
+    pub missing_images: Vec<MissingImageRef>,
+    /// 不是四个有限坐标的 bbox 个数
+    pub malformed_bboxes: usize,
+    /// 没有渲染出任何正文内容的页面（页码从 0 开始）
+    pub empty_pages: Vec<usize>,
+    /// 转换时会给出的全部警告
+    pub warnings: Vec<String>,
+    /// 无法解析的页面；转换时会中止，加上 lenient 才能跳过
+    pub errors: Vec<String>,
+}
+
+/// 按转换时的规则检查 middle.json 或 content_list.json：解析每一页，以 `options` 分析并渲染所有块，
+/// 图片只检查文件是否存在，不读取、不编码；结果中的警告与转换时完全相同
+///
+/// 无法解析的页面记入 [`Validation::errors`]，只有整个文件无法解析时才返回错误。
+/// `options` 中的 `strict_blocks` 与 `fail_on_missing_images` 不起作用，以便列出所有问题。
+pub fn validate_layout(
+    json: &str,
+    format: InputFormat,
+    base_path: &Path,
+    options: &ConvertOptions,
+) -> Result<Validation, ConvertError> {
+    let (layout, skipped) = parse_layout_lenient(json, format)?;
+    let options = ConvertOptions {
+        strict_blocks: false,
+        fail_on_missing_images: false,
+        progress: None,
+        ..options.clone()
+    };
+    let options = &*output_options(&options);
+    let documents = [Document {
+        layout: &layout,
+        base_path,
+    }];
+    let plan = PagePlan::new(&documents, options, Some(ImageResolver::check()))?;
+    let pages = plan.render(0..plan.len(), options, None)?;
+    let rendered = RenderedDocument::new(pages, &ConvertStats::default(), options);
+
+    let stats = &rendered.stats;
+    let mut validation = Validation {
+        schema: guess_schema(json, format),
+        pages: stats.pages,
+        blocks: stats
+            .blocks
+            .iter()
+            .map(|(block_type, count)| (block_type.clone(), count.rendered + count.skipped))
+            .collect(),
+        images_referenced: stats.images_included + stats.images_missing,
+        images_found: stats.images_included,
+        errors: skipped.iter().map(Warning::to_string).collect(),
+        ..Validation::default()
+    };
+    for warning in &rendered.warnings {
+        match warning {
+            Warning::UnknownBlockType { block_type, .. } => {
+                *validation
+                    .unknown_block_types
+                    .entry(block_type.clone())
+                    .or_default() += 1;
+            }
+            Warning::UnknownSpanType {
+                span_type, count, ..
+            } => {
+                *validation
+                    .unknown_span_types
+                    .entry(span_type.clone())
+                    .or_default() += count;
+            }
+            Warning::MissingImage { page_idx, path } => {
+                validation.missing_images.push(MissingImageRef {
+                    page_idx: *page_idx,
+                    path: path.clone(),
+                });
+            }
+            Warning::MalformedBBox { count, .. } => validation.malformed_bboxes += count,
+            Warning::EmptyPage { page_idx } => validation.empty_pages.push(*page_idx),
+            _ => {}
+        }
+        validation.warnings.push(warning.to_string());
+    }
+    Ok(validation)
+}
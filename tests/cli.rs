@@ -0,0 +1,250 @@
+//! 通过命令行运行编译出的程序，检查 stdin / stdout 管道与退出码
+
+use std::io::Write;
+use std::path::Path;
+use std::process::{self, Command, Output, Stdio};
+use std::{env, fs};
+
+fn run(dir: &Path, args: &[&str], stdin: &[u8]) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_mineru-json-to-md"))
+        .current_dir(dir)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child.stdin.take().unwrap().write_all(stdin).unwrap();
+    child.wait_with_output().unwrap()
+}
+
+fn fixture(name: &str) -> Vec<u8> {
+    fs::read(
+        Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/fixtures")
+            .join(name),
+    )
+    .unwrap()
+}
+
+#[test]
+fn converts_from_stdin_to_stdout() {
+    let json = fixture("sample.json");
+    let fixtures = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures");
+    // 当前目录为 src，图片依 --base-path 相对于 fixtures 目录解析
+    let output = run(
+        &Path::new(env!("CARGO_MANIFEST_DIR")).join("src"),
+        &[
+            "-",
+            "-",
+            "--flavor",
+            "gfm",
+            "--base-path",
+            fixtures.to_str().unwrap(),
+        ],
+        &json,
+    );
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    // 与直接转换文件的结果相同，提示信息只写到 stderr
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        String::from_utf8(fixture("sample.gfm.md")).unwrap()
+    );
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("Processing 2 pages"), "{}", stderr);
+    assert!(stderr.contains("Done!"), "{}", stderr);
+}
+
+#[test]
+fn images_from_stdin_resolve_against_the_current_directory() {
+    // 空目录中找不到 sample.json 引用的图片
+    let dir = env::temp_dir().join(format!("mineru-md-cli-{}", process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let output = run(
+        &dir,
+        &["-", "-", "--flavor", "gfm"],
+        &fixture("sample.json"),
+    );
+    fs::remove_dir_all(&dir).unwrap();
+    assert!(output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("figure.png"), "{}", stderr);
+    assert!(stderr.contains("1 missing"), "{}", stderr);
+}
+
+/// 每个测试单独的空目录，`name` 区分同一进程中的不同测试
+fn scratch(name: &str) -> std::path::PathBuf {
+    let dir = env::temp_dir().join(format!("mineru-md-cli-{}-{}", name, process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// 统计摘要中的 `cache:` 一行
+fn cache_status(output: &Output) -> String {
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    stderr
+        .lines()
+        .find_map(|line| line.trim().strip_prefix("cache: "))
+        .unwrap_or_else(|| panic!("no cache line in:\n{}", stderr))
+        .to_string()
+}
+
+#[test]
+fn cache_entries_are_reused_until_the_inputs_or_options_change() {
+    let dir = scratch("cache");
+    fs::write(dir.join("sample.json"), fixture("sample.json")).unwrap();
+    fs::create_dir_all(dir.join("images")).unwrap();
+    fs::write(dir.join("images/figure.png"), fixture("images/figure.png")).unwrap();
+    let convert = |extra: &[&str]| {
+        let mut args = vec!["sample.json", "out.md", "--cache-dir", "cache"];
+        args.extend_from_slice(extra);
+        let output = run(&dir, &args, b"");
+        assert!(
+            output.status.success(),
+            "{}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+        output
+    };
+
+    assert_eq!(cache_status(&convert(&[])), "miss, stored");
+    let converted = fs::read(dir.join("out.md")).unwrap();
+    // 命中时输出文件由缓存的结果重新写出
+    fs::remove_file(dir.join("out.md")).unwrap();
+    assert_eq!(cache_status(&convert(&[])), "hit");
+    assert_eq!(fs::read(dir.join("out.md")).unwrap(), converted);
+
+    // 影响输出的选项、输入文件与引用的图片都属于缓存键；只影响日志的选项不属于
+    assert_eq!(cache_status(&convert(&["--flavor", "gfm"])), "miss, stored");
+    assert_eq!(cache_status(&convert(&["--flavor", "gfm", "-v"])), "hit");
+    let mut edited = fixture("sample.json");
+    edited.push(b'\n');
+    fs::write(dir.join("sample.json"), edited).unwrap();
+    assert_eq!(cache_status(&convert(&[])), "miss, stored");
+    fs::write(
+        dir.join("images/figure.png"),
+        fixture("images/figure.png").repeat(2),
+    )
+    .unwrap();
+    assert_eq!(cache_status(&convert(&[])), "miss, stored");
+    assert_eq!(cache_status(&convert(&[])), "hit");
+
+    // 结果与元数据不一致的条目当作未命中
+    for entry in fs::read_dir(dir.join("cache")).unwrap() {
+        let path = entry.unwrap().path();
+        if path.to_string_lossy().ends_with(".out") {
+            fs::write(&path, "truncated").unwrap();
+        }
+    }
+    assert_eq!(cache_status(&convert(&[])), "miss, stored");
+    let uncached = convert(&["--no-cache"]);
+    assert!(!String::from_utf8_lossy(&uncached.stderr).contains("cache:"));
+    assert_eq!(
+        cache_status(&convert(&["--report", "report.json"])),
+        "not used (--report needs a fresh conversion)"
+    );
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn fail_on_and_strict_exit_with_status_3_and_the_report_says_why() {
+    // 空目录中找不到 sample.json 引用的图片，转换得到一条 missing_image 警告
+    let dir = scratch("fail-on");
+    fs::write(dir.join("sample.json"), fixture("sample.json")).unwrap();
+    let status = |extra: &[&str]| {
+        let _ = fs::remove_file(dir.join("out.md"));
+        let mut args = vec!["sample.json", "out.md", "-q"];
+        args.extend_from_slice(extra);
+        let output = run(&dir, &args, b"");
+        (output.status.code(), dir.join("out.md").exists())
+    };
+
+    assert_eq!(status(&[]), (Some(0), true));
+    // 超出限制时退出码为 3，输出照常写出
+    assert_eq!(status(&["--fail-on", "missing_image"]), (Some(3), true));
+    assert_eq!(status(&["--fail-on", "missing_image:1"]), (Some(0), true));
+    assert_eq!(status(&["--fail-on", "unknown_block"]), (Some(0), true));
+    assert_eq!(status(&["--strict"]), (Some(3), true));
+    // 未知的类别是用法错误
+    assert_eq!(status(&["--fail-on", "nosuch"]), (Some(2), false));
+
+    assert_eq!(
+        status(&[
+            "--report",
+            "report.json",
+            "--fail-on",
+            "missing_image,unknown_block"
+        ]),
+        (Some(3), true)
+    );
+    let report: serde_json::Value =
+        serde_json::from_slice(&fs::read(dir.join("report.json")).unwrap()).unwrap();
+    assert_eq!(report["completed"], true);
+    assert_eq!(
+        report["failed_categories"],
+        serde_json::json!(["missing_image"])
+    );
+    assert_eq!(
+        report["warning_counts"],
+        serde_json::json!({"missing_image": 1})
+    );
+    assert_eq!(report["warnings"][0]["category"], "missing_image");
+    assert_eq!(report["pages"].as_array().unwrap().len(), 2);
+    assert_eq!(report["options"]["fail-on"], "missing_image,unknown_block");
+
+    // 转换出错时同样写出报告
+    let output = run(
+        &dir,
+        &["missing.json", "out.md", "-q", "--report", "failed.json"],
+        b"",
+    );
+    assert_eq!(output.status.code(), Some(1));
+    let report: serde_json::Value =
+        serde_json::from_slice(&fs::read(dir.join("failed.json")).unwrap()).unwrap();
+    assert_eq!(report["completed"], false);
+    assert!(report["error"].as_str().unwrap().contains("missing.json"));
+    assert!(report["stats"].is_null());
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn validate_exits_with_0_clean_1_warnings_and_2_errors() {
+    let fixtures = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures");
+    let sample = fixtures.join("sample.json");
+    let clean = run(&fixtures, &["validate", sample.to_str().unwrap()], b"");
+    assert_eq!(clean.status.code(), Some(0));
+    let report = String::from_utf8(clean.stdout).unwrap();
+    assert!(report.contains("Pages: 2\n"), "{}", report);
+    assert!(report.contains("Result: clean"), "{}", report);
+
+    // 图片相对于空目录解析时缺失，只检查、不复制任何文件
+    let dir = scratch("validate");
+    let args = [
+        "validate",
+        sample.to_str().unwrap(),
+        "--json",
+        "--base-path",
+        dir.to_str().unwrap(),
+    ];
+    let missing = run(&dir, &args, b"");
+    assert_eq!(missing.status.code(), Some(1));
+    let findings: serde_json::Value = serde_json::from_slice(&missing.stdout).unwrap();
+    assert_eq!(findings["images_referenced"], 1);
+    assert_eq!(
+        findings["missing_images"],
+        serde_json::json!([{"page_idx": 0, "path": "figure.png"}])
+    );
+    assert_eq!(findings["warnings"].as_array().unwrap().len(), 1);
+    assert_eq!(fs::read_dir(&dir).unwrap().count(), 0);
+
+    let invalid = run(&dir, &["validate", "-", "--json"], b"{\"pdf_info\": 3}");
+    assert_eq!(invalid.status.code(), Some(2));
+    let findings: serde_json::Value = serde_json::from_slice(&invalid.stdout).unwrap();
+    assert_eq!(findings["errors"].as_array().unwrap().len(), 1);
+    fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,242 @@
+//! 固定输入（tests/fixtures 中的 sample.json 等）的完整输出与同目录下的期望文件逐字比较。
+//! 有意改动输出后以 `UPDATE_GOLDEN=1 cargo test --test golden` 重新生成期望文件
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::{env, process};
+
+use mineru_json_to_md::{
+    ConvertOptions, CssMode, Flavor, ImageOutput, InputFormat, OutputFormat, convert_file,
+};
+
+fn fixtures() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures")
+}
+
+fn check_golden(input: &str, expected: &str, options: &ConvertOptions) {
+    let output = convert_file(&fixtures().join(input), InputFormat::Auto, options).unwrap();
+    assert!(output.warnings.is_empty(), "{:?}", output.warnings);
+    let path = fixtures().join(expected);
+    if env::var_os("UPDATE_GOLDEN").is_some() {
+        fs::write(&path, &output.markdown).unwrap();
+        return;
+    }
+    let golden = fs::read_to_string(&path).unwrap();
+    assert!(
+        output.markdown == golden,
+        "output differs from {}; rerun with UPDATE_GOLDEN=1 if the change is intended\n{}",
+        path.display(),
+        output.markdown
+    );
+}
+
+#[test]
+fn styled_output_matches_the_golden_file() {
+    check_golden(
+        "sample.json",
+        "sample.styled.md",
+        &ConvertOptions::default(),
+    );
+}
+
+#[test]
+fn gfm_output_matches_the_golden_file() {
+    let options = ConvertOptions {
+        flavor: Flavor::Gfm,
+        ..ConvertOptions::default()
+    };
+    check_golden("sample.json", "sample.gfm.md", &options);
+}
+
+/// 幻灯片式的 PDF：项目符号与编号各自是一个文本块
+#[test]
+fn slides_keep_flat_paragraphs_unless_lists_are_detected() {
+    let options = ConvertOptions {
+        flavor: Flavor::Gfm,
+        ..ConvertOptions::default()
+    };
+    check_golden("slides.json", "slides.gfm.md", &options);
+    let options = ConvertOptions {
+        detect_lists: true,
+        ..options
+    };
+    check_golden("slides.json", "slides.lists.gfm.md", &options);
+}
+
+/// MinerU 的 list / list_item 块：两层以上的有序与无序嵌套、去掉项目符号，
+/// 编号风格不一致的列表按无序列表输出
+#[test]
+fn nested_lists_match_the_golden_file() {
+    let options = ConvertOptions {
+        flavor: Flavor::Gfm,
+        ..ConvertOptions::default()
+    };
+    check_golden("lists.json", "lists.gfm.md", &options);
+}
+
+/// 三种样式写法各有期望文件；类名写法中出现的每个类都在样式块中定义，两种类名写法不含内联样式
+#[test]
+fn each_css_mode_matches_its_golden_file() {
+    let with_mode = |css_mode| ConvertOptions {
+        css_mode,
+        ..ConvertOptions::default()
+    };
+    check_golden(
+        "sample.json",
+        "sample.styled.md",
+        &with_mode(CssMode::Inline),
+    );
+    check_golden(
+        "sample.json",
+        "sample.classes.md",
+        &with_mode(CssMode::Classes),
+    );
+    check_golden(
+        "sample.json",
+        "sample.css-none.md",
+        &with_mode(CssMode::None),
+    );
+
+    let classes = fs::read_to_string(fixtures().join("sample.classes.md")).unwrap();
+    let none = fs::read_to_string(fixtures().join("sample.css-none.md")).unwrap();
+    assert!(!classes.contains(" style=\""));
+    assert!(!none.contains(" style=\"") && !none.contains("<style>"));
+    let (header, body) = classes.split_once("</style>").unwrap();
+    for attr in body.split(" class=\"").skip(1) {
+        for class in attr[..attr.find('"').unwrap()].split_whitespace() {
+            assert!(header.contains(&format!(".{} {{", class)), "{}", class);
+        }
+    }
+    // 去掉样式块后两种类名写法相同
+    assert_eq!(body.trim_start(), none.trim_start());
+}
+
+/// Org 输出无法内联图片，图片写入临时目录；正文中的 `*literal*` 以零宽空格隔开，不成为粗体
+#[test]
+fn org_output_matches_the_golden_file() {
+    let dir = env::temp_dir().join(format!("mineru-md-golden-org-{}", process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let options = ConvertOptions {
+        format: OutputFormat::Org,
+        images: ImageOutput::Directory {
+            dir: dir.clone(),
+            link_prefix: "sample_images".to_string(),
+        },
+        ..ConvertOptions::default()
+    };
+    check_golden("sample.json", "sample.org", &options);
+    assert!(dir.join("figure.png").is_file());
+    fs::remove_dir_all(&dir).unwrap();
+
+    let golden = fs::read_to_string(fixtures().join("sample.org")).unwrap();
+    assert!(golden.contains("\u{200b}*literal*\u{200b}"), "{}", golden);
+}
+
+/// 完整的 HTML 文档：标题取自第一个标题，目录为 `<nav>`，正文中的 Markdown 标记原样保留
+#[test]
+fn html_output_matches_the_golden_file() {
+    let options = ConvertOptions {
+        format: OutputFormat::Html,
+        ..ConvertOptions::default()
+    };
+    check_golden("sample.json", "sample.html", &options);
+
+    let golden = fs::read_to_string(fixtures().join("sample.html")).unwrap();
+    assert!(golden.starts_with("<!DOCTYPE html>\n"), "{}", golden);
+    assert!(golden.contains("<title>Sample Report</title>"));
+    assert!(golden.contains("<li><a href=\"#toc-1-Conclusion\">Conclusion</a>"));
+    assert!(golden.contains("<h2 id=\"toc-1-Conclusion\">Conclusion</h2>"));
+    assert!(golden.contains("<p>The first paragraph has *literal* markup.</p>"));
+    assert!(golden.ends_with("</body>\n</html>\n"));
+}
+
+/// 纯文本：只有正文段落；开启 text_placeholders 与 text_title_levels 时图表、公式以一行占位，
+/// 标题前标出层级
+#[test]
+fn text_output_matches_the_golden_file() {
+    let options = ConvertOptions {
+        format: OutputFormat::Text,
+        text_placeholders: true,
+        text_title_levels: true,
+        ..ConvertOptions::default()
+    };
+    check_golden("sample.json", "sample.txt", &options);
+
+    let plain = ConvertOptions {
+        format: OutputFormat::Text,
+        ..ConvertOptions::default()
+    };
+    let output = convert_file(&fixtures().join("sample.json"), InputFormat::Auto, &plain).unwrap();
+    assert_eq!(
+        output.markdown,
+        "Sample Report\n\nThe first paragraph has *literal* markup.\n\n\
+         Conclusion\n\nClosing words.\n"
+    );
+
+    // 有序与无序列表的项目都以 `- ` 开头，每级缩进两个空格
+    let lists = convert_file(&fixtures().join("lists.json"), InputFormat::Auto, &plain).unwrap();
+    assert!(
+        lists
+            .markdown
+            .contains("- middle.json\n  - pipeline backend\n    - 1.x and 2.x\n  - vlm backend\n"),
+        "{}",
+        lists.markdown
+    );
+}
+
+/// JSON 输出：每个内容条目独占一行，整体是合法的 JSON；列表条目保留嵌套结构
+#[test]
+fn json_output_matches_the_golden_file() {
+    let options = ConvertOptions {
+        format: OutputFormat::Json,
+        ..ConvertOptions::default()
+    };
+    check_golden("sample.json", "sample.doc.json", &options);
+
+    let golden = fs::read_to_string(fixtures().join("sample.doc.json")).unwrap();
+    let document: serde_json::Value = serde_json::from_str(&golden).unwrap();
+    assert_eq!(document["schema_version"], 1);
+    let kinds: Vec<&str> = document["items"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|item| item["kind"].as_str().unwrap())
+        .collect();
+    assert_eq!(
+        kinds,
+        [
+            "heading",
+            "paragraph",
+            "figure",
+            "table",
+            "heading",
+            "equation",
+            "paragraph"
+        ]
+    );
+    assert_eq!(golden.lines().count(), kinds.len() + 2);
+
+    let lists = convert_file(&fixtures().join("lists.json"), InputFormat::Auto, &options).unwrap();
+    let lists: serde_json::Value = serde_json::from_str(&lists.markdown).unwrap();
+    let list = &lists["items"][2];
+    assert_eq!(list["kind"], "list");
+    assert_eq!(list["ordered"], true);
+    assert_eq!(list["items"][0]["number"], 1);
+    assert_eq!(list["items"][0]["items"][1]["text"], "Check its checksum");
+}
+
+/// AsciiDoc：章节标题带 `[[id]]` 锚点，目录为 `xref:` 列表，正文中的 `*literal*` 写成字符引用
+#[test]
+fn asciidoc_output_matches_the_golden_file() {
+    let options = ConvertOptions {
+        format: OutputFormat::Asciidoc,
+        ..ConvertOptions::default()
+    };
+    check_golden("sample.json", "sample.adoc", &options);
+
+    let golden = fs::read_to_string(fixtures().join("sample.adoc")).unwrap();
+    assert!(golden.starts_with(":stem: latexmath\n"), "{}", golden);
+    assert!(golden.contains("[[toc-1-Conclusion]]\n== Conclusion\n"));
+    assert!(golden.contains("* xref:toc-1-Conclusion[Conclusion]"));
+    assert!(golden.contains("has &#42;literal&#42; markup."));
+}
@@ -0,0 +1,155 @@
+//! 文档模型：analyze_documents 得到的节点，以及 write_document 写出的结果与直接转换逐字相同
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use mineru_json_to_md::{
+    ConvertOptions, DocNode, Document, Flavor, ImageSource, Inline, InputFormat, LayoutJson,
+    OutputFormat, TableBody, analyze_documents, convert_documents, parse_layout, write_document,
+};
+
+fn fixtures() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures")
+}
+
+fn sample() -> LayoutJson {
+    let json = fs::read_to_string(fixtures().join("sample.json")).unwrap();
+    parse_layout(&json, InputFormat::Auto).unwrap()
+}
+
+#[test]
+fn writing_the_model_matches_direct_conversion() {
+    let layout = sample();
+    let base_path = fixtures();
+    let documents = [Document {
+        layout: &layout,
+        base_path: &base_path,
+    }];
+    let golden = [
+        (ConvertOptions::default(), Some("sample.styled.md")),
+        (
+            ConvertOptions {
+                flavor: Flavor::Gfm,
+                ..ConvertOptions::default()
+            },
+            Some("sample.gfm.md"),
+        ),
+        (
+            ConvertOptions {
+                format: OutputFormat::Html,
+                ..ConvertOptions::default()
+            },
+            None,
+        ),
+        (
+            ConvertOptions {
+                format: OutputFormat::Text,
+                ..ConvertOptions::default()
+            },
+            None,
+        ),
+    ];
+    for (options, expected) in golden {
+        let model = analyze_documents(&documents, &options).unwrap();
+        let written = write_document(&model, &options).unwrap();
+        let direct = convert_documents(&documents, &options).unwrap();
+        assert_eq!(written.markdown, direct.markdown, "{:?}", options.format);
+        assert_eq!(written.outline.len(), direct.outline.len());
+        if let Some(expected) = expected {
+            let golden = fs::read_to_string(fixtures().join(expected)).unwrap();
+            assert_eq!(written.markdown, golden, "{}", expected);
+        }
+    }
+}
+
+#[test]
+fn each_block_becomes_one_node() {
+    let layout = sample();
+    let base_path = fixtures();
+    let documents = [Document {
+        layout: &layout,
+        base_path: &base_path,
+    }];
+    let model = analyze_documents(&documents, &ConvertOptions::default()).unwrap();
+    assert_eq!(model.pages.len(), 2);
+
+    let first: Vec<&DocNode> = model.pages[0].nodes.iter().map(|node| &node.node).collect();
+    match first.as_slice() {
+        [
+            DocNode::Heading { level, text, .. },
+            DocNode::Paragraph { inlines, .. },
+            DocNode::Figure {
+                image: Some(ImageSource::Src { size, .. }),
+                captions,
+                ..
+            },
+            DocNode::Table {
+                body: Some(TableBody::Html(html)),
+                ..
+            },
+            DocNode::PageBreak {
+                page: 1,
+                printed_page: None,
+            },
+        ] => {
+            assert_eq!((*level, text.as_str()), (1, "Sample Report"));
+            // 文字未转义，转义在写出时进行
+            assert!(matches!(
+                inlines.as_slice(),
+                [Inline::Text { text, .. }] if text.contains("*literal*")
+            ));
+            assert_eq!(size.map(|size| (size.width, size.height)), Some((4, 3)));
+            assert_eq!(captions[0].text, "Figure 1. A red square");
+            assert!(html.starts_with("<table>"), "{}", html);
+        }
+        other => panic!("unexpected nodes {:#?}", other),
+    }
+
+    let second = &model.pages[1].nodes;
+    assert!(matches!(
+        &second[1].node,
+        DocNode::Equation { latex: Some(latex), .. } if latex == "E = mc^2"
+    ));
+    // 正文节点记录来源块，分页线没有来源
+    assert_eq!(
+        second[1].source.as_ref().unwrap().block_type,
+        "interline_equation"
+    );
+    assert!(second.last().unwrap().source.is_none());
+}
+
+#[test]
+fn edited_nodes_are_written_with_the_options() {
+    let layout = sample();
+    let base_path = fixtures();
+    let documents = [Document {
+        layout: &layout,
+        base_path: &base_path,
+    }];
+    let options = ConvertOptions {
+        flavor: Flavor::Gfm,
+        ..ConvertOptions::default()
+    };
+    let mut model = analyze_documents(&documents, &options).unwrap();
+    for page in &mut model.pages {
+        page.nodes
+            .retain(|node| !matches!(node.node, DocNode::Figure { .. }));
+        for node in &mut page.nodes {
+            if let DocNode::Heading { text, .. } = &mut node.node {
+                *text = text.to_uppercase();
+            }
+        }
+    }
+    let output = write_document(&model, &options).unwrap();
+    assert!(
+        !output.markdown.contains("data:image/png"),
+        "{}",
+        output.markdown
+    );
+    assert!(
+        output.markdown.contains("\n## SAMPLE REPORT\n"),
+        "{}",
+        output.markdown
+    );
+    assert_eq!(output.outline[0].title, "SAMPLE REPORT");
+}
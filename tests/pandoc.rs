@@ -0,0 +1,97 @@
+//! `FigureStyle::Pandoc` 的输出交给 pandoc 解析，图注、表注与行间公式应保留在语法树中。
+//! 没有安装 pandoc 时跳过
+
+use std::path::Path;
+use std::process::{self, Command};
+use std::{env, fs};
+
+use mineru_json_to_md::{ConvertOptions, FigureStyle, ImageOutput, InputFormat, convert_file};
+use serde_json::Value;
+
+/// 语法树中所有 `t` 为 `kind` 的节点
+fn nodes<'a>(value: &'a Value, kind: &str, out: &mut Vec<&'a Value>) {
+    match value {
+        Value::Object(map) => {
+            if map.get("t").and_then(Value::as_str) == Some(kind) {
+                out.push(value);
+            }
+            map.values().for_each(|child| nodes(child, kind, out));
+        }
+        Value::Array(items) => items.iter().for_each(|child| nodes(child, kind, out)),
+        _ => {}
+    }
+}
+
+/// 节点中各个 `Str` 的文字，以空格连接
+fn words(value: &Value) -> String {
+    let mut strs = Vec::new();
+    nodes(value, "Str", &mut strs);
+    strs.iter()
+        .filter_map(|node| node["c"].as_str())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[test]
+fn pandoc_keeps_figure_and_table_captions() {
+    if Command::new("pandoc").arg("--version").output().is_err() {
+        eprintln!("pandoc not found, skipping");
+        return;
+    }
+    let dir = env::temp_dir().join(format!("mineru-md-pandoc-{}", process::id()));
+    fs::create_dir_all(dir.join("img")).unwrap();
+    let options = ConvertOptions {
+        figure_style: FigureStyle::Pandoc,
+        number_figures: true,
+        images: ImageOutput::Directory {
+            dir: dir.join("img"),
+            link_prefix: "img".to_string(),
+        },
+        ..ConvertOptions::default()
+    };
+    let fixture = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/sample.json");
+    let output = convert_file(&fixture, InputFormat::Auto, &options).unwrap();
+    fs::write(dir.join("sample.md"), &output.markdown).unwrap();
+
+    let ast = Command::new("pandoc")
+        .current_dir(&dir)
+        .args(["-f", "markdown", "-t", "json", "sample.md"])
+        .output()
+        .unwrap();
+    fs::remove_dir_all(&dir).unwrap();
+    assert!(
+        ast.status.success(),
+        "{}",
+        String::from_utf8_lossy(&ast.stderr)
+    );
+    let ast: Value = serde_json::from_slice(&ast.stdout).unwrap();
+
+    // pandoc 3 起独立的图片段落为 Figure，之前为带图注的 Image
+    let mut figures = Vec::new();
+    nodes(&ast, "Figure", &mut figures);
+    nodes(&ast, "Image", &mut figures);
+    assert!(
+        figures
+            .iter()
+            .any(|figure| words(figure).contains("A red square")),
+        "{}",
+        ast
+    );
+    let mut tables = Vec::new();
+    nodes(&ast, "Table", &mut tables);
+    assert!(
+        tables
+            .iter()
+            .any(|table| words(table).contains("Table 1. Results")),
+        "{}",
+        ast
+    );
+    let mut math = Vec::new();
+    nodes(&ast, "Math", &mut math);
+    assert!(
+        math.iter()
+            .any(|math| math["c"][0]["t"] == "DisplayMath" && math["c"][1] == "E = mc^2"),
+        "{}",
+        ast
+    );
+}
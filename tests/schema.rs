@@ -0,0 +1,193 @@
+//! 各版本 MinerU 输出的 middle.json（tests/fixtures/schema）都能解析，并得到相同的正文；
+//! 缺少或无法使用 page_size 的页面推断页面尺寸并给出警告
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use mineru_json_to_md::{
+    ConvertError, ConvertOptions, Flavor, InputFormat, PageSizeSource, Warning,
+    convert_layout_to_markdown, parse_layout, parse_layout_lenient,
+};
+use serde_json::json;
+
+const GENERATIONS: &[&str] = &["v0.9.json", "v1.3.json", "v2.1.json"];
+
+fn schema_fixture(name: &str) -> String {
+    let path: PathBuf = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/schema");
+    fs::read_to_string(path.join(name)).unwrap()
+}
+
+fn body(json: &str) -> String {
+    let layout = parse_layout(json, InputFormat::Auto).unwrap();
+    let options = ConvertOptions {
+        style_header: false,
+        footer: None,
+        page_dividers: false,
+        skip_headers: true,
+        ..ConvertOptions::default()
+    };
+    let output = convert_layout_to_markdown(&layout, Path::new("."), &options).unwrap();
+    assert!(output.warnings.is_empty(), "{:?}", output.warnings);
+    let start = output.markdown.find("## ").unwrap();
+    output.markdown[start..].to_string()
+}
+
+#[test]
+fn every_schema_generation_converts_to_the_same_body() {
+    for name in GENERATIONS {
+        assert_eq!(
+            body(&schema_fixture(name)),
+            "## Results\n\nBody text.\n\n",
+            "{}",
+            name
+        );
+    }
+}
+
+#[test]
+fn optional_fields_take_their_defaults() {
+    let old = parse_layout(&schema_fixture("v0.9.json"), InputFormat::Auto).unwrap();
+    let page = &old.pdf_info[0];
+    assert!(page.discarded_blocks.is_empty());
+    assert_eq!(page.page_size, (612.0, 792.0));
+    assert_eq!(page.page_size_source, PageSizeSource::Given);
+    assert!(old.version_name.is_none());
+
+    let new = parse_layout(&schema_fixture("v2.1.json"), InputFormat::Auto).unwrap();
+    assert_eq!(new.backend.as_deref(), Some("vlm"));
+    assert_eq!(new.version_name.as_deref(), Some("2.1.0"));
+}
+
+#[test]
+fn a_page_that_does_not_parse_is_named_and_can_be_skipped() {
+    let mut json: serde_json::Value = serde_json::from_str(&schema_fixture("v1.3.json")).unwrap();
+    let pages = json["pdf_info"].as_array_mut().unwrap();
+    pages.push(serde_json::json!({"page_idx": 1, "page_size": [612, 792], "para_blocks": "oops"}));
+    let json = json.to_string();
+
+    match parse_layout(&json, InputFormat::Auto) {
+        Err(ConvertError::InvalidPage { page_idx, .. }) => assert_eq!(page_idx, 1),
+        other => panic!(
+            "expected an invalid page error, got {:?}",
+            other.map(|_| ())
+        ),
+    }
+
+    let (layout, warnings) = parse_layout_lenient(&json, InputFormat::Auto).unwrap();
+    assert_eq!(layout.pdf_info.len(), 1);
+    assert!(matches!(
+        warnings.as_slice(),
+        [Warning::SkippedPage { page_idx: 1, .. }]
+    ));
+}
+
+#[test]
+fn missing_page_sizes_are_inferred_with_a_warning() {
+    // 第 2 页没有 page_size，第 4 页为 [0, 0]
+    let layout =
+        parse_layout(&schema_fixture("missing_page_size.json"), InputFormat::Auto).unwrap();
+    let sources: Vec<PageSizeSource> = layout
+        .pdf_info
+        .iter()
+        .map(|page| page.page_size_source)
+        .collect();
+    assert_eq!(
+        sources,
+        [
+            PageSizeSource::Given,
+            PageSizeSource::Blocks,
+            PageSizeSource::Given,
+            PageSizeSource::Blocks,
+            PageSizeSource::Given
+        ]
+    );
+    assert_eq!(layout.pdf_info[1].page_size, (540.0, 300.0));
+
+    let options = ConvertOptions {
+        style_header: false,
+        footer: None,
+        page_dividers: false,
+        ..ConvertOptions::default()
+    };
+    let output = convert_layout_to_markdown(&layout, Path::new("."), &options).unwrap();
+    let inferred: Vec<usize> = output
+        .warnings
+        .iter()
+        .map(|warning| match warning {
+            Warning::InferredPageSize {
+                page_idx,
+                source: PageSizeSource::Blocks,
+            } => *page_idx,
+            other => panic!("unexpected warning {}", other),
+        })
+        .collect();
+    assert_eq!(inferred, [1, 3]);
+    // 推断出页宽后双栏页面仍按先左后右的顺序输出
+    assert!(
+        output.markdown.contains("Left column.\n\nRight column."),
+        "{}",
+        output.markdown
+    );
+}
+
+#[test]
+fn pages_without_blocks_or_a_usable_size_fall_back_to_a4() {
+    let json = json!({"pdf_info": [
+        {"page_idx": 0, "page_size": [-1, 792], "para_blocks": []},
+        {"page_idx": 1, "page_size": null, "para_blocks": []}
+    ]});
+    let layout = parse_layout(&json.to_string(), InputFormat::Auto).unwrap();
+    for page in &layout.pdf_info {
+        assert_eq!(page.page_size_source, PageSizeSource::A4);
+        assert_eq!(page.page_size, (595.28, 841.89));
+    }
+}
+
+#[test]
+fn malformed_page_sizes_name_the_page() {
+    for page_size in [json!([612]), json!(["wide", "tall"]), json!("A4")] {
+        let json = json!({"pdf_info": [
+            {"page_idx": 0, "page_size": [612, 792], "para_blocks": []},
+            {"page_idx": 7, "page_size": page_size, "para_blocks": []}
+        ]});
+        match parse_layout(&json.to_string(), InputFormat::Auto) {
+            Err(error @ ConvertError::InvalidPage { page_idx: 7, .. }) => {
+                assert!(error.to_string().contains("page_size"), "{}", error);
+            }
+            other => panic!(
+                "expected an invalid page error, got {:?}",
+                other.map(|_| ())
+            ),
+        }
+    }
+}
+
+#[test]
+fn content_list_v1_figure_captions_and_footnotes_are_kept() {
+    // MinerU 1.x 的 content_list.json 以 img_caption / img_footnote 记录图注
+    let layout = parse_layout(&schema_fixture("content_list_v1.json"), InputFormat::Auto).unwrap();
+    let options = ConvertOptions {
+        flavor: Flavor::Gfm,
+        style_header: false,
+        footer: None,
+        page_dividers: false,
+        ..ConvertOptions::default()
+    };
+    let fixtures = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures");
+    let output = convert_layout_to_markdown(&layout, &fixtures, &options).unwrap();
+    assert!(output.warnings.is_empty(), "{:?}", output.warnings);
+    assert!(
+        output
+            .markdown
+            .contains("![Figure 1. Cap A](data:image/png;base64,"),
+        "{}",
+        output.markdown
+    );
+    assert!(
+        output
+            .markdown
+            .contains("*Figure 1. Cap A*\n\n*Source: survey data.*\n"),
+        "{}",
+        output.markdown
+    );
+}